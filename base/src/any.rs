@@ -0,0 +1,237 @@
+//! Retrieval of entities of any kind.
+//!
+//! Generic tooling, such as admin dashboards or debugging REPLs, often only
+//! knows the kind of entity to retrieve at runtime. [`get_any`] retrieves an
+//! entity by its [`EntityKind`] and raw ID as an [`AnyEntity`], without a
+//! match over every repository.
+//!
+//! Most entities are identified by a single ID. Members, presences, and voice
+//! states are identified by the IDs of their guild and user, so their raw ID
+//! is a pair of IDs.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{
+//!     any::{AnyEntity, EntityKind},
+//!     entity::guild::GuildEntity,
+//!     Repository,
+//! };
+//! use twilight_cache_inmemory::InMemoryCache;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let cache = InMemoryCache::new();
+//! let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+//! cache.guilds.upsert(guild.clone()).await?;
+//!
+//! let kind = EntityKind::from_name("guild").unwrap();
+//! assert_eq!(Some(AnyEntity::Guild(guild)), cache.get_any(kind, 1).await?);
+//! assert!(cache.get_any(EntityKind::Member, (1, 2)).await?.is_none());
+//! # Ok(()) }
+//! ```
+//!
+//! [`AnyEntity`]: enum.AnyEntity.html
+//! [`EntityKind`]: enum.EntityKind.html
+//! [`get_any`]: fn.get_any.html
+
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{
+            AttachmentEntity, CategoryChannelEntity, GroupEntity, MessageEntity,
+            PrivateChannelEntity, TextChannelEntity, VoiceChannelEntity,
+        },
+        gateway::PresenceEntity,
+        guild::{EmojiEntity, GuildEntity, GuildPreviewEntity, MemberEntity, RoleEntity},
+        user::{CurrentUserEntity, UserEntity},
+        voice::{VoiceRegionEntity, VoiceRegionId, VoiceStateEntity},
+    },
+    repository::{Repository, SingleEntityRepository},
+};
+use twilight_model::id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+/// Raw ID of an entity of any kind.
+///
+/// Created from a `u64` for entities identified by a single ID, or from a
+/// pair of `u64`s for entities identified by the IDs of their guild and user.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RawId {
+    /// Single ID, such as the ID of a guild.
+    Single(u64),
+    /// Pair of IDs, such as the IDs of a member's guild and user.
+    Pair(u64, u64),
+}
+
+impl From<u64> for RawId {
+    fn from(id: u64) -> Self {
+        Self::Single(id)
+    }
+}
+
+impl From<(u64, u64)> for RawId {
+    fn from((first, second): (u64, u64)) -> Self {
+        Self::Pair(first, second)
+    }
+}
+
+/// ID type that can be created from a raw ID of the matching shape.
+trait FromRawId: Sized {
+    fn from_raw(raw_id: RawId) -> Option<Self>;
+}
+
+macro_rules! single_ids {
+    ($($id:ident),*) => {
+        $(
+            impl FromRawId for $id {
+                fn from_raw(raw_id: RawId) -> Option<Self> {
+                    match raw_id {
+                        RawId::Single(id) => Some(Self(id)),
+                        RawId::Pair(..) => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+single_ids!(
+    AttachmentId,
+    ChannelId,
+    EmojiId,
+    GuildId,
+    MessageId,
+    RoleId,
+    UserId,
+    VoiceRegionId
+);
+
+impl FromRawId for (GuildId, UserId) {
+    fn from_raw(raw_id: RawId) -> Option<Self> {
+        match raw_id {
+            RawId::Single(_) => None,
+            RawId::Pair(guild_id, user_id) => Some((GuildId(guild_id), UserId(user_id))),
+        }
+    }
+}
+
+macro_rules! any_entities {
+    ($($variant:ident($entity:ty) => $repository:ident, $name:literal),* $(,)?) => {
+        /// Kind of entity.
+        #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+        pub enum EntityKind {
+            CurrentUser,
+            $($variant,)*
+        }
+
+        impl EntityKind {
+            /// Every kind of entity.
+            pub const ALL: &'static [Self] = &[Self::CurrentUser, $(Self::$variant,)*];
+
+            /// Return the kind with a name, as returned by [`name`].
+            ///
+            /// [`name`]: #method.name
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    "current_user" => Some(Self::CurrentUser),
+                    $($name => Some(Self::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// Return the name of the kind, such as `guild` or `text_channel`.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    Self::CurrentUser => "current_user",
+                    $(Self::$variant => $name,)*
+                }
+            }
+        }
+
+        /// Entity of any kind.
+        #[derive(Clone, Debug, Eq, PartialEq)]
+        pub enum AnyEntity {
+            CurrentUser(CurrentUserEntity),
+            $($variant($entity),)*
+        }
+
+        impl AnyEntity {
+            /// Return the kind of the entity.
+            pub const fn kind(&self) -> EntityKind {
+                match self {
+                    Self::CurrentUser(_) => EntityKind::CurrentUser,
+                    $(Self::$variant(_) => EntityKind::$variant,)*
+                }
+            }
+        }
+
+        impl From<CurrentUserEntity> for AnyEntity {
+            fn from(entity: CurrentUserEntity) -> Self {
+                Self::CurrentUser(entity)
+            }
+        }
+
+        $(
+            impl From<$entity> for AnyEntity {
+                fn from(entity: $entity) -> Self {
+                    Self::$variant(entity)
+                }
+            }
+        )*
+
+        /// Retrieve an entity of a kind by its raw ID.
+        ///
+        /// Returns `None` if the entity isn't cached or the shape of the raw
+        /// ID doesn't match the kind, such as a single ID for a member. The
+        /// ID is ignored for the current user.
+        ///
+        /// # Errors
+        ///
+        /// Returns a backend error if retrieving the entity fails.
+        pub async fn get_any<B: Backend>(
+            backend: &B,
+            kind: EntityKind,
+            raw_id: impl Into<RawId>,
+        ) -> Result<Option<AnyEntity>, B::Error> {
+            let raw_id = raw_id.into();
+
+            match kind {
+                EntityKind::CurrentUser => Ok(backend
+                    .current_user()
+                    .get()
+                    .await?
+                    .map(AnyEntity::CurrentUser)),
+                $(
+                    EntityKind::$variant => match FromRawId::from_raw(raw_id) {
+                        Some(id) => {
+                            let entity = backend.$repository().get(id).await?;
+
+                            Ok(entity.map(AnyEntity::$variant))
+                        }
+                        None => Ok(None),
+                    },
+                )*
+            }
+        }
+    };
+}
+
+any_entities! {
+    Attachment(AttachmentEntity) => attachments, "attachment",
+    CategoryChannel(CategoryChannelEntity) => category_channels, "category_channel",
+    Emoji(EmojiEntity) => emojis, "emoji",
+    Group(GroupEntity) => groups, "group",
+    Guild(GuildEntity) => guilds, "guild",
+    GuildPreview(GuildPreviewEntity) => guild_previews, "guild_preview",
+    Member(MemberEntity) => members, "member",
+    Message(MessageEntity) => messages, "message",
+    Presence(PresenceEntity) => presences, "presence",
+    PrivateChannel(PrivateChannelEntity) => private_channels, "private_channel",
+    Role(RoleEntity) => roles, "role",
+    TextChannel(TextChannelEntity) => text_channels, "text_channel",
+    User(UserEntity) => users, "user",
+    VoiceChannel(VoiceChannelEntity) => voice_channels, "voice_channel",
+    VoiceRegion(VoiceRegionEntity) => voice_regions, "voice_region",
+    VoiceState(VoiceStateEntity) => voice_states, "voice_state",
+}
@@ -0,0 +1,198 @@
+//! Zero-copy archived representations of entities via [`rkyv`].
+//!
+//! With the `rkyv` feature enabled all entities implement rkyv's `Archive`,
+//! `Serialize`, and `Deserialize` traits. Key-value backends can store the
+//! archived bytes of an entity and hand out a reference to the archived entity
+//! (such as an `ArchivedMessageEntity`) directly from the stored bytes instead
+//! of deserializing the entire entity on every read. Archived entities
+//! implement `CheckBytes`, so stored bytes can be validated with
+//! `rkyv::check_archived_root` before they are accessed.
+//!
+//! IDs and bitflags are archived as their raw integers. Other types from
+//! `twilight-model`, such as embeds, activities, and permission overwrites,
+//! have no archived representation of their own and are archived as JSON
+//! bytes. These fields can be deserialized on demand with [`from_json`].
+//!
+//! Deserializing an archived entity deserializes its JSON fields, so it needs a
+//! deserializer whose error can be created from a JSON error, such as
+//! [`JsonDeserializer`].
+//!
+//! [`from_json`]: fn.from_json.html
+//! [`JsonDeserializer`]: struct.JsonDeserializer.html
+//! [`rkyv`]: https://docs.rs/rkyv
+
+use rkyv::{
+    ser::Serializer,
+    vec::{ArchivedVec, VecResolver},
+    with::{ArchiveWith, DeserializeWith, SerializeWith},
+    Archive, Archived, Deserialize, Fallible, SerializeUnsized,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use twilight_model::{
+    channel::message::MessageFlags,
+    guild::{Permissions, SystemChannelFlags},
+    id::{
+        ApplicationId, AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId,
+        WebhookId,
+    },
+    user::UserFlags,
+};
+
+/// Deserialize a field of an archived entity that was archived as JSON.
+///
+/// # Errors
+///
+/// Returns an error if the archived bytes aren't a valid JSON representation
+/// of the type.
+pub fn from_json<T: DeserializeOwned>(field: &ArchivedVec<u8>) -> serde_json::Result<T> {
+    serde_json::from_slice(field.as_slice())
+}
+
+/// Deserializer of archived entities, failing if a field archived as JSON
+/// can't be deserialized.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonDeserializer;
+
+impl Fallible for JsonDeserializer {
+    type Error = serde_json::Error;
+}
+
+/// Archive an ID as its raw integer.
+#[derive(Clone, Copy, Debug)]
+pub struct AsId;
+
+/// Archive bitflags as their raw bits.
+#[derive(Clone, Copy, Debug)]
+pub struct AsBits;
+
+/// Archive a type without an archived representation as JSON bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct AsJson;
+
+/// Resolver for a field archived with [`AsJson`].
+///
+/// [`AsJson`]: struct.AsJson.html
+pub struct JsonResolver {
+    len: usize,
+    resolver: VecResolver,
+}
+
+macro_rules! impl_as_id {
+    ($($id: ident),*) => {
+        $(
+            impl ArchiveWith<$id> for AsId {
+                type Archived = Archived<u64>;
+                type Resolver = ();
+
+                unsafe fn resolve_with(
+                    field: &$id,
+                    pos: usize,
+                    resolver: (),
+                    out: *mut Self::Archived,
+                ) {
+                    field.0.resolve(pos, resolver, out);
+                }
+            }
+
+            impl<S: Fallible + ?Sized> SerializeWith<$id, S> for AsId {
+                fn serialize_with(_: &$id, _: &mut S) -> Result<(), S::Error> {
+                    Ok(())
+                }
+            }
+
+            impl<D: Fallible + ?Sized> DeserializeWith<Archived<u64>, $id, D> for AsId {
+                fn deserialize_with(
+                    field: &Archived<u64>,
+                    deserializer: &mut D,
+                ) -> Result<$id, D::Error> {
+                    Ok($id(field.deserialize(deserializer)?))
+                }
+            }
+        )*
+    };
+}
+
+impl_as_id!(
+    ApplicationId,
+    AttachmentId,
+    ChannelId,
+    EmojiId,
+    GuildId,
+    MessageId,
+    RoleId,
+    UserId,
+    WebhookId
+);
+
+macro_rules! impl_as_bits {
+    ($($flags: ident),*) => {
+        $(
+            impl ArchiveWith<$flags> for AsBits {
+                type Archived = Archived<u64>;
+                type Resolver = ();
+
+                unsafe fn resolve_with(
+                    field: &$flags,
+                    pos: usize,
+                    resolver: (),
+                    out: *mut Self::Archived,
+                ) {
+                    field.bits().resolve(pos, resolver, out);
+                }
+            }
+
+            impl<S: Fallible + ?Sized> SerializeWith<$flags, S> for AsBits {
+                fn serialize_with(_: &$flags, _: &mut S) -> Result<(), S::Error> {
+                    Ok(())
+                }
+            }
+
+            impl<D: Fallible + ?Sized> DeserializeWith<Archived<u64>, $flags, D> for AsBits {
+                fn deserialize_with(
+                    field: &Archived<u64>,
+                    deserializer: &mut D,
+                ) -> Result<$flags, D::Error> {
+                    Ok($flags::from_bits_truncate(field.deserialize(deserializer)?))
+                }
+            }
+        )*
+    };
+}
+
+impl_as_bits!(MessageFlags, Permissions, SystemChannelFlags, UserFlags);
+
+impl<T: Serialize> ArchiveWith<T> for AsJson {
+    type Archived = ArchivedVec<u8>;
+    type Resolver = JsonResolver;
+
+    unsafe fn resolve_with(_: &T, pos: usize, resolver: JsonResolver, out: *mut Self::Archived) {
+        ArchivedVec::resolve_from_len(resolver.len, pos, resolver.resolver, out);
+    }
+}
+
+impl<T: Serialize, S: Serializer + ?Sized> SerializeWith<T, S> for AsJson
+where
+    [u8]: SerializeUnsized<S>,
+{
+    fn serialize_with(field: &T, serializer: &mut S) -> Result<JsonResolver, S::Error> {
+        // Models from twilight-model always serialize to JSON, the only
+        // failure case being maps with non-string keys.
+        let bytes = serde_json::to_vec(field).expect("models are serializable as json");
+
+        Ok(JsonResolver {
+            len: bytes.len(),
+            resolver: ArchivedVec::serialize_from_slice(&bytes, serializer)?,
+        })
+    }
+}
+
+impl<T: DeserializeOwned, D: Fallible + ?Sized> DeserializeWith<ArchivedVec<u8>, T, D> for AsJson
+where
+    D::Error: From<serde_json::Error>,
+{
+    fn deserialize_with(field: &ArchivedVec<u8>, _: &mut D) -> Result<T, D::Error> {
+        // Stored bytes may have been corrupted or archived by a version with
+        // a different model, so they're not assumed to be valid.
+        Ok(from_json(field)?)
+    }
+}
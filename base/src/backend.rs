@@ -1,83 +1,275 @@
+#[cfg(feature = "compression")]
+use super::compression::CompressionStats;
+#[cfg(feature = "discord")]
 use super::entity::{
     channel::{
         AttachmentRepository, CategoryChannelRepository, GroupRepository, MessageRepository,
         PrivateChannelRepository, TextChannelRepository, VoiceChannelRepository,
     },
     gateway::PresenceRepository,
-    guild::{EmojiRepository, GuildRepository, MemberRepository, RoleRepository},
+    guild::{
+        EmojiRepository, GuildPreviewRepository, GuildRepository, MemberRepository, RoleRepository,
+    },
     user::{CurrentUserRepository, UserRepository},
-    voice::VoiceStateRepository,
+    voice::{VoiceRegionRepository, VoiceStateRepository},
 };
+#[cfg(feature = "discord")]
+use super::integrity::{IntegrityReport, VerifyIntegrityFuture};
+use super::repository::{CloseFuture, ConnectFuture, FlushFuture, HealthFuture};
+use futures_util::future::{self, FutureExt};
+#[cfg(feature = "discord")]
+use twilight_model::id::{GuildId, UserId};
 
+/// Datastore backing the repositories of the cache.
+///
+/// Every backend has an error type. With the `discord` feature, which is
+/// enabled by default, backends also return an instance of a repository for
+/// each of Discord's entities.
+///
+/// Backends buffering writes should implement [`flush`] and [`close`] so
+/// that pending writes are persisted when the cache is shut down. Backends
+/// connecting to a remote datastore should implement [`connect`] and
+/// [`reconnect`] so that they can be wrapped in a [`ReconnectingBackend`].
+///
+/// [`close`]: #method.close
+/// [`connect`]: #method.connect
+/// [`flush`]: #method.flush
+/// [`reconnect`]: #method.reconnect
+/// [`ReconnectingBackend`]: reconnect/struct.ReconnectingBackend.html
 pub trait Backend: Send + Sync + Sized + 'static {
     type Error: Send + 'static;
+    #[cfg(feature = "discord")]
     type AttachmentRepository: AttachmentRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type CategoryChannelRepository: CategoryChannelRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type CurrentUserRepository: CurrentUserRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type EmojiRepository: EmojiRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type GroupRepository: GroupRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
+    type GuildPreviewRepository: GuildPreviewRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type GuildRepository: GuildRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type MemberRepository: MemberRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type MessageRepository: MessageRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type PresenceRepository: PresenceRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type PrivateChannelRepository: PrivateChannelRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type RoleRepository: RoleRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type TextChannelRepository: TextChannelRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type UserRepository: UserRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type VoiceChannelRepository: VoiceChannelRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
+    type VoiceRegionRepository: VoiceRegionRepository<Self> + Send + Sync;
+    #[cfg(feature = "discord")]
     type VoiceStateRepository: VoiceStateRepository<Self> + Send + Sync;
 
     /// Return a new instance of the backend's attachment repository
     /// implementation.
+    #[cfg(feature = "discord")]
     fn attachments(&self) -> Self::AttachmentRepository;
 
     /// Return a new instance of the backend's guild category channel repository
     /// implementation.
+    #[cfg(feature = "discord")]
     fn category_channels(&self) -> Self::CategoryChannelRepository;
 
     /// Return a new instance of the backend's current user repository
     /// implementation.
+    #[cfg(feature = "discord")]
     fn current_user(&self) -> Self::CurrentUserRepository;
 
     /// Return a new instance of the backend's emoji repository implementation.
+    #[cfg(feature = "discord")]
     fn emojis(&self) -> Self::EmojiRepository;
 
     /// Return a new instance of the backend's group repository implementation.
+    #[cfg(feature = "discord")]
     fn groups(&self) -> Self::GroupRepository;
 
+    /// Return a new instance of the backend's guild preview repository
+    /// implementation.
+    #[cfg(feature = "discord")]
+    fn guild_previews(&self) -> Self::GuildPreviewRepository;
+
     /// Return a new instance of the backend's guild repository implementation.
+    #[cfg(feature = "discord")]
     fn guilds(&self) -> Self::GuildRepository;
 
     /// Return a new instance of the backend's member repository implementation.
+    #[cfg(feature = "discord")]
     fn members(&self) -> Self::MemberRepository;
 
     /// Return a new instance of the backend's message repository
     /// implementation.
+    #[cfg(feature = "discord")]
     fn messages(&self) -> Self::MessageRepository;
 
     /// Return a new instance of the backend's presence repository
     /// implementation.
+    #[cfg(feature = "discord")]
     fn presences(&self) -> Self::PresenceRepository;
 
     /// Return a new instance of the backend's guild private channel repository
     /// implementation.
+    #[cfg(feature = "discord")]
     fn private_channels(&self) -> Self::PrivateChannelRepository;
 
     /// Return a new instance of the backend's role repository implementation.
+    #[cfg(feature = "discord")]
     fn roles(&self) -> Self::RoleRepository;
 
     /// Return a new instance of the backend's guild text channel repository
     /// implementation.
+    #[cfg(feature = "discord")]
     fn text_channels(&self) -> Self::TextChannelRepository;
 
     /// Return a new instance of the backend's user repository implementation.
+    #[cfg(feature = "discord")]
     fn users(&self) -> Self::UserRepository;
 
     /// Return a new instance of the backend's voice channel repository
     /// implementation.
+    #[cfg(feature = "discord")]
     fn voice_channels(&self) -> Self::VoiceChannelRepository;
 
+    /// Return a new instance of the backend's voice region repository
+    /// implementation.
+    #[cfg(feature = "discord")]
+    fn voice_regions(&self) -> Self::VoiceRegionRepository;
+
     /// Return a new instance of the backend's voice state repository
     /// implementation.
+    #[cfg(feature = "discord")]
     fn voice_states(&self) -> Self::VoiceStateRepository;
+
+    /// Establish the backend's connection to its datastore.
+    ///
+    /// Backends storing entities in the process, or connecting lazily when
+    /// the first operation is made, have nothing to connect, so by default
+    /// this completes immediately.
+    fn connect(&self) -> ConnectFuture<'_, Self::Error> {
+        future::ok(()).boxed()
+    }
+
+    /// Re-establish the backend's connection to its datastore after it was
+    /// lost, such as when the datastore restarted.
+    ///
+    /// The backend should reconnect in place, so that repositories created
+    /// before reconnecting use the new connection. By default this calls
+    /// [`connect`].
+    ///
+    /// [`connect`]: #method.connect
+    fn reconnect(&self) -> ConnectFuture<'_, Self::Error> {
+        self.connect()
+    }
+
+    /// Persist the writes buffered by the backend.
+    ///
+    /// Backends writing through to their datastore have nothing to flush, so
+    /// by default this completes immediately.
+    fn flush(&self) -> FlushFuture<'_, Self::Error> {
+        future::ok(()).boxed()
+    }
+
+    /// Release the resources held by the backend, such as connections.
+    ///
+    /// [`Cache::shutdown`] calls this after [`flush`]. Operations made after
+    /// the backend is closed may error. By default this completes
+    /// immediately.
+    ///
+    /// [`Cache::shutdown`]: struct.Cache.html#method.shutdown
+    /// [`flush`]: #method.flush
+    fn close(&self) -> CloseFuture<'_, Self::Error> {
+        future::ok(()).boxed()
+    }
+
+    /// Check that the backend can reach its datastore, returning the error
+    /// that prevents it from doing so.
+    ///
+    /// Backends storing entities remotely should make a cheap round trip,
+    /// such as a ping or a `SELECT 1`, so that readiness probes can report
+    /// cache connectivity through [`Cache::health`]. Backends storing
+    /// entities in the process are always healthy, which is the default.
+    ///
+    /// [`Cache::health`]: struct.Cache.html#method.health
+    fn health(&self) -> HealthFuture<'_, Self::Error> {
+        future::ok(()).boxed()
+    }
+
+    /// Return the number of entities and bytes compressed by the backend's
+    /// [`Compressor`], if it compresses entities.
+    ///
+    /// Backends using a compressor should return its [`Compressor::stats`] so
+    /// that they're available through [`Cache::compression_stats`]. By
+    /// default entities aren't compressed.
+    ///
+    /// [`Cache::compression_stats`]: struct.Cache.html#method.compression_stats
+    /// [`Compressor`]: compression/struct.Compressor.html
+    /// [`Compressor::stats`]: compression/struct.Compressor.html#method.stats
+    #[cfg(feature = "compression")]
+    fn compression_stats(&self) -> Option<CompressionStats> {
+        None
+    }
+
+    /// Return whether the backend caches the entities of a guild.
+    ///
+    /// [`Cache::process`] skips events for guilds that aren't cached
+    /// entirely, so backends configured to only cache some guilds can avoid
+    /// the work of processing events for the others. By default every guild
+    /// is cached.
+    ///
+    /// [`Cache::process`]: struct.Cache.html#method.process
+    #[cfg(feature = "discord")]
+    fn caches_guild(&self, _: GuildId) -> bool {
+        true
+    }
+
+    /// Return whether a guild is pinned, exempting it from caps on the number
+    /// of entities cached per guild and protecting its messages and presences
+    /// from removal by a [`Pruner`].
+    ///
+    /// Bots pin guilds that they can't work without, such as their home
+    /// guild, so that caps and retention rules only apply to the others. By
+    /// default no guild is pinned.
+    ///
+    /// [`Pruner`]: prune/struct.Pruner.html
+    #[cfg(feature = "discord")]
+    fn pins_guild(&self, _: GuildId) -> bool {
+        false
+    }
+
+    /// Return whether a user is pinned, protecting the user's members and
+    /// presences from being evicted to make room for others, and the user's
+    /// messages and presences from removal by a [`Pruner`].
+    ///
+    /// By default no user is pinned.
+    ///
+    /// [`Pruner`]: prune/struct.Pruner.html
+    #[cfg(feature = "discord")]
+    fn pins_user(&self, _: UserId) -> bool {
+        false
+    }
+
+    /// Check the relations indexed by the backend for relations pointing at
+    /// entities that aren't cached, optionally removing them.
+    ///
+    /// Backends that don't index relations separately from their entities
+    /// have nothing to check, so by default this returns an empty report.
+    /// Refer to the [`integrity`] module for more information.
+    ///
+    /// [`integrity`]: integrity/index.html
+    #[cfg(feature = "discord")]
+    fn verify_integrity(&self, repair: bool) -> VerifyIntegrityFuture<'_, Self::Error> {
+        future::ok(IntegrityReport::new(Vec::new(), repair)).boxed()
+    }
 }
@@ -0,0 +1,949 @@
+//! Backend queueing writes in memory and flushing them to another backend in
+//! batches.
+//!
+//! Processing events writes to the backend for nearly every entity in them,
+//! so event storms such as a bot reconnecting to thousands of guilds result in
+//! a flood of small writes. For backends performing each write over the
+//! network, such as SQL databases or HTTP services, most of that time is spent
+//! on round trips. A [`BatchingBackend`] wraps a backend and queues upserts
+//! and removals in memory instead. Writes to an entity replace its queued
+//! write, so an entity that is updated many times before the queue is flushed
+//! is only written once.
+//!
+//! Queued writes are flushed to the wrapped backend:
+//!
+//! - when the number of entities with queued writes reaches the batch size;
+//! - when [`Backend::flush`] is called, such as by [`Cache::shutdown`];
+//! - with the `tokio` feature, periodically by a task spawned with
+//!   [`BatchingBackend::spawn_flusher`].
+//!
+//! Getting an entity returns its queued write if there is one. Listing
+//! entities or the entities related to another, such as the members of a
+//! guild, flushes the queued writes first so that the results are up to date.
+//! Writes to different entities may be flushed in a different order than they
+//! were made.
+//!
+//! [`Repository::take`] and [`Repository::upsert_with_outcome`] aren't
+//! forwarded to the wrapped backend, since their writes are queued like any
+//! other. They use the default implementations, which read the entity's
+//! queued write, or the wrapped backend's entity if there's none, before
+//! queueing the write. An upsert is therefore never reported as
+//! [`UpsertOutcome::Skipped`], even if the wrapped backend doesn't store the
+//! entity once it's flushed.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{batch::BatchingBackend, entity::guild::GuildEntity, Backend, Repository};
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let backend = BatchingBackend::new(InMemoryBackend::new(), 1000);
+//!
+//! let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+//! backend.guilds().upsert(guild.clone()).await?;
+//!
+//! // The guild is queued, but can already be retrieved.
+//! assert_eq!(Some(guild.clone()), backend.guilds().get(GuildId(1)).await?);
+//! assert!(backend.inner().guilds().get(GuildId(1)).await?.is_none());
+//!
+//! backend.flush().await?;
+//! assert_eq!(Some(guild), backend.inner().guilds().get(GuildId(1)).await?);
+//! # Ok(()) }
+//! ```
+//!
+//! [`Backend::flush`]: ../trait.Backend.html#method.flush
+//! [`BatchingBackend`]: struct.BatchingBackend.html
+//! [`BatchingBackend::spawn_flusher`]: struct.BatchingBackend.html#method.spawn_flusher
+//! [`Cache::shutdown`]: ../struct.Cache.html#method.shutdown
+//! [`Repository::take`]: ../repository/trait.Repository.html#method.take
+//! [`Repository::upsert_with_outcome`]: ../repository/trait.Repository.html#method.upsert_with_outcome
+//! [`UpsertOutcome::Skipped`]: ../repository/enum.UpsertOutcome.html#variant.Skipped
+
+#[cfg(feature = "compression")]
+use super::compression::CompressionStats;
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{
+            AttachmentEntity, AttachmentRepository, CategoryChannelEntity,
+            CategoryChannelRepository, GroupEntity, GroupRepository, GuildChannelEntity,
+            MessageContext, MessageEntity, MessageRepository, PrivateChannelEntity,
+            PrivateChannelRepository, TextChannelEntity, TextChannelRepository, VoiceChannelEntity,
+            VoiceChannelRepository,
+        },
+        gateway::{PresenceEntity, PresenceRepository},
+        guild::{
+            EmojiEntity, EmojiRepository, GuildEntity, GuildPreviewEntity, GuildPreviewRepository,
+            GuildRepository, MemberEntity, MemberRepository, NameChange, RoleEntity,
+            RoleRepository,
+        },
+        user::{
+            AvatarChange, CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository,
+        },
+        voice::{
+            VoiceRegionEntity, VoiceRegionRepository, VoiceStateChange, VoiceStateEntity,
+            VoiceStateRepository,
+        },
+        Entity,
+    },
+    integrity::VerifyIntegrityFuture,
+    meta::CacheMeta,
+    repository::{
+        CloseFuture, ConnectFuture, FlushFuture, GetEntityFuture, HealthFuture, ListEntitiesFuture,
+        ListEntityIdsFuture, RemoveEntityFuture, Repository, SingleEntityRepository,
+        UpsertEntityFuture,
+    },
+};
+use futures_util::{
+    future::{self, FutureExt, TryFutureExt},
+    lock::Mutex as AsyncMutex,
+};
+#[cfg(feature = "tokio")]
+use std::time::Duration;
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+};
+use twilight_model::id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+type ReadFuture<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>;
+
+/// Queued write to an entity.
+#[derive(Clone, Debug)]
+enum Write<E> {
+    Remove,
+    Upsert { entity: E, sequence: Option<u64> },
+}
+
+/// Writes queued for the entities of one type, keyed by their IDs.
+///
+/// Each write is numbered so that a flushed write is only dequeued if it
+/// wasn't replaced while it was being flushed.
+#[derive(Debug)]
+pub struct Batch<K, E> {
+    writes: Mutex<HashMap<K, (u64, Write<E>)>>,
+}
+
+impl<K, E> Default for Batch<K, E> {
+    fn default() -> Self {
+        Self {
+            writes: Mutex::default(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash, E: Clone> Batch<K, E> {
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, (u64, Write<E>)>> {
+        self.writes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Return the queued write of an entity, if there is one.
+    ///
+    /// A queued removal is returned as `Some(None)`.
+    fn get(&self, key: K) -> Option<Option<E>> {
+        self.lock().get(&key).map(|(_, write)| match write {
+            Write::Remove => None,
+            Write::Upsert { entity, .. } => Some(entity.clone()),
+        })
+    }
+
+    /// Queue a write, returning whether the entity didn't already have one.
+    fn push(&self, key: K, number: u64, write: Write<E>) -> bool {
+        let mut writes = self.lock();
+
+        // The wrapped backend would reject a versioned upsert older than the
+        // queued one, so there's no need to replace it.
+        if let (
+            Write::Upsert {
+                sequence: Some(sequence),
+                ..
+            },
+            Some((
+                _,
+                Write::Upsert {
+                    sequence: Some(queued),
+                    ..
+                },
+            )),
+        ) = (&write, writes.get(&key))
+        {
+            if sequence < queued {
+                return false;
+            }
+        }
+
+        writes.insert(key, (number, write)).is_none()
+    }
+
+    fn snapshot(&self) -> Vec<(K, u64, Write<E>)> {
+        self.lock()
+            .iter()
+            .map(|(key, (number, write))| (*key, *number, write.clone()))
+            .collect()
+    }
+
+    /// Dequeue the flushed writes that weren't replaced in the meantime,
+    /// returning how many were dequeued.
+    fn complete(&self, flushed: impl IntoIterator<Item = (K, u64)>) -> usize {
+        let mut writes = self.lock();
+
+        flushed
+            .into_iter()
+            .filter(|(key, number)| {
+                let current = writes.get(key).is_some_and(|(queued, _)| queued == number);
+
+                if current {
+                    writes.remove(key);
+                }
+
+                current
+            })
+            .count()
+    }
+}
+
+/// Flush the writes of a batch to a repository, returning how many entities
+/// were written.
+async fn flush_batch<B, E, R>(batch: &Batch<E::Id, E>, repo: &R) -> Result<usize, B::Error>
+where
+    B: Backend,
+    E: Entity + Clone,
+    R: Repository<E, B> + Sync,
+{
+    let writes = batch.snapshot();
+
+    if writes.is_empty() {
+        return Ok(0);
+    }
+
+    let mut flushed = Vec::with_capacity(writes.len());
+    let mut removals = Vec::new();
+    let mut upserts = Vec::new();
+    let mut versioned = Vec::new();
+
+    for (entity_id, number, write) in writes {
+        flushed.push((entity_id, number));
+
+        match write {
+            Write::Remove => removals.push(entity_id),
+            Write::Upsert {
+                entity,
+                sequence: None,
+            } => upserts.push(entity),
+            Write::Upsert {
+                entity,
+                sequence: Some(sequence),
+            } => versioned.push((entity, sequence)),
+        }
+    }
+
+    future::try_join3(
+        repo.remove_bulk(removals.into_iter()),
+        repo.upsert_bulk(upserts.into_iter()),
+        future::try_join_all(
+            versioned
+                .into_iter()
+                .map(|(entity, sequence)| repo.versioned_upsert(entity, sequence)),
+        ),
+    )
+    .await?;
+
+    Ok(batch.complete(flushed))
+}
+
+/// Flush the queued write of the current user, returning how many entities
+/// were written.
+async fn flush_current_user<B: Backend>(
+    batch: &Batch<(), CurrentUserEntity>,
+    repo: &B::CurrentUserRepository,
+) -> Result<usize, B::Error> {
+    let mut flushed = Vec::new();
+
+    for ((), number, write) in batch.snapshot() {
+        match write {
+            Write::Remove => repo.remove().await?,
+            Write::Upsert { entity, .. } => repo.upsert(entity).await?,
+        }
+
+        flushed.push(((), number));
+    }
+
+    Ok(batch.complete(flushed))
+}
+
+/// Entity that can be queued by a [`BatchingBackend`].
+///
+/// [`BatchingBackend`]: struct.BatchingBackend.html
+pub trait Batchable: Entity + Clone + 'static {
+    /// Return the writes queued for entities of the type.
+    fn batch(writes: &PendingWrites) -> &Batch<Self::Id, Self>;
+}
+
+macro_rules! batches {
+    ($($repository:ident($entity:ty)),* $(,)?) => {
+        /// Writes queued by a [`BatchingBackend`] for each type of entity.
+        ///
+        /// [`BatchingBackend`]: struct.BatchingBackend.html
+        #[derive(Debug, Default)]
+        pub struct PendingWrites {
+            len: AtomicUsize,
+            next: AtomicU64,
+            current_user: Batch<(), CurrentUserEntity>,
+            $($repository: Batch<<$entity as Entity>::Id, $entity>,)*
+        }
+
+        impl PendingWrites {
+            /// Flush the writes to a backend, returning how many entities were
+            /// written.
+            ///
+            /// Writes are dequeued as each type of entity is flushed, so the
+            /// writes that fail to be flushed stay queued.
+            async fn flush<B: Backend>(&self, backend: &B) -> Result<usize, B::Error> {
+                let mut flushed = flush_current_user::<B>(
+                    &self.current_user,
+                    &backend.current_user(),
+                )
+                .await?;
+                self.len.fetch_sub(flushed, Ordering::Relaxed);
+
+                $(
+                    let count =
+                        flush_batch::<B, _, _>(&self.$repository, &backend.$repository()).await?;
+                    self.len.fetch_sub(count, Ordering::Relaxed);
+                    flushed += count;
+                )*
+
+                Ok(flushed)
+            }
+        }
+
+        $(
+            impl Batchable for $entity {
+                fn batch(writes: &PendingWrites) -> &Batch<Self::Id, Self> {
+                    &writes.$repository
+                }
+            }
+        )*
+    };
+}
+
+batches! {
+    attachments(AttachmentEntity),
+    category_channels(CategoryChannelEntity),
+    emojis(EmojiEntity),
+    groups(GroupEntity),
+    guilds(GuildEntity),
+    guild_previews(GuildPreviewEntity),
+    members(MemberEntity),
+    messages(MessageEntity),
+    presences(PresenceEntity),
+    private_channels(PrivateChannelEntity),
+    roles(RoleEntity),
+    text_channels(TextChannelEntity),
+    users(UserEntity),
+    voice_channels(VoiceChannelEntity),
+    voice_regions(VoiceRegionEntity),
+    voice_states(VoiceStateEntity),
+}
+
+impl PendingWrites {
+    /// Queue a write, returning the number of entities with queued writes.
+    fn push<K: Copy + Eq + Hash, E: Clone>(
+        &self,
+        batch: &Batch<K, E>,
+        key: K,
+        write: Write<E>,
+    ) -> usize {
+        let number = self.next.fetch_add(1, Ordering::Relaxed);
+
+        if batch.push(key, number, write) {
+            self.len.fetch_add(1, Ordering::Relaxed) + 1
+        } else {
+            self.len.load(Ordering::Relaxed)
+        }
+    }
+}
+
+fn current_user(writes: &PendingWrites) -> &Batch<(), CurrentUserEntity> {
+    &writes.current_user
+}
+
+struct BatchingState<B> {
+    backend: B,
+    closed: AtomicBool,
+    flushing: AsyncMutex<()>,
+    max_batch_size: usize,
+    writes: PendingWrites,
+}
+
+/// Backend queueing writes and flushing them to the wrapped backend in
+/// batches.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+pub struct BatchingBackend<B>(Arc<BatchingState<B>>);
+
+impl<B: Backend> BatchingBackend<B> {
+    /// Wrap a backend, flushing the queued writes once the given number of
+    /// entities have queued writes.
+    ///
+    /// A batch size of 0 is treated as 1.
+    pub fn new(backend: B, max_batch_size: usize) -> Self {
+        Self(Arc::new(BatchingState {
+            backend,
+            closed: AtomicBool::new(false),
+            flushing: AsyncMutex::new(()),
+            max_batch_size: max_batch_size.max(1),
+            writes: PendingWrites::default(),
+        }))
+    }
+
+    /// Return an immutable reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.0.backend
+    }
+
+    /// Return the number of entities with queued writes.
+    pub fn queued(&self) -> usize {
+        self.0.writes.len.load(Ordering::Relaxed)
+    }
+
+    /// Flush the queued writes to the wrapped backend, returning how many
+    /// entities were written.
+    ///
+    /// Unlike [`Backend::flush`], this doesn't flush the wrapped backend.
+    ///
+    /// # Errors
+    ///
+    /// Returns the wrapped backend's error if a write fails. The writes that
+    /// failed to be flushed stay queued.
+    ///
+    /// [`Backend::flush`]: ../trait.Backend.html#method.flush
+    pub async fn flush_queued(&self) -> Result<usize, B::Error> {
+        // Flushes are serialized so that an older write of an entity can't be
+        // flushed after a newer one.
+        let _flushing = self.0.flushing.lock().await;
+
+        self.0.writes.flush(&self.0.backend).await
+    }
+
+    /// Spawn a task on the Tokio runtime flushing the queued writes every
+    /// period.
+    ///
+    /// The task stops once the backend is closed, or when flushing fails,
+    /// resolving the handle to the error.
+    #[cfg(feature = "tokio")]
+    pub fn spawn_flusher(&self, period: Duration) -> tokio::task::JoinHandle<Result<(), B::Error>> {
+        let backend = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+
+            loop {
+                interval.tick().await;
+
+                if backend.0.closed.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+
+                backend.flush_queued().await?;
+            }
+        })
+    }
+
+    /// Queue a write, flushing the queued writes if the batch is full.
+    fn queue<K: Copy + Eq + Hash, E: Clone>(
+        &self,
+        batch: fn(&PendingWrites) -> &Batch<K, E>,
+        key: K,
+        write: Write<E>,
+    ) -> UpsertEntityFuture<'_, B::Error> {
+        let writes = &self.0.writes;
+
+        if writes.push(batch(writes), key, write) < self.0.max_batch_size {
+            return future::ok(()).boxed();
+        }
+
+        self.flush_queued().map_ok(|_| ()).boxed()
+    }
+
+    fn repository<R>(&self, f: impl FnOnce(&B) -> R) -> BatchingRepository<B, R> {
+        BatchingRepository {
+            backend: self.clone(),
+            repo: f(&self.0.backend),
+        }
+    }
+}
+
+impl<B> Clone for BatchingBackend<B> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<B: Debug> Debug for BatchingBackend<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("BatchingBackend")
+            .field("backend", &self.0.backend)
+            .field("max_batch_size", &self.0.max_batch_size)
+            .field("queued", &self.0.writes.len.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<B: Backend> Backend for BatchingBackend<B> {
+    type Error = B::Error;
+    type AttachmentRepository = BatchingRepository<B, B::AttachmentRepository>;
+    type CategoryChannelRepository = BatchingRepository<B, B::CategoryChannelRepository>;
+    type CurrentUserRepository = BatchingRepository<B, B::CurrentUserRepository>;
+    type EmojiRepository = BatchingRepository<B, B::EmojiRepository>;
+    type GroupRepository = BatchingRepository<B, B::GroupRepository>;
+    type GuildPreviewRepository = BatchingRepository<B, B::GuildPreviewRepository>;
+    type GuildRepository = BatchingRepository<B, B::GuildRepository>;
+    type MemberRepository = BatchingRepository<B, B::MemberRepository>;
+    type MessageRepository = BatchingRepository<B, B::MessageRepository>;
+    type PresenceRepository = BatchingRepository<B, B::PresenceRepository>;
+    type PrivateChannelRepository = BatchingRepository<B, B::PrivateChannelRepository>;
+    type RoleRepository = BatchingRepository<B, B::RoleRepository>;
+    type TextChannelRepository = BatchingRepository<B, B::TextChannelRepository>;
+    type UserRepository = BatchingRepository<B, B::UserRepository>;
+    type VoiceChannelRepository = BatchingRepository<B, B::VoiceChannelRepository>;
+    type VoiceRegionRepository = BatchingRepository<B, B::VoiceRegionRepository>;
+    type VoiceStateRepository = BatchingRepository<B, B::VoiceStateRepository>;
+
+    fn attachments(&self) -> Self::AttachmentRepository {
+        self.repository(B::attachments)
+    }
+
+    fn category_channels(&self) -> Self::CategoryChannelRepository {
+        self.repository(B::category_channels)
+    }
+
+    fn current_user(&self) -> Self::CurrentUserRepository {
+        self.repository(B::current_user)
+    }
+
+    fn emojis(&self) -> Self::EmojiRepository {
+        self.repository(B::emojis)
+    }
+
+    fn groups(&self) -> Self::GroupRepository {
+        self.repository(B::groups)
+    }
+
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        self.repository(B::guild_previews)
+    }
+
+    fn guilds(&self) -> Self::GuildRepository {
+        self.repository(B::guilds)
+    }
+
+    fn members(&self) -> Self::MemberRepository {
+        self.repository(B::members)
+    }
+
+    fn messages(&self) -> Self::MessageRepository {
+        self.repository(B::messages)
+    }
+
+    fn presences(&self) -> Self::PresenceRepository {
+        self.repository(B::presences)
+    }
+
+    fn private_channels(&self) -> Self::PrivateChannelRepository {
+        self.repository(B::private_channels)
+    }
+
+    fn roles(&self) -> Self::RoleRepository {
+        self.repository(B::roles)
+    }
+
+    fn text_channels(&self) -> Self::TextChannelRepository {
+        self.repository(B::text_channels)
+    }
+
+    fn users(&self) -> Self::UserRepository {
+        self.repository(B::users)
+    }
+
+    fn voice_channels(&self) -> Self::VoiceChannelRepository {
+        self.repository(B::voice_channels)
+    }
+
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        self.repository(B::voice_regions)
+    }
+
+    fn voice_states(&self) -> Self::VoiceStateRepository {
+        self.repository(B::voice_states)
+    }
+
+    fn connect(&self) -> ConnectFuture<'_, Self::Error> {
+        self.0.backend.connect()
+    }
+
+    fn reconnect(&self) -> ConnectFuture<'_, Self::Error> {
+        self.0.backend.reconnect()
+    }
+
+    fn flush(&self) -> FlushFuture<'_, Self::Error> {
+        Box::pin(async move {
+            self.flush_queued().await?;
+
+            self.0.backend.flush().await
+        })
+    }
+
+    fn close(&self) -> CloseFuture<'_, Self::Error> {
+        self.0.closed.store(true, Ordering::Relaxed);
+
+        self.0.backend.close()
+    }
+
+    fn health(&self) -> HealthFuture<'_, Self::Error> {
+        self.0.backend.health()
+    }
+
+    #[cfg(feature = "compression")]
+    fn compression_stats(&self) -> Option<CompressionStats> {
+        self.0.backend.compression_stats()
+    }
+
+    fn caches_guild(&self, guild_id: GuildId) -> bool {
+        self.0.backend.caches_guild(guild_id)
+    }
+
+    fn pins_guild(&self, guild_id: GuildId) -> bool {
+        self.0.backend.pins_guild(guild_id)
+    }
+
+    fn pins_user(&self, user_id: UserId) -> bool {
+        self.0.backend.pins_user(user_id)
+    }
+
+    fn verify_integrity(&self, repair: bool) -> VerifyIntegrityFuture<'_, Self::Error> {
+        Box::pin(async move {
+            self.flush_queued().await?;
+
+            self.0.backend.verify_integrity(repair).await
+        })
+    }
+}
+
+/// Repository of a [`BatchingBackend`] wrapping a repository of the wrapped
+/// backend.
+///
+/// Taking an entity or upserting it with an outcome queues the write instead
+/// of forwarding it to the wrapped repository. Refer to the [module-level
+/// documentation] for more information.
+///
+/// [`BatchingBackend`]: struct.BatchingBackend.html
+/// [module-level documentation]: index.html
+#[derive(Debug)]
+pub struct BatchingRepository<B, R> {
+    backend: BatchingBackend<B>,
+    repo: R,
+}
+
+impl<B, R> BatchingRepository<B, R> {
+    /// Return an immutable reference to the wrapped repository.
+    pub fn inner(&self) -> &R {
+        &self.repo
+    }
+}
+
+impl<B: Backend, R: Sync> BatchingRepository<B, R> {
+    /// Flush the queued writes before reading from the wrapped repository.
+    fn flushed<'a, T: 'a>(
+        &'a self,
+        read: impl FnOnce(&'a R) -> ReadFuture<'a, T, B::Error> + Send + 'a,
+    ) -> ReadFuture<'a, T, B::Error> {
+        Box::pin(async move {
+            self.backend.flush_queued().await?;
+
+            read(&self.repo).await
+        })
+    }
+}
+
+impl<B, R: Clone> Clone for BatchingRepository<B, R> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            repo: self.repo.clone(),
+        }
+    }
+}
+
+impl<B, E, R> Repository<E, BatchingBackend<B>> for BatchingRepository<B, R>
+where
+    B: Backend,
+    E: Batchable,
+    R: Repository<E, B> + Send + Sync,
+{
+    fn backend(&self) -> &BatchingBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        match E::batch(&self.backend.0.writes).get(entity_id) {
+            Some(queued) => future::ok(queued).boxed(),
+            None => self.repo.get(entity_id),
+        }
+    }
+
+    fn get_local(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        match E::batch(&self.backend.0.writes).get(entity_id) {
+            Some(queued) => future::ok(queued).boxed(),
+            None => self.repo.get_local(entity_id),
+        }
+    }
+
+    fn get_with_meta(
+        &self,
+        entity_id: E::Id,
+    ) -> GetEntityFuture<'_, (E, Option<CacheMeta>), B::Error> {
+        // Queued entities haven't been cached by the wrapped backend yet.
+        match E::batch(&self.backend.0.writes).get(entity_id) {
+            Some(queued) => future::ok(queued.map(|entity| (entity, None))).boxed(),
+            None => self.repo.get_with_meta(entity_id),
+        }
+    }
+
+    fn list(&self) -> ListEntitiesFuture<'_, E, B::Error> {
+        self.flushed(R::list)
+    }
+
+    fn remove(&self, entity_id: E::Id) -> RemoveEntityFuture<'_, B::Error> {
+        self.backend.queue(E::batch, entity_id, Write::Remove)
+    }
+
+    fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, B::Error> {
+        let entity_id = entity.id();
+        let write = Write::Upsert {
+            entity,
+            sequence: None,
+        };
+
+        self.backend.queue(E::batch, entity_id, write)
+    }
+
+    fn versioned_upsert(&self, entity: E, sequence: u64) -> UpsertEntityFuture<'_, B::Error> {
+        let entity_id = entity.id();
+        let write = Write::Upsert {
+            entity,
+            sequence: Some(sequence),
+        };
+
+        self.backend.queue(E::batch, entity_id, write)
+    }
+}
+
+impl<B, R> SingleEntityRepository<CurrentUserEntity, BatchingBackend<B>>
+    for BatchingRepository<B, R>
+where
+    B: Backend,
+    R: SingleEntityRepository<CurrentUserEntity, B> + Send + Sync,
+{
+    fn backend(&self) -> &BatchingBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self) -> GetEntityFuture<'_, CurrentUserEntity, B::Error> {
+        match self.backend.0.writes.current_user.get(()) {
+            Some(queued) => future::ok(queued).boxed(),
+            None => self.repo.get(),
+        }
+    }
+
+    fn get_local(&self) -> GetEntityFuture<'_, CurrentUserEntity, B::Error> {
+        match self.backend.0.writes.current_user.get(()) {
+            Some(queued) => future::ok(queued).boxed(),
+            None => self.repo.get_local(),
+        }
+    }
+
+    fn remove(&self) -> RemoveEntityFuture<'_, B::Error> {
+        self.backend.queue(current_user, (), Write::Remove)
+    }
+
+    fn upsert(&self, entity: CurrentUserEntity) -> UpsertEntityFuture<'_, B::Error> {
+        let write = Write::Upsert {
+            entity,
+            sequence: None,
+        };
+
+        self.backend.queue(current_user, (), write)
+    }
+}
+
+impl<B: Backend> AttachmentRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::AttachmentRepository>
+{
+}
+
+impl<B: Backend> CategoryChannelRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::CategoryChannelRepository>
+{
+}
+
+impl<B: Backend> CurrentUserRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::CurrentUserRepository>
+{
+    fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        self.flushed(|repo| repo.guild_ids())
+    }
+}
+
+impl<B: Backend> EmojiRepository<BatchingBackend<B>> for BatchingRepository<B, B::EmojiRepository> {}
+
+impl<B: Backend> GroupRepository<BatchingBackend<B>> for BatchingRepository<B, B::GroupRepository> {}
+
+impl<B: Backend> GuildPreviewRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::GuildPreviewRepository>
+{
+}
+
+impl<B: Backend> GuildRepository<BatchingBackend<B>> for BatchingRepository<B, B::GuildRepository> {
+    fn channel_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, ChannelId, B::Error> {
+        self.flushed(move |repo| repo.channel_ids(guild_id))
+    }
+
+    fn channels(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, GuildChannelEntity, B::Error> {
+        self.flushed(move |repo| repo.channels(guild_id))
+    }
+
+    fn emoji_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, EmojiId, B::Error> {
+        self.flushed(move |repo| repo.emoji_ids(guild_id))
+    }
+
+    fn member_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.flushed(move |repo| repo.member_ids(guild_id))
+    }
+
+    fn members(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        self.flushed(move |repo| repo.members(guild_id))
+    }
+
+    fn members_with_users(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, (MemberEntity, Option<UserEntity>), B::Error> {
+        self.flushed(move |repo| repo.members_with_users(guild_id))
+    }
+
+    fn presence_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.flushed(move |repo| repo.presence_ids(guild_id))
+    }
+
+    fn presences(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, PresenceEntity, B::Error> {
+        self.flushed(move |repo| repo.presences(guild_id))
+    }
+
+    fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, B::Error> {
+        self.flushed(move |repo| repo.role_ids(guild_id))
+    }
+
+    fn search_members(
+        &self,
+        guild_id: GuildId,
+        query: &str,
+        limit: usize,
+    ) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        let query = query.to_owned();
+
+        self.flushed(move |repo| repo.search_members(guild_id, &query, limit))
+    }
+
+    fn voice_state_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.flushed(move |repo| repo.voice_state_ids(guild_id))
+    }
+
+    fn voice_states(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceStateEntity, B::Error> {
+        self.flushed(move |repo| repo.voice_states(guild_id))
+    }
+}
+
+impl<B: Backend> MemberRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::MemberRepository>
+{
+    fn nick_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, NameChange, B::Error> {
+        self.flushed(move |repo| repo.nick_history(guild_id, user_id))
+    }
+}
+
+impl<B: Backend> MessageRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::MessageRepository>
+{
+    fn context(&self, message_id: MessageId) -> GetEntityFuture<'_, MessageContext, B::Error> {
+        self.flushed(move |repo| repo.context(message_id))
+    }
+}
+
+impl<B: Backend> PresenceRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::PresenceRepository>
+{
+}
+
+impl<B: Backend> PrivateChannelRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::PrivateChannelRepository>
+{
+}
+
+impl<B: Backend> RoleRepository<BatchingBackend<B>> for BatchingRepository<B, B::RoleRepository> {}
+
+impl<B: Backend> TextChannelRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::TextChannelRepository>
+{
+}
+
+impl<B: Backend> UserRepository<BatchingBackend<B>> for BatchingRepository<B, B::UserRepository> {
+    fn avatar_history(&self, user_id: UserId) -> ListEntitiesFuture<'_, AvatarChange, B::Error> {
+        self.flushed(move |repo| repo.avatar_history(user_id))
+    }
+
+    fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        self.flushed(move |repo| repo.guild_ids(user_id))
+    }
+}
+
+impl<B: Backend> VoiceChannelRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::VoiceChannelRepository>
+{
+}
+
+impl<B: Backend> VoiceRegionRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::VoiceRegionRepository>
+{
+}
+
+impl<B: Backend> VoiceStateRepository<BatchingBackend<B>>
+    for BatchingRepository<B, B::VoiceStateRepository>
+{
+    fn transition_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, VoiceStateChange, B::Error> {
+        self.flushed(move |repo| repo.transition_history(guild_id, user_id))
+    }
+}
@@ -1,4 +1,9 @@
+#[cfg(feature = "compression")]
+use super::compression::CompressionStats;
+#[cfg(feature = "export")]
+use super::export::{self, ExportError, ExportType};
 use super::{
+    any::{self, AnyEntity, EntityKind, RawId},
     entity::{
         channel::{
             AttachmentEntity, CategoryChannelEntity, GroupEntity, GuildChannelEntity,
@@ -8,37 +13,136 @@ use super::{
         gateway::PresenceEntity,
         guild::{EmojiEntity, GuildEntity, GuildRepository, MemberEntity, RoleEntity},
         user::{CurrentUserEntity, UserEntity},
-        voice::VoiceStateEntity,
+        voice::{VoiceStateEntity, VoiceTransition},
+        MergePartial,
     },
-    repository::SingleEntityRepository,
-    Backend, Repository,
+    hook::{CacheChange, ChangeHook},
+    integrity::IntegrityReport,
+    intercept::{EntityInterceptor, Intercept, SharedInterceptor},
+    limit::BackendOpLimit,
+    metrics::{EventMetrics, EventStats, SlowEventHook},
+    ordering::{self, OrderingLocks},
+    prune::{PruneStats, Pruner},
+    repository::{GetEntityFuture, SingleEntityRepository, UpsertEntityFuture},
+    session::{Session, Sessions},
+    unavailable::UnavailableGuilds,
+    view::{ChannelCacheView, GuildCacheView},
+    Backend, Entity, Repository,
 };
+#[cfg(feature = "emoji-usage")]
+use super::{emoji_usage, entity::guild::EmojiRepository};
 use futures_util::{
     future::{self, FutureExt, TryFutureExt},
     stream::{FuturesUnordered, StreamExt, TryStreamExt},
 };
 use std::{
+    any::{self, Any},
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
     future::Future,
+    panic::AssertUnwindSafe,
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
+#[cfg(feature = "export")]
+use std::{io::Write, path::Path};
 use twilight_model::{
-    channel::{Channel, GuildChannel},
+    application::interaction::Interaction,
+    channel::{message::MessageFlags, Channel, GuildChannel},
     gateway::{
-        event::Event,
+        event::{Event, EventType},
         payload::{
             ChannelCreate, ChannelDelete, ChannelPinsUpdate, ChannelUpdate, GuildCreate,
-            GuildDelete, GuildEmojisUpdate, GuildUpdate, MemberAdd, MemberChunk, MemberRemove,
-            MemberUpdate, MessageCreate, MessageDelete, MessageDeleteBulk, MessageUpdate,
-            PresenceUpdate, Ready, RoleCreate, RoleDelete, RoleUpdate, UserUpdate,
-            VoiceStateUpdate,
+            GuildDelete, GuildEmojisUpdate, GuildUpdate, InteractionCreate, MemberAdd, MemberChunk,
+            MemberRemove, MemberUpdate, MessageCreate, MessageDelete, MessageDeleteBulk,
+            MessageUpdate, PresenceUpdate, Ready, RoleCreate, RoleDelete, RoleUpdate,
+            UnavailableGuild, UserUpdate, VoiceStateUpdate,
         },
         presence::UserOrId,
     },
+    guild::{Guild, GuildStatus},
+    id::{ChannelId, GuildId, UserId},
 };
+#[cfg(feature = "emoji-usage")]
+use twilight_model::{channel::ReactionType, gateway::payload::ReactionAdd};
+
+/// Future updating the cache, resolving to an error with the context of the
+/// failed operation.
+type UpdateFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<(), ProcessError<<T as Backend>::Error>>> + Send + 'a>>;
+
+/// Return the ID of the guild that an event is for, if any.
+fn event_guild_id(event: &Event) -> Option<GuildId> {
+    let channel = |channel: &Channel| match channel {
+        Channel::Guild(channel) => channel.guild_id(),
+        _ => None,
+    };
+
+    match event {
+        Event::ChannelCreate(event) => channel(&event.0),
+        Event::ChannelDelete(event) => channel(&event.0),
+        Event::ChannelPinsUpdate(event) => event.guild_id,
+        Event::ChannelUpdate(event) => channel(&event.0),
+        Event::GuildCreate(event) => Some(event.id),
+        Event::GuildDelete(event) => Some(event.id),
+        Event::GuildEmojisUpdate(event) => Some(event.guild_id),
+        Event::GuildUpdate(event) => Some(event.id),
+        Event::InteractionCreate(event) => interaction_guild_id(&event.0),
+        Event::MemberAdd(event) => Some(event.guild_id),
+        Event::MemberChunk(event) => Some(event.guild_id),
+        Event::MemberRemove(event) => Some(event.guild_id),
+        Event::MemberUpdate(event) => Some(event.guild_id),
+        Event::MessageCreate(event) => event.guild_id,
+        Event::MessageDelete(event) => event.guild_id,
+        Event::MessageDeleteBulk(event) => event.guild_id,
+        Event::MessageUpdate(event) => event.guild_id,
+        Event::PresenceUpdate(event) => Some(event.guild_id),
+        Event::ReactionAdd(event) => event.guild_id,
+        Event::RoleCreate(event) => Some(event.guild_id),
+        Event::RoleDelete(event) => Some(event.guild_id),
+        Event::RoleUpdate(event) => Some(event.guild_id),
+        Event::UnavailableGuild(event) => Some(event.id),
+        Event::VoiceStateUpdate(event) => event.0.guild_id,
+        _ => None,
+    }
+}
+
+/// Return the ID of the guild that an interaction was invoked in, if any.
+fn interaction_guild_id(interaction: &Interaction) -> Option<GuildId> {
+    if let Interaction::ApplicationCommand(command) = interaction {
+        command.guild_id
+    } else {
+        None
+    }
+}
 
-fn noop<T: Backend>() -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send>> {
+/// Return whether an event is for a private channel or group.
+fn is_private_event(event: &Event) -> bool {
+    let channel = |channel: &Channel| matches!(channel, Channel::Group(_) | Channel::Private(_));
+
+    match event {
+        Event::ChannelCreate(event) => channel(&event.0),
+        Event::ChannelDelete(event) => channel(&event.0),
+        Event::ChannelPinsUpdate(event) => event.guild_id.is_none(),
+        Event::ChannelUpdate(event) => channel(&event.0),
+        Event::InteractionCreate(event) => matches!(
+            &event.0,
+            Interaction::ApplicationCommand(command) if command.guild_id.is_none()
+        ),
+        Event::MessageCreate(event) => event.guild_id.is_none(),
+        Event::MessageDelete(event) => event.guild_id.is_none(),
+        Event::MessageDeleteBulk(event) => event.guild_id.is_none(),
+        Event::MessageUpdate(event) => event.guild_id.is_none(),
+        Event::ReactionAdd(event) => event.guild_id.is_none(),
+        _ => false,
+    }
+}
+
+fn noop<T: Backend>() -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send>> {
     future::ok(()).boxed()
 }
 
@@ -46,26 +150,269 @@ pub trait CacheUpdate<T: Backend> {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>>;
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>>;
+
+    /// Update the cache with the event, upserting entities with a sequence so
+    /// that backends can reject stale writes.
+    ///
+    /// Refer to [`Repository::versioned_upsert`] for more information.
+    ///
+    /// [`Repository::versioned_upsert`]: ../repository/trait.Repository.html#method.versioned_upsert
+    fn process_sequenced<'a>(
+        &'a self,
+        cache: &'a Cache<T>,
+        sequence: u64,
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>>
+    where
+        Self: Sync,
+    {
+        let cache = cache.with_sequence(sequence);
+
+        Box::pin(async move { self.process(&cache).await })
+    }
 }
 
 pub struct ProcessFuture<'a, T: Backend> {
-    inner: Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>>,
+    inner: Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>>,
 }
 
 impl<T: Backend> Future for ProcessFuture<'_, T> {
-    type Output = Result<(), T::Error>;
+    type Output = Result<(), ProcessError<T::Error>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         self.inner.poll_unpin(cx)
     }
 }
 
+/// Kinds of events that the cache processes.
+///
+/// Refer to [`Cache::set_mode`] for more information.
+///
+/// [`Cache::set_mode`]: struct.Cache.html#method.set_mode
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum CacheMode {
+    /// Process events for both guilds and private channels.
+    #[default]
+    Full,
+    /// Only process events for private channels and groups, skipping the
+    /// events of guilds.
+    DirectMessagesOnly,
+    /// Only process events for guilds, skipping the events of private
+    /// channels and groups.
+    GuildsOnly,
+}
+
+/// Operation on a backend that failed while processing an event.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ProcessOperation {
+    /// Retrieving an entity.
+    Get,
+    /// Listing the entities related to an entity, such as a guild's members.
+    List,
+    /// Removing an entity.
+    Remove,
+    /// Upserting an entity.
+    Upsert,
+}
+
+impl ProcessOperation {
+    /// Return the name of the operation.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Get => "get",
+            Self::List => "list",
+            Self::Remove => "remove",
+            Self::Upsert => "upsert",
+        }
+    }
+}
+
+/// Error returned when processing an event fails.
+///
+/// Carries the type of the event being processed along with the reason for
+/// the error, such as the backend operation and type of entity that failed,
+/// so that failing writes can be diagnosed.
+#[derive(Debug)]
+pub struct ProcessError<E> {
+    event_type: Option<EventType>,
+    kind: ProcessErrorType<E>,
+}
+
+impl<E> ProcessError<E> {
+    /// Type of the event being processed.
+    ///
+    /// This is `None` for operations made outside of processing an event,
+    /// such as while reconciling a guild.
+    pub const fn event_type(&self) -> Option<EventType> {
+        self.event_type
+    }
+
+    /// Immutable reference to the type of error that occurred.
+    pub const fn kind(&self) -> &ProcessErrorType<E> {
+        &self.kind
+    }
+
+    /// Consume the error, returning the type of error that occurred.
+    pub fn into_kind(self) -> ProcessErrorType<E> {
+        self.kind
+    }
+
+    /// Consume the error, returning the backend's error if a backend
+    /// operation failed.
+    pub fn into_source(self) -> Option<E> {
+        match self.kind {
+            ProcessErrorType::Backend { source, .. } => Some(source),
+            ProcessErrorType::Panicked { .. } => None,
+        }
+    }
+
+    fn new<En: Entity>(operation: ProcessOperation, source: E) -> Self {
+        let entity_type = any::type_name::<En>();
+
+        Self {
+            event_type: None,
+            kind: ProcessErrorType::Backend {
+                entity_type: entity_type.rsplit("::").next().unwrap_or(entity_type),
+                operation,
+                source,
+            },
+        }
+    }
+
+    fn get<En: Entity>(source: E) -> Self {
+        Self::new::<En>(ProcessOperation::Get, source)
+    }
+
+    fn list<En: Entity>(source: E) -> Self {
+        Self::new::<En>(ProcessOperation::List, source)
+    }
+
+    fn remove<En: Entity>(source: E) -> Self {
+        Self::new::<En>(ProcessOperation::Remove, source)
+    }
+
+    fn upsert<En: Entity>(source: E) -> Self {
+        Self::new::<En>(ProcessOperation::Upsert, source)
+    }
+
+    /// Create an error from the payload of a caught panic.
+    fn panicked(payload: Box<dyn Any + Send>) -> Self {
+        let message = payload
+            .downcast::<String>()
+            .map(|message| *message)
+            .or_else(|payload| {
+                payload
+                    .downcast::<&str>()
+                    .map(|message| (*message).to_owned())
+            })
+            .ok();
+
+        Self {
+            event_type: None,
+            kind: ProcessErrorType::Panicked { message },
+        }
+    }
+
+    /// Set the type of the event being processed, if it isn't already set by
+    /// a buffered event.
+    fn during(mut self, event_type: EventType) -> Self {
+        self.event_type = self.event_type.or(Some(event_type));
+
+        self
+    }
+}
+
+impl<E> Display for ProcessError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            ProcessErrorType::Backend {
+                entity_type,
+                operation,
+                ..
+            } => {
+                f.write_str(operation.name())?;
+                f.write_str(" of ")?;
+                f.write_str(entity_type)?;
+                f.write_str(" failed")?;
+            }
+            ProcessErrorType::Panicked { message } => {
+                f.write_str("backend panicked")?;
+
+                if let Some(message) = message {
+                    f.write_str(": ")?;
+                    f.write_str(message)?;
+                }
+            }
+        }
+
+        if let Some(event_type) = self.event_type {
+            f.write_str(" while processing ")?;
+            Debug::fmt(&event_type, f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<E: Error + 'static> Error for ProcessError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ProcessErrorType::Backend { source, .. } => Some(source),
+            ProcessErrorType::Panicked { .. } => None,
+        }
+    }
+}
+
+/// Type of [`ProcessError`] that occurred.
+///
+/// [`ProcessError`]: struct.ProcessError.html
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProcessErrorType<E> {
+    /// A backend operation failed.
+    Backend {
+        /// Name of the type of entity that the operation was performed on,
+        /// such as `MemberEntity`.
+        ///
+        /// For listings, this is the type of the entity whose related
+        /// entities were being listed.
+        entity_type: &'static str,
+        /// Operation that failed.
+        operation: ProcessOperation,
+        /// Backend's error.
+        source: E,
+    },
+    /// Processing the event panicked, such as in a backend's future, and the
+    /// panic was caught.
+    ///
+    /// Refer to [`Cache::enable_panic_isolation`] for more information.
+    ///
+    /// [`Cache::enable_panic_isolation`]: struct.Cache.html#method.enable_panic_isolation
+    Panicked {
+        /// Message that the panic was raised with, if it was a string.
+        message: Option<String>,
+    },
+}
+
 /// The cache, a container over a backend that allows you to retrieve and work
 /// with entities.
 #[derive(Clone, Debug, Default)]
 pub struct Cache<T: Backend> {
     backend: Arc<T>,
+    backend_ops: Option<Arc<BackendOpLimit>>,
+    filter_ephemeral: bool,
+    hooks: Vec<ChangeHook>,
+    interceptor: Option<SharedInterceptor>,
+    isolate_panics: bool,
+    metrics: Arc<EventMetrics>,
+    mode: CacheMode,
+    ordering: Option<Arc<OrderingLocks>>,
+    sequence: Option<u64>,
+    sessions: Arc<Sessions>,
+    slow_event_hook: Option<SlowEventHook>,
+    suppress_duplicates: bool,
+    unavailable: Option<Arc<UnavailableGuilds>>,
     /// Repository for working with attachments.
     pub attachments: T::AttachmentRepository,
     /// Repository for working with category channels.
@@ -76,6 +423,8 @@ pub struct Cache<T: Backend> {
     pub emojis: T::EmojiRepository,
     /// Repository for working with groups.
     pub groups: T::GroupRepository,
+    /// Repository for working with guild previews.
+    pub guild_previews: T::GuildPreviewRepository,
     /// Repository for working with guilds.
     pub guilds: T::GuildRepository,
     /// Repository for working with members.
@@ -94,6 +443,8 @@ pub struct Cache<T: Backend> {
     pub users: T::UserRepository,
     /// Repository for working with users.
     pub voice_channels: T::VoiceChannelRepository,
+    /// Repository for working with voice regions.
+    pub voice_regions: T::VoiceRegionRepository,
     /// Repository for working with voice state.
     pub voice_states: T::VoiceStateRepository,
 }
@@ -114,6 +465,7 @@ impl<T: Backend> Cache<T> {
         let current_user = backend.current_user();
         let emojis = backend.emojis();
         let groups = backend.groups();
+        let guild_previews = backend.guild_previews();
         let guilds = backend.guilds();
         let members = backend.members();
         let messages = backend.messages();
@@ -123,15 +475,30 @@ impl<T: Backend> Cache<T> {
         let text_channels = backend.text_channels();
         let users = backend.users();
         let voice_channels = backend.voice_channels();
+        let voice_regions = backend.voice_regions();
         let voice_states = backend.voice_states();
 
         Self {
-            attachments,
             backend,
+            backend_ops: None,
+            filter_ephemeral: false,
+            hooks: Vec::new(),
+            interceptor: None,
+            isolate_panics: false,
+            metrics: Arc::default(),
+            mode: CacheMode::Full,
+            ordering: None,
+            sequence: None,
+            sessions: Arc::default(),
+            slow_event_hook: None,
+            suppress_duplicates: false,
+            unavailable: None,
+            attachments,
             category_channels,
             current_user,
             emojis,
             groups,
+            guild_previews,
             guilds,
             members,
             messages,
@@ -141,49 +508,918 @@ impl<T: Backend> Cache<T> {
             text_channels,
             users,
             voice_channels,
+            voice_regions,
             voice_states,
         }
     }
 
-    /// Return an immutable reference to the backend.
-    pub fn backend(&self) -> &Arc<T> {
-        &self.backend
+    /// Return an immutable reference to the backend.
+    pub fn backend(&self) -> &Arc<T> {
+        &self.backend
+    }
+
+    /// Get a view of the cache scoped to a channel of any kind, if the
+    /// channel is cached.
+    ///
+    /// Refer to the [`view`] module for more information.
+    ///
+    /// # Errors
+    ///
+    /// Returns a backend error if a backend repository operation errors.
+    ///
+    /// [`view`]: view/index.html
+    pub fn channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> GetEntityFuture<'_, ChannelCacheView<'_, T>, T::Error> {
+        ChannelCacheView::resolve(self, channel_id)
+    }
+
+    /// Return a view of the cache scoped to a guild.
+    ///
+    /// Refer to the [`view`] module for more information.
+    ///
+    /// [`view`]: view/index.html
+    pub const fn guild(&self, guild_id: GuildId) -> GuildCacheView<'_, T> {
+        GuildCacheView::new(self, guild_id)
+    }
+
+    /// Register a hook to be called with the changes made to the cache while
+    /// processing events.
+    ///
+    /// Refer to the [`hook`] module for more information.
+    ///
+    /// [`hook`]: hook/index.html
+    pub fn add_hook(&mut self, hook: impl Fn(&CacheChange) + Send + Sync + 'static) {
+        self.hooks.push(ChangeHook::new(hook));
+    }
+
+    /// Set the interceptor called with entities before they're upserted while
+    /// processing events, replacing any previously set interceptor.
+    ///
+    /// Refer to the [`intercept`] module for more information.
+    ///
+    /// [`intercept`]: intercept/index.html
+    pub fn set_entity_interceptor(&mut self, interceptor: impl EntityInterceptor + 'static) {
+        self.interceptor = Some(SharedInterceptor(Arc::new(interceptor)));
+    }
+
+    /// Update the cache with an event, upserting entities with a sequence so
+    /// that backends can reject stale writes.
+    ///
+    /// The sequence must increase with each event, such as the sequence
+    /// number of the gateway event. Refer to
+    /// [`Repository::versioned_upsert`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ProcessError`] with the backend's error if a backend
+    /// repository operation errors.
+    ///
+    /// [`ProcessError`]: cache/struct.ProcessError.html
+    /// [`Repository::versioned_upsert`]: repository/trait.Repository.html#method.versioned_upsert
+    pub fn process_sequenced<'a>(
+        &'a self,
+        event: &'a Event,
+        sequence: u64,
+    ) -> ProcessFuture<'a, T> {
+        self.process_event(event, Some(sequence))
+    }
+
+    /// Create a cache sharing the backend, hooks, and metrics of this one
+    /// that upserts entities with a sequence.
+    fn with_sequence(&self, sequence: u64) -> Self {
+        Self {
+            sequence: Some(sequence),
+            ..self.clone()
+        }
+    }
+
+    /// Retrieve an entity, attaching the operation to the error.
+    fn get<'a, E: Entity + 'a, R: Repository<E, T>>(
+        &'a self,
+        repo: &'a R,
+        entity_id: E::Id,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<E>, ProcessError<T::Error>>> + Send + 'a>> {
+        repo.get(entity_id).map_err(ProcessError::get::<E>).boxed()
+    }
+
+    /// Remove an entity, attaching the operation to the error.
+    fn remove<'a, E: Entity + 'a, R: Repository<E, T>>(
+        &'a self,
+        repo: &'a R,
+        entity_id: E::Id,
+    ) -> UpdateFuture<'a, T> {
+        repo.remove(entity_id)
+            .map_err(ProcessError::remove::<E>)
+            .boxed()
+    }
+
+    /// Upsert an entity after passing it through the interceptor.
+    fn upsert<'a, E: Entity + Intercept + PartialEq + 'a, R: Repository<E, T> + Sync>(
+        &'a self,
+        repo: &'a R,
+        entity: E,
+    ) -> UpdateFuture<'a, T> {
+        match self.intercept(entity) {
+            Some(entity) => self.write(repo, entity),
+            None => future::ok(()).boxed(),
+        }
+    }
+
+    /// Upsert an entity built from partial data after merging it into the
+    /// cached entity, if any, and passing it through the interceptor.
+    ///
+    /// Refer to [`MergePartial`] for more information.
+    ///
+    /// [`MergePartial`]: entity/trait.MergePartial.html
+    fn upsert_partial<
+        'a,
+        E: Entity + Intercept + MergePartial + PartialEq + 'a,
+        R: Repository<E, T> + Sync,
+    >(
+        &'a self,
+        repo: &'a R,
+        partial: E,
+    ) -> UpdateFuture<'a, T> {
+        Box::pin(async move {
+            let entity = match self.get(repo, partial.id()).await? {
+                Some(cached) => cached.merge_partial(partial),
+                None => partial,
+            };
+
+            self.upsert(repo, entity).await
+        })
+    }
+
+    /// Upsert entities after passing them through the interceptor.
+    ///
+    /// With a limit on concurrent backend operations, entities are upserted
+    /// in chunks no larger than the limit, one chunk at a time.
+    fn upsert_bulk<'a, E: Entity + Intercept + PartialEq + 'a, R: Repository<E, T> + Sync>(
+        &'a self,
+        repo: &'a R,
+        entities: impl Iterator<Item = E> + Send + 'a,
+    ) -> UpdateFuture<'a, T> {
+        let entities = entities.filter_map(move |entity| self.intercept(entity));
+
+        let Some(limit) = self.backend_ops.as_deref() else {
+            return self.write_bulk(repo, entities);
+        };
+
+        Box::pin(async move {
+            let mut entities = entities;
+
+            loop {
+                let chunk = entities.by_ref().take(limit.max()).collect::<Vec<_>>();
+
+                if chunk.is_empty() {
+                    return Ok(());
+                }
+
+                let _permit = limit.acquire(chunk.len()).await;
+                self.write_bulk(repo, chunk.into_iter()).await?;
+            }
+        })
+    }
+
+    /// Upsert entities, with the sequence of the event being processed if
+    /// there is one.
+    ///
+    /// With duplicate suppression enabled, entities identical to the cached
+    /// ones are skipped.
+    fn write_bulk<'a, E: Entity + PartialEq + 'a, R: Repository<E, T> + Sync>(
+        &'a self,
+        repo: &'a R,
+        entities: impl Iterator<Item = E> + Send + 'a,
+    ) -> UpdateFuture<'a, T> {
+        match self.sequence {
+            Some(sequence) => Box::pin(
+                future::try_join_all(
+                    entities.map(|entity| repo.versioned_upsert(entity, sequence)),
+                )
+                .map_ok(|_| ())
+                .map_err(ProcessError::upsert::<E>),
+            ),
+            None if self.suppress_duplicates => Box::pin(async move {
+                let changed = future::try_join_all(entities.map(|entity| async move {
+                    let cached = repo
+                        .get(entity.id())
+                        .await
+                        .map_err(ProcessError::get::<E>)?;
+
+                    Ok::<_, ProcessError<T::Error>>(
+                        (cached.as_ref() != Some(&entity)).then_some(entity),
+                    )
+                }))
+                .await?;
+
+                repo.upsert_bulk(changed.into_iter().flatten())
+                    .await
+                    .map_err(ProcessError::upsert::<E>)
+            }),
+            None => repo
+                .upsert_bulk(entities)
+                .map_err(ProcessError::upsert::<E>)
+                .boxed(),
+        }
+    }
+
+    /// Upsert an entity, with the sequence of the event being processed if
+    /// there is one.
+    ///
+    /// With duplicate suppression enabled, the entity is skipped if it's
+    /// identical to the cached one.
+    fn write<'a, E: Entity + PartialEq + 'a, R: Repository<E, T> + Sync>(
+        &'a self,
+        repo: &'a R,
+        entity: E,
+    ) -> UpdateFuture<'a, T> {
+        if self.suppress_duplicates && self.sequence.is_none() {
+            return Box::pin(async move {
+                if self.get(repo, entity.id()).await?.as_ref() == Some(&entity) {
+                    return Ok(());
+                }
+
+                self.store(repo, entity).await
+            });
+        }
+
+        self.store(repo, entity)
+    }
+
+    /// Upsert an entity without checking whether it's identical to the cached
+    /// one.
+    fn store<'a, E: Entity + 'a, R: Repository<E, T> + Sync>(
+        &'a self,
+        repo: &'a R,
+        entity: E,
+    ) -> UpdateFuture<'a, T> {
+        let sequence = self.sequence;
+
+        self.limited(move || match sequence {
+            Some(sequence) => repo.versioned_upsert(entity, sequence),
+            None => repo.upsert(entity),
+        })
+        .map_err(ProcessError::upsert::<E>)
+        .boxed()
+    }
+
+    /// Start a backend operation once the limit on concurrent backend
+    /// operations allows it, if there is one.
+    fn limited<'a>(
+        &'a self,
+        operation: impl FnOnce() -> UpsertEntityFuture<'a, T::Error> + Send + 'a,
+    ) -> UpsertEntityFuture<'a, T::Error> {
+        match self.backend_ops.as_deref() {
+            Some(limit) => Box::pin(async move {
+                let _permit = limit.acquire(1).await;
+
+                operation().await
+            }),
+            None => operation(),
+        }
+    }
+
+    /// Upsert the current user after passing it through the interceptor.
+    ///
+    /// With duplicate suppression enabled, the current user is skipped if
+    /// it's identical to the cached one.
+    fn upsert_current_user(&self, entity: CurrentUserEntity) -> UpdateFuture<'_, T> {
+        let Some(entity) = self.intercept(entity) else {
+            return future::ok(()).boxed();
+        };
+
+        let upsert = move |entity| {
+            self.limited(move || self.current_user.upsert(entity))
+                .map_err(ProcessError::upsert::<CurrentUserEntity>)
+        };
+
+        if !self.suppress_duplicates {
+            return upsert(entity).boxed();
+        }
+
+        Box::pin(async move {
+            let cached = self
+                .current_user
+                .get()
+                .await
+                .map_err(ProcessError::get::<CurrentUserEntity>)?;
+
+            if cached.as_ref() == Some(&entity) {
+                return Ok(());
+            }
+
+            upsert(entity).await
+        })
+    }
+
+    fn intercept<E: Intercept>(&self, entity: E) -> Option<E> {
+        match &self.interceptor {
+            Some(interceptor) => entity.intercept(interceptor.0.as_ref()),
+            None => Some(entity),
+        }
+    }
+
+    /// Return metrics about the events processed by the cache, by event type.
+    ///
+    /// Metrics are shared between clones of the cache. Refer to the
+    /// [`metrics`] module for more information.
+    ///
+    /// [`metrics`]: metrics/index.html
+    pub fn event_stats(&self) -> HashMap<EventType, EventStats> {
+        self.metrics.stats()
+    }
+
+    /// Return the number of entities and bytes compressed by the backend, if
+    /// it compresses entities.
+    ///
+    /// Refer to [`Backend::compression_stats`] for more information.
+    ///
+    /// [`Backend::compression_stats`]: trait.Backend.html#method.compression_stats
+    #[cfg(feature = "compression")]
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.backend.compression_stats()
+    }
+
+    /// Return the most recent session started by a `Ready` event for a shard.
+    ///
+    /// Connections that aren't sharded have a shard ID of 0. Sessions are
+    /// shared between clones of the cache. Refer to the [`session`] module
+    /// for more information.
+    ///
+    /// [`session`]: session/index.html
+    pub fn session(&self, shard_id: u64) -> Option<Session> {
+        self.sessions.get(shard_id)
+    }
+
+    /// Set a hook called when processing an event takes longer than a
+    /// threshold, replacing any previously set hook.
+    pub fn set_slow_event_hook(
+        &mut self,
+        threshold: Duration,
+        hook: impl Fn(EventType, Duration) + Send + Sync + 'static,
+    ) {
+        self.slow_event_hook = Some(SlowEventHook::new(threshold, hook));
+    }
+
+    /// Process events affecting the same entities one at a time, in the order
+    /// they're passed to [`process`], using the given number of queues.
+    ///
+    /// Clones of the cache made after calling this share the queues. Refer to
+    /// the [`ordering`] module for more information.
+    ///
+    /// [`ordering`]: ordering/index.html
+    /// [`process`]: #method.process
+    pub fn enable_ordering(&mut self, shards: usize) {
+        self.ordering = Some(Arc::new(OrderingLocks::new(shards)));
+    }
+
+    /// Limit the number of entities written to the backend at once across all
+    /// events being processed.
+    ///
+    /// Clones of the cache made after calling this share the limit. Refer to
+    /// the [`limit`] module for more information.
+    ///
+    /// [`limit`]: limit/index.html
+    pub fn set_max_concurrent_backend_ops(&mut self, max: usize) {
+        self.backend_ops = Some(Arc::new(BackendOpLimit::new(max)));
+    }
+
+    /// Catch panics while processing events and return them as errors instead
+    /// of unwinding through the caller.
+    ///
+    /// Backends, especially third-party ones, may panic on unexpected data.
+    /// With this enabled, a panic while processing an event is returned from
+    /// [`process`] as a [`ProcessError`] of type
+    /// [`ProcessErrorType::Panicked`], so that a gateway loop can log it and
+    /// move on to the next event.
+    ///
+    /// Entities written before the panic are kept, so the cache may be left
+    /// partially updated with the event.
+    ///
+    /// [`process`]: #method.process
+    /// [`ProcessError`]: cache/struct.ProcessError.html
+    /// [`ProcessErrorType::Panicked`]: cache/enum.ProcessErrorType.html#variant.Panicked
+    pub fn enable_panic_isolation(&mut self) {
+        self.isolate_panics = true;
+    }
+
+    /// Skip upserting entities identical to the cached ones while processing
+    /// events, saving writes to backends such as remote databases.
+    ///
+    /// Many events, such as periodic presence updates, re-send data that
+    /// hasn't changed. With this enabled, each entity is compared with the
+    /// cached entity before it's upserted, at the cost of an extra read, and
+    /// isn't written if they're identical. Change hooks aren't called for
+    /// skipped entities.
+    ///
+    /// Entities upserted with a sequence via [`process_sequenced`] are always
+    /// written, as skipping them would allow older writes to replace them.
+    ///
+    /// [`process_sequenced`]: #method.process_sequenced
+    pub fn enable_duplicate_suppression(&mut self) {
+        self.suppress_duplicates = true;
+    }
+
+    /// Skip caching ephemeral messages, such as responses to interactions
+    /// that only the invoking user can see.
+    ///
+    /// Ephemeral messages can't be retrieved or replied to by other users, so
+    /// bots handling many interactions would otherwise fill the bounded
+    /// message caches of channels with messages that are of no use to them.
+    pub fn enable_ephemeral_filtering(&mut self) {
+        self.filter_ephemeral = true;
+    }
+
+    /// Set the kinds of events that the cache processes.
+    ///
+    /// Bots that are only used in direct messages can skip processing the
+    /// events of guilds with [`CacheMode::DirectMessagesOnly`], and bots that
+    /// are only used in guilds can skip the events of private channels and
+    /// groups with [`CacheMode::GuildsOnly`]. Skipped events are ignored
+    /// entirely, without calling into the backend.
+    ///
+    /// Defaults to [`CacheMode::Full`].
+    ///
+    /// [`CacheMode::DirectMessagesOnly`]: cache/enum.CacheMode.html#variant.DirectMessagesOnly
+    /// [`CacheMode::Full`]: cache/enum.CacheMode.html#variant.Full
+    /// [`CacheMode::GuildsOnly`]: cache/enum.CacheMode.html#variant.GuildsOnly
+    pub fn set_mode(&mut self, mode: CacheMode) {
+        self.mode = mode;
+    }
+
+    /// Buffer member and channel events for guilds while they're unavailable
+    /// and process them once the guild is available again, keeping up to the
+    /// given number of events per guild.
+    ///
+    /// Clones of the cache made after calling this share the buffers. Refer
+    /// to the [`unavailable`] module for more information.
+    ///
+    /// [`unavailable`]: unavailable/index.html
+    pub fn enable_unavailable_buffering(&mut self, capacity: usize) {
+        self.unavailable = Some(Arc::new(UnavailableGuilds::new(capacity)));
+    }
+
+    /// Return whether the cache processes the events of a guild.
+    fn caches_guild(&self, guild_id: GuildId) -> bool {
+        self.mode != CacheMode::DirectMessagesOnly && self.backend.caches_guild(guild_id)
+    }
+
+    /// Return whether the cache processes an event, according to its mode
+    /// and the guilds cached by the backend.
+    fn routes(&self, event: &Event) -> bool {
+        match event_guild_id(event) {
+            Some(guild_id) => self.caches_guild(guild_id),
+            None => self.mode != CacheMode::GuildsOnly || !is_private_event(event),
+        }
+    }
+
+    /// Call the registered hooks with a change.
+    fn emit(&self, change: &CacheChange) {
+        for hook in &self.hooks {
+            hook.call(change);
+        }
+    }
+
+    /// Call the registered hooks if the owner of a guild changed.
+    fn emit_owner_change(&self, guild_id: GuildId, old: UserId, new: UserId) {
+        if old != new {
+            self.emit(&CacheChange::OwnerChanged { guild_id, old, new });
+        }
+    }
+
+    /// Call the registered hooks if the slowmode or topic of a text channel
+    /// changed.
+    fn emit_text_channel_changes(&self, old: TextChannelEntity, new: TextChannelEntity) {
+        if old.rate_limit_per_user != new.rate_limit_per_user {
+            self.emit(&CacheChange::SlowmodeChanged {
+                guild_id: new.guild_id,
+                channel_id: new.id,
+                old: old.rate_limit_per_user,
+                new: new.rate_limit_per_user,
+            });
+        }
+
+        if old.topic != new.topic {
+            self.emit(&CacheChange::TopicChanged {
+                guild_id: new.guild_id,
+                channel_id: new.id,
+                old: old.topic,
+                new: new.topic,
+            });
+        }
+    }
+
+    /// Update the cache with an event.
+    ///
+    /// # Examples
+    ///
+    /// Update the cache with a `RoleDelete` event, which will use the backend's
+    /// role repository to delete the role from the datastore:
+    ///
+    /// ```no_run
+    /// use twilight_cache::Cache;
+    /// use twilight_cache_inmemory::{InMemoryBackend, Repository};
+    /// use twilight_model::{
+    ///     gateway::{event::Event, payload::RoleDelete},
+    ///     id::{GuildId, RoleId},
+    /// };
+    ///
+    /// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let event = Event::RoleDelete(RoleDelete {
+    ///     guild_id: GuildId(123),
+    ///     role_id: RoleId(456),
+    /// });
+    ///
+    /// let cache: Cache<InMemoryBackend> = Cache::new();
+    ///
+    /// // And now update the cache with the event:
+    /// cache.process(&event).await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ProcessError`] with the backend's error if a backend
+    /// repository operation errors.
+    ///
+    /// [`ProcessError`]: cache/struct.ProcessError.html
+    pub fn process<'a>(&'a self, event: &'a Event) -> ProcessFuture<'a, T> {
+        self.process_event(event, None)
+    }
+
+    fn process_event<'a>(
+        &'a self,
+        event: &'a Event,
+        sequence: Option<u64>,
+    ) -> ProcessFuture<'a, T> {
+        let ticket = self
+            .ordering
+            .as_ref()
+            .zip(ordering::ordering_key(event))
+            .map(|(locks, key)| locks.ticket(key));
+
+        ProcessFuture {
+            inner: Box::pin(async move {
+                if !self.routes(event) {
+                    return Ok(());
+                }
+
+                if let Some(ticket) = ticket.as_ref() {
+                    ticket.wait().await;
+                }
+
+                if let Some(unavailable) = self.unavailable.as_ref() {
+                    if unavailable.buffer(event, sequence) {
+                        return Ok(());
+                    }
+                }
+
+                let started = Instant::now();
+                let event_type = event.kind();
+                let processing = async {
+                    let mut result = match sequence {
+                        Some(sequence) => event.process_sequenced(self, sequence).await,
+                        None => event.process(self).await,
+                    };
+
+                    if result.is_ok() {
+                        result = self.update_availability(event).await;
+                    }
+
+                    result
+                };
+
+                let result = if self.isolate_panics {
+                    AssertUnwindSafe(processing)
+                        .catch_unwind()
+                        .await
+                        .unwrap_or_else(|payload| Err(ProcessError::panicked(payload)))
+                } else {
+                    processing.await
+                };
+
+                let result = result.map_err(|error| error.during(event_type));
+                let duration = started.elapsed();
+
+                self.metrics.record(event_type, duration, result.is_err());
+
+                if let Some(hook) = self.slow_event_hook.as_ref() {
+                    hook.call(event_type, duration);
+                }
+
+                drop(ticket);
+
+                result
+            }),
+        }
+    }
+
+    /// Track the availability of guilds for buffering, processing the events
+    /// buffered for a guild once it's available again.
+    async fn update_availability(&self, event: &Event) -> Result<(), ProcessError<T::Error>> {
+        let Some(unavailable) = self.unavailable.as_ref() else {
+            return Ok(());
+        };
+
+        match event {
+            Event::GuildCreate(event) => {
+                for (event, sequence) in unavailable.take(event.id) {
+                    let result = match sequence {
+                        Some(sequence) => event.process_sequenced(self, sequence).await,
+                        None => event.process(self).await,
+                    };
+
+                    result.map_err(|error| error.during(event.kind()))?;
+                }
+            }
+            Event::GuildDelete(event) if event.unavailable => unavailable.mark(event.id),
+            Event::GuildDelete(event) => {
+                unavailable.take(event.id);
+            }
+            Event::Ready(event) => {
+                for status in &event.guilds {
+                    if let GuildStatus::Offline(guild) = status {
+                        unavailable.mark(guild.id);
+                    }
+                }
+            }
+            Event::UnavailableGuild(event) => unavailable.mark(event.id),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Remove the entities matching a pruner's retention rules.
+    ///
+    /// Refer to [`Pruner::prune`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Returns a backend error if a backend repository operation errors.
+    ///
+    /// [`Pruner::prune`]: prune/struct.Pruner.html#method.prune
+    pub async fn prune(&self, pruner: &Pruner) -> Result<PruneStats, T::Error> {
+        pruner.prune(self.backend.as_ref()).await
+    }
+
+    /// Retrieve an entity of a kind by its raw ID.
+    ///
+    /// Refer to [`any::get_any`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Returns a backend error if retrieving the entity fails.
+    ///
+    /// [`any::get_any`]: any/fn.get_any.html
+    pub async fn get_any(
+        &self,
+        kind: EntityKind,
+        raw_id: impl Into<RawId>,
+    ) -> Result<Option<AnyEntity>, T::Error> {
+        any::get_any(self.backend.as_ref(), kind, raw_id).await
+    }
+
+    /// Check the backend for relations pointing at entities that aren't
+    /// cached, removing them if `repair` is set.
+    ///
+    /// Refer to [`Backend::verify_integrity`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Returns a backend error if checking or repairing the backend errors.
+    ///
+    /// [`Backend::verify_integrity`]: trait.Backend.html#method.verify_integrity
+    pub async fn verify_integrity(&self, repair: bool) -> Result<IntegrityReport, T::Error> {
+        self.backend.verify_integrity(repair).await
+    }
+
+    /// Write the entities of the selected types in the cache to a writer as
+    /// newline-delimited JSON, returning the number of entities written.
+    ///
+    /// Refer to [`export::export_ndjson`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if listing, serializing, or writing an entity fails.
+    ///
+    /// [`export::export_ndjson`]: export/fn.export_ndjson.html
+    #[cfg(feature = "export")]
+    pub async fn export_ndjson(
+        &self,
+        types: &[ExportType],
+        writer: impl Write,
+    ) -> Result<u64, ExportError<T::Error>> {
+        export::export_ndjson(self.backend.as_ref(), types, writer).await
+    }
+
+    /// Write the entities of the selected types in the cache to a file as
+    /// newline-delimited JSON, returning the number of entities written.
+    ///
+    /// Refer to [`export::export_ndjson_file`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if creating the file fails or if listing,
+    /// serializing, or writing an entity fails.
+    ///
+    /// [`export::export_ndjson_file`]: export/fn.export_ndjson_file.html
+    #[cfg(feature = "export")]
+    pub async fn export_ndjson_file(
+        &self,
+        types: &[ExportType],
+        path: impl AsRef<Path>,
+    ) -> Result<u64, ExportError<T::Error>> {
+        export::export_ndjson_file(self.backend.as_ref(), types, path).await
+    }
+
+    /// Check that the backend can reach its datastore.
+    ///
+    /// Refer to [`Backend::health`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Returns the backend error that prevents it from reaching its
+    /// datastore.
+    ///
+    /// [`Backend::health`]: trait.Backend.html#method.health
+    pub async fn health(&self) -> Result<(), T::Error> {
+        self.backend.health().await
     }
 
-    /// Update the cache with an event.
+    /// Shut down the cache, flushing the backend's pending writes and then
+    /// closing it.
     ///
-    /// # Examples
+    /// Call this before the process exits so that backends buffering writes
+    /// don't lose them. Refer to [`Backend::flush`] and [`Backend::close`]
+    /// for more information.
     ///
-    /// Update the cache with a `RoleDelete` event, which will use the backend's
-    /// role repository to delete the role from the datastore:
+    /// # Errors
     ///
-    /// ```no_run
-    /// use twilight_cache::Cache;
-    /// use twilight_cache_inmemory::{InMemoryBackend, Repository};
-    /// use twilight_model::{
-    ///     gateway::{event::Event, payload::RoleDelete},
-    ///     id::{GuildId, RoleId},
-    /// };
+    /// Returns a backend error if flushing or closing the backend errors.
     ///
-    /// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let event = Event::RoleDelete(RoleDelete {
-    ///     guild_id: GuildId(123),
-    ///     role_id: RoleId(456),
-    /// });
+    /// [`Backend::close`]: trait.Backend.html#method.close
+    /// [`Backend::flush`]: trait.Backend.html#method.flush
+    pub async fn shutdown(&self) -> Result<(), T::Error> {
+        self.backend.flush().await?;
+
+        self.backend.close().await
+    }
+
+    /// Reconcile the cache with a full copy of a guild, such as one fetched
+    /// over HTTP, to repair drift caused by missed events.
     ///
-    /// let cache: Cache<InMemoryBackend> = Cache::new();
+    /// Everything in the guild is upserted and cached channels, emojis, and
+    /// roles of the guild that aren't in it are removed.
     ///
-    /// // And now update the cache with the event:
-    /// cache.process(&event).await?;
-    /// # Ok(()) }
-    /// ```
+    /// Guilds fetched over HTTP don't contain members, presences, or voice
+    /// states. These are only reconciled if the guild contains members, as a
+    /// guild always has at least one member.
     ///
     /// # Errors
     ///
-    /// Returns a backend error if a backend repository operation errors.
-    pub fn process<'a>(&'a self, event: &'a Event) -> ProcessFuture<'a, T> {
+    /// Returns a [`ProcessError`] with the backend's error if a backend
+    /// repository operation errors.
+    ///
+    /// [`ProcessError`]: cache/struct.ProcessError.html
+    pub fn reconcile_guild<'a>(
+        &'a self,
+        guild_id: GuildId,
+        guild: &'a Guild,
+    ) -> ProcessFuture<'a, T> {
         ProcessFuture {
-            inner: event.process(self),
+            inner: Box::pin(async move {
+                let futures = FuturesUnordered::new();
+
+                let channel_ids = guild
+                    .channels
+                    .iter()
+                    .map(GuildChannel::id)
+                    .collect::<HashSet<_>>();
+                let mut channels = self
+                    .guilds
+                    .channels(guild_id)
+                    .await
+                    .map_err(ProcessError::list::<GuildEntity>)?;
+
+                while let Some(channel) = channels.next().await {
+                    match channel.map_err(ProcessError::list::<GuildEntity>)? {
+                        GuildChannelEntity::Category(c) if !channel_ids.contains(&c.id) => {
+                            futures.push(self.remove(&self.category_channels, c.id));
+                        }
+                        GuildChannelEntity::Text(c) if !channel_ids.contains(&c.id) => {
+                            futures.push(self.remove(&self.text_channels, c.id));
+                        }
+                        GuildChannelEntity::Voice(c) if !channel_ids.contains(&c.id) => {
+                            futures.push(self.remove(&self.voice_channels, c.id));
+                        }
+                        _ => {}
+                    }
+                }
+
+                let emoji_ids = guild.emojis.iter().map(|e| e.id).collect::<HashSet<_>>();
+                let mut emojis = self
+                    .guilds
+                    .emoji_ids(guild_id)
+                    .await
+                    .map_err(ProcessError::list::<GuildEntity>)?;
+
+                while let Some(id) = emojis.next().await {
+                    let id = id.map_err(ProcessError::list::<GuildEntity>)?;
+
+                    if !emoji_ids.contains(&id) {
+                        futures.push(self.remove(&self.emojis, id));
+                    }
+                }
+
+                let role_ids = guild.roles.iter().map(|r| r.id).collect::<HashSet<_>>();
+                let mut roles = self
+                    .guilds
+                    .role_ids(guild_id)
+                    .await
+                    .map_err(ProcessError::list::<GuildEntity>)?;
+
+                while let Some(id) = roles.next().await {
+                    let id = id.map_err(ProcessError::list::<GuildEntity>)?;
+
+                    if !role_ids.contains(&id) {
+                        futures.push(self.remove(&self.roles, id));
+                    }
+                }
+
+                if !guild.members.is_empty() {
+                    let user_ids = guild
+                        .members
+                        .iter()
+                        .map(|m| m.user.id)
+                        .collect::<HashSet<_>>();
+                    let mut members = self
+                        .guilds
+                        .member_ids(guild_id)
+                        .await
+                        .map_err(ProcessError::list::<GuildEntity>)?;
+
+                    while let Some(id) = members.next().await {
+                        let id = id.map_err(ProcessError::list::<GuildEntity>)?;
+
+                        if !user_ids.contains(&id) {
+                            futures.push(self.remove(&self.members, (guild_id, id)));
+                        }
+                    }
+
+                    let user_ids = guild
+                        .presences
+                        .iter()
+                        .map(|p| match &p.user {
+                            UserOrId::User(user) => user.id,
+                            UserOrId::UserId { id } => *id,
+                        })
+                        .collect::<HashSet<_>>();
+                    let mut presences = self
+                        .guilds
+                        .presence_ids(guild_id)
+                        .await
+                        .map_err(ProcessError::list::<GuildEntity>)?;
+
+                    while let Some(id) = presences.next().await {
+                        let id = id.map_err(ProcessError::list::<GuildEntity>)?;
+
+                        if !user_ids.contains(&id) {
+                            futures.push(self.remove(&self.presences, (guild_id, id)));
+                        }
+                    }
+
+                    let user_ids = guild
+                        .voice_states
+                        .iter()
+                        .map(|v| v.user_id)
+                        .collect::<HashSet<_>>();
+                    let mut voice_states = self
+                        .guilds
+                        .voice_state_ids(guild_id)
+                        .await
+                        .map_err(ProcessError::list::<GuildEntity>)?;
+
+                    while let Some(id) = voice_states.next().await {
+                        let id = id.map_err(ProcessError::list::<GuildEntity>)?;
+
+                        if !user_ids.contains(&id) {
+                            futures.push(self.remove(&self.voice_states, (guild_id, id)));
+                        }
+                    }
+                }
+
+                futures.try_collect::<()>().await?;
+
+                let event = GuildCreate(Guild {
+                    id: guild_id,
+                    ..guild.clone()
+                });
+
+                event.process(self).await
+            }),
         }
     }
 }
@@ -192,7 +1428,7 @@ impl<T: Backend> CacheUpdate<T> for Event {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         match self {
             Event::BanAdd(_) => noop::<T>(),
             Event::BanRemove(_) => noop::<T>(),
@@ -205,6 +1441,7 @@ impl<T: Backend> CacheUpdate<T> for Event {
             Event::GuildEmojisUpdate(event) => event.process(cache),
             Event::GuildIntegrationsUpdate(_) => noop::<T>(),
             Event::GuildUpdate(event) => event.process(cache),
+            Event::InteractionCreate(event) => event.process(cache),
             Event::InviteCreate(_) => noop::<T>(),
             Event::InviteDelete(_) => noop::<T>(),
             Event::MemberAdd(event) => event.process(cache),
@@ -216,6 +1453,9 @@ impl<T: Backend> CacheUpdate<T> for Event {
             Event::MessageDeleteBulk(event) => event.process(cache),
             Event::MessageUpdate(event) => event.process(cache),
             Event::PresenceUpdate(event) => event.process(cache),
+            #[cfg(feature = "emoji-usage")]
+            Event::ReactionAdd(event) => event.process(cache),
+            #[cfg(not(feature = "emoji-usage"))]
             Event::ReactionAdd(_) => noop::<T>(),
             Event::ReactionRemove(_) => noop::<T>(),
             Event::ReactionRemoveAll(_) => noop::<T>(),
@@ -225,7 +1465,7 @@ impl<T: Backend> CacheUpdate<T> for Event {
             Event::RoleDelete(event) => event.process(cache),
             Event::RoleUpdate(event) => event.process(cache),
             Event::TypingStart(_) => noop::<T>(),
-            Event::UnavailableGuild(_) => noop::<T>(),
+            Event::UnavailableGuild(event) => event.process(cache),
             Event::UserUpdate(event) => event.process(cache),
             Event::VoiceServerUpdate(_) => noop::<T>(),
             Event::VoiceStateUpdate(event) => event.process(cache),
@@ -256,48 +1496,46 @@ impl<T: Backend> CacheUpdate<T> for ChannelCreate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         match &self.0 {
             Channel::Group(group) => {
                 let futures = FuturesUnordered::new();
 
-                futures.push(
-                    cache
-                        .users
-                        .upsert_bulk(group.recipients.iter().cloned().map(UserEntity::from)),
-                );
+                futures.push(cache.upsert_bulk(
+                    &cache.users,
+                    group.recipients.iter().cloned().map(UserEntity::from),
+                ));
 
                 let entity = GroupEntity::from(group.clone());
-                futures.push(cache.groups.upsert(entity));
+                futures.push(cache.upsert(&cache.groups, entity));
 
                 futures.try_collect().boxed()
             }
             Channel::Guild(GuildChannel::Category(c)) => {
                 let entity = CategoryChannelEntity::from(c.clone());
 
-                cache.category_channels.upsert(entity)
+                cache.upsert(&cache.category_channels, entity)
             }
             Channel::Guild(GuildChannel::Text(c)) => {
                 let entity = TextChannelEntity::from(c.clone());
 
-                cache.text_channels.upsert(entity)
+                cache.upsert(&cache.text_channels, entity)
             }
-            Channel::Guild(GuildChannel::Voice(c)) => {
+            Channel::Guild(GuildChannel::Voice(c) | GuildChannel::Stage(c)) => {
                 let entity = VoiceChannelEntity::from(c.clone());
 
-                cache.voice_channels.upsert(entity)
+                cache.upsert(&cache.voice_channels, entity)
             }
             Channel::Private(c) => {
                 let futures = FuturesUnordered::new();
 
-                futures.push(
-                    cache
-                        .users
-                        .upsert_bulk(c.recipients.iter().cloned().map(UserEntity::from)),
-                );
+                futures.push(cache.upsert_bulk(
+                    &cache.users,
+                    c.recipients.iter().cloned().map(UserEntity::from),
+                ));
 
                 let entity = PrivateChannelEntity::from(c.clone());
-                futures.push(cache.private_channels.upsert(entity));
+                futures.push(cache.upsert(&cache.private_channels, entity));
 
                 futures.try_collect().boxed()
             }
@@ -309,13 +1547,17 @@ impl<T: Backend> CacheUpdate<T> for ChannelDelete {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         match &self.0 {
-            Channel::Group(group) => cache.groups.remove(group.id),
-            Channel::Guild(GuildChannel::Category(c)) => cache.category_channels.remove(c.id),
-            Channel::Guild(GuildChannel::Text(c)) => cache.text_channels.remove(c.id),
-            Channel::Guild(GuildChannel::Voice(c)) => cache.voice_channels.remove(c.id),
-            Channel::Private(c) => cache.private_channels.remove(c.id),
+            Channel::Group(group) => cache.remove(&cache.groups, group.id),
+            Channel::Guild(GuildChannel::Category(c)) => {
+                cache.remove(&cache.category_channels, c.id)
+            }
+            Channel::Guild(GuildChannel::Text(c)) => cache.remove(&cache.text_channels, c.id),
+            Channel::Guild(GuildChannel::Voice(c) | GuildChannel::Stage(c)) => {
+                cache.remove(&cache.voice_channels, c.id)
+            }
+            Channel::Private(c) => cache.remove(&cache.private_channels, c.id),
         }
     }
 }
@@ -324,35 +1566,43 @@ impl<T: Backend> CacheUpdate<T> for ChannelPinsUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         Box::pin(async move {
-            if let Some(group) = cache.groups.get(self.channel_id).await? {
+            if let Some(group) = cache.get(&cache.groups, self.channel_id).await? {
                 return cache
-                    .groups
-                    .upsert(GroupEntity {
-                        last_pin_timestamp: self.last_pin_timestamp.clone(),
-                        ..group
-                    })
+                    .upsert(
+                        &cache.groups,
+                        GroupEntity {
+                            last_pin_timestamp: self.last_pin_timestamp.clone(),
+                            ..group
+                        },
+                    )
                     .await;
             }
 
-            if let Some(text_channel) = cache.text_channels.get(self.channel_id).await? {
+            if let Some(text_channel) = cache.get(&cache.text_channels, self.channel_id).await? {
                 return cache
-                    .text_channels
-                    .upsert(TextChannelEntity {
-                        last_pin_timestamp: self.last_pin_timestamp.clone(),
-                        ..text_channel
-                    })
+                    .upsert(
+                        &cache.text_channels,
+                        TextChannelEntity {
+                            last_pin_timestamp: self.last_pin_timestamp.clone(),
+                            ..text_channel
+                        },
+                    )
                     .await;
             }
 
-            if let Some(private_channel) = cache.private_channels.get(self.channel_id).await? {
+            if let Some(private_channel) =
+                cache.get(&cache.private_channels, self.channel_id).await?
+            {
                 return cache
-                    .private_channels
-                    .upsert(PrivateChannelEntity {
-                        last_pin_timestamp: self.last_pin_timestamp.clone(),
-                        ..private_channel
-                    })
+                    .upsert(
+                        &cache.private_channels,
+                        PrivateChannelEntity {
+                            last_pin_timestamp: self.last_pin_timestamp.clone(),
+                            ..private_channel
+                        },
+                    )
                     .await;
             }
 
@@ -365,48 +1615,64 @@ impl<T: Backend> CacheUpdate<T> for ChannelUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         match &self.0 {
             Channel::Group(group) => {
                 let futures = FuturesUnordered::new();
 
-                futures.push(
-                    cache
-                        .users
-                        .upsert_bulk(group.recipients.iter().cloned().map(UserEntity::from)),
-                );
+                futures.push(cache.upsert_bulk(
+                    &cache.users,
+                    group.recipients.iter().cloned().map(UserEntity::from),
+                ));
 
                 let entity = GroupEntity::from(group.clone());
-                futures.push(cache.groups.upsert(entity));
+                futures.push(cache.upsert(&cache.groups, entity));
 
                 futures.try_collect().boxed()
             }
             Channel::Guild(GuildChannel::Category(c)) => {
                 let entity = CategoryChannelEntity::from(c.clone());
 
-                cache.category_channels.upsert(entity)
+                cache.upsert(&cache.category_channels, entity)
             }
             Channel::Guild(GuildChannel::Text(c)) => {
                 let entity = TextChannelEntity::from(c.clone());
 
-                cache.text_channels.upsert(entity)
+                if cache.hooks.is_empty() {
+                    return cache.upsert(&cache.text_channels, entity);
+                }
+
+                Box::pin(async move {
+                    let previous = cache.get(&cache.text_channels, entity.id).await?;
+
+                    let Some(entity) = cache.intercept(entity) else {
+                        return Ok(());
+                    };
+
+                    cache.write(&cache.text_channels, entity.clone()).await?;
+
+                    if let Some(previous) = previous {
+                        cache.emit_text_channel_changes(previous, entity);
+                    }
+
+                    Ok(())
+                })
             }
-            Channel::Guild(GuildChannel::Voice(c)) => {
+            Channel::Guild(GuildChannel::Voice(c) | GuildChannel::Stage(c)) => {
                 let entity = VoiceChannelEntity::from(c.clone());
 
-                cache.voice_channels.upsert(entity)
+                cache.upsert(&cache.voice_channels, entity)
             }
             Channel::Private(c) => {
                 let futures = FuturesUnordered::new();
 
-                futures.push(
-                    cache
-                        .users
-                        .upsert_bulk(c.recipients.iter().cloned().map(UserEntity::from)),
-                );
+                futures.push(cache.upsert_bulk(
+                    &cache.users,
+                    c.recipients.iter().cloned().map(UserEntity::from),
+                ));
 
                 let entity = PrivateChannelEntity::from(c.clone());
-                futures.push(cache.private_channels.upsert(entity));
+                futures.push(cache.upsert(&cache.private_channels, entity));
 
                 futures.try_collect().boxed()
             }
@@ -418,43 +1684,44 @@ impl<T: Backend> CacheUpdate<T> for GuildCreate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         let futures = FuturesUnordered::new();
 
-        for channel in self.channels.iter() {
+        for channel in &self.channels {
             match channel {
                 GuildChannel::Category(c) => {
                     let entity = CategoryChannelEntity::from(c.clone());
-                    futures.push(cache.category_channels.upsert(entity));
+                    futures.push(cache.upsert(&cache.category_channels, entity));
                 }
                 GuildChannel::Text(c) => {
                     let entity = TextChannelEntity::from(c.clone());
-                    futures.push(cache.text_channels.upsert(entity));
+                    futures.push(cache.upsert(&cache.text_channels, entity));
                 }
-                GuildChannel::Voice(c) => {
+                GuildChannel::Voice(c) | GuildChannel::Stage(c) => {
                     let entity = VoiceChannelEntity::from(c.clone());
-                    futures.push(cache.voice_channels.upsert(entity));
+                    futures.push(cache.upsert(&cache.voice_channels, entity));
                 }
             }
         }
 
         futures.push(
-            cache.emojis.upsert_bulk(
+            cache.upsert_bulk(
+                &cache.emojis,
                 self.emojis
                     .iter()
                     .cloned()
-                    .map(|e| EmojiEntity::from((self.id, e))),
+                    .map(move |e| EmojiEntity::from((self.id, e))),
             ),
         );
 
-        futures.push(
-            cache
-                .members
-                .upsert_bulk(self.members.iter().cloned().map(MemberEntity::from)),
-        );
+        futures.push(cache.upsert_bulk(
+            &cache.members,
+            self.members.iter().cloned().map(MemberEntity::from),
+        ));
 
         futures.push(
-            cache.users.upsert_bulk(
+            cache.upsert_bulk(
+                &cache.users,
                 self.members
                     .iter()
                     .cloned()
@@ -462,103 +1729,199 @@ impl<T: Backend> CacheUpdate<T> for GuildCreate {
             ),
         );
 
-        futures.push(
-            cache
-                .presences
-                .upsert_bulk(self.presences.iter().cloned().map(PresenceEntity::from)),
-        );
+        futures.push(cache.upsert_bulk(
+            &cache.presences,
+            self.presences.iter().cloned().map(PresenceEntity::from),
+        ));
 
         futures.push(
-            cache.roles.upsert_bulk(
+            cache.upsert_bulk(
+                &cache.roles,
                 self.roles
                     .iter()
                     .cloned()
-                    .map(|r| RoleEntity::from((r, self.id))),
+                    .map(move |r| RoleEntity::from((r, self.id))),
             ),
         );
 
         futures.push(
-            cache.voice_states.upsert_bulk(
+            cache.upsert_bulk(
+                &cache.voice_states,
                 self.voice_states
                     .iter()
                     .cloned()
-                    .map(|v| VoiceStateEntity::from((v, self.id))),
+                    .map(move |v| VoiceStateEntity::from((v, self.id))),
             ),
         );
 
-        let entity = GuildEntity::from(self.0.clone());
-        futures.push(cache.guilds.upsert(entity));
+        futures.push(Box::pin(async move {
+            let mut entity = GuildEntity::from(self.0.clone());
+            let previous = cache
+                .get(&cache.guilds, self.id)
+                .await?
+                .filter(|guild| !guild.is_skeleton());
+
+            if let Some(previous) = previous.as_ref() {
+                entity.inherit_owner_history(previous);
+            }
+
+            let Some(entity) = cache.intercept(entity) else {
+                return Ok(());
+            };
+
+            let owner_id = entity.owner_id;
+            cache.write(&cache.guilds, entity).await?;
+
+            if let Some(previous) = previous {
+                cache.emit_owner_change(self.id, previous.owner_id, owner_id);
+            }
+
+            Ok(())
+        }));
 
         futures.try_collect().boxed()
     }
 }
 
+/// Mark a cached guild as unavailable.
+fn mark_unavailable<T: Backend>(
+    cache: &Cache<T>,
+    guild_id: GuildId,
+) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + '_>> {
+    cache
+        .get(&cache.guilds, guild_id)
+        .and_then(move |guild| {
+            guild.map_or_else(
+                || future::ok(()).boxed(),
+                |guild| {
+                    let entity = GuildEntity {
+                        unavailable: true,
+                        ..guild
+                    };
+
+                    cache.upsert(&cache.guilds, entity)
+                },
+            )
+        })
+        .boxed()
+}
+
+/// Refresh the name of a cached guild channel, if any.
+fn rename_channel<'a, T: Backend>(
+    cache: &'a Cache<T>,
+    channel_id: ChannelId,
+    name: &'a str,
+) -> UpdateFuture<'a, T> {
+    Box::pin(async move {
+        if let Some(channel) = cache.get(&cache.text_channels, channel_id).await? {
+            let entity = TextChannelEntity {
+                name: name.to_owned(),
+                ..channel
+            };
+
+            return cache.upsert(&cache.text_channels, entity).await;
+        }
+
+        if let Some(channel) = cache.get(&cache.voice_channels, channel_id).await? {
+            let entity = VoiceChannelEntity {
+                name: name.to_owned(),
+                ..channel
+            };
+
+            return cache.upsert(&cache.voice_channels, entity).await;
+        }
+
+        if let Some(channel) = cache.get(&cache.category_channels, channel_id).await? {
+            let entity = CategoryChannelEntity {
+                name: name.to_owned(),
+                ..channel
+            };
+
+            return cache.upsert(&cache.category_channels, entity).await;
+        }
+
+        Ok(())
+    })
+}
+
 impl<T: Backend> CacheUpdate<T> for GuildDelete {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         if self.unavailable {
-            return cache
-                .guilds
-                .get(self.id)
-                .and_then(move |guild| {
-                    guild.map_or_else(
-                        || future::ok(()).boxed(),
-                        |guild| {
-                            let entity = GuildEntity {
-                                unavailable: self.unavailable,
-                                ..guild
-                            };
-
-                            cache.guilds.upsert(entity)
-                        },
-                    )
-                })
-                .boxed();
+            return mark_unavailable(cache, self.id);
         }
 
         Box::pin(async move {
             let futures = FuturesUnordered::new();
 
-            let mut channels = cache.guilds.channels(self.id).await?;
+            let mut channels = cache
+                .guilds
+                .channels(self.id)
+                .await
+                .map_err(ProcessError::list::<GuildEntity>)?;
             while let Some(Ok(c)) = channels.next().await {
                 match c {
                     GuildChannelEntity::Category(c) => {
-                        futures.push(cache.category_channels.remove(c.id));
+                        futures.push(cache.remove(&cache.category_channels, c.id));
+                    }
+                    GuildChannelEntity::Text(c) => {
+                        futures.push(cache.remove(&cache.text_channels, c.id));
+                    }
+                    GuildChannelEntity::Voice(c) => {
+                        futures.push(cache.remove(&cache.voice_channels, c.id));
                     }
-                    GuildChannelEntity::Text(c) => futures.push(cache.text_channels.remove(c.id)),
-                    GuildChannelEntity::Voice(c) => futures.push(cache.voice_channels.remove(c.id)),
                 }
             }
 
-            let mut emojis = cache.guilds.emoji_ids(self.id).await?;
+            let mut emojis = cache
+                .guilds
+                .emoji_ids(self.id)
+                .await
+                .map_err(ProcessError::list::<GuildEntity>)?;
             while let Some(Ok(id)) = emojis.next().await {
-                futures.push(cache.emojis.remove(id));
+                futures.push(cache.remove(&cache.emojis, id));
             }
 
-            let mut members = cache.guilds.member_ids(self.id).await?;
+            let mut members = cache
+                .guilds
+                .member_ids(self.id)
+                .await
+                .map_err(ProcessError::list::<GuildEntity>)?;
             while let Some(Ok(id)) = members.next().await {
-                futures.push(cache.members.remove((self.id, id)));
+                futures.push(cache.remove(&cache.members, (self.id, id)));
             }
 
-            let mut presences = cache.guilds.presence_ids(self.id).await?;
+            let mut presences = cache
+                .guilds
+                .presence_ids(self.id)
+                .await
+                .map_err(ProcessError::list::<GuildEntity>)?;
             while let Some(Ok(id)) = presences.next().await {
-                futures.push(cache.presences.remove((self.id, id)))
+                futures.push(cache.remove(&cache.presences, (self.id, id)));
             }
 
-            let mut roles = cache.guilds.role_ids(self.id).await?;
+            let mut roles = cache
+                .guilds
+                .role_ids(self.id)
+                .await
+                .map_err(ProcessError::list::<GuildEntity>)?;
             while let Some(Ok(id)) = roles.next().await {
-                futures.push(cache.roles.remove(id))
+                futures.push(cache.remove(&cache.roles, id));
             }
 
-            let mut voice_states = cache.guilds.voice_state_ids(self.id).await?;
+            let mut voice_states = cache
+                .guilds
+                .voice_state_ids(self.id)
+                .await
+                .map_err(ProcessError::list::<GuildEntity>)?;
             while let Some(Ok(id)) = voice_states.next().await {
-                futures.push(cache.voice_states.remove((self.id, id)))
+                futures.push(cache.remove(&cache.voice_states, (self.id, id)));
             }
 
             futures.try_collect::<()>().await?;
-            cache.guilds.remove(self.id).await
+            cache.remove(&cache.guilds, self.id).await
         })
     }
 }
@@ -567,12 +1930,13 @@ impl<T: Backend> CacheUpdate<T> for GuildEmojisUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
-        cache.emojis.upsert_bulk(
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        cache.upsert_bulk(
+            &cache.emojis,
             self.emojis
                 .iter()
                 .cloned()
-                .map(|e| EmojiEntity::from((self.guild_id, e))),
+                .map(move |e| EmojiEntity::from((self.guild_id, e))),
         )
     }
 }
@@ -581,17 +1945,107 @@ impl<T: Backend> CacheUpdate<T> for GuildUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
-        cache
-            .guilds
-            .get(self.id)
-            .and_then(move |guild| {
-                guild.map_or_else(
-                    || future::ok(()).boxed(),
-                    |guild| cache.guilds.upsert(guild.update(self.0.clone())),
-                )
-            })
-            .boxed()
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        Box::pin(async move {
+            let Some(guild) = cache
+                .get(&cache.guilds, self.id)
+                .await?
+                .filter(|guild| !guild.is_skeleton())
+            else {
+                return Ok(());
+            };
+
+            let previous_owner_id = guild.owner_id;
+            let Some(entity) = cache.intercept(guild.update(self.0.clone())) else {
+                return Ok(());
+            };
+
+            let owner_id = entity.owner_id;
+            cache.write(&cache.guilds, entity).await?;
+            cache.emit_owner_change(self.id, previous_owner_id, owner_id);
+
+            Ok(())
+        })
+    }
+}
+
+impl<T: Backend> CacheUpdate<T> for InteractionCreate {
+    fn process<'a>(
+        &'a self,
+        cache: &'a Cache<T>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        // Pings carry no user or guild data.
+        let Interaction::ApplicationCommand(command) = &self.0 else {
+            return noop::<T>();
+        };
+
+        Box::pin(async move {
+            let futures = FuturesUnordered::new();
+
+            // Commands invoked in guilds only include the user in the member.
+            let user = command.user.as_ref().or_else(|| {
+                command
+                    .member
+                    .as_ref()
+                    .and_then(|member| member.user.as_ref())
+            });
+
+            if let Some(user) = user {
+                let entity = UserEntity::from(user.clone());
+                futures.push(cache.upsert_partial(&cache.users, entity));
+
+                if let (Some(guild_id), Some(member)) = (command.guild_id, command.member.clone()) {
+                    let entity = MemberEntity::from((guild_id, user.id, member));
+                    futures.push(cache.upsert_partial(&cache.members, entity));
+                }
+            }
+
+            if let Some(resolved) = &command.data.resolved {
+                for user in &resolved.users {
+                    let entity = UserEntity::from(user.clone());
+                    futures.push(cache.upsert_partial(&cache.users, entity));
+                }
+
+                if let Some(guild_id) = command.guild_id {
+                    // Resolved members don't say whether they're deafened or
+                    // muted, and may omit when they joined or started
+                    // boosting, so keep what's cached.
+                    for member in &resolved.members {
+                        futures.push(Box::pin(async move {
+                            let cached = cache.get(&cache.members, (guild_id, member.id)).await?;
+                            let entity = MemberEntity::from((guild_id, member.clone()));
+
+                            let entity = match cached {
+                                Some(cached) => MemberEntity {
+                                    deaf: cached.deaf,
+                                    joined_at: entity.joined_at.or(cached.joined_at),
+                                    mute: cached.mute,
+                                    pending: cached.pending,
+                                    premium_since: entity.premium_since.or(cached.premium_since),
+                                    ..entity
+                                },
+                                None => entity,
+                            };
+
+                            cache.upsert(&cache.members, entity).await
+                        }));
+                    }
+
+                    for role in &resolved.roles {
+                        let entity = RoleEntity::from((role.clone(), guild_id));
+                        futures.push(cache.upsert(&cache.roles, entity));
+                    }
+                }
+
+                // Resolved channels are partial, so only refresh the names
+                // of channels that are already cached.
+                for channel in &resolved.channels {
+                    futures.push(rename_channel(cache, channel.id, &channel.name));
+                }
+            }
+
+            futures.try_collect().await
+        })
     }
 }
 
@@ -599,14 +2053,14 @@ impl<T: Backend> CacheUpdate<T> for MemberAdd {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         let futures = FuturesUnordered::new();
 
         let user_entity = UserEntity::from(self.user.clone());
-        futures.push(cache.users.upsert(user_entity));
+        futures.push(cache.upsert(&cache.users, user_entity));
 
         let member_entity = MemberEntity::from(self.0.clone());
-        futures.push(cache.members.upsert(member_entity));
+        futures.push(cache.upsert(&cache.members, member_entity));
 
         futures.try_collect().boxed()
     }
@@ -616,8 +2070,8 @@ impl<T: Backend> CacheUpdate<T> for MemberRemove {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
-        cache.members.remove((self.guild_id, self.user.id))
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        cache.remove(&cache.members, (self.guild_id, self.user.id))
     }
 }
 
@@ -625,26 +2079,28 @@ impl<T: Backend> CacheUpdate<T> for MemberUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
-        cache
-            .members
-            .get((self.guild_id, self.user.id))
-            .and_then(move |member| {
-                member.map_or_else(
-                    || future::ok(()).boxed(),
-                    |member| {
-                        let futures = FuturesUnordered::new();
-
-                        let user_entity = UserEntity::from(self.user.clone());
-                        futures.push(cache.users.upsert(user_entity));
-
-                        futures.push(cache.members.upsert(member.update(self.clone())));
-
-                        futures.try_collect().boxed()
-                    },
-                )
-            })
-            .boxed()
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        Box::pin(async move {
+            let futures = FuturesUnordered::new();
+
+            let user_entity = UserEntity::from(self.user.clone());
+            futures.push(cache.upsert_partial(&cache.users, user_entity));
+
+            // The member may not be cached yet, such as when a shard starts
+            // receiving events for a guild after its member list was sent, so
+            // build a fresh entity from the update payload instead of dropping
+            // it.
+            let member_entity = match cache
+                .get(&cache.members, (self.guild_id, self.user.id))
+                .await?
+            {
+                Some(member) => member.update(self.clone()),
+                None => MemberEntity::from(self.clone()),
+            };
+            futures.push(cache.upsert(&cache.members, member_entity));
+
+            futures.try_collect().await
+        })
     }
 }
 
@@ -652,17 +2108,17 @@ impl<T: Backend> CacheUpdate<T> for MemberChunk {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         let futures = FuturesUnordered::new();
 
-        futures.push(
-            cache
-                .members
-                .upsert_bulk(self.members.iter().cloned().map(MemberEntity::from)),
-        );
+        futures.push(cache.upsert_bulk(
+            &cache.members,
+            self.members.iter().cloned().map(MemberEntity::from),
+        ));
 
         futures.push(
-            cache.users.upsert_bulk(
+            cache.upsert_bulk(
+                &cache.users,
                 self.members
                     .iter()
                     .cloned()
@@ -670,11 +2126,10 @@ impl<T: Backend> CacheUpdate<T> for MemberChunk {
             ),
         );
 
-        futures.push(
-            cache
-                .presences
-                .upsert_bulk(self.presences.iter().cloned().map(PresenceEntity::from)),
-        );
+        futures.push(cache.upsert_bulk(
+            &cache.presences,
+            self.presences.iter().cloned().map(PresenceEntity::from),
+        ));
 
         futures.try_collect().boxed()
     }
@@ -684,59 +2139,146 @@ impl<T: Backend> CacheUpdate<T> for MessageCreate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         Box::pin(async move {
+            let ephemeral = self
+                .flags
+                .is_some_and(|flags| flags.contains(MessageFlags::EPHEMERAL));
+
+            if cache.filter_ephemeral && ephemeral {
+                return Ok(());
+            }
+
             let futures = FuturesUnordered::new();
 
-            if let Some(group) = cache.groups.get(self.channel_id).await? {
-                futures.push(cache.groups.upsert(GroupEntity {
-                    last_message_id: Some(self.id),
-                    ..group
-                }));
+            if let Some(group) = cache.get(&cache.groups, self.channel_id).await? {
+                futures.push(cache.upsert(
+                    &cache.groups,
+                    GroupEntity {
+                        last_message_id: Some(self.id),
+                        ..group
+                    },
+                ));
             }
 
-            if let Some(text_channel) = cache.text_channels.get(self.channel_id).await? {
-                futures.push(cache.text_channels.upsert(TextChannelEntity {
-                    last_message_id: Some(self.id),
-                    ..text_channel
-                }));
+            if let Some(text_channel) = cache.get(&cache.text_channels, self.channel_id).await? {
+                futures.push(cache.upsert(
+                    &cache.text_channels,
+                    TextChannelEntity {
+                        last_message_id: Some(self.id),
+                        ..text_channel
+                    },
+                ));
             }
 
-            if let Some(private_channel) = cache.private_channels.get(self.channel_id).await? {
-                futures.push(cache.private_channels.upsert(PrivateChannelEntity {
-                    last_message_id: Some(self.id),
-                    ..private_channel
-                }));
+            if let Some(private_channel) =
+                cache.get(&cache.private_channels, self.channel_id).await?
+            {
+                futures.push(cache.upsert(
+                    &cache.private_channels,
+                    PrivateChannelEntity {
+                        last_message_id: Some(self.id),
+                        ..private_channel
+                    },
+                ));
             }
 
             for attachment in self.0.attachments.iter().cloned() {
                 let entity = AttachmentEntity::from((self.id, attachment));
-                futures.push(cache.attachments.upsert(entity));
+                futures.push(cache.upsert(&cache.attachments, entity));
+            }
+
+            // Authors carry their current public flags, so upserting them
+            // keeps the badges of users outside of cached guilds fresh.
+            // Webhook authors aren't users.
+            if self.webhook_id.is_none() {
+                let entity = UserEntity::from(self.author.clone());
+                futures.push(cache.upsert_partial(&cache.users, entity));
+
+                if let (Some(guild_id), Some(member)) = (self.guild_id, self.member.clone()) {
+                    let entity = MemberEntity::from((guild_id, self.author.id, member));
+                    futures.push(cache.upsert_partial(&cache.members, entity));
+                }
+            }
+
+            // Authors may mention themselves, and are already upserted.
+            for mention in &self.mentions {
+                if mention.id == self.author.id && self.webhook_id.is_none() {
+                    continue;
+                }
+
+                if let (Some(guild_id), Some(member)) = (self.guild_id, mention.member.clone()) {
+                    let entity = MemberEntity::from((guild_id, mention.id, member));
+                    futures.push(cache.upsert_partial(&cache.members, entity));
+                }
+
+                let entity = UserEntity::from(mention.clone());
+                futures.push(cache.upsert_partial(&cache.users, entity));
             }
 
             let entity = MessageEntity::from(self.0.clone());
-            futures.push(cache.messages.upsert(entity));
+            futures.push(cache.upsert(&cache.messages, entity));
+
+            #[cfg(feature = "emoji-usage")]
+            if let Some(guild_id) = self.guild_id {
+                let mut counts = HashMap::new();
+
+                for emoji_id in emoji_usage::custom_emoji_ids(&self.content) {
+                    *counts.entry(emoji_id).or_insert(0) += 1;
+                }
+
+                for (emoji_id, count) in counts {
+                    futures.push(
+                        cache
+                            .emojis
+                            .record_usage(guild_id, emoji_id, count)
+                            .map_err(ProcessError::upsert::<EmojiEntity>)
+                            .boxed(),
+                    );
+                }
+            }
 
             futures.try_collect().await
         })
     }
 }
 
+#[cfg(feature = "emoji-usage")]
+impl<T: Backend> CacheUpdate<T> for ReactionAdd {
+    fn process<'a>(
+        &'a self,
+        cache: &'a Cache<T>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        match (self.guild_id, &self.emoji) {
+            (Some(guild_id), ReactionType::Custom { id, .. }) => cache
+                .emojis
+                .record_usage(guild_id, *id, 1)
+                .map_err(ProcessError::upsert::<EmojiEntity>)
+                .boxed(),
+            _ => noop::<T>(),
+        }
+    }
+}
+
 impl<T: Backend> CacheUpdate<T> for MessageDelete {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         Box::pin(async move {
             let futures = FuturesUnordered::new();
 
-            let mut attachments = cache.messages.attachments(self.id).await?;
+            let mut attachments = cache
+                .messages
+                .attachments(self.id)
+                .await
+                .map_err(ProcessError::list::<MessageEntity>)?;
             while let Some(Ok(attachment)) = attachments.next().await {
-                futures.push(cache.attachments.remove(attachment.id));
+                futures.push(cache.remove(&cache.attachments, attachment.id));
             }
 
             futures.try_collect::<()>().await?;
-            cache.messages.remove(self.id).await
+            cache.remove(&cache.messages, self.id).await
         })
     }
 }
@@ -745,18 +2287,22 @@ impl<T: Backend> CacheUpdate<T> for MessageDeleteBulk {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         Box::pin(async move {
             let attachment_futures = FuturesUnordered::new();
             let message_futures = FuturesUnordered::new();
 
             for id in self.ids.iter().copied() {
-                let mut attachments = cache.messages.attachments(id).await?;
+                let mut attachments = cache
+                    .messages
+                    .attachments(id)
+                    .await
+                    .map_err(ProcessError::list::<MessageEntity>)?;
                 while let Some(Ok(attachment)) = attachments.next().await {
-                    attachment_futures.push(cache.attachments.remove(attachment.id));
+                    attachment_futures.push(cache.remove(&cache.attachments, attachment.id));
                 }
 
-                message_futures.push(cache.messages.remove(id));
+                message_futures.push(cache.remove(&cache.messages, id));
             }
 
             attachment_futures.try_collect::<()>().await?;
@@ -769,13 +2315,14 @@ impl<T: Backend> CacheUpdate<T> for MessageUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         Box::pin(async move {
             let futures = FuturesUnordered::new();
 
             if let Some(attachments) = &self.attachments {
                 futures.push(
-                    cache.attachments.upsert_bulk(
+                    cache.upsert_bulk(
+                        &cache.attachments,
                         attachments
                             .iter()
                             .cloned()
@@ -786,12 +2333,11 @@ impl<T: Backend> CacheUpdate<T> for MessageUpdate {
 
             futures.push(
                 cache
-                    .messages
-                    .get(self.id)
+                    .get(&cache.messages, self.id)
                     .and_then(|message| {
                         message.map_or_else(
                             || future::ok(()).boxed(),
-                            |message| cache.messages.upsert(message.update(self.clone())),
+                            |message| cache.upsert(&cache.messages, message.update(self.clone())),
                         )
                     })
                     .boxed(),
@@ -806,16 +2352,41 @@ impl<T: Backend> CacheUpdate<T> for PresenceUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         let futures = FuturesUnordered::new();
 
         if let UserOrId::User(user) = &self.user {
             let entity = UserEntity::from(user.clone());
-            futures.push(cache.users.upsert(entity));
+            futures.push(cache.upsert_partial(&cache.users, entity));
         }
 
         let entity = PresenceEntity::from(self.clone());
-        futures.push(cache.presences.upsert(entity));
+
+        if cache.hooks.is_empty() {
+            futures.push(cache.upsert(&cache.presences, entity));
+
+            return futures.try_collect().boxed();
+        }
+
+        let Some(entity) = cache.intercept(entity) else {
+            return futures.try_collect().boxed();
+        };
+
+        futures.push(Box::pin(async move {
+            let old = cache
+                .get(&cache.presences, (entity.guild_id, entity.user_id))
+                .await?;
+            let changed = old.as_ref() != Some(&entity);
+
+            if changed {
+                cache.store(&cache.presences, entity.clone()).await?;
+                cache.emit(&CacheChange::PresenceChanged { old, new: entity });
+            } else if !cache.suppress_duplicates || cache.sequence.is_some() {
+                cache.store(&cache.presences, entity).await?;
+            }
+
+            Ok(())
+        }));
 
         futures.try_collect().boxed()
     }
@@ -825,10 +2396,39 @@ impl<T: Backend> CacheUpdate<T> for Ready {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
-        let entity = CurrentUserEntity::from(self.user.clone());
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        Box::pin(async move {
+            cache.sessions.insert(Session::from(self));
 
-        cache.current_user.upsert(entity)
+            let entity = CurrentUserEntity::from(self.user.clone());
+            cache.upsert_current_user(entity).await?;
+
+            for status in &self.guilds {
+                let guild_id = match status {
+                    GuildStatus::Online(guild) => guild.id,
+                    GuildStatus::Offline(guild) => guild.id,
+                };
+
+                if !cache.caches_guild(guild_id) {
+                    continue;
+                }
+
+                match status {
+                    GuildStatus::Online(guild) => GuildCreate(guild.clone()).process(cache).await?,
+                    GuildStatus::Offline(guild) => {
+                        if cache.get(&cache.guilds, guild.id).await?.is_some() {
+                            mark_unavailable(cache, guild.id).await?;
+                        } else {
+                            cache
+                                .upsert(&cache.guilds, GuildEntity::skeleton(guild.id))
+                                .await?;
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        })
     }
 }
 
@@ -836,10 +2436,10 @@ impl<T: Backend> CacheUpdate<T> for RoleCreate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         let entity = RoleEntity::from((self.role.clone(), self.guild_id));
 
-        cache.roles.upsert(entity)
+        cache.upsert(&cache.roles, entity)
     }
 }
 
@@ -847,8 +2447,8 @@ impl<T: Backend> CacheUpdate<T> for RoleDelete {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
-        cache.roles.remove(self.role_id)
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        cache.remove(&cache.roles, self.role_id)
     }
 }
 
@@ -856,10 +2456,19 @@ impl<T: Backend> CacheUpdate<T> for RoleUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         let entity = RoleEntity::from((self.role.clone(), self.guild_id));
 
-        cache.roles.upsert(entity)
+        cache.upsert(&cache.roles, entity)
+    }
+}
+
+impl<T: Backend> CacheUpdate<T> for UnavailableGuild {
+    fn process<'a>(
+        &'a self,
+        cache: &'a Cache<T>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        mark_unavailable(cache, self.id)
     }
 }
 
@@ -867,10 +2476,10 @@ impl<T: Backend> CacheUpdate<T> for UserUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
         let entity = CurrentUserEntity::from(self.0.clone());
 
-        cache.current_user.upsert(entity)
+        cache.upsert_current_user(entity)
     }
 }
 
@@ -878,14 +2487,55 @@ impl<T: Backend> CacheUpdate<T> for VoiceStateUpdate {
     fn process<'a>(
         &'a self,
         cache: &'a Cache<T>,
-    ) -> Pin<Box<dyn Future<Output = Result<(), T::Error>> + Send + 'a>> {
-        self.0.guild_id.map_or_else(
-            || future::ok(()).boxed(),
-            |guild_id| {
-                let entity = VoiceStateEntity::from((self.0.clone(), guild_id));
+    ) -> Pin<Box<dyn Future<Output = Result<(), ProcessError<T::Error>>> + Send + 'a>> {
+        let Some(guild_id) = self.0.guild_id else {
+            return future::ok(()).boxed();
+        };
 
-                cache.voice_states.upsert(entity)
-            },
-        )
+        Box::pin(async move {
+            let mut entity = VoiceStateEntity::from((self.0.clone(), guild_id));
+            let previous = if entity.channel_id.is_some() || !cache.hooks.is_empty() {
+                cache
+                    .get(&cache.voice_states, (guild_id, entity.user_id))
+                    .await?
+            } else {
+                None
+            };
+
+            if entity.channel_id.is_some() {
+                entity.joined_channel_at = match &previous {
+                    Some(previous) if previous.channel_id == entity.channel_id => {
+                        previous.joined_channel_at
+                    }
+                    _ => SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .ok()
+                        .and_then(|now| u64::try_from(now.as_millis()).ok()),
+                };
+            }
+
+            if cache.hooks.is_empty() {
+                return cache.upsert(&cache.voice_states, entity).await;
+            }
+
+            let Some(entity) = cache.intercept(entity) else {
+                return Ok(());
+            };
+
+            let transitions = VoiceTransition::between(previous.as_ref(), &entity);
+            let user_id = entity.user_id;
+
+            cache.write(&cache.voice_states, entity).await?;
+
+            if !transitions.is_empty() {
+                cache.emit(&CacheChange::VoiceStateChanged {
+                    guild_id,
+                    user_id,
+                    transitions,
+                });
+            }
+
+            Ok(())
+        })
     }
 }
@@ -0,0 +1,83 @@
+//! URLs of images hosted on Discord's CDN.
+//!
+//! Entities store images as the hashes that Discord sends. The functions in
+//! this module build the URLs that the images are served from, which is also
+//! possible for previous hashes such as those recorded in a user's
+//! [`UserRepository::avatar_history`].
+//!
+//! [`UserRepository::avatar_history`]: ../entity/user/trait.UserRepository.html#method.avatar_history
+
+use twilight_model::id::UserId;
+
+/// Base URL of Discord's CDN.
+pub const CDN_URL: &str = "https://cdn.discordapp.com";
+
+/// Number of default avatars that users without a custom avatar are assigned
+/// one of.
+const DEFAULT_AVATARS: u16 = 5;
+
+/// Format to request an image from the CDN in.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ImageFormat {
+    /// GIF, which is only available for animated images.
+    Gif,
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl ImageFormat {
+    /// Return the file extension of the format.
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::WebP => "webp",
+        }
+    }
+}
+
+/// Return whether an image hash is the hash of an animated image.
+pub fn is_animated(hash: &str) -> bool {
+    hash.starts_with("a_")
+}
+
+/// Build the URL of a user's avatar.
+///
+/// Users without a custom avatar, whose hash is `None`, have the default
+/// avatar for their discriminator, which is always a PNG. Requesting a GIF of
+/// an avatar that isn't animated returns the URL of a PNG instead.
+///
+/// `size` is the width and height of the image in pixels, which Discord
+/// requires to be a power of two between 16 and 4096.
+pub fn avatar_url(
+    user_id: UserId,
+    discriminator: &str,
+    hash: Option<&str>,
+    size: u16,
+    format: ImageFormat,
+) -> String {
+    let Some(hash) = hash else {
+        return default_avatar_url(discriminator);
+    };
+
+    let format = if format == ImageFormat::Gif && !is_animated(hash) {
+        ImageFormat::Png
+    } else {
+        format
+    };
+
+    format!(
+        "{CDN_URL}/avatars/{}/{hash}.{}?size={size}",
+        user_id.0,
+        format.extension(),
+    )
+}
+
+/// Build the URL of the default avatar for a discriminator.
+pub fn default_avatar_url(discriminator: &str) -> String {
+    let index = discriminator.parse::<u16>().unwrap_or_default() % DEFAULT_AVATARS;
+
+    format!("{CDN_URL}/embed/avatars/{index}.png")
+}
@@ -0,0 +1,206 @@
+//! Compression of large serialized entities for persistent backends.
+//!
+//! Most entities serialize to a few dozen bytes, but messages with embeds or
+//! guilds with many features can be several kilobytes. A [`Compressor`]
+//! compresses the serialized bytes of entities above a size threshold with
+//! LZ4 before backends store them, reducing the footprint of large entities
+//! without spending time on small ones.
+//!
+//! Compressed and uncompressed entities are distinguished by a header byte,
+//! so the threshold can be changed, or compression enabled, without
+//! rewriting stored entities.
+//!
+//! # Examples
+//!
+//! ```
+//! use twilight_cache::compression::Compressor;
+//!
+//! let compressor = Compressor::new(64);
+//!
+//! let content = "a".repeat(1024);
+//! let compressed = compressor.compress(content.as_bytes());
+//! assert!(compressed.len() < content.len());
+//! assert_eq!(content.as_bytes(), &*compressor.decompress(&compressed)?);
+//!
+//! let stats = compressor.stats();
+//! assert_eq!(1, stats.compressed);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`Compressor`]: struct.Compressor.html
+
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Header of entities stored without compression.
+const HEADER_RAW: u8 = 0;
+
+/// Header of entities compressed with LZ4.
+const HEADER_LZ4: u8 = 1;
+
+/// Error returned when a stored entity can't be decompressed.
+///
+/// This occurs when the bytes weren't produced by a [`Compressor`] or were
+/// modified.
+///
+/// [`Compressor`]: struct.Compressor.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DecompressError;
+
+impl Display for DecompressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("entity could not be decompressed")
+    }
+}
+
+impl Error for DecompressError {}
+
+/// Number of entities and bytes handled by a compressor.
+///
+/// Retrieved via [`Compressor::stats`].
+///
+/// [`Compressor::stats`]: struct.Compressor.html#method.stats
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CompressionStats {
+    /// Number of entities that were compressed.
+    pub compressed: u64,
+    /// Total size of the compressed entities before compression.
+    pub input_bytes: u64,
+    /// Total size of the compressed entities after compression.
+    pub output_bytes: u64,
+    /// Number of entities stored without compression, either because they
+    /// were below the threshold or because compressing them didn't make them
+    /// smaller.
+    pub skipped: u64,
+}
+
+impl CompressionStats {
+    /// Ratio of the compressed size to the original size of compressed
+    /// entities, or `None` if no entities were compressed.
+    pub fn ratio(&self) -> Option<f64> {
+        if self.input_bytes == 0 {
+            return None;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        Some(self.output_bytes as f64 / self.input_bytes as f64)
+    }
+
+    /// Number of bytes saved by compression.
+    pub const fn saved_bytes(&self) -> u64 {
+        self.input_bytes.saturating_sub(self.output_bytes)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    compressed: AtomicU64,
+    input_bytes: AtomicU64,
+    output_bytes: AtomicU64,
+    skipped: AtomicU64,
+}
+
+/// Codec compressing serialized entities above a size threshold.
+///
+/// Clones share their statistics.
+///
+/// **Backend implementations**: pass the serialized bytes of an entity through
+/// [`compress`] before storing them, and stored bytes through [`decompress`]
+/// before deserializing them. When entities are also encrypted, compress them
+/// before encrypting them, as encrypted bytes don't compress.
+///
+/// [`compress`]: #method.compress
+/// [`decompress`]: #method.decompress
+#[derive(Clone, Debug)]
+pub struct Compressor {
+    counters: Arc<Counters>,
+    threshold: usize,
+}
+
+impl Compressor {
+    /// Create a compressor compressing entities whose serialized size is at
+    /// least the threshold, in bytes.
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            counters: Arc::default(),
+            threshold,
+        }
+    }
+
+    /// Return the size in bytes from which entities are compressed.
+    pub const fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Return the number of entities and bytes compressed so far.
+    pub fn stats(&self) -> CompressionStats {
+        CompressionStats {
+            compressed: self.counters.compressed.load(Ordering::Relaxed),
+            input_bytes: self.counters.input_bytes.load(Ordering::Relaxed),
+            output_bytes: self.counters.output_bytes.load(Ordering::Relaxed),
+            skipped: self.counters.skipped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Compress the serialized bytes of an entity if they're at least as
+    /// long as the threshold.
+    ///
+    /// Entities are stored uncompressed if compressing them doesn't make them
+    /// smaller.
+    pub fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+        if bytes.len() >= self.threshold {
+            let compressed = lz4_flex::compress_prepend_size(bytes);
+
+            if compressed.len() < bytes.len() {
+                let mut output = Vec::with_capacity(compressed.len() + 1);
+                output.push(HEADER_LZ4);
+                output.extend_from_slice(&compressed);
+
+                self.counters.compressed.fetch_add(1, Ordering::Relaxed);
+                self.counters
+                    .input_bytes
+                    .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                self.counters
+                    .output_bytes
+                    .fetch_add(output.len() as u64, Ordering::Relaxed);
+
+                return output;
+            }
+        }
+
+        self.counters.skipped.fetch_add(1, Ordering::Relaxed);
+
+        let mut output = Vec::with_capacity(bytes.len() + 1);
+        output.push(HEADER_RAW);
+        output.extend_from_slice(bytes);
+
+        output
+    }
+
+    /// Decompress the stored bytes of an entity.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecompressError`] if the bytes weren't produced by
+    /// [`compress`] or were modified.
+    ///
+    /// [`compress`]: #method.compress
+    /// [`DecompressError`]: struct.DecompressError.html
+    pub fn decompress<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, [u8]>, DecompressError> {
+        match bytes.split_first() {
+            Some((&HEADER_RAW, rest)) => Ok(Cow::Borrowed(rest)),
+            Some((&HEADER_LZ4, rest)) => lz4_flex::decompress_size_prepended(rest)
+                .map(Cow::Owned)
+                .map_err(|_| DecompressError),
+            _ => Err(DecompressError),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Counting how often a guild's custom emojis are used.
+//!
+//! With the `emoji-usage` feature enabled, the cache counts every custom emoji
+//! used in the content of a guild message and every custom emoji reaction
+//! added to a guild message, recording them through
+//! [`EmojiRepository::record_usage`]. The counts of a guild can be retrieved
+//! with [`EmojiRepository::usage`].
+//!
+//! Usage is only counted by backends that store it; the default
+//! implementations of both methods discard usage and return no counts.
+//! Stickers aren't counted.
+//!
+//! # Examples
+//!
+//! ```
+//! use twilight_cache::emoji_usage;
+//! use twilight_model::id::EmojiId;
+//!
+//! let content = "hi <:wave:123> <a:party:456> :not_custom:";
+//! let ids = emoji_usage::custom_emoji_ids(content).collect::<Vec<_>>();
+//!
+//! assert_eq!(vec![EmojiId(123), EmojiId(456)], ids);
+//! ```
+//!
+//! [`EmojiRepository::record_usage`]: ../entity/guild/trait.EmojiRepository.html#method.record_usage
+//! [`EmojiRepository::usage`]: ../entity/guild/trait.EmojiRepository.html#method.usage
+
+use twilight_model::id::EmojiId;
+
+/// Return an iterator over the IDs of the custom emojis mentioned in message
+/// content, in the form of `<:name:id>` or `<a:name:id>`.
+///
+/// An emoji mentioned more than once is returned once for every mention.
+pub fn custom_emoji_ids(content: &str) -> impl Iterator<Item = EmojiId> + '_ {
+    content.split('<').skip(1).filter_map(parse_mention)
+}
+
+/// Parse the remainder of a mention after its opening `<`.
+fn parse_mention(rest: &str) -> Option<EmojiId> {
+    let end = rest.find('>')?;
+    let mention = &rest[..end];
+    let mention = mention.strip_prefix('a').unwrap_or(mention);
+    let mention = mention.strip_prefix(':')?;
+    let (name, id) = mention.split_once(':')?;
+
+    if name.is_empty() || id.is_empty() || !id.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    id.parse().ok().map(EmojiId)
+}
@@ -0,0 +1,144 @@
+//! Encryption of serialized entities for persistent backends.
+//!
+//! Backends that persist entities to disk or to a shared database store
+//! personal data of users, such as their names and the content of their
+//! messages. An [`EntityCipher`] encrypts the serialized bytes of entities
+//! before backends store them and decrypts them after reading them back, so
+//! that the stored data can't be read without the key.
+//!
+//! Entities are encrypted with ChaCha20-Poly1305 using a random nonce per
+//! entity. The key that an entity is stored under is authenticated along with
+//! it, so an encrypted entity copied to another key fails to decrypt.
+//!
+//! # Examples
+//!
+//! ```
+//! use twilight_cache::encryption::EntityCipher;
+//!
+//! let key = EntityCipher::generate_key();
+//! let cipher = EntityCipher::new(&key);
+//!
+//! let encrypted = cipher.encrypt(b"u:1", b"serialized user");
+//! assert_eq!(b"serialized user".to_vec(), cipher.decrypt(b"u:1", &encrypted)?);
+//! assert!(cipher.decrypt(b"u:2", &encrypted).is_err());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`EntityCipher`]: struct.EntityCipher.html
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use std::{
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+};
+
+/// Length of an encryption key in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// Length of the nonce prepended to encrypted entities in bytes.
+pub const NONCE_LEN: usize = 12;
+
+/// Error returned when an encrypted entity can't be decrypted.
+///
+/// This occurs when the entity was encrypted with a different key or stored
+/// under a different key, was modified, or wasn't encrypted at all.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DecryptError;
+
+impl Display for DecryptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("entity could not be decrypted")
+    }
+}
+
+impl Error for DecryptError {}
+
+/// Cipher encrypting and decrypting serialized entities with a key.
+///
+/// **Backend implementations**: encrypt the serialized bytes of an entity with
+/// [`encrypt`] before storing them, passing the key that the entity is stored
+/// under as the associated data, and [`decrypt`] them with the same key after
+/// reading them. The nonce is stored as part of the returned bytes.
+///
+/// [`decrypt`]: #method.decrypt
+/// [`encrypt`]: #method.encrypt
+#[derive(Clone)]
+pub struct EntityCipher(ChaCha20Poly1305);
+
+impl EntityCipher {
+    /// Create a cipher with a key.
+    ///
+    /// The key must be kept secret and be the same whenever the stored
+    /// entities are read.
+    pub fn new(key: &[u8; KEY_LEN]) -> Self {
+        Self(ChaCha20Poly1305::new(Key::from_slice(key)))
+    }
+
+    /// Generate a random key from the operating system's random number
+    /// generator.
+    pub fn generate_key() -> [u8; KEY_LEN] {
+        ChaCha20Poly1305::generate_key(&mut OsRng).into()
+    }
+
+    /// Encrypt the serialized bytes of an entity, authenticating the key that
+    /// it is stored under.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bytes are too long to be encrypted, which is 256 GiB.
+    pub fn encrypt(&self, storage_key: &[u8], bytes: &[u8]) -> Vec<u8> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let encrypted = self
+            .0
+            .encrypt(
+                &nonce,
+                Payload {
+                    aad: storage_key,
+                    msg: bytes,
+                },
+            )
+            .expect("entity is too long to encrypt");
+
+        let mut output = Vec::with_capacity(NONCE_LEN + encrypted.len());
+        output.extend_from_slice(&nonce);
+        output.extend_from_slice(&encrypted);
+
+        output
+    }
+
+    /// Decrypt the bytes of an entity stored under a key.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecryptError`] if the bytes weren't encrypted by a cipher
+    /// with the same key for the same storage key or were modified.
+    ///
+    /// [`DecryptError`]: struct.DecryptError.html
+    pub fn decrypt(&self, storage_key: &[u8], bytes: &[u8]) -> Result<Vec<u8>, DecryptError> {
+        if bytes.len() < NONCE_LEN {
+            return Err(DecryptError);
+        }
+
+        let (nonce, encrypted) = bytes.split_at(NONCE_LEN);
+
+        self.0
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    aad: storage_key,
+                    msg: encrypted,
+                },
+            )
+            .map_err(|_| DecryptError)
+    }
+}
+
+impl Debug for EntityCipher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("EntityCipher").finish_non_exhaustive()
+    }
+}
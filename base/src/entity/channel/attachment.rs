@@ -10,10 +10,18 @@ use twilight_model::{
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct AttachmentEntity {
     pub filename: String,
     pub height: Option<u64>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: AttachmentId,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub message_id: MessageId,
     pub proxy_url: String,
     pub size: u64,
@@ -36,6 +44,14 @@ impl From<(MessageId, Attachment)> for AttachmentEntity {
     }
 }
 
+impl AttachmentEntity {
+    /// Create a builder for an attachment with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(id: AttachmentId, message_id: MessageId) -> AttachmentEntityBuilder {
+        AttachmentEntityBuilder::new(id, message_id)
+    }
+}
+
 impl Entity for AttachmentEntity {
     type Id = AttachmentId;
 
@@ -45,12 +61,73 @@ impl Entity for AttachmentEntity {
     }
 }
 
+/// Builder to create an [`AttachmentEntity`] without the need to specify every field.
+///
+/// [`AttachmentEntity`]: struct.AttachmentEntity.html
+#[derive(Clone, Debug)]
+pub struct AttachmentEntityBuilder(AttachmentEntity);
+
+impl AttachmentEntityBuilder {
+    pub fn new(id: AttachmentId, message_id: MessageId) -> Self {
+        Self(AttachmentEntity {
+            filename: String::new(),
+            height: None,
+            id,
+            message_id,
+            proxy_url: String::new(),
+            size: 0,
+            url: String::new(),
+            width: None,
+        })
+    }
+
+    pub fn build(self) -> AttachmentEntity {
+        self.0
+    }
+
+    pub fn filename(&mut self, filename: impl Into<String>) -> &mut Self {
+        self.0.filename = filename.into();
+
+        self
+    }
+
+    pub fn height(&mut self, height: u64) -> &mut Self {
+        self.0.height = Some(height);
+
+        self
+    }
+
+    pub fn proxy_url(&mut self, proxy_url: impl Into<String>) -> &mut Self {
+        self.0.proxy_url = proxy_url.into();
+
+        self
+    }
+
+    pub fn size(&mut self, size: u64) -> &mut Self {
+        self.0.size = size;
+
+        self
+    }
+
+    pub fn url(&mut self, url: impl Into<String>) -> &mut Self {
+        self.0.url = url.into();
+
+        self
+    }
+
+    pub fn width(&mut self, width: u64) -> &mut Self {
+        self.0.width = Some(width);
+
+        self
+    }
+}
+
 pub trait AttachmentRepository<B: Backend>: Repository<AttachmentEntity, B> + Send {
     fn message(&self, attachment_id: AttachmentId) -> GetEntityFuture<'_, MessageEntity, B::Error> {
         utils::relation_map(
-            self.backend().attachments(),
-            self.backend().messages(),
-            attachment_id,
+            self.get(attachment_id),
+            self.backend(),
+            B::messages,
             |attachment| attachment.message_id,
         )
     }
@@ -1,20 +1,34 @@
-use super::super::guild::GuildEntity;
+use super::super::guild::{GuildEntity, GuildRepository};
 use crate::{
-    repository::{GetEntityFuture, Repository},
+    repository::{GetEntityFuture, ListEntitiesFuture, Repository},
     utils, Backend, Entity,
 };
+use futures_util::future::TryFutureExt;
 use twilight_model::{
-    channel::{permission_overwrite::PermissionOverwrite, CategoryChannel, ChannelType},
-    id::{ChannelId, GuildId},
+    channel::{
+        permission_overwrite::{PermissionOverwrite, PermissionOverwriteType},
+        CategoryChannel, ChannelType,
+    },
+    id::{ChannelId, GuildId, UserId},
 };
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct CategoryChannelEntity {
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub guild_id: Option<GuildId>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: ChannelId,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub kind: ChannelType,
     pub name: String,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub permission_overwrites: Vec<PermissionOverwrite>,
     pub position: i64,
 }
@@ -32,6 +46,14 @@ impl From<CategoryChannel> for CategoryChannelEntity {
     }
 }
 
+impl CategoryChannelEntity {
+    /// Create a builder for a category channel with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(id: ChannelId, name: impl Into<String>) -> CategoryChannelEntityBuilder {
+        CategoryChannelEntityBuilder::new(id, name)
+    }
+}
+
 impl Entity for CategoryChannelEntity {
     type Id = ChannelId;
 
@@ -41,16 +63,117 @@ impl Entity for CategoryChannelEntity {
     }
 }
 
+/// Builder to create a [`CategoryChannelEntity`] without the need to specify every field.
+///
+/// [`CategoryChannelEntity`]: struct.CategoryChannelEntity.html
+#[derive(Clone, Debug)]
+pub struct CategoryChannelEntityBuilder(CategoryChannelEntity);
+
+impl CategoryChannelEntityBuilder {
+    pub fn new(id: ChannelId, name: impl Into<String>) -> Self {
+        Self(CategoryChannelEntity {
+            guild_id: None,
+            id,
+            kind: ChannelType::GuildCategory,
+            name: name.into(),
+            permission_overwrites: Vec::new(),
+            position: 0,
+        })
+    }
+
+    pub fn build(self) -> CategoryChannelEntity {
+        self.0
+    }
+
+    pub fn guild_id(&mut self, guild_id: GuildId) -> &mut Self {
+        self.0.guild_id = Some(guild_id);
+
+        self
+    }
+
+    pub fn kind(&mut self, kind: ChannelType) -> &mut Self {
+        self.0.kind = kind;
+
+        self
+    }
+
+    pub fn permission_overwrites(
+        &mut self,
+        permission_overwrites: Vec<PermissionOverwrite>,
+    ) -> &mut Self {
+        self.0.permission_overwrites = permission_overwrites;
+
+        self
+    }
+
+    pub fn position(&mut self, position: i64) -> &mut Self {
+        self.0.position = position;
+
+        self
+    }
+}
+
 /// Repository to work with guild category channels and their associated
 /// entities.
 pub trait CategoryChannelRepository<B: Backend>: Repository<CategoryChannelEntity, B> {
     /// Retrieve the guild associated with a guild category channel.
     fn guild(&self, channel_id: ChannelId) -> GetEntityFuture<'_, GuildEntity, B::Error> {
-        utils::relation_and_then(
-            self.backend().category_channels(),
-            self.backend().guilds(),
-            channel_id,
-            |channel| channel.guild_id,
+        utils::relation_and_then(self.get(channel_id), self.backend(), B::guilds, |channel| {
+            channel.guild_id
+        })
+    }
+
+    /// Retrieve the permission overwrite of a category channel targeting a
+    /// member or role.
+    ///
+    /// Backend implementations should return `None` if the channel isn't
+    /// cached or has no overwrite for the target.
+    fn overwrite_for(
+        &self,
+        channel_id: ChannelId,
+        target: PermissionOverwriteType,
+    ) -> GetEntityFuture<'_, PermissionOverwrite, B::Error> {
+        Box::pin(self.get(channel_id).map_ok(move |channel| {
+            channel.and_then(|channel| super::find_overwrite(channel.permission_overwrites, target))
+        }))
+    }
+
+    /// Retrieve the permission overwrites of a category channel that apply to a
+    /// member, in the order that Discord applies them.
+    ///
+    /// The `@everyone` role's overwrite comes first, followed by the
+    /// overwrites of the member's roles and then the member's own overwrite,
+    /// which is what permission calculators expect.
+    ///
+    /// Backend implementations should return `None` if the channel or member
+    /// isn't cached.
+    fn effective_overwrites(
+        &self,
+        channel_id: ChannelId,
+        user_id: UserId,
+    ) -> GetEntityFuture<'_, Vec<PermissionOverwrite>, B::Error> {
+        super::member_overwrites(self.get(channel_id), self.backend(), user_id, |channel| {
+            (channel.guild_id, channel.permission_overwrites)
+        })
+    }
+
+    /// Retrieve a stream of category channels within a guild.
+    ///
+    /// **Backend implementations**: the default implementation retrieves the
+    /// IDs from [`GuildRepository::channel_ids`]. Backends storing entities
+    /// by keys prefixed with their guild ID can instead scan the keys.
+    ///
+    /// [`GuildRepository::channel_ids`]: ../guild/trait.GuildRepository.html#tymethod.channel_ids
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, CategoryChannelEntity, B::Error> {
+        utils::stream_guild(
+            self.backend(),
+            guild_id,
+            |guilds, guild_id| guilds.channel_ids(guild_id),
+            |_, channel_id| channel_id,
+            B::category_channels,
         )
     }
 }
@@ -1,6 +1,9 @@
 use super::{super::user::UserEntity, MessageEntity};
+#[cfg(feature = "time")]
+use crate::timestamp::{OffsetDateTime, ParseTimestampError};
 use crate::{
     repository::{GetEntityFuture, ListEntitiesFuture, Repository},
+    timestamp::ParsedTimestamp,
     utils, Backend, Entity,
 };
 use twilight_model::{
@@ -10,15 +13,31 @@ use twilight_model::{
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct GroupEntity {
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub application_id: Option<ApplicationId>,
     pub icon: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: ChannelId,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub kind: ChannelType,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub last_message_id: Option<MessageId>,
     pub last_pin_timestamp: Option<String>,
+    /// Cache of the parsed `last_pin_timestamp`.
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub parsed_last_pin_timestamp: ParsedTimestamp,
     pub name: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub owner_id: UserId,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub recipient_ids: Vec<UserId>,
 }
 
@@ -33,6 +52,7 @@ impl From<Group> for GroupEntity {
             kind: group.kind,
             last_message_id: group.last_message_id,
             last_pin_timestamp: group.last_pin_timestamp,
+            parsed_last_pin_timestamp: ParsedTimestamp::default(),
             name: group.name,
             owner_id: group.owner_id,
             recipient_ids,
@@ -40,6 +60,27 @@ impl From<Group> for GroupEntity {
     }
 }
 
+impl GroupEntity {
+    /// Create a builder for a group with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(id: ChannelId, owner_id: UserId) -> GroupEntityBuilder {
+        GroupEntityBuilder::new(id, owner_id)
+    }
+
+    /// Parse the date a message was last pinned in the group.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored timestamp is invalid.
+    #[cfg(feature = "time")]
+    pub fn last_pin_timestamp_datetime(
+        &self,
+    ) -> Result<Option<OffsetDateTime>, ParseTimestampError> {
+        self.parsed_last_pin_timestamp
+            .get_optional(self.last_pin_timestamp.as_deref())
+    }
+}
+
 impl Entity for GroupEntity {
     type Id = ChannelId;
 
@@ -49,34 +90,94 @@ impl Entity for GroupEntity {
     }
 }
 
+/// Builder to create a [`GroupEntity`] without the need to specify every field.
+///
+/// [`GroupEntity`]: struct.GroupEntity.html
+#[derive(Clone, Debug)]
+pub struct GroupEntityBuilder(GroupEntity);
+
+impl GroupEntityBuilder {
+    pub fn new(id: ChannelId, owner_id: UserId) -> Self {
+        Self(GroupEntity {
+            application_id: None,
+            icon: None,
+            id,
+            kind: ChannelType::Group,
+            last_message_id: None,
+            last_pin_timestamp: None,
+            parsed_last_pin_timestamp: ParsedTimestamp::default(),
+            name: None,
+            owner_id,
+            recipient_ids: Vec::new(),
+        })
+    }
+
+    pub fn build(self) -> GroupEntity {
+        self.0
+    }
+
+    pub fn application_id(&mut self, application_id: ApplicationId) -> &mut Self {
+        self.0.application_id = Some(application_id);
+
+        self
+    }
+
+    pub fn icon(&mut self, icon: impl Into<String>) -> &mut Self {
+        self.0.icon = Some(icon.into());
+
+        self
+    }
+
+    pub fn kind(&mut self, kind: ChannelType) -> &mut Self {
+        self.0.kind = kind;
+
+        self
+    }
+
+    pub fn last_message_id(&mut self, last_message_id: MessageId) -> &mut Self {
+        self.0.last_message_id = Some(last_message_id);
+
+        self
+    }
+
+    pub fn last_pin_timestamp(&mut self, last_pin_timestamp: impl Into<String>) -> &mut Self {
+        self.0.last_pin_timestamp = Some(last_pin_timestamp.into());
+
+        self
+    }
+
+    pub fn name(&mut self, name: impl Into<String>) -> &mut Self {
+        self.0.name = Some(name.into());
+
+        self
+    }
+
+    pub fn recipient_ids(&mut self, recipient_ids: Vec<UserId>) -> &mut Self {
+        self.0.recipient_ids = recipient_ids;
+
+        self
+    }
+}
+
 pub trait GroupRepository<B: Backend>: Repository<GroupEntity, B> {
     /// Retrieve the last message of a group.
     fn last_message(&self, group_id: ChannelId) -> GetEntityFuture<'_, MessageEntity, B::Error> {
-        utils::relation_and_then(
-            self.backend().groups(),
-            self.backend().messages(),
-            group_id,
-            |group| group.last_message_id,
-        )
+        utils::relation_and_then(self.get(group_id), self.backend(), B::messages, |group| {
+            group.last_message_id
+        })
     }
 
     /// Retrieve the owner of a group.
     fn owner(&self, group_id: ChannelId) -> GetEntityFuture<'_, UserEntity, B::Error> {
-        utils::relation_map(
-            self.backend().groups(),
-            self.backend().users(),
-            group_id,
-            |group| group.owner_id,
-        )
+        utils::relation_map(self.get(group_id), self.backend(), B::users, |group| {
+            group.owner_id
+        })
     }
 
     /// Retrieve a stream of recipients associated with a group.
     fn recipients(&self, group_id: ChannelId) -> ListEntitiesFuture<'_, UserEntity, B::Error> {
-        utils::stream(
-            self.backend().groups(),
-            self.backend().users(),
-            group_id,
-            |group| group.recipient_ids.into_iter(),
-        )
+        utils::stream(self.get(group_id), self.backend(), B::users, |group| {
+            group.recipient_ids.into_iter()
+        })
     }
 }
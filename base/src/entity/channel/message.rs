@@ -1,18 +1,25 @@
 use super::{
     super::{
-        guild::{GuildEntity, RoleEntity},
+        guild::{GuildEntity, MemberEntity, RoleEntity},
         user::UserEntity,
     },
     AttachmentEntity, ChannelEntity, GuildChannelEntity, TextChannelEntity,
 };
+#[cfg(feature = "time")]
+use crate::timestamp::{OffsetDateTime, ParseTimestampError};
 use crate::{
     repository::{GetEntityFuture, ListEntitiesFuture, Repository},
+    timestamp::ParsedTimestamp,
     utils, Backend, Entity,
 };
+use futures_util::{
+    future::{self, FutureExt, TryFutureExt},
+    stream::{StreamExt, TryStreamExt},
+};
 use twilight_model::{
     channel::{
         embed::Embed,
-        message::{MessageActivity, MessageFlags, MessageReaction, MessageType},
+        message::{MessageActivity, MessageFlags, MessageReaction, MessageReference, MessageType},
         Message,
     },
     gateway::payload::MessageUpdate,
@@ -21,27 +28,59 @@ use twilight_model::{
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct MessageEntity {
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub activity: Option<MessageActivity>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub application_id: Option<ApplicationId>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub attachments: Vec<AttachmentId>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub author_id: UserId,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub channel_id: ChannelId,
     pub content: String,
     pub edited_timestamp: Option<String>,
+    /// Cache of the parsed `edited_timestamp`.
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub parsed_edited_timestamp: ParsedTimestamp,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub embeds: Vec<Embed>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsBits>))]
     pub flags: Option<MessageFlags>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub guild_id: Option<GuildId>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: MessageId,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub kind: MessageType,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub mention_channels: Vec<ChannelId>,
     pub mention_everyone: bool,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub mention_roles: Vec<RoleId>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub mentions: Vec<UserId>,
     pub pinned: bool,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub reactions: Vec<MessageReaction>,
+    /// Message that this message replies to or was crossposted from, if any.
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
+    pub reference: Option<MessageReference>,
     pub timestamp: String,
+    /// Cache of the parsed `timestamp`.
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub parsed_timestamp: ParsedTimestamp,
     pub tts: bool,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub webhook_id: Option<WebhookId>,
 }
 
@@ -75,6 +114,7 @@ impl From<Message> for MessageEntity {
             channel_id: message.channel_id,
             content: message.content,
             edited_timestamp: message.edited_timestamp,
+            parsed_edited_timestamp: ParsedTimestamp::default(),
             embeds: message.embeds,
             flags: message.flags,
             guild_id: message.guild_id,
@@ -86,7 +126,9 @@ impl From<Message> for MessageEntity {
             mentions,
             pinned: message.pinned,
             reactions: message.reactions,
+            reference: message.reference,
             timestamp: message.timestamp,
+            parsed_timestamp: ParsedTimestamp::default(),
             tts: message.tts,
             webhook_id: message.webhook_id,
         }
@@ -94,6 +136,75 @@ impl From<Message> for MessageEntity {
 }
 
 impl MessageEntity {
+    /// Create a builder for a message with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(
+        id: MessageId,
+        channel_id: ChannelId,
+        author_id: UserId,
+    ) -> MessageEntityBuilder {
+        MessageEntityBuilder::new(id, channel_id, author_id)
+    }
+
+    /// Return whether the message is ephemeral, which is only visible to the
+    /// user who invoked the interaction that it's a response to.
+    pub fn is_ephemeral(&self) -> bool {
+        self.flags
+            .is_some_and(|flags| flags.contains(MessageFlags::EPHEMERAL))
+    }
+
+    /// Return whether the message is a crosspost of an announcement made in a
+    /// followed news channel.
+    ///
+    /// The announcement is the message that the [`reference`] points to.
+    ///
+    /// [`reference`]: #structfield.reference
+    pub fn is_crosspost(&self) -> bool {
+        self.flags
+            .is_some_and(|flags| flags.contains(MessageFlags::IS_CROSSPOST))
+    }
+
+    /// Return whether the message is an announcement that has been
+    /// crossposted to the channels following its news channel.
+    pub fn is_crossposted(&self) -> bool {
+        self.flags
+            .is_some_and(|flags| flags.contains(MessageFlags::CROSSPOSTED))
+    }
+
+    /// Return whether the message was sent by Discord about an event in the
+    /// channel, such as a member joining or a message being pinned.
+    ///
+    /// Regular messages, replies, and responses to application commands
+    /// aren't system messages.
+    pub fn is_system(&self) -> bool {
+        !matches!(
+            self.kind,
+            MessageType::Regular | MessageType::Reply | MessageType::ApplicationCommand
+        )
+    }
+
+    /// Parse the date the message was sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored timestamp is invalid.
+    #[cfg(feature = "time")]
+    pub fn timestamp_datetime(&self) -> Result<OffsetDateTime, ParseTimestampError> {
+        self.parsed_timestamp.get(&self.timestamp)
+    }
+
+    /// Parse the date the message was last edited.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored timestamp is invalid.
+    #[cfg(feature = "time")]
+    pub fn edited_timestamp_datetime(&self) -> Result<Option<OffsetDateTime>, ParseTimestampError> {
+        self.parsed_edited_timestamp
+            .get_optional(self.edited_timestamp.as_deref())
+    }
+
+    #[must_use]
     pub fn update(self, update: MessageUpdate) -> Self {
         let attachments = update
             .attachments
@@ -109,6 +220,7 @@ impl MessageEntity {
             channel_id: update.channel_id,
             content: update.content.map_or(self.content, |m| m),
             edited_timestamp: update.edited_timestamp.or(self.edited_timestamp),
+            parsed_edited_timestamp: ParsedTimestamp::default(),
             embeds: update.embeds.map_or(self.embeds, |e| e),
             guild_id: update.guild_id.or(self.guild_id),
             id: update.id,
@@ -118,6 +230,7 @@ impl MessageEntity {
             mentions,
             pinned: update.pinned.map_or(self.pinned, |p| p),
             timestamp: update.timestamp.map_or(self.timestamp, |t| t),
+            parsed_timestamp: ParsedTimestamp::default(),
             tts: update.tts.map_or(self.tts, |t| t),
             ..self
         }
@@ -133,71 +246,260 @@ impl Entity for MessageEntity {
     }
 }
 
+/// Builder to create a [`MessageEntity`] without the need to specify every field.
+///
+/// [`MessageEntity`]: struct.MessageEntity.html
+#[derive(Clone, Debug)]
+pub struct MessageEntityBuilder(MessageEntity);
+
+impl MessageEntityBuilder {
+    pub fn new(id: MessageId, channel_id: ChannelId, author_id: UserId) -> Self {
+        Self(MessageEntity {
+            activity: None,
+            application_id: None,
+            attachments: Vec::new(),
+            author_id,
+            channel_id,
+            content: String::new(),
+            edited_timestamp: None,
+            parsed_edited_timestamp: ParsedTimestamp::default(),
+            embeds: Vec::new(),
+            flags: None,
+            guild_id: None,
+            id,
+            kind: MessageType::Regular,
+            mention_channels: Vec::new(),
+            mention_everyone: false,
+            mention_roles: Vec::new(),
+            mentions: Vec::new(),
+            pinned: false,
+            reactions: Vec::new(),
+            reference: None,
+            timestamp: String::new(),
+            parsed_timestamp: ParsedTimestamp::default(),
+            tts: false,
+            webhook_id: None,
+        })
+    }
+
+    pub fn build(self) -> MessageEntity {
+        self.0
+    }
+
+    pub fn activity(&mut self, activity: MessageActivity) -> &mut Self {
+        self.0.activity = Some(activity);
+
+        self
+    }
+
+    pub fn application_id(&mut self, application_id: ApplicationId) -> &mut Self {
+        self.0.application_id = Some(application_id);
+
+        self
+    }
+
+    pub fn attachments(&mut self, attachments: Vec<AttachmentId>) -> &mut Self {
+        self.0.attachments = attachments;
+
+        self
+    }
+
+    pub fn content(&mut self, content: impl Into<String>) -> &mut Self {
+        self.0.content = content.into();
+
+        self
+    }
+
+    pub fn edited_timestamp(&mut self, edited_timestamp: impl Into<String>) -> &mut Self {
+        self.0.edited_timestamp = Some(edited_timestamp.into());
+
+        self
+    }
+
+    pub fn embeds(&mut self, embeds: Vec<Embed>) -> &mut Self {
+        self.0.embeds = embeds;
+
+        self
+    }
+
+    pub fn flags(&mut self, flags: MessageFlags) -> &mut Self {
+        self.0.flags = Some(flags);
+
+        self
+    }
+
+    pub fn guild_id(&mut self, guild_id: GuildId) -> &mut Self {
+        self.0.guild_id = Some(guild_id);
+
+        self
+    }
+
+    pub fn kind(&mut self, kind: MessageType) -> &mut Self {
+        self.0.kind = kind;
+
+        self
+    }
+
+    pub fn mention_channels(&mut self, mention_channels: Vec<ChannelId>) -> &mut Self {
+        self.0.mention_channels = mention_channels;
+
+        self
+    }
+
+    pub fn mention_everyone(&mut self, mention_everyone: bool) -> &mut Self {
+        self.0.mention_everyone = mention_everyone;
+
+        self
+    }
+
+    pub fn mention_roles(&mut self, mention_roles: Vec<RoleId>) -> &mut Self {
+        self.0.mention_roles = mention_roles;
+
+        self
+    }
+
+    pub fn mentions(&mut self, mentions: Vec<UserId>) -> &mut Self {
+        self.0.mentions = mentions;
+
+        self
+    }
+
+    pub fn pinned(&mut self, pinned: bool) -> &mut Self {
+        self.0.pinned = pinned;
+
+        self
+    }
+
+    pub fn reactions(&mut self, reactions: Vec<MessageReaction>) -> &mut Self {
+        self.0.reactions = reactions;
+
+        self
+    }
+
+    pub fn reference(&mut self, reference: MessageReference) -> &mut Self {
+        self.0.reference = Some(reference);
+
+        self
+    }
+
+    pub fn timestamp(&mut self, timestamp: impl Into<String>) -> &mut Self {
+        self.0.timestamp = timestamp.into();
+
+        self
+    }
+
+    pub fn tts(&mut self, tts: bool) -> &mut Self {
+        self.0.tts = tts;
+
+        self
+    }
+
+    pub fn webhook_id(&mut self, webhook_id: WebhookId) -> &mut Self {
+        self.0.webhook_id = Some(webhook_id);
+
+        self
+    }
+}
+
+/// Message along with the entities related to it.
+///
+/// Retrieved via [`MessageRepository::context`].
+///
+/// [`MessageRepository::context`]: trait.MessageRepository.html#method.context
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MessageContext {
+    /// Author of the message, if cached.
+    pub author: Option<UserEntity>,
+    /// Channel that the message was sent in, if cached.
+    pub channel: Option<ChannelEntity>,
+    /// Guild that the message was sent in, if it was sent in a guild and the
+    /// guild is cached.
+    pub guild: Option<GuildEntity>,
+    /// Member who sent the message, if it was sent in a guild and the member
+    /// is cached.
+    pub member: Option<MemberEntity>,
+    /// The message.
+    pub message: MessageEntity,
+}
+
 pub trait MessageRepository<B: Backend>: Repository<MessageEntity, B> + Send {
     fn attachments(
         &self,
         message_id: MessageId,
     ) -> ListEntitiesFuture<'_, AttachmentEntity, B::Error> {
         utils::stream(
-            self.backend().messages(),
-            self.backend().attachments(),
-            message_id,
+            self.get(message_id),
+            self.backend(),
+            B::attachments,
             |message| message.attachments.into_iter(),
         )
     }
 
     fn author(&self, message_id: MessageId) -> GetEntityFuture<'_, UserEntity, B::Error> {
-        utils::relation_map(
-            self.backend().messages(),
-            self.backend().users(),
-            message_id,
-            |message| message.author_id,
-        )
+        utils::relation_map(self.get(message_id), self.backend(), B::users, |message| {
+            message.author_id
+        })
     }
 
     fn channel(&self, message_id: MessageId) -> GetEntityFuture<'_, ChannelEntity, B::Error> {
         let backend = self.backend();
+        let message = self.get(message_id);
 
         Box::pin(async move {
-            let messages = backend.messages();
-
-            let channel_id = if let Some(msg) = messages.get(message_id).await? {
+            let channel_id = if let Some(msg) = message.await? {
                 msg.channel_id
             } else {
                 return Ok(None);
             };
 
-            let text_channels = backend.text_channels();
-
-            if let Some(channel) = text_channels.get(channel_id).await? {
-                return Ok(Some(ChannelEntity::Guild(GuildChannelEntity::Text(
-                    channel,
-                ))));
-            }
-
-            let private_channels = backend.private_channels();
+            message_channel(backend, channel_id).await
+        })
+    }
 
-            if let Some(channel) = private_channels.get(channel_id).await? {
-                return Ok(Some(ChannelEntity::Private(channel)));
-            }
+    /// Retrieve a message along with its author, the member who sent it, and
+    /// the channel and guild that it was sent in.
+    ///
+    /// Backend implementations should return `None` if the message is not in
+    /// the cache.
+    ///
+    /// **Backend implementations**: the default implementation retrieves each
+    /// related entity after the message, one at a time. Backends that can
+    /// retrieve them at once, such as by joining tables, should implement
+    /// this manually.
+    fn context(&self, message_id: MessageId) -> GetEntityFuture<'_, MessageContext, B::Error> {
+        let backend = self.backend();
+        let message = self.get(message_id);
 
-            let groups = backend.groups();
+        Box::pin(async move {
+            let Some(message) = message.await? else {
+                return Ok(None);
+            };
 
-            if let Some(channel) = groups.get(channel_id).await? {
-                return Ok(Some(ChannelEntity::Group(channel)));
-            }
+            let author = backend.users().get(message.author_id).await?;
+            let channel = message_channel(backend, message.channel_id).await?;
+            let (guild, member) = match message.guild_id {
+                Some(guild_id) => (
+                    backend.guilds().get(guild_id).await?,
+                    backend.members().get((guild_id, message.author_id)).await?,
+                ),
+                None => (None, None),
+            };
 
-            Ok(None)
+            Ok(Some(MessageContext {
+                author,
+                channel,
+                guild,
+                member,
+                message,
+            }))
         })
     }
 
     fn guild(&self, message_id: MessageId) -> GetEntityFuture<'_, GuildEntity, B::Error> {
-        utils::relation_and_then(
-            self.backend().messages(),
-            self.backend().guilds(),
-            message_id,
-            |message| message.guild_id,
-        )
+        utils::relation_and_then(self.get(message_id), self.backend(), B::guilds, |message| {
+            message.guild_id
+        })
     }
 
     fn mention_channels(
@@ -205,28 +507,117 @@ pub trait MessageRepository<B: Backend>: Repository<MessageEntity, B> + Send {
         message_id: MessageId,
     ) -> ListEntitiesFuture<'_, TextChannelEntity, B::Error> {
         utils::stream(
-            self.backend().messages(),
-            self.backend().text_channels(),
-            message_id,
+            self.get(message_id),
+            self.backend(),
+            B::text_channels,
             |message| message.mention_channels.into_iter(),
         )
     }
 
     fn mention_roles(&self, message_id: MessageId) -> ListEntitiesFuture<'_, RoleEntity, B::Error> {
-        utils::stream(
-            self.backend().messages(),
-            self.backend().roles(),
-            message_id,
-            |message| message.mention_roles.into_iter(),
-        )
+        utils::stream(self.get(message_id), self.backend(), B::roles, |message| {
+            message.mention_roles.into_iter()
+        })
     }
 
     fn mentions(&self, message_id: MessageId) -> ListEntitiesFuture<'_, UserEntity, B::Error> {
-        utils::stream(
-            self.backend().messages(),
-            self.backend().users(),
-            message_id,
-            |message| message.mentions.into_iter(),
-        )
+        utils::stream(self.get(message_id), self.backend(), B::users, |message| {
+            message.mentions.into_iter()
+        })
+    }
+
+    /// Retrieve a stream of the messages in a channel.
+    ///
+    /// **Backend implementations**: the default implementation filters the
+    /// messages from [`list`]. Backends indexing messages by their channel
+    /// should implement this manually, returning messages from oldest to
+    /// newest.
+    ///
+    /// [`list`]: ../../repository/trait.Repository.html#tymethod.list
+    fn list_by_channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> ListEntitiesFuture<'_, MessageEntity, B::Error> {
+        self.list()
+            .map_ok(move |messages| {
+                messages
+                    .try_filter(move |message| future::ready(message.channel_id == channel_id))
+                    .boxed()
+            })
+            .boxed()
     }
+
+    /// Retrieve a stream of the cached crossposts of an announcement, which
+    /// are the messages posted in channels following its news channel.
+    ///
+    /// **Backend implementations**: the default implementation filters the
+    /// messages from [`list`]. Backends indexing messages by the message they
+    /// reference may implement this manually.
+    ///
+    /// [`list`]: ../../repository/trait.Repository.html#tymethod.list
+    fn crossposts_of(
+        &self,
+        message_id: MessageId,
+    ) -> ListEntitiesFuture<'_, MessageEntity, B::Error> {
+        self.list()
+            .map_ok(move |messages| {
+                messages
+                    .try_filter(move |message| {
+                        let source = message
+                            .reference
+                            .as_ref()
+                            .and_then(|reference| reference.message_id);
+
+                        future::ready(message.is_crosspost() && source == Some(message_id))
+                    })
+                    .boxed()
+            })
+            .boxed()
+    }
+
+    /// Retrieve a stream of the messages in a channel that aren't [system
+    /// messages].
+    ///
+    /// System messages, such as notices of members joining, take up room in
+    /// the bounded message cache but are rarely of interest to bots.
+    ///
+    /// [system messages]: struct.MessageEntity.html#method.is_system
+    fn non_system(&self, channel_id: ChannelId) -> ListEntitiesFuture<'_, MessageEntity, B::Error> {
+        self.list_by_channel(channel_id)
+            .map_ok(|messages| {
+                messages
+                    .try_filter(|message| future::ready(!message.is_system()))
+                    .boxed()
+            })
+            .boxed()
+    }
+}
+
+/// Retrieve the channel that a message was sent in, which may be a text
+/// channel, private channel, or group.
+async fn message_channel<B: Backend>(
+    backend: &B,
+    channel_id: ChannelId,
+) -> Result<Option<ChannelEntity>, B::Error> {
+    let text_channels = backend.text_channels();
+
+    if let Some(channel) = text_channels.get(channel_id).await? {
+        return Ok(Some(ChannelEntity::Guild(GuildChannelEntity::Text(
+            channel,
+        ))));
+    }
+
+    let private_channels = backend.private_channels();
+
+    if let Some(channel) = private_channels.get(channel_id).await? {
+        return Ok(Some(ChannelEntity::Private(channel)));
+    }
+
+    let groups = backend.groups();
+
+    if let Some(channel) = groups.get(channel_id).await? {
+        return Ok(Some(ChannelEntity::Group(channel)));
+    }
+
+    Ok(None)
 }
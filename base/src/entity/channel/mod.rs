@@ -9,13 +9,27 @@ pub mod text_channel;
 pub mod voice_channel;
 
 pub use self::{
-    attachment::{AttachmentEntity, AttachmentRepository},
-    category_channel::{CategoryChannelEntity, CategoryChannelRepository},
-    group::{GroupEntity, GroupRepository},
-    message::{MessageEntity, MessageRepository},
-    private_channel::{PrivateChannelEntity, PrivateChannelRepository},
-    text_channel::{TextChannelEntity, TextChannelRepository},
-    voice_channel::{VoiceChannelEntity, VoiceChannelRepository},
+    attachment::{AttachmentEntity, AttachmentEntityBuilder, AttachmentRepository},
+    category_channel::{
+        CategoryChannelEntity, CategoryChannelEntityBuilder, CategoryChannelRepository,
+    },
+    group::{GroupEntity, GroupEntityBuilder, GroupRepository},
+    message::{MessageContext, MessageEntity, MessageEntityBuilder, MessageRepository},
+    private_channel::{
+        PrivateChannelEntity, PrivateChannelEntityBuilder, PrivateChannelRepository,
+    },
+    text_channel::{TextChannelEntity, TextChannelEntityBuilder, TextChannelRepository},
+    voice_channel::{VoiceChannelEntity, VoiceChannelEntityBuilder, VoiceChannelRepository},
+};
+
+use super::guild::MemberEntity;
+use crate::{
+    repository::{GetEntityFuture, Repository},
+    Backend,
+};
+use twilight_model::{
+    channel::permission_overwrite::{PermissionOverwrite, PermissionOverwriteType},
+    id::{ChannelId, GuildId, RoleId, UserId},
 };
 
 #[cfg_attr(
@@ -41,3 +55,118 @@ pub enum GuildChannelEntity {
     Text(TextChannelEntity),
     Voice(VoiceChannelEntity),
 }
+
+impl GuildChannelEntity {
+    /// Return the ID of the channel.
+    pub fn id(&self) -> ChannelId {
+        match self {
+            Self::Category(c) => c.id,
+            Self::Text(c) => c.id,
+            Self::Voice(c) => c.id,
+        }
+    }
+
+    /// Return the position of the channel within its guild.
+    pub fn position(&self) -> i64 {
+        match self {
+            Self::Category(c) => c.position,
+            Self::Text(c) => c.position,
+            Self::Voice(c) => c.position,
+        }
+    }
+}
+
+/// Find the overwrite targeting a member or role among a channel's permission
+/// overwrites.
+fn find_overwrite(
+    overwrites: Vec<PermissionOverwrite>,
+    target: PermissionOverwriteType,
+) -> Option<PermissionOverwrite> {
+    overwrites
+        .into_iter()
+        .find(|overwrite| overwrite.kind == target)
+}
+
+/// Resolve a guild channel, and then the channel's permission overwrites that
+/// apply to a cached member.
+///
+/// `f` returns the guild ID and permission overwrites of the channel.
+fn member_overwrites<'a, B: Backend + 'a, C: Send + 'a>(
+    channel: GetEntityFuture<'a, C, B::Error>,
+    backend: &'a B,
+    user_id: UserId,
+    f: fn(C) -> (Option<GuildId>, Vec<PermissionOverwrite>),
+) -> GetEntityFuture<'a, Vec<PermissionOverwrite>, B::Error> {
+    Box::pin(async move {
+        let Some((Some(guild_id), overwrites)) = channel.await?.map(f) else {
+            return Ok(None);
+        };
+
+        let members = backend.members();
+
+        let Some(member) = members.get((guild_id, user_id)).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(applicable_overwrites(overwrites, guild_id, &member)))
+    })
+}
+
+/// Resolve a guild channel and its parent category, and then whether the
+/// channel's permission overwrites are synced with the category's.
+///
+/// `f` returns the parent ID and permission overwrites of the channel.
+fn synced_with_parent<'a, B: Backend + 'a, C: Send + 'a>(
+    channel: GetEntityFuture<'a, C, B::Error>,
+    backend: &'a B,
+    f: fn(C) -> (Option<ChannelId>, Vec<PermissionOverwrite>),
+) -> GetEntityFuture<'a, bool, B::Error> {
+    Box::pin(async move {
+        let Some((Some(parent_id), overwrites)) = channel.await?.map(f) else {
+            return Ok(None);
+        };
+
+        let categories = backend.category_channels();
+
+        let Some(parent) = categories.get(parent_id).await? else {
+            return Ok(None);
+        };
+
+        let synced = overwrites.len() == parent.permission_overwrites.len()
+            && overwrites
+                .iter()
+                .all(|overwrite| parent.permission_overwrites.contains(overwrite));
+
+        Ok(Some(synced))
+    })
+}
+
+/// Filter a channel's permission overwrites to those that apply to a member,
+/// in the order that Discord applies them: the `@everyone` role's overwrite,
+/// then the overwrites of the member's roles, then the member's own
+/// overwrite.
+fn applicable_overwrites(
+    overwrites: Vec<PermissionOverwrite>,
+    guild_id: GuildId,
+    member: &MemberEntity,
+) -> Vec<PermissionOverwrite> {
+    let everyone = RoleId(guild_id.0);
+    let (mut applicable, rest): (Vec<_>, Vec<_>) = overwrites
+        .into_iter()
+        .filter(|overwrite| match overwrite.kind {
+            PermissionOverwriteType::Member(user_id) => user_id == member.user_id,
+            PermissionOverwriteType::Role(role_id) => {
+                role_id == everyone || member.role_ids.contains(&role_id)
+            }
+        })
+        .partition(|overwrite| overwrite.kind == PermissionOverwriteType::Role(everyone));
+
+    let (own, roles): (Vec<_>, Vec<_>) = rest
+        .into_iter()
+        .partition(|overwrite| matches!(overwrite.kind, PermissionOverwriteType::Member(_)));
+
+    applicable.extend(roles);
+    applicable.extend(own);
+
+    applicable
+}
@@ -1,6 +1,9 @@
 use super::{super::user::UserEntity, MessageEntity};
+#[cfg(feature = "time")]
+use crate::timestamp::{OffsetDateTime, ParseTimestampError};
 use crate::{
     repository::{GetEntityFuture, Repository},
+    timestamp::ParsedTimestamp,
     utils, Backend, Entity,
 };
 use twilight_model::{
@@ -10,11 +13,25 @@ use twilight_model::{
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct PrivateChannelEntity {
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: ChannelId,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub last_message_id: Option<MessageId>,
     pub last_pin_timestamp: Option<String>,
+    /// Cache of the parsed `last_pin_timestamp`.
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub parsed_last_pin_timestamp: ParsedTimestamp,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub kind: ChannelType,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub recipient_id: Option<UserId>,
 }
 
@@ -26,12 +43,34 @@ impl From<PrivateChannel> for PrivateChannelEntity {
             id: channel.id,
             last_message_id: channel.last_message_id,
             last_pin_timestamp: channel.last_pin_timestamp,
+            parsed_last_pin_timestamp: ParsedTimestamp::default(),
             kind: channel.kind,
             recipient_id,
         }
     }
 }
 
+impl PrivateChannelEntity {
+    /// Create a builder for a private channel with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(id: ChannelId) -> PrivateChannelEntityBuilder {
+        PrivateChannelEntityBuilder::new(id)
+    }
+
+    /// Parse the date a message was last pinned in the channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored timestamp is invalid.
+    #[cfg(feature = "time")]
+    pub fn last_pin_timestamp_datetime(
+        &self,
+    ) -> Result<Option<OffsetDateTime>, ParseTimestampError> {
+        self.parsed_last_pin_timestamp
+            .get_optional(self.last_pin_timestamp.as_deref())
+    }
+}
+
 impl Entity for PrivateChannelEntity {
     type Id = ChannelId;
 
@@ -41,25 +80,69 @@ impl Entity for PrivateChannelEntity {
     }
 }
 
+/// Builder to create a [`PrivateChannelEntity`] without the need to specify every field.
+///
+/// [`PrivateChannelEntity`]: struct.PrivateChannelEntity.html
+#[derive(Clone, Debug)]
+pub struct PrivateChannelEntityBuilder(PrivateChannelEntity);
+
+impl PrivateChannelEntityBuilder {
+    pub fn new(id: ChannelId) -> Self {
+        Self(PrivateChannelEntity {
+            id,
+            last_message_id: None,
+            last_pin_timestamp: None,
+            parsed_last_pin_timestamp: ParsedTimestamp::default(),
+            kind: ChannelType::Private,
+            recipient_id: None,
+        })
+    }
+
+    pub fn build(self) -> PrivateChannelEntity {
+        self.0
+    }
+
+    pub fn last_message_id(&mut self, last_message_id: MessageId) -> &mut Self {
+        self.0.last_message_id = Some(last_message_id);
+
+        self
+    }
+
+    pub fn last_pin_timestamp(&mut self, last_pin_timestamp: impl Into<String>) -> &mut Self {
+        self.0.last_pin_timestamp = Some(last_pin_timestamp.into());
+
+        self
+    }
+
+    pub fn kind(&mut self, kind: ChannelType) -> &mut Self {
+        self.0.kind = kind;
+
+        self
+    }
+
+    pub fn recipient_id(&mut self, recipient_id: UserId) -> &mut Self {
+        self.0.recipient_id = Some(recipient_id);
+
+        self
+    }
+}
+
 /// Repository to work with guild channels and their associated entities.
 pub trait PrivateChannelRepository<B: Backend>: Repository<PrivateChannelEntity, B> {
     /// Retrieve the last message of a private channel.
     fn last_message(&self, channel_id: ChannelId) -> GetEntityFuture<'_, MessageEntity, B::Error> {
         utils::relation_and_then(
-            self.backend().private_channels(),
-            self.backend().messages(),
-            channel_id,
+            self.get(channel_id),
+            self.backend(),
+            B::messages,
             |channel| channel.last_message_id,
         )
     }
 
     /// Retrieve the recipient user associated with a private channel.
     fn recipient(&self, channel_id: ChannelId) -> GetEntityFuture<'_, UserEntity, B::Error> {
-        utils::relation_and_then(
-            self.backend().private_channels(),
-            self.backend().users(),
-            channel_id,
-            |channel| channel.recipient_id,
-        )
+        utils::relation_and_then(self.get(channel_id), self.backend(), B::users, |channel| {
+            channel.recipient_id
+        })
     }
 }
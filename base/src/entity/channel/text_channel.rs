@@ -1,24 +1,50 @@
-use super::{super::guild::GuildEntity, CategoryChannelEntity, MessageEntity};
+use super::{
+    super::guild::{GuildEntity, GuildRepository},
+    CategoryChannelEntity, MessageEntity,
+};
+#[cfg(feature = "time")]
+use crate::timestamp::{OffsetDateTime, ParseTimestampError};
 use crate::{
-    repository::{GetEntityFuture, Repository},
+    repository::{GetEntityFuture, ListEntitiesFuture, Repository},
+    timestamp::ParsedTimestamp,
     utils, Backend, Entity,
 };
+use futures_util::future::TryFutureExt;
 use twilight_model::{
-    channel::{permission_overwrite::PermissionOverwrite, ChannelType, TextChannel},
-    id::{ChannelId, GuildId, MessageId},
+    channel::{
+        permission_overwrite::{PermissionOverwrite, PermissionOverwriteType},
+        ChannelType, TextChannel,
+    },
+    id::{ChannelId, GuildId, MessageId, UserId},
 };
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct TextChannelEntity {
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub guild_id: Option<GuildId>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: ChannelId,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub kind: ChannelType,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub last_message_id: Option<MessageId>,
     pub last_pin_timestamp: Option<String>,
+    /// Cache of the parsed `last_pin_timestamp`.
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub parsed_last_pin_timestamp: ParsedTimestamp,
     pub name: String,
     pub nsfw: bool,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub permission_overwrites: Vec<PermissionOverwrite>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub parent_id: Option<ChannelId>,
     pub position: i64,
     pub rate_limit_per_user: Option<u64>,
@@ -33,6 +59,7 @@ impl From<TextChannel> for TextChannelEntity {
             kind: channel.kind,
             last_message_id: channel.last_message_id,
             last_pin_timestamp: channel.last_pin_timestamp,
+            parsed_last_pin_timestamp: ParsedTimestamp::default(),
             name: channel.name,
             nsfw: channel.nsfw,
             permission_overwrites: channel.permission_overwrites,
@@ -44,6 +71,35 @@ impl From<TextChannel> for TextChannelEntity {
     }
 }
 
+impl TextChannelEntity {
+    /// Create a builder for a text channel with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(id: ChannelId, name: impl Into<String>) -> TextChannelEntityBuilder {
+        TextChannelEntityBuilder::new(id, name)
+    }
+
+    /// Return whether the channel is a news channel, whose messages can be
+    /// crossposted to the channels following it.
+    ///
+    /// News channels are cached as text channels of the `GuildNews` kind.
+    pub fn is_news(&self) -> bool {
+        self.kind == ChannelType::GuildNews
+    }
+
+    /// Parse the date a message was last pinned in the channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored timestamp is invalid.
+    #[cfg(feature = "time")]
+    pub fn last_pin_timestamp_datetime(
+        &self,
+    ) -> Result<Option<OffsetDateTime>, ParseTimestampError> {
+        self.parsed_last_pin_timestamp
+            .get_optional(self.last_pin_timestamp.as_deref())
+    }
+}
+
 impl Entity for TextChannelEntity {
     type Id = ChannelId;
 
@@ -53,38 +109,211 @@ impl Entity for TextChannelEntity {
     }
 }
 
+/// Builder to create a [`TextChannelEntity`] without the need to specify every field.
+///
+/// [`TextChannelEntity`]: struct.TextChannelEntity.html
+#[derive(Clone, Debug)]
+pub struct TextChannelEntityBuilder(TextChannelEntity);
+
+impl TextChannelEntityBuilder {
+    pub fn new(id: ChannelId, name: impl Into<String>) -> Self {
+        Self(TextChannelEntity {
+            guild_id: None,
+            id,
+            kind: ChannelType::GuildText,
+            last_message_id: None,
+            last_pin_timestamp: None,
+            parsed_last_pin_timestamp: ParsedTimestamp::default(),
+            name: name.into(),
+            nsfw: false,
+            permission_overwrites: Vec::new(),
+            parent_id: None,
+            position: 0,
+            rate_limit_per_user: None,
+            topic: None,
+        })
+    }
+
+    pub fn build(self) -> TextChannelEntity {
+        self.0
+    }
+
+    pub fn guild_id(&mut self, guild_id: GuildId) -> &mut Self {
+        self.0.guild_id = Some(guild_id);
+
+        self
+    }
+
+    pub fn kind(&mut self, kind: ChannelType) -> &mut Self {
+        self.0.kind = kind;
+
+        self
+    }
+
+    pub fn last_message_id(&mut self, last_message_id: MessageId) -> &mut Self {
+        self.0.last_message_id = Some(last_message_id);
+
+        self
+    }
+
+    pub fn last_pin_timestamp(&mut self, last_pin_timestamp: impl Into<String>) -> &mut Self {
+        self.0.last_pin_timestamp = Some(last_pin_timestamp.into());
+
+        self
+    }
+
+    pub fn nsfw(&mut self, nsfw: bool) -> &mut Self {
+        self.0.nsfw = nsfw;
+
+        self
+    }
+
+    pub fn permission_overwrites(
+        &mut self,
+        permission_overwrites: Vec<PermissionOverwrite>,
+    ) -> &mut Self {
+        self.0.permission_overwrites = permission_overwrites;
+
+        self
+    }
+
+    pub fn parent_id(&mut self, parent_id: ChannelId) -> &mut Self {
+        self.0.parent_id = Some(parent_id);
+
+        self
+    }
+
+    pub fn position(&mut self, position: i64) -> &mut Self {
+        self.0.position = position;
+
+        self
+    }
+
+    pub fn rate_limit_per_user(&mut self, rate_limit_per_user: u64) -> &mut Self {
+        self.0.rate_limit_per_user = Some(rate_limit_per_user);
+
+        self
+    }
+
+    pub fn topic(&mut self, topic: impl Into<String>) -> &mut Self {
+        self.0.topic = Some(topic.into());
+
+        self
+    }
+}
+
 /// Repository to work with guild text channels and their associated entities.
 pub trait TextChannelRepository<B: Backend>: Repository<TextChannelEntity, B> {
     /// Retrieve the guild associated with a guild text channel.
     fn guild(&self, channel_id: ChannelId) -> GetEntityFuture<'_, GuildEntity, B::Error> {
-        utils::relation_and_then(
-            self.backend().text_channels(),
-            self.backend().guilds(),
-            channel_id,
-            |channel| channel.guild_id,
-        )
+        utils::relation_and_then(self.get(channel_id), self.backend(), B::guilds, |channel| {
+            channel.guild_id
+        })
+    }
+
+    /// Retrieve the permission overwrite of a text channel targeting a
+    /// member or role.
+    ///
+    /// Backend implementations should return `None` if the channel isn't
+    /// cached or has no overwrite for the target.
+    fn overwrite_for(
+        &self,
+        channel_id: ChannelId,
+        target: PermissionOverwriteType,
+    ) -> GetEntityFuture<'_, PermissionOverwrite, B::Error> {
+        Box::pin(self.get(channel_id).map_ok(move |channel| {
+            channel.and_then(|channel| super::find_overwrite(channel.permission_overwrites, target))
+        }))
+    }
+
+    /// Retrieve the permission overwrites of a text channel that apply to a
+    /// member, in the order that Discord applies them.
+    ///
+    /// The `@everyone` role's overwrite comes first, followed by the
+    /// overwrites of the member's roles and then the member's own overwrite,
+    /// which is what permission calculators expect.
+    ///
+    /// Backend implementations should return `None` if the channel or member
+    /// isn't cached.
+    fn effective_overwrites(
+        &self,
+        channel_id: ChannelId,
+        user_id: UserId,
+    ) -> GetEntityFuture<'_, Vec<PermissionOverwrite>, B::Error> {
+        super::member_overwrites(self.get(channel_id), self.backend(), user_id, |channel| {
+            (channel.guild_id, channel.permission_overwrites)
+        })
     }
 
     /// Retrieve the last message of a text channel.
     fn last_message(&self, channel_id: ChannelId) -> GetEntityFuture<'_, MessageEntity, B::Error> {
         utils::relation_and_then(
-            self.backend().text_channels(),
-            self.backend().messages(),
-            channel_id,
+            self.get(channel_id),
+            self.backend(),
+            B::messages,
             |channel| channel.last_message_id,
         )
     }
 
+    /// Retrieve whether the permission overwrites of a text channel are synced
+    /// with its parent category's.
+    ///
+    /// Discord copies a category's overwrites to its channels when they're
+    /// synced, so a channel's own overwrites are always the ones in effect.
+    /// They're synced while they're the same as the category's, in any
+    /// order, which is what clients show as the channel being synced.
+    ///
+    /// Backend implementations should return `None` if the channel isn't
+    /// cached, has no parent, or its parent isn't cached.
+    fn is_synced_with_parent(&self, channel_id: ChannelId) -> GetEntityFuture<'_, bool, B::Error> {
+        super::synced_with_parent(self.get(channel_id), self.backend(), |channel| {
+            (channel.parent_id, channel.permission_overwrites)
+        })
+    }
+
+    /// Retrieve the slowmode of a text channel, which is the number of
+    /// seconds that users have to wait between sending messages.
+    ///
+    /// Backend implementations should return `None` if the channel isn't
+    /// cached or has no slowmode.
+    fn slowmode(&self, channel_id: ChannelId) -> GetEntityFuture<'_, u64, B::Error> {
+        Box::pin(self.get(channel_id).map_ok(|channel| {
+            channel
+                .and_then(|channel| channel.rate_limit_per_user)
+                .filter(|&seconds| seconds > 0)
+        }))
+    }
+
     /// Retrieve the parent category channel of the voice channel.
     fn parent(
         &self,
         channel_id: ChannelId,
     ) -> GetEntityFuture<'_, CategoryChannelEntity, B::Error> {
         utils::relation_and_then(
-            self.backend().text_channels(),
-            self.backend().category_channels(),
-            channel_id,
+            self.get(channel_id),
+            self.backend(),
+            B::category_channels,
             |channel| channel.parent_id,
         )
     }
+
+    /// Retrieve a stream of text channels within a guild.
+    ///
+    /// **Backend implementations**: the default implementation retrieves the
+    /// IDs from [`GuildRepository::channel_ids`]. Backends storing entities
+    /// by keys prefixed with their guild ID can instead scan the keys.
+    ///
+    /// [`GuildRepository::channel_ids`]: ../guild/trait.GuildRepository.html#tymethod.channel_ids
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, TextChannelEntity, B::Error> {
+        utils::stream_guild(
+            self.backend(),
+            guild_id,
+            |guilds, guild_id| guilds.channel_ids(guild_id),
+            |_, channel_id| channel_id,
+            B::text_channels,
+        )
+    }
 }
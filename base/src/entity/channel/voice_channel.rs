@@ -1,24 +1,48 @@
-use super::{super::guild::GuildEntity, CategoryChannelEntity};
+use super::{
+    super::{
+        guild::{GuildEntity, GuildRepository},
+        voice::{VoiceRegionEntity, VoiceRegionId},
+    },
+    CategoryChannelEntity,
+};
 use crate::{
-    repository::{GetEntityFuture, Repository},
+    repository::{GetEntityFuture, ListEntitiesFuture, Repository},
     utils, Backend, Entity,
 };
+use futures_util::future::TryFutureExt;
 use twilight_model::{
-    channel::{permission_overwrite::PermissionOverwrite, ChannelType, VoiceChannel},
-    id::{ChannelId, GuildId},
+    channel::{
+        permission_overwrite::{PermissionOverwrite, PermissionOverwriteType},
+        ChannelType, VoiceChannel,
+    },
+    id::{ChannelId, GuildId, UserId},
 };
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct VoiceChannelEntity {
     pub bitrate: u64,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub guild_id: Option<GuildId>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: ChannelId,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub kind: ChannelType,
     pub name: String,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub permission_overwrites: Vec<PermissionOverwrite>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub parent_id: Option<ChannelId>,
     pub position: i64,
+    /// Name of the voice region of the channel, such as `us-east`, or `None`
+    /// if Discord picks the region automatically.
+    pub rtc_region: Option<String>,
     pub user_limit: Option<u64>,
 }
 
@@ -33,11 +57,20 @@ impl From<VoiceChannel> for VoiceChannelEntity {
             permission_overwrites: channel.permission_overwrites,
             parent_id: channel.parent_id,
             position: channel.position,
+            rtc_region: channel.rtc_region,
             user_limit: channel.user_limit,
         }
     }
 }
 
+impl VoiceChannelEntity {
+    /// Create a builder for a voice channel with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(id: ChannelId, name: impl Into<String>) -> VoiceChannelEntityBuilder {
+        VoiceChannelEntityBuilder::new(id, name)
+    }
+}
+
 impl Entity for VoiceChannelEntity {
     type Id = ChannelId;
 
@@ -47,16 +80,141 @@ impl Entity for VoiceChannelEntity {
     }
 }
 
+/// Builder to create a [`VoiceChannelEntity`] without the need to specify every field.
+///
+/// [`VoiceChannelEntity`]: struct.VoiceChannelEntity.html
+#[derive(Clone, Debug)]
+pub struct VoiceChannelEntityBuilder(VoiceChannelEntity);
+
+impl VoiceChannelEntityBuilder {
+    pub fn new(id: ChannelId, name: impl Into<String>) -> Self {
+        Self(VoiceChannelEntity {
+            bitrate: 64_000,
+            guild_id: None,
+            id,
+            kind: ChannelType::GuildVoice,
+            name: name.into(),
+            permission_overwrites: Vec::new(),
+            parent_id: None,
+            position: 0,
+            rtc_region: None,
+            user_limit: None,
+        })
+    }
+
+    pub fn build(self) -> VoiceChannelEntity {
+        self.0
+    }
+
+    pub fn bitrate(&mut self, bitrate: u64) -> &mut Self {
+        self.0.bitrate = bitrate;
+
+        self
+    }
+
+    pub fn guild_id(&mut self, guild_id: GuildId) -> &mut Self {
+        self.0.guild_id = Some(guild_id);
+
+        self
+    }
+
+    pub fn kind(&mut self, kind: ChannelType) -> &mut Self {
+        self.0.kind = kind;
+
+        self
+    }
+
+    pub fn permission_overwrites(
+        &mut self,
+        permission_overwrites: Vec<PermissionOverwrite>,
+    ) -> &mut Self {
+        self.0.permission_overwrites = permission_overwrites;
+
+        self
+    }
+
+    pub fn parent_id(&mut self, parent_id: ChannelId) -> &mut Self {
+        self.0.parent_id = Some(parent_id);
+
+        self
+    }
+
+    pub fn position(&mut self, position: i64) -> &mut Self {
+        self.0.position = position;
+
+        self
+    }
+
+    pub fn rtc_region(&mut self, rtc_region: impl Into<String>) -> &mut Self {
+        self.0.rtc_region = Some(rtc_region.into());
+
+        self
+    }
+
+    pub fn user_limit(&mut self, user_limit: u64) -> &mut Self {
+        self.0.user_limit = Some(user_limit);
+
+        self
+    }
+}
+
 /// Repository to work with guild voice channels and their associated entities.
 pub trait VoiceChannelRepository<B: Backend>: Repository<VoiceChannelEntity, B> {
     /// Retrieve the guild associated with a guild voice channel.
     fn guild(&self, channel_id: ChannelId) -> GetEntityFuture<'_, GuildEntity, B::Error> {
-        utils::relation_and_then(
-            self.backend().voice_channels(),
-            self.backend().guilds(),
-            channel_id,
-            |channel| channel.guild_id,
-        )
+        utils::relation_and_then(self.get(channel_id), self.backend(), B::guilds, |channel| {
+            channel.guild_id
+        })
+    }
+
+    /// Retrieve the permission overwrite of a voice channel targeting a
+    /// member or role.
+    ///
+    /// Backend implementations should return `None` if the channel isn't
+    /// cached or has no overwrite for the target.
+    fn overwrite_for(
+        &self,
+        channel_id: ChannelId,
+        target: PermissionOverwriteType,
+    ) -> GetEntityFuture<'_, PermissionOverwrite, B::Error> {
+        Box::pin(self.get(channel_id).map_ok(move |channel| {
+            channel.and_then(|channel| super::find_overwrite(channel.permission_overwrites, target))
+        }))
+    }
+
+    /// Retrieve the permission overwrites of a voice channel that apply to a
+    /// member, in the order that Discord applies them.
+    ///
+    /// The `@everyone` role's overwrite comes first, followed by the
+    /// overwrites of the member's roles and then the member's own overwrite,
+    /// which is what permission calculators expect.
+    ///
+    /// Backend implementations should return `None` if the channel or member
+    /// isn't cached.
+    fn effective_overwrites(
+        &self,
+        channel_id: ChannelId,
+        user_id: UserId,
+    ) -> GetEntityFuture<'_, Vec<PermissionOverwrite>, B::Error> {
+        super::member_overwrites(self.get(channel_id), self.backend(), user_id, |channel| {
+            (channel.guild_id, channel.permission_overwrites)
+        })
+    }
+
+    /// Retrieve whether the permission overwrites of a voice channel are synced
+    /// with its parent category's.
+    ///
+    /// Discord copies a category's overwrites to its channels when they're
+    /// synced, so a channel's own overwrites are always the ones in effect.
+    /// They're synced while they're the same as the category's, in any
+    /// order, which is what clients show as the channel being synced.
+    ///
+    /// Backend implementations should return `None` if the channel isn't
+    /// cached, has no parent, or its parent isn't cached.
+    fn is_synced_with_parent(&self, channel_id: ChannelId) -> GetEntityFuture<'_, bool, B::Error> {
+        super::synced_with_parent(self.get(channel_id), self.backend(), |channel| {
+            (channel.parent_id, channel.permission_overwrites)
+        })
     }
 
     /// Retrieve the parent category channel of the voice channel.
@@ -65,10 +223,47 @@ pub trait VoiceChannelRepository<B: Backend>: Repository<VoiceChannelEntity, B>
         channel_id: ChannelId,
     ) -> GetEntityFuture<'_, CategoryChannelEntity, B::Error> {
         utils::relation_and_then(
-            self.backend().voice_channels(),
-            self.backend().category_channels(),
-            channel_id,
+            self.get(channel_id),
+            self.backend(),
+            B::category_channels,
             |channel| channel.parent_id,
         )
     }
+
+    /// Retrieve the voice region of a voice channel.
+    ///
+    /// Backend implementations should return `None` if the channel isn't
+    /// cached, its region is picked automatically, or its region isn't
+    /// cached.
+    fn rtc_region(
+        &self,
+        channel_id: ChannelId,
+    ) -> GetEntityFuture<'_, VoiceRegionEntity, B::Error> {
+        utils::relation_and_then(
+            self.get(channel_id),
+            self.backend(),
+            B::voice_regions,
+            |channel| channel.rtc_region.as_deref().map(VoiceRegionId::new),
+        )
+    }
+
+    /// Retrieve a stream of voice channels within a guild.
+    ///
+    /// **Backend implementations**: the default implementation retrieves the
+    /// IDs from [`GuildRepository::channel_ids`]. Backends storing entities
+    /// by keys prefixed with their guild ID can instead scan the keys.
+    ///
+    /// [`GuildRepository::channel_ids`]: ../guild/trait.GuildRepository.html#tymethod.channel_ids
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceChannelEntity, B::Error> {
+        utils::stream_guild(
+            self.backend(),
+            guild_id,
+            |guilds, guild_id| guilds.channel_ids(guild_id),
+            |_, channel_id| channel_id,
+            B::voice_channels,
+        )
+    }
 }
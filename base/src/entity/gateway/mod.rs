@@ -2,4 +2,6 @@
 
 pub mod presence;
 
-pub use self::presence::{PresenceEntity, PresenceRepository};
+pub use self::presence::{
+    ActivityQuery, PresenceEntity, PresenceEntityBuilder, PresenceRepository,
+};
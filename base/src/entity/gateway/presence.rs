@@ -1,19 +1,89 @@
-use crate::{Backend, Entity, Repository};
+use crate::{
+    entity::guild::GuildRepository,
+    repository::{ListEntitiesFuture, ListEntitiesStream},
+    utils, Backend, Entity, Repository,
+};
+use futures_util::{
+    future,
+    stream::{StreamExt, TryStreamExt},
+};
 use twilight_model::{
     gateway::{
-        presence::{Activity, ClientStatus, Presence, Status, UserOrId},
         payload::PresenceUpdate,
+        presence::{Activity, ActivityType, ClientStatus, Presence, Status, UserOrId},
     },
-    id::{GuildId, UserId},
+    id::{ApplicationId, GuildId, UserId},
 };
 
+/// Activity to search for in the activities of presences.
+///
+/// Refer to [`PresenceRepository::by_activity`] for more information.
+///
+/// [`PresenceRepository::by_activity`]: trait.PresenceRepository.html#method.by_activity
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ActivityQuery {
+    /// Activities of the application with the ID, such as a game with Rich
+    /// Presence support.
+    ApplicationId(ApplicationId),
+    /// Activities with the name, such as the name of a game.
+    Name(String),
+}
+
+impl ActivityQuery {
+    /// Return the queries that an activity matches.
+    pub fn keys(activity: &Activity) -> impl Iterator<Item = Self> {
+        activity
+            .application_id
+            .map(Self::ApplicationId)
+            .into_iter()
+            .chain(Some(Self::Name(activity.name.clone())))
+    }
+
+    /// Whether an activity matches the query.
+    pub fn matches(&self, activity: &Activity) -> bool {
+        match self {
+            Self::ApplicationId(id) => activity.application_id == Some(*id),
+            Self::Name(name) => activity.name == *name,
+        }
+    }
+}
+
+impl From<ApplicationId> for ActivityQuery {
+    fn from(application_id: ApplicationId) -> Self {
+        Self::ApplicationId(application_id)
+    }
+}
+
+impl From<&str> for ActivityQuery {
+    fn from(name: &str) -> Self {
+        Self::Name(name.to_owned())
+    }
+}
+
+impl From<String> for ActivityQuery {
+    fn from(name: String) -> Self {
+        Self::Name(name)
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct PresenceEntity {
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub activities: Vec<Activity>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub client_status: ClientStatus,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub guild_id: GuildId,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub status: Status,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub user_id: UserId,
 }
 
@@ -59,6 +129,28 @@ impl From<PresenceUpdate> for PresenceEntity {
     }
 }
 
+impl PresenceEntity {
+    /// Create a builder for a presence with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(guild_id: GuildId, user_id: UserId) -> PresenceEntityBuilder {
+        PresenceEntityBuilder::new(guild_id, user_id)
+    }
+
+    /// Whether any of the user's activities matches a query.
+    pub fn has_activity(&self, query: &ActivityQuery) -> bool {
+        self.activities
+            .iter()
+            .any(|activity| query.matches(activity))
+    }
+
+    /// Whether the user is streaming.
+    pub fn is_streaming(&self) -> bool {
+        self.activities
+            .iter()
+            .any(|activity| activity.kind == ActivityType::Streaming)
+    }
+}
+
 impl Entity for PresenceEntity {
     type Id = (GuildId, UserId);
 
@@ -68,4 +160,92 @@ impl Entity for PresenceEntity {
     }
 }
 
-pub trait PresenceRepository<B: Backend>: Repository<PresenceEntity, B> {}
+/// Builder to create a [`PresenceEntity`] without the need to specify every field.
+///
+/// [`PresenceEntity`]: struct.PresenceEntity.html
+#[derive(Clone, Debug)]
+pub struct PresenceEntityBuilder(PresenceEntity);
+
+impl PresenceEntityBuilder {
+    pub fn new(guild_id: GuildId, user_id: UserId) -> Self {
+        Self(PresenceEntity {
+            activities: Vec::new(),
+            client_status: ClientStatus {
+                desktop: None,
+                mobile: None,
+                web: None,
+            },
+            guild_id,
+            status: Status::Online,
+            user_id,
+        })
+    }
+
+    pub fn build(self) -> PresenceEntity {
+        self.0
+    }
+
+    pub fn activities(&mut self, activities: Vec<Activity>) -> &mut Self {
+        self.0.activities = activities;
+
+        self
+    }
+
+    pub fn client_status(&mut self, client_status: ClientStatus) -> &mut Self {
+        self.0.client_status = client_status;
+
+        self
+    }
+
+    pub fn status(&mut self, status: Status) -> &mut Self {
+        self.0.status = status;
+
+        self
+    }
+}
+
+pub trait PresenceRepository<B: Backend>: Repository<PresenceEntity, B> {
+    /// Retrieve a stream of the presences in a guild with an activity matching
+    /// a query.
+    ///
+    /// This can be used to find the users playing a game, for example to give
+    /// them a "now playing" role.
+    fn by_activity(
+        &self,
+        guild_id: GuildId,
+        query: ActivityQuery,
+    ) -> ListEntitiesFuture<'_, PresenceEntity, B::Error> {
+        filter_by_guild(self.backend(), guild_id, move |presence| {
+            presence.has_activity(&query)
+        })
+    }
+
+    /// Retrieve a stream of the presences in a guild of users that are
+    /// streaming.
+    fn streaming(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, PresenceEntity, B::Error> {
+        filter_by_guild(self.backend(), guild_id, PresenceEntity::is_streaming)
+    }
+}
+
+fn filter_by_guild<'a, B: Backend + 'a>(
+    backend: &'a B,
+    guild_id: GuildId,
+    mut predicate: impl FnMut(&PresenceEntity) -> bool + Send + 'a,
+) -> ListEntitiesFuture<'a, PresenceEntity, B::Error> {
+    let presences = utils::stream_guild(
+        backend,
+        guild_id,
+        |guilds, guild_id| guilds.presence_ids(guild_id),
+        |guild_id, user_id| (guild_id, user_id),
+        B::presences,
+    );
+
+    Box::pin(async move {
+        let stream: ListEntitiesStream<'a, PresenceEntity, B::Error> = presences
+            .await?
+            .try_filter(move |presence| future::ready(predicate(presence)))
+            .boxed();
+
+        Ok(stream)
+    })
+}
@@ -0,0 +1,91 @@
+use super::super::channel::{
+    CategoryChannelEntity, GuildChannelEntity, TextChannelEntity, VoiceChannelEntity,
+};
+use std::collections::HashMap;
+use twilight_model::id::ChannelId;
+
+/// Channels of a guild nested within their categories.
+///
+/// Categories and channels are ordered by their positions, falling back to
+/// their IDs for equal positions, like how Discord clients display them. Text
+/// and voice channels are ordered separately, as clients display voice
+/// channels after text channels.
+///
+/// Retrieved via [`GuildRepository::channel_tree`].
+///
+/// [`GuildRepository::channel_tree`]: ../trait.GuildRepository.html#method.channel_tree
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChannelTree {
+    /// Categories with their child channels.
+    pub categories: Vec<ChannelTreeCategory>,
+    /// Text channels without a cached parent category.
+    pub text_channels: Vec<TextChannelEntity>,
+    /// Voice channels without a cached parent category.
+    pub voice_channels: Vec<VoiceChannelEntity>,
+}
+
+impl ChannelTree {
+    /// Build a tree from the channels of a guild.
+    pub fn from_channels(channels: impl IntoIterator<Item = GuildChannelEntity>) -> Self {
+        let mut categories = Vec::new();
+        let mut text_channels = Vec::new();
+        let mut voice_channels = Vec::new();
+
+        for channel in channels {
+            match channel {
+                GuildChannelEntity::Category(c) => categories.push(ChannelTreeCategory {
+                    category: c,
+                    text_channels: Vec::new(),
+                    voice_channels: Vec::new(),
+                }),
+                GuildChannelEntity::Text(c) => text_channels.push(c),
+                GuildChannelEntity::Voice(c) => voice_channels.push(c),
+            }
+        }
+
+        categories.sort_by_key(|node| (node.category.position, node.category.id));
+
+        let indexes = categories
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| (node.category.id, idx))
+            .collect::<HashMap<ChannelId, usize>>();
+        let mut tree = Self {
+            categories,
+            text_channels: Vec::new(),
+            voice_channels: Vec::new(),
+        };
+
+        text_channels.sort_by_key(|c| (c.position, c.id));
+        voice_channels.sort_by_key(|c| (c.position, c.id));
+
+        for channel in text_channels {
+            match channel.parent_id.and_then(|id| indexes.get(&id)) {
+                Some(idx) => tree.categories[*idx].text_channels.push(channel),
+                None => tree.text_channels.push(channel),
+            }
+        }
+
+        for channel in voice_channels {
+            match channel.parent_id.and_then(|id| indexes.get(&id)) {
+                Some(idx) => tree.categories[*idx].voice_channels.push(channel),
+                None => tree.voice_channels.push(channel),
+            }
+        }
+
+        tree
+    }
+}
+
+/// Category channel with its child channels.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelTreeCategory {
+    /// The category channel.
+    pub category: CategoryChannelEntity,
+    /// Text channels within the category.
+    pub text_channels: Vec<TextChannelEntity>,
+    /// Voice channels within the category.
+    pub voice_channels: Vec<VoiceChannelEntity>,
+}
@@ -1,8 +1,15 @@
-use super::{super::user::UserEntity, GuildEntity, RoleEntity};
+use super::{super::user::UserEntity, GuildEntity, GuildRepository, RoleEntity};
+#[cfg(feature = "emoji-usage")]
+use crate::repository::UpsertEntityFuture;
 use crate::{
     repository::{GetEntityFuture, ListEntitiesFuture, Repository},
     utils, Backend, Entity,
 };
+#[cfg(feature = "emoji-usage")]
+use futures_util::{
+    future::{self, FutureExt},
+    stream::{self, StreamExt},
+};
 use twilight_model::{
     guild::Emoji,
     id::{EmojiId, GuildId, RoleId, UserId},
@@ -12,15 +19,25 @@ use twilight_model::{
 #[allow(clippy::struct_excessive_bools)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct EmojiEntity {
     pub animated: bool,
     pub available: bool,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub guild_id: GuildId,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: EmojiId,
     pub managed: bool,
     pub name: String,
     pub require_colons: bool,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub role_ids: Vec<RoleId>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub user_id: Option<UserId>,
 }
 
@@ -42,6 +59,14 @@ impl From<(GuildId, Emoji)> for EmojiEntity {
     }
 }
 
+impl EmojiEntity {
+    /// Create a builder for an emoji with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(id: EmojiId, guild_id: GuildId, name: impl Into<String>) -> EmojiEntityBuilder {
+        EmojiEntityBuilder::new(id, guild_id, name)
+    }
+}
+
 impl Entity for EmojiEntity {
     type Id = EmojiId;
 
@@ -51,34 +76,138 @@ impl Entity for EmojiEntity {
     }
 }
 
+/// Builder to create an [`EmojiEntity`] without the need to specify every field.
+///
+/// [`EmojiEntity`]: struct.EmojiEntity.html
+#[derive(Clone, Debug)]
+pub struct EmojiEntityBuilder(EmojiEntity);
+
+impl EmojiEntityBuilder {
+    pub fn new(id: EmojiId, guild_id: GuildId, name: impl Into<String>) -> Self {
+        Self(EmojiEntity {
+            animated: false,
+            available: true,
+            guild_id,
+            id,
+            managed: false,
+            name: name.into(),
+            require_colons: true,
+            role_ids: Vec::new(),
+            user_id: None,
+        })
+    }
+
+    pub fn build(self) -> EmojiEntity {
+        self.0
+    }
+
+    pub fn animated(&mut self, animated: bool) -> &mut Self {
+        self.0.animated = animated;
+
+        self
+    }
+
+    pub fn available(&mut self, available: bool) -> &mut Self {
+        self.0.available = available;
+
+        self
+    }
+
+    pub fn managed(&mut self, managed: bool) -> &mut Self {
+        self.0.managed = managed;
+
+        self
+    }
+
+    pub fn require_colons(&mut self, require_colons: bool) -> &mut Self {
+        self.0.require_colons = require_colons;
+
+        self
+    }
+
+    pub fn role_ids(&mut self, role_ids: Vec<RoleId>) -> &mut Self {
+        self.0.role_ids = role_ids;
+
+        self
+    }
+
+    pub fn user_id(&mut self, user_id: UserId) -> &mut Self {
+        self.0.user_id = Some(user_id);
+
+        self
+    }
+}
+
 pub trait EmojiRepository<B: Backend>: Repository<EmojiEntity, B> {
     /// Retrieve the guild associated with an emoji.
     fn guild(&self, emoji_id: EmojiId) -> GetEntityFuture<'_, GuildEntity, B::Error> {
-        utils::relation_map(
-            self.backend().emojis(),
-            self.backend().guilds(),
-            emoji_id,
-            |emoji| emoji.guild_id,
-        )
+        utils::relation_map(self.get(emoji_id), self.backend(), B::guilds, |emoji| {
+            emoji.guild_id
+        })
     }
 
     /// Retrieve a stream of roles associated with an emoji.
     fn roles(&self, emoji_id: EmojiId) -> ListEntitiesFuture<'_, RoleEntity, B::Error> {
-        utils::stream(
-            self.backend().emojis(),
-            self.backend().roles(),
-            emoji_id,
-            |emoji| emoji.role_ids.into_iter(),
-        )
+        utils::stream(self.get(emoji_id), self.backend(), B::roles, |emoji| {
+            emoji.role_ids.into_iter()
+        })
     }
 
     /// Retrieve the user associated with an emoji.
     fn user(&self, emoji_id: EmojiId) -> GetEntityFuture<'_, UserEntity, B::Error> {
-        utils::relation_and_then(
-            self.backend().emojis(),
-            self.backend().users(),
-            emoji_id,
-            |emoji| emoji.user_id,
+        utils::relation_and_then(self.get(emoji_id), self.backend(), B::users, |emoji| {
+            emoji.user_id
+        })
+    }
+
+    /// Retrieve a stream of emojis within a guild.
+    ///
+    /// **Backend implementations**: the default implementation retrieves the
+    /// IDs from [`GuildRepository::emoji_ids`]. Backends storing entities
+    /// by keys prefixed with their guild ID can instead scan the keys.
+    ///
+    /// [`GuildRepository::emoji_ids`]: ../trait.GuildRepository.html#tymethod.emoji_ids
+    fn list_by_guild(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, EmojiEntity, B::Error> {
+        utils::stream_guild(
+            self.backend(),
+            guild_id,
+            |guilds, guild_id| guilds.emoji_ids(guild_id),
+            |_, emoji_id| emoji_id,
+            B::emojis,
         )
     }
+
+    /// Record that a custom emoji of a guild was used a number of times.
+    ///
+    /// The cache calls this for the custom emojis used in guild messages and
+    /// reactions. Refer to the [`emoji_usage`] module for more information.
+    ///
+    /// **Backend implementations**: the default implementation discards the
+    /// usage. Backends counting usage should add `count` to the existing
+    /// count of the emoji, whether or not the emoji itself is cached.
+    ///
+    /// [`emoji_usage`]: ../../emoji_usage/index.html
+    #[cfg(feature = "emoji-usage")]
+    fn record_usage(
+        &self,
+        guild_id: GuildId,
+        emoji_id: EmojiId,
+        count: u64,
+    ) -> UpsertEntityFuture<'_, B::Error> {
+        let _ = (guild_id, emoji_id, count);
+
+        future::ok(()).boxed()
+    }
+
+    /// Retrieve a stream of the IDs of the custom emojis used in a guild along
+    /// with how many times each was used.
+    ///
+    /// **Backend implementations**: the default implementation returns an
+    /// empty stream.
+    #[cfg(feature = "emoji-usage")]
+    fn usage(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, (EmojiId, u64), B::Error> {
+        let _ = guild_id;
+
+        future::ok(stream::empty().boxed()).boxed()
+    }
 }
@@ -1,26 +1,54 @@
-use super::role::RoleEntity;
+use super::{role::RoleEntity, GuildRepository};
+#[cfg(feature = "time")]
+use crate::timestamp::{OffsetDateTime, ParseTimestampError};
 use crate::{
+    entity::MergePartial,
     repository::{GetEntityFuture, ListEntitiesFuture, Repository},
+    timestamp::ParsedTimestamp,
     utils, Backend, Entity,
 };
+use futures_util::{
+    future::{self, FutureExt},
+    stream::{self, StreamExt},
+};
+use std::time::SystemTime;
 use twilight_model::{
-    guild::Member,
+    application::interaction::application_command::InteractionMember,
     gateway::payload::MemberUpdate,
+    guild::{Member, PartialMember},
     id::{GuildId, RoleId, UserId},
 };
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct MemberEntity {
     pub deaf: bool,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub guild_id: GuildId,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub hoisted_role_id: Option<RoleId>,
     pub joined_at: Option<String>,
+    /// Cache of the parsed `joined_at`.
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub parsed_joined_at: ParsedTimestamp,
     pub mute: bool,
     pub nick: Option<String>,
     pub pending: bool,
     pub premium_since: Option<String>,
+    /// Cache of the parsed `premium_since`.
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub parsed_premium_since: ParsedTimestamp,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub role_ids: Vec<RoleId>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub user_id: UserId,
 }
 
@@ -31,23 +59,117 @@ impl From<Member> for MemberEntity {
             guild_id: member.guild_id,
             hoisted_role_id: member.hoisted_role,
             joined_at: member.joined_at,
+            parsed_joined_at: ParsedTimestamp::default(),
             mute: member.mute,
             nick: member.nick,
             pending: member.pending,
             premium_since: member.premium_since,
+            parsed_premium_since: ParsedTimestamp::default(),
             role_ids: member.roles,
             user_id: member.user.id,
         }
     }
 }
 
+impl From<MemberUpdate> for MemberEntity {
+    fn from(update: MemberUpdate) -> Self {
+        Self {
+            deaf: update.deaf.unwrap_or_default(),
+            guild_id: update.guild_id,
+            hoisted_role_id: None,
+            joined_at: Some(update.joined_at),
+            parsed_joined_at: ParsedTimestamp::default(),
+            mute: update.mute.unwrap_or_default(),
+            nick: update.nick,
+            pending: update.pending,
+            premium_since: update.premium_since,
+            parsed_premium_since: ParsedTimestamp::default(),
+            role_ids: update.roles,
+            user_id: update.user.id,
+        }
+    }
+}
+
+impl From<(GuildId, UserId, PartialMember)> for MemberEntity {
+    fn from((guild_id, user_id, member): (GuildId, UserId, PartialMember)) -> Self {
+        Self {
+            deaf: member.deaf,
+            guild_id,
+            hoisted_role_id: None,
+            joined_at: member.joined_at,
+            parsed_joined_at: ParsedTimestamp::default(),
+            mute: member.mute,
+            nick: member.nick,
+            pending: false,
+            premium_since: member.premium_since,
+            parsed_premium_since: ParsedTimestamp::default(),
+            role_ids: member.roles,
+            user_id,
+        }
+    }
+}
+
+/// Resolved members of interactions don't say whether they're deafened, muted,
+/// or pending, so these are `false`.
+impl From<(GuildId, InteractionMember)> for MemberEntity {
+    fn from((guild_id, member): (GuildId, InteractionMember)) -> Self {
+        Self {
+            deaf: false,
+            guild_id,
+            hoisted_role_id: member.hoisted_role,
+            joined_at: member.joined_at,
+            parsed_joined_at: ParsedTimestamp::default(),
+            mute: false,
+            nick: member.nick,
+            pending: false,
+            premium_since: member.premium_since,
+            parsed_premium_since: ParsedTimestamp::default(),
+            role_ids: member.roles,
+            user_id: member.id,
+        }
+    }
+}
+
 impl MemberEntity {
+    /// Create a builder for a member with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(guild_id: GuildId, user_id: UserId) -> MemberEntityBuilder {
+        MemberEntityBuilder::new(guild_id, user_id)
+    }
+
+    /// Parse the date the member joined the guild.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored timestamp is invalid.
+    #[cfg(feature = "time")]
+    pub fn joined_at_datetime(&self) -> Result<Option<OffsetDateTime>, ParseTimestampError> {
+        self.parsed_joined_at
+            .get_optional(self.joined_at.as_deref())
+    }
+
+    /// Parse the date the member started boosting the guild.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored timestamp is invalid.
+    #[cfg(feature = "time")]
+    pub fn premium_since_datetime(&self) -> Result<Option<OffsetDateTime>, ParseTimestampError> {
+        self.parsed_premium_since
+            .get_optional(self.premium_since.as_deref())
+    }
+
+    #[must_use]
     pub fn update(self, update: MemberUpdate) -> Self {
         Self {
+            deaf: update.deaf.unwrap_or(self.deaf),
             guild_id: update.guild_id,
             joined_at: Some(update.joined_at),
+            parsed_joined_at: ParsedTimestamp::default(),
+            mute: update.mute.unwrap_or(self.mute),
             nick: update.nick.or(self.nick),
             premium_since: update.premium_since.or(self.premium_since),
+            parsed_premium_since: ParsedTimestamp::default(),
             role_ids: update.roles,
             user_id: update.user.id,
             ..self
@@ -64,6 +186,127 @@ impl Entity for MemberEntity {
     }
 }
 
+/// Partial members, such as the members of the authors of messages, omit the
+/// member's hoisted role, whether they're pending, and in some payloads when
+/// they joined. These keep their known values, while the other fields are
+/// replaced.
+impl MergePartial for MemberEntity {
+    fn merge_partial(self, partial: Self) -> Self {
+        Self {
+            hoisted_role_id: partial.hoisted_role_id.or(self.hoisted_role_id),
+            joined_at: partial.joined_at.or(self.joined_at),
+            parsed_joined_at: ParsedTimestamp::default(),
+            pending: self.pending,
+            ..partial
+        }
+    }
+}
+
+/// Builder to create a [`MemberEntity`] without the need to specify every field.
+///
+/// [`MemberEntity`]: struct.MemberEntity.html
+#[derive(Clone, Debug)]
+pub struct MemberEntityBuilder(MemberEntity);
+
+impl MemberEntityBuilder {
+    pub fn new(guild_id: GuildId, user_id: UserId) -> Self {
+        Self(MemberEntity {
+            deaf: false,
+            guild_id,
+            hoisted_role_id: None,
+            joined_at: None,
+            parsed_joined_at: ParsedTimestamp::default(),
+            mute: false,
+            nick: None,
+            pending: false,
+            premium_since: None,
+            parsed_premium_since: ParsedTimestamp::default(),
+            role_ids: Vec::new(),
+            user_id,
+        })
+    }
+
+    pub fn build(self) -> MemberEntity {
+        self.0
+    }
+
+    pub fn deaf(&mut self, deaf: bool) -> &mut Self {
+        self.0.deaf = deaf;
+
+        self
+    }
+
+    pub fn hoisted_role_id(&mut self, hoisted_role_id: RoleId) -> &mut Self {
+        self.0.hoisted_role_id = Some(hoisted_role_id);
+
+        self
+    }
+
+    pub fn joined_at(&mut self, joined_at: impl Into<String>) -> &mut Self {
+        self.0.joined_at = Some(joined_at.into());
+
+        self
+    }
+
+    pub fn mute(&mut self, mute: bool) -> &mut Self {
+        self.0.mute = mute;
+
+        self
+    }
+
+    pub fn nick(&mut self, nick: impl Into<String>) -> &mut Self {
+        self.0.nick = Some(nick.into());
+
+        self
+    }
+
+    pub fn pending(&mut self, pending: bool) -> &mut Self {
+        self.0.pending = pending;
+
+        self
+    }
+
+    pub fn premium_since(&mut self, premium_since: impl Into<String>) -> &mut Self {
+        self.0.premium_since = Some(premium_since.into());
+
+        self
+    }
+
+    pub fn role_ids(&mut self, role_ids: Vec<RoleId>) -> &mut Self {
+        self.0.role_ids = role_ids;
+
+        self
+    }
+}
+
+/// Kind of name recorded in a [`NameChange`].
+///
+/// [`NameChange`]: struct.NameChange.html
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NameKind {
+    /// Nickname of the member within the guild.
+    Nickname,
+    /// Username of the member's user, shared between guilds.
+    Username,
+}
+
+/// Previous name of a member, recorded when the cache saw it change.
+///
+/// Retrieved via [`MemberRepository::nick_history`].
+///
+/// [`MemberRepository::nick_history`]: trait.MemberRepository.html#method.nick_history
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NameChange {
+    /// When the cache saw the name change.
+    pub changed_at: SystemTime,
+    /// Kind of name that changed.
+    pub kind: NameKind,
+    /// Name before the change.
+    ///
+    /// A nickname is `None` if the member didn't have one.
+    pub old: Option<String>,
+}
+
 pub trait MemberRepository<B: Backend>: Repository<MemberEntity, B> {
     /// Retrieve the hoisted role associated with a role.
     fn hoisted_role(
@@ -72,13 +315,31 @@ pub trait MemberRepository<B: Backend>: Repository<MemberEntity, B> {
         user_id: UserId,
     ) -> GetEntityFuture<'_, RoleEntity, B::Error> {
         utils::relation_and_then(
-            self.backend().members(),
-            self.backend().roles(),
-            (guild_id, user_id),
+            self.get((guild_id, user_id)),
+            self.backend(),
+            B::roles,
             |member| member.hoisted_role_id,
         )
     }
 
+    /// Retrieve a stream of the previous nicknames of a member and usernames
+    /// of its user, from oldest to most recent.
+    ///
+    /// Only changes seen by the cache are recorded, such as through
+    /// `MemberUpdate` events. Recording them is optional: backends that don't
+    /// track name history or have it disabled return an empty stream.
+    ///
+    /// **Backend implementations**: the default implementation returns an
+    /// empty stream. Backends that record names as members and users are
+    /// upserted should implement this manually.
+    fn nick_history(
+        &self,
+        _guild_id: GuildId,
+        _user_id: UserId,
+    ) -> ListEntitiesFuture<'_, NameChange, B::Error> {
+        future::ok(stream::empty().boxed()).boxed()
+    }
+
     /// Retrieve a stream of roles associated with a member.
     ///
     /// Backend implementations aren't obligated to return roles in any
@@ -89,10 +350,27 @@ pub trait MemberRepository<B: Backend>: Repository<MemberEntity, B> {
         user_id: UserId,
     ) -> ListEntitiesFuture<'_, RoleEntity, B::Error> {
         utils::stream(
-            self.backend().members(),
-            self.backend().roles(),
-            (guild_id, user_id),
+            self.get((guild_id, user_id)),
+            self.backend(),
+            B::roles,
             |member| member.role_ids.into_iter(),
         )
     }
+
+    /// Retrieve a stream of members within a guild.
+    ///
+    /// **Backend implementations**: the default implementation retrieves the
+    /// IDs from [`GuildRepository::member_ids`]. Backends storing entities
+    /// by keys prefixed with their guild ID can instead scan the keys.
+    ///
+    /// [`GuildRepository::member_ids`]: ../trait.GuildRepository.html#tymethod.member_ids
+    fn list_by_guild(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        utils::stream_guild(
+            self.backend(),
+            guild_id,
+            |guilds, guild_id| guilds.member_ids(guild_id),
+            |guild_id, user_id| (guild_id, user_id),
+            B::members,
+        )
+    }
 }
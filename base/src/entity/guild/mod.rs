@@ -1,13 +1,19 @@
 //! Entities related to and within guilds.
 
+pub mod channel_tree;
 pub mod emoji;
 pub mod member;
+pub mod preview;
 pub mod role;
+pub mod stats;
 
 pub use self::{
-    emoji::{EmojiEntity, EmojiRepository},
-    member::{MemberEntity, MemberRepository},
-    role::{RoleEntity, RoleRepository},
+    channel_tree::{ChannelTree, ChannelTreeCategory},
+    emoji::{EmojiEntity, EmojiEntityBuilder, EmojiRepository},
+    member::{MemberEntity, MemberEntityBuilder, MemberRepository, NameChange, NameKind},
+    preview::{GuildPreviewEntity, GuildPreviewEntityBuilder, GuildPreviewRepository},
+    role::{RoleEntity, RoleEntityBuilder, RoleRepository},
+    stats::{GuildStats, PresenceStats},
 };
 
 use super::{
@@ -16,10 +22,20 @@ use super::{
     user::UserEntity,
     voice::VoiceStateEntity,
 };
+#[cfg(feature = "time")]
+use crate::timestamp::{OffsetDateTime, ParseTimestampError};
 use crate::{
-    repository::{GetEntityFuture, ListEntitiesFuture, ListEntityIdsFuture, Repository},
+    repository::{
+        GetEntityFuture, ListEntitiesFuture, ListEntityIdsFuture, ListEntityIdsStream, Repository,
+        UpsertEntitiesFuture,
+    },
+    timestamp::ParsedTimestamp,
     utils, Backend, Entity,
 };
+use futures_util::{
+    future::{self, TryFutureExt},
+    stream::{self, StreamExt, TryStreamExt},
+};
 use twilight_model::{
     guild::{
         DefaultMessageNotificationLevel, ExplicitContentFilter, Guild, MfaLevel, PartialGuild,
@@ -28,23 +44,44 @@ use twilight_model::{
     id::{ApplicationId, ChannelId, EmojiId, GuildId, RoleId, UserId},
 };
 
+/// Maximum number of previous owners retained in
+/// [`GuildEntity::previous_owner_ids`].
+///
+/// [`GuildEntity::previous_owner_ids`]: struct.GuildEntity.html#structfield.previous_owner_ids
+pub const OWNER_HISTORY_LIMIT: usize = 5;
+
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct GuildEntity {
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub afk_channel_id: Option<ChannelId>,
     pub afk_timeout: u64,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub application_id: Option<ApplicationId>,
     pub approximate_member_count: Option<u64>,
     pub approximate_presence_count: Option<u64>,
     pub banner: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub default_message_notifications: DefaultMessageNotificationLevel,
     pub description: Option<String>,
     pub discovery_splash: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub explicit_content_filter: ExplicitContentFilter,
     pub features: Vec<String>,
     pub icon: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: GuildId,
     pub joined_at: Option<String>,
+    /// Cache of the parsed `joined_at`.
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub parsed_joined_at: ParsedTimestamp,
     #[cfg_attr(feature = "serde", serde(default))]
     pub large: bool,
     // Not documented so I marked it as optional.
@@ -53,24 +90,42 @@ pub struct GuildEntity {
     pub max_presences: Option<u64>,
     pub max_video_channel_users: Option<u64>,
     pub member_count: Option<u64>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub mfa_level: MfaLevel,
     pub name: String,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub owner_id: UserId,
     pub owner: Option<bool>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsBits>))]
     pub permissions: Option<Permissions>,
     pub preferred_locale: String,
     pub premium_subscription_count: Option<u64>,
     #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub premium_tier: PremiumTier,
+    /// IDs of the previous owners of the guild, from oldest to most recent.
+    ///
+    /// This only contains ownership transfers seen by the cache and is limited
+    /// to the last [`OWNER_HISTORY_LIMIT`] owners.
+    ///
+    /// [`OWNER_HISTORY_LIMIT`]: constant.OWNER_HISTORY_LIMIT.html
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
+    pub previous_owner_ids: Vec<UserId>,
     pub region: String,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub rules_channel_id: Option<ChannelId>,
     pub splash: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsBits))]
     pub system_channel_flags: SystemChannelFlags,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub system_channel_id: Option<ChannelId>,
     #[cfg_attr(feature = "serde", serde(default))]
     pub unavailable: bool,
     pub vanity_url_code: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub verification_level: VerificationLevel,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub widget_channel_id: Option<ChannelId>,
     pub widget_enabled: Option<bool>,
 }
@@ -92,6 +147,7 @@ impl From<Guild> for GuildEntity {
             icon: guild.icon,
             id: guild.id,
             joined_at: guild.joined_at,
+            parsed_joined_at: ParsedTimestamp::default(),
             large: guild.large,
             lazy: guild.lazy,
             max_members: guild.max_members,
@@ -106,6 +162,7 @@ impl From<Guild> for GuildEntity {
             preferred_locale: guild.preferred_locale,
             premium_subscription_count: guild.premium_subscription_count,
             premium_tier: guild.premium_tier,
+            previous_owner_ids: Vec::new(),
             region: guild.region,
             rules_channel_id: guild.rules_channel_id,
             splash: guild.splash,
@@ -121,7 +178,65 @@ impl From<Guild> for GuildEntity {
 }
 
 impl GuildEntity {
+    /// Create a builder for a guild with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(id: GuildId, name: impl Into<String>, owner_id: UserId) -> GuildEntityBuilder {
+        GuildEntityBuilder::new(id, name, owner_id)
+    }
+
+    /// Parse the date the current user joined the guild.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored timestamp is invalid.
+    #[cfg(feature = "time")]
+    pub fn joined_at_datetime(&self) -> Result<Option<OffsetDateTime>, ParseTimestampError> {
+        self.parsed_joined_at
+            .get_optional(self.joined_at.as_deref())
+    }
+
+    /// Create a placeholder for an unavailable guild whose details aren't
+    /// known yet, such as a guild listed in the `Ready` event.
+    ///
+    /// Skeletons have an empty name and an owner ID of 0 until the guild's
+    /// `GuildCreate` is received.
+    pub fn skeleton(id: GuildId) -> Self {
+        let mut builder = GuildEntityBuilder::new(id, String::new(), UserId(0));
+        builder.unavailable(true);
+
+        builder.build()
+    }
+
+    /// Return whether the guild is a placeholder created by [`skeleton`].
+    ///
+    /// [`skeleton`]: #method.skeleton
+    pub const fn is_skeleton(&self) -> bool {
+        self.owner_id.0 == 0
+    }
+
+    /// Return the ID of the owner before the most recent ownership transfer
+    /// seen by the cache.
+    pub fn previous_owner_id(&self) -> Option<UserId> {
+        self.previous_owner_ids.last().copied()
+    }
+
+    /// Carry over the ownership history of a previously cached version of the
+    /// guild, recording its owner if the owner has since changed.
+    pub fn inherit_owner_history(&mut self, previous: &Self) {
+        self.previous_owner_ids
+            .clone_from(&previous.previous_owner_ids);
+        record_owner(
+            &mut self.previous_owner_ids,
+            previous.owner_id,
+            self.owner_id,
+        );
+    }
+
+    #[must_use]
     pub fn update(self, update: PartialGuild) -> Self {
+        let mut previous_owner_ids = self.previous_owner_ids.clone();
+        record_owner(&mut previous_owner_ids, self.owner_id, update.owner_id);
+
         Self {
             afk_channel_id: update.afk_channel_id.or(self.afk_channel_id),
             afk_timeout: update.afk_timeout,
@@ -147,6 +262,7 @@ impl GuildEntity {
                 .premium_subscription_count
                 .or(self.premium_subscription_count),
             premium_tier: update.premium_tier,
+            previous_owner_ids,
             region: update.region,
             rules_channel_id: update.rules_channel_id.or(self.rules_channel_id),
             splash: update.splash.or(self.splash),
@@ -161,6 +277,17 @@ impl GuildEntity {
     }
 }
 
+/// Add the previous owner of a guild to its ownership history if the owner
+/// changed, dropping the oldest owners over the limit.
+fn record_owner(previous_owner_ids: &mut Vec<UserId>, previous: UserId, current: UserId) {
+    if previous != current {
+        previous_owner_ids.push(previous);
+    }
+
+    let excess = previous_owner_ids.len().saturating_sub(OWNER_HISTORY_LIMIT);
+    previous_owner_ids.drain(..excess);
+}
+
 impl Entity for GuildEntity {
     type Id = GuildId;
 
@@ -170,7 +297,289 @@ impl Entity for GuildEntity {
     }
 }
 
+/// Builder to create a [`GuildEntity`] without the need to specify every field.
+///
+/// [`GuildEntity`]: struct.GuildEntity.html
+#[derive(Clone, Debug)]
+pub struct GuildEntityBuilder(GuildEntity);
+
+impl GuildEntityBuilder {
+    pub fn new(id: GuildId, name: impl Into<String>, owner_id: UserId) -> Self {
+        Self(GuildEntity {
+            afk_channel_id: None,
+            afk_timeout: 0,
+            application_id: None,
+            approximate_member_count: None,
+            approximate_presence_count: None,
+            banner: None,
+            default_message_notifications: DefaultMessageNotificationLevel::All,
+            description: None,
+            discovery_splash: None,
+            explicit_content_filter: ExplicitContentFilter::None,
+            features: Vec::new(),
+            icon: None,
+            id,
+            joined_at: None,
+            parsed_joined_at: ParsedTimestamp::default(),
+            large: false,
+            lazy: None,
+            max_members: None,
+            max_presences: None,
+            max_video_channel_users: None,
+            member_count: None,
+            mfa_level: MfaLevel::None,
+            name: name.into(),
+            owner_id,
+            owner: None,
+            permissions: None,
+            preferred_locale: String::from("en-US"),
+            premium_subscription_count: None,
+            premium_tier: PremiumTier::None,
+            previous_owner_ids: Vec::new(),
+            region: String::from("us-east"),
+            rules_channel_id: None,
+            splash: None,
+            system_channel_flags: SystemChannelFlags::empty(),
+            system_channel_id: None,
+            unavailable: false,
+            vanity_url_code: None,
+            verification_level: VerificationLevel::None,
+            widget_channel_id: None,
+            widget_enabled: None,
+        })
+    }
+
+    pub fn build(self) -> GuildEntity {
+        self.0
+    }
+
+    pub fn afk_channel_id(&mut self, afk_channel_id: ChannelId) -> &mut Self {
+        self.0.afk_channel_id = Some(afk_channel_id);
+
+        self
+    }
+
+    pub fn afk_timeout(&mut self, afk_timeout: u64) -> &mut Self {
+        self.0.afk_timeout = afk_timeout;
+
+        self
+    }
+
+    pub fn application_id(&mut self, application_id: ApplicationId) -> &mut Self {
+        self.0.application_id = Some(application_id);
+
+        self
+    }
+
+    pub fn approximate_member_count(&mut self, approximate_member_count: u64) -> &mut Self {
+        self.0.approximate_member_count = Some(approximate_member_count);
+
+        self
+    }
+
+    pub fn approximate_presence_count(&mut self, approximate_presence_count: u64) -> &mut Self {
+        self.0.approximate_presence_count = Some(approximate_presence_count);
+
+        self
+    }
+
+    pub fn banner(&mut self, banner: impl Into<String>) -> &mut Self {
+        self.0.banner = Some(banner.into());
+
+        self
+    }
+
+    pub fn default_message_notifications(
+        &mut self,
+        default_message_notifications: DefaultMessageNotificationLevel,
+    ) -> &mut Self {
+        self.0.default_message_notifications = default_message_notifications;
+
+        self
+    }
+
+    pub fn description(&mut self, description: impl Into<String>) -> &mut Self {
+        self.0.description = Some(description.into());
+
+        self
+    }
+
+    pub fn discovery_splash(&mut self, discovery_splash: impl Into<String>) -> &mut Self {
+        self.0.discovery_splash = Some(discovery_splash.into());
+
+        self
+    }
+
+    pub fn explicit_content_filter(
+        &mut self,
+        explicit_content_filter: ExplicitContentFilter,
+    ) -> &mut Self {
+        self.0.explicit_content_filter = explicit_content_filter;
+
+        self
+    }
+
+    pub fn features(&mut self, features: Vec<String>) -> &mut Self {
+        self.0.features = features;
+
+        self
+    }
+
+    pub fn icon(&mut self, icon: impl Into<String>) -> &mut Self {
+        self.0.icon = Some(icon.into());
+
+        self
+    }
+
+    pub fn joined_at(&mut self, joined_at: impl Into<String>) -> &mut Self {
+        self.0.joined_at = Some(joined_at.into());
+
+        self
+    }
+
+    pub fn large(&mut self, large: bool) -> &mut Self {
+        self.0.large = large;
+
+        self
+    }
+
+    pub fn lazy(&mut self, lazy: bool) -> &mut Self {
+        self.0.lazy = Some(lazy);
+
+        self
+    }
+
+    pub fn max_members(&mut self, max_members: u64) -> &mut Self {
+        self.0.max_members = Some(max_members);
+
+        self
+    }
+
+    pub fn max_presences(&mut self, max_presences: u64) -> &mut Self {
+        self.0.max_presences = Some(max_presences);
+
+        self
+    }
+
+    pub fn max_video_channel_users(&mut self, max_video_channel_users: u64) -> &mut Self {
+        self.0.max_video_channel_users = Some(max_video_channel_users);
+
+        self
+    }
+
+    pub fn member_count(&mut self, member_count: u64) -> &mut Self {
+        self.0.member_count = Some(member_count);
+
+        self
+    }
+
+    pub fn mfa_level(&mut self, mfa_level: MfaLevel) -> &mut Self {
+        self.0.mfa_level = mfa_level;
+
+        self
+    }
+
+    pub fn owner(&mut self, owner: bool) -> &mut Self {
+        self.0.owner = Some(owner);
+
+        self
+    }
+
+    pub fn permissions(&mut self, permissions: Permissions) -> &mut Self {
+        self.0.permissions = Some(permissions);
+
+        self
+    }
+
+    pub fn preferred_locale(&mut self, preferred_locale: impl Into<String>) -> &mut Self {
+        self.0.preferred_locale = preferred_locale.into();
+
+        self
+    }
+
+    pub fn premium_subscription_count(&mut self, premium_subscription_count: u64) -> &mut Self {
+        self.0.premium_subscription_count = Some(premium_subscription_count);
+
+        self
+    }
+
+    pub fn premium_tier(&mut self, premium_tier: PremiumTier) -> &mut Self {
+        self.0.premium_tier = premium_tier;
+
+        self
+    }
+
+    pub fn region(&mut self, region: impl Into<String>) -> &mut Self {
+        self.0.region = region.into();
+
+        self
+    }
+
+    pub fn rules_channel_id(&mut self, rules_channel_id: ChannelId) -> &mut Self {
+        self.0.rules_channel_id = Some(rules_channel_id);
+
+        self
+    }
+
+    pub fn splash(&mut self, splash: impl Into<String>) -> &mut Self {
+        self.0.splash = Some(splash.into());
+
+        self
+    }
+
+    pub fn system_channel_flags(&mut self, system_channel_flags: SystemChannelFlags) -> &mut Self {
+        self.0.system_channel_flags = system_channel_flags;
+
+        self
+    }
+
+    pub fn system_channel_id(&mut self, system_channel_id: ChannelId) -> &mut Self {
+        self.0.system_channel_id = Some(system_channel_id);
+
+        self
+    }
+
+    pub fn unavailable(&mut self, unavailable: bool) -> &mut Self {
+        self.0.unavailable = unavailable;
+
+        self
+    }
+
+    pub fn vanity_url_code(&mut self, vanity_url_code: impl Into<String>) -> &mut Self {
+        self.0.vanity_url_code = Some(vanity_url_code.into());
+
+        self
+    }
+
+    pub fn verification_level(&mut self, verification_level: VerificationLevel) -> &mut Self {
+        self.0.verification_level = verification_level;
+
+        self
+    }
+
+    pub fn widget_channel_id(&mut self, widget_channel_id: ChannelId) -> &mut Self {
+        self.0.widget_channel_id = Some(widget_channel_id);
+
+        self
+    }
+
+    pub fn widget_enabled(&mut self, widget_enabled: bool) -> &mut Self {
+        self.0.widget_enabled = Some(widget_enabled);
+
+        self
+    }
+}
+
 /// Repository to work with guilds and their associated entities.
+///
+/// # Ordering
+///
+/// Streams of the IDs of entities within a guild, such as [`member_ids`],
+/// yield each ID once in ascending order, so they can be paginated by
+/// resuming after the last ID received. Backends must uphold this when
+/// implementing the methods.
+///
+/// [`member_ids`]: #tymethod.member_ids
 pub trait GuildRepository<B: Backend>: Repository<GuildEntity, B> {
     /// Retrieve the AFK voice channel associated with a guild.
     ///
@@ -181,20 +590,42 @@ pub trait GuildRepository<B: Backend>: Repository<GuildEntity, B> {
     /// [`GuildEntity::afk_channel_id`]: struct.GuildEntity.html#structfield.afk_channel_id
     fn afk_channel(&self, guild_id: GuildId) -> GetEntityFuture<'_, VoiceChannelEntity, B::Error> {
         utils::relation_and_then(
-            self.backend().guilds(),
-            self.backend().voice_channels(),
-            guild_id,
+            self.get(guild_id),
+            self.backend(),
+            B::voice_channels,
             |guild| guild.afk_channel_id,
         )
     }
 
-    /// Retrieve a stream of channel IDs within a guild.
+    /// Retrieve the channels of a guild nested within their categories.
+    ///
+    /// Backend implementations should return `None` if the guild is not
+    /// present in the cache.
+    fn channel_tree(&self, guild_id: GuildId) -> GetEntityFuture<'_, ChannelTree, B::Error> {
+        let guilds = self.backend().guilds();
+
+        Box::pin(async move {
+            if guilds.get(guild_id).await?.is_none() {
+                return Ok(None);
+            }
+
+            let channels = guilds
+                .channels(guild_id)
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?;
+
+            Ok(Some(ChannelTree::from_channels(channels)))
+        })
+    }
+
+    /// Retrieve a stream of channel IDs within a guild, in ascending order.
     fn channel_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, ChannelId, B::Error>;
 
     /// Retrieve a stream of channels within a guild.
     fn channels(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, GuildChannelEntity, B::Error>;
 
-    /// Retrieve a stream of emoji IDs within a guild.
+    /// Retrieve a stream of emoji IDs within a guild, in ascending order.
     fn emoji_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, EmojiId, B::Error>;
 
     /// Retrieve a stream of emojis within a guild.
@@ -202,32 +633,230 @@ pub trait GuildRepository<B: Backend>: Repository<GuildEntity, B> {
         utils::stream_ids(self.emoji_ids(guild_id), self.backend().emojis())
     }
 
-    /// Retrieve a stream of member IDs within a guild.
+    /// Retrieve a stream of member IDs within a guild, in ascending order.
     fn member_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error>;
 
     /// Retrieve a stream of members within a guild.
     fn members(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, MemberEntity, B::Error>;
 
+    /// Retrieve a stream of members within a guild along with their users.
+    ///
+    /// Members whose user isn't cached are paired with `None`.
+    ///
+    /// **Backend implementations**: the default implementation retrieves the
+    /// user of each member after the member, one at a time. Backends that can
+    /// retrieve both at once, such as by joining tables, should implement
+    /// this manually to avoid a lookup per member.
+    fn members_with_users(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, (MemberEntity, Option<UserEntity>), B::Error> {
+        let members = self.members(guild_id);
+        let users = self.backend().users();
+
+        Box::pin(async move {
+            let members = members.await?;
+
+            Ok(
+                stream::unfold((members, users), |(mut members, users)| async move {
+                    let member = match members.try_next().await {
+                        Ok(Some(member)) => member,
+                        Ok(None) => return None,
+                        Err(source) => return Some((Err(source), (members, users))),
+                    };
+                    let user = users.get(member.user_id).await;
+
+                    Some((user.map(|user| (member, user)), (members, users)))
+                })
+                .boxed(),
+            )
+        })
+    }
+
+    /// Retrieve a stream of the IDs of channels within a guild ordered by
+    /// their positions, from lowest to highest.
+    ///
+    /// Channels with the same position, such as while the `ChannelUpdate`
+    /// events of a reorder are still being received, are ordered by their ID
+    /// so that the order is the same however the updates interleave. Every
+    /// kind of channel is included in one order; use [`channel_tree`] for the
+    /// order in which clients display them.
+    ///
+    /// **Backend implementations**: the default implementation sorts the
+    /// guild's [`channels`] on every call. Backends can instead keep an index
+    /// of channels ordered by position up to date as channels are upserted
+    /// and removed.
+    ///
+    /// [`channel_tree`]: #method.channel_tree
+    /// [`channels`]: #tymethod.channels
+    fn ordered_channel_ids(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntityIdsFuture<'_, ChannelId, B::Error> {
+        let channels = self.channels(guild_id);
+
+        Box::pin(async move {
+            let mut channels = channels
+                .await?
+                .map_ok(|channel| (channel.position(), channel.id()))
+                .try_collect::<Vec<_>>()
+                .await?;
+            channels.sort_unstable();
+
+            Ok(stream::iter(channels.into_iter().map(|(_, id)| Ok(id))).boxed())
+        })
+    }
+
     /// Retrieve the owner associated with a guild.
     ///
     /// Backend implementations should return `None` if the user is not in the
     /// cache.
     fn owner(&self, guild_id: GuildId) -> GetEntityFuture<'_, UserEntity, B::Error> {
-        utils::relation_map(
-            self.backend().guilds(),
-            self.backend().users(),
-            guild_id,
-            |guild| guild.owner_id,
-        )
+        utils::relation_map(self.get(guild_id), self.backend(), B::users, |guild| {
+            guild.owner_id
+        })
+    }
+
+    /// Retrieve the previous owner of a guild.
+    ///
+    /// Refer to [`GuildEntity::previous_owner_ids`] for more information.
+    ///
+    /// Backend implementations should return `None` if the guild's ownership
+    /// hasn't been seen changing or the user is not in the cache.
+    ///
+    /// [`GuildEntity::previous_owner_ids`]: struct.GuildEntity.html#structfield.previous_owner_ids
+    fn previous_owner(&self, guild_id: GuildId) -> GetEntityFuture<'_, UserEntity, B::Error> {
+        utils::relation_and_then(self.get(guild_id), self.backend(), B::users, |guild| {
+            guild.previous_owner_id()
+        })
     }
 
-    /// Retrieve a stream of user IDs of presences within a guild.
+    /// Retrieve a stream of user IDs of presences within a guild, in ascending order.
     fn presence_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error>;
 
     /// Retrieve a stream of presences within a guild.
     fn presences(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, PresenceEntity, B::Error>;
 
-    /// Retrieve a stream of role IDs within a guild.
+    /// Retrieve a stream of the most recent joins to a guild, newest first,
+    /// as the user IDs of the members who joined and when they joined.
+    ///
+    /// At most `limit` joins are returned. Members whose join date isn't
+    /// known are skipped. Useful for detecting bursts of joins, such as
+    /// during a raid.
+    ///
+    /// **Backend implementations**: the default implementation sorts the
+    /// guild's [`members`] by their join date, comparing the timestamps as
+    /// strings. Backends can instead keep an index of recent joins.
+    ///
+    /// [`members`]: #tymethod.members
+    fn recent_joins(
+        &self,
+        guild_id: GuildId,
+        limit: usize,
+    ) -> ListEntitiesFuture<'_, (UserId, String), B::Error> {
+        let members = self.members(guild_id);
+
+        Box::pin(async move {
+            let mut joins = members
+                .await?
+                .try_filter_map(|member| {
+                    let user_id = member.user_id;
+
+                    future::ok(member.joined_at.map(|joined_at| (user_id, joined_at)))
+                })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+            joins.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+            joins.truncate(limit);
+
+            Ok(stream::iter(joins.into_iter().map(Ok)).boxed())
+        })
+    }
+
+    /// Retrieve a stream of the members within a guild whose nickname or
+    /// username starts with a query, ignoring case.
+    ///
+    /// Members are ordered by the name that matched, then by their ID. A
+    /// member whose nickname and username both match is returned once, under
+    /// the name that comes first. At most `limit` members are returned.
+    ///
+    /// **Backend implementations**: the default implementation retrieves
+    /// every member of the guild along with their user and filters them.
+    /// Backends can instead keep an index of the names of each guild's
+    /// members.
+    fn search_members(
+        &self,
+        guild_id: GuildId,
+        query: &str,
+        limit: usize,
+    ) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        let members = self.members_with_users(guild_id);
+        let query = query.to_lowercase();
+
+        Box::pin(async move {
+            let mut matches = members
+                .await?
+                .try_filter_map(|(member, user)| {
+                    let name = member
+                        .nick
+                        .iter()
+                        .chain(user.as_ref().map(|user| &user.name))
+                        .map(|name| name.to_lowercase())
+                        .filter(|name| name.starts_with(&query))
+                        .min();
+
+                    future::ok(name.map(|name| (name, member)))
+                })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+            matches.sort_unstable_by(|(a, a_member), (b, b_member)| {
+                a.cmp(b).then(a_member.user_id.cmp(&b_member.user_id))
+            });
+            matches.truncate(limit);
+
+            Ok(stream::iter(matches.into_iter().map(|(_, member)| Ok(member))).boxed())
+        })
+    }
+
+    /// Set the positions of a guild's cached roles to match their order.
+    ///
+    /// `role_ids` are ordered from the lowest role, usually `@everyone`, to
+    /// the highest, such as the roles returned by Discord after modifying
+    /// role positions. Each role's position is set to its index. Roles that
+    /// aren't cached or aren't in the guild are skipped, and only roles whose
+    /// position changed are upserted.
+    ///
+    /// Discord sends a `RoleUpdate` event for every role that moved, so this
+    /// applies a reorder at once instead of leaving roles with duplicate
+    /// positions until every event has been received.
+    fn reorder_roles(
+        &self,
+        guild_id: GuildId,
+        role_ids: Vec<RoleId>,
+    ) -> UpsertEntitiesFuture<'_, B::Error> {
+        let roles = self.backend().roles();
+
+        Box::pin(async move {
+            let mut moved = Vec::new();
+
+            for (position, role_id) in (0..).zip(role_ids) {
+                let Some(mut role) = roles.get(role_id).await? else {
+                    continue;
+                };
+
+                if role.guild_id == guild_id && role.position != position {
+                    role.position = position;
+                    moved.push(role);
+                }
+            }
+
+            roles.upsert_bulk(moved.into_iter()).await
+        })
+    }
+
+    /// Retrieve a stream of role IDs within a guild, in ascending order.
     fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, B::Error>;
 
     /// Retrieve a stream of roles within a guild.
@@ -235,6 +864,23 @@ pub trait GuildRepository<B: Backend>: Repository<GuildEntity, B> {
         utils::stream_ids(self.role_ids(guild_id), self.backend().roles())
     }
 
+    /// Retrieve a stream of roles within a guild sorted by their position,
+    /// from lowest to highest.
+    ///
+    /// Roles with the same position, such as while the events of a reorder
+    /// are still being received, are sorted by their ID so that the order is
+    /// the same however the updates interleave.
+    fn sorted_roles(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, RoleEntity, B::Error> {
+        let roles = self.roles(guild_id);
+
+        Box::pin(async move {
+            let mut roles = roles.await?.try_collect::<Vec<_>>().await?;
+            roles.sort_unstable_by_key(|role| (role.position, role.id));
+
+            Ok(stream::iter(roles.into_iter().map(Ok)).boxed())
+        })
+    }
+
     /// Retrieve the rules channel associated with a guild.
     ///
     /// Backend implementations should return `None` if the rules channel isn't
@@ -244,13 +890,55 @@ pub trait GuildRepository<B: Backend>: Repository<GuildEntity, B> {
     /// [`GuildEntity::rules_channel_id`]: struct.GuildEntity.html#structfield.rules_channel_id
     fn rules_channel(&self, guild_id: GuildId) -> GetEntityFuture<'_, TextChannelEntity, B::Error> {
         utils::relation_and_then(
-            self.backend().guilds(),
-            self.backend().text_channels(),
-            guild_id,
+            self.get(guild_id),
+            self.backend(),
+            B::text_channels,
             |guild| guild.rules_channel_id,
         )
     }
 
+    /// Retrieve counts of the cached channels, emojis, members, presences,
+    /// roles, and voice states within a guild.
+    ///
+    /// Backend implementations should return `None` if the guild is not
+    /// present in the cache. The default implementation streams every related
+    /// entity, so backends that store relations should override it to count
+    /// them directly.
+    fn stats(&self, guild_id: GuildId) -> GetEntityFuture<'_, GuildStats, B::Error> {
+        let guilds = self.backend().guilds();
+
+        Box::pin(async move {
+            if guilds.get(guild_id).await?.is_none() {
+                return Ok(None);
+            }
+
+            let mut stats = GuildStats::default();
+
+            let mut channels = guilds.channels(guild_id).await?;
+
+            while let Some(channel) = channels.next().await {
+                match channel? {
+                    GuildChannelEntity::Category(_) => stats.category_channels += 1,
+                    GuildChannelEntity::Text(_) => stats.text_channels += 1,
+                    GuildChannelEntity::Voice(_) => stats.voice_channels += 1,
+                }
+            }
+
+            let mut presences = guilds.presences(guild_id).await?;
+
+            while let Some(presence) = presences.next().await {
+                stats.presences.add(presence?.status);
+            }
+
+            stats.emojis = count(guilds.emoji_ids(guild_id).await?).await?;
+            stats.members = count(guilds.member_ids(guild_id).await?).await?;
+            stats.roles = count(guilds.role_ids(guild_id).await?).await?;
+            stats.voice_states = count(guilds.voice_state_ids(guild_id).await?).await?;
+
+            Ok(Some(stats))
+        })
+    }
+
     /// Retrieve the system channel associated with a guild.
     ///
     /// Backend implementations should return `None` if the system channel isn't
@@ -263,14 +951,30 @@ pub trait GuildRepository<B: Backend>: Repository<GuildEntity, B> {
         guild_id: GuildId,
     ) -> GetEntityFuture<'_, TextChannelEntity, B::Error> {
         utils::relation_and_then(
-            self.backend().guilds(),
-            self.backend().text_channels(),
-            guild_id,
+            self.get(guild_id),
+            self.backend(),
+            B::text_channels,
             |guild| guild.system_channel_id,
         )
     }
 
-    /// Retrieve a stream of voice states' user IDs within a guild.
+    /// Retrieve a stream of the IDs of cached guilds that are unavailable due
+    /// to an outage.
+    ///
+    /// This tells guilds that are temporarily unavailable apart from guilds
+    /// that aren't cached.
+    ///
+    /// The default implementation streams every guild, so backends that can
+    /// query guilds by their availability should override it.
+    fn unavailable_ids(&self) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        Box::pin(self.list().map_ok(|guilds| {
+            guilds
+                .try_filter_map(|guild| future::ok(Some(guild.id).filter(|_| guild.unavailable)))
+                .boxed()
+        }))
+    }
+
+    /// Retrieve a stream of voice states' user IDs within a guild, in ascending order.
     fn voice_state_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error>;
 
     /// Retrieve a stream of voice states within a guild.
@@ -293,13 +997,12 @@ pub trait GuildRepository<B: Backend>: Repository<GuildEntity, B> {
         Box::pin(async move {
             let guilds = backend.guilds();
 
-            let channel_id = match guilds
+            let Some(channel_id) = guilds
                 .get(guild_id)
                 .await?
                 .and_then(|g| g.widget_channel_id)
-            {
-                Some(channel_id) => channel_id,
-                None => return Ok(None),
+            else {
+                return Ok(None);
             };
 
             let text_channels = backend.text_channels();
@@ -324,3 +1027,14 @@ pub trait GuildRepository<B: Backend>: Repository<GuildEntity, B> {
         })
     }
 }
+
+async fn count<T, E>(mut ids: ListEntityIdsStream<'_, T, E>) -> Result<usize, E> {
+    let mut count = 0;
+
+    while let Some(id) = ids.next().await {
+        id?;
+        count += 1;
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,167 @@
+use super::{EmojiEntity, GuildEntity};
+use crate::{
+    repository::{GetEntityFuture, ListEntitiesFuture, Repository},
+    utils, Backend, Entity,
+};
+use twilight_model::{
+    guild::GuildPreview,
+    id::{EmojiId, GuildId},
+};
+
+/// Cachable version of a guild preview.
+///
+/// Previews are available for guilds that the current user is not a member
+/// of, such as discoverable guilds, so they are cached separately from full
+/// guilds. Discord doesn't send previews over the gateway: upsert them after
+/// retrieving them over HTTP.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
+pub struct GuildPreviewEntity {
+    pub approximate_member_count: u64,
+    pub approximate_presence_count: u64,
+    pub description: Option<String>,
+    pub discovery_splash: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
+    pub emoji_ids: Vec<EmojiId>,
+    pub features: Vec<String>,
+    pub icon: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
+    pub id: GuildId,
+    pub name: String,
+    pub splash: Option<String>,
+}
+
+impl From<GuildPreview> for GuildPreviewEntity {
+    fn from(preview: GuildPreview) -> Self {
+        let emoji_ids = preview.emojis.into_iter().map(|emoji| emoji.id).collect();
+
+        Self {
+            approximate_member_count: preview.approximate_member_count,
+            approximate_presence_count: preview.approximate_presence_count,
+            description: preview.description,
+            discovery_splash: preview.discovery_splash,
+            emoji_ids,
+            features: preview.features,
+            icon: preview.icon,
+            id: preview.id,
+            name: preview.name,
+            splash: preview.splash,
+        }
+    }
+}
+
+impl GuildPreviewEntity {
+    /// Create a builder for a guild preview with the required fields set and
+    /// the remaining fields set to their defaults.
+    pub fn builder(id: GuildId, name: impl Into<String>) -> GuildPreviewEntityBuilder {
+        GuildPreviewEntityBuilder::new(id, name)
+    }
+}
+
+impl Entity for GuildPreviewEntity {
+    type Id = GuildId;
+
+    /// Return the ID of the previewed guild.
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+}
+
+/// Builder to create a [`GuildPreviewEntity`] without the need to specify
+/// every field.
+///
+/// [`GuildPreviewEntity`]: struct.GuildPreviewEntity.html
+#[derive(Clone, Debug)]
+pub struct GuildPreviewEntityBuilder(GuildPreviewEntity);
+
+impl GuildPreviewEntityBuilder {
+    pub fn new(id: GuildId, name: impl Into<String>) -> Self {
+        Self(GuildPreviewEntity {
+            approximate_member_count: 0,
+            approximate_presence_count: 0,
+            description: None,
+            discovery_splash: None,
+            emoji_ids: Vec::new(),
+            features: Vec::new(),
+            icon: None,
+            id,
+            name: name.into(),
+            splash: None,
+        })
+    }
+
+    pub fn build(self) -> GuildPreviewEntity {
+        self.0
+    }
+
+    pub fn approximate_member_count(&mut self, approximate_member_count: u64) -> &mut Self {
+        self.0.approximate_member_count = approximate_member_count;
+
+        self
+    }
+
+    pub fn approximate_presence_count(&mut self, approximate_presence_count: u64) -> &mut Self {
+        self.0.approximate_presence_count = approximate_presence_count;
+
+        self
+    }
+
+    pub fn description(&mut self, description: impl Into<String>) -> &mut Self {
+        self.0.description = Some(description.into());
+
+        self
+    }
+
+    pub fn discovery_splash(&mut self, discovery_splash: impl Into<String>) -> &mut Self {
+        self.0.discovery_splash = Some(discovery_splash.into());
+
+        self
+    }
+
+    pub fn emoji_ids(&mut self, emoji_ids: Vec<EmojiId>) -> &mut Self {
+        self.0.emoji_ids = emoji_ids;
+
+        self
+    }
+
+    pub fn features(&mut self, features: Vec<String>) -> &mut Self {
+        self.0.features = features;
+
+        self
+    }
+
+    pub fn icon(&mut self, icon: impl Into<String>) -> &mut Self {
+        self.0.icon = Some(icon.into());
+
+        self
+    }
+
+    pub fn splash(&mut self, splash: impl Into<String>) -> &mut Self {
+        self.0.splash = Some(splash.into());
+
+        self
+    }
+}
+
+/// Repository to work with guild previews and their associated entities.
+pub trait GuildPreviewRepository<B: Backend>: Repository<GuildPreviewEntity, B> {
+    /// Retrieve a stream of the cached emojis listed by a guild preview.
+    fn emojis(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, EmojiEntity, B::Error> {
+        utils::stream(self.get(guild_id), self.backend(), B::emojis, |preview| {
+            preview.emoji_ids.into_iter()
+        })
+    }
+
+    /// Retrieve the full guild of a guild preview, if the guild is cached.
+    fn guild(&self, guild_id: GuildId) -> GetEntityFuture<'_, GuildEntity, B::Error> {
+        utils::relation_map(self.get(guild_id), self.backend(), B::guilds, |preview| {
+            preview.id
+        })
+    }
+}
@@ -1,6 +1,6 @@
-use super::GuildEntity;
+use super::{GuildEntity, GuildRepository};
 use crate::{
-    repository::{GetEntityFuture, Repository},
+    repository::{GetEntityFuture, ListEntitiesFuture, Repository},
     utils, Backend, Entity,
 };
 use twilight_model::{
@@ -10,14 +10,23 @@ use twilight_model::{
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct RoleEntity {
     pub color: u32,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub guild_id: GuildId,
     pub hoist: bool,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: RoleId,
     pub managed: bool,
     pub mentionable: bool,
     pub name: String,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsBits))]
     pub permissions: Permissions,
     pub position: i64,
 }
@@ -38,6 +47,14 @@ impl From<(Role, GuildId)> for RoleEntity {
     }
 }
 
+impl RoleEntity {
+    /// Create a builder for a role with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(id: RoleId, guild_id: GuildId, name: impl Into<String>) -> RoleEntityBuilder {
+        RoleEntityBuilder::new(id, guild_id, name)
+    }
+}
+
 impl Entity for RoleEntity {
     type Id = RoleId;
 
@@ -47,14 +64,90 @@ impl Entity for RoleEntity {
     }
 }
 
+/// Builder to create a [`RoleEntity`] without the need to specify every field.
+///
+/// [`RoleEntity`]: struct.RoleEntity.html
+#[derive(Clone, Debug)]
+pub struct RoleEntityBuilder(RoleEntity);
+
+impl RoleEntityBuilder {
+    pub fn new(id: RoleId, guild_id: GuildId, name: impl Into<String>) -> Self {
+        Self(RoleEntity {
+            color: 0,
+            guild_id,
+            hoist: false,
+            id,
+            managed: false,
+            mentionable: false,
+            name: name.into(),
+            permissions: Permissions::empty(),
+            position: 0,
+        })
+    }
+
+    pub fn build(self) -> RoleEntity {
+        self.0
+    }
+
+    pub fn color(&mut self, color: u32) -> &mut Self {
+        self.0.color = color;
+
+        self
+    }
+
+    pub fn hoist(&mut self, hoist: bool) -> &mut Self {
+        self.0.hoist = hoist;
+
+        self
+    }
+
+    pub fn managed(&mut self, managed: bool) -> &mut Self {
+        self.0.managed = managed;
+
+        self
+    }
+
+    pub fn mentionable(&mut self, mentionable: bool) -> &mut Self {
+        self.0.mentionable = mentionable;
+
+        self
+    }
+
+    pub fn permissions(&mut self, permissions: Permissions) -> &mut Self {
+        self.0.permissions = permissions;
+
+        self
+    }
+
+    pub fn position(&mut self, position: i64) -> &mut Self {
+        self.0.position = position;
+
+        self
+    }
+}
+
 pub trait RoleRepository<B: Backend>: Repository<RoleEntity, B> {
     /// Retrieve the guild associated with a role.
     fn guild(&self, role_id: RoleId) -> GetEntityFuture<'_, GuildEntity, B::Error> {
-        utils::relation_map(
-            self.backend().roles(),
-            self.backend().guilds(),
-            role_id,
-            |role| role.guild_id,
+        utils::relation_map(self.get(role_id), self.backend(), B::guilds, |role| {
+            role.guild_id
+        })
+    }
+
+    /// Retrieve a stream of roles within a guild.
+    ///
+    /// **Backend implementations**: the default implementation retrieves the
+    /// IDs from [`GuildRepository::role_ids`]. Backends storing entities
+    /// by keys prefixed with their guild ID can instead scan the keys.
+    ///
+    /// [`GuildRepository::role_ids`]: ../trait.GuildRepository.html#tymethod.role_ids
+    fn list_by_guild(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, RoleEntity, B::Error> {
+        utils::stream_guild(
+            self.backend(),
+            guild_id,
+            |guilds, guild_id| guilds.role_ids(guild_id),
+            |_, role_id| role_id,
+            B::roles,
         )
     }
 }
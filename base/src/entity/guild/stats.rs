@@ -0,0 +1,74 @@
+use twilight_model::gateway::presence::Status;
+
+/// Counts of the cached entities within a guild.
+///
+/// Retrieved via [`GuildRepository::stats`].
+///
+/// [`GuildRepository::stats`]: ../trait.GuildRepository.html#method.stats
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct GuildStats {
+    /// Number of category channels.
+    pub category_channels: usize,
+    /// Number of emojis.
+    pub emojis: usize,
+    /// Number of cached members.
+    ///
+    /// This may be less than the guild's member count if not all members are
+    /// cached.
+    pub members: usize,
+    /// Number of presences by status.
+    pub presences: PresenceStats,
+    /// Number of roles, including the `@everyone` role.
+    pub roles: usize,
+    /// Number of text channels.
+    pub text_channels: usize,
+    /// Number of voice channels.
+    pub voice_channels: usize,
+    /// Number of users connected to a voice channel.
+    pub voice_states: usize,
+}
+
+impl GuildStats {
+    /// Total number of channels of all kinds.
+    pub const fn channels(&self) -> usize {
+        self.category_channels + self.text_channels + self.voice_channels
+    }
+}
+
+/// Counts of the cached presences within a guild by their status.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PresenceStats {
+    /// Number of presences with a status of "do not disturb".
+    pub dnd: usize,
+    /// Number of idle presences.
+    pub idle: usize,
+    /// Number of invisible presences.
+    ///
+    /// Discord sends the presences of invisible users as offline, so this is
+    /// usually only non-zero for the current user.
+    pub invisible: usize,
+    /// Number of offline presences.
+    pub offline: usize,
+    /// Number of online presences.
+    pub online: usize,
+}
+
+impl PresenceStats {
+    /// Count a presence with the given status.
+    pub fn add(&mut self, status: Status) {
+        match status {
+            Status::DoNotDisturb => self.dnd += 1,
+            Status::Idle => self.idle += 1,
+            Status::Invisible => self.invisible += 1,
+            Status::Offline => self.offline += 1,
+            Status::Online => self.online += 1,
+        }
+    }
+
+    /// Total number of presences of all statuses.
+    pub const fn total(&self) -> usize {
+        self.dnd + self.idle + self.invisible + self.offline + self.online
+    }
+}
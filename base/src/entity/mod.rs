@@ -1,19 +1,28 @@
+#[cfg(feature = "discord")]
 pub mod channel;
+#[cfg(feature = "discord")]
 pub mod gateway;
+#[cfg(feature = "discord")]
 pub mod guild;
+#[cfg(feature = "discord")]
 pub mod user;
+#[cfg(feature = "discord")]
 pub mod voice;
 
 use std::hash::Hash;
 
-/// Efficient cachable entities mapping to the models returned from Discord's
-/// API.
+/// Efficient cachable entities, such as those mapping to the models returned
+/// from Discord's API.
+///
+/// Entities of other platforms can be cached by implementing this trait and
+/// [`Repository`] for them, without the `discord` feature.
 ///
 /// For example, the [`EmojiEntity`] does not contain the user data within it,
 /// but contains only the ID of the user. This can act similar to foreign keys
 /// in a relational database.
 ///
 /// [`EmojiEntity`]: emoji/struct.EmojiEntity.html
+/// [`Repository`]: ../repository/trait.Repository.html
 pub trait Entity: Send + Sync {
     type Id: Copy + Eq + Hash + Send + Sync;
 
@@ -27,3 +36,18 @@ pub trait Entity: Send + Sync {
     /// [`MemberEntity`]: member/struct.MemberEntity.html
     fn id(&self) -> Self::Id;
 }
+
+/// Entity that may be built from partial data, such as the users mentioned in
+/// messages.
+///
+/// Partial payloads omit some of an entity's fields, which become `None` or
+/// empty in the entity built from them. Upserting such an entity as is would
+/// overwrite the known values of those fields in the cache, so it's merged
+/// into the cached entity first.
+pub trait MergePartial: Entity {
+    /// Merge an entity built from partial data into this entity, keeping the
+    /// values of the fields that the partial data may be missing and replacing
+    /// the rest.
+    #[must_use]
+    fn merge_partial(self, partial: Self) -> Self;
+}
@@ -10,16 +10,26 @@ use twilight_model::{
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct CurrentUserEntity {
     pub avatar: Option<String>,
     pub bot: bool,
     pub discriminator: String,
     pub email: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsBits>))]
     pub flags: Option<UserFlags>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: UserId,
     pub mfa_enabled: bool,
     pub name: String,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub premium_type: Option<PremiumType>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsBits>))]
     pub public_flags: Option<UserFlags>,
     pub verified: Option<bool>,
 }
@@ -42,6 +52,18 @@ impl From<CurrentUser> for CurrentUserEntity {
     }
 }
 
+impl CurrentUserEntity {
+    /// Create a builder for a current user with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(
+        id: UserId,
+        name: impl Into<String>,
+        discriminator: impl Into<String>,
+    ) -> CurrentUserEntityBuilder {
+        CurrentUserEntityBuilder::new(id, name, discriminator)
+    }
+}
+
 impl Entity for CurrentUserEntity {
     type Id = UserId;
 
@@ -51,6 +73,82 @@ impl Entity for CurrentUserEntity {
     }
 }
 
+/// Builder to create a [`CurrentUserEntity`] without the need to specify every field.
+///
+/// [`CurrentUserEntity`]: struct.CurrentUserEntity.html
+#[derive(Clone, Debug)]
+pub struct CurrentUserEntityBuilder(CurrentUserEntity);
+
+impl CurrentUserEntityBuilder {
+    pub fn new(id: UserId, name: impl Into<String>, discriminator: impl Into<String>) -> Self {
+        Self(CurrentUserEntity {
+            avatar: None,
+            bot: false,
+            discriminator: discriminator.into(),
+            email: None,
+            flags: None,
+            id,
+            mfa_enabled: false,
+            name: name.into(),
+            premium_type: None,
+            public_flags: None,
+            verified: None,
+        })
+    }
+
+    pub fn build(self) -> CurrentUserEntity {
+        self.0
+    }
+
+    pub fn avatar(&mut self, avatar: impl Into<String>) -> &mut Self {
+        self.0.avatar = Some(avatar.into());
+
+        self
+    }
+
+    pub fn bot(&mut self, bot: bool) -> &mut Self {
+        self.0.bot = bot;
+
+        self
+    }
+
+    pub fn email(&mut self, email: impl Into<String>) -> &mut Self {
+        self.0.email = Some(email.into());
+
+        self
+    }
+
+    pub fn flags(&mut self, flags: UserFlags) -> &mut Self {
+        self.0.flags = Some(flags);
+
+        self
+    }
+
+    pub fn mfa_enabled(&mut self, mfa_enabled: bool) -> &mut Self {
+        self.0.mfa_enabled = mfa_enabled;
+
+        self
+    }
+
+    pub fn premium_type(&mut self, premium_type: PremiumType) -> &mut Self {
+        self.0.premium_type = Some(premium_type);
+
+        self
+    }
+
+    pub fn public_flags(&mut self, public_flags: UserFlags) -> &mut Self {
+        self.0.public_flags = Some(public_flags);
+
+        self
+    }
+
+    pub fn verified(&mut self, verified: bool) -> &mut Self {
+        self.0.verified = Some(verified);
+
+        self
+    }
+}
+
 pub trait CurrentUserRepository<B: Backend>: SingleEntityRepository<CurrentUserEntity, B> {
     /// Retrieve a stream of guild IDs associated with the current user.
     fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, B::Error>;
@@ -2,31 +2,48 @@
 
 pub mod current_user;
 
-pub use self::current_user::{CurrentUserEntity, CurrentUserRepository};
+pub use self::current_user::{CurrentUserEntity, CurrentUserEntityBuilder, CurrentUserRepository};
 
 use crate::{
-    entity::{guild::GuildEntity, Entity},
+    cdn::{self, ImageFormat},
+    entity::{guild::GuildEntity, Entity, MergePartial},
     repository::{ListEntitiesFuture, ListEntityIdsFuture, Repository},
     utils, Backend,
 };
+use futures_util::{
+    future::{self, FutureExt, TryFutureExt},
+    stream::{self, StreamExt, TryStreamExt},
+};
+use std::time::SystemTime;
 use twilight_model::{
+    channel::message::Mention,
     id::{GuildId, UserId},
     user::{PremiumType, User, UserFlags},
 };
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct UserEntity {
     pub avatar: Option<String>,
     pub bot: bool,
     pub discriminator: String,
     pub email: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsBits>))]
     pub flags: Option<UserFlags>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub id: UserId,
     pub locale: Option<String>,
     pub mfa_enabled: Option<bool>,
     pub name: String,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsJson))]
     pub premium_type: Option<PremiumType>,
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsBits>))]
     pub public_flags: Option<UserFlags>,
     pub system: Option<bool>,
     pub verified: Option<bool>,
@@ -52,6 +69,67 @@ impl From<User> for UserEntity {
     }
 }
 
+impl From<Mention> for UserEntity {
+    fn from(mention: Mention) -> Self {
+        Self {
+            avatar: mention.avatar,
+            bot: mention.bot,
+            discriminator: mention.discriminator,
+            email: None,
+            flags: None,
+            id: mention.id,
+            locale: None,
+            mfa_enabled: None,
+            name: mention.name,
+            premium_type: None,
+            public_flags: Some(mention.public_flags),
+            system: None,
+            verified: None,
+        }
+    }
+}
+
+impl UserEntity {
+    /// Create a builder for an user with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(
+        id: UserId,
+        name: impl Into<String>,
+        discriminator: impl Into<String>,
+    ) -> UserEntityBuilder {
+        UserEntityBuilder::new(id, name, discriminator)
+    }
+
+    /// Return the URL of the user's avatar, or of the default avatar for
+    /// their discriminator if they don't have one.
+    ///
+    /// Refer to [`cdn::avatar_url`] for more information.
+    ///
+    /// [`cdn::avatar_url`]: ../../cdn/fn.avatar_url.html
+    pub fn avatar_url(&self, size: u16, format: ImageFormat) -> String {
+        cdn::avatar_url(
+            self.id,
+            &self.discriminator,
+            self.avatar.as_deref(),
+            size,
+            format,
+        )
+    }
+
+    /// Return the URL of the default avatar for the user's discriminator.
+    pub fn default_avatar_url(&self) -> String {
+        cdn::default_avatar_url(&self.discriminator)
+    }
+
+    /// Return whether the user's public flags contain a badge, such as
+    /// `UserFlags::VERIFIED_BOT_DEVELOPER`.
+    ///
+    /// Users whose public flags weren't sent by Discord have no badges.
+    pub fn has_badge(&self, flag: UserFlags) -> bool {
+        self.public_flags.is_some_and(|flags| flags.contains(flag))
+    }
+}
+
 impl Entity for UserEntity {
     type Id = UserId;
 
@@ -61,7 +139,162 @@ impl Entity for UserEntity {
     }
 }
 
+/// Users embedded in other payloads, such as the authors of messages, omit the
+/// fields that are only sent to the user themselves or with some payloads,
+/// such as their email and public flags. These keep their known values, while
+/// the fields sent with every user, such as the name and avatar, are replaced.
+impl MergePartial for UserEntity {
+    fn merge_partial(self, partial: Self) -> Self {
+        Self {
+            email: partial.email.or(self.email),
+            flags: partial.flags.or(self.flags),
+            locale: partial.locale.or(self.locale),
+            mfa_enabled: partial.mfa_enabled.or(self.mfa_enabled),
+            premium_type: partial.premium_type.or(self.premium_type),
+            public_flags: partial.public_flags.or(self.public_flags),
+            system: partial.system.or(self.system),
+            verified: partial.verified.or(self.verified),
+            ..partial
+        }
+    }
+}
+
+/// Builder to create an [`UserEntity`] without the need to specify every field.
+///
+/// [`UserEntity`]: struct.UserEntity.html
+#[derive(Clone, Debug)]
+pub struct UserEntityBuilder(UserEntity);
+
+impl UserEntityBuilder {
+    pub fn new(id: UserId, name: impl Into<String>, discriminator: impl Into<String>) -> Self {
+        Self(UserEntity {
+            avatar: None,
+            bot: false,
+            discriminator: discriminator.into(),
+            email: None,
+            flags: None,
+            id,
+            locale: None,
+            mfa_enabled: None,
+            name: name.into(),
+            premium_type: None,
+            public_flags: None,
+            system: None,
+            verified: None,
+        })
+    }
+
+    pub fn build(self) -> UserEntity {
+        self.0
+    }
+
+    pub fn avatar(&mut self, avatar: impl Into<String>) -> &mut Self {
+        self.0.avatar = Some(avatar.into());
+
+        self
+    }
+
+    pub fn bot(&mut self, bot: bool) -> &mut Self {
+        self.0.bot = bot;
+
+        self
+    }
+
+    pub fn email(&mut self, email: impl Into<String>) -> &mut Self {
+        self.0.email = Some(email.into());
+
+        self
+    }
+
+    pub fn flags(&mut self, flags: UserFlags) -> &mut Self {
+        self.0.flags = Some(flags);
+
+        self
+    }
+
+    pub fn locale(&mut self, locale: impl Into<String>) -> &mut Self {
+        self.0.locale = Some(locale.into());
+
+        self
+    }
+
+    pub fn mfa_enabled(&mut self, mfa_enabled: bool) -> &mut Self {
+        self.0.mfa_enabled = Some(mfa_enabled);
+
+        self
+    }
+
+    pub fn premium_type(&mut self, premium_type: PremiumType) -> &mut Self {
+        self.0.premium_type = Some(premium_type);
+
+        self
+    }
+
+    pub fn public_flags(&mut self, public_flags: UserFlags) -> &mut Self {
+        self.0.public_flags = Some(public_flags);
+
+        self
+    }
+
+    pub fn system(&mut self, system: bool) -> &mut Self {
+        self.0.system = Some(system);
+
+        self
+    }
+
+    pub fn verified(&mut self, verified: bool) -> &mut Self {
+        self.0.verified = Some(verified);
+
+        self
+    }
+}
+
+/// Previous avatar of a user, recorded when the cache saw it change.
+///
+/// Retrieved via [`UserRepository::avatar_history`].
+///
+/// [`UserRepository::avatar_history`]: trait.UserRepository.html#method.avatar_history
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AvatarChange {
+    /// When the cache saw the avatar change.
+    pub changed_at: SystemTime,
+    /// Hash of the avatar before the change, if the user had a custom
+    /// avatar.
+    pub old: Option<String>,
+}
+
+impl AvatarChange {
+    /// Return the URL of the avatar before the change.
+    ///
+    /// Refer to [`cdn::avatar_url`] for more information.
+    ///
+    /// [`cdn::avatar_url`]: ../../cdn/fn.avatar_url.html
+    pub fn avatar_url(&self, user: &UserEntity, size: u16, format: ImageFormat) -> String {
+        cdn::avatar_url(
+            user.id,
+            &user.discriminator,
+            self.old.as_deref(),
+            size,
+            format,
+        )
+    }
+}
+
 pub trait UserRepository<B: Backend>: Repository<UserEntity, B> {
+    /// Retrieve a stream of the previous avatars of a user, from oldest to
+    /// most recent.
+    ///
+    /// Only changes seen by the cache are recorded. Recording them is
+    /// optional: backends that don't track avatar history or have it disabled
+    /// return an empty stream.
+    ///
+    /// **Backend implementations**: the default implementation returns an
+    /// empty stream. Backends that record avatars as users are upserted
+    /// should implement this manually.
+    fn avatar_history(&self, _user_id: UserId) -> ListEntitiesFuture<'_, AvatarChange, B::Error> {
+        future::ok(stream::empty().boxed()).boxed()
+    }
+
     /// Retrieve a stream of guild IDs associated with a user.
     fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, B::Error>;
 
@@ -69,4 +302,24 @@ pub trait UserRepository<B: Backend>: Repository<UserEntity, B> {
     fn guilds(&self, user_id: UserId) -> ListEntitiesFuture<'_, GuildEntity, B::Error> {
         utils::stream_ids(self.guild_ids(user_id), self.backend().guilds())
     }
+
+    /// Retrieve a stream of the users whose public flags contain a flag.
+    ///
+    /// Refer to [`UserEntity::has_badge`] for more information.
+    ///
+    /// **Backend implementations**: the default implementation filters the
+    /// users from [`list`]. Backends indexing users by their flags should
+    /// implement this manually.
+    ///
+    /// [`UserEntity::has_badge`]: struct.UserEntity.html#method.has_badge
+    /// [`list`]: ../../repository/trait.Repository.html#tymethod.list
+    fn with_public_flag(&self, flag: UserFlags) -> ListEntitiesFuture<'_, UserEntity, B::Error> {
+        self.list()
+            .map_ok(move |users| {
+                users
+                    .try_filter(move |user| future::ready(user.has_badge(flag)))
+                    .boxed()
+            })
+            .boxed()
+    }
 }
@@ -1,5 +1,12 @@
 //! Entities relating to the voice API.
 
+pub mod region;
 pub mod state;
 
-pub use self::state::{VoiceStateEntity, VoiceStateRepository};
+pub use self::{
+    region::{VoiceRegionEntity, VoiceRegionEntityBuilder, VoiceRegionId, VoiceRegionRepository},
+    state::{
+        VoiceStateChange, VoiceStateEntity, VoiceStateEntityBuilder, VoiceStateRepository,
+        VoiceTransition,
+    },
+};
@@ -0,0 +1,167 @@
+use crate::{
+    repository::{GetEntityFuture, Repository},
+    Backend, Entity,
+};
+use futures_util::stream::TryStreamExt;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use twilight_model::voice::VoiceRegion;
+
+/// ID of a voice region.
+///
+/// Discord identifies voice regions by their names, such as `us-east`, so the
+/// ID is derived from a region's name. It's the same for a name across
+/// processes and versions of the cache, so it can be stored.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct VoiceRegionId(pub u64);
+
+impl VoiceRegionId {
+    /// Create the ID of a voice region from its name, such as `us-east`.
+    pub fn new(region: &str) -> Self {
+        // FNV-1a, shifted to fit in the positive range of an `i64` like
+        // snowflakes do
+        let hash = region.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+
+        Self(hash >> 1)
+    }
+}
+
+impl Display for VoiceRegionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// Cachable version of a voice region.
+///
+/// Discord doesn't send voice regions over the gateway: upsert them after
+/// retrieving them over HTTP, or manually, to validate the regions that
+/// voice channels are moved to.
+#[allow(clippy::struct_excessive_bools)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
+pub struct VoiceRegionEntity {
+    pub custom: bool,
+    pub deprecated: bool,
+    /// Name identifying the region, such as `us-east`.
+    pub id: String,
+    pub name: String,
+    pub optimal: bool,
+    pub vip: bool,
+}
+
+impl From<VoiceRegion> for VoiceRegionEntity {
+    fn from(region: VoiceRegion) -> Self {
+        Self {
+            custom: region.custom,
+            deprecated: region.deprecated,
+            id: region.id,
+            name: region.name,
+            optimal: region.optimal,
+            vip: region.vip,
+        }
+    }
+}
+
+impl VoiceRegionEntity {
+    /// Create a builder for a voice region with the required fields set and
+    /// the remaining fields set to their defaults.
+    pub fn builder(id: impl Into<String>, name: impl Into<String>) -> VoiceRegionEntityBuilder {
+        VoiceRegionEntityBuilder::new(id, name)
+    }
+}
+
+impl Entity for VoiceRegionEntity {
+    type Id = VoiceRegionId;
+
+    /// Return the ID derived from the region's name.
+    fn id(&self) -> Self::Id {
+        VoiceRegionId::new(&self.id)
+    }
+}
+
+/// Builder to create a [`VoiceRegionEntity`] without the need to specify
+/// every field.
+///
+/// [`VoiceRegionEntity`]: struct.VoiceRegionEntity.html
+#[derive(Clone, Debug)]
+pub struct VoiceRegionEntityBuilder(VoiceRegionEntity);
+
+impl VoiceRegionEntityBuilder {
+    pub fn new(id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self(VoiceRegionEntity {
+            custom: false,
+            deprecated: false,
+            id: id.into(),
+            name: name.into(),
+            optimal: false,
+            vip: false,
+        })
+    }
+
+    pub fn build(self) -> VoiceRegionEntity {
+        self.0
+    }
+
+    pub fn custom(&mut self, custom: bool) -> &mut Self {
+        self.0.custom = custom;
+
+        self
+    }
+
+    pub fn deprecated(&mut self, deprecated: bool) -> &mut Self {
+        self.0.deprecated = deprecated;
+
+        self
+    }
+
+    pub fn optimal(&mut self, optimal: bool) -> &mut Self {
+        self.0.optimal = optimal;
+
+        self
+    }
+
+    pub fn vip(&mut self, vip: bool) -> &mut Self {
+        self.0.vip = vip;
+
+        self
+    }
+}
+
+/// Repository to work with voice regions.
+pub trait VoiceRegionRepository<B: Backend>: Repository<VoiceRegionEntity, B> {
+    /// Retrieve a voice region by its name, such as `us-east`.
+    ///
+    /// Returns `None` if the region isn't cached, such as when the name isn't
+    /// a valid region.
+    fn by_name(&self, region: &str) -> GetEntityFuture<'_, VoiceRegionEntity, B::Error> {
+        self.get(VoiceRegionId::new(region))
+    }
+
+    /// Retrieve the voice region closest to the current user.
+    ///
+    /// Returns `None` if no cached region is marked as optimal.
+    fn optimal(&self) -> GetEntityFuture<'_, VoiceRegionEntity, B::Error> {
+        let regions = self.list();
+
+        Box::pin(async move {
+            let mut regions = regions.await?;
+
+            while let Some(region) = regions.try_next().await? {
+                if region.optimal {
+                    return Ok(Some(region));
+                }
+            }
+
+            Ok(None)
+        })
+    }
+}
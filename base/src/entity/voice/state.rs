@@ -1,8 +1,13 @@
-use super::super::channel::VoiceChannelEntity;
+use super::super::{channel::VoiceChannelEntity, guild::GuildRepository};
 use crate::{
-    repository::{GetEntityFuture, Repository},
+    repository::{GetEntityFuture, ListEntitiesFuture, Repository},
     utils, Backend, Entity,
 };
+use futures_util::{
+    future::{self, FutureExt, TryFutureExt},
+    stream::{self, StreamExt},
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use twilight_model::{
     id::{ChannelId, GuildId, UserId},
     voice::VoiceState,
@@ -11,10 +16,27 @@ use twilight_model::{
 #[allow(clippy::struct_excessive_bools)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize),
+    archive(check_bytes),
+    archive_attr(derive(Debug))
+)]
 pub struct VoiceStateEntity {
+    #[cfg_attr(feature = "rkyv", with(rkyv::with::Map<crate::archive::AsId>))]
     pub channel_id: Option<ChannelId>,
     pub deaf: bool,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub guild_id: GuildId,
+    /// Milliseconds since the Unix epoch at which the user joined the
+    /// channel.
+    ///
+    /// This is recorded by the cache when it processes a voice state update
+    /// in which the user joins a channel, so it's `None` if the user was
+    /// already in the channel when the cache started tracking the voice
+    /// state.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub joined_channel_at: Option<u64>,
     pub mute: bool,
     pub self_deaf: bool,
     pub self_mute: bool,
@@ -22,6 +44,7 @@ pub struct VoiceStateEntity {
     pub session_id: String,
     pub suppress: bool,
     pub token: Option<String>,
+    #[cfg_attr(feature = "rkyv", with(crate::archive::AsId))]
     pub user_id: UserId,
 }
 
@@ -31,6 +54,7 @@ impl From<(VoiceState, GuildId)> for VoiceStateEntity {
             channel_id: voice_state.channel_id,
             deaf: voice_state.deaf,
             guild_id,
+            joined_channel_at: None,
             mute: voice_state.mute,
             self_deaf: voice_state.self_deaf,
             self_mute: voice_state.self_mute,
@@ -43,6 +67,30 @@ impl From<(VoiceState, GuildId)> for VoiceStateEntity {
     }
 }
 
+impl VoiceStateEntity {
+    /// Create a builder for a voice state with the required fields set and the
+    /// remaining fields set to their defaults.
+    pub fn builder(
+        guild_id: GuildId,
+        user_id: UserId,
+        session_id: impl Into<String>,
+    ) -> VoiceStateEntityBuilder {
+        VoiceStateEntityBuilder::new(guild_id, user_id, session_id)
+    }
+
+    /// Return how long the user has been in their current channel.
+    ///
+    /// Returns `None` if the user isn't in a channel or it's unknown when they
+    /// joined it.
+    pub fn session_duration(&self) -> Option<Duration> {
+        self.channel_id?;
+        let joined_at = Duration::from_millis(self.joined_channel_at?);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+
+        Some(now.saturating_sub(joined_at))
+    }
+}
+
 impl Entity for VoiceStateEntity {
     type Id = (GuildId, UserId);
 
@@ -52,6 +100,180 @@ impl Entity for VoiceStateEntity {
     }
 }
 
+/// Change to a voice state's channel or the flags set by moderators.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum VoiceTransition {
+    /// The user joined, left, or moved between channels.
+    Channel {
+        /// Channel before the change, if the user was in one.
+        old: Option<ChannelId>,
+        /// Channel after the change, if the user is in one.
+        new: Option<ChannelId>,
+    },
+    /// The user was deafened or undeafened by the guild.
+    Deaf {
+        /// Whether the user was deafened before the change.
+        old: bool,
+        /// Whether the user is deafened after the change.
+        new: bool,
+    },
+    /// The user was muted or unmuted by the guild.
+    Mute {
+        /// Whether the user was muted before the change.
+        old: bool,
+        /// Whether the user is muted after the change.
+        new: bool,
+    },
+    /// The user was suppressed or unsuppressed in a stage channel.
+    Suppress {
+        /// Whether the user was suppressed before the change.
+        old: bool,
+        /// Whether the user is suppressed after the change.
+        new: bool,
+    },
+}
+
+impl VoiceTransition {
+    /// Return the transitions from a previous version of a voice state to a
+    /// newer one.
+    ///
+    /// A voice state without a previous version is compared to a user who
+    /// isn't in a channel and has no flags set.
+    pub fn between(old: Option<&VoiceStateEntity>, new: &VoiceStateEntity) -> Vec<Self> {
+        let (channel_id, deaf, mute, suppress) = old.map_or((None, false, false, false), |old| {
+            (old.channel_id, old.deaf, old.mute, old.suppress)
+        });
+        let mut transitions = Vec::new();
+
+        if channel_id != new.channel_id {
+            transitions.push(Self::Channel {
+                old: channel_id,
+                new: new.channel_id,
+            });
+        }
+
+        if deaf != new.deaf {
+            transitions.push(Self::Deaf {
+                old: deaf,
+                new: new.deaf,
+            });
+        }
+
+        if mute != new.mute {
+            transitions.push(Self::Mute {
+                old: mute,
+                new: new.mute,
+            });
+        }
+
+        if suppress != new.suppress {
+            transitions.push(Self::Suppress {
+                old: suppress,
+                new: new.suppress,
+            });
+        }
+
+        transitions
+    }
+}
+
+/// Transition of a voice state, recorded when the cache saw it.
+///
+/// Retrieved via [`VoiceStateRepository::transition_history`].
+///
+/// [`VoiceStateRepository::transition_history`]: trait.VoiceStateRepository.html#method.transition_history
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VoiceStateChange {
+    /// When the cache saw the transition.
+    pub changed_at: SystemTime,
+    /// Transition that was made.
+    pub transition: VoiceTransition,
+}
+
+/// Builder to create a [`VoiceStateEntity`] without the need to specify every field.
+///
+/// [`VoiceStateEntity`]: struct.VoiceStateEntity.html
+#[derive(Clone, Debug)]
+pub struct VoiceStateEntityBuilder(VoiceStateEntity);
+
+impl VoiceStateEntityBuilder {
+    pub fn new(guild_id: GuildId, user_id: UserId, session_id: impl Into<String>) -> Self {
+        Self(VoiceStateEntity {
+            channel_id: None,
+            deaf: false,
+            guild_id,
+            joined_channel_at: None,
+            mute: false,
+            self_deaf: false,
+            self_mute: false,
+            self_stream: false,
+            session_id: session_id.into(),
+            suppress: false,
+            token: None,
+            user_id,
+        })
+    }
+
+    pub fn build(self) -> VoiceStateEntity {
+        self.0
+    }
+
+    pub fn channel_id(&mut self, channel_id: ChannelId) -> &mut Self {
+        self.0.channel_id = Some(channel_id);
+
+        self
+    }
+
+    pub fn deaf(&mut self, deaf: bool) -> &mut Self {
+        self.0.deaf = deaf;
+
+        self
+    }
+
+    pub fn joined_channel_at(&mut self, joined_channel_at: u64) -> &mut Self {
+        self.0.joined_channel_at = Some(joined_channel_at);
+
+        self
+    }
+
+    pub fn mute(&mut self, mute: bool) -> &mut Self {
+        self.0.mute = mute;
+
+        self
+    }
+
+    pub fn self_deaf(&mut self, self_deaf: bool) -> &mut Self {
+        self.0.self_deaf = self_deaf;
+
+        self
+    }
+
+    pub fn self_mute(&mut self, self_mute: bool) -> &mut Self {
+        self.0.self_mute = self_mute;
+
+        self
+    }
+
+    pub fn self_stream(&mut self, self_stream: bool) -> &mut Self {
+        self.0.self_stream = self_stream;
+
+        self
+    }
+
+    pub fn suppress(&mut self, suppress: bool) -> &mut Self {
+        self.0.suppress = suppress;
+
+        self
+    }
+
+    pub fn token(&mut self, token: impl Into<String>) -> &mut Self {
+        self.0.token = Some(token.into());
+
+        self
+    }
+}
+
 pub trait VoiceStateRepository<B: Backend>: Repository<VoiceStateEntity, B> {
     /// Retrieve the channel associated with a webhook.
     ///
@@ -64,10 +286,59 @@ pub trait VoiceStateRepository<B: Backend>: Repository<VoiceStateEntity, B> {
         user_id: UserId,
     ) -> GetEntityFuture<'_, VoiceChannelEntity, B::Error> {
         utils::relation_and_then(
-            self.backend().voice_states(),
-            self.backend().voice_channels(),
-            (guild_id, user_id),
+            self.get((guild_id, user_id)),
+            self.backend(),
+            B::voice_channels,
             |state| state.channel_id,
         )
     }
+
+    /// Retrieve how long a user has been in their current voice channel.
+    ///
+    /// Refer to [`VoiceStateEntity::session_duration`] for more information.
+    ///
+    /// [`VoiceStateEntity::session_duration`]: struct.VoiceStateEntity.html#method.session_duration
+    fn session_duration(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> GetEntityFuture<'_, Duration, B::Error> {
+        self.get((guild_id, user_id))
+            .map_ok(|state| state.and_then(|state| state.session_duration()))
+            .boxed()
+    }
+
+    /// Retrieve a stream of a user's previous voice state transitions in a
+    /// guild, oldest first.
+    ///
+    /// **Backend implementations**: the default implementation returns an
+    /// empty stream. Backends that record transitions when an upsert changes
+    /// a voice state's channel or moderation flags should implement this.
+    fn transition_history(
+        &self,
+        _guild_id: GuildId,
+        _user_id: UserId,
+    ) -> ListEntitiesFuture<'_, VoiceStateChange, B::Error> {
+        future::ok(stream::empty().boxed()).boxed()
+    }
+
+    /// Retrieve a stream of voice states within a guild.
+    ///
+    /// **Backend implementations**: the default implementation retrieves the
+    /// IDs from [`GuildRepository::voice_state_ids`]. Backends storing entities
+    /// by keys prefixed with their guild ID can instead scan the keys.
+    ///
+    /// [`GuildRepository::voice_state_ids`]: ../guild/trait.GuildRepository.html#tymethod.voice_state_ids
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceStateEntity, B::Error> {
+        utils::stream_guild(
+            self.backend(),
+            guild_id,
+            |guilds, guild_id| guilds.voice_state_ids(guild_id),
+            |guild_id, user_id| (guild_id, user_id),
+            B::voice_states,
+        )
+    }
 }
@@ -0,0 +1,332 @@
+//! Export of cached entities as newline-delimited JSON.
+//!
+//! [`export_ndjson`] writes the entities of the selected types stored by a
+//! backend to a writer, one JSON object per line. Each line contains the
+//! [name] of the entity's type and the entity itself, so exports can be
+//! filtered with line-oriented tools or loaded into other programs for
+//! analysis without reading the whole export at once, unlike a
+//! [`Snapshot`].
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{
+//!     entity::guild::GuildEntity,
+//!     export::{self, ExportType},
+//!     repository::Repository,
+//!     Backend,
+//! };
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let backend = InMemoryBackend::new();
+//! let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+//! backend.guilds().upsert(guild).await?;
+//!
+//! let mut output = Vec::new();
+//! let exported = export::export_ndjson(&backend, &[ExportType::Guild], &mut output).await?;
+//!
+//! assert_eq!(1, exported);
+//! assert!(String::from_utf8(output)?.starts_with(r#"{"type":"guild","entity":{"#));
+//! # Ok(()) }
+//! ```
+//!
+//! [`export_ndjson`]: fn.export_ndjson.html
+//! [`Snapshot`]: ../snapshot/struct.Snapshot.html
+//! [name]: enum.ExportType.html#method.name
+
+use super::{
+    backend::Backend,
+    entity::Entity,
+    repository::{Repository, SingleEntityRepository},
+};
+use futures_util::stream::TryStreamExt;
+use serde::Serialize;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::File,
+    io::{BufWriter, Error as IoError, Write},
+    path::Path,
+};
+
+/// Type of entity that can be exported.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ExportType {
+    Attachment,
+    CategoryChannel,
+    CurrentUser,
+    Emoji,
+    Group,
+    Guild,
+    GuildPreview,
+    Member,
+    Message,
+    Presence,
+    PrivateChannel,
+    Role,
+    TextChannel,
+    User,
+    VoiceChannel,
+    VoiceRegion,
+    VoiceState,
+}
+
+impl ExportType {
+    /// Every type of entity, in the order that they're exported in.
+    pub const ALL: &'static [Self] = &[
+        Self::Attachment,
+        Self::CategoryChannel,
+        Self::CurrentUser,
+        Self::Emoji,
+        Self::Group,
+        Self::Guild,
+        Self::GuildPreview,
+        Self::Member,
+        Self::Message,
+        Self::Presence,
+        Self::PrivateChannel,
+        Self::Role,
+        Self::TextChannel,
+        Self::User,
+        Self::VoiceChannel,
+        Self::VoiceRegion,
+        Self::VoiceState,
+    ];
+
+    /// Return the name of the type, which is included in each exported line.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Attachment => "attachment",
+            Self::CategoryChannel => "category_channel",
+            Self::CurrentUser => "current_user",
+            Self::Emoji => "emoji",
+            Self::Group => "group",
+            Self::Guild => "guild",
+            Self::GuildPreview => "guild_preview",
+            Self::Member => "member",
+            Self::Message => "message",
+            Self::Presence => "presence",
+            Self::PrivateChannel => "private_channel",
+            Self::Role => "role",
+            Self::TextChannel => "text_channel",
+            Self::User => "user",
+            Self::VoiceChannel => "voice_channel",
+            Self::VoiceRegion => "voice_region",
+            Self::VoiceState => "voice_state",
+        }
+    }
+}
+
+/// Error returned when exporting entities fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ExportError<E> {
+    /// Listing the entities of a type failed.
+    Backend {
+        /// Reason for the error.
+        source: E,
+    },
+    /// Creating the file to export to or writing to it failed.
+    Io {
+        /// Reason for the error.
+        source: IoError,
+    },
+    /// Serializing an entity failed.
+    Serialization {
+        /// Reason for the error.
+        source: serde_json::Error,
+    },
+}
+
+impl<E> Display for ExportError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Backend { .. } => f.write_str("listing entities failed"),
+            Self::Io { .. } => f.write_str("writing the export failed"),
+            Self::Serialization { .. } => f.write_str("serializing an entity failed"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for ExportError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Backend { source } => Some(source),
+            Self::Io { source } => Some(source),
+            Self::Serialization { source } => Some(source),
+        }
+    }
+}
+
+impl<E> From<IoError> for ExportError<E> {
+    fn from(source: IoError) -> Self {
+        Self::Io { source }
+    }
+}
+
+impl<E> From<serde_json::Error> for ExportError<E> {
+    fn from(source: serde_json::Error) -> Self {
+        Self::Serialization { source }
+    }
+}
+
+/// Line of an export.
+#[derive(Serialize)]
+struct ExportLine<'a, E> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    entity: &'a E,
+}
+
+/// Write the entities of the selected types stored by a backend to a writer
+/// as newline-delimited JSON, returning the number of entities written.
+///
+/// Types are exported in the order they're selected in. Entities are listed
+/// one type at a time, so entities that are modified while the export is
+/// being written may or may not be included.
+///
+/// The writer isn't buffered, so wrap writers such as files in a
+/// [`BufWriter`].
+///
+/// # Errors
+///
+/// Returns [`ExportError::Backend`] if listing any type of entity fails.
+///
+/// Returns [`ExportError::Io`] if writing to the writer fails.
+///
+/// Returns [`ExportError::Serialization`] if serializing an entity fails.
+///
+/// [`BufWriter`]: https://doc.rust-lang.org/std/io/struct.BufWriter.html
+/// [`ExportError::Backend`]: enum.ExportError.html#variant.Backend
+/// [`ExportError::Io`]: enum.ExportError.html#variant.Io
+/// [`ExportError::Serialization`]: enum.ExportError.html#variant.Serialization
+pub async fn export_ndjson<B: Backend>(
+    backend: &B,
+    types: &[ExportType],
+    mut writer: impl Write,
+) -> Result<u64, ExportError<B::Error>> {
+    let mut exported = 0;
+
+    for kind in types {
+        exported += match kind {
+            ExportType::Attachment => {
+                write_entities(backend.attachments(), *kind, &mut writer).await?
+            }
+            ExportType::CategoryChannel => {
+                write_entities(backend.category_channels(), *kind, &mut writer).await?
+            }
+            ExportType::CurrentUser => {
+                let current_user = backend
+                    .current_user()
+                    .get()
+                    .await
+                    .map_err(|source| ExportError::Backend { source })?;
+
+                match current_user {
+                    Some(current_user) => {
+                        write_line(&mut writer, *kind, &current_user)?;
+
+                        1
+                    }
+                    None => 0,
+                }
+            }
+            ExportType::Emoji => write_entities(backend.emojis(), *kind, &mut writer).await?,
+            ExportType::Group => write_entities(backend.groups(), *kind, &mut writer).await?,
+            ExportType::Guild => write_entities(backend.guilds(), *kind, &mut writer).await?,
+            ExportType::GuildPreview => {
+                write_entities(backend.guild_previews(), *kind, &mut writer).await?
+            }
+            ExportType::Member => write_entities(backend.members(), *kind, &mut writer).await?,
+            ExportType::Message => write_entities(backend.messages(), *kind, &mut writer).await?,
+            ExportType::Presence => write_entities(backend.presences(), *kind, &mut writer).await?,
+            ExportType::PrivateChannel => {
+                write_entities(backend.private_channels(), *kind, &mut writer).await?
+            }
+            ExportType::Role => write_entities(backend.roles(), *kind, &mut writer).await?,
+            ExportType::TextChannel => {
+                write_entities(backend.text_channels(), *kind, &mut writer).await?
+            }
+            ExportType::User => write_entities(backend.users(), *kind, &mut writer).await?,
+            ExportType::VoiceChannel => {
+                write_entities(backend.voice_channels(), *kind, &mut writer).await?
+            }
+            ExportType::VoiceRegion => {
+                write_entities(backend.voice_regions(), *kind, &mut writer).await?
+            }
+            ExportType::VoiceState => {
+                write_entities(backend.voice_states(), *kind, &mut writer).await?
+            }
+        };
+    }
+
+    writer.flush()?;
+
+    Ok(exported)
+}
+
+/// Write the entities of the selected types stored by a backend to a file as
+/// newline-delimited JSON, returning the number of entities written.
+///
+/// The file is created if it doesn't exist and truncated if it does. Refer to
+/// [`export_ndjson`] for more information.
+///
+/// # Errors
+///
+/// Returns [`ExportError::Io`] if creating the file fails, in addition to the
+/// errors returned by [`export_ndjson`].
+///
+/// [`ExportError::Io`]: enum.ExportError.html#variant.Io
+/// [`export_ndjson`]: fn.export_ndjson.html
+pub async fn export_ndjson_file<B: Backend>(
+    backend: &B,
+    types: &[ExportType],
+    path: impl AsRef<Path>,
+) -> Result<u64, ExportError<B::Error>> {
+    let file = File::create(path)?;
+
+    export_ndjson(backend, types, BufWriter::new(file)).await
+}
+
+async fn write_entities<B: Backend, E: Entity + Serialize, R: Repository<E, B>>(
+    repo: R,
+    kind: ExportType,
+    writer: &mut impl Write,
+) -> Result<u64, ExportError<B::Error>> {
+    let mut entities = repo
+        .list()
+        .await
+        .map_err(|source| ExportError::Backend { source })?;
+    let mut written = 0;
+
+    while let Some(entity) = entities
+        .try_next()
+        .await
+        .map_err(|source| ExportError::Backend { source })?
+    {
+        write_line(writer, kind, &entity)?;
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+fn write_line<T: Serialize, E>(
+    writer: &mut impl Write,
+    kind: ExportType,
+    entity: &T,
+) -> Result<(), ExportError<E>> {
+    serde_json::to_writer(
+        &mut *writer,
+        &ExportLine {
+            kind: kind.name(),
+            entity,
+        },
+    )?;
+    writer.write_all(b"\n")?;
+
+    Ok(())
+}
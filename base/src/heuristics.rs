@@ -0,0 +1,154 @@
+//! Heuristics for detecting raids from cached data.
+//!
+//! Security bots commonly look at how many members joined a guild recently
+//! and how old the accounts of those members are, since raids are often
+//! carried out by many new accounts joining at once. Both only need cached
+//! data, so [`GuildHeuristics`] provides them for every guild repository.
+//!
+//! Joins are retrieved with [`GuildRepository::recent_joins`]; backends that
+//! index a limited number of recent joins per guild only consider those.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use twilight_cache::heuristics::GuildHeuristics;
+//! use twilight_cache_inmemory::InMemoryCache;
+//! use twilight_model::id::GuildId;
+//!
+//! # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cache = InMemoryCache::new();
+//!
+//! let joins = cache.guilds.join_rate(GuildId(1), Duration::from_secs(60)).await?;
+//! let ages = cache.guilds.account_ages(GuildId(1), 50).await?;
+//!
+//! if joins > 10 && ages.under_day * 2 > ages.total() {
+//!     println!("possible raid");
+//! }
+//! # Ok(()) }
+//! ```
+//!
+//! [`GuildHeuristics`]: trait.GuildHeuristics.html
+//! [`GuildRepository::recent_joins`]: ../entity/guild/trait.GuildRepository.html#method.recent_joins
+
+use super::{entity::guild::GuildRepository, snowflake::SnowflakeExt, timestamp, Backend};
+use futures_util::stream::TryStreamExt;
+use std::{
+    convert::TryFrom,
+    future::Future,
+    pin::Pin,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use twilight_model::id::GuildId;
+
+/// Future resolving to the result of a heuristic.
+pub type HeuristicFuture<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>;
+
+/// Seconds in a day.
+const DAY: u64 = 86_400;
+
+/// Number of accounts by how long ago they were created.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct AccountAges {
+    /// Number of accounts created less than a day ago.
+    pub under_day: usize,
+    /// Number of accounts created at least a day but less than a week ago.
+    pub under_week: usize,
+    /// Number of accounts created at least a week but less than 30 days ago.
+    pub under_month: usize,
+    /// Number of accounts created at least 30 days ago.
+    pub older: usize,
+}
+
+impl AccountAges {
+    /// Count an account of the given age.
+    pub fn add(&mut self, age: Duration) {
+        let age = age.as_secs();
+
+        if age < DAY {
+            self.under_day += 1;
+        } else if age < DAY * 7 {
+            self.under_week += 1;
+        } else if age < DAY * 30 {
+            self.under_month += 1;
+        } else {
+            self.older += 1;
+        }
+    }
+
+    /// Return the total number of accounts.
+    pub const fn total(&self) -> usize {
+        self.under_day + self.under_week + self.under_month + self.older
+    }
+}
+
+/// Raid heuristics computed from a guild's recent joins.
+///
+/// Implemented for every [`GuildRepository`]. Refer to the
+/// [module-level documentation] for more information.
+///
+/// [`GuildRepository`]: ../entity/guild/trait.GuildRepository.html
+/// [module-level documentation]: index.html
+pub trait GuildHeuristics<B: Backend>: GuildRepository<B> {
+    /// Return the number of members that joined a guild within a window of
+    /// time before now.
+    ///
+    /// Members with an invalid join date are skipped.
+    fn join_rate(
+        &self,
+        guild_id: GuildId,
+        window: Duration,
+    ) -> HeuristicFuture<'_, usize, B::Error> {
+        let cutoff = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .saturating_sub(window)
+            .as_nanos();
+        let joins = self.recent_joins(guild_id, usize::MAX);
+
+        Box::pin(async move {
+            let mut joins = joins.await?;
+            let mut count = 0;
+
+            while let Some((_, joined_at)) = joins.try_next().await? {
+                let Ok(joined_at) = timestamp::parse(&joined_at) else {
+                    continue;
+                };
+
+                if u128::try_from(joined_at.unix_timestamp_nanos()).unwrap_or_default() < cutoff {
+                    break;
+                }
+
+                count += 1;
+            }
+
+            Ok(count)
+        })
+    }
+
+    /// Return the distribution of the account ages of the most recent members
+    /// to join a guild, considering at most `limit` joins.
+    ///
+    /// Ages are derived from the creation date encoded in user IDs.
+    fn account_ages(
+        &self,
+        guild_id: GuildId,
+        limit: usize,
+    ) -> HeuristicFuture<'_, AccountAges, B::Error> {
+        let now = SystemTime::now();
+        let joins = self.recent_joins(guild_id, limit);
+
+        Box::pin(async move {
+            joins
+                .await?
+                .try_fold(AccountAges::default(), move |mut ages, (user_id, _)| {
+                    ages.add(now.duration_since(user_id.created_at()).unwrap_or_default());
+
+                    async move { Ok(ages) }
+                })
+                .await
+        })
+    }
+}
+
+impl<B: Backend, T: GuildRepository<B> + ?Sized> GuildHeuristics<B> for T {}
@@ -0,0 +1,130 @@
+//! Notifications of changes made to the cache while processing events.
+//!
+//! Hooks registered with [`Cache::add_hook`] are called with a [`CacheChange`]
+//! describing the previous and current versions of entities, so consumers
+//! don't need to fetch an entity before processing an event to find out what
+//! changed about it.
+//!
+//! # Examples
+//!
+//! Print when users start streaming:
+//!
+//! ```
+//! use twilight_cache::hook::CacheChange;
+//! use twilight_cache_inmemory::InMemoryCache;
+//!
+//! let mut cache = InMemoryCache::new();
+//! cache.add_hook(|change| match change {
+//!     CacheChange::PresenceChanged { old, new } => {
+//!         let was_streaming = old.as_ref().map_or(false, |old| old.is_streaming());
+//!
+//!         if new.is_streaming() && !was_streaming {
+//!             println!("user {} started streaming", new.user_id);
+//!         }
+//!     }
+//!     _ => {}
+//! });
+//! ```
+//!
+//! [`Cache::add_hook`]: ../struct.Cache.html#method.add_hook
+//! [`CacheChange`]: enum.CacheChange.html
+
+use super::entity::{gateway::PresenceEntity, voice::VoiceTransition};
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+use twilight_model::id::{ChannelId, GuildId, UserId};
+
+/// Change made to the cache while processing an event.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum CacheChange {
+    /// The owner of a guild changed.
+    ///
+    /// The previous owner is also recorded in the guild's
+    /// [`previous_owner_ids`].
+    ///
+    /// [`previous_owner_ids`]: ../entity/guild/struct.GuildEntity.html#structfield.previous_owner_ids
+    OwnerChanged {
+        /// ID of the guild.
+        guild_id: GuildId,
+        /// ID of the previous owner.
+        old: UserId,
+        /// ID of the new owner.
+        new: UserId,
+    },
+    /// The presence of a user in a guild changed, such as their status or
+    /// activities.
+    ///
+    /// This isn't emitted when a presence update doesn't change the cached
+    /// presence.
+    PresenceChanged {
+        /// Previously cached presence, if any.
+        old: Option<PresenceEntity>,
+        /// Presence after the update.
+        new: PresenceEntity,
+    },
+    /// The slowmode of a text channel changed, which is the number of seconds
+    /// that users have to wait between sending messages.
+    ///
+    /// This is only emitted for channels that were already cached.
+    SlowmodeChanged {
+        /// ID of the guild, if known.
+        guild_id: Option<GuildId>,
+        /// ID of the channel.
+        channel_id: ChannelId,
+        /// Previous slowmode, if any.
+        old: Option<u64>,
+        /// Slowmode after the update, if any.
+        new: Option<u64>,
+    },
+    /// The topic of a text channel changed.
+    ///
+    /// This is only emitted for channels that were already cached.
+    TopicChanged {
+        /// ID of the guild, if known.
+        guild_id: Option<GuildId>,
+        /// ID of the channel.
+        channel_id: ChannelId,
+        /// Previous topic, if any.
+        old: Option<String>,
+        /// Topic after the update, if any.
+        new: Option<String>,
+    },
+    /// The channel of a user's voice state or the flags set on it by
+    /// moderators changed, such as the user being server muted.
+    ///
+    /// This isn't emitted for changes that only affect other fields, such as
+    /// the user muting themselves.
+    VoiceStateChanged {
+        /// ID of the guild.
+        guild_id: GuildId,
+        /// ID of the user.
+        user_id: UserId,
+        /// Transitions made by the update, with their old and new values.
+        transitions: Vec<VoiceTransition>,
+    },
+}
+
+/// Callback called with each change made to the cache.
+#[derive(Clone)]
+pub struct ChangeHook(Arc<dyn Fn(&CacheChange) + Send + Sync>);
+
+impl ChangeHook {
+    /// Create a hook from a callback.
+    pub fn new(hook: impl Fn(&CacheChange) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    /// Call the hook.
+    pub fn call(&self, change: &CacheChange) {
+        (self.0)(change);
+    }
+}
+
+impl Debug for ChangeHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple("ChangeHook").finish()
+    }
+}
@@ -0,0 +1,142 @@
+//! Checks for relations between cached entities that point at missing
+//! entities.
+//!
+//! Backends index some relations separately from the entities themselves,
+//! such as the IDs of the members of each guild or of the messages of each
+//! channel. A bug in a backend, or entities written to it out of order, can
+//! leave such a relation pointing at an entity that is no longer cached,
+//! which shows up as streams silently yielding fewer entities than their IDs.
+//!
+//! [`Backend::verify_integrity`] scans a backend for these dangling relations
+//! and returns an [`IntegrityReport`], optionally repairing them by removing
+//! the relations. Backends without separate relation indexes have nothing to
+//! check and always return an empty report.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache_inmemory::InMemoryCache;
+//!
+//! let cache = InMemoryCache::new();
+//! let report = cache.verify_integrity(true).await?;
+//!
+//! for relation in report.dangling() {
+//!     eprintln!("repaired dangling relation: {:?}", relation);
+//! }
+//! # Ok(()) }
+//! ```
+//!
+//! [`Backend::verify_integrity`]: ../trait.Backend.html#method.verify_integrity
+//! [`IntegrityReport`]: struct.IntegrityReport.html
+
+#[cfg(feature = "tokio")]
+use super::Backend;
+use std::{future::Future, pin::Pin};
+#[cfg(feature = "tokio")]
+use std::{sync::Arc, time::Duration};
+use twilight_model::id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+pub type VerifyIntegrityFuture<'a, E> =
+    Pin<Box<dyn Future<Output = Result<IntegrityReport, E>> + Send + 'a>>;
+
+/// Relation between entities where the related entity isn't cached.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum DanglingRelation {
+    /// Message of a channel that isn't cached, such as one that was evicted.
+    ChannelMessage {
+        channel_id: ChannelId,
+        message_id: MessageId,
+    },
+    /// Channel of a guild that isn't cached.
+    GuildChannel {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    },
+    /// Emoji of a guild that isn't cached.
+    GuildEmoji {
+        guild_id: GuildId,
+        emoji_id: EmojiId,
+    },
+    /// Member of a guild that isn't cached.
+    GuildMember { guild_id: GuildId, user_id: UserId },
+    /// Presence in a guild that isn't cached.
+    GuildPresence { guild_id: GuildId, user_id: UserId },
+    /// Role of a guild that isn't cached.
+    GuildRole { guild_id: GuildId, role_id: RoleId },
+    /// Voice state in a guild that isn't cached.
+    GuildVoiceState { guild_id: GuildId, user_id: UserId },
+    /// Guild of a user that isn't cached, such as one that was removed.
+    UserGuild { user_id: UserId, guild_id: GuildId },
+}
+
+/// Result of checking a backend for dangling relations.
+///
+/// Refer to [`Backend::verify_integrity`] for more information.
+///
+/// [`Backend::verify_integrity`]: ../trait.Backend.html#method.verify_integrity
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IntegrityReport {
+    dangling: Vec<DanglingRelation>,
+    repaired: bool,
+}
+
+impl IntegrityReport {
+    /// Create a report of the dangling relations found by a check, and
+    /// whether they were repaired.
+    pub fn new(dangling: Vec<DanglingRelation>, repaired: bool) -> Self {
+        Self { dangling, repaired }
+    }
+
+    /// Return the dangling relations that were found.
+    pub fn dangling(&self) -> &[DanglingRelation] {
+        &self.dangling
+    }
+
+    /// Return whether no dangling relations were found.
+    pub fn is_consistent(&self) -> bool {
+        self.dangling.is_empty()
+    }
+
+    /// Return whether the dangling relations that were found have been
+    /// removed.
+    ///
+    /// Checks that were asked to repair the backend are considered repaired
+    /// even if nothing was found.
+    pub fn is_repaired(&self) -> bool {
+        self.repaired
+    }
+
+    /// Merge the report of another check into this one, such as the report
+    /// of another partition of a backend.
+    ///
+    /// The merged report is only considered repaired if both were.
+    pub fn merge(&mut self, other: Self) {
+        self.dangling.extend(other.dangling);
+        self.repaired &= other.repaired;
+    }
+}
+
+/// Spawn a task on the Tokio runtime checking a backend for dangling
+/// relations every period, calling a callback with each report.
+///
+/// The first check happens immediately. The task stops when checking fails,
+/// resolving the handle to the error.
+#[cfg(feature = "tokio")]
+pub fn spawn_check<B: Backend>(
+    backend: Arc<B>,
+    period: Duration,
+    repair: bool,
+    on_report: impl Fn(IntegrityReport) + Send + 'static,
+) -> tokio::task::JoinHandle<Result<(), B::Error>> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+            on_report(backend.verify_integrity(repair).await?);
+        }
+    })
+}
@@ -0,0 +1,166 @@
+//! Gateway intents required to populate entities.
+//!
+//! Entities are only cached from the events that the gateway sends, and
+//! which events are sent depends on the intents the connection was identified
+//! with. Without the `GUILD_PRESENCES` intent, for example, no presences are
+//! ever received, so a backend storing presences only wastes memory on an
+//! empty store.
+//!
+//! Backends can use [`EntityIntents`] to disable the entities that the
+//! intents of a connection can't populate.
+//!
+//! # Examples
+//!
+//! ```
+//! use twilight_cache::{
+//!     entity::{gateway::PresenceEntity, guild::GuildEntity},
+//!     intents::EntityIntents,
+//! };
+//! use twilight_model::gateway::Intents;
+//!
+//! let intents = Intents::GUILDS | Intents::GUILD_MESSAGES;
+//!
+//! assert!(GuildEntity::is_populated_by(intents));
+//! assert!(!PresenceEntity::is_populated_by(intents));
+//! ```
+//!
+//! [`EntityIntents`]: trait.EntityIntents.html
+
+use super::entity::{
+    channel::{
+        AttachmentEntity, CategoryChannelEntity, GroupEntity, MessageEntity, PrivateChannelEntity,
+        TextChannelEntity, VoiceChannelEntity,
+    },
+    gateway::PresenceEntity,
+    guild::{EmojiEntity, GuildEntity, GuildPreviewEntity, MemberEntity, RoleEntity},
+    user::{CurrentUserEntity, UserEntity},
+    voice::{VoiceRegionEntity, VoiceStateEntity},
+};
+use twilight_model::gateway::Intents;
+
+/// Gateway intents whose events populate an entity.
+pub trait EntityIntents {
+    /// Return the intents whose events populate the entity, any one of which
+    /// is enough for the entity to be cached.
+    ///
+    /// Entities that are populated regardless of intents, such as the current
+    /// user from the `Ready` event, return no intents.
+    fn populating_intents() -> Intents;
+
+    /// Return whether the entity is populated by events received with the
+    /// given intents.
+    fn is_populated_by(intents: Intents) -> bool {
+        let populating = Self::populating_intents();
+
+        populating.is_empty() || intents.intersects(populating)
+    }
+}
+
+impl EntityIntents for AttachmentEntity {
+    fn populating_intents() -> Intents {
+        MessageEntity::populating_intents()
+    }
+}
+
+impl EntityIntents for CategoryChannelEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILDS
+    }
+}
+
+impl EntityIntents for CurrentUserEntity {
+    fn populating_intents() -> Intents {
+        Intents::empty()
+    }
+}
+
+impl EntityIntents for EmojiEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILDS | Intents::GUILD_EMOJIS
+    }
+}
+
+impl EntityIntents for GroupEntity {
+    fn populating_intents() -> Intents {
+        Intents::DIRECT_MESSAGES
+    }
+}
+
+impl EntityIntents for GuildEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILDS
+    }
+}
+
+/// Guild previews aren't populated by events but are upserted manually, so
+/// they don't depend on any intents.
+impl EntityIntents for GuildPreviewEntity {
+    fn populating_intents() -> Intents {
+        Intents::empty()
+    }
+}
+
+impl EntityIntents for MemberEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILD_MEMBERS
+    }
+}
+
+impl EntityIntents for MessageEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILD_MESSAGES | Intents::DIRECT_MESSAGES
+    }
+}
+
+impl EntityIntents for PresenceEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILD_PRESENCES
+    }
+}
+
+impl EntityIntents for PrivateChannelEntity {
+    fn populating_intents() -> Intents {
+        Intents::DIRECT_MESSAGES
+    }
+}
+
+impl EntityIntents for RoleEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILDS
+    }
+}
+
+impl EntityIntents for TextChannelEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILDS
+    }
+}
+
+impl EntityIntents for UserEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILD_MEMBERS
+            | Intents::GUILD_PRESENCES
+            | Intents::GUILD_MESSAGES
+            | Intents::DIRECT_MESSAGES
+    }
+}
+
+impl EntityIntents for VoiceChannelEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILDS
+    }
+}
+
+/// Voice regions aren't sent over the gateway but are upserted manually, so
+/// they don't depend on any intents.
+impl EntityIntents for VoiceRegionEntity {
+    fn populating_intents() -> Intents {
+        Intents::empty()
+    }
+}
+
+impl EntityIntents for VoiceStateEntity {
+    fn populating_intents() -> Intents {
+        Intents::GUILD_VOICE_STATES
+    }
+}
@@ -0,0 +1,175 @@
+//! Transformation of entities before they're upserted by the cache.
+//!
+//! An [`EntityInterceptor`] set with [`Cache::set_entity_interceptor`] is
+//! called with every entity that the cache upserts while processing events.
+//! It can modify an entity, such as to redact email addresses or message
+//! contents, or drop the entity entirely so that it's never cached. Because
+//! the interceptor is called by the cache it applies to every backend.
+//!
+//! # Examples
+//!
+//! Redact the content of messages and don't cache bots:
+//!
+//! ```
+//! use twilight_cache::{
+//!     entity::{channel::MessageEntity, user::UserEntity},
+//!     intercept::EntityInterceptor,
+//! };
+//! use twilight_cache_inmemory::InMemoryCache;
+//!
+//! struct Redact;
+//!
+//! impl EntityInterceptor for Redact {
+//!     fn message(&self, mut message: MessageEntity) -> Option<MessageEntity> {
+//!         message.content.clear();
+//!
+//!         Some(message)
+//!     }
+//!
+//!     fn user(&self, user: UserEntity) -> Option<UserEntity> {
+//!         if user.bot {
+//!             None
+//!         } else {
+//!             Some(user)
+//!         }
+//!     }
+//! }
+//!
+//! let mut cache = InMemoryCache::new();
+//! cache.set_entity_interceptor(Redact);
+//! ```
+//!
+//! [`Cache::set_entity_interceptor`]: ../struct.Cache.html#method.set_entity_interceptor
+//! [`EntityInterceptor`]: trait.EntityInterceptor.html
+
+use super::entity::{
+    channel::{
+        AttachmentEntity, CategoryChannelEntity, GroupEntity, MessageEntity, PrivateChannelEntity,
+        TextChannelEntity, VoiceChannelEntity,
+    },
+    gateway::PresenceEntity,
+    guild::{EmojiEntity, GuildEntity, MemberEntity, RoleEntity},
+    user::{CurrentUserEntity, UserEntity},
+    voice::VoiceStateEntity,
+};
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+
+/// Transformation applied to entities before the cache upserts them.
+///
+/// Each method is called with an entity of its type and returns the entity to
+/// upsert, or `None` to skip upserting it. By default entities are upserted
+/// unchanged.
+///
+/// Entities are only intercepted when upserted by the cache while processing
+/// events; upserting an entity through a repository directly bypasses the
+/// interceptor.
+pub trait EntityInterceptor: Send + Sync {
+    fn attachment(&self, entity: AttachmentEntity) -> Option<AttachmentEntity> {
+        Some(entity)
+    }
+
+    fn category_channel(&self, entity: CategoryChannelEntity) -> Option<CategoryChannelEntity> {
+        Some(entity)
+    }
+
+    fn current_user(&self, entity: CurrentUserEntity) -> Option<CurrentUserEntity> {
+        Some(entity)
+    }
+
+    fn emoji(&self, entity: EmojiEntity) -> Option<EmojiEntity> {
+        Some(entity)
+    }
+
+    fn group(&self, entity: GroupEntity) -> Option<GroupEntity> {
+        Some(entity)
+    }
+
+    fn guild(&self, entity: GuildEntity) -> Option<GuildEntity> {
+        Some(entity)
+    }
+
+    fn member(&self, entity: MemberEntity) -> Option<MemberEntity> {
+        Some(entity)
+    }
+
+    fn message(&self, entity: MessageEntity) -> Option<MessageEntity> {
+        Some(entity)
+    }
+
+    fn presence(&self, entity: PresenceEntity) -> Option<PresenceEntity> {
+        Some(entity)
+    }
+
+    fn private_channel(&self, entity: PrivateChannelEntity) -> Option<PrivateChannelEntity> {
+        Some(entity)
+    }
+
+    fn role(&self, entity: RoleEntity) -> Option<RoleEntity> {
+        Some(entity)
+    }
+
+    fn text_channel(&self, entity: TextChannelEntity) -> Option<TextChannelEntity> {
+        Some(entity)
+    }
+
+    fn user(&self, entity: UserEntity) -> Option<UserEntity> {
+        Some(entity)
+    }
+
+    fn voice_channel(&self, entity: VoiceChannelEntity) -> Option<VoiceChannelEntity> {
+        Some(entity)
+    }
+
+    fn voice_state(&self, entity: VoiceStateEntity) -> Option<VoiceStateEntity> {
+        Some(entity)
+    }
+}
+
+/// Interceptor shared between clones of a cache.
+#[derive(Clone)]
+pub(crate) struct SharedInterceptor(pub(crate) Arc<dyn EntityInterceptor>);
+
+impl Debug for SharedInterceptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple("SharedInterceptor").finish()
+    }
+}
+
+/// Entity that can be passed to the matching method of an interceptor.
+pub trait Intercept: Sized {
+    /// Pass the entity to the interceptor's method for its type.
+    fn intercept(self, interceptor: &dyn EntityInterceptor) -> Option<Self>;
+}
+
+macro_rules! impl_intercept {
+    ($($entity: ty => $method: ident,)*) => {
+        $(
+            impl Intercept for $entity {
+                fn intercept(self, interceptor: &dyn EntityInterceptor) -> Option<Self> {
+                    interceptor.$method(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_intercept! {
+    AttachmentEntity => attachment,
+    CategoryChannelEntity => category_channel,
+    CurrentUserEntity => current_user,
+    EmojiEntity => emoji,
+    GroupEntity => group,
+    GuildEntity => guild,
+    MemberEntity => member,
+    MessageEntity => message,
+    PresenceEntity => presence,
+    PrivateChannelEntity => private_channel,
+    RoleEntity => role,
+    TextChannelEntity => text_channel,
+    UserEntity => user,
+    VoiceChannelEntity => voice_channel,
+    VoiceStateEntity => voice_state,
+}
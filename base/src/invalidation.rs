@@ -0,0 +1,274 @@
+//! Broadcasting the writes made to one cache instance to other instances.
+//!
+//! Bots that are scaled horizontally run multiple processes, each with its own
+//! cache. When the processes share entities, such as by caching the same
+//! guilds from different shards or layering a memory cache over a shared
+//! persistent store, a write made by one process leaves the others with stale
+//! entities.
+//!
+//! An [`Invalidator`] wraps a backend so that every upsert and removal made
+//! through it is published to the other instances on an [`InvalidationBus`],
+//! such as Redis' pub/sub. [`Invalidator::listen`] applies the writes
+//! published by other instances to the wrapped backend, updating upserted
+//! entities and removing removed ones, without publishing them again.
+//!
+//! Writes are applied to the backend even if publishing them fails, since
+//! they're published as they're made. The first error publishing a write is
+//! kept until it's taken with [`Invalidator::take_publish_error`].
+//!
+//! The `twilight-cache-redis` crate provides a bus built on Redis' pub/sub.
+//!
+//! # Examples
+//!
+//! ```
+//! use twilight_cache::{invalidation::{InvalidationBus, Invalidator}, Cache};
+//! use twilight_cache_inmemory::InMemoryBackend;
+//!
+//! async fn run<I: InvalidationBus + Clone>(bus: I) {
+//!     let invalidator = Invalidator::new(InMemoryBackend::new(), bus);
+//!     let listener = invalidator.clone();
+//!
+//!     // keep listening when applying or receiving a write fails
+//!     tokio::spawn(async move {
+//!         listener
+//!             .listen(|error| {
+//!                 eprintln!("listening for writes failed: {}", error);
+//!
+//!                 true
+//!             })
+//!             .await
+//!     });
+//!
+//!     let cache = Cache::with_backend(invalidator.backend());
+//!     // process events with the cache...
+//! }
+//! ```
+//!
+//! [`Invalidator`]: struct.Invalidator.html
+//! [`Invalidator::listen`]: struct.Invalidator.html#method.listen
+//! [`Invalidator::take_publish_error`]: struct.Invalidator.html#method.take_publish_error
+//! [`InvalidationBus`]: trait.InvalidationBus.html
+
+use super::{
+    backend::Backend,
+    recording::{self, Record, RecordingBackend},
+};
+use futures_util::stream::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::RandomState,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    iter,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+pub type SubscribeFuture<'a, E> =
+    Pin<Box<dyn Future<Output = Result<InvalidationStream<E>, E>> + Send + 'a>>;
+pub type InvalidationStream<E> = Pin<Box<dyn Stream<Item = Result<Invalidation, E>> + Send>>;
+
+/// Write published by a cache instance.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Invalidation {
+    /// ID of the instance that made the write.
+    pub origin: u64,
+    /// The write.
+    pub record: Record,
+}
+
+/// Channel that cache instances publish their writes to and receive the
+/// writes of other instances from.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+pub trait InvalidationBus: Send + Sync + 'static {
+    /// Error returned when publishing or receiving writes fails.
+    type Error: Error + Send + Sync + 'static;
+
+    /// Publish a write to every subscribed instance.
+    ///
+    /// This is called with each write as it's made, so it mustn't block.
+    /// Writes should be queued and published in the order they're queued, so
+    /// that an entity's removal isn't published before its upsert.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the write can't be queued or published.
+    fn publish(&self, invalidation: &Invalidation) -> Result<(), Self::Error>;
+
+    /// Subscribe to the writes published by every instance, including this
+    /// one.
+    fn subscribe(&self) -> SubscribeFuture<'_, Self::Error>;
+}
+
+/// Error returned when listening for the writes of other instances fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ListenError<E, F> {
+    /// Applying a write to the backend failed.
+    Backend {
+        /// Reason for the error.
+        source: E,
+    },
+    /// Subscribing to the bus or receiving a write from it failed.
+    Bus {
+        /// Reason for the error.
+        source: F,
+    },
+}
+
+impl<E, F> Display for ListenError<E, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Backend { .. } => f.write_str("applying a published write failed"),
+            Self::Bus { .. } => f.write_str("receiving published writes failed"),
+        }
+    }
+}
+
+impl<E: Error + 'static, F: Error + 'static> Error for ListenError<E, F> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Backend { source } => Some(source),
+            Self::Bus { source } => Some(source),
+        }
+    }
+}
+
+/// Backend wrapper publishing its writes to, and applying the writes of,
+/// other cache instances.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+#[derive(Clone, Debug)]
+pub struct Invalidator<B, I: InvalidationBus> {
+    backend: RecordingBackend<B>,
+    bus: Arc<I>,
+    origin: u64,
+    /// First error publishing a write since it was last taken.
+    publish_error: Arc<Mutex<Option<I::Error>>>,
+}
+
+impl<B: Backend + Clone, I: InvalidationBus> Invalidator<B, I> {
+    /// Wrap a backend, publishing its writes to a bus.
+    ///
+    /// The instance is given a random ID so that it can tell its own writes
+    /// apart from those of other instances.
+    pub fn new(backend: B, bus: I) -> Self {
+        Self::with_origin(backend, bus, RandomState::new().build_hasher().finish())
+    }
+
+    /// Wrap a backend, publishing its writes to a bus under an instance ID.
+    ///
+    /// The ID must be unique among the instances subscribed to the bus.
+    pub fn with_origin(backend: B, bus: I, origin: u64) -> Self {
+        let bus = Arc::new(bus);
+        let publisher = Arc::clone(&bus);
+        let publish_error = Arc::new(Mutex::new(None));
+        let failed = Arc::clone(&publish_error);
+
+        let backend = RecordingBackend::new(backend, move |record| {
+            let invalidation = Invalidation {
+                origin,
+                record: record.clone(),
+            };
+
+            if let Err(source) = publisher.publish(&invalidation) {
+                failed
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get_or_insert(source);
+            }
+        });
+
+        Self {
+            backend,
+            bus,
+            origin,
+            publish_error,
+        }
+    }
+
+    /// Return the backend whose writes are published.
+    ///
+    /// Writes made to the wrapped backend directly aren't published.
+    pub fn backend(&self) -> RecordingBackend<B> {
+        self.backend.clone()
+    }
+
+    /// Return an immutable reference to the bus.
+    pub fn bus(&self) -> &I {
+        &self.bus
+    }
+
+    /// Return the ID of the instance.
+    pub const fn origin(&self) -> u64 {
+        self.origin
+    }
+
+    /// Return the first error publishing a write since this was last called,
+    /// if any.
+    ///
+    /// Writes that fail to be published have still been applied to the
+    /// wrapped backend, so other instances may have stale entities until
+    /// they're written again.
+    pub fn take_publish_error(&self) -> Option<I::Error> {
+        self.publish_error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    /// Apply the writes published by other instances to the wrapped backend
+    /// until the bus stops delivering them.
+    ///
+    /// Errors applying or receiving a write are passed to a callback, which
+    /// decides whether to keep listening by returning `true` or to stop with
+    /// the error by returning `false`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ListenError::Backend`] if applying a write fails and the
+    /// callback stops listening.
+    ///
+    /// Returns [`ListenError::Bus`] if subscribing to the bus fails, or if
+    /// receiving a write fails and the callback stops listening.
+    ///
+    /// [`ListenError::Backend`]: enum.ListenError.html#variant.Backend
+    /// [`ListenError::Bus`]: enum.ListenError.html#variant.Bus
+    pub async fn listen(
+        &self,
+        mut on_error: impl FnMut(&ListenError<B::Error, I::Error>) -> bool + Send,
+    ) -> Result<(), ListenError<B::Error, I::Error>> {
+        let mut invalidations = self
+            .bus
+            .subscribe()
+            .await
+            .map_err(|source| ListenError::Bus { source })?;
+
+        while let Some(invalidation) = invalidations.next().await {
+            let result = match invalidation {
+                Ok(invalidation) if invalidation.origin == self.origin => Ok(()),
+                Ok(invalidation) => {
+                    recording::replay(self.backend.inner(), iter::once(invalidation.record))
+                        .await
+                        .map_err(|source| ListenError::Backend { source })
+                }
+                Err(source) => Err(ListenError::Bus { source }),
+            };
+
+            if let Err(error) = result {
+                if !on_error(&error) {
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,534 @@
+//! Generic repositories for backends built on a key-value store.
+//!
+//! Most persistent stores, such as UnQLite, sled, or Redis, boil down to
+//! getting, putting, and deleting bytes under a key. Instead of implementing a
+//! repository for every entity, backends for these stores can implement
+//! [`KvStore`] and use a [`KvRepository`] for each entity. Entities are stored
+//! under a key made of a per-entity prefix and their ID, provided by
+//! [`KvEntity`], and are encoded by the store so that it can choose its own
+//! serialization format, compression, and encryption.
+//!
+//! Relation traits like [`GuildRepository`] can be implemented for a backend's
+//! `KvRepository<GuildEntity, Backend>` with the helpers of [`KvRepository`].
+//! Members, presences, and voice states are keyed by their guild's ID and
+//! their user's ID, so the entities of a guild are listed by scanning the
+//! keys prefixed with [`KvGuildEntity::guild_prefix`], and their user IDs are
+//! read from the keys without decoding the entities. Other relations, such as
+//! the roles of a guild, are found by scanning and filtering all entities of
+//! the type.
+//!
+//! # Examples
+//!
+//! ```
+//! use twilight_cache::{entity::guild::MemberEntity, kv::KvEntity};
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! assert_eq!(b"m:1:2".to_vec(), MemberEntity::key((GuildId(1), UserId(2))));
+//! assert!(MemberEntity::key((GuildId(1), UserId(2))).starts_with(MemberEntity::PREFIX));
+//! ```
+//!
+//! The guild prefix ends with the separator, so that scanning it doesn't
+//! include guilds whose IDs start with the same digits:
+//!
+//! ```
+//! use twilight_cache::{entity::guild::MemberEntity, kv::KvGuildEntity};
+//! use twilight_model::id::GuildId;
+//!
+//! assert_eq!(b"m:1:".to_vec(), MemberEntity::guild_prefix(GuildId(1)));
+//! ```
+//!
+//! [`GuildRepository`]: ../entity/guild/trait.GuildRepository.html
+//! [`KvEntity`]: trait.KvEntity.html
+//! [`KvGuildEntity::guild_prefix`]: trait.KvGuildEntity.html#method.guild_prefix
+//! [`KvRepository`]: struct.KvRepository.html
+//! [`KvStore`]: trait.KvStore.html
+
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{
+            AttachmentEntity, CategoryChannelEntity, GroupEntity, MessageEntity,
+            PrivateChannelEntity, TextChannelEntity, VoiceChannelEntity,
+        },
+        gateway::PresenceEntity,
+        guild::{EmojiEntity, GuildEntity, GuildPreviewEntity, MemberEntity, RoleEntity},
+        user::{CurrentUserEntity, UserEntity},
+        voice::{VoiceRegionEntity, VoiceRegionId, VoiceStateEntity},
+        Entity,
+    },
+    repository::{
+        GetEntityFuture, ListEntitiesFuture, ListEntityIdsFuture, ListEntityIdsStream,
+        RemoveEntityFuture, Repository, SingleEntityRepository, UpsertEntityFuture,
+    },
+};
+use futures_util::{
+    future::{self, FutureExt, TryFutureExt},
+    stream::{self, StreamExt, TryStreamExt},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt::Display, marker::PhantomData, str};
+use twilight_model::id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+/// Entity stored under a key of a [`KvStore`].
+///
+/// [`KvStore`]: trait.KvStore.html
+pub trait KvEntity: Entity {
+    /// Prefix of the keys of all entities of this type.
+    ///
+    /// No prefix starts with another, so listing entities by their prefix
+    /// never includes entities of another type.
+    const PREFIX: &'static [u8];
+
+    /// Return the key to store an entity under.
+    fn key(id: Self::Id) -> Vec<u8>;
+}
+
+/// Entity of which there's only one, stored under a fixed key of a
+/// [`KvStore`].
+///
+/// [`KvStore`]: trait.KvStore.html
+pub trait KvSingleEntity: Entity {
+    /// Key to store the entity under.
+    const KEY: &'static [u8];
+}
+
+/// Entity stored under a key of a [`KvStore`] made of its guild's ID and its
+/// user's ID.
+///
+/// [`KvStore`]: trait.KvStore.html
+pub trait KvGuildEntity: KvEntity<Id = (GuildId, UserId)> {
+    /// Return the prefix of the keys of all entities of this type in a guild.
+    ///
+    /// The prefix ends with the separator after the guild's ID, so it isn't a
+    /// prefix of the keys of guilds whose IDs start with the same digits.
+    fn guild_prefix(guild_id: GuildId) -> Vec<u8> {
+        let mut prefix = key(Self::PREFIX, &[&guild_id]);
+        prefix.push(b':');
+
+        prefix
+    }
+}
+
+/// Key made of a prefix and one or more parts of an ID.
+fn key(prefix: &[u8], parts: &[&dyn Display]) -> Vec<u8> {
+    let mut key = prefix.to_vec();
+
+    for (idx, part) in parts.iter().enumerate() {
+        if idx > 0 {
+            key.push(b':');
+        }
+
+        key.extend_from_slice(part.to_string().as_bytes());
+    }
+
+    key
+}
+
+/// Parse the guild and user IDs from the key of a [`KvGuildEntity`].
+///
+/// [`KvGuildEntity`]: trait.KvGuildEntity.html
+fn parse_guild_key<T: KvGuildEntity>(key: &[u8]) -> Option<(GuildId, UserId)> {
+    let key = str::from_utf8(key.strip_prefix(T::PREFIX)?).ok()?;
+    let (guild_id, user_id) = key.split_once(':')?;
+
+    Some((
+        GuildId(guild_id.parse().ok()?),
+        UserId(user_id.parse().ok()?),
+    ))
+}
+
+/// Decode the entities stored under keys starting with a prefix.
+async fn scan<T: DeserializeOwned + 'static, S: KvStore>(
+    store: &S,
+    prefix: Vec<u8>,
+) -> Result<Vec<T>, S::Error> {
+    store
+        .scan_prefix(prefix)
+        .await?
+        .try_filter_map(|(key, bytes)| future::ok(store.decode(&key, &bytes)))
+        .try_collect()
+        .await
+}
+
+/// Stream IDs in ascending order.
+fn sorted<I: Ord + Send + 'static, E: Send + 'static>(
+    mut ids: Vec<I>,
+) -> ListEntityIdsStream<'static, I, E> {
+    ids.sort_unstable();
+
+    stream::iter(ids.into_iter().map(Ok)).boxed()
+}
+
+impl KvEntity for AttachmentEntity {
+    const PREFIX: &'static [u8] = b"at:";
+
+    fn key(id: AttachmentId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for CategoryChannelEntity {
+    const PREFIX: &'static [u8] = b"cc:";
+
+    fn key(id: ChannelId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvSingleEntity for CurrentUserEntity {
+    const KEY: &'static [u8] = b"uc";
+}
+
+impl KvEntity for EmojiEntity {
+    const PREFIX: &'static [u8] = b"em:";
+
+    fn key(id: EmojiId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for GroupEntity {
+    const PREFIX: &'static [u8] = b"gr:";
+
+    fn key(id: ChannelId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for GuildEntity {
+    const PREFIX: &'static [u8] = b"g:";
+
+    fn key(id: GuildId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for GuildPreviewEntity {
+    const PREFIX: &'static [u8] = b"gp:";
+
+    fn key(id: GuildId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for MemberEntity {
+    const PREFIX: &'static [u8] = b"m:";
+
+    fn key((guild_id, user_id): (GuildId, UserId)) -> Vec<u8> {
+        key(Self::PREFIX, &[&guild_id, &user_id])
+    }
+}
+
+impl KvGuildEntity for MemberEntity {}
+
+impl KvEntity for MessageEntity {
+    const PREFIX: &'static [u8] = b"ms:";
+
+    fn key(id: MessageId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for PresenceEntity {
+    const PREFIX: &'static [u8] = b"pr:";
+
+    fn key((guild_id, user_id): (GuildId, UserId)) -> Vec<u8> {
+        key(Self::PREFIX, &[&guild_id, &user_id])
+    }
+}
+
+impl KvGuildEntity for PresenceEntity {}
+
+impl KvEntity for PrivateChannelEntity {
+    const PREFIX: &'static [u8] = b"cp:";
+
+    fn key(id: ChannelId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for RoleEntity {
+    const PREFIX: &'static [u8] = b"r:";
+
+    fn key(id: RoleId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for TextChannelEntity {
+    const PREFIX: &'static [u8] = b"ct:";
+
+    fn key(id: ChannelId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for UserEntity {
+    const PREFIX: &'static [u8] = b"u:";
+
+    fn key(id: UserId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for VoiceChannelEntity {
+    const PREFIX: &'static [u8] = b"cv:";
+
+    fn key(id: ChannelId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for VoiceRegionEntity {
+    const PREFIX: &'static [u8] = b"vr:";
+
+    fn key(id: VoiceRegionId) -> Vec<u8> {
+        key(Self::PREFIX, &[&id])
+    }
+}
+
+impl KvEntity for VoiceStateEntity {
+    const PREFIX: &'static [u8] = b"v:";
+
+    fn key((guild_id, user_id): (GuildId, UserId)) -> Vec<u8> {
+        key(Self::PREFIX, &[&guild_id, &user_id])
+    }
+}
+
+impl KvGuildEntity for VoiceStateEntity {}
+
+/// Minimal key-value store that a backend is built on.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+pub trait KvStore: Backend + Clone {
+    /// Get the value stored under a key.
+    fn get(&self, key: Vec<u8>) -> GetEntityFuture<'_, Vec<u8>, Self::Error>;
+
+    /// Store a value under a key, replacing the value already stored under it.
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> UpsertEntityFuture<'_, Self::Error>;
+
+    /// Delete the value stored under a key, if any.
+    fn delete(&self, key: Vec<u8>) -> RemoveEntityFuture<'_, Self::Error>;
+
+    /// Stream the keys and values of all keys starting with a prefix.
+    fn scan_prefix(
+        &self,
+        prefix: Vec<u8>,
+    ) -> ListEntitiesFuture<'_, (Vec<u8>, Vec<u8>), Self::Error>;
+
+    /// Encode an entity to store under a key.
+    ///
+    /// Entities should be wrapped in a [`VersionedEntity`].
+    ///
+    /// [`VersionedEntity`]: ../migration/struct.VersionedEntity.html
+    fn encode<T: Serialize>(&self, key: &[u8], entity: &T) -> Vec<u8>;
+
+    /// Decode an entity stored under a key.
+    ///
+    /// Entities stored with an older schema version should be passed to the
+    /// configured [`Migrator`] of their type, if any. Entities that can't be
+    /// decoded or migrated should be treated as not being in the cache by
+    /// returning `None`.
+    ///
+    /// [`Migrator`]: ../migration/trait.Migrator.html
+    fn decode<T: DeserializeOwned + 'static>(&self, key: &[u8], bytes: &[u8]) -> Option<T>;
+}
+
+/// Repository of an entity stored in a [`KvStore`].
+///
+/// [`KvStore`]: trait.KvStore.html
+#[derive(Clone, Debug)]
+pub struct KvRepository<T, S>(S, PhantomData<T>);
+
+impl<T, S> KvRepository<T, S> {
+    /// Create a repository of entities stored in a store.
+    pub const fn new(store: S) -> Self {
+        Self(store, PhantomData)
+    }
+
+    /// Return an immutable reference to the store.
+    pub const fn store(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<T, S> KvRepository<T, S>
+where
+    T: DeserializeOwned + KvEntity + Serialize + 'static,
+    S: KvStore,
+{
+    /// Stream the entities of the type that a predicate returns `true` for.
+    ///
+    /// All entities of the type are decoded, so this should only be used for
+    /// relations that aren't part of the entities' keys, such as the guild of
+    /// a role.
+    pub fn list_where(
+        &self,
+        predicate: impl Fn(&T) -> bool + Send + Sync + 'static,
+    ) -> ListEntitiesFuture<'static, T, S::Error> {
+        let store = self.0.clone();
+
+        Box::pin(async move {
+            let mut entities = scan(&store, T::PREFIX.to_vec()).await?;
+            entities.retain(|entity| predicate(entity));
+
+            Ok(stream::iter(entities.into_iter().map(Ok)).boxed())
+        })
+    }
+
+    /// Stream the IDs that a function returns for the entities of the type,
+    /// in ascending order.
+    ///
+    /// All entities of the type are decoded, like with [`list_where`].
+    ///
+    /// [`list_where`]: #method.list_where
+    pub fn ids_where<I: Ord + Send + 'static>(
+        &self,
+        f: impl Fn(&T) -> Option<I> + Send + Sync + 'static,
+    ) -> ListEntityIdsFuture<'static, I, S::Error> {
+        let store = self.0.clone();
+
+        Box::pin(async move {
+            let entities = scan(&store, T::PREFIX.to_vec()).await?;
+
+            Ok(sorted(entities.iter().filter_map(f).collect()))
+        })
+    }
+}
+
+impl<T, S> KvRepository<T, S>
+where
+    T: DeserializeOwned + KvGuildEntity + Serialize + 'static,
+    S: KvStore,
+{
+    /// Stream the entities of a guild, scanning only the keys prefixed with
+    /// the guild's ID.
+    pub fn list_by_guild_key(&self, guild_id: GuildId) -> ListEntitiesFuture<'static, T, S::Error> {
+        let store = self.0.clone();
+
+        Box::pin(async move {
+            let entities = scan(&store, T::guild_prefix(guild_id)).await?;
+
+            Ok(stream::iter(entities.into_iter().map(Ok)).boxed())
+        })
+    }
+
+    /// Stream the IDs of the users of a guild's entities in ascending order,
+    /// reading them from the keys without decoding the entities.
+    pub fn user_ids_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntityIdsFuture<'static, UserId, S::Error> {
+        let store = self.0.clone();
+
+        Box::pin(async move {
+            let ids = store
+                .scan_prefix(T::guild_prefix(guild_id))
+                .await?
+                .try_filter_map(|(key, _)| {
+                    future::ok(parse_guild_key::<T>(&key).map(|(_, user_id)| user_id))
+                })
+                .try_collect()
+                .await?;
+
+            Ok(sorted(ids))
+        })
+    }
+
+    /// Stream the IDs of the guilds that a user has an entity of the type in,
+    /// in ascending order, reading them from the keys without decoding the
+    /// entities.
+    pub fn guild_ids_by_user(
+        &self,
+        user_id: UserId,
+    ) -> ListEntityIdsFuture<'static, GuildId, S::Error> {
+        let store = self.0.clone();
+
+        Box::pin(async move {
+            let ids = store
+                .scan_prefix(T::PREFIX.to_vec())
+                .await?
+                .try_filter_map(|(key, _)| {
+                    future::ok(
+                        parse_guild_key::<T>(&key)
+                            .filter(|(_, id)| *id == user_id)
+                            .map(|(guild_id, _)| guild_id),
+                    )
+                })
+                .try_collect()
+                .await?;
+
+            Ok(sorted(ids))
+        })
+    }
+}
+
+impl<T, S> Repository<T, S> for KvRepository<T, S>
+where
+    T: DeserializeOwned + KvEntity + Serialize + 'static,
+    S: KvStore,
+{
+    fn backend(&self) -> &S {
+        &self.0
+    }
+
+    fn get(&self, entity_id: T::Id) -> GetEntityFuture<'_, T, S::Error> {
+        let key = T::key(entity_id);
+
+        self.0
+            .get(key.clone())
+            .map_ok(move |bytes| bytes.and_then(|bytes| self.0.decode(&key, &bytes)))
+            .boxed()
+    }
+
+    fn list(&self) -> ListEntitiesFuture<'_, T, S::Error> {
+        self.0
+            .scan_prefix(T::PREFIX.to_vec())
+            .map_ok(move |entries| {
+                entries
+                    .try_filter_map(move |(key, bytes)| future::ok(self.0.decode(&key, &bytes)))
+                    .boxed()
+            })
+            .boxed()
+    }
+
+    fn remove(&self, entity_id: T::Id) -> RemoveEntityFuture<'_, S::Error> {
+        self.0.delete(T::key(entity_id))
+    }
+
+    fn upsert(&self, entity: T) -> UpsertEntityFuture<'_, S::Error> {
+        let key = T::key(entity.id());
+        let bytes = self.0.encode(&key, &entity);
+
+        self.0.put(key, bytes)
+    }
+}
+
+impl<T, S> SingleEntityRepository<T, S> for KvRepository<T, S>
+where
+    T: DeserializeOwned + KvSingleEntity + Serialize + 'static,
+    S: KvStore,
+{
+    fn backend(&self) -> &S {
+        &self.0
+    }
+
+    fn get(&self) -> GetEntityFuture<'_, T, S::Error> {
+        self.0
+            .get(T::KEY.to_vec())
+            .map_ok(move |bytes| bytes.and_then(|bytes| self.0.decode(T::KEY, &bytes)))
+            .boxed()
+    }
+
+    fn remove(&self) -> RemoveEntityFuture<'_, S::Error> {
+        self.0.delete(T::KEY.to_vec())
+    }
+
+    fn upsert(&self, entity: T) -> UpsertEntityFuture<'_, S::Error> {
+        let bytes = self.0.encode(T::KEY, &entity);
+
+        self.0.put(T::KEY.to_vec(), bytes)
+    }
+}
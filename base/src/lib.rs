@@ -19,7 +19,9 @@
 //!
 //! Here's a list of backends supported by Twilight:
 //!
+//! - [`twilight-cache-graph`]: graph of entities in the process's memory
 //! - [`twilight-cache-inmemory`]: datastore in the process's memory
+//! - [`twilight-cache-sqlite`]: datastore in a SQLite database
 //!
 //! # Usage
 //!
@@ -88,12 +90,98 @@
 //!
 //! For detailed information, read the documentation for both traits.
 //!
+//! Backends connecting to a remote datastore should also implement
+//! [`Backend::connect`], [`Backend::reconnect`], and [`Backend::health`], so
+//! that they can be wrapped in a [`ReconnectingBackend`] rather than
+//! re-establishing lost connections themselves.
+//!
 //! # Features
 //!
+//! The `discord` feature provides the entities, repositories, and [`Cache`]
+//! for Discord's models. It is enabled by default. Without it, only the
+//! [`Entity`], [`Repository`], and [`Backend`] traits and the generic
+//! utilities are available, so that other projects can define their own sets
+//! of entities on top of the same caching core. The `emoji-usage`,
+//! `export`, `heuristics`, `invalidation`, `recording`, `rkyv`, and
+//! `warm-start` features enable it.
+//!
 //! The `serde` feature can be disabled to remove the `Deserialize` and
 //! `Serialize` implementations on entities. It is enabled by default.
 //!
+//! Backends persisting serialized entities should store them in a
+//! [`VersionedEntity`], which is only available with the `serde` feature.
+//! Backends built on a key-value store can use the repositories of the
+//! [`kv`] module, which also requires it.
+//!
+//! The `rkyv` feature implements [`rkyv`]'s traits for all entities so that
+//! backends can return zero-copy views of stored entities. Refer to the
+//! [`archive`] module for more information. It is disabled by default.
+//!
+//! The `compression` feature provides a [`Compressor`] for backends to
+//! compress large serialized entities before persisting them. It is disabled
+//! by default.
+//!
+//! The `encryption` feature provides an [`EntityCipher`] for backends to
+//! encrypt serialized entities before persisting them. It is disabled by
+//! default.
+//!
+//! The `emoji-usage` feature counts how often the custom emojis of guilds
+//! are used in messages and reactions. Refer to the [`emoji_usage`] module for
+//! more information. It is disabled by default.
+//!
+//! The `export` feature writes the entities of selected types stored by a
+//! backend as newline-delimited JSON for offline analysis and backups. Refer
+//! to the [`export`] module for more information. It is disabled by default.
+//!
+//! The `heuristics` feature provides raid heuristics computed from the
+//! recent joins to guilds. Refer to the [`heuristics`] module for more
+//! information. It enables the `time` feature and is disabled by default.
+//!
+//! The `invalidation` feature broadcasts the writes made to one cache instance
+//! to other instances so that horizontally scaled bots don't serve stale
+//! entities. Refer to the [`invalidation`] module for more information. It
+//! enables the `recording` feature and is disabled by default.
+//!
+//! The `recording` feature provides a [`RecordingBackend`] that records the
+//! writes made to a backend so that they can be replayed against another. It
+//! is disabled by default.
+//!
+//! The `time` feature adds accessors to entities that parse their timestamps,
+//! such as a member's join date, into [`time`]'s `OffsetDateTime`. It is
+//! disabled by default.
+//!
+//! The `tokio` feature allows spawning a [`Pruner`] to periodically remove old
+//! entities in a background task, as well as a periodic [integrity check],
+//! and provides a [`RateLimitedBackend`] to limit the rate of operations
+//! against a backend. It is disabled by default.
+//!
+//! The `warm-start` feature loads dumps of guild payloads into a cache
+//! without a live gateway, for testing against realistic data. Refer to the
+//! [`warm_start`] module for more information. It is disabled by default.
+//!
+//! [`archive`]: archive/index.html
+//! [`Backend::connect`]: trait.Backend.html#method.connect
+//! [`Backend::health`]: trait.Backend.html#method.health
+//! [`Backend::reconnect`]: trait.Backend.html#method.reconnect
+//! [`Compressor`]: compression/struct.Compressor.html
+//! [`emoji_usage`]: emoji_usage/index.html
+//! [`EntityCipher`]: encryption/struct.EntityCipher.html
+//! [`export`]: export/index.html
+//! [`heuristics`]: heuristics/index.html
+//! [integrity check]: integrity/fn.spawn_check.html
+//! [`invalidation`]: invalidation/index.html
+//! [`kv`]: kv/index.html
+//! [`Pruner`]: prune/struct.Pruner.html
+//! [`RateLimitedBackend`]: rate_limit/struct.RateLimitedBackend.html
+//! [`ReconnectingBackend`]: reconnect/struct.ReconnectingBackend.html
+//! [`RecordingBackend`]: recording/struct.RecordingBackend.html
+//! [`rkyv`]: https://docs.rs/rkyv
+//! [`time`]: https://docs.rs/time
+//! [`VersionedEntity`]: migration/struct.VersionedEntity.html
+//! [`warm_start`]: warm_start/index.html
+//! [`twilight-cache-graph`]: ../twilight_cache_graph/index.html
 //! [`twilight-cache-inmemory`]: ../twilight_cache_inmemory/index.html
+//! [`twilight-cache-sqlite`]: ../twilight_cache_sqlite/index.html
 //! [docs:repo:microsoft]: https://docs.microsoft.com/en-us/dotnet/architecture/microservices/microservice-ddd-cqrs-patterns/infrastructure-persistence-layer-design
 
 #![deny(
@@ -112,11 +200,83 @@
     clippy::must_use_candidate
 )]
 
+#[cfg(feature = "discord")]
+pub mod any;
+#[cfg(feature = "rkyv")]
+pub mod archive;
+#[cfg(feature = "discord")]
+pub mod batch;
+#[cfg(feature = "discord")]
 pub mod cache;
+#[cfg(feature = "discord")]
+pub mod cdn;
+#[cfg(feature = "compression")]
+pub mod compression;
+#[cfg(feature = "emoji-usage")]
+pub mod emoji_usage;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 pub mod entity;
+#[cfg(feature = "export")]
+pub mod export;
+#[cfg(feature = "heuristics")]
+pub mod heuristics;
+#[cfg(feature = "discord")]
+pub mod hook;
+#[cfg(feature = "discord")]
+pub mod integrity;
+#[cfg(feature = "discord")]
+pub mod intents;
+#[cfg(feature = "discord")]
+pub mod intercept;
+#[cfg(feature = "invalidation")]
+pub mod invalidation;
+#[cfg(all(feature = "discord", feature = "serde"))]
+pub mod kv;
+#[cfg(feature = "discord")]
+pub mod limit;
+#[cfg(feature = "discord")]
+pub mod local_repository;
+pub mod meta;
+#[cfg(feature = "discord")]
+pub mod metrics;
+pub mod migration;
+#[cfg(feature = "discord")]
+pub mod ordering;
+#[cfg(feature = "discord")]
+pub mod partition;
+pub mod projection;
+#[cfg(feature = "discord")]
+pub mod prune;
+#[cfg(all(feature = "discord", feature = "tokio"))]
+pub mod rate_limit;
+#[cfg(feature = "discord")]
+pub mod reconnect;
+#[cfg(feature = "recording")]
+pub mod recording;
 pub mod repository;
+#[cfg(feature = "discord")]
+pub mod schema;
+#[cfg(feature = "discord")]
+pub mod session;
+#[cfg(feature = "discord")]
+pub mod size;
+#[cfg(feature = "discord")]
+pub mod snapshot;
+#[cfg(feature = "discord")]
+pub mod snowflake;
+pub mod timestamp;
+#[cfg(feature = "discord")]
+pub mod unavailable;
+pub mod utils;
+#[cfg(feature = "discord")]
+pub mod view;
+#[cfg(feature = "warm-start")]
+pub mod warm_start;
 
 mod backend;
-mod utils;
 
-pub use self::{backend::Backend, cache::Cache, entity::Entity, repository::Repository};
+pub use self::{backend::Backend, entity::Entity, repository::Repository};
+
+#[cfg(feature = "discord")]
+pub use self::{cache::Cache, schema::schema};
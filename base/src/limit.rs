@@ -0,0 +1,164 @@
+//! Limiting the number of concurrent writes to a backend.
+//!
+//! Processing a `GuildCreate` or `MemberChunk` of a large guild upserts
+//! thousands of entities at once. Backends performing operations over the
+//! network, such as to a database, can be overwhelmed by that many concurrent
+//! requests.
+//!
+//! When enabled with [`Cache::set_max_concurrent_backend_ops`], at most the
+//! given number of entities are written to the backend at once across all
+//! events being processed. Bulk upserts are dispatched in chunks of at most
+//! that many entities, each of which counts as one operation per entity.
+//! Writes beyond the limit wait for earlier ones to finish, in the order they
+//! were started.
+//!
+//! [`Cache::set_max_concurrent_backend_ops`]: ../struct.Cache.html#method.set_max_concurrent_backend_ops
+
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+/// Semaphore of the operations that can be performed on a backend at once.
+#[derive(Debug)]
+pub(crate) struct BackendOpLimit {
+    max: usize,
+    state: Mutex<LimitState>,
+}
+
+impl BackendOpLimit {
+    pub(crate) fn new(max: usize) -> Self {
+        let max = max.max(1);
+
+        Self {
+            max,
+            state: Mutex::new(LimitState {
+                available: max,
+                ..LimitState::default()
+            }),
+        }
+    }
+
+    /// Return the maximum number of operations performed at once.
+    pub(crate) const fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Wait until a number of operations can be performed, returning a permit
+    /// that allows them until it's dropped.
+    ///
+    /// The number of operations is capped to the maximum. A place in line is
+    /// taken immediately, so permits are granted in the order that this is
+    /// called.
+    pub(crate) fn acquire(&self, operations: usize) -> Acquire<'_> {
+        let mut state = self.lock();
+        let number = state.next;
+        state.next += 1;
+        state.queue.push_back(number);
+
+        Acquire {
+            limit: self,
+            number,
+            operations: operations.clamp(1, self.max),
+            acquired: false,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LimitState> {
+        self.state.lock().expect("backend op limit poisoned")
+    }
+}
+
+#[derive(Debug, Default)]
+struct LimitState {
+    /// Number of operations that can currently be started.
+    available: usize,
+    /// Next place in line to hand out.
+    next: u64,
+    /// Places in line waiting for a permit, in order.
+    queue: VecDeque<u64>,
+    /// Wakers of places in line waiting for a permit.
+    wakers: HashMap<u64, Waker>,
+}
+
+impl LimitState {
+    /// Wake the first place in line so that it can check for a permit.
+    fn wake_next(&mut self) {
+        if let Some(number) = self.queue.front() {
+            if let Some(waker) = self.wakers.remove(number) {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Future resolving to a [`Permit`] once it's first in line and enough
+/// operations are available.
+#[derive(Debug)]
+pub(crate) struct Acquire<'a> {
+    limit: &'a BackendOpLimit,
+    number: u64,
+    operations: usize,
+    acquired: bool,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Permit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut guard = self.limit.lock();
+        let state = &mut *guard;
+
+        if state.queue.front() != Some(&self.number) || state.available < self.operations {
+            state.wakers.insert(self.number, cx.waker().clone());
+
+            return Poll::Pending;
+        }
+
+        state.available -= self.operations;
+        state.queue.pop_front();
+        state.wake_next();
+        drop(guard);
+        self.acquired = true;
+
+        Poll::Ready(Permit {
+            limit: self.limit,
+            operations: self.operations,
+        })
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        if self.acquired {
+            return;
+        }
+
+        let mut state = self.limit.lock();
+        state.wakers.remove(&self.number);
+        let first = state.queue.front() == Some(&self.number);
+        state.queue.retain(|number| *number != self.number);
+
+        if first {
+            state.wake_next();
+        }
+    }
+}
+
+/// Permit to perform a number of operations, releasing them when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit<'a> {
+    limit: &'a BackendOpLimit,
+    operations: usize,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.limit.lock();
+        state.available += self.operations;
+        state.wake_next();
+    }
+}
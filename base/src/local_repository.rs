@@ -0,0 +1,361 @@
+//! Repository access to backends whose futures aren't `Send`, for
+//! single-threaded executors.
+//!
+//! The futures returned by [`Backend`] repositories must be `Send` so that
+//! the cache can be used from multi-threaded executors. This rules out
+//! backends built on clients that can't be sent across threads, such as
+//! clients on WASM or in thread-per-core runtimes.
+//!
+//! [`LocalBackend`] and [`LocalRepository`] are variants of the backend and
+//! repository traits without the `Send` requirement. Every [`Backend`] is also
+//! a [`LocalBackend`], so code written against the local traits works with
+//! any backend.
+//!
+//! Only repository access is supported: the local traits get, list, remove,
+//! and upsert entities. A [`Cache`] still requires a [`Backend`], so events
+//! can't be processed into a backend that only implements [`LocalBackend`],
+//! and relation traits such as [`GuildRepository`] have no local variants.
+//!
+//! # Examples
+//!
+//! Count the guilds in any backend, whether or not its futures are `Send`:
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use futures_util::stream::StreamExt;
+//! use twilight_cache::{
+//!     entity::guild::GuildEntity,
+//!     local_repository::{LocalBackend, LocalRepository},
+//! };
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! async fn guild_count<B: LocalBackend>(backend: &B) -> Result<usize, B::Error> {
+//!     Ok(backend.guilds().list().await?.count().await)
+//! }
+//!
+//! let backend = InMemoryBackend::new();
+//! let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+//! backend.guilds().upsert(guild).await?;
+//!
+//! assert_eq!(1, guild_count(&backend).await?);
+//! # Ok(()) }
+//! ```
+//!
+//! [`Backend`]: ../trait.Backend.html
+//! [`Cache`]: ../struct.Cache.html
+//! [`GuildRepository`]: ../entity/guild/trait.GuildRepository.html
+//! [`LocalBackend`]: trait.LocalBackend.html
+//! [`LocalRepository`]: trait.LocalRepository.html
+
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{
+            AttachmentEntity, CategoryChannelEntity, GroupEntity, MessageEntity,
+            PrivateChannelEntity, TextChannelEntity, VoiceChannelEntity,
+        },
+        gateway::PresenceEntity,
+        guild::{EmojiEntity, GuildEntity, GuildPreviewEntity, MemberEntity, RoleEntity},
+        user::{CurrentUserEntity, UserEntity},
+        voice::{VoiceRegionEntity, VoiceStateEntity},
+        Entity,
+    },
+    repository::{Repository, SingleEntityRepository},
+};
+use futures_util::stream::Stream;
+use std::{future::Future, pin::Pin};
+
+pub type LocalGetEntityFuture<'a, T, E> = Pin<Box<dyn Future<Output = Result<Option<T>, E>> + 'a>>;
+pub type LocalListEntitiesFuture<'a, T, E> =
+    Pin<Box<dyn Future<Output = Result<LocalListEntitiesStream<'a, T, E>, E>> + 'a>>;
+pub type LocalListEntitiesStream<'a, T, E> = Pin<Box<dyn Stream<Item = Result<T, E>> + 'a>>;
+pub type LocalRemoveEntityFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + 'a>>;
+pub type LocalUpsertEntityFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + 'a>>;
+
+/// Variant of [`Repository`] whose futures aren't required to be `Send`.
+///
+/// This is implemented for all [`Repository`] implementations.
+///
+/// [`Repository`]: ../repository/trait.Repository.html
+pub trait LocalRepository<E: Entity, B: LocalBackend> {
+    /// Retrieve an immutable reference to the backend that the repository is
+    /// tied to.
+    fn backend(&self) -> &B;
+
+    /// Get an entity by its ID in the cache.
+    fn get(&self, entity_id: E::Id) -> LocalGetEntityFuture<'_, E, B::Error>;
+
+    /// Stream a list of records of the entity.
+    fn list(&self) -> LocalListEntitiesFuture<'_, E, B::Error>;
+
+    /// Remove an entity by its ID from the cache.
+    fn remove(&self, entity_id: E::Id) -> LocalRemoveEntityFuture<'_, B::Error>;
+
+    /// Upsert an entity into the cache.
+    fn upsert(&self, entity: E) -> LocalUpsertEntityFuture<'_, B::Error>;
+
+    /// Upsert an entity into the cache unless a newer version of it has
+    /// already been upserted.
+    ///
+    /// Refer to [`Repository::versioned_upsert`] for more information.
+    ///
+    /// [`Repository::versioned_upsert`]: ../repository/trait.Repository.html#method.versioned_upsert
+    fn versioned_upsert(&self, entity: E, sequence: u64) -> LocalUpsertEntityFuture<'_, B::Error> {
+        let _ = sequence;
+
+        self.upsert(entity)
+    }
+}
+
+/// Variant of [`SingleEntityRepository`] whose futures aren't required to be
+/// `Send`.
+///
+/// This is implemented for all [`SingleEntityRepository`] implementations.
+///
+/// [`SingleEntityRepository`]: ../repository/trait.SingleEntityRepository.html
+pub trait LocalSingleEntityRepository<E: Entity, B: LocalBackend> {
+    /// Retrieve an immutable reference to the backend that the repository is
+    /// tied to.
+    fn backend(&self) -> &B;
+
+    /// Get the entity in the cache.
+    fn get(&self) -> LocalGetEntityFuture<'_, E, B::Error>;
+
+    /// Remove the entity from the cache.
+    fn remove(&self) -> LocalRemoveEntityFuture<'_, B::Error>;
+
+    /// Upsert the entity into the cache.
+    fn upsert(&self, entity: E) -> LocalUpsertEntityFuture<'_, B::Error>;
+}
+
+/// Variant of [`Backend`] that isn't required to be `Send` or `Sync`, and
+/// whose repositories' futures aren't required to be `Send`.
+///
+/// This is implemented for all [`Backend`] implementations. It only provides
+/// access to the backend's repositories and can't be used with a [`Cache`].
+///
+/// [`Backend`]: ../trait.Backend.html
+/// [`Cache`]: ../struct.Cache.html
+pub trait LocalBackend: Sized + 'static {
+    type Error: 'static;
+    type AttachmentRepository: LocalRepository<AttachmentEntity, Self>;
+    type CategoryChannelRepository: LocalRepository<CategoryChannelEntity, Self>;
+    type CurrentUserRepository: LocalSingleEntityRepository<CurrentUserEntity, Self>;
+    type EmojiRepository: LocalRepository<EmojiEntity, Self>;
+    type GroupRepository: LocalRepository<GroupEntity, Self>;
+    type GuildPreviewRepository: LocalRepository<GuildPreviewEntity, Self>;
+    type GuildRepository: LocalRepository<GuildEntity, Self>;
+    type MemberRepository: LocalRepository<MemberEntity, Self>;
+    type MessageRepository: LocalRepository<MessageEntity, Self>;
+    type PresenceRepository: LocalRepository<PresenceEntity, Self>;
+    type PrivateChannelRepository: LocalRepository<PrivateChannelEntity, Self>;
+    type RoleRepository: LocalRepository<RoleEntity, Self>;
+    type TextChannelRepository: LocalRepository<TextChannelEntity, Self>;
+    type UserRepository: LocalRepository<UserEntity, Self>;
+    type VoiceChannelRepository: LocalRepository<VoiceChannelEntity, Self>;
+    type VoiceRegionRepository: LocalRepository<VoiceRegionEntity, Self>;
+    type VoiceStateRepository: LocalRepository<VoiceStateEntity, Self>;
+
+    /// Return a new instance of the backend's attachment repository
+    /// implementation.
+    fn attachments(&self) -> Self::AttachmentRepository;
+
+    /// Return a new instance of the backend's guild category channel repository
+    /// implementation.
+    fn category_channels(&self) -> Self::CategoryChannelRepository;
+
+    /// Return a new instance of the backend's current user repository
+    /// implementation.
+    fn current_user(&self) -> Self::CurrentUserRepository;
+
+    /// Return a new instance of the backend's emoji repository implementation.
+    fn emojis(&self) -> Self::EmojiRepository;
+
+    /// Return a new instance of the backend's group repository implementation.
+    fn groups(&self) -> Self::GroupRepository;
+
+    /// Return a new instance of the backend's guild preview repository
+    /// implementation.
+    fn guild_previews(&self) -> Self::GuildPreviewRepository;
+
+    /// Return a new instance of the backend's guild repository implementation.
+    fn guilds(&self) -> Self::GuildRepository;
+
+    /// Return a new instance of the backend's member repository implementation.
+    fn members(&self) -> Self::MemberRepository;
+
+    /// Return a new instance of the backend's message repository
+    /// implementation.
+    fn messages(&self) -> Self::MessageRepository;
+
+    /// Return a new instance of the backend's presence repository
+    /// implementation.
+    fn presences(&self) -> Self::PresenceRepository;
+
+    /// Return a new instance of the backend's guild private channel repository
+    /// implementation.
+    fn private_channels(&self) -> Self::PrivateChannelRepository;
+
+    /// Return a new instance of the backend's role repository implementation.
+    fn roles(&self) -> Self::RoleRepository;
+
+    /// Return a new instance of the backend's guild text channel repository
+    /// implementation.
+    fn text_channels(&self) -> Self::TextChannelRepository;
+
+    /// Return a new instance of the backend's user repository implementation.
+    fn users(&self) -> Self::UserRepository;
+
+    /// Return a new instance of the backend's voice channel repository
+    /// implementation.
+    fn voice_channels(&self) -> Self::VoiceChannelRepository;
+
+    /// Return a new instance of the backend's voice region repository
+    /// implementation.
+    fn voice_regions(&self) -> Self::VoiceRegionRepository;
+
+    /// Return a new instance of the backend's voice state repository
+    /// implementation.
+    fn voice_states(&self) -> Self::VoiceStateRepository;
+}
+
+impl<E: Entity, B: Backend, R: Repository<E, B>> LocalRepository<E, B> for R {
+    fn backend(&self) -> &B {
+        Repository::backend(self)
+    }
+
+    fn get(&self, entity_id: E::Id) -> LocalGetEntityFuture<'_, E, B::Error> {
+        Repository::get(self, entity_id)
+    }
+
+    fn list(&self) -> LocalListEntitiesFuture<'_, E, B::Error> {
+        Box::pin(async move {
+            let stream: LocalListEntitiesStream<'_, E, B::Error> = Repository::list(self).await?;
+
+            Ok(stream)
+        })
+    }
+
+    fn remove(&self, entity_id: E::Id) -> LocalRemoveEntityFuture<'_, B::Error> {
+        Repository::remove(self, entity_id)
+    }
+
+    fn upsert(&self, entity: E) -> LocalUpsertEntityFuture<'_, B::Error> {
+        Repository::upsert(self, entity)
+    }
+
+    fn versioned_upsert(&self, entity: E, sequence: u64) -> LocalUpsertEntityFuture<'_, B::Error> {
+        Repository::versioned_upsert(self, entity, sequence)
+    }
+}
+
+impl<E: Entity, B: Backend, R: SingleEntityRepository<E, B>> LocalSingleEntityRepository<E, B>
+    for R
+{
+    fn backend(&self) -> &B {
+        SingleEntityRepository::backend(self)
+    }
+
+    fn get(&self) -> LocalGetEntityFuture<'_, E, B::Error> {
+        SingleEntityRepository::get(self)
+    }
+
+    fn remove(&self) -> LocalRemoveEntityFuture<'_, B::Error> {
+        SingleEntityRepository::remove(self)
+    }
+
+    fn upsert(&self, entity: E) -> LocalUpsertEntityFuture<'_, B::Error> {
+        SingleEntityRepository::upsert(self, entity)
+    }
+}
+
+impl<B: Backend> LocalBackend for B {
+    type Error = B::Error;
+    type AttachmentRepository = B::AttachmentRepository;
+    type CategoryChannelRepository = B::CategoryChannelRepository;
+    type CurrentUserRepository = B::CurrentUserRepository;
+    type EmojiRepository = B::EmojiRepository;
+    type GroupRepository = B::GroupRepository;
+    type GuildPreviewRepository = B::GuildPreviewRepository;
+    type GuildRepository = B::GuildRepository;
+    type MemberRepository = B::MemberRepository;
+    type MessageRepository = B::MessageRepository;
+    type PresenceRepository = B::PresenceRepository;
+    type PrivateChannelRepository = B::PrivateChannelRepository;
+    type RoleRepository = B::RoleRepository;
+    type TextChannelRepository = B::TextChannelRepository;
+    type UserRepository = B::UserRepository;
+    type VoiceChannelRepository = B::VoiceChannelRepository;
+    type VoiceRegionRepository = B::VoiceRegionRepository;
+    type VoiceStateRepository = B::VoiceStateRepository;
+
+    fn attachments(&self) -> Self::AttachmentRepository {
+        Backend::attachments(self)
+    }
+
+    fn category_channels(&self) -> Self::CategoryChannelRepository {
+        Backend::category_channels(self)
+    }
+
+    fn current_user(&self) -> Self::CurrentUserRepository {
+        Backend::current_user(self)
+    }
+
+    fn emojis(&self) -> Self::EmojiRepository {
+        Backend::emojis(self)
+    }
+
+    fn groups(&self) -> Self::GroupRepository {
+        Backend::groups(self)
+    }
+
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        Backend::guild_previews(self)
+    }
+
+    fn guilds(&self) -> Self::GuildRepository {
+        Backend::guilds(self)
+    }
+
+    fn members(&self) -> Self::MemberRepository {
+        Backend::members(self)
+    }
+
+    fn messages(&self) -> Self::MessageRepository {
+        Backend::messages(self)
+    }
+
+    fn presences(&self) -> Self::PresenceRepository {
+        Backend::presences(self)
+    }
+
+    fn private_channels(&self) -> Self::PrivateChannelRepository {
+        Backend::private_channels(self)
+    }
+
+    fn roles(&self) -> Self::RoleRepository {
+        Backend::roles(self)
+    }
+
+    fn text_channels(&self) -> Self::TextChannelRepository {
+        Backend::text_channels(self)
+    }
+
+    fn users(&self) -> Self::UserRepository {
+        Backend::users(self)
+    }
+
+    fn voice_channels(&self) -> Self::VoiceChannelRepository {
+        Backend::voice_channels(self)
+    }
+
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        Backend::voice_regions(self)
+    }
+
+    fn voice_states(&self) -> Self::VoiceStateRepository {
+        Backend::voice_states(self)
+    }
+}
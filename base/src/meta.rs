@@ -0,0 +1,52 @@
+//! Metadata about when entities were cached.
+//!
+//! Backends may track when each entity was first inserted into the cache and
+//! when it was last updated. This is useful for deciding whether an entity is
+//! stale enough to be refreshed, or for debugging why an entity is out of
+//! date. The metadata of an entity is retrieved along with it via
+//! [`Repository::get_with_meta`].
+//!
+//! Tracking metadata is optional, so backends that don't track it, or that
+//! have it disabled, return no metadata.
+//!
+//! [`Repository::get_with_meta`]: ../repository/trait.Repository.html#method.get_with_meta
+
+use std::time::{Duration, SystemTime};
+
+/// Times at which an entity was cached and last updated.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CacheMeta {
+    /// When the entity was first inserted into the cache.
+    ///
+    /// This is reset if the entity is removed and then inserted again.
+    pub cached_at: SystemTime,
+    /// When the entity was last upserted.
+    pub updated_at: SystemTime,
+}
+
+impl CacheMeta {
+    /// Create the metadata of an entity inserted at the given time.
+    pub const fn new(now: SystemTime) -> Self {
+        Self {
+            cached_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Return the metadata of the entity after it's updated at the given
+    /// time.
+    pub const fn updated(self, now: SystemTime) -> Self {
+        Self {
+            cached_at: self.cached_at,
+            updated_at: now,
+        }
+    }
+
+    /// Return how long ago the entity was last updated.
+    ///
+    /// Returns a duration of zero if the system clock has gone backwards
+    /// since.
+    pub fn since_update(&self) -> Duration {
+        self.updated_at.elapsed().unwrap_or_default()
+    }
+}
@@ -0,0 +1,154 @@
+//! Metrics about the events processed by the cache.
+//!
+//! The cache counts the events it processes and how long processing them
+//! takes, by event type. Slow backends can stall the loop receiving events
+//! from the gateway, so these can be used to find which events a backend
+//! struggles with. Retrieve them via [`Cache::event_stats`].
+//!
+//! A [`SlowEventHook`] can additionally be set with
+//! [`Cache::set_slow_event_hook`] to be called whenever processing an event
+//! takes longer than a threshold.
+//!
+//! With the `compression` feature, the number of entities and bytes
+//! compressed by backends that compress entities can be retrieved via
+//! [`Cache::compression_stats`].
+//!
+//! [`Cache::compression_stats`]: ../struct.Cache.html#method.compression_stats
+//! [`Cache::event_stats`]: ../struct.Cache.html#method.event_stats
+//! [`Cache::set_slow_event_hook`]: ../struct.Cache.html#method.set_slow_event_hook
+//! [`SlowEventHook`]: struct.SlowEventHook.html
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use twilight_model::gateway::event::EventType;
+
+/// Number of the most recent processing durations of each event type used to
+/// calculate percentiles.
+pub const DURATION_SAMPLES: usize = 1024;
+
+/// Metrics about the processing of an event type.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct EventStats {
+    /// Number of events processed, including failures.
+    pub processed: u64,
+    /// Number of events whose processing returned an error.
+    pub failed: u64,
+    /// 99th percentile of the durations of the most recently processed
+    /// events.
+    ///
+    /// Up to [`DURATION_SAMPLES`] durations are considered.
+    ///
+    /// [`DURATION_SAMPLES`]: constant.DURATION_SAMPLES.html
+    pub p99_duration: Duration,
+    /// Longest duration that processing an event has taken.
+    pub max_duration: Duration,
+}
+
+/// Callback called with the type of an event and how long processing it took
+/// when it took longer than a threshold.
+#[derive(Clone)]
+pub struct SlowEventHook {
+    hook: Arc<dyn Fn(EventType, Duration) + Send + Sync>,
+    threshold: Duration,
+}
+
+impl SlowEventHook {
+    /// Create a hook called for events that take longer than the threshold.
+    pub fn new(
+        threshold: Duration,
+        hook: impl Fn(EventType, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            hook: Arc::new(hook),
+            threshold,
+        }
+    }
+
+    /// Return the duration after which events are considered slow.
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Call the hook if the duration exceeds the threshold.
+    pub fn call(&self, event_type: EventType, duration: Duration) {
+        if duration > self.threshold {
+            (self.hook)(event_type, duration);
+        }
+    }
+}
+
+impl Debug for SlowEventHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("SlowEventHook")
+            .field("threshold", &self.threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+struct EventRecord {
+    durations: VecDeque<Duration>,
+    failed: u64,
+    max_duration: Duration,
+    processed: u64,
+}
+
+impl EventRecord {
+    fn stats(&self) -> EventStats {
+        let mut durations = self.durations.iter().copied().collect::<Vec<_>>();
+        durations.sort_unstable();
+
+        // Nearest-rank percentile: the smallest duration that at least 99% of
+        // the durations are less than or equal to.
+        let rank = (durations.len() * 99).div_ceil(100);
+        let p99_duration = rank
+            .checked_sub(1)
+            .and_then(|idx| durations.get(idx))
+            .copied()
+            .unwrap_or_default();
+
+        EventStats {
+            failed: self.failed,
+            max_duration: self.max_duration,
+            p99_duration,
+            processed: self.processed,
+        }
+    }
+}
+
+/// Metrics of processed events shared between clones of a cache.
+#[derive(Debug, Default)]
+pub(crate) struct EventMetrics(Mutex<HashMap<EventType, EventRecord>>);
+
+impl EventMetrics {
+    pub(crate) fn record(&self, event_type: EventType, duration: Duration, failed: bool) {
+        let mut records = self.0.lock().expect("event metrics poisoned");
+        let record = records.entry(event_type).or_default();
+
+        record.processed += 1;
+
+        if failed {
+            record.failed += 1;
+        }
+
+        if record.durations.len() == DURATION_SAMPLES {
+            record.durations.pop_front();
+        }
+
+        record.durations.push_back(duration);
+        record.max_duration = record.max_duration.max(duration);
+    }
+
+    pub(crate) fn stats(&self) -> HashMap<EventType, EventStats> {
+        self.0
+            .lock()
+            .expect("event metrics poisoned")
+            .iter()
+            .map(|(event_type, record)| (*event_type, record.stats()))
+            .collect()
+    }
+}
@@ -0,0 +1,198 @@
+//! Versioning of serialized entities for persistent backends.
+//!
+//! Entities change shape over time as fields are added, removed, or renamed.
+//! Backends that persist serialized entities outside of the process, such as
+//! to a file or a database, should wrap entities in a [`VersionedEntity`] when
+//! storing them so that data written by an older version of the cache can be
+//! detected instead of silently failing to deserialize, or worse, being
+//! deserialized into the wrong fields.
+//!
+//! When a backend reads an entity stored with a version older than
+//! [`CACHE_SCHEMA_VERSION`] it should pass the stored bytes to a [`Migrator`],
+//! if one is configured. Without a migrator, backends should treat the stored
+//! entity as if it wasn't present in the cache. Entities stored with a newer
+//! version, by a newer version of the cache, can't be migrated and are always
+//! treated as not being present.
+//!
+//! Backends accept the migrators of each type of entity in a [`Migrations`].
+//!
+//! [`CACHE_SCHEMA_VERSION`]: constant.CACHE_SCHEMA_VERSION.html
+//! [`Migrations`]: struct.Migrations.html
+//! [`Migrator`]: trait.Migrator.html
+//! [`VersionedEntity`]: struct.VersionedEntity.html
+
+use super::entity::Entity;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    error::Error,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+
+/// Version of the schema of serialized entities.
+///
+/// This is incremented whenever a change is made to an entity that would
+/// change its serialized representation.
+pub const CACHE_SCHEMA_VERSION: u32 = 1;
+
+/// Envelope around a serialized entity containing the schema version that it
+/// was serialized with.
+///
+/// Backends can serialize a `VersionedEntity<&E>` to avoid cloning the entity.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct VersionedEntity<T> {
+    /// Schema version that the entity was serialized with.
+    pub version: u32,
+    /// The entity itself.
+    pub entity: T,
+}
+
+#[cfg(feature = "serde")]
+impl<T> VersionedEntity<T> {
+    /// Wrap an entity with the current [`CACHE_SCHEMA_VERSION`].
+    ///
+    /// [`CACHE_SCHEMA_VERSION`]: constant.CACHE_SCHEMA_VERSION.html
+    pub const fn new(entity: T) -> Self {
+        Self {
+            version: CACHE_SCHEMA_VERSION,
+            entity,
+        }
+    }
+
+    /// Whether the entity was serialized with an older schema version and
+    /// needs to be migrated.
+    pub const fn is_outdated(&self) -> bool {
+        self.version < CACHE_SCHEMA_VERSION
+    }
+}
+
+/// Only the version of a serialized [`VersionedEntity`].
+///
+/// Deserializing this first allows backends to check the version of a stored
+/// entity without deserializing the entity itself, which may fail if the
+/// entity was stored with an older schema.
+///
+/// [`VersionedEntity`]: struct.VersionedEntity.html
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+pub struct VersionHeader {
+    /// Schema version that the entity was serialized with.
+    pub version: u32,
+}
+
+/// Migrate entities stored with an older schema version into the current
+/// schema.
+///
+/// **Backend implementations**: this should be called when deserializing an
+/// entity whose [`VersionHeader::version`] is older than
+/// [`CACHE_SCHEMA_VERSION`]. The bytes are the full stored value, including
+/// the envelope, in the backend's serialization format.
+///
+/// [`CACHE_SCHEMA_VERSION`]: constant.CACHE_SCHEMA_VERSION.html
+/// [`VersionHeader::version`]: struct.VersionHeader.html#structfield.version
+pub trait Migrator<E: Entity> {
+    /// Error returned when migration of an entity fails.
+    type Error;
+
+    /// Migrate the bytes of an entity stored with the provided schema version
+    /// into an entity of the current schema.
+    ///
+    /// Return `Ok(None)` to discard the stored entity.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored entity can't be migrated.
+    fn migrate(&self, version: u32, bytes: &[u8]) -> Result<Option<E>, Self::Error>;
+}
+
+type MigrateFn<E> =
+    dyn Fn(u32, &[u8]) -> Result<Option<E>, Box<dyn Error + Send + Sync>> + Send + Sync;
+
+/// Configured [`Migrator`]s of a backend, one per type of entity.
+///
+/// # Examples
+///
+/// ```
+/// use twilight_cache::{
+///     entity::user::UserEntity,
+///     migration::{Migrations, Migrator},
+/// };
+///
+/// struct DiscardUsers;
+///
+/// impl Migrator<UserEntity> for DiscardUsers {
+///     type Error = std::io::Error;
+///
+///     fn migrate(&self, _: u32, _: &[u8]) -> Result<Option<UserEntity>, Self::Error> {
+///         Ok(None)
+///     }
+/// }
+///
+/// let mut migrations = Migrations::new();
+/// migrations.add::<UserEntity, _>(DiscardUsers);
+/// ```
+///
+/// [`Migrator`]: trait.Migrator.html
+#[derive(Clone, Default)]
+pub struct Migrations(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl Migrations {
+    /// Create a new set of migrations without any migrators.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the migrator of a type of entity, replacing the previous migrator
+    /// of the type if there was one.
+    pub fn add<E, M>(&mut self, migrator: M) -> &mut Self
+    where
+        E: Entity + 'static,
+        M: Migrator<E> + Send + Sync + 'static,
+        M::Error: Error + Send + Sync + 'static,
+    {
+        let migrate: Box<MigrateFn<E>> = Box::new(move |version: u32, bytes: &[u8]| {
+            migrator
+                .migrate(version, bytes)
+                .map_err(|source| -> Box<dyn Error + Send + Sync> { Box::new(source) })
+        });
+        self.0.insert(TypeId::of::<E>(), Arc::new(migrate));
+
+        self
+    }
+
+    /// Migrate the stored bytes of an entity with the configured migrator of
+    /// its type.
+    ///
+    /// Returns `Ok(None)` if no migrator is configured for the type or if the
+    /// migrator discarded the entity.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the migrator if migrating the entity fails.
+    pub fn migrate<E: 'static>(
+        &self,
+        version: u32,
+        bytes: &[u8],
+    ) -> Result<Option<E>, Box<dyn Error + Send + Sync>> {
+        let migrate = match self
+            .0
+            .get(&TypeId::of::<E>())
+            .and_then(|migrate| migrate.downcast_ref::<Box<MigrateFn<E>>>())
+        {
+            Some(migrate) => migrate,
+            None => return Ok(None),
+        };
+
+        migrate(version, bytes)
+    }
+}
+
+impl Debug for Migrations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Migrations")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
@@ -0,0 +1,177 @@
+//! Ordering of concurrently processed events that affect the same entities.
+//!
+//! [`Cache::process`] can be called concurrently, such as by one task per
+//! shard. With backends that perform operations over the network, a later
+//! event can finish processing before an earlier one, so a `MemberRemove`
+//! could be applied before a preceding `MemberUpdate` and leave the removed
+//! member in the cache.
+//!
+//! When enabled with [`Cache::enable_ordering`], events that share an
+//! [ordering key] are processed one at a time in the order that
+//! [`Cache::process`] was called with them. Keys are distributed over a fixed
+//! number of shards, so unrelated events may also wait on each other if
+//! their keys map to the same shard.
+//!
+//! [`Cache::enable_ordering`]: ../struct.Cache.html#method.enable_ordering
+//! [`Cache::process`]: ../struct.Cache.html#method.process
+//! [ordering key]: fn.ordering_key.html
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    convert::TryFrom,
+    future::Future,
+    hash::{Hash, Hasher},
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll, Waker},
+};
+use twilight_model::{channel::Channel, gateway::event::Event};
+
+/// Return the key that determines which events are processed in order with
+/// an event.
+///
+/// Events within a guild are keyed by the guild's ID, events in private
+/// channels and groups by the channel's ID. Events that aren't tied to a guild
+/// or channel, such as `Ready`, have no key and aren't ordered.
+pub fn ordering_key(event: &Event) -> Option<u64> {
+    let channel = |channel: &Channel| match channel {
+        Channel::Guild(channel) => channel.guild_id().map_or(channel.id().0, |id| id.0),
+        _ => channel.id().0,
+    };
+
+    let key = match event {
+        Event::ChannelCreate(event) => channel(&event.0),
+        Event::ChannelDelete(event) => channel(&event.0),
+        Event::ChannelUpdate(event) => channel(&event.0),
+        Event::ChannelPinsUpdate(event) => event.guild_id.map_or(event.channel_id.0, |id| id.0),
+        Event::GuildCreate(event) => event.id.0,
+        Event::GuildDelete(event) => event.id.0,
+        Event::GuildEmojisUpdate(event) => event.guild_id.0,
+        Event::GuildUpdate(event) => event.id.0,
+        Event::MemberAdd(event) => event.guild_id.0,
+        Event::MemberChunk(event) => event.guild_id.0,
+        Event::MemberRemove(event) => event.guild_id.0,
+        Event::MemberUpdate(event) => event.guild_id.0,
+        Event::MessageCreate(event) => event.guild_id.map_or(event.channel_id.0, |id| id.0),
+        Event::MessageDelete(event) => event.guild_id.map_or(event.channel_id.0, |id| id.0),
+        Event::MessageDeleteBulk(event) => event.guild_id.map_or(event.channel_id.0, |id| id.0),
+        Event::MessageUpdate(event) => event.guild_id.map_or(event.channel_id.0, |id| id.0),
+        Event::PresenceUpdate(event) => event.guild_id.0,
+        Event::RoleCreate(event) => event.guild_id.0,
+        Event::RoleDelete(event) => event.guild_id.0,
+        Event::RoleUpdate(event) => event.guild_id.0,
+        Event::VoiceStateUpdate(event) => event.0.guild_id?.0,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+/// Sharded queues of events waiting to be processed.
+#[derive(Debug)]
+pub(crate) struct OrderingLocks {
+    shards: Box<[Shard]>,
+}
+
+impl OrderingLocks {
+    pub(crate) fn new(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1)).map(|_| Shard::default()).collect(),
+        }
+    }
+
+    /// Take a place in the queue of the shard of a key.
+    ///
+    /// The place is taken immediately, so tickets are served in the order
+    /// that this is called rather than the order they're first polled in.
+    pub(crate) fn ticket(&self, key: u64) -> Ticket<'_> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let len = self.shards.len() as u64;
+        let shard = &self.shards[usize::try_from(hasher.finish() % len).unwrap_or_default()];
+
+        let mut state = shard.lock();
+        let number = state.next;
+        state.next += 1;
+
+        Ticket { number, shard }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shard(Mutex<ShardState>);
+
+impl Shard {
+    fn lock(&self) -> std::sync::MutexGuard<'_, ShardState> {
+        self.0.lock().expect("ordering shard poisoned")
+    }
+}
+
+#[derive(Debug, Default)]
+struct ShardState {
+    /// Tickets dropped before being served.
+    abandoned: HashSet<u64>,
+    /// Next ticket to hand out.
+    next: u64,
+    /// Ticket currently being served.
+    serving: u64,
+    /// Wakers of tickets waiting to be served.
+    wakers: HashMap<u64, Waker>,
+}
+
+/// Place in the queue of a shard, releasing the place to the next ticket when
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct Ticket<'a> {
+    number: u64,
+    shard: &'a Shard,
+}
+
+impl Ticket<'_> {
+    /// Wait until it's the ticket's turn.
+    pub(crate) fn wait(&self) -> impl Future<Output = ()> + Send + '_ {
+        Wait(self)
+    }
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut guard = self.shard.lock();
+        let state = &mut *guard;
+        state.wakers.remove(&self.number);
+
+        if state.serving != self.number {
+            state.abandoned.insert(self.number);
+
+            return;
+        }
+
+        state.serving += 1;
+
+        while state.abandoned.remove(&state.serving) {
+            state.serving += 1;
+        }
+
+        if let Some(waker) = state.wakers.remove(&state.serving) {
+            waker.wake();
+        }
+    }
+}
+
+struct Wait<'a, 'b>(&'a Ticket<'b>);
+
+impl Future for Wait<'_, '_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.0.shard.lock();
+
+        if state.serving == self.0.number {
+            return Poll::Ready(());
+        }
+
+        state.wakers.insert(self.0.number, cx.waker().clone());
+
+        Poll::Pending
+    }
+}
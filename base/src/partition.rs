@@ -0,0 +1,806 @@
+//! Backend spreading entities across multiple backends by guild.
+//!
+//! Bots in very many guilds may not fit their cache into a single process or
+//! host. A [`PartitionedBackend`] wraps a number of backends, such as backends
+//! that talk to other processes, and stores each entity in only one of them,
+//! chosen by the ID of the guild that owns the entity. Entities of a guild are
+//! kept together so that relations within a guild, such as a guild's members,
+//! are answered by a single partition.
+//!
+//! Entities not owned by a guild, such as users and private channels, are
+//! partitioned by their own IDs. The current user is stored in every
+//! partition.
+//!
+//! # Examples
+//!
+//! Spread a cache across two in-memory backends:
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{
+//!     entity::guild::GuildEntity, partition::PartitionedBackend, Cache, Repository,
+//! };
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let backend = PartitionedBackend::new(vec![InMemoryBackend::new(), InMemoryBackend::new()]);
+//! let cache = Cache::with_backend(backend);
+//!
+//! let guild = GuildEntity::builder(GuildId(1 << 22), "guild", UserId(2)).build();
+//! cache.guilds.upsert(guild.clone()).await?;
+//!
+//! assert_eq!(Some(guild), cache.guilds.get(GuildId(1 << 22)).await?);
+//! # Ok(()) }
+//! ```
+//!
+//! [`PartitionedBackend`]: struct.PartitionedBackend.html
+
+#[cfg(feature = "compression")]
+use super::compression::CompressionStats;
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{
+            AttachmentEntity, AttachmentRepository, CategoryChannelEntity,
+            CategoryChannelRepository, GroupEntity, GroupRepository, GuildChannelEntity,
+            MessageEntity, MessageRepository, PrivateChannelEntity, PrivateChannelRepository,
+            TextChannelEntity, TextChannelRepository, VoiceChannelEntity, VoiceChannelRepository,
+        },
+        gateway::{PresenceEntity, PresenceRepository},
+        guild::{
+            EmojiEntity, EmojiRepository, GuildEntity, GuildPreviewEntity, GuildPreviewRepository,
+            GuildRepository, MemberEntity, MemberRepository, NameChange, RoleEntity,
+            RoleRepository,
+        },
+        user::{
+            AvatarChange, CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository,
+        },
+        voice::{
+            VoiceRegionEntity, VoiceRegionRepository, VoiceStateChange, VoiceStateEntity,
+            VoiceStateRepository,
+        },
+        Entity,
+    },
+    integrity::{IntegrityReport, VerifyIntegrityFuture},
+    meta::CacheMeta,
+    repository::{
+        CloseFuture, ConnectFuture, FlushFuture, GetEntityFuture, HealthFuture, ListEntitiesFuture,
+        ListEntityIdsFuture, RemoveEntityFuture, Repository, SingleEntityRepository,
+        UpsertEntityFuture, UpsertOutcomeFuture,
+    },
+};
+use futures_util::{
+    future::{self, FutureExt, TryFutureExt},
+    stream::{self, StreamExt},
+};
+use std::{convert::TryFrom, sync::Arc};
+use twilight_model::id::{ChannelId, EmojiId, GuildId, RoleId, UserId};
+
+/// Key deciding which partition an entity is stored in.
+///
+/// The key is the ID of the guild that owns the entity, if any, and otherwise
+/// an ID of the entity itself.
+pub trait PartitionKey: Entity {
+    /// Return the key of the entity.
+    fn partition_key(&self) -> u64;
+
+    /// Return the key of an entity by its ID, if the ID alone determines it.
+    ///
+    /// Entities whose key can't be determined by their IDs, such as roles,
+    /// are looked up in every partition.
+    fn id_partition_key(entity_id: Self::Id) -> Option<u64>;
+}
+
+impl PartitionKey for AttachmentEntity {
+    fn partition_key(&self) -> u64 {
+        self.message_id.0
+    }
+
+    fn id_partition_key(_: Self::Id) -> Option<u64> {
+        None
+    }
+}
+
+impl PartitionKey for CategoryChannelEntity {
+    fn partition_key(&self) -> u64 {
+        self.guild_id.map_or(self.id.0, |id| id.0)
+    }
+
+    fn id_partition_key(_: Self::Id) -> Option<u64> {
+        None
+    }
+}
+
+impl PartitionKey for CurrentUserEntity {
+    fn partition_key(&self) -> u64 {
+        self.id.0
+    }
+
+    fn id_partition_key(user_id: Self::Id) -> Option<u64> {
+        Some(user_id.0)
+    }
+}
+
+impl PartitionKey for EmojiEntity {
+    fn partition_key(&self) -> u64 {
+        self.guild_id.0
+    }
+
+    fn id_partition_key(_: Self::Id) -> Option<u64> {
+        None
+    }
+}
+
+impl PartitionKey for GroupEntity {
+    fn partition_key(&self) -> u64 {
+        self.id.0
+    }
+
+    fn id_partition_key(channel_id: Self::Id) -> Option<u64> {
+        Some(channel_id.0)
+    }
+}
+
+impl PartitionKey for GuildEntity {
+    fn partition_key(&self) -> u64 {
+        self.id.0
+    }
+
+    fn id_partition_key(guild_id: Self::Id) -> Option<u64> {
+        Some(guild_id.0)
+    }
+}
+
+impl PartitionKey for GuildPreviewEntity {
+    fn partition_key(&self) -> u64 {
+        self.id.0
+    }
+
+    fn id_partition_key(guild_id: Self::Id) -> Option<u64> {
+        Some(guild_id.0)
+    }
+}
+
+impl PartitionKey for MemberEntity {
+    fn partition_key(&self) -> u64 {
+        self.guild_id.0
+    }
+
+    fn id_partition_key((guild_id, _): Self::Id) -> Option<u64> {
+        Some(guild_id.0)
+    }
+}
+
+impl PartitionKey for MessageEntity {
+    fn partition_key(&self) -> u64 {
+        self.guild_id.map_or(self.channel_id.0, |id| id.0)
+    }
+
+    fn id_partition_key(_: Self::Id) -> Option<u64> {
+        None
+    }
+}
+
+impl PartitionKey for PresenceEntity {
+    fn partition_key(&self) -> u64 {
+        self.guild_id.0
+    }
+
+    fn id_partition_key((guild_id, _): Self::Id) -> Option<u64> {
+        Some(guild_id.0)
+    }
+}
+
+impl PartitionKey for PrivateChannelEntity {
+    fn partition_key(&self) -> u64 {
+        self.id.0
+    }
+
+    fn id_partition_key(channel_id: Self::Id) -> Option<u64> {
+        Some(channel_id.0)
+    }
+}
+
+impl PartitionKey for RoleEntity {
+    fn partition_key(&self) -> u64 {
+        self.guild_id.0
+    }
+
+    fn id_partition_key(_: Self::Id) -> Option<u64> {
+        None
+    }
+}
+
+impl PartitionKey for TextChannelEntity {
+    fn partition_key(&self) -> u64 {
+        self.guild_id.map_or(self.id.0, |id| id.0)
+    }
+
+    fn id_partition_key(_: Self::Id) -> Option<u64> {
+        None
+    }
+}
+
+impl PartitionKey for UserEntity {
+    fn partition_key(&self) -> u64 {
+        self.id.0
+    }
+
+    fn id_partition_key(user_id: Self::Id) -> Option<u64> {
+        Some(user_id.0)
+    }
+}
+
+impl PartitionKey for VoiceChannelEntity {
+    fn partition_key(&self) -> u64 {
+        self.guild_id.map_or(self.id.0, |id| id.0)
+    }
+
+    fn id_partition_key(_: Self::Id) -> Option<u64> {
+        None
+    }
+}
+
+impl PartitionKey for VoiceRegionEntity {
+    fn partition_key(&self) -> u64 {
+        self.id().0
+    }
+
+    fn id_partition_key(region_id: Self::Id) -> Option<u64> {
+        Some(region_id.0)
+    }
+}
+
+impl PartitionKey for VoiceStateEntity {
+    fn partition_key(&self) -> u64 {
+        self.guild_id.0
+    }
+
+    fn id_partition_key((guild_id, _): Self::Id) -> Option<u64> {
+        Some(guild_id.0)
+    }
+}
+
+/// Backend storing each entity in one of a number of backends.
+///
+/// The partition of an entity is chosen with the same formula Discord uses to
+/// assign guilds to shards, `(guild_id >> 22) % partitions`, so partitions
+/// can be aligned with the shards of a bot.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+#[derive(Debug)]
+pub struct PartitionedBackend<B> {
+    partitions: Arc<[B]>,
+}
+
+impl<B: Backend> PartitionedBackend<B> {
+    /// Create a backend spreading entities across the given backends.
+    ///
+    /// The order of the backends must be the same in every process sharing
+    /// them, as it decides which backend an entity is stored in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no backends are given.
+    pub fn new(partitions: impl IntoIterator<Item = B>) -> Self {
+        let partitions = partitions.into_iter().collect::<Arc<[B]>>();
+        assert!(!partitions.is_empty(), "at least one partition is required");
+
+        Self { partitions }
+    }
+
+    /// Return an immutable reference to the partitions.
+    pub fn partitions(&self) -> &[B] {
+        &self.partitions
+    }
+
+    /// Return the index of the partition storing entities with the given
+    /// key, such as a guild ID.
+    pub fn partition(&self, key: u64) -> usize {
+        let len = self.partitions.len() as u64;
+
+        usize::try_from((key >> 22) % len).unwrap_or_default()
+    }
+
+    fn repository<R>(&self, f: impl FnMut(&B) -> R) -> PartitionedRepository<B, R> {
+        PartitionedRepository {
+            backend: self.clone(),
+            repos: self.partitions.iter().map(f).collect(),
+        }
+    }
+}
+
+impl<B> Clone for PartitionedBackend<B> {
+    fn clone(&self) -> Self {
+        Self {
+            partitions: Arc::clone(&self.partitions),
+        }
+    }
+}
+
+impl<B: Backend> Backend for PartitionedBackend<B> {
+    type Error = B::Error;
+    type AttachmentRepository = PartitionedRepository<B, B::AttachmentRepository>;
+    type CategoryChannelRepository = PartitionedRepository<B, B::CategoryChannelRepository>;
+    type CurrentUserRepository = PartitionedRepository<B, B::CurrentUserRepository>;
+    type EmojiRepository = PartitionedRepository<B, B::EmojiRepository>;
+    type GroupRepository = PartitionedRepository<B, B::GroupRepository>;
+    type GuildPreviewRepository = PartitionedRepository<B, B::GuildPreviewRepository>;
+    type GuildRepository = PartitionedRepository<B, B::GuildRepository>;
+    type MemberRepository = PartitionedRepository<B, B::MemberRepository>;
+    type MessageRepository = PartitionedRepository<B, B::MessageRepository>;
+    type PresenceRepository = PartitionedRepository<B, B::PresenceRepository>;
+    type PrivateChannelRepository = PartitionedRepository<B, B::PrivateChannelRepository>;
+    type RoleRepository = PartitionedRepository<B, B::RoleRepository>;
+    type TextChannelRepository = PartitionedRepository<B, B::TextChannelRepository>;
+    type UserRepository = PartitionedRepository<B, B::UserRepository>;
+    type VoiceChannelRepository = PartitionedRepository<B, B::VoiceChannelRepository>;
+    type VoiceRegionRepository = PartitionedRepository<B, B::VoiceRegionRepository>;
+    type VoiceStateRepository = PartitionedRepository<B, B::VoiceStateRepository>;
+
+    fn attachments(&self) -> Self::AttachmentRepository {
+        self.repository(B::attachments)
+    }
+
+    fn category_channels(&self) -> Self::CategoryChannelRepository {
+        self.repository(B::category_channels)
+    }
+
+    fn current_user(&self) -> Self::CurrentUserRepository {
+        self.repository(B::current_user)
+    }
+
+    fn emojis(&self) -> Self::EmojiRepository {
+        self.repository(B::emojis)
+    }
+
+    fn groups(&self) -> Self::GroupRepository {
+        self.repository(B::groups)
+    }
+
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        self.repository(B::guild_previews)
+    }
+
+    fn guilds(&self) -> Self::GuildRepository {
+        self.repository(B::guilds)
+    }
+
+    fn members(&self) -> Self::MemberRepository {
+        self.repository(B::members)
+    }
+
+    fn messages(&self) -> Self::MessageRepository {
+        self.repository(B::messages)
+    }
+
+    fn presences(&self) -> Self::PresenceRepository {
+        self.repository(B::presences)
+    }
+
+    fn private_channels(&self) -> Self::PrivateChannelRepository {
+        self.repository(B::private_channels)
+    }
+
+    fn roles(&self) -> Self::RoleRepository {
+        self.repository(B::roles)
+    }
+
+    fn text_channels(&self) -> Self::TextChannelRepository {
+        self.repository(B::text_channels)
+    }
+
+    fn users(&self) -> Self::UserRepository {
+        self.repository(B::users)
+    }
+
+    fn voice_channels(&self) -> Self::VoiceChannelRepository {
+        self.repository(B::voice_channels)
+    }
+
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        self.repository(B::voice_regions)
+    }
+
+    fn voice_states(&self) -> Self::VoiceStateRepository {
+        self.repository(B::voice_states)
+    }
+
+    fn connect(&self) -> ConnectFuture<'_, Self::Error> {
+        future::try_join_all(self.partitions.iter().map(B::connect))
+            .map_ok(|_| ())
+            .boxed()
+    }
+
+    fn reconnect(&self) -> ConnectFuture<'_, Self::Error> {
+        future::try_join_all(self.partitions.iter().map(B::reconnect))
+            .map_ok(|_| ())
+            .boxed()
+    }
+
+    fn flush(&self) -> FlushFuture<'_, Self::Error> {
+        future::try_join_all(self.partitions.iter().map(B::flush))
+            .map_ok(|_| ())
+            .boxed()
+    }
+
+    fn close(&self) -> CloseFuture<'_, Self::Error> {
+        future::try_join_all(self.partitions.iter().map(B::close))
+            .map_ok(|_| ())
+            .boxed()
+    }
+
+    fn health(&self) -> HealthFuture<'_, Self::Error> {
+        future::try_join_all(self.partitions.iter().map(B::health))
+            .map_ok(|_| ())
+            .boxed()
+    }
+
+    /// Return the statistics of the partitions' compressors added together,
+    /// if any of them compress entities.
+    #[cfg(feature = "compression")]
+    fn compression_stats(&self) -> Option<CompressionStats> {
+        self.partitions
+            .iter()
+            .filter_map(B::compression_stats)
+            .reduce(|total, stats| CompressionStats {
+                compressed: total.compressed + stats.compressed,
+                input_bytes: total.input_bytes + stats.input_bytes,
+                output_bytes: total.output_bytes + stats.output_bytes,
+                skipped: total.skipped + stats.skipped,
+            })
+    }
+
+    fn caches_guild(&self, guild_id: GuildId) -> bool {
+        self.partitions[self.partition(guild_id.0)].caches_guild(guild_id)
+    }
+
+    fn pins_guild(&self, guild_id: GuildId) -> bool {
+        self.partitions[self.partition(guild_id.0)].pins_guild(guild_id)
+    }
+
+    fn pins_user(&self, user_id: UserId) -> bool {
+        self.partitions
+            .iter()
+            .any(|partition| partition.pins_user(user_id))
+    }
+
+    fn verify_integrity(&self, repair: bool) -> VerifyIntegrityFuture<'_, Self::Error> {
+        future::try_join_all(
+            self.partitions
+                .iter()
+                .map(|partition| partition.verify_integrity(repair)),
+        )
+        .map_ok(move |reports| {
+            reports.into_iter().fold(
+                IntegrityReport::new(Vec::new(), repair),
+                |mut merged, report| {
+                    merged.merge(report);
+
+                    merged
+                },
+            )
+        })
+        .boxed()
+    }
+}
+
+/// Repository of a [`PartitionedBackend`] wrapping the repositories of its
+/// partitions.
+///
+/// [`PartitionedBackend`]: struct.PartitionedBackend.html
+#[derive(Clone, Debug)]
+pub struct PartitionedRepository<B, R> {
+    backend: PartitionedBackend<B>,
+    repos: Vec<R>,
+}
+
+impl<B: Backend, R> PartitionedRepository<B, R> {
+    /// Return an immutable reference to the repositories of the partitions.
+    pub fn partitions(&self) -> &[R] {
+        &self.repos
+    }
+
+    fn partition(&self, key: u64) -> &R {
+        &self.repos[self.backend.partition(key)]
+    }
+}
+
+impl<B, E, R> Repository<E, PartitionedBackend<B>> for PartitionedRepository<B, R>
+where
+    B: Backend,
+    E: PartitionKey + 'static,
+    R: Repository<E, B> + Send + Sync,
+{
+    fn backend(&self) -> &PartitionedBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        match E::id_partition_key(entity_id) {
+            Some(key) => self.partition(key).get(entity_id),
+            None => find(self.repos.iter().map(move |repo| repo.get(entity_id))),
+        }
+    }
+
+    fn get_local(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        match E::id_partition_key(entity_id) {
+            Some(key) => self.partition(key).get_local(entity_id),
+            None => find(self.repos.iter().map(move |repo| repo.get_local(entity_id))),
+        }
+    }
+
+    fn get_with_meta(
+        &self,
+        entity_id: E::Id,
+    ) -> GetEntityFuture<'_, (E, Option<CacheMeta>), B::Error> {
+        match E::id_partition_key(entity_id) {
+            Some(key) => self.partition(key).get_with_meta(entity_id),
+            None => find(
+                self.repos
+                    .iter()
+                    .map(move |repo| repo.get_with_meta(entity_id)),
+            ),
+        }
+    }
+
+    fn list(&self) -> ListEntitiesFuture<'_, E, B::Error> {
+        chain(self.repos.iter().map(Repository::list))
+    }
+
+    fn remove(&self, entity_id: E::Id) -> RemoveEntityFuture<'_, B::Error> {
+        match E::id_partition_key(entity_id) {
+            Some(key) => self.partition(key).remove(entity_id),
+            None => future::try_join_all(self.repos.iter().map(|repo| repo.remove(entity_id)))
+                .map_ok(|_| ())
+                .boxed(),
+        }
+    }
+
+    fn take(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        match E::id_partition_key(entity_id) {
+            Some(key) => self.partition(key).take(entity_id),
+            None => Box::pin(async move {
+                let entity = self.get_local(entity_id).await?;
+                self.remove(entity_id).await?;
+
+                Ok(entity)
+            }),
+        }
+    }
+
+    fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, B::Error> {
+        self.partition(entity.partition_key()).upsert(entity)
+    }
+
+    fn upsert_with_outcome(&self, entity: E) -> UpsertOutcomeFuture<'_, B::Error> {
+        self.partition(entity.partition_key())
+            .upsert_with_outcome(entity)
+    }
+
+    fn versioned_upsert(&self, entity: E, sequence: u64) -> UpsertEntityFuture<'_, B::Error> {
+        self.partition(entity.partition_key())
+            .versioned_upsert(entity, sequence)
+    }
+}
+
+impl<B, E, R> SingleEntityRepository<E, PartitionedBackend<B>> for PartitionedRepository<B, R>
+where
+    B: Backend,
+    E: Clone + Entity + 'static,
+    R: SingleEntityRepository<E, B> + Send + Sync,
+{
+    fn backend(&self) -> &PartitionedBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self) -> GetEntityFuture<'_, E, B::Error> {
+        find(self.repos.iter().map(SingleEntityRepository::get))
+    }
+
+    fn get_local(&self) -> GetEntityFuture<'_, E, B::Error> {
+        find(self.repos.iter().map(SingleEntityRepository::get_local))
+    }
+
+    fn remove(&self) -> RemoveEntityFuture<'_, B::Error> {
+        future::try_join_all(self.repos.iter().map(SingleEntityRepository::remove))
+            .map_ok(|_| ())
+            .boxed()
+    }
+
+    fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, B::Error> {
+        future::try_join_all(self.repos.iter().map(|repo| repo.upsert(entity.clone())))
+            .map_ok(|_| ())
+            .boxed()
+    }
+}
+
+impl<B: Backend> AttachmentRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::AttachmentRepository>
+{
+}
+
+impl<B: Backend> CategoryChannelRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::CategoryChannelRepository>
+{
+}
+
+impl<B: Backend> CurrentUserRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::CurrentUserRepository>
+{
+    fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        chain(self.repos.iter().map(CurrentUserRepository::guild_ids))
+    }
+}
+
+impl<B: Backend> EmojiRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::EmojiRepository>
+{
+}
+
+impl<B: Backend> GroupRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::GroupRepository>
+{
+}
+
+impl<B: Backend> GuildPreviewRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::GuildPreviewRepository>
+{
+}
+
+/// Relations within a guild are answered by the guild's partition.
+impl<B: Backend> GuildRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::GuildRepository>
+{
+    fn channel_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, ChannelId, B::Error> {
+        self.partition(guild_id.0).channel_ids(guild_id)
+    }
+
+    fn channels(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, GuildChannelEntity, B::Error> {
+        self.partition(guild_id.0).channels(guild_id)
+    }
+
+    fn emoji_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, EmojiId, B::Error> {
+        self.partition(guild_id.0).emoji_ids(guild_id)
+    }
+
+    fn emojis(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, EmojiEntity, B::Error> {
+        self.partition(guild_id.0).emojis(guild_id)
+    }
+
+    fn member_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.partition(guild_id.0).member_ids(guild_id)
+    }
+
+    fn members(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        self.partition(guild_id.0).members(guild_id)
+    }
+
+    fn presence_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.partition(guild_id.0).presence_ids(guild_id)
+    }
+
+    fn presences(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, PresenceEntity, B::Error> {
+        self.partition(guild_id.0).presences(guild_id)
+    }
+
+    fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, B::Error> {
+        self.partition(guild_id.0).role_ids(guild_id)
+    }
+
+    fn roles(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, RoleEntity, B::Error> {
+        self.partition(guild_id.0).roles(guild_id)
+    }
+
+    fn voice_state_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.partition(guild_id.0).voice_state_ids(guild_id)
+    }
+
+    fn voice_states(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceStateEntity, B::Error> {
+        self.partition(guild_id.0).voice_states(guild_id)
+    }
+}
+
+impl<B: Backend> MemberRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::MemberRepository>
+{
+    fn nick_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, NameChange, B::Error> {
+        self.partition(guild_id.0).nick_history(guild_id, user_id)
+    }
+}
+
+impl<B: Backend> MessageRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::MessageRepository>
+{
+}
+
+impl<B: Backend> PresenceRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::PresenceRepository>
+{
+}
+
+impl<B: Backend> PrivateChannelRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::PrivateChannelRepository>
+{
+}
+
+impl<B: Backend> RoleRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::RoleRepository>
+{
+}
+
+impl<B: Backend> TextChannelRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::TextChannelRepository>
+{
+}
+
+/// A user's guilds may be in any partition, so they're listed from all of
+/// them.
+impl<B: Backend> UserRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::UserRepository>
+{
+    fn avatar_history(&self, user_id: UserId) -> ListEntitiesFuture<'_, AvatarChange, B::Error> {
+        self.partition(user_id.0).avatar_history(user_id)
+    }
+
+    fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        chain(self.repos.iter().map(|repo| repo.guild_ids(user_id)))
+    }
+}
+
+impl<B: Backend> VoiceChannelRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::VoiceChannelRepository>
+{
+}
+
+impl<B: Backend> VoiceRegionRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::VoiceRegionRepository>
+{
+}
+
+impl<B: Backend> VoiceStateRepository<PartitionedBackend<B>>
+    for PartitionedRepository<B, B::VoiceStateRepository>
+{
+    fn transition_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, VoiceStateChange, B::Error> {
+        self.partition(guild_id.0)
+            .transition_history(guild_id, user_id)
+    }
+}
+
+/// Return the first entity found by the futures, awaiting them in order.
+fn find<'a, T: Send + 'a, E: Send + 'a>(
+    futures: impl Iterator<Item = GetEntityFuture<'a, T, E>> + Send + 'a,
+) -> GetEntityFuture<'a, T, E> {
+    Box::pin(async move {
+        for future in futures {
+            if let Some(entity) = future.await? {
+                return Ok(Some(entity));
+            }
+        }
+
+        Ok(None)
+    })
+}
+
+/// Concatenate the streams of the futures.
+fn chain<'a, T: Send + 'a, E: Send + 'a>(
+    futures: impl Iterator<Item = ListEntitiesFuture<'a, T, E>>,
+) -> ListEntitiesFuture<'a, T, E> {
+    future::try_join_all(futures)
+        .map_ok(|streams| stream::iter(streams).flatten().boxed())
+        .boxed()
+}
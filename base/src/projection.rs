@@ -0,0 +1,64 @@
+//! Reading a subset of an entity's fields.
+//!
+//! Backends storing entities remotely, such as in a database or another
+//! service, transfer a whole entity on every [`Repository::get`] even when
+//! only one of its fields is needed. A [`Projection`] describes the fields
+//! that a read needs and how to build the projected value, and can be
+//! retrieved with [`Repository::get_projected`].
+//!
+//! By default a projection is made from the full entity, so projections work
+//! with every backend. Backends can implement `get_projected` to only fetch
+//! the fields named by [`Projection::FIELDS`].
+//!
+//! # Examples
+//!
+//! Define a projection of a user's name and discriminator:
+//!
+//! ```
+//! use twilight_cache::{entity::user::UserEntity, projection::Projection};
+//!
+//! struct UserTag(String);
+//!
+//! impl Projection<UserEntity> for UserTag {
+//!     const FIELDS: &'static [&'static str] = &["discriminator", "name"];
+//!
+//!     fn project(user: UserEntity) -> Self {
+//!         Self(format!("{}#{}", user.name, user.discriminator))
+//!     }
+//! }
+//! ```
+//!
+//! [`Projection`]: trait.Projection.html
+//! [`Projection::FIELDS`]: trait.Projection.html#associatedconstant.FIELDS
+//! [`Repository::get`]: ../repository/trait.Repository.html#tymethod.get
+//! [`Repository::get_projected`]: ../repository/trait.Repository.html#method.get_projected
+
+#[cfg(feature = "discord")]
+use super::entity::guild::GuildEntity;
+use super::entity::Entity;
+
+/// Value made from a subset of the fields of an entity.
+pub trait Projection<T: Entity>: Sized {
+    /// Names of the entity's fields that the projection is made from.
+    ///
+    /// Names are those of the entity's struct fields, which are also the
+    /// names they're serialized with.
+    const FIELDS: &'static [&'static str];
+
+    /// Make the projection from an entity.
+    fn project(entity: T) -> Self;
+}
+
+/// Projection of a guild's name.
+#[cfg(feature = "discord")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct GuildName(pub String);
+
+#[cfg(feature = "discord")]
+impl Projection<GuildEntity> for GuildName {
+    const FIELDS: &'static [&'static str] = &["name"];
+
+    fn project(guild: GuildEntity) -> Self {
+        Self(guild.name)
+    }
+}
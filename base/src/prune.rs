@@ -0,0 +1,244 @@
+//! Removal of old and orphaned entities according to retention rules.
+//!
+//! Backends that persist entities outside of the process only remove entities
+//! when Discord says they were deleted, so they grow forever as messages are
+//! sent. A [`Pruner`] removes entities that are older than configured
+//! retention periods or that no longer relate to a cached entity.
+//!
+//! A pruner can be run manually via [`Cache::prune`], or, with the `tokio`
+//! feature enabled, periodically in a background task via [`Pruner::spawn`].
+//!
+//! # Examples
+//!
+//! Remove messages sent more than a week ago and attachments of messages that
+//! aren't cached:
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::time::Duration;
+//! use twilight_cache::prune::Pruner;
+//! use twilight_cache_inmemory::InMemoryCache;
+//!
+//! let mut builder = Pruner::builder();
+//! builder
+//!     .message_max_age(Duration::from_secs(7 * 24 * 60 * 60))
+//!     .orphaned_attachments(true);
+//! let pruner = builder.build();
+//!
+//! let cache = InMemoryCache::new();
+//! let stats = cache.prune(&pruner).await?;
+//! println!("pruned {} messages", stats.messages);
+//! # Ok(()) }
+//! ```
+//!
+//! [`Cache::prune`]: ../struct.Cache.html#method.prune
+//! [`Pruner`]: struct.Pruner.html
+//! [`Pruner::spawn`]: struct.Pruner.html#method.spawn
+
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{AttachmentEntity, MessageEntity},
+        gateway::PresenceEntity,
+        Entity,
+    },
+    repository::Repository,
+    snowflake::SnowflakeEntity,
+};
+use futures_util::stream::StreamExt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use twilight_model::gateway::presence::Status;
+
+/// Retention rules deciding which entities to remove from a cache.
+///
+/// By default no entities are removed. Use [`Pruner::builder`] to configure
+/// the rules.
+///
+/// [`Pruner::builder`]: #method.builder
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Pruner {
+    message_max_age: Option<Duration>,
+    offline_presences: bool,
+    orphaned_attachments: bool,
+}
+
+impl Pruner {
+    /// Create a builder to configure the retention rules of a pruner.
+    pub fn builder() -> PrunerBuilder {
+        PrunerBuilder::new()
+    }
+
+    /// Return the age after which messages are removed.
+    pub fn message_max_age(&self) -> Option<Duration> {
+        self.message_max_age
+    }
+
+    /// Return whether presences with an offline status are removed.
+    pub fn offline_presences(&self) -> bool {
+        self.offline_presences
+    }
+
+    /// Return whether attachments of messages that aren't cached are removed.
+    pub fn orphaned_attachments(&self) -> bool {
+        self.orphaned_attachments
+    }
+
+    /// Remove the entities matching the retention rules from a backend.
+    ///
+    /// Messages are removed first so that attachments orphaned by their
+    /// removal are removed in the same run. Messages and presences in
+    /// [pinned guilds] or of [pinned users] are kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns the backend's error if listing or removing entities fails.
+    ///
+    /// [pinned guilds]: ../trait.Backend.html#method.pins_guild
+    /// [pinned users]: ../trait.Backend.html#method.pins_user
+    pub async fn prune<B: Backend>(&self, backend: &B) -> Result<PruneStats, B::Error> {
+        let mut stats = PruneStats::default();
+
+        if let Some(max_age) = self.message_max_age {
+            let cutoff = SystemTime::now().checked_sub(max_age).unwrap_or(UNIX_EPOCH);
+            let messages = backend.messages();
+
+            stats.messages = remove_matching(&messages, |message: &MessageEntity| {
+                message.created_at() < cutoff
+                    && !message
+                        .guild_id
+                        .is_some_and(|guild_id| backend.pins_guild(guild_id))
+                    && !backend.pins_user(message.author_id)
+            })
+            .await?;
+        }
+
+        if self.orphaned_attachments {
+            let attachments = backend.attachments();
+            let messages = backend.messages();
+            let mut orphaned = Vec::new();
+            let mut stream = attachments.list().await?;
+
+            while let Some(attachment) = stream.next().await {
+                let attachment: AttachmentEntity = attachment?;
+
+                if messages.get(attachment.message_id).await?.is_none() {
+                    orphaned.push(attachment.id);
+                }
+            }
+
+            drop(stream);
+            stats.attachments = orphaned.len();
+            attachments.remove_bulk(orphaned.into_iter()).await?;
+        }
+
+        if self.offline_presences {
+            let presences = backend.presences();
+
+            stats.presences = remove_matching(&presences, |presence: &PresenceEntity| {
+                presence.status == Status::Offline
+                    && !backend.pins_guild(presence.guild_id)
+                    && !backend.pins_user(presence.user_id)
+            })
+            .await?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Spawn a task on the Tokio runtime pruning a backend every period.
+    ///
+    /// The first run happens immediately. The task stops when pruning fails,
+    /// resolving the handle to the error.
+    #[cfg(feature = "tokio")]
+    pub fn spawn<B: Backend>(
+        self,
+        backend: std::sync::Arc<B>,
+        period: Duration,
+    ) -> tokio::task::JoinHandle<Result<(), B::Error>> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+
+            loop {
+                interval.tick().await;
+                self.prune(backend.as_ref()).await?;
+            }
+        })
+    }
+}
+
+/// Builder to create a [`Pruner`] with retention rules.
+///
+/// [`Pruner`]: struct.Pruner.html
+#[derive(Clone, Debug, Default)]
+pub struct PrunerBuilder(Pruner);
+
+impl PrunerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn build(self) -> Pruner {
+        self.0
+    }
+
+    /// Remove messages sent longer ago than the given age.
+    ///
+    /// The age of a message is determined by its ID.
+    pub fn message_max_age(&mut self, message_max_age: Duration) -> &mut Self {
+        self.0.message_max_age = Some(message_max_age);
+
+        self
+    }
+
+    /// Whether to remove presences with an offline status.
+    ///
+    /// Presences don't record when they were last updated, so they can't be
+    /// removed by age. Offline presences carry little information and usually
+    /// make up most of a large guild's presences.
+    pub fn offline_presences(&mut self, offline_presences: bool) -> &mut Self {
+        self.0.offline_presences = offline_presences;
+
+        self
+    }
+
+    /// Whether to remove attachments of messages that aren't cached.
+    pub fn orphaned_attachments(&mut self, orphaned_attachments: bool) -> &mut Self {
+        self.0.orphaned_attachments = orphaned_attachments;
+
+        self
+    }
+}
+
+/// Number of entities of each type removed by a pruner.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PruneStats {
+    /// Number of removed attachments.
+    pub attachments: usize,
+    /// Number of removed messages.
+    pub messages: usize,
+    /// Number of removed presences.
+    pub presences: usize,
+}
+
+async fn remove_matching<B: Backend, E: Entity, R: Repository<E, B>>(
+    repo: &R,
+    mut predicate: impl FnMut(&E) -> bool,
+) -> Result<usize, B::Error> {
+    let mut ids = Vec::new();
+    let mut stream = repo.list().await?;
+
+    while let Some(entity) = stream.next().await {
+        let entity = entity?;
+
+        if predicate(&entity) {
+            ids.push(entity.id());
+        }
+    }
+
+    drop(stream);
+    let count = ids.len();
+    repo.remove_bulk(ids.into_iter()).await?;
+
+    Ok(count)
+}
@@ -0,0 +1,670 @@
+//! Backend limiting the rate of operations performed on another backend.
+//!
+//! When a large bot reconnects, it receives a `GuildCreate` for each of its
+//! guilds in quick succession. Processing them results in a burst of tens of
+//! thousands of operations, which can saturate a database shared with other
+//! services. A [`RateLimitedBackend`] wraps a backend and allows at most a
+//! number of operations per second against it, queuing the operations beyond
+//! that until they're allowed.
+//!
+//! The limit is enforced with a token bucket: up to the burst size of
+//! operations can be performed at once, after which operations are let
+//! through at the configured rate. Queued operations are let through in the
+//! order they were made.
+//!
+//! Each get, upsert, removal, and listing counts as one operation. Bulk
+//! upserts and removals count as one operation per entity.
+//!
+//! # Examples
+//!
+//! Allow at most 500 operations per second against a backend:
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{
+//!     entity::guild::GuildEntity, rate_limit::RateLimitedBackend, Backend, Repository,
+//! };
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let backend = RateLimitedBackend::new(InMemoryBackend::new(), 500);
+//!
+//! let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+//! backend.guilds().upsert(guild.clone()).await?;
+//!
+//! assert_eq!(Some(guild), backend.guilds().get(GuildId(1)).await?);
+//! # Ok(()) }
+//! ```
+//!
+//! [`RateLimitedBackend`]: struct.RateLimitedBackend.html
+
+#[cfg(feature = "compression")]
+use super::compression::CompressionStats;
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{
+            AttachmentRepository, CategoryChannelRepository, GroupRepository, GuildChannelEntity,
+            MessageContext, MessageRepository, PrivateChannelRepository, TextChannelRepository,
+            VoiceChannelRepository,
+        },
+        gateway::{PresenceEntity, PresenceRepository},
+        guild::{
+            EmojiRepository, GuildPreviewRepository, GuildRepository, MemberEntity,
+            MemberRepository, NameChange, RoleRepository,
+        },
+        user::{
+            AvatarChange, CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository,
+        },
+        voice::{VoiceRegionRepository, VoiceStateChange, VoiceStateEntity, VoiceStateRepository},
+        Entity,
+    },
+    integrity::VerifyIntegrityFuture,
+    meta::CacheMeta,
+    repository::{
+        CloseFuture, ConnectFuture, FlushFuture, GetEntityFuture, HealthFuture, ListEntitiesFuture,
+        ListEntityIdsFuture, RemoveEntitiesFuture, RemoveEntityFuture, Repository,
+        SingleEntityRepository, UpsertEntitiesFuture, UpsertEntityFuture, UpsertOutcomeFuture,
+    },
+};
+use std::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use twilight_model::id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+type OperationFuture<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>;
+
+/// Token bucket deciding when operations are allowed.
+///
+/// Rather than tracking the tokens in the bucket, this tracks the time at
+/// which the bucket would be full again if no more operations were made.
+/// Operations reserve their tokens immediately, so an operation that has to
+/// wait is never overtaken by one made after it.
+#[derive(Debug)]
+struct TokenBucket {
+    burst: Duration,
+    interval: Duration,
+    full_at: Mutex<Instant>,
+}
+
+impl TokenBucket {
+    fn new(per_second: u32, burst: u32) -> Self {
+        let interval = Duration::from_secs(1) / per_second.max(1);
+
+        Self {
+            burst: interval * burst.max(1),
+            interval,
+            full_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Reserve tokens for a number of operations, returning how long to wait
+    /// until they're allowed.
+    fn reserve(&self, operations: usize) -> Duration {
+        let operations = u32::try_from(operations).unwrap_or(u32::MAX);
+        let now = Instant::now();
+        let mut full_at = self.full_at.lock().unwrap_or_else(PoisonError::into_inner);
+
+        *full_at = (*full_at).max(now) + self.interval * operations;
+
+        full_at
+            .saturating_duration_since(now)
+            .saturating_sub(self.burst)
+    }
+}
+
+struct RateLimitedState<B> {
+    backend: B,
+    bucket: TokenBucket,
+}
+
+/// Backend limiting the rate of operations performed on the wrapped backend.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+pub struct RateLimitedBackend<B>(Arc<RateLimitedState<B>>);
+
+impl<B: Backend> RateLimitedBackend<B> {
+    /// Wrap a backend, allowing at most the given number of operations per
+    /// second with bursts of up to as many operations.
+    ///
+    /// A rate of 0 is treated as 1.
+    pub fn new(backend: B, per_second: u32) -> Self {
+        Self::with_burst(backend, per_second, per_second)
+    }
+
+    /// Wrap a backend, allowing at most the given number of operations per
+    /// second with bursts of up to `burst` operations.
+    ///
+    /// A rate or burst size of 0 is treated as 1.
+    pub fn with_burst(backend: B, per_second: u32, burst: u32) -> Self {
+        Self(Arc::new(RateLimitedState {
+            backend,
+            bucket: TokenBucket::new(per_second, burst),
+        }))
+    }
+
+    /// Return an immutable reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.0.backend
+    }
+
+    /// Wait until a number of operations are allowed.
+    async fn acquire(&self, operations: usize) {
+        let wait = self.0.bucket.reserve(operations);
+
+        if !wait.is_zero() {
+            tokio::time::delay_for(wait).await;
+        }
+    }
+
+    fn repository<R>(&self, f: impl FnOnce(&B) -> R) -> RateLimitedRepository<B, R> {
+        RateLimitedRepository {
+            backend: self.clone(),
+            repo: f(self.inner()),
+        }
+    }
+}
+
+impl<B> Clone for RateLimitedBackend<B> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<B: Debug> Debug for RateLimitedBackend<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("RateLimitedBackend")
+            .field("backend", &self.0.backend)
+            .field("bucket", &self.0.bucket)
+            .finish()
+    }
+}
+
+impl<B: Backend> Backend for RateLimitedBackend<B> {
+    type Error = B::Error;
+    type AttachmentRepository = RateLimitedRepository<B, B::AttachmentRepository>;
+    type CategoryChannelRepository = RateLimitedRepository<B, B::CategoryChannelRepository>;
+    type CurrentUserRepository = RateLimitedRepository<B, B::CurrentUserRepository>;
+    type EmojiRepository = RateLimitedRepository<B, B::EmojiRepository>;
+    type GroupRepository = RateLimitedRepository<B, B::GroupRepository>;
+    type GuildPreviewRepository = RateLimitedRepository<B, B::GuildPreviewRepository>;
+    type GuildRepository = RateLimitedRepository<B, B::GuildRepository>;
+    type MemberRepository = RateLimitedRepository<B, B::MemberRepository>;
+    type MessageRepository = RateLimitedRepository<B, B::MessageRepository>;
+    type PresenceRepository = RateLimitedRepository<B, B::PresenceRepository>;
+    type PrivateChannelRepository = RateLimitedRepository<B, B::PrivateChannelRepository>;
+    type RoleRepository = RateLimitedRepository<B, B::RoleRepository>;
+    type TextChannelRepository = RateLimitedRepository<B, B::TextChannelRepository>;
+    type UserRepository = RateLimitedRepository<B, B::UserRepository>;
+    type VoiceChannelRepository = RateLimitedRepository<B, B::VoiceChannelRepository>;
+    type VoiceRegionRepository = RateLimitedRepository<B, B::VoiceRegionRepository>;
+    type VoiceStateRepository = RateLimitedRepository<B, B::VoiceStateRepository>;
+
+    fn attachments(&self) -> Self::AttachmentRepository {
+        self.repository(B::attachments)
+    }
+
+    fn category_channels(&self) -> Self::CategoryChannelRepository {
+        self.repository(B::category_channels)
+    }
+
+    fn current_user(&self) -> Self::CurrentUserRepository {
+        self.repository(B::current_user)
+    }
+
+    fn emojis(&self) -> Self::EmojiRepository {
+        self.repository(B::emojis)
+    }
+
+    fn groups(&self) -> Self::GroupRepository {
+        self.repository(B::groups)
+    }
+
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        self.repository(B::guild_previews)
+    }
+
+    fn guilds(&self) -> Self::GuildRepository {
+        self.repository(B::guilds)
+    }
+
+    fn members(&self) -> Self::MemberRepository {
+        self.repository(B::members)
+    }
+
+    fn messages(&self) -> Self::MessageRepository {
+        self.repository(B::messages)
+    }
+
+    fn presences(&self) -> Self::PresenceRepository {
+        self.repository(B::presences)
+    }
+
+    fn private_channels(&self) -> Self::PrivateChannelRepository {
+        self.repository(B::private_channels)
+    }
+
+    fn roles(&self) -> Self::RoleRepository {
+        self.repository(B::roles)
+    }
+
+    fn text_channels(&self) -> Self::TextChannelRepository {
+        self.repository(B::text_channels)
+    }
+
+    fn users(&self) -> Self::UserRepository {
+        self.repository(B::users)
+    }
+
+    fn voice_channels(&self) -> Self::VoiceChannelRepository {
+        self.repository(B::voice_channels)
+    }
+
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        self.repository(B::voice_regions)
+    }
+
+    fn voice_states(&self) -> Self::VoiceStateRepository {
+        self.repository(B::voice_states)
+    }
+
+    fn connect(&self) -> ConnectFuture<'_, Self::Error> {
+        self.inner().connect()
+    }
+
+    fn reconnect(&self) -> ConnectFuture<'_, Self::Error> {
+        self.inner().reconnect()
+    }
+
+    fn flush(&self) -> FlushFuture<'_, Self::Error> {
+        self.inner().flush()
+    }
+
+    fn close(&self) -> CloseFuture<'_, Self::Error> {
+        self.inner().close()
+    }
+
+    fn health(&self) -> HealthFuture<'_, Self::Error> {
+        self.inner().health()
+    }
+
+    #[cfg(feature = "compression")]
+    fn compression_stats(&self) -> Option<CompressionStats> {
+        self.inner().compression_stats()
+    }
+
+    fn caches_guild(&self, guild_id: GuildId) -> bool {
+        self.inner().caches_guild(guild_id)
+    }
+
+    fn pins_guild(&self, guild_id: GuildId) -> bool {
+        self.inner().pins_guild(guild_id)
+    }
+
+    fn pins_user(&self, user_id: UserId) -> bool {
+        self.inner().pins_user(user_id)
+    }
+
+    fn verify_integrity(&self, repair: bool) -> VerifyIntegrityFuture<'_, Self::Error> {
+        self.inner().verify_integrity(repair)
+    }
+}
+
+/// Repository of a [`RateLimitedBackend`] wrapping a repository of the
+/// wrapped backend.
+///
+/// [`RateLimitedBackend`]: struct.RateLimitedBackend.html
+#[derive(Debug)]
+pub struct RateLimitedRepository<B, R> {
+    backend: RateLimitedBackend<B>,
+    repo: R,
+}
+
+impl<B, R> RateLimitedRepository<B, R> {
+    /// Return an immutable reference to the wrapped repository.
+    pub fn inner(&self) -> &R {
+        &self.repo
+    }
+}
+
+impl<B: Backend, R: Sync> RateLimitedRepository<B, R> {
+    /// Perform an operation on the wrapped repository once it's allowed.
+    fn limited<'a, T: 'a>(
+        &'a self,
+        operations: usize,
+        operation: impl FnOnce(&'a R) -> OperationFuture<'a, T, B::Error> + Send + 'a,
+    ) -> OperationFuture<'a, T, B::Error> {
+        Box::pin(async move {
+            self.backend.acquire(operations).await;
+
+            operation(&self.repo).await
+        })
+    }
+}
+
+impl<B, R: Clone> Clone for RateLimitedRepository<B, R> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            repo: self.repo.clone(),
+        }
+    }
+}
+
+impl<B, E, R> Repository<E, RateLimitedBackend<B>> for RateLimitedRepository<B, R>
+where
+    B: Backend,
+    E: Entity + 'static,
+    R: Repository<E, B> + Send + Sync,
+{
+    fn backend(&self) -> &RateLimitedBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.limited(1, move |repo| repo.get(entity_id))
+    }
+
+    fn get_local(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.limited(1, move |repo| repo.get_local(entity_id))
+    }
+
+    fn get_with_meta(
+        &self,
+        entity_id: E::Id,
+    ) -> GetEntityFuture<'_, (E, Option<CacheMeta>), B::Error> {
+        self.limited(1, move |repo| repo.get_with_meta(entity_id))
+    }
+
+    fn list(&self) -> ListEntitiesFuture<'_, E, B::Error> {
+        self.limited(1, R::list)
+    }
+
+    fn remove(&self, entity_id: E::Id) -> RemoveEntityFuture<'_, B::Error> {
+        self.limited(1, move |repo| repo.remove(entity_id))
+    }
+
+    fn remove_bulk<T: Iterator<Item = E::Id>>(
+        &self,
+        entity_ids: T,
+    ) -> RemoveEntitiesFuture<'_, B::Error> {
+        let entity_ids = entity_ids.collect::<Vec<_>>();
+
+        self.limited(entity_ids.len(), move |repo| {
+            repo.remove_bulk(entity_ids.into_iter())
+        })
+    }
+
+    fn take(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.limited(1, move |repo| repo.take(entity_id))
+    }
+
+    fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, B::Error> {
+        self.limited(1, move |repo| repo.upsert(entity))
+    }
+
+    fn upsert_with_outcome(&self, entity: E) -> UpsertOutcomeFuture<'_, B::Error> {
+        self.limited(1, move |repo| repo.upsert_with_outcome(entity))
+    }
+
+    fn versioned_upsert(&self, entity: E, sequence: u64) -> UpsertEntityFuture<'_, B::Error> {
+        self.limited(1, move |repo| repo.versioned_upsert(entity, sequence))
+    }
+
+    fn upsert_bulk<T: Iterator<Item = E> + Send>(
+        &self,
+        entities: T,
+    ) -> UpsertEntitiesFuture<'_, B::Error> {
+        let entities = entities.collect::<Vec<_>>();
+
+        self.limited(entities.len(), move |repo| {
+            repo.upsert_bulk(entities.into_iter())
+        })
+    }
+}
+
+impl<B, R> SingleEntityRepository<CurrentUserEntity, RateLimitedBackend<B>>
+    for RateLimitedRepository<B, R>
+where
+    B: Backend,
+    R: SingleEntityRepository<CurrentUserEntity, B> + Send + Sync,
+{
+    fn backend(&self) -> &RateLimitedBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self) -> GetEntityFuture<'_, CurrentUserEntity, B::Error> {
+        self.limited(1, R::get)
+    }
+
+    fn get_local(&self) -> GetEntityFuture<'_, CurrentUserEntity, B::Error> {
+        self.limited(1, R::get_local)
+    }
+
+    fn remove(&self) -> RemoveEntityFuture<'_, B::Error> {
+        self.limited(1, R::remove)
+    }
+
+    fn upsert(&self, entity: CurrentUserEntity) -> UpsertEntityFuture<'_, B::Error> {
+        self.limited(1, move |repo| repo.upsert(entity))
+    }
+}
+
+impl<B: Backend> AttachmentRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::AttachmentRepository>
+{
+}
+
+impl<B: Backend> CategoryChannelRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::CategoryChannelRepository>
+{
+}
+
+impl<B: Backend> CurrentUserRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::CurrentUserRepository>
+{
+    fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        self.limited(1, |repo| repo.guild_ids())
+    }
+}
+
+impl<B: Backend> EmojiRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::EmojiRepository>
+{
+}
+
+impl<B: Backend> GroupRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::GroupRepository>
+{
+}
+
+impl<B: Backend> GuildPreviewRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::GuildPreviewRepository>
+{
+}
+
+impl<B: Backend> GuildRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::GuildRepository>
+{
+    fn channel_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, ChannelId, B::Error> {
+        self.limited(1, move |repo| repo.channel_ids(guild_id))
+    }
+
+    fn channels(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, GuildChannelEntity, B::Error> {
+        self.limited(1, move |repo| repo.channels(guild_id))
+    }
+
+    fn emoji_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, EmojiId, B::Error> {
+        self.limited(1, move |repo| repo.emoji_ids(guild_id))
+    }
+
+    fn member_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.limited(1, move |repo| repo.member_ids(guild_id))
+    }
+
+    fn members(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        self.limited(1, move |repo| repo.members(guild_id))
+    }
+
+    fn members_with_users(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, (MemberEntity, Option<UserEntity>), B::Error> {
+        self.limited(1, move |repo| repo.members_with_users(guild_id))
+    }
+
+    fn presence_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.limited(1, move |repo| repo.presence_ids(guild_id))
+    }
+
+    fn presences(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, PresenceEntity, B::Error> {
+        self.limited(1, move |repo| repo.presences(guild_id))
+    }
+
+    fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, B::Error> {
+        self.limited(1, move |repo| repo.role_ids(guild_id))
+    }
+
+    fn search_members(
+        &self,
+        guild_id: GuildId,
+        query: &str,
+        limit: usize,
+    ) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        let query = query.to_owned();
+
+        self.limited(1, move |repo| repo.search_members(guild_id, &query, limit))
+    }
+
+    fn voice_state_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.limited(1, move |repo| repo.voice_state_ids(guild_id))
+    }
+
+    fn voice_states(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceStateEntity, B::Error> {
+        self.limited(1, move |repo| repo.voice_states(guild_id))
+    }
+}
+
+impl<B: Backend> MemberRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::MemberRepository>
+{
+    fn nick_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, NameChange, B::Error> {
+        self.limited(1, move |repo| repo.nick_history(guild_id, user_id))
+    }
+}
+
+impl<B: Backend> MessageRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::MessageRepository>
+{
+    fn context(&self, message_id: MessageId) -> GetEntityFuture<'_, MessageContext, B::Error> {
+        self.limited(1, move |repo| repo.context(message_id))
+    }
+}
+
+impl<B: Backend> PresenceRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::PresenceRepository>
+{
+}
+
+impl<B: Backend> PrivateChannelRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::PrivateChannelRepository>
+{
+}
+
+impl<B: Backend> RoleRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::RoleRepository>
+{
+}
+
+impl<B: Backend> TextChannelRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::TextChannelRepository>
+{
+}
+
+impl<B: Backend> UserRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::UserRepository>
+{
+    fn avatar_history(&self, user_id: UserId) -> ListEntitiesFuture<'_, AvatarChange, B::Error> {
+        self.limited(1, move |repo| repo.avatar_history(user_id))
+    }
+
+    fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        self.limited(1, move |repo| repo.guild_ids(user_id))
+    }
+}
+
+impl<B: Backend> VoiceChannelRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::VoiceChannelRepository>
+{
+}
+
+impl<B: Backend> VoiceRegionRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::VoiceRegionRepository>
+{
+}
+
+impl<B: Backend> VoiceStateRepository<RateLimitedBackend<B>>
+    for RateLimitedRepository<B, B::VoiceStateRepository>
+{
+    fn transition_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, VoiceStateChange, B::Error> {
+        self.limited(1, move |repo| repo.transition_history(guild_id, user_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenBucket;
+    use std::time::Duration;
+
+    #[test]
+    fn test_burst() {
+        let bucket = TokenBucket::new(10, 3);
+
+        for _ in 0..3 {
+            assert_eq!(Duration::ZERO, bucket.reserve(1));
+        }
+
+        assert!(!bucket.reserve(1).is_zero());
+    }
+
+    #[test]
+    fn test_queued_waits_grow() {
+        let bucket = TokenBucket::new(10, 1);
+        assert_eq!(Duration::ZERO, bucket.reserve(1));
+
+        let first = bucket.reserve(1);
+        let second = bucket.reserve(1);
+        let third = bucket.reserve(2);
+
+        assert!(first > Duration::ZERO && first <= Duration::from_millis(100));
+        assert!(second > first);
+        assert!(third > second + Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_operations_saturate() {
+        let bucket = TokenBucket::new(1000, 1);
+        let interval = Duration::from_millis(1);
+
+        assert_eq!(interval * (u32::MAX - 1), bucket.reserve(usize::MAX));
+    }
+}
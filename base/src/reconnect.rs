@@ -0,0 +1,808 @@
+//! Backend re-establishing the connection of another backend after outages.
+//!
+//! Backends storing entities in a remote datastore, such as a database
+//! server, lose their connection when the datastore restarts or the network
+//! fails. [`Backend::connect`] and [`Backend::reconnect`] define how a backend
+//! establishes its connection, and a [`ReconnectingBackend`] wraps a backend
+//! to reconnect it transparently, so that backends don't each implement their
+//! own retry logic.
+//!
+//! When an operation on the wrapped backend fails, its [health check] decides
+//! whether the failure is an outage. During an outage:
+//!
+//! - writes are buffered, up to a maximum number, and replayed in the order
+//!   they were made once the backend has reconnected;
+//! - reconnecting is attempted by the first operation made after the retry
+//!   interval elapses, and reads fail if the attempt fails;
+//! - reads made between attempts are passed through to the wrapped backend,
+//!   and don't see the buffered writes.
+//!
+//! So that an outage can't consume unbounded memory, a write made while the
+//! buffer is full drops the buffered writes before being buffered itself.
+//! Writes are never passed through to the wrapped backend ahead of buffered
+//! ones, since replaying the older writes would then overwrite newer data.
+//! The dropped writes leave the cache stale, which
+//! [`ReconnectingBackend::take_overflow`] reports so that it can be
+//! resynchronized. Flushing the backend, such as when the cache is shut down,
+//! attempts to reconnect it and replay the buffered writes regardless of the
+//! retry interval, and fails if it can't.
+//!
+//! # Examples
+//!
+//! Buffer up to 10000 writes while the wrapped backend is disconnected:
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{
+//!     entity::guild::GuildEntity, reconnect::ReconnectingBackend, Backend, Repository,
+//! };
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let backend = ReconnectingBackend::new(InMemoryBackend::new(), 10_000);
+//! backend.connect().await?;
+//!
+//! let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+//! backend.guilds().upsert(guild.clone()).await?;
+//!
+//! assert_eq!(Some(guild), backend.guilds().get(GuildId(1)).await?);
+//! assert!(backend.is_connected());
+//! # Ok(()) }
+//! ```
+//!
+//! [`Backend::connect`]: ../trait.Backend.html#method.connect
+//! [`Backend::reconnect`]: ../trait.Backend.html#method.reconnect
+//! [`ReconnectingBackend`]: struct.ReconnectingBackend.html
+//! [`ReconnectingBackend::take_overflow`]: struct.ReconnectingBackend.html#method.take_overflow
+//! [health check]: ../trait.Backend.html#method.health
+
+#[cfg(feature = "compression")]
+use super::compression::CompressionStats;
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{
+            AttachmentRepository, CategoryChannelRepository, GroupRepository, GuildChannelEntity,
+            MessageContext, MessageRepository, PrivateChannelRepository, TextChannelRepository,
+            VoiceChannelRepository,
+        },
+        gateway::{PresenceEntity, PresenceRepository},
+        guild::{
+            EmojiRepository, GuildPreviewRepository, GuildRepository, MemberEntity,
+            MemberRepository, NameChange, RoleRepository,
+        },
+        user::{
+            AvatarChange, CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository,
+        },
+        voice::{VoiceRegionRepository, VoiceStateChange, VoiceStateEntity, VoiceStateRepository},
+        Entity,
+    },
+    integrity::VerifyIntegrityFuture,
+    meta::CacheMeta,
+    repository::{
+        CloseFuture, ConnectFuture, FlushFuture, GetEntityFuture, HealthFuture, ListEntitiesFuture,
+        ListEntityIdsFuture, RemoveEntitiesFuture, RemoveEntityFuture, Repository,
+        SingleEntityRepository, UpsertEntitiesFuture, UpsertEntityFuture,
+    },
+};
+use futures_util::lock::Mutex as AsyncMutex;
+use std::{
+    collections::VecDeque,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+use twilight_model::id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+type OperationFuture<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>;
+
+/// Write made during an outage, replayed against the wrapped backend once it
+/// has reconnected.
+type BufferedWrite<B> =
+    Box<dyn for<'a> Fn(&'a B) -> OperationFuture<'a, (), <B as Backend>::Error> + Send + Sync>;
+
+/// Box a write so that it can be buffered.
+///
+/// Taking the write as a generic parameter lets the compiler infer the
+/// closure's signature from the higher-ranked bound.
+fn buffered<B, F>(write: F) -> BufferedWrite<B>
+where
+    B: Backend,
+    F: for<'a> Fn(&'a B) -> OperationFuture<'a, (), B::Error> + Send + Sync + 'static,
+{
+    Box::new(write)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+struct ReconnectingState<B: Backend> {
+    backend: B,
+    buffer: Mutex<VecDeque<BufferedWrite<B>>>,
+    connected: AtomicBool,
+    max_buffered: usize,
+    next_attempt: Mutex<Instant>,
+    overflowed: AtomicBool,
+    reconnecting: AsyncMutex<()>,
+    retry_interval: Duration,
+}
+
+/// Backend reconnecting the wrapped backend after outages and buffering the
+/// writes made during them.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+pub struct ReconnectingBackend<B: Backend>(Arc<ReconnectingState<B>>);
+
+impl<B: Backend> ReconnectingBackend<B> {
+    /// Wrap a backend, buffering up to the given number of writes during an
+    /// outage and attempting to reconnect at most once per second.
+    pub fn new(backend: B, max_buffered: usize) -> Self {
+        Self::with_retry_interval(backend, max_buffered, Duration::from_secs(1))
+    }
+
+    /// Wrap a backend, buffering up to the given number of writes during an
+    /// outage and attempting to reconnect at most once per retry interval.
+    pub fn with_retry_interval(backend: B, max_buffered: usize, retry_interval: Duration) -> Self {
+        Self(Arc::new(ReconnectingState {
+            backend,
+            buffer: Mutex::default(),
+            connected: AtomicBool::new(true),
+            max_buffered,
+            next_attempt: Mutex::new(Instant::now()),
+            overflowed: AtomicBool::new(false),
+            reconnecting: AsyncMutex::new(()),
+            retry_interval,
+        }))
+    }
+
+    /// Return an immutable reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.0.backend
+    }
+
+    /// Return the number of writes buffered until the wrapped backend has
+    /// reconnected.
+    pub fn buffered(&self) -> usize {
+        lock(&self.0.buffer).len()
+    }
+
+    /// Return whether the wrapped backend is considered connected.
+    ///
+    /// The backend is considered disconnected from when an operation on it
+    /// fails along with its health check, until it has reconnected and the
+    /// buffered writes have been replayed.
+    pub fn is_connected(&self) -> bool {
+        self.0.connected.load(Ordering::Acquire)
+    }
+
+    /// Return whether buffered writes were dropped because the buffer was
+    /// full since the last call, resetting the flag.
+    ///
+    /// The cache missed the dropped writes, so it should be resynchronized,
+    /// such as by requesting the members of the affected guilds again.
+    pub fn take_overflow(&self) -> bool {
+        self.0.overflowed.swap(false, Ordering::AcqRel)
+    }
+
+    /// Mark the wrapped backend as disconnected if its health check fails
+    /// after an operation failed, returning whether it did.
+    async fn check_outage(&self) -> bool {
+        if self.0.backend.health().await.is_ok() {
+            return false;
+        }
+
+        let _buffer = lock(&self.0.buffer);
+
+        if self.0.connected.swap(false, Ordering::AcqRel) {
+            *lock(&self.0.next_attempt) = Instant::now() + self.0.retry_interval;
+        }
+
+        true
+    }
+
+    /// Reconnect the wrapped backend and replay the buffered writes if it's
+    /// disconnected and the retry interval has elapsed, or regardless of the
+    /// retry interval if forced, returning whether it's connected.
+    ///
+    /// Replayed writes are dequeued one at a time, so the writes that fail to
+    /// be replayed stay buffered.
+    async fn restore(&self, force: bool) -> Result<bool, B::Error> {
+        if self.is_connected() {
+            return Ok(true);
+        }
+
+        {
+            let now = Instant::now();
+            let mut next_attempt = lock(&self.0.next_attempt);
+
+            if !force && now < *next_attempt {
+                return Ok(false);
+            }
+
+            *next_attempt = now + self.0.retry_interval;
+        }
+
+        let _reconnecting = self.0.reconnecting.lock().await;
+
+        // Another operation may have reconnected the backend while this one
+        // was waiting.
+        if self.is_connected() {
+            return Ok(true);
+        }
+
+        self.0.backend.reconnect().await?;
+
+        loop {
+            let write = {
+                let mut buffer = lock(&self.0.buffer);
+
+                match buffer.pop_front() {
+                    Some(write) => write,
+                    None => {
+                        self.0.connected.store(true, Ordering::Release);
+
+                        return Ok(true);
+                    }
+                }
+            };
+
+            if let Err(source) = write(&self.0.backend).await {
+                let mut buffer = lock(&self.0.buffer);
+
+                // If the buffer overflowed in the meantime, the writes buffered
+                // since are newer than this one, which is dropped along with
+                // the others.
+                if !self.0.overflowed.load(Ordering::Acquire) {
+                    buffer.push_front(write);
+                }
+
+                return Err(source);
+            }
+        }
+    }
+
+    /// Buffer a write, returning it if the wrapped backend has reconnected in
+    /// the meantime.
+    ///
+    /// If the buffer is full, the buffered writes are dropped and the
+    /// overflow is recorded.
+    fn try_buffer(&self, write: BufferedWrite<B>) -> Option<BufferedWrite<B>> {
+        let mut buffer = lock(&self.0.buffer);
+
+        if self.is_connected() {
+            return Some(write);
+        }
+
+        if buffer.len() >= self.0.max_buffered {
+            buffer.clear();
+            self.0.overflowed.store(true, Ordering::Release);
+        }
+
+        if self.0.max_buffered > 0 {
+            buffer.push_back(write);
+        }
+
+        None
+    }
+
+    /// Make a write against the wrapped backend, or buffer it during an
+    /// outage.
+    async fn write(&self, write: BufferedWrite<B>) -> Result<(), B::Error> {
+        if let Ok(true) = self.restore(false).await {
+            let source = match write(&self.0.backend).await {
+                Ok(()) => return Ok(()),
+                Err(source) => source,
+            };
+
+            if !self.check_outage().await {
+                return Err(source);
+            }
+        }
+
+        match self.try_buffer(write) {
+            // The buffered writes were replayed when the backend reconnected,
+            // so the write can't overtake them.
+            Some(write) => write(&self.0.backend).await,
+            None => Ok(()),
+        }
+    }
+
+    fn repository<R>(&self, make: fn(&B) -> R) -> ReconnectingRepository<B, R> {
+        ReconnectingRepository {
+            backend: self.clone(),
+            make,
+            repo: make(self.inner()),
+        }
+    }
+}
+
+impl<B: Backend> Clone for ReconnectingBackend<B> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<B: Backend + Debug> Debug for ReconnectingBackend<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ReconnectingBackend")
+            .field("backend", &self.0.backend)
+            .field("buffered", &self.buffered())
+            .field("connected", &self.is_connected())
+            .field("max_buffered", &self.0.max_buffered)
+            .field("overflowed", &self.0.overflowed.load(Ordering::Acquire))
+            .field("retry_interval", &self.0.retry_interval)
+            .finish()
+    }
+}
+
+impl<B: Backend> Backend for ReconnectingBackend<B> {
+    type Error = B::Error;
+    type AttachmentRepository = ReconnectingRepository<B, B::AttachmentRepository>;
+    type CategoryChannelRepository = ReconnectingRepository<B, B::CategoryChannelRepository>;
+    type CurrentUserRepository = ReconnectingRepository<B, B::CurrentUserRepository>;
+    type EmojiRepository = ReconnectingRepository<B, B::EmojiRepository>;
+    type GroupRepository = ReconnectingRepository<B, B::GroupRepository>;
+    type GuildPreviewRepository = ReconnectingRepository<B, B::GuildPreviewRepository>;
+    type GuildRepository = ReconnectingRepository<B, B::GuildRepository>;
+    type MemberRepository = ReconnectingRepository<B, B::MemberRepository>;
+    type MessageRepository = ReconnectingRepository<B, B::MessageRepository>;
+    type PresenceRepository = ReconnectingRepository<B, B::PresenceRepository>;
+    type PrivateChannelRepository = ReconnectingRepository<B, B::PrivateChannelRepository>;
+    type RoleRepository = ReconnectingRepository<B, B::RoleRepository>;
+    type TextChannelRepository = ReconnectingRepository<B, B::TextChannelRepository>;
+    type UserRepository = ReconnectingRepository<B, B::UserRepository>;
+    type VoiceChannelRepository = ReconnectingRepository<B, B::VoiceChannelRepository>;
+    type VoiceRegionRepository = ReconnectingRepository<B, B::VoiceRegionRepository>;
+    type VoiceStateRepository = ReconnectingRepository<B, B::VoiceStateRepository>;
+
+    fn attachments(&self) -> Self::AttachmentRepository {
+        self.repository(B::attachments)
+    }
+
+    fn category_channels(&self) -> Self::CategoryChannelRepository {
+        self.repository(B::category_channels)
+    }
+
+    fn current_user(&self) -> Self::CurrentUserRepository {
+        self.repository(B::current_user)
+    }
+
+    fn emojis(&self) -> Self::EmojiRepository {
+        self.repository(B::emojis)
+    }
+
+    fn groups(&self) -> Self::GroupRepository {
+        self.repository(B::groups)
+    }
+
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        self.repository(B::guild_previews)
+    }
+
+    fn guilds(&self) -> Self::GuildRepository {
+        self.repository(B::guilds)
+    }
+
+    fn members(&self) -> Self::MemberRepository {
+        self.repository(B::members)
+    }
+
+    fn messages(&self) -> Self::MessageRepository {
+        self.repository(B::messages)
+    }
+
+    fn presences(&self) -> Self::PresenceRepository {
+        self.repository(B::presences)
+    }
+
+    fn private_channels(&self) -> Self::PrivateChannelRepository {
+        self.repository(B::private_channels)
+    }
+
+    fn roles(&self) -> Self::RoleRepository {
+        self.repository(B::roles)
+    }
+
+    fn text_channels(&self) -> Self::TextChannelRepository {
+        self.repository(B::text_channels)
+    }
+
+    fn users(&self) -> Self::UserRepository {
+        self.repository(B::users)
+    }
+
+    fn voice_channels(&self) -> Self::VoiceChannelRepository {
+        self.repository(B::voice_channels)
+    }
+
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        self.repository(B::voice_regions)
+    }
+
+    fn voice_states(&self) -> Self::VoiceStateRepository {
+        self.repository(B::voice_states)
+    }
+
+    fn connect(&self) -> ConnectFuture<'_, Self::Error> {
+        self.inner().connect()
+    }
+
+    fn reconnect(&self) -> ConnectFuture<'_, Self::Error> {
+        self.inner().reconnect()
+    }
+
+    fn flush(&self) -> FlushFuture<'_, Self::Error> {
+        Box::pin(async move {
+            self.restore(true).await?;
+
+            self.inner().flush().await
+        })
+    }
+
+    fn close(&self) -> CloseFuture<'_, Self::Error> {
+        self.inner().close()
+    }
+
+    fn health(&self) -> HealthFuture<'_, Self::Error> {
+        self.inner().health()
+    }
+
+    #[cfg(feature = "compression")]
+    fn compression_stats(&self) -> Option<CompressionStats> {
+        self.inner().compression_stats()
+    }
+
+    fn caches_guild(&self, guild_id: GuildId) -> bool {
+        self.inner().caches_guild(guild_id)
+    }
+
+    fn pins_guild(&self, guild_id: GuildId) -> bool {
+        self.inner().pins_guild(guild_id)
+    }
+
+    fn pins_user(&self, user_id: UserId) -> bool {
+        self.inner().pins_user(user_id)
+    }
+
+    fn verify_integrity(&self, repair: bool) -> VerifyIntegrityFuture<'_, Self::Error> {
+        self.inner().verify_integrity(repair)
+    }
+}
+
+/// Repository of a [`ReconnectingBackend`] wrapping a repository of the
+/// wrapped backend.
+///
+/// [`ReconnectingBackend`]: struct.ReconnectingBackend.html
+#[derive(Debug)]
+pub struct ReconnectingRepository<B: Backend, R> {
+    backend: ReconnectingBackend<B>,
+    make: fn(&B) -> R,
+    repo: R,
+}
+
+impl<B: Backend, R> ReconnectingRepository<B, R> {
+    /// Return an immutable reference to the wrapped repository.
+    pub fn inner(&self) -> &R {
+        &self.repo
+    }
+}
+
+impl<B: Backend, R: Send + Sync + 'static> ReconnectingRepository<B, R> {
+    /// Read from the wrapped repository, reconnecting the wrapped backend
+    /// first if it's disconnected.
+    fn read<'a, T: 'a>(
+        &'a self,
+        read: impl FnOnce(&'a R) -> OperationFuture<'a, T, B::Error> + Send + 'a,
+    ) -> OperationFuture<'a, T, B::Error> {
+        Box::pin(async move {
+            self.backend.restore(false).await?;
+
+            let result = read(&self.repo).await;
+
+            if result.is_err() {
+                self.backend.check_outage().await;
+            }
+
+            result
+        })
+    }
+
+    /// Write to a repository of the wrapped backend, or buffer the write
+    /// during an outage.
+    fn write<W>(&self, write: W) -> OperationFuture<'_, (), B::Error>
+    where
+        W: for<'b> Fn(&'b R) -> OperationFuture<'b, (), B::Error> + Send + Sync + 'static,
+    {
+        let make = self.make;
+        let write = Arc::new(write);
+        let write = buffered(move |backend| {
+            let write = Arc::clone(&write);
+
+            Box::pin(async move {
+                let repo = make(backend);
+
+                write(&repo).await
+            })
+        });
+
+        Box::pin(self.backend.write(write))
+    }
+}
+
+impl<B: Backend, R: Clone> Clone for ReconnectingRepository<B, R> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            make: self.make,
+            repo: self.repo.clone(),
+        }
+    }
+}
+
+impl<B, E, R> Repository<E, ReconnectingBackend<B>> for ReconnectingRepository<B, R>
+where
+    B: Backend,
+    E: Entity + Clone + 'static,
+    R: Repository<E, B> + Send + Sync + 'static,
+{
+    fn backend(&self) -> &ReconnectingBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.read(move |repo| repo.get(entity_id))
+    }
+
+    fn get_local(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.read(move |repo| repo.get_local(entity_id))
+    }
+
+    fn get_with_meta(
+        &self,
+        entity_id: E::Id,
+    ) -> GetEntityFuture<'_, (E, Option<CacheMeta>), B::Error> {
+        self.read(move |repo| repo.get_with_meta(entity_id))
+    }
+
+    fn list(&self) -> ListEntitiesFuture<'_, E, B::Error> {
+        self.read(R::list)
+    }
+
+    fn remove(&self, entity_id: E::Id) -> RemoveEntityFuture<'_, B::Error> {
+        self.write(move |repo| repo.remove(entity_id))
+    }
+
+    fn remove_bulk<T: Iterator<Item = E::Id>>(
+        &self,
+        entity_ids: T,
+    ) -> RemoveEntitiesFuture<'_, B::Error> {
+        let entity_ids = entity_ids.collect::<Vec<_>>();
+
+        self.write(move |repo| repo.remove_bulk(entity_ids.clone().into_iter()))
+    }
+
+    fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, B::Error> {
+        self.write(move |repo| repo.upsert(entity.clone()))
+    }
+
+    fn versioned_upsert(&self, entity: E, sequence: u64) -> UpsertEntityFuture<'_, B::Error> {
+        self.write(move |repo| repo.versioned_upsert(entity.clone(), sequence))
+    }
+
+    fn upsert_bulk<T: Iterator<Item = E> + Send>(
+        &self,
+        entities: T,
+    ) -> UpsertEntitiesFuture<'_, B::Error> {
+        let entities = entities.collect::<Vec<_>>();
+
+        self.write(move |repo| repo.upsert_bulk(entities.clone().into_iter()))
+    }
+}
+
+impl<B, R> SingleEntityRepository<CurrentUserEntity, ReconnectingBackend<B>>
+    for ReconnectingRepository<B, R>
+where
+    B: Backend,
+    R: SingleEntityRepository<CurrentUserEntity, B> + Send + Sync + 'static,
+{
+    fn backend(&self) -> &ReconnectingBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self) -> GetEntityFuture<'_, CurrentUserEntity, B::Error> {
+        self.read(R::get)
+    }
+
+    fn get_local(&self) -> GetEntityFuture<'_, CurrentUserEntity, B::Error> {
+        self.read(R::get_local)
+    }
+
+    fn remove(&self) -> RemoveEntityFuture<'_, B::Error> {
+        self.write(R::remove)
+    }
+
+    fn upsert(&self, entity: CurrentUserEntity) -> UpsertEntityFuture<'_, B::Error> {
+        self.write(move |repo| repo.upsert(entity.clone()))
+    }
+}
+
+impl<B: Backend> AttachmentRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::AttachmentRepository>
+{
+}
+
+impl<B: Backend> CategoryChannelRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::CategoryChannelRepository>
+{
+}
+
+impl<B: Backend> CurrentUserRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::CurrentUserRepository>
+{
+    fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        self.read(|repo| repo.guild_ids())
+    }
+}
+
+impl<B: Backend> EmojiRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::EmojiRepository>
+{
+}
+
+impl<B: Backend> GroupRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::GroupRepository>
+{
+}
+
+impl<B: Backend> GuildPreviewRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::GuildPreviewRepository>
+{
+}
+
+impl<B: Backend> GuildRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::GuildRepository>
+{
+    fn channel_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, ChannelId, B::Error> {
+        self.read(move |repo| repo.channel_ids(guild_id))
+    }
+
+    fn channels(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, GuildChannelEntity, B::Error> {
+        self.read(move |repo| repo.channels(guild_id))
+    }
+
+    fn emoji_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, EmojiId, B::Error> {
+        self.read(move |repo| repo.emoji_ids(guild_id))
+    }
+
+    fn member_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.read(move |repo| repo.member_ids(guild_id))
+    }
+
+    fn members(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        self.read(move |repo| repo.members(guild_id))
+    }
+
+    fn members_with_users(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, (MemberEntity, Option<UserEntity>), B::Error> {
+        self.read(move |repo| repo.members_with_users(guild_id))
+    }
+
+    fn presence_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.read(move |repo| repo.presence_ids(guild_id))
+    }
+
+    fn presences(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, PresenceEntity, B::Error> {
+        self.read(move |repo| repo.presences(guild_id))
+    }
+
+    fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, B::Error> {
+        self.read(move |repo| repo.role_ids(guild_id))
+    }
+
+    fn search_members(
+        &self,
+        guild_id: GuildId,
+        query: &str,
+        limit: usize,
+    ) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        let query = query.to_owned();
+
+        self.read(move |repo| repo.search_members(guild_id, &query, limit))
+    }
+
+    fn voice_state_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.read(move |repo| repo.voice_state_ids(guild_id))
+    }
+
+    fn voice_states(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceStateEntity, B::Error> {
+        self.read(move |repo| repo.voice_states(guild_id))
+    }
+}
+
+impl<B: Backend> MemberRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::MemberRepository>
+{
+    fn nick_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, NameChange, B::Error> {
+        self.read(move |repo| repo.nick_history(guild_id, user_id))
+    }
+}
+
+impl<B: Backend> MessageRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::MessageRepository>
+{
+    fn context(&self, message_id: MessageId) -> GetEntityFuture<'_, MessageContext, B::Error> {
+        self.read(move |repo| repo.context(message_id))
+    }
+}
+
+impl<B: Backend> PresenceRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::PresenceRepository>
+{
+}
+
+impl<B: Backend> PrivateChannelRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::PrivateChannelRepository>
+{
+}
+
+impl<B: Backend> RoleRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::RoleRepository>
+{
+}
+
+impl<B: Backend> TextChannelRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::TextChannelRepository>
+{
+}
+
+impl<B: Backend> UserRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::UserRepository>
+{
+    fn avatar_history(&self, user_id: UserId) -> ListEntitiesFuture<'_, AvatarChange, B::Error> {
+        self.read(move |repo| repo.avatar_history(user_id))
+    }
+
+    fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        self.read(move |repo| repo.guild_ids(user_id))
+    }
+}
+
+impl<B: Backend> VoiceChannelRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::VoiceChannelRepository>
+{
+}
+
+impl<B: Backend> VoiceRegionRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::VoiceRegionRepository>
+{
+}
+
+impl<B: Backend> VoiceStateRepository<ReconnectingBackend<B>>
+    for ReconnectingRepository<B, B::VoiceStateRepository>
+{
+    fn transition_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, VoiceStateChange, B::Error> {
+        self.read(move |repo| repo.transition_history(guild_id, user_id))
+    }
+}
@@ -0,0 +1,840 @@
+//! Backend recording the writes made to a cache so that they can be replayed.
+//!
+//! Bugs that leave a cache in an inconsistent state are hard to reproduce, as
+//! they often depend on the exact order of the events that a bot received. A
+//! [`RecordingBackend`] wraps a backend and records every upsert and removal
+//! made through it, including the full entity, either to a callback or as
+//! newline-delimited JSON to a writer such as a file. The recording can then
+//! be read back with [`read_records`] and applied to a fresh backend with
+//! [`replay`] to reproduce the state of the cache.
+//!
+//! Reads aren't recorded, as they don't change the cache.
+//!
+//! # Examples
+//!
+//! Record writes to a buffer and replay them against another backend:
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::sync::{Arc, Mutex};
+//! use twilight_cache::{
+//!     entity::guild::GuildEntity,
+//!     recording::{self, RecordingBackend},
+//!     Backend, Repository,
+//! };
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let records = Arc::new(Mutex::new(Vec::new()));
+//! let recorded = Arc::clone(&records);
+//! let backend = RecordingBackend::new(InMemoryBackend::new(), move |record| {
+//!     recorded.lock().unwrap().push(record.clone());
+//! });
+//!
+//! let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+//! backend.guilds().upsert(guild.clone()).await?;
+//!
+//! let fresh = InMemoryBackend::new();
+//! let records = records.lock().unwrap().clone();
+//! recording::replay(&fresh, records).await?;
+//!
+//! assert_eq!(Some(guild), fresh.guilds().get(GuildId(1)).await?);
+//! # Ok(()) }
+//! ```
+//!
+//! [`read_records`]: fn.read_records.html
+//! [`RecordingBackend`]: struct.RecordingBackend.html
+//! [`replay`]: fn.replay.html
+
+#[cfg(feature = "compression")]
+use super::compression::CompressionStats;
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{
+            AttachmentEntity, AttachmentRepository, CategoryChannelEntity,
+            CategoryChannelRepository, GroupEntity, GroupRepository, GuildChannelEntity,
+            MessageContext, MessageEntity, MessageRepository, PrivateChannelEntity,
+            PrivateChannelRepository, TextChannelEntity, TextChannelRepository, VoiceChannelEntity,
+            VoiceChannelRepository,
+        },
+        gateway::{PresenceEntity, PresenceRepository},
+        guild::{
+            EmojiEntity, EmojiRepository, GuildEntity, GuildPreviewEntity, GuildPreviewRepository,
+            GuildRepository, MemberEntity, MemberRepository, NameChange, RoleEntity,
+            RoleRepository,
+        },
+        user::{
+            AvatarChange, CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository,
+        },
+        voice::{
+            VoiceRegionEntity, VoiceRegionRepository, VoiceStateChange, VoiceStateEntity,
+            VoiceStateRepository,
+        },
+        Entity,
+    },
+    integrity::VerifyIntegrityFuture,
+    meta::CacheMeta,
+    repository::{
+        CloseFuture, ConnectFuture, FlushFuture, GetEntityFuture, HealthFuture, ListEntitiesFuture,
+        ListEntityIdsFuture, RemoveEntityFuture, Repository, SingleEntityRepository,
+        UpsertEntityFuture, UpsertOutcomeFuture,
+    },
+};
+use futures_util::future::{FutureExt, TryFutureExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    io::{Read, Write},
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+};
+use twilight_model::id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+type OperationFuture<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>;
+
+/// Write made to a cache.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "op")]
+pub enum Record {
+    /// An entity was removed.
+    Remove {
+        /// ID of the removed entity.
+        id: RecordedId,
+    },
+    /// An entity was upserted.
+    Upsert {
+        /// The upserted entity.
+        entity: Box<RecordedEntity>,
+        /// Sequence that the entity was upserted with, if it was a versioned
+        /// upsert.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sequence: Option<u64>,
+    },
+}
+
+impl Record {
+    /// Apply the write to a backend.
+    async fn apply<B: Backend>(self, backend: &B) -> Result<(), B::Error> {
+        match self {
+            Self::Remove { id } => id.remove(backend).await,
+            Self::Upsert { entity, sequence } => (*entity).upsert(backend, sequence).await,
+        }
+    }
+}
+
+/// Entity that can be recorded by a [`RecordingBackend`].
+///
+/// [`RecordingBackend`]: struct.RecordingBackend.html
+pub trait Recordable: Entity + Clone + 'static {
+    /// Wrap the entity for recording.
+    fn into_recorded(self) -> RecordedEntity;
+
+    /// Wrap an ID of the entity for recording.
+    fn recorded_id(entity_id: Self::Id) -> RecordedId;
+}
+
+macro_rules! recorded {
+    ($($variant:ident($entity:ty) => $repository:ident),* $(,)?) => {
+        /// Entity of any type recorded as being upserted.
+        #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+        #[serde(rename_all = "snake_case")]
+        pub enum RecordedEntity {
+            CurrentUser(CurrentUserEntity),
+            $($variant($entity),)*
+        }
+
+        impl RecordedEntity {
+            /// Return the ID of the entity.
+            pub fn id(&self) -> RecordedId {
+                match self {
+                    Self::CurrentUser(_) => RecordedId::CurrentUser,
+                    $(Self::$variant(entity) => RecordedId::$variant(entity.id()),)*
+                }
+            }
+
+            async fn upsert<B: Backend>(
+                self,
+                backend: &B,
+                sequence: Option<u64>,
+            ) -> Result<(), B::Error> {
+                match (self, sequence) {
+                    (Self::CurrentUser(entity), _) => backend.current_user().upsert(entity).await,
+                    $(
+                        (Self::$variant(entity), Some(sequence)) => {
+                            backend.$repository().versioned_upsert(entity, sequence).await
+                        }
+                        (Self::$variant(entity), None) => {
+                            backend.$repository().upsert(entity).await
+                        }
+                    )*
+                }
+            }
+        }
+
+        /// ID of an entity of any type recorded as being removed.
+        #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+        #[serde(rename_all = "snake_case")]
+        pub enum RecordedId {
+            /// The current user, which has no ID as there's only one.
+            CurrentUser,
+            $($variant(<$entity as Entity>::Id),)*
+        }
+
+        impl RecordedId {
+            async fn remove<B: Backend>(self, backend: &B) -> Result<(), B::Error> {
+                match self {
+                    Self::CurrentUser => backend.current_user().remove().await,
+                    $(Self::$variant(id) => backend.$repository().remove(id).await,)*
+                }
+            }
+        }
+
+        $(
+            impl Recordable for $entity {
+                fn into_recorded(self) -> RecordedEntity {
+                    RecordedEntity::$variant(self)
+                }
+
+                fn recorded_id(entity_id: Self::Id) -> RecordedId {
+                    RecordedId::$variant(entity_id)
+                }
+            }
+        )*
+    };
+}
+
+recorded! {
+    Attachment(AttachmentEntity) => attachments,
+    CategoryChannel(CategoryChannelEntity) => category_channels,
+    Emoji(EmojiEntity) => emojis,
+    Group(GroupEntity) => groups,
+    Guild(GuildEntity) => guilds,
+    GuildPreview(GuildPreviewEntity) => guild_previews,
+    Member(MemberEntity) => members,
+    Message(MessageEntity) => messages,
+    Presence(PresenceEntity) => presences,
+    PrivateChannel(PrivateChannelEntity) => private_channels,
+    Role(RoleEntity) => roles,
+    TextChannel(TextChannelEntity) => text_channels,
+    User(UserEntity) => users,
+    VoiceChannel(VoiceChannelEntity) => voice_channels,
+    VoiceRegion(VoiceRegionEntity) => voice_regions,
+    VoiceState(VoiceStateEntity) => voice_states,
+}
+
+/// Apply recorded writes to a backend in order.
+///
+/// # Errors
+///
+/// Returns the backend's error if applying a write fails. Writes after it
+/// aren't applied.
+pub async fn replay<B: Backend>(
+    backend: &B,
+    records: impl IntoIterator<Item = Record>,
+) -> Result<(), B::Error> {
+    for record in records {
+        record.apply(backend).await?;
+    }
+
+    Ok(())
+}
+
+/// Read records written as newline-delimited JSON by a
+/// [`RecordingBackend`].
+///
+/// Each item is an error if the record couldn't be read or parsed.
+///
+/// [`RecordingBackend`]: struct.RecordingBackend.html
+pub fn read_records(reader: impl Read) -> impl Iterator<Item = Result<Record, serde_json::Error>> {
+    serde_json::Deserializer::from_reader(reader).into_iter()
+}
+
+/// Callback called with a record and a function passing the write to the
+/// wrapped backend.
+type WriteAheadFn = dyn Fn(&Record, &mut dyn FnMut()) + Send + Sync;
+
+#[derive(Clone)]
+enum RecorderKind {
+    /// Called once the wrapped backend has completed the write.
+    After(Arc<dyn Fn(&Record) + Send + Sync>),
+    /// Called before the write is passed to the wrapped backend.
+    Ahead(Arc<WriteAheadFn>),
+}
+
+/// Callback called with each write made through a [`RecordingBackend`].
+///
+/// [`RecordingBackend`]: struct.RecordingBackend.html
+#[derive(Clone)]
+pub struct Recorder(RecorderKind);
+
+impl Recorder {
+    /// Create a recorder from a callback.
+    pub fn new(recorder: impl Fn(&Record) + Send + Sync + 'static) -> Self {
+        Self(RecorderKind::After(Arc::new(recorder)))
+    }
+
+    /// Create a recorder called with each write before it's passed to the
+    /// wrapped backend.
+    ///
+    /// The callback is called with the record and a function passing the
+    /// write to the wrapped backend, which it should call once the record has
+    /// been persisted. Backends that apply writes as they're passed to them,
+    /// such as the in-memory backend, have applied the write once the
+    /// function returns, so a callback holding a lock while calling it
+    /// records writes in the order they're applied.
+    ///
+    /// Writes are recorded even if the wrapped backend fails to complete
+    /// them. If the callback doesn't call the function, the write is passed
+    /// to the wrapped backend once the callback returns.
+    pub fn write_ahead(
+        recorder: impl Fn(&Record, &mut dyn FnMut()) + Send + Sync + 'static,
+    ) -> Self {
+        Self(RecorderKind::Ahead(Arc::new(recorder)))
+    }
+
+    /// Create a recorder writing each record as a line of JSON.
+    ///
+    /// Records that fail to be written are dropped. Use [`new`] to handle
+    /// errors.
+    ///
+    /// [`new`]: #method.new
+    pub fn writer(writer: impl Write + Send + 'static) -> Self {
+        let writer = Mutex::new(writer);
+
+        Self::new(move |record| {
+            let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+
+            if serde_json::to_writer(&mut *writer, record).is_ok() {
+                let _ = writer.write_all(b"\n");
+            }
+        })
+    }
+
+    /// Call the recorder.
+    pub fn call(&self, record: &Record) {
+        match &self.0 {
+            RecorderKind::After(recorder) => recorder(record),
+            RecorderKind::Ahead(recorder) => recorder(record, &mut || {}),
+        }
+    }
+}
+
+impl Debug for Recorder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple("Recorder").finish()
+    }
+}
+
+/// Backend recording the writes made through it before passing them to the
+/// wrapped backend.
+///
+/// Writes are recorded once the wrapped backend has completed them, so writes
+/// that fail aren't recorded, unless the backend is created with a
+/// [write-ahead recorder].
+///
+/// [write-ahead recorder]: struct.Recorder.html#method.write_ahead
+#[derive(Clone, Debug)]
+pub struct RecordingBackend<B> {
+    backend: B,
+    recorder: Recorder,
+}
+
+impl<B: Backend + Clone> RecordingBackend<B> {
+    /// Wrap a backend, calling the callback with each write.
+    pub fn new(backend: B, recorder: impl Fn(&Record) + Send + Sync + 'static) -> Self {
+        Self::with_recorder(backend, Recorder::new(recorder))
+    }
+
+    /// Wrap a backend, writing each write to the writer as a line of JSON.
+    ///
+    /// Refer to [`Recorder::writer`] for more information.
+    ///
+    /// [`Recorder::writer`]: struct.Recorder.html#method.writer
+    pub fn to_writer(backend: B, writer: impl Write + Send + 'static) -> Self {
+        Self::with_recorder(backend, Recorder::writer(writer))
+    }
+
+    /// Wrap a backend, calling the recorder with each write.
+    pub fn with_recorder(backend: B, recorder: Recorder) -> Self {
+        Self { backend, recorder }
+    }
+
+    /// Return an immutable reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.backend
+    }
+
+    fn repository<R>(&self, f: impl FnOnce(&B) -> R) -> RecordingRepository<B, R> {
+        RecordingRepository {
+            backend: self.clone(),
+            repo: f(&self.backend),
+        }
+    }
+}
+
+impl<B: Backend + Clone> Backend for RecordingBackend<B> {
+    type Error = B::Error;
+    type AttachmentRepository = RecordingRepository<B, B::AttachmentRepository>;
+    type CategoryChannelRepository = RecordingRepository<B, B::CategoryChannelRepository>;
+    type CurrentUserRepository = RecordingRepository<B, B::CurrentUserRepository>;
+    type EmojiRepository = RecordingRepository<B, B::EmojiRepository>;
+    type GroupRepository = RecordingRepository<B, B::GroupRepository>;
+    type GuildPreviewRepository = RecordingRepository<B, B::GuildPreviewRepository>;
+    type GuildRepository = RecordingRepository<B, B::GuildRepository>;
+    type MemberRepository = RecordingRepository<B, B::MemberRepository>;
+    type MessageRepository = RecordingRepository<B, B::MessageRepository>;
+    type PresenceRepository = RecordingRepository<B, B::PresenceRepository>;
+    type PrivateChannelRepository = RecordingRepository<B, B::PrivateChannelRepository>;
+    type RoleRepository = RecordingRepository<B, B::RoleRepository>;
+    type TextChannelRepository = RecordingRepository<B, B::TextChannelRepository>;
+    type UserRepository = RecordingRepository<B, B::UserRepository>;
+    type VoiceChannelRepository = RecordingRepository<B, B::VoiceChannelRepository>;
+    type VoiceRegionRepository = RecordingRepository<B, B::VoiceRegionRepository>;
+    type VoiceStateRepository = RecordingRepository<B, B::VoiceStateRepository>;
+
+    fn attachments(&self) -> Self::AttachmentRepository {
+        self.repository(B::attachments)
+    }
+
+    fn category_channels(&self) -> Self::CategoryChannelRepository {
+        self.repository(B::category_channels)
+    }
+
+    fn current_user(&self) -> Self::CurrentUserRepository {
+        self.repository(B::current_user)
+    }
+
+    fn emojis(&self) -> Self::EmojiRepository {
+        self.repository(B::emojis)
+    }
+
+    fn groups(&self) -> Self::GroupRepository {
+        self.repository(B::groups)
+    }
+
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        self.repository(B::guild_previews)
+    }
+
+    fn guilds(&self) -> Self::GuildRepository {
+        self.repository(B::guilds)
+    }
+
+    fn members(&self) -> Self::MemberRepository {
+        self.repository(B::members)
+    }
+
+    fn messages(&self) -> Self::MessageRepository {
+        self.repository(B::messages)
+    }
+
+    fn presences(&self) -> Self::PresenceRepository {
+        self.repository(B::presences)
+    }
+
+    fn private_channels(&self) -> Self::PrivateChannelRepository {
+        self.repository(B::private_channels)
+    }
+
+    fn roles(&self) -> Self::RoleRepository {
+        self.repository(B::roles)
+    }
+
+    fn text_channels(&self) -> Self::TextChannelRepository {
+        self.repository(B::text_channels)
+    }
+
+    fn users(&self) -> Self::UserRepository {
+        self.repository(B::users)
+    }
+
+    fn voice_channels(&self) -> Self::VoiceChannelRepository {
+        self.repository(B::voice_channels)
+    }
+
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        self.repository(B::voice_regions)
+    }
+
+    fn voice_states(&self) -> Self::VoiceStateRepository {
+        self.repository(B::voice_states)
+    }
+
+    fn connect(&self) -> ConnectFuture<'_, Self::Error> {
+        self.backend.connect()
+    }
+
+    fn reconnect(&self) -> ConnectFuture<'_, Self::Error> {
+        self.backend.reconnect()
+    }
+
+    fn flush(&self) -> FlushFuture<'_, Self::Error> {
+        self.backend.flush()
+    }
+
+    fn close(&self) -> CloseFuture<'_, Self::Error> {
+        self.backend.close()
+    }
+
+    fn health(&self) -> HealthFuture<'_, Self::Error> {
+        self.backend.health()
+    }
+
+    #[cfg(feature = "compression")]
+    fn compression_stats(&self) -> Option<CompressionStats> {
+        self.backend.compression_stats()
+    }
+
+    fn caches_guild(&self, guild_id: GuildId) -> bool {
+        self.backend.caches_guild(guild_id)
+    }
+
+    fn pins_guild(&self, guild_id: GuildId) -> bool {
+        self.backend.pins_guild(guild_id)
+    }
+
+    fn pins_user(&self, user_id: UserId) -> bool {
+        self.backend.pins_user(user_id)
+    }
+
+    fn verify_integrity(&self, repair: bool) -> VerifyIntegrityFuture<'_, Self::Error> {
+        self.backend.verify_integrity(repair)
+    }
+}
+
+/// Repository of a [`RecordingBackend`] wrapping a repository of the
+/// wrapped backend.
+///
+/// [`RecordingBackend`]: struct.RecordingBackend.html
+#[derive(Clone, Debug)]
+pub struct RecordingRepository<B, R> {
+    backend: RecordingBackend<B>,
+    repo: R,
+}
+
+impl<B, R> RecordingRepository<B, R> {
+    /// Return an immutable reference to the wrapped repository.
+    pub fn inner(&self) -> &R {
+        &self.repo
+    }
+
+    /// Record a write once the future completing it succeeds, or before
+    /// starting it if the recorder is a write-ahead recorder.
+    fn record<'a, T: 'a, E: 'a>(
+        &self,
+        write: impl FnOnce() -> OperationFuture<'a, T, E>,
+        record: Record,
+    ) -> OperationFuture<'a, T, E> {
+        match &self.backend.recorder.0 {
+            RecorderKind::After(recorder) => {
+                let recorder = Arc::clone(recorder);
+
+                write()
+                    .map_ok(move |value| {
+                        recorder(&record);
+
+                        value
+                    })
+                    .boxed()
+            }
+            RecorderKind::Ahead(recorder) => {
+                let mut write = Some(write);
+                let mut future = None;
+
+                recorder(&record, &mut || {
+                    if let Some(write) = write.take() {
+                        future = Some(write());
+                    }
+                });
+
+                match (future, write) {
+                    (Some(future), _) => future,
+                    (None, Some(write)) => write(),
+                    (None, None) => unreachable!("started writes return a future"),
+                }
+            }
+        }
+    }
+}
+
+impl<B, E, R> Repository<E, RecordingBackend<B>> for RecordingRepository<B, R>
+where
+    B: Backend + Clone,
+    E: Recordable,
+    R: Repository<E, B> + Send + Sync,
+{
+    fn backend(&self) -> &RecordingBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.repo.get(entity_id)
+    }
+
+    fn get_local(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.repo.get_local(entity_id)
+    }
+
+    fn get_with_meta(
+        &self,
+        entity_id: E::Id,
+    ) -> GetEntityFuture<'_, (E, Option<CacheMeta>), B::Error> {
+        self.repo.get_with_meta(entity_id)
+    }
+
+    fn list(&self) -> ListEntitiesFuture<'_, E, B::Error> {
+        self.repo.list()
+    }
+
+    fn remove(&self, entity_id: E::Id) -> RemoveEntityFuture<'_, B::Error> {
+        let record = Record::Remove {
+            id: E::recorded_id(entity_id),
+        };
+
+        self.record(move || self.repo.remove(entity_id), record)
+    }
+
+    fn take(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        let record = Record::Remove {
+            id: E::recorded_id(entity_id),
+        };
+
+        self.record(move || self.repo.take(entity_id), record)
+    }
+
+    fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, B::Error> {
+        let record = Record::Upsert {
+            entity: Box::new(entity.clone().into_recorded()),
+            sequence: None,
+        };
+
+        self.record(move || self.repo.upsert(entity), record)
+    }
+
+    fn upsert_with_outcome(&self, entity: E) -> UpsertOutcomeFuture<'_, B::Error> {
+        let record = Record::Upsert {
+            entity: Box::new(entity.clone().into_recorded()),
+            sequence: None,
+        };
+
+        self.record(move || self.repo.upsert_with_outcome(entity), record)
+    }
+
+    fn versioned_upsert(&self, entity: E, sequence: u64) -> UpsertEntityFuture<'_, B::Error> {
+        let record = Record::Upsert {
+            entity: Box::new(entity.clone().into_recorded()),
+            sequence: Some(sequence),
+        };
+
+        self.record(move || self.repo.versioned_upsert(entity, sequence), record)
+    }
+}
+
+impl<B, R> SingleEntityRepository<CurrentUserEntity, RecordingBackend<B>>
+    for RecordingRepository<B, R>
+where
+    B: Backend + Clone,
+    R: SingleEntityRepository<CurrentUserEntity, B> + Send + Sync,
+{
+    fn backend(&self) -> &RecordingBackend<B> {
+        &self.backend
+    }
+
+    fn get(&self) -> GetEntityFuture<'_, CurrentUserEntity, B::Error> {
+        self.repo.get()
+    }
+
+    fn get_local(&self) -> GetEntityFuture<'_, CurrentUserEntity, B::Error> {
+        self.repo.get_local()
+    }
+
+    fn remove(&self) -> RemoveEntityFuture<'_, B::Error> {
+        let record = Record::Remove {
+            id: RecordedId::CurrentUser,
+        };
+
+        self.record(move || self.repo.remove(), record)
+    }
+
+    fn upsert(&self, entity: CurrentUserEntity) -> UpsertEntityFuture<'_, B::Error> {
+        let record = Record::Upsert {
+            entity: Box::new(RecordedEntity::CurrentUser(entity.clone())),
+            sequence: None,
+        };
+
+        self.record(move || self.repo.upsert(entity), record)
+    }
+}
+
+impl<B: Backend + Clone> AttachmentRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::AttachmentRepository>
+{
+}
+
+impl<B: Backend + Clone> CategoryChannelRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::CategoryChannelRepository>
+{
+}
+
+impl<B: Backend + Clone> CurrentUserRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::CurrentUserRepository>
+{
+    fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        self.repo.guild_ids()
+    }
+}
+
+impl<B: Backend + Clone> EmojiRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::EmojiRepository>
+{
+}
+
+impl<B: Backend + Clone> GroupRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::GroupRepository>
+{
+}
+
+impl<B: Backend + Clone> GuildPreviewRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::GuildPreviewRepository>
+{
+}
+
+impl<B: Backend + Clone> GuildRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::GuildRepository>
+{
+    fn channel_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, ChannelId, B::Error> {
+        self.repo.channel_ids(guild_id)
+    }
+
+    fn channels(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, GuildChannelEntity, B::Error> {
+        self.repo.channels(guild_id)
+    }
+
+    fn emoji_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, EmojiId, B::Error> {
+        self.repo.emoji_ids(guild_id)
+    }
+
+    fn member_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.repo.member_ids(guild_id)
+    }
+
+    fn members(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        self.repo.members(guild_id)
+    }
+
+    fn members_with_users(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, (MemberEntity, Option<UserEntity>), B::Error> {
+        self.repo.members_with_users(guild_id)
+    }
+
+    fn presence_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.repo.presence_ids(guild_id)
+    }
+
+    fn presences(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, PresenceEntity, B::Error> {
+        self.repo.presences(guild_id)
+    }
+
+    fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, B::Error> {
+        self.repo.role_ids(guild_id)
+    }
+
+    fn search_members(
+        &self,
+        guild_id: GuildId,
+        query: &str,
+        limit: usize,
+    ) -> ListEntitiesFuture<'_, MemberEntity, B::Error> {
+        self.repo.search_members(guild_id, query, limit)
+    }
+
+    fn voice_state_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, B::Error> {
+        self.repo.voice_state_ids(guild_id)
+    }
+
+    fn voice_states(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceStateEntity, B::Error> {
+        self.repo.voice_states(guild_id)
+    }
+}
+
+impl<B: Backend + Clone> MemberRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::MemberRepository>
+{
+    fn nick_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, NameChange, B::Error> {
+        self.repo.nick_history(guild_id, user_id)
+    }
+}
+
+impl<B: Backend + Clone> MessageRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::MessageRepository>
+{
+    fn context(&self, message_id: MessageId) -> GetEntityFuture<'_, MessageContext, B::Error> {
+        self.repo.context(message_id)
+    }
+}
+
+impl<B: Backend + Clone> PresenceRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::PresenceRepository>
+{
+}
+
+impl<B: Backend + Clone> PrivateChannelRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::PrivateChannelRepository>
+{
+}
+
+impl<B: Backend + Clone> RoleRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::RoleRepository>
+{
+}
+
+impl<B: Backend + Clone> TextChannelRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::TextChannelRepository>
+{
+}
+
+impl<B: Backend + Clone> UserRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::UserRepository>
+{
+    fn avatar_history(&self, user_id: UserId) -> ListEntitiesFuture<'_, AvatarChange, B::Error> {
+        self.repo.avatar_history(user_id)
+    }
+
+    fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, B::Error> {
+        self.repo.guild_ids(user_id)
+    }
+}
+
+impl<B: Backend + Clone> VoiceChannelRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::VoiceChannelRepository>
+{
+}
+
+impl<B: Backend + Clone> VoiceRegionRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::VoiceRegionRepository>
+{
+}
+
+impl<B: Backend + Clone> VoiceStateRepository<RecordingBackend<B>>
+    for RecordingRepository<B, B::VoiceStateRepository>
+{
+    fn transition_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, VoiceStateChange, B::Error> {
+        self.repo.transition_history(guild_id, user_id)
+    }
+}
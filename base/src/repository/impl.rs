@@ -1,24 +1,120 @@
 use super::{
-    super::{backend::Backend, entity::Entity},
+    super::{backend::Backend, entity::Entity, meta::CacheMeta, projection::Projection},
     GetEntityFuture, ListEntitiesFuture, RemoveEntitiesFuture, RemoveEntityFuture,
-    UpsertEntitiesFuture, UpsertEntityFuture,
+    UpsertEntitiesFuture, UpsertEntityFuture, UpsertOutcome, UpsertOutcomeFuture,
 };
 use futures_util::future::{self, FutureExt, TryFutureExt};
 
 pub trait Repository<E: Entity, B: Backend> {
     /// Retrieve an immutable reference to the backend that the repository is
     /// tied to.
-    fn backend(&self) -> B;
+    fn backend(&self) -> &B;
 
     /// Get an entity by its ID in the cache.
+    ///
+    /// Backends that are able to fetch missing entities from a remote origin,
+    /// such as Discord's HTTP API or another tier of the cache, may do so and
+    /// populate the cache with the result. Use [`get_local`] for lookups that
+    /// must not be slow.
+    ///
+    /// [`get_local`]: #method.get_local
     fn get(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error>;
 
+    /// Get an entity by its ID only if it's already cached, without ever
+    /// fetching it from a remote origin.
+    ///
+    /// **Backend implementations**: a default implementation is provided that
+    /// calls [`get`], which is correct for backends that never fetch from a
+    /// remote origin. Backends that do must implement this manually.
+    ///
+    /// [`get`]: #tymethod.get
+    fn get_local(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.get(entity_id)
+    }
+
+    /// Get a projection of an entity by its ID in the cache, such as only
+    /// the name of a guild.
+    ///
+    /// Refer to the [`projection`] module for more information.
+    ///
+    /// **Backend implementations**: a default implementation is provided that
+    /// calls [`get`] and makes the projection from the full entity. Backends
+    /// that can fetch individual fields may implement this manually to only
+    /// fetch the fields named by [`Projection::FIELDS`].
+    ///
+    /// [`get`]: #tymethod.get
+    /// [`projection`]: ../projection/index.html
+    /// [`Projection::FIELDS`]: ../projection/trait.Projection.html#associatedconstant.FIELDS
+    fn get_projected<P: Projection<E> + 'static>(
+        &self,
+        entity_id: E::Id,
+    ) -> GetEntityFuture<'_, P, B::Error>
+    where
+        E: 'static,
+    {
+        self.get(entity_id)
+            .map_ok(|entity| entity.map(P::project))
+            .boxed()
+    }
+
+    /// Get an entity by its ID in the cache along with metadata about when it
+    /// was cached, if the backend tracks it.
+    ///
+    /// Refer to the [`meta`] module for more information.
+    ///
+    /// **Backend implementations**: a default implementation is provided that
+    /// calls [`get`] and returns no metadata. Backends that track when
+    /// entities were cached should implement this manually.
+    ///
+    /// [`get`]: #tymethod.get
+    /// [`meta`]: ../meta/index.html
+    fn get_with_meta(
+        &self,
+        entity_id: E::Id,
+    ) -> GetEntityFuture<'_, (E, Option<CacheMeta>), B::Error>
+    where
+        E: 'static,
+    {
+        self.get(entity_id)
+            .map_ok(|entity| entity.map(|entity| (entity, None)))
+            .boxed()
+    }
+
     /// Stream a list of records of the entity.
     fn list(&self) -> ListEntitiesFuture<'_, E, B::Error>;
 
     /// Remove an entity by its ID from the cache.
     fn remove(&self, entity_id: E::Id) -> RemoveEntityFuture<'_, B::Error>;
 
+    /// Remove an entity by its ID from the cache, returning the entity as it
+    /// was cached.
+    ///
+    /// This lets callers act on the final state of an entity, such as logging
+    /// a member that left, without racing a separate [`get`] against the
+    /// removal.
+    ///
+    /// **Backend implementations**: a default implementation is provided that
+    /// calls [`get_local`] before calling [`remove`], so a write made between
+    /// the two calls isn't reflected in the returned entity. Backends that
+    /// are able to remove an entity and return it at once should implement
+    /// this manually.
+    ///
+    /// [`get`]: #tymethod.get
+    /// [`get_local`]: #method.get_local
+    /// [`remove`]: #tymethod.remove
+    fn take(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error>
+    where
+        Self: Sync,
+        E: 'static,
+    {
+        Box::pin(async move {
+            let entity = self.get_local(entity_id).await?;
+            self.remove(entity_id).await?;
+
+            Ok(entity)
+        })
+    }
+
     /// Bulk remove multiple entities from the cache.
     ///
     /// **B implementations**: a default implementation is provided that
@@ -39,6 +135,59 @@ pub trait Repository<E: Entity, B: Backend> {
     /// Upsert an entity into the cache.
     fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, B::Error>;
 
+    /// Upsert an entity into the cache, returning whether it was newly
+    /// created or replaced a cached version of itself.
+    ///
+    /// This lets event hooks and metrics tell inserts apart from updates
+    /// without an extra [`get`].
+    ///
+    /// **Backend implementations**: a default implementation is provided that
+    /// calls [`get_local`] before calling [`upsert`]. Backends that are able
+    /// to tell whether an upsert replaced an entity, or that the entity wasn't
+    /// stored at all, should implement this manually to avoid the extra
+    /// lookup.
+    ///
+    /// [`get`]: #tymethod.get
+    /// [`get_local`]: #method.get_local
+    /// [`upsert`]: #tymethod.upsert
+    fn upsert_with_outcome(&self, entity: E) -> UpsertOutcomeFuture<'_, B::Error>
+    where
+        Self: Sync,
+        E: 'static,
+    {
+        Box::pin(async move {
+            let existing = self.get_local(entity.id()).await?;
+            self.upsert(entity).await?;
+
+            Ok(if existing.is_some() {
+                UpsertOutcome::Updated
+            } else {
+                UpsertOutcome::Created
+            })
+        })
+    }
+
+    /// Upsert an entity into the cache unless a newer version of it has
+    /// already been upserted.
+    ///
+    /// The sequence is a number that increases with each event, such as the
+    /// sequence number of the gateway event that contained the entity. This
+    /// lets backends reject stale writes, such as when an older update is
+    /// applied after a newer one while events are being replayed or
+    /// processed concurrently.
+    ///
+    /// **Backend implementations**: a default implementation is provided that
+    /// ignores the sequence and calls [`upsert`]. Backends that track the
+    /// sequences of entities should skip upserting an entity if the sequence
+    /// is lower than the sequence it was last upserted with.
+    ///
+    /// [`upsert`]: #tymethod.upsert
+    fn versioned_upsert(&self, entity: E, sequence: u64) -> UpsertEntityFuture<'_, B::Error> {
+        let _ = sequence;
+
+        self.upsert(entity)
+    }
+
     /// Bulk upsert multiple entities in the cache.
     ///
     /// **B implementations**: a default implementation is provided that
@@ -58,14 +207,54 @@ pub trait Repository<E: Entity, B: Backend> {
 pub trait SingleEntityRepository<E: Entity, B: Backend> {
     /// Retrieve an immutable reference to the backend that the repository is
     /// tied to.
-    fn backend(&self) -> B;
+    fn backend(&self) -> &B;
 
     /// Get the entity in the cache.
+    ///
+    /// Backends that are able to fetch a missing entity from a remote origin
+    /// may do so and populate the cache with the result. Use [`get_local`]
+    /// for lookups that must not be slow.
+    ///
+    /// [`get_local`]: #method.get_local
     fn get(&self) -> GetEntityFuture<'_, E, B::Error>;
 
+    /// Get the entity only if it's already cached, without ever fetching it
+    /// from a remote origin.
+    ///
+    /// **Backend implementations**: a default implementation is provided that
+    /// calls [`get`], which is correct for backends that never fetch from a
+    /// remote origin. Backends that do must implement this manually.
+    ///
+    /// [`get`]: #tymethod.get
+    fn get_local(&self) -> GetEntityFuture<'_, E, B::Error> {
+        self.get()
+    }
+
     /// Remove the entity from the cache.
     fn remove(&self) -> RemoveEntityFuture<'_, B::Error>;
 
+    /// Remove the entity from the cache, returning it as it was cached.
+    ///
+    /// **Backend implementations**: a default implementation is provided that
+    /// calls [`get_local`] before calling [`remove`]. Backends that are able
+    /// to remove the entity and return it at once should implement this
+    /// manually.
+    ///
+    /// [`get_local`]: #method.get_local
+    /// [`remove`]: #tymethod.remove
+    fn take(&self) -> GetEntityFuture<'_, E, B::Error>
+    where
+        Self: Sync,
+        E: 'static,
+    {
+        Box::pin(async move {
+            let entity = self.get_local().await?;
+            self.remove().await?;
+
+            Ok(entity)
+        })
+    }
+
     /// Upsert the entity into the cache.
     fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, B::Error>;
 }
@@ -1,16 +1,25 @@
 mod r#impl;
+#[cfg(feature = "discord")]
 mod noop;
+mod refresh;
 
 pub use self::{
-    noop::NoopRepository,
     r#impl::{Repository, SingleEntityRepository},
+    refresh::RefreshingRepository,
 };
 
+#[cfg(feature = "discord")]
+pub use self::noop::NoopRepository;
+
 use futures_util::stream::Stream;
 use std::{future::Future, pin::Pin};
 
+pub type CloseFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'a>>;
+pub type ConnectFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'a>>;
+pub type FlushFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'a>>;
 pub type GetEntityFuture<'a, T, E> =
     Pin<Box<dyn Future<Output = Result<Option<T>, E>> + Send + 'a>>;
+pub type HealthFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'a>>;
 pub type ListEntitiesFuture<'a, T, E> =
     Pin<Box<dyn Future<Output = Result<ListEntitiesStream<'a, T, E>, E>> + Send + 'a>>;
 pub type ListEntitiesStream<'a, T, E> = Pin<Box<dyn Stream<Item = Result<T, E>> + Send + 'a>>;
@@ -20,4 +29,38 @@ pub type ListEntityIdsStream<'a, T, E> = Pin<Box<dyn Stream<Item = Result<T, E>>
 pub type RemoveEntityFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'a>>;
 pub type RemoveEntitiesFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'a>>;
 pub type UpsertEntityFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'a>>;
+pub type UpsertOutcomeFuture<'a, E> =
+    Pin<Box<dyn Future<Output = Result<UpsertOutcome, E>> + Send + 'a>>;
 pub type UpsertEntitiesFuture<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + Send + 'a>>;
+
+/// Result of upserting an entity.
+///
+/// Returned by [`Repository::upsert_with_outcome`].
+///
+/// [`Repository::upsert_with_outcome`]: trait.Repository.html#method.upsert_with_outcome
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum UpsertOutcome {
+    /// The entity wasn't cached before and was inserted.
+    Created,
+    /// The entity replaced a cached version of itself.
+    Updated,
+    /// The entity wasn't stored, such as because its type isn't cached or the
+    /// backend refused to admit it.
+    ///
+    /// Only backends that are able to tell report this; others report the
+    /// entity as created or updated.
+    Skipped,
+}
+
+impl UpsertOutcome {
+    /// Whether the entity was newly inserted.
+    pub fn is_created(self) -> bool {
+        self == Self::Created
+    }
+
+    /// Whether the entity replaced a cached version of itself.
+    pub fn is_updated(self) -> bool {
+        self == Self::Updated
+    }
+}
@@ -16,11 +16,14 @@ use super::{
             guild::{
                 emoji::{EmojiEntity, EmojiRepository},
                 member::{MemberEntity, MemberRepository},
+                preview::GuildPreviewRepository,
                 role::{RoleEntity, RoleRepository},
                 GuildEntity, GuildRepository,
             },
             user::{UserEntity, UserRepository},
-            voice::{VoiceStateEntity, VoiceStateRepository},
+            voice::{
+                VoiceRegionEntity, VoiceRegionRepository, VoiceStateEntity, VoiceStateRepository,
+            },
             Entity,
         },
     },
@@ -59,8 +62,8 @@ impl<B: Backend + Clone> NoopRepository<B> {
 
 impl<B: Backend + Clone, E: Entity + 'static> Repository<E, B> for NoopRepository<B> {
     /// Returns an immutable reference to the backend.
-    fn backend(&self) -> B {
-        self.0.clone()
+    fn backend(&self) -> &B {
+        &self.0
     }
 
     /// Always returns no entity.
@@ -137,6 +140,16 @@ impl<B: Backend + Clone + Send> GroupRepository<B> for NoopRepository<B> {
     }
 }
 
+impl<B: Backend + Clone + Send> GuildPreviewRepository<B> for NoopRepository<B> {
+    fn emojis(&self, _: GuildId) -> ListEntitiesFuture<'_, EmojiEntity, B::Error> {
+        future::ok(stream::empty().boxed()).boxed()
+    }
+
+    fn guild(&self, _: GuildId) -> GetEntityFuture<'_, GuildEntity, B::Error> {
+        future::ok(None).boxed()
+    }
+}
+
 impl<B: Backend + Clone + Send> GuildRepository<B> for NoopRepository<B> {
     fn afk_channel(&self, _: GuildId) -> GetEntityFuture<'_, VoiceChannelEntity, B::Error> {
         future::ok(None).boxed()
@@ -318,6 +331,20 @@ impl<B: Backend + Clone + Send> VoiceChannelRepository<B> for NoopRepository<B>
     fn parent(&self, _: ChannelId) -> GetEntityFuture<'_, CategoryChannelEntity, B::Error> {
         future::ok(None).boxed()
     }
+
+    fn rtc_region(&self, _: ChannelId) -> GetEntityFuture<'_, VoiceRegionEntity, B::Error> {
+        future::ok(None).boxed()
+    }
+}
+
+impl<B: Backend + Clone + Send> VoiceRegionRepository<B> for NoopRepository<B> {
+    fn by_name(&self, _: &str) -> GetEntityFuture<'_, VoiceRegionEntity, B::Error> {
+        future::ok(None).boxed()
+    }
+
+    fn optimal(&self) -> GetEntityFuture<'_, VoiceRegionEntity, B::Error> {
+        future::ok(None).boxed()
+    }
 }
 
 impl<B: Backend + Clone + Send> VoiceStateRepository<B> for NoopRepository<B> {
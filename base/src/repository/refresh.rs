@@ -0,0 +1,245 @@
+use super::{
+    super::{backend::Backend, entity::Entity, meta::CacheMeta},
+    GetEntityFuture, ListEntitiesFuture, RemoveEntityFuture, Repository, UpsertEntityFuture,
+    UpsertOutcomeFuture,
+};
+use futures_util::future::{FutureExt, TryFutureExt};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    hash::Hash,
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Minimum number of tracked entities before the tracked times are pruned.
+const MIN_PRUNE_LEN: usize = 1024;
+
+/// Times at which entities were last known to be fresh.
+///
+/// Times older than the staleness carry no information, since entities
+/// without a time are considered stale anyway, so they're pruned whenever the
+/// number of tracked entities doubles.
+struct FreshTimes<Id> {
+    at: HashMap<Id, Instant>,
+    prune_len: usize,
+}
+
+impl<Id: Eq + Hash> FreshTimes<Id> {
+    fn new() -> Self {
+        Self {
+            at: HashMap::new(),
+            prune_len: MIN_PRUNE_LEN,
+        }
+    }
+
+    /// Return whether an entity was fresh less than the staleness ago.
+    fn is_fresh(&self, entity_id: &Id, now: Instant, staleness: Duration) -> bool {
+        self.at
+            .get(entity_id)
+            .is_some_and(|at| now.duration_since(*at) < staleness)
+    }
+
+    /// Record that an entity is fresh.
+    fn touch(&mut self, entity_id: Id, now: Instant, staleness: Duration) {
+        self.at.insert(entity_id, now);
+
+        if self.at.len() >= self.prune_len {
+            self.at.retain(|_, at| now.duration_since(*at) < staleness);
+            self.prune_len = (self.at.len() * 2).max(MIN_PRUNE_LEN);
+        }
+    }
+}
+
+/// Repository layer serving stale entities while they're refreshed in the
+/// background.
+///
+/// When an entity older than the configured staleness is retrieved, the stale
+/// entity is returned immediately and the user-supplied refresh callback is
+/// called with its ID. The callback is expected to start fetching the entity,
+/// such as by spawning a task that requests it over HTTP, and upsert the fresh
+/// entity into the repository once done. The entity's age is reset when the
+/// callback is called so that a refresh isn't requested again while one is
+/// in flight.
+///
+/// The age of an entity is how long ago it was last upserted through the
+/// layer. Entities upserted elsewhere, such as by a persistent backend in a
+/// previous run, are aged by when they were last updated according to their
+/// [`CacheMeta`], and are considered stale if the backend doesn't track
+/// metadata.
+///
+/// # Examples
+///
+/// Refresh guilds that were cached more than an hour ago:
+///
+/// ```
+/// use std::time::Duration;
+/// use twilight_cache::{entity::guild::GuildEntity, repository::RefreshingRepository, Backend};
+/// use twilight_cache_inmemory::InMemoryBackend;
+///
+/// let backend = InMemoryBackend::new();
+/// let guilds: RefreshingRepository<GuildEntity, _> = RefreshingRepository::new(
+///     backend.guilds(),
+///     Duration::from_secs(60 * 60),
+///     |guild_id| {
+///         println!("guild {} is stale, fetching it", guild_id);
+///     },
+/// );
+/// ```
+///
+/// [`CacheMeta`]: ../meta/struct.CacheMeta.html
+pub struct RefreshingRepository<E: Entity, R> {
+    fresh: Arc<Mutex<FreshTimes<E::Id>>>,
+    inner: R,
+    refresh: Arc<dyn Fn(E::Id) + Send + Sync>,
+    staleness: Duration,
+    phantom: PhantomData<E>,
+}
+
+impl<E: Entity, R> RefreshingRepository<E, R> {
+    /// Wrap a repository, calling the refresh callback when an entity older
+    /// than the staleness is retrieved.
+    pub fn new(
+        inner: R,
+        staleness: Duration,
+        refresh: impl Fn(E::Id) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            fresh: Arc::new(Mutex::new(FreshTimes::new())),
+            inner,
+            refresh: Arc::new(refresh),
+            staleness,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Return an immutable reference to the wrapped repository.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Return the time after which entities are considered stale.
+    pub fn staleness(&self) -> Duration {
+        self.staleness
+    }
+
+    /// Return when an entity was last upserted through the layer or had a
+    /// refresh requested, if that was less than the staleness ago.
+    pub fn cached_at(&self, entity_id: E::Id) -> Option<Instant> {
+        let fresh = self.lock();
+        let at = fresh.at.get(&entity_id).copied()?;
+
+        (at.elapsed() < self.staleness).then_some(at)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, FreshTimes<E::Id>> {
+        self.fresh.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record that an entity was upserted.
+    fn touch(&self, entity_id: E::Id) {
+        self.lock().touch(entity_id, Instant::now(), self.staleness);
+    }
+
+    /// Record that an entity was retrieved, returning whether it is stale.
+    ///
+    /// Stale entities are recorded as fresh, so that a refresh is requested
+    /// once.
+    fn check(&self, entity_id: E::Id, meta: Option<CacheMeta>) -> bool {
+        let now = Instant::now();
+        let mut fresh = self.lock();
+
+        if fresh.is_fresh(&entity_id, now, self.staleness)
+            || meta.is_some_and(|meta| meta.since_update() < self.staleness)
+        {
+            return false;
+        }
+
+        fresh.touch(entity_id, now, self.staleness);
+
+        true
+    }
+}
+
+impl<E: Entity, R: Clone> Clone for RefreshingRepository<E, R> {
+    fn clone(&self) -> Self {
+        Self {
+            fresh: Arc::clone(&self.fresh),
+            inner: self.inner.clone(),
+            refresh: Arc::clone(&self.refresh),
+            staleness: self.staleness,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: Entity, R: Debug> Debug for RefreshingRepository<E, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("RefreshingRepository")
+            .field("inner", &self.inner)
+            .field("staleness", &self.staleness)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B: Backend, E: Entity + 'static, R: Repository<E, B> + Sync> Repository<E, B>
+    for RefreshingRepository<E, R>
+{
+    fn backend(&self) -> &B {
+        self.inner.backend()
+    }
+
+    fn get(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.inner
+            .get_with_meta(entity_id)
+            .map_ok(move |entity| {
+                let (entity, meta) = entity?;
+
+                if self.check(entity_id, meta) {
+                    (self.refresh)(entity_id);
+                }
+
+                Some(entity)
+            })
+            .boxed()
+    }
+
+    fn get_local(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.inner.get_local(entity_id)
+    }
+
+    fn list(&self) -> ListEntitiesFuture<'_, E, B::Error> {
+        self.inner.list()
+    }
+
+    fn remove(&self, entity_id: E::Id) -> RemoveEntityFuture<'_, B::Error> {
+        self.lock().at.remove(&entity_id);
+
+        self.inner.remove(entity_id)
+    }
+
+    fn take(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, B::Error> {
+        self.lock().at.remove(&entity_id);
+
+        self.inner.take(entity_id)
+    }
+
+    fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, B::Error> {
+        self.touch(entity.id());
+
+        self.inner.upsert(entity)
+    }
+
+    fn upsert_with_outcome(&self, entity: E) -> UpsertOutcomeFuture<'_, B::Error> {
+        self.touch(entity.id());
+
+        self.inner.upsert_with_outcome(entity)
+    }
+
+    fn versioned_upsert(&self, entity: E, sequence: u64) -> UpsertEntityFuture<'_, B::Error> {
+        self.touch(entity.id());
+
+        self.inner.versioned_upsert(entity, sequence)
+    }
+}
@@ -0,0 +1,385 @@
+//! Machine-readable description of the entities and the relations between
+//! them.
+//!
+//! Entities refer to each other by ID, such as a message referring to its
+//! author by the author's user ID, much like foreign keys in a database.
+//! [`schema`] describes every [kind] of entity, the types of the IDs that
+//! identify it, and the fields that relate it to other entities, so backends
+//! can generate tables or graph edges from it and tooling can visualize the
+//! cache's model without hard-coding it.
+//!
+//! Only relations to kinds of entities that are cached are described, so
+//! fields such as a message's webhook ID aren't included.
+//!
+//! # Examples
+//!
+//! List the kinds of entities that refer to guilds:
+//!
+//! ```
+//! use twilight_cache::{any::EntityKind, schema};
+//!
+//! for (entity, relation) in schema().relations_to(EntityKind::Guild) {
+//!     println!("{}.{} -> guild", entity.kind.name(), relation.field);
+//! }
+//!
+//! let member = schema().entity(EntityKind::Member);
+//! assert_eq!(2, member.id.len());
+//!
+//! assert_eq!(EntityKind::ALL.len(), schema().entities().len());
+//!
+//! for (entity, kind) in schema().entities().iter().zip(EntityKind::ALL) {
+//!     assert_eq!(*kind, entity.kind);
+//! }
+//! ```
+//!
+//! [kind]: ../any/enum.EntityKind.html
+//! [`schema`]: fn.schema.html
+
+use super::any::EntityKind;
+
+/// Type of ID that identifies an entity or refers to one.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum IdType {
+    Attachment,
+    Channel,
+    Emoji,
+    Guild,
+    Message,
+    Role,
+    User,
+    VoiceRegion,
+}
+
+/// How many entities a relation refers to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Cardinality {
+    /// Exactly one entity, such as a message's author.
+    One,
+    /// At most one entity, such as a channel's parent category.
+    Optional,
+    /// Any number of entities, such as a member's roles.
+    Many,
+}
+
+/// Field of an entity that refers to other entities.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct RelationSchema {
+    /// How many entities the field refers to.
+    pub cardinality: Cardinality,
+    /// Name of the field, such as `author_id`.
+    pub field: &'static str,
+    /// Type of the IDs in the field.
+    pub id: IdType,
+    /// Kinds of entities that the IDs may refer to.
+    ///
+    /// Channel IDs may refer to multiple kinds of channels, such as the
+    /// channel of a message, which may be a text channel or a private
+    /// channel.
+    pub targets: &'static [EntityKind],
+}
+
+/// Description of a kind of entity.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct EntitySchema {
+    /// Types of the IDs that together identify the entity, such as a guild
+    /// ID and user ID for members.
+    pub id: &'static [IdType],
+    /// Kind of the entity.
+    pub kind: EntityKind,
+    /// Fields of the entity that refer to other entities.
+    pub relations: &'static [RelationSchema],
+}
+
+/// Description of every kind of entity.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Schema {
+    entities: &'static [EntitySchema],
+}
+
+impl Schema {
+    /// Return the descriptions of every kind of entity, in the order of
+    /// [`EntityKind::ALL`].
+    ///
+    /// [`EntityKind::ALL`]: ../any/enum.EntityKind.html#associatedconstant.ALL
+    pub const fn entities(&self) -> &'static [EntitySchema] {
+        self.entities
+    }
+
+    /// Return the description of a kind of entity.
+    pub fn entity(&self, kind: EntityKind) -> &'static EntitySchema {
+        self.entities
+            .iter()
+            .find(|entity| entity.kind == kind)
+            .expect("every kind of entity is described")
+    }
+
+    /// Return the relations that may refer to a kind of entity, along with
+    /// the entity each one is a field of.
+    pub fn relations_to(
+        &self,
+        kind: EntityKind,
+    ) -> impl Iterator<Item = (&'static EntitySchema, &'static RelationSchema)> {
+        self.entities.iter().flat_map(move |entity| {
+            entity
+                .relations
+                .iter()
+                .filter(move |relation| relation.targets.contains(&kind))
+                .map(move |relation| (entity, relation))
+        })
+    }
+}
+
+/// Return the description of every kind of entity.
+pub fn schema() -> &'static Schema {
+    &SCHEMA
+}
+
+const GUILD_CHANNELS: &[EntityKind] = &[
+    EntityKind::CategoryChannel,
+    EntityKind::TextChannel,
+    EntityKind::VoiceChannel,
+];
+
+const MESSAGE_CHANNELS: &[EntityKind] = &[
+    EntityKind::Group,
+    EntityKind::PrivateChannel,
+    EntityKind::TextChannel,
+];
+
+const fn relation(
+    field: &'static str,
+    cardinality: Cardinality,
+    id: IdType,
+    targets: &'static [EntityKind],
+) -> RelationSchema {
+    RelationSchema {
+        cardinality,
+        field,
+        id,
+        targets,
+    }
+}
+
+const fn guild_id(cardinality: Cardinality) -> RelationSchema {
+    relation("guild_id", cardinality, IdType::Guild, &[EntityKind::Guild])
+}
+
+const fn user_id(field: &'static str, cardinality: Cardinality) -> RelationSchema {
+    relation(field, cardinality, IdType::User, &[EntityKind::User])
+}
+
+const fn last_message_id() -> RelationSchema {
+    relation(
+        "last_message_id",
+        Cardinality::Optional,
+        IdType::Message,
+        &[EntityKind::Message],
+    )
+}
+
+const fn parent_id() -> RelationSchema {
+    relation(
+        "parent_id",
+        Cardinality::Optional,
+        IdType::Channel,
+        &[EntityKind::CategoryChannel],
+    )
+}
+
+const fn role_ids(field: &'static str) -> RelationSchema {
+    relation(field, Cardinality::Many, IdType::Role, &[EntityKind::Role])
+}
+
+static SCHEMA: Schema = Schema {
+    entities: &[
+        EntitySchema {
+            id: &[IdType::User],
+            kind: EntityKind::CurrentUser,
+            relations: &[],
+        },
+        EntitySchema {
+            id: &[IdType::Attachment],
+            kind: EntityKind::Attachment,
+            relations: &[relation(
+                "message_id",
+                Cardinality::One,
+                IdType::Message,
+                &[EntityKind::Message],
+            )],
+        },
+        EntitySchema {
+            id: &[IdType::Channel],
+            kind: EntityKind::CategoryChannel,
+            relations: &[guild_id(Cardinality::Optional)],
+        },
+        EntitySchema {
+            id: &[IdType::Emoji],
+            kind: EntityKind::Emoji,
+            relations: &[
+                guild_id(Cardinality::One),
+                role_ids("role_ids"),
+                user_id("user_id", Cardinality::Optional),
+            ],
+        },
+        EntitySchema {
+            id: &[IdType::Channel],
+            kind: EntityKind::Group,
+            relations: &[
+                last_message_id(),
+                user_id("owner_id", Cardinality::One),
+                user_id("recipient_ids", Cardinality::Many),
+            ],
+        },
+        EntitySchema {
+            id: &[IdType::Guild],
+            kind: EntityKind::Guild,
+            relations: &[
+                relation(
+                    "afk_channel_id",
+                    Cardinality::Optional,
+                    IdType::Channel,
+                    &[EntityKind::VoiceChannel],
+                ),
+                user_id("owner_id", Cardinality::One),
+                user_id("previous_owner_ids", Cardinality::Many),
+                relation(
+                    "rules_channel_id",
+                    Cardinality::Optional,
+                    IdType::Channel,
+                    &[EntityKind::TextChannel],
+                ),
+                relation(
+                    "system_channel_id",
+                    Cardinality::Optional,
+                    IdType::Channel,
+                    &[EntityKind::TextChannel],
+                ),
+                relation(
+                    "widget_channel_id",
+                    Cardinality::Optional,
+                    IdType::Channel,
+                    GUILD_CHANNELS,
+                ),
+            ],
+        },
+        EntitySchema {
+            id: &[IdType::Guild],
+            kind: EntityKind::GuildPreview,
+            relations: &[relation(
+                "emoji_ids",
+                Cardinality::Many,
+                IdType::Emoji,
+                &[EntityKind::Emoji],
+            )],
+        },
+        EntitySchema {
+            id: &[IdType::Guild, IdType::User],
+            kind: EntityKind::Member,
+            relations: &[
+                guild_id(Cardinality::One),
+                relation(
+                    "hoisted_role_id",
+                    Cardinality::Optional,
+                    IdType::Role,
+                    &[EntityKind::Role],
+                ),
+                role_ids("role_ids"),
+                user_id("user_id", Cardinality::One),
+            ],
+        },
+        EntitySchema {
+            id: &[IdType::Message],
+            kind: EntityKind::Message,
+            relations: &[
+                relation(
+                    "attachments",
+                    Cardinality::Many,
+                    IdType::Attachment,
+                    &[EntityKind::Attachment],
+                ),
+                user_id("author_id", Cardinality::One),
+                relation(
+                    "channel_id",
+                    Cardinality::One,
+                    IdType::Channel,
+                    MESSAGE_CHANNELS,
+                ),
+                guild_id(Cardinality::Optional),
+                relation(
+                    "mention_channels",
+                    Cardinality::Many,
+                    IdType::Channel,
+                    GUILD_CHANNELS,
+                ),
+                role_ids("mention_roles"),
+                user_id("mentions", Cardinality::Many),
+            ],
+        },
+        EntitySchema {
+            id: &[IdType::Guild, IdType::User],
+            kind: EntityKind::Presence,
+            relations: &[
+                guild_id(Cardinality::One),
+                user_id("user_id", Cardinality::One),
+            ],
+        },
+        EntitySchema {
+            id: &[IdType::Channel],
+            kind: EntityKind::PrivateChannel,
+            relations: &[
+                last_message_id(),
+                user_id("recipient_id", Cardinality::Optional),
+            ],
+        },
+        EntitySchema {
+            id: &[IdType::Role],
+            kind: EntityKind::Role,
+            relations: &[guild_id(Cardinality::One)],
+        },
+        EntitySchema {
+            id: &[IdType::Channel],
+            kind: EntityKind::TextChannel,
+            relations: &[
+                guild_id(Cardinality::Optional),
+                last_message_id(),
+                parent_id(),
+            ],
+        },
+        EntitySchema {
+            id: &[IdType::User],
+            kind: EntityKind::User,
+            relations: &[],
+        },
+        EntitySchema {
+            id: &[IdType::Channel],
+            kind: EntityKind::VoiceChannel,
+            relations: &[guild_id(Cardinality::Optional), parent_id()],
+        },
+        EntitySchema {
+            id: &[IdType::VoiceRegion],
+            kind: EntityKind::VoiceRegion,
+            relations: &[],
+        },
+        EntitySchema {
+            id: &[IdType::Guild, IdType::User],
+            kind: EntityKind::VoiceState,
+            relations: &[
+                relation(
+                    "channel_id",
+                    Cardinality::Optional,
+                    IdType::Channel,
+                    &[EntityKind::VoiceChannel],
+                ),
+                guild_id(Cardinality::One),
+                user_id("user_id", Cardinality::One),
+            ],
+        },
+    ],
+};
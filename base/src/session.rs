@@ -0,0 +1,63 @@
+//! Gateway sessions seen by the cache.
+//!
+//! Each `Ready` event processed by the cache starts a session for the shard
+//! that received it. The most recent session of each shard is retained and
+//! can be retrieved with [`Cache::session`], such as to resume it after a
+//! restart.
+//!
+//! [`Cache::session`]: ../struct.Cache.html#method.session
+
+use std::{collections::HashMap, sync::Mutex};
+use twilight_model::gateway::payload::Ready;
+
+/// Gateway session started by a `Ready` event.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Session {
+    /// ID of the session, used to resume it.
+    pub session_id: String,
+    /// ID of the shard and total number of shards, if the connection is
+    /// sharded.
+    pub shard: Option<[u64; 2]>,
+    /// Version of the gateway API.
+    pub version: u64,
+}
+
+impl Session {
+    /// Return the ID of the session's shard, which is 0 if the connection
+    /// isn't sharded.
+    pub fn shard_id(&self) -> u64 {
+        self.shard.map_or(0, |[id, _]| id)
+    }
+}
+
+impl From<&Ready> for Session {
+    fn from(ready: &Ready) -> Self {
+        Self {
+            session_id: ready.session_id.clone(),
+            shard: ready.shard,
+            version: ready.version,
+        }
+    }
+}
+
+/// Most recent session of each shard.
+#[derive(Debug, Default)]
+pub(crate) struct Sessions(Mutex<HashMap<u64, Session>>);
+
+impl Sessions {
+    pub(crate) fn get(&self, shard_id: u64) -> Option<Session> {
+        self.0
+            .lock()
+            .expect("sessions poisoned")
+            .get(&shard_id)
+            .cloned()
+    }
+
+    pub(crate) fn insert(&self, session: Session) {
+        self.0
+            .lock()
+            .expect("sessions poisoned")
+            .insert(session.shard_id(), session);
+    }
+}
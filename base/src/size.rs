@@ -0,0 +1,231 @@
+//! Estimation of the memory used by entities.
+//!
+//! Backends can use the estimated size of entities to reject entities that are
+//! too large, such as messages with many large embeds, or to report how much
+//! memory each type of entity is using.
+
+use super::entity::{
+    channel::{
+        AttachmentEntity, CategoryChannelEntity, GroupEntity, MessageEntity, PrivateChannelEntity,
+        TextChannelEntity, VoiceChannelEntity,
+    },
+    gateway::PresenceEntity,
+    guild::{EmojiEntity, GuildEntity, GuildPreviewEntity, MemberEntity, RoleEntity},
+    user::{CurrentUserEntity, UserEntity},
+    voice::{VoiceRegionEntity, VoiceStateEntity},
+};
+use std::mem;
+use twilight_model::{channel::embed::Embed, gateway::presence::Activity};
+
+/// Estimate of the number of bytes an entity occupies in memory.
+pub trait EstimateSize {
+    /// Return the approximate number of bytes that the entity occupies,
+    /// including the contents of its strings and lists.
+    ///
+    /// Nested models from `twilight-model`, such as embeds and activities,
+    /// only count the lengths of their text. The estimate doesn't account for
+    /// unused capacity or allocator overhead.
+    fn estimated_size(&self) -> usize;
+}
+
+fn string(value: &str) -> usize {
+    value.len()
+}
+
+fn optional_string(value: Option<&str>) -> usize {
+    value.map_or(0, string)
+}
+
+fn list<T>(values: &[T]) -> usize {
+    mem::size_of_val(values)
+}
+
+fn embed(embed: &Embed) -> usize {
+    let fields = embed
+        .fields
+        .iter()
+        .map(|field| field.name.len() + field.value.len())
+        .sum::<usize>();
+
+    fields
+        + embed.kind.len()
+        + optional_string(embed.description.as_deref())
+        + optional_string(embed.timestamp.as_deref())
+        + optional_string(embed.title.as_deref())
+        + optional_string(embed.url.as_deref())
+        + embed
+            .author
+            .as_ref()
+            .map_or(0, |author| optional_string(author.name.as_deref()))
+        + embed.footer.as_ref().map_or(0, |footer| footer.text.len())
+}
+
+fn activity(activity: &Activity) -> usize {
+    activity.name.len()
+        + optional_string(activity.details.as_deref())
+        + optional_string(activity.id.as_deref())
+        + optional_string(activity.state.as_deref())
+        + optional_string(activity.url.as_deref())
+}
+
+impl EstimateSize for AttachmentEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + string(&self.filename)
+            + string(&self.proxy_url)
+            + string(&self.url)
+    }
+}
+
+impl EstimateSize for CategoryChannelEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>() + string(&self.name) + list(&self.permission_overwrites)
+    }
+}
+
+impl EstimateSize for CurrentUserEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + optional_string(self.avatar.as_deref())
+            + string(&self.discriminator)
+            + optional_string(self.email.as_deref())
+            + string(&self.name)
+    }
+}
+
+impl EstimateSize for EmojiEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>() + string(&self.name) + list(&self.role_ids)
+    }
+}
+
+impl EstimateSize for GroupEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + optional_string(self.icon.as_deref())
+            + optional_string(self.last_pin_timestamp.as_deref())
+            + optional_string(self.name.as_deref())
+            + list(&self.recipient_ids)
+    }
+}
+
+impl EstimateSize for GuildEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + optional_string(self.banner.as_deref())
+            + optional_string(self.description.as_deref())
+            + optional_string(self.discovery_splash.as_deref())
+            + list(&self.features)
+            + self.features.iter().map(String::len).sum::<usize>()
+            + optional_string(self.icon.as_deref())
+            + optional_string(self.joined_at.as_deref())
+            + string(&self.name)
+            + string(&self.preferred_locale)
+            + list(&self.previous_owner_ids)
+            + string(&self.region)
+            + optional_string(self.splash.as_deref())
+            + optional_string(self.vanity_url_code.as_deref())
+    }
+}
+
+impl EstimateSize for GuildPreviewEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + optional_string(self.description.as_deref())
+            + optional_string(self.discovery_splash.as_deref())
+            + list(&self.emoji_ids)
+            + list(&self.features)
+            + self.features.iter().map(String::len).sum::<usize>()
+            + optional_string(self.icon.as_deref())
+            + string(&self.name)
+            + optional_string(self.splash.as_deref())
+    }
+}
+
+impl EstimateSize for MemberEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + optional_string(self.joined_at.as_deref())
+            + optional_string(self.nick.as_deref())
+            + optional_string(self.premium_since.as_deref())
+            + list(&self.role_ids)
+    }
+}
+
+impl EstimateSize for MessageEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + list(&self.attachments)
+            + string(&self.content)
+            + optional_string(self.edited_timestamp.as_deref())
+            + list(&self.embeds)
+            + self.embeds.iter().map(embed).sum::<usize>()
+            + list(&self.mention_channels)
+            + list(&self.mention_roles)
+            + list(&self.mentions)
+            + list(&self.reactions)
+            + string(&self.timestamp)
+    }
+}
+
+impl EstimateSize for PresenceEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + list(&self.activities)
+            + self.activities.iter().map(activity).sum::<usize>()
+    }
+}
+
+impl EstimateSize for PrivateChannelEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>() + optional_string(self.last_pin_timestamp.as_deref())
+    }
+}
+
+impl EstimateSize for RoleEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>() + string(&self.name)
+    }
+}
+
+impl EstimateSize for TextChannelEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + optional_string(self.last_pin_timestamp.as_deref())
+            + string(&self.name)
+            + list(&self.permission_overwrites)
+            + optional_string(self.topic.as_deref())
+    }
+}
+
+impl EstimateSize for UserEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + optional_string(self.avatar.as_deref())
+            + string(&self.discriminator)
+            + optional_string(self.email.as_deref())
+            + optional_string(self.locale.as_deref())
+            + string(&self.name)
+    }
+}
+
+impl EstimateSize for VoiceChannelEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + string(&self.name)
+            + list(&self.permission_overwrites)
+            + optional_string(self.rtc_region.as_deref())
+    }
+}
+
+impl EstimateSize for VoiceRegionEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>() + string(&self.id) + string(&self.name)
+    }
+}
+
+impl EstimateSize for VoiceStateEntity {
+    fn estimated_size(&self) -> usize {
+        mem::size_of::<Self>() + string(&self.session_id) + optional_string(self.token.as_deref())
+    }
+}
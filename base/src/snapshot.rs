@@ -0,0 +1,301 @@
+//! Point-in-time copies of the contents of a cache and differences between
+//! them.
+//!
+//! A [`Snapshot`] contains every entity stored by a backend. Two snapshots,
+//! such as one of a primary cache and one of a replica or ones taken before and
+//! after a restart, can be compared with [`diff`] to find the entities that
+//! were added, removed, or changed between them.
+//!
+//! With the `serde` feature enabled snapshots and diffs are serializable, so
+//! they can be written to a file and compared or inspected later. A snapshot
+//! read back from a file can be loaded into a backend with
+//! [`Snapshot::restore`].
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{entity::guild::GuildEntity, repository::Repository, snapshot, Backend};
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let backend = InMemoryBackend::new();
+//! let before = snapshot::Snapshot::capture(&backend).await?;
+//!
+//! let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+//! backend.guilds().upsert(guild.clone()).await?;
+//!
+//! let after = snapshot::Snapshot::capture(&backend).await?;
+//! let diff = snapshot::diff(&before, &after);
+//!
+//! assert_eq!(vec![guild], diff.guilds.added);
+//! # Ok(()) }
+//! ```
+//!
+//! [`diff`]: fn.diff.html
+//! [`Snapshot`]: struct.Snapshot.html
+//! [`Snapshot::restore`]: struct.Snapshot.html#method.restore
+
+use super::{
+    backend::Backend,
+    entity::{
+        channel::{
+            AttachmentEntity, CategoryChannelEntity, GroupEntity, MessageEntity,
+            PrivateChannelEntity, TextChannelEntity, VoiceChannelEntity,
+        },
+        gateway::PresenceEntity,
+        guild::{EmojiEntity, GuildEntity, GuildPreviewEntity, MemberEntity, RoleEntity},
+        user::{CurrentUserEntity, UserEntity},
+        voice::{VoiceRegionEntity, VoiceStateEntity},
+        Entity,
+    },
+    repository::{Repository, SingleEntityRepository},
+};
+use futures_util::stream::TryStreamExt;
+use std::collections::HashMap;
+
+/// Every entity stored by a backend at a point in time.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Snapshot {
+    pub attachments: Vec<AttachmentEntity>,
+    pub category_channels: Vec<CategoryChannelEntity>,
+    pub current_user: Option<CurrentUserEntity>,
+    pub emojis: Vec<EmojiEntity>,
+    pub groups: Vec<GroupEntity>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub guild_previews: Vec<GuildPreviewEntity>,
+    pub guilds: Vec<GuildEntity>,
+    pub members: Vec<MemberEntity>,
+    pub messages: Vec<MessageEntity>,
+    pub presences: Vec<PresenceEntity>,
+    pub private_channels: Vec<PrivateChannelEntity>,
+    pub roles: Vec<RoleEntity>,
+    pub text_channels: Vec<TextChannelEntity>,
+    pub users: Vec<UserEntity>,
+    pub voice_channels: Vec<VoiceChannelEntity>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub voice_regions: Vec<VoiceRegionEntity>,
+    pub voice_states: Vec<VoiceStateEntity>,
+}
+
+impl Snapshot {
+    /// Capture every entity stored by a backend.
+    ///
+    /// Entities are listed one type at a time, so entities that are modified
+    /// while the snapshot is being captured may or may not be included.
+    ///
+    /// # Errors
+    ///
+    /// Returns the backend's error if listing any type of entity fails.
+    pub async fn capture<B: Backend>(backend: &B) -> Result<Self, B::Error> {
+        Ok(Self {
+            attachments: list(backend.attachments()).await?,
+            category_channels: list(backend.category_channels()).await?,
+            current_user: backend.current_user().get().await?,
+            emojis: list(backend.emojis()).await?,
+            groups: list(backend.groups()).await?,
+            guild_previews: list(backend.guild_previews()).await?,
+            guilds: list(backend.guilds()).await?,
+            members: list(backend.members()).await?,
+            messages: list(backend.messages()).await?,
+            presences: list(backend.presences()).await?,
+            private_channels: list(backend.private_channels()).await?,
+            roles: list(backend.roles()).await?,
+            text_channels: list(backend.text_channels()).await?,
+            users: list(backend.users()).await?,
+            voice_channels: list(backend.voice_channels()).await?,
+            voice_regions: list(backend.voice_regions()).await?,
+            voice_states: list(backend.voice_states()).await?,
+        })
+    }
+
+    /// Upsert every entity in the snapshot into a backend, such as one
+    /// loaded from a file for inspection.
+    ///
+    /// Guilds and channels are upserted before the entities belonging to
+    /// them. Entities already stored by the backend that aren't in the
+    /// snapshot are kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns the backend's error if upserting any type of entity fails.
+    pub async fn restore<B: Backend>(&self, backend: &B) -> Result<(), B::Error> {
+        if let Some(current_user) = self.current_user.clone() {
+            backend.current_user().upsert(current_user).await?;
+        }
+
+        upsert(backend.guilds(), &self.guilds).await?;
+        upsert(backend.guild_previews(), &self.guild_previews).await?;
+        upsert(backend.users(), &self.users).await?;
+        upsert(backend.voice_regions(), &self.voice_regions).await?;
+        upsert(backend.category_channels(), &self.category_channels).await?;
+        upsert(backend.groups(), &self.groups).await?;
+        upsert(backend.private_channels(), &self.private_channels).await?;
+        upsert(backend.text_channels(), &self.text_channels).await?;
+        upsert(backend.voice_channels(), &self.voice_channels).await?;
+        upsert(backend.emojis(), &self.emojis).await?;
+        upsert(backend.members(), &self.members).await?;
+        upsert(backend.presences(), &self.presences).await?;
+        upsert(backend.roles(), &self.roles).await?;
+        upsert(backend.voice_states(), &self.voice_states).await?;
+        upsert(backend.messages(), &self.messages).await?;
+
+        upsert(backend.attachments(), &self.attachments).await
+    }
+}
+
+/// Entities of a type that differ between two snapshots.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(bound(
+        deserialize = "E: serde::Deserialize<'de>, E::Id: serde::Deserialize<'de>",
+        serialize = "E: serde::Serialize, E::Id: serde::Serialize"
+    ))
+)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EntityDiff<E: Entity> {
+    /// Entities present in only the newer snapshot.
+    pub added: Vec<E>,
+    /// Entities present in both snapshots with different contents.
+    pub changed: Vec<ChangedEntity<E>>,
+    /// IDs of entities present in only the older snapshot.
+    pub removed: Vec<E::Id>,
+}
+
+impl<E: Entity> EntityDiff<E> {
+    /// Whether the entities are the same in both snapshots.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl<E: Entity> Default for EntityDiff<E> {
+    fn default() -> Self {
+        Self {
+            added: Vec::new(),
+            changed: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+/// Contents of an entity in two snapshots.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChangedEntity<E> {
+    /// Entity in the older snapshot.
+    pub old: E,
+    /// Entity in the newer snapshot.
+    pub new: E,
+}
+
+/// Entities that differ between two snapshots, by type.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SnapshotDiff {
+    pub attachments: EntityDiff<AttachmentEntity>,
+    pub category_channels: EntityDiff<CategoryChannelEntity>,
+    pub current_user: EntityDiff<CurrentUserEntity>,
+    pub emojis: EntityDiff<EmojiEntity>,
+    pub groups: EntityDiff<GroupEntity>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub guild_previews: EntityDiff<GuildPreviewEntity>,
+    pub guilds: EntityDiff<GuildEntity>,
+    pub members: EntityDiff<MemberEntity>,
+    pub messages: EntityDiff<MessageEntity>,
+    pub presences: EntityDiff<PresenceEntity>,
+    pub private_channels: EntityDiff<PrivateChannelEntity>,
+    pub roles: EntityDiff<RoleEntity>,
+    pub text_channels: EntityDiff<TextChannelEntity>,
+    pub users: EntityDiff<UserEntity>,
+    pub voice_channels: EntityDiff<VoiceChannelEntity>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub voice_regions: EntityDiff<VoiceRegionEntity>,
+    pub voice_states: EntityDiff<VoiceStateEntity>,
+}
+
+impl SnapshotDiff {
+    /// Whether every entity is the same in both snapshots.
+    pub fn is_empty(&self) -> bool {
+        self.attachments.is_empty()
+            && self.category_channels.is_empty()
+            && self.current_user.is_empty()
+            && self.emojis.is_empty()
+            && self.groups.is_empty()
+            && self.guild_previews.is_empty()
+            && self.guilds.is_empty()
+            && self.members.is_empty()
+            && self.messages.is_empty()
+            && self.presences.is_empty()
+            && self.private_channels.is_empty()
+            && self.roles.is_empty()
+            && self.text_channels.is_empty()
+            && self.users.is_empty()
+            && self.voice_channels.is_empty()
+            && self.voice_regions.is_empty()
+            && self.voice_states.is_empty()
+    }
+}
+
+/// Compare an older snapshot with a newer one.
+///
+/// Entities are matched by their IDs. The order of entities in the diff is
+/// unspecified.
+pub fn diff(old: &Snapshot, new: &Snapshot) -> SnapshotDiff {
+    SnapshotDiff {
+        attachments: diff_entities(&old.attachments, &new.attachments),
+        category_channels: diff_entities(&old.category_channels, &new.category_channels),
+        current_user: diff_entities(old.current_user.as_slice(), new.current_user.as_slice()),
+        emojis: diff_entities(&old.emojis, &new.emojis),
+        groups: diff_entities(&old.groups, &new.groups),
+        guild_previews: diff_entities(&old.guild_previews, &new.guild_previews),
+        guilds: diff_entities(&old.guilds, &new.guilds),
+        members: diff_entities(&old.members, &new.members),
+        messages: diff_entities(&old.messages, &new.messages),
+        presences: diff_entities(&old.presences, &new.presences),
+        private_channels: diff_entities(&old.private_channels, &new.private_channels),
+        roles: diff_entities(&old.roles, &new.roles),
+        text_channels: diff_entities(&old.text_channels, &new.text_channels),
+        users: diff_entities(&old.users, &new.users),
+        voice_channels: diff_entities(&old.voice_channels, &new.voice_channels),
+        voice_regions: diff_entities(&old.voice_regions, &new.voice_regions),
+        voice_states: diff_entities(&old.voice_states, &new.voice_states),
+    }
+}
+
+fn diff_entities<E: Clone + Entity + PartialEq>(old: &[E], new: &[E]) -> EntityDiff<E> {
+    let mut old = old
+        .iter()
+        .map(|entity| (entity.id(), entity))
+        .collect::<HashMap<_, _>>();
+    let mut diff = EntityDiff::default();
+
+    for entity in new {
+        match old.remove(&entity.id()) {
+            Some(old) if old != entity => diff.changed.push(ChangedEntity {
+                old: old.clone(),
+                new: entity.clone(),
+            }),
+            Some(_) => {}
+            None => diff.added.push(entity.clone()),
+        }
+    }
+
+    diff.removed = old.into_keys().collect();
+
+    diff
+}
+
+async fn upsert<B: Backend, E: Clone + Entity, R: Repository<E, B>>(
+    repo: R,
+    entities: &[E],
+) -> Result<(), B::Error> {
+    repo.upsert_bulk(entities.iter().cloned()).await
+}
+
+async fn list<B: Backend, E: Entity, R: Repository<E, B>>(repo: R) -> Result<Vec<E>, B::Error> {
+    repo.list().await?.try_collect().await
+}
@@ -0,0 +1,99 @@
+//! Creation dates of entities derived from their IDs.
+//!
+//! Discord's IDs are snowflakes, which encode the number of milliseconds
+//! since the start of 2015 that they were created at. [`SnowflakeExt`] reads
+//! the creation date of an ID, and [`SnowflakeEntity`] that of any entity
+//! identified by one.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::{Duration, UNIX_EPOCH};
+//! use twilight_cache::{
+//!     entity::user::UserEntity,
+//!     snowflake::{SnowflakeEntity, SnowflakeExt},
+//! };
+//! use twilight_model::id::UserId;
+//!
+//! let user_id = UserId(80_351_110_224_678_912);
+//! assert_eq!(1_439_227_597_529, user_id.timestamp());
+//! assert_eq!(
+//!     UNIX_EPOCH + Duration::from_millis(1_439_227_597_529),
+//!     user_id.created_at(),
+//! );
+//!
+//! let user = UserEntity::builder(user_id, "user", "0001").build();
+//! assert_eq!(user_id.created_at(), user.created_at());
+//! ```
+//!
+//! [`SnowflakeEntity`]: trait.SnowflakeEntity.html
+//! [`SnowflakeExt`]: trait.SnowflakeExt.html
+
+use super::entity::Entity;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use twilight_model::id::{
+    ApplicationId, AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId,
+};
+
+/// Milliseconds since the Unix epoch of the first second of 2015, which
+/// snowflakes are relative to.
+pub const DISCORD_EPOCH: u64 = 1_420_070_400_000;
+
+/// ID that is a snowflake.
+pub trait SnowflakeExt: Copy {
+    /// Return the raw snowflake.
+    fn snowflake(self) -> u64;
+
+    /// Return the number of milliseconds since the Unix epoch at which the ID
+    /// was created.
+    fn timestamp(self) -> u64 {
+        (self.snowflake() >> 22) + DISCORD_EPOCH
+    }
+
+    /// Return the time at which the ID was created.
+    fn created_at(self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.timestamp())
+    }
+}
+
+macro_rules! snowflakes {
+    ($($id:ident),* $(,)?) => {
+        $(
+            impl SnowflakeExt for $id {
+                fn snowflake(self) -> u64 {
+                    self.0
+                }
+            }
+        )*
+    };
+}
+
+snowflakes!(
+    ApplicationId,
+    AttachmentId,
+    ChannelId,
+    EmojiId,
+    GuildId,
+    MessageId,
+    RoleId,
+    UserId
+);
+
+/// Entity identified by a snowflake.
+///
+/// Implemented for every entity whose ID implements [`SnowflakeExt`].
+///
+/// [`SnowflakeExt`]: trait.SnowflakeExt.html
+pub trait SnowflakeEntity: Entity {
+    /// Return the time at which the entity was created.
+    fn created_at(&self) -> SystemTime;
+}
+
+impl<T: Entity> SnowflakeEntity for T
+where
+    T::Id: SnowflakeExt,
+{
+    fn created_at(&self) -> SystemTime {
+        self.id().created_at()
+    }
+}
@@ -0,0 +1,104 @@
+//! Parsing of the timestamps stored in entities.
+//!
+//! Entities store timestamps as the ISO 8601 strings that Discord sends. With
+//! the `time` feature enabled entities have accessors that parse them, such as
+//! [`MemberEntity::joined_at_datetime`]. Parsed timestamps are cached in the
+//! entity's [`ParsedTimestamp`] fields, so only the first call to an accessor
+//! parses the timestamp.
+//!
+//! [`MemberEntity::joined_at_datetime`]: ../entity/guild/member/struct.MemberEntity.html#method.joined_at_datetime
+//! [`ParsedTimestamp`]: struct.ParsedTimestamp.html
+
+#[cfg(feature = "time")]
+pub use time::{error::Parse as ParseTimestampError, OffsetDateTime};
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+#[cfg(feature = "time")]
+use std::sync::OnceLock;
+#[cfg(feature = "time")]
+use time::format_description::well_known::Rfc3339;
+
+/// Parse a timestamp sent by Discord.
+///
+/// # Errors
+///
+/// Returns a `ParseTimestampError` if the timestamp isn't a valid ISO 8601
+/// timestamp.
+#[cfg(feature = "time")]
+pub fn parse(timestamp: &str) -> Result<OffsetDateTime, ParseTimestampError> {
+    OffsetDateTime::parse(timestamp, &Rfc3339)
+}
+
+/// Parse an optional timestamp sent by Discord.
+///
+/// # Errors
+///
+/// Returns a `ParseTimestampError` if the timestamp is present but isn't a
+/// valid ISO 8601 timestamp.
+#[cfg(feature = "time")]
+pub fn parse_optional(
+    timestamp: Option<&str>,
+) -> Result<Option<OffsetDateTime>, ParseTimestampError> {
+    timestamp.map(parse).transpose()
+}
+
+/// Cache of a timestamp field of an entity, parsed by the field's accessor.
+///
+/// The timestamp is parsed on the first call to the accessor and reused for as
+/// long as the field isn't changed. Without the `time` feature this is empty.
+///
+/// Parsed timestamps aren't serialized or archived, and are ignored when
+/// comparing entities.
+#[derive(Clone, Default)]
+pub struct ParsedTimestamp {
+    #[cfg(feature = "time")]
+    #[allow(clippy::type_complexity)]
+    parsed: OnceLock<(
+        Option<String>,
+        Result<Option<OffsetDateTime>, ParseTimestampError>,
+    )>,
+}
+
+impl ParsedTimestamp {
+    /// Parse a timestamp, reusing the cached timestamp if it was parsed from
+    /// the same string.
+    #[cfg(feature = "time")]
+    pub(crate) fn get(&self, timestamp: &str) -> Result<OffsetDateTime, ParseTimestampError> {
+        self.get_optional(Some(timestamp))
+            .transpose()
+            .unwrap_or_else(|| parse(timestamp))
+    }
+
+    /// Parse an optional timestamp, reusing the cached timestamp if it was
+    /// parsed from the same string.
+    #[cfg(feature = "time")]
+    pub(crate) fn get_optional(
+        &self,
+        timestamp: Option<&str>,
+    ) -> Result<Option<OffsetDateTime>, ParseTimestampError> {
+        let (source, parsed) = self
+            .parsed
+            .get_or_init(|| (timestamp.map(ToOwned::to_owned), parse_optional(timestamp)));
+
+        // The field may have been changed after it was first parsed.
+        if source.as_deref() == timestamp {
+            *parsed
+        } else {
+            parse_optional(timestamp)
+        }
+    }
+}
+
+impl Debug for ParsedTimestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ParsedTimestamp").finish()
+    }
+}
+
+impl Eq for ParsedTimestamp {}
+
+impl PartialEq for ParsedTimestamp {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
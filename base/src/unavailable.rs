@@ -0,0 +1,107 @@
+//! Buffering of events for guilds that are unavailable due to an outage.
+//!
+//! When a guild becomes unavailable, Discord sends a `GuildDelete` with
+//! `unavailable` set, or an `UnavailableGuild` event, and the cached guild is
+//! marked as unavailable. Once the guild is available again a `GuildCreate`
+//! with its full state is sent, replacing what was cached.
+//!
+//! When enabled with [`Cache::enable_unavailable_buffering`], member and
+//! channel events for a guild received while it's unavailable are held back
+//! instead of being applied to stale state, and processed in order after the
+//! guild's `GuildCreate`. A limited number of events are buffered per guild;
+//! when the limit is reached the oldest event is dropped.
+//!
+//! [`Cache::enable_unavailable_buffering`]: ../struct.Cache.html#method.enable_unavailable_buffering
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+use twilight_model::{channel::Channel, gateway::event::Event, id::GuildId};
+
+/// Return the ID of the guild that an event is buffered for while the guild
+/// is unavailable.
+///
+/// Only member events and events of guild channels are buffered.
+pub fn buffered_guild_id(event: &Event) -> Option<GuildId> {
+    let channel = |channel: &Channel| match channel {
+        Channel::Guild(channel) => channel.guild_id(),
+        _ => None,
+    };
+
+    match event {
+        Event::ChannelCreate(event) => channel(&event.0),
+        Event::ChannelDelete(event) => channel(&event.0),
+        Event::ChannelPinsUpdate(event) => event.guild_id,
+        Event::ChannelUpdate(event) => channel(&event.0),
+        Event::MemberAdd(event) => Some(event.guild_id),
+        Event::MemberChunk(event) => Some(event.guild_id),
+        Event::MemberRemove(event) => Some(event.guild_id),
+        Event::MemberUpdate(event) => Some(event.guild_id),
+        _ => None,
+    }
+}
+
+/// Event held back until its guild is available, with the sequence it was
+/// processed with.
+pub(crate) type BufferedEvent = (Event, Option<u64>);
+
+/// Unavailable guilds and the events buffered for them.
+#[derive(Debug)]
+pub(crate) struct UnavailableGuilds {
+    capacity: usize,
+    guilds: Mutex<HashMap<GuildId, VecDeque<BufferedEvent>>>,
+}
+
+impl UnavailableGuilds {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            guilds: Mutex::default(),
+        }
+    }
+
+    /// Buffer an event if its guild is unavailable, returning whether it was.
+    pub(crate) fn buffer(&self, event: &Event, sequence: Option<u64>) -> bool {
+        let Some(guild_id) = buffered_guild_id(event) else {
+            return false;
+        };
+
+        let mut guilds = self.guilds.lock().expect("unavailable guilds poisoned");
+
+        let Some(events) = guilds.get_mut(&guild_id) else {
+            return false;
+        };
+
+        if self.capacity == 0 {
+            return true;
+        }
+
+        if events.len() >= self.capacity {
+            events.pop_front();
+        }
+
+        events.push_back((event.clone(), sequence));
+
+        true
+    }
+
+    /// Mark a guild as unavailable, buffering its events from now on.
+    pub(crate) fn mark(&self, guild_id: GuildId) {
+        self.guilds
+            .lock()
+            .expect("unavailable guilds poisoned")
+            .entry(guild_id)
+            .or_default();
+    }
+
+    /// Mark a guild as no longer unavailable, returning the events buffered
+    /// for it.
+    pub(crate) fn take(&self, guild_id: GuildId) -> VecDeque<BufferedEvent> {
+        self.guilds
+            .lock()
+            .expect("unavailable guilds poisoned")
+            .remove(&guild_id)
+            .unwrap_or_default()
+    }
+}
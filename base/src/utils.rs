@@ -1,71 +1,87 @@
+//! Utilities for implementing repository methods that resolve relations
+//! between entities.
+//!
+//! These are used by the Discord entities and can be used by other entity
+//! sets to implement their own repositories' relation methods.
+
 use super::{
     backend::Backend,
     entity::Entity,
-    repository::{GetEntityFuture, ListEntitiesFuture, ListEntityIdsStream, Repository},
+    repository::{
+        GetEntityFuture, ListEntitiesFuture, ListEntitiesStream, ListEntityIdsFuture,
+        ListEntityIdsStream, Repository,
+    },
 };
-use futures_util::stream::{self, StreamExt};
+use futures_util::stream::{self, StreamExt, TryStreamExt};
 use std::future::Future;
-
+#[cfg(feature = "discord")]
+use twilight_model::id::GuildId;
+
+/// Resolve an entity, and then the foreign entity whose ID is optionally
+/// returned by `f`.
+///
+/// The foreign repository is only created via `foreign` once the entity has
+/// been found, so a missing entity costs no more than its own lookup.
 pub fn relation_and_then<
     'a,
     B: Backend + 'a,
     F: FnOnce(M1) -> Option<M2::Id> + Send + 'a,
     M1: Entity + 'a,
     M2: Entity + 'a,
-    R1: Repository<M1, B> + Send + 'a,
-    R2: Repository<M2, B> + Send + Sync + 'a,
+    R: Repository<M2, B> + Send + Sync + 'a,
 >(
-    repo: R1,
-    foreign: R2,
-    id: M1::Id,
+    entity: GetEntityFuture<'a, M1, B::Error>,
+    backend: &'a B,
+    foreign: fn(&B) -> R,
     f: F,
 ) -> GetEntityFuture<'a, M2, B::Error>
 where
     B::Error: Send,
 {
     Box::pin(async move {
-        let fut = repo.get(id);
-
-        let foreign_id = if let Some(foreign_id) = fut.await?.and_then(|e| f(e)) {
-            foreign_id
-        } else {
+        let Some(foreign_id) = entity.await?.and_then(f) else {
             return Ok(None);
         };
 
+        let foreign = foreign(backend);
+
         foreign.get(foreign_id).await
     })
 }
 
+/// Resolve an entity, and then the foreign entity whose ID is returned by
+/// `f`.
 pub fn relation_map<
     'a,
     B: Backend + 'a,
     F: FnOnce(M1) -> M2::Id + Send + 'a,
     M1: Entity + 'a,
     M2: Entity + 'a,
-    R1: Repository<M1, B> + Send + 'a,
-    R2: Repository<M2, B> + Send + Sync + 'a,
+    R: Repository<M2, B> + Send + Sync + 'a,
 >(
-    repo: R1,
-    foreign: R2,
-    id: M1::Id,
+    entity: GetEntityFuture<'a, M1, B::Error>,
+    backend: &'a B,
+    foreign: fn(&B) -> R,
     f: F,
 ) -> GetEntityFuture<'a, M2, B::Error>
 where
     B::Error: Send,
 {
     Box::pin(async move {
-        let fut = repo.get(id);
-
-        let foreign_id = if let Some(entity) = fut.await? {
+        let foreign_id = if let Some(entity) = entity.await? {
             f(entity)
         } else {
             return Ok(None);
         };
 
+        let foreign = foreign(backend);
+
         foreign.get(foreign_id).await
     })
 }
 
+/// Resolve an entity, and then stream the foreign entities whose IDs are
+/// returned by `f`.
 pub fn stream<
     'a,
     B: Backend + 'a,
@@ -73,50 +89,97 @@ pub fn stream<
     I: Iterator<Item = M2::Id> + Send + 'a,
     M1: Entity + 'a,
     M2: Entity + 'a,
-    R1: Repository<M1, B> + Send + 'a,
-    R2: Repository<M2, B> + Send + 'a,
+    R: Repository<M2, B> + Send + 'a,
 >(
-    repo: R1,
-    foreign: R2,
-    id: M1::Id,
+    entity: GetEntityFuture<'a, M1, B::Error>,
+    backend: &'a B,
+    foreign: fn(&B) -> R,
     f: F,
 ) -> ListEntitiesFuture<'a, M2, B::Error> {
-    struct StreamState<I, R2> {
-        foreign: R2,
-        ids: I,
-    }
-
     Box::pin(async move {
-        let fut = repo.get(id);
-
-        let foreign_ids = if let Some(entity) = fut.await? {
+        let foreign_ids = if let Some(entity) = entity.await? {
             f(entity)
         } else {
             return Ok(stream::empty().boxed());
         };
 
-        let state = StreamState {
-            foreign,
-            ids: foreign_ids,
-        };
+        Ok(stream_iter(foreign_ids, foreign(backend)))
+    })
+}
 
-        Ok(stream::unfold(state, |mut state| async move {
-            loop {
-                let id = state.ids.next()?;
+/// Stream the foreign entities of a guild whose IDs are listed by `ids`.
+#[cfg(feature = "discord")]
+pub fn stream_guild<
+    'a,
+    B: Backend + 'a,
+    F: for<'r> FnOnce(&'r B::GuildRepository, GuildId) -> ListEntityIdsFuture<'r, T, B::Error>
+        + Send
+        + 'a,
+    M: Entity + 'a,
+    R: Repository<M, B> + Send + 'a,
+    T: Send + 'a,
+>(
+    backend: &'a B,
+    guild_id: GuildId,
+    ids: F,
+    to_id: fn(GuildId, T) -> M::Id,
+    foreign: fn(&B) -> R,
+) -> ListEntitiesFuture<'a, M, B::Error> {
+    Box::pin(async move {
+        let guilds = backend.guilds();
+        let ids = ids(&guilds, guild_id)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
 
-                let fut = state.foreign.get(id);
+        if ids.is_empty() {
+            return Ok(stream::empty().boxed());
+        }
 
-                match fut.await {
-                    Ok(Some(e)) => return Some((Ok(e), state)),
-                    Ok(None) => continue,
-                    Err(why) => return Some((Err(why), state)),
-                }
+        Ok(stream_iter(
+            ids.into_iter().map(move |id| to_id(guild_id, id)),
+            foreign(backend),
+        ))
+    })
+}
+
+/// Stream the foreign entities with the given IDs, skipping those that are
+/// not in the repository.
+pub fn stream_iter<
+    'a,
+    B: Backend + 'a,
+    I: Iterator<Item = M::Id> + Send + 'a,
+    M: Entity + 'a,
+    R: Repository<M, B> + Send + 'a,
+>(
+    ids: I,
+    foreign: R,
+) -> ListEntitiesStream<'a, M, B::Error> {
+    struct StreamState<I, R> {
+        foreign: R,
+        ids: I,
+    }
+
+    let state = StreamState { foreign, ids };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            let id = state.ids.next()?;
+
+            let fut = state.foreign.get(id);
+
+            match fut.await {
+                Ok(Some(e)) => return Some((Ok(e), state)),
+                Ok(None) => {}
+                Err(why) => return Some((Err(why), state)),
             }
-        })
-        .boxed())
+        }
     })
+    .boxed()
 }
 
+/// Stream the foreign entities with the IDs returned by a future, skipping
+/// those that are not in the repository.
 pub fn stream_ids<
     'a,
     B: Backend + 'a,
@@ -137,7 +200,7 @@ pub fn stream_ids<
 
         let state = StreamState {
             foreign,
-            ids: foreign_ids.boxed(),
+            ids: foreign_ids,
         };
 
         Ok(stream::unfold(state, |mut state| async move {
@@ -151,7 +214,7 @@ pub fn stream_ids<
 
                 match fut.await {
                     Ok(Some(e)) => return Some((Ok(e), state)),
-                    Ok(None) => continue,
+                    Ok(None) => {}
                     Err(why) => return Some((Err(why), state)),
                 }
             }
@@ -0,0 +1,273 @@
+//! Views of the cache scoped to a single guild or channel.
+//!
+//! Command handlers and other code working within one guild would otherwise
+//! pass the guild's ID to every repository call. A [`GuildCacheView`],
+//! returned by [`Cache::guild`], bundles the guild-scoped accessors of the
+//! cache's repositories.
+//!
+//! Channels are stored in a repository per kind of channel, so working with a
+//! channel whose kind isn't known means trying each repository. A
+//! [`ChannelCacheView`], returned by [`Cache::channel`], resolves the kind of
+//! the channel once and provides accessors that work with any kind.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use twilight_cache::Cache;
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cache: Cache<InMemoryBackend> = Cache::new();
+//! let guild = cache.guild(GuildId(1));
+//!
+//! if let Some(member) = guild.member(UserId(2)).await? {
+//!     println!("member's nickname: {:?}", member.nick);
+//! }
+//! # Ok(()) }
+//! ```
+//!
+//! [`Cache::channel`]: ../struct.Cache.html#method.channel
+//! [`Cache::guild`]: ../struct.Cache.html#method.guild
+//! [`ChannelCacheView`]: struct.ChannelCacheView.html
+//! [`GuildCacheView`]: struct.GuildCacheView.html
+
+use super::{
+    cache::Cache,
+    entity::{
+        channel::{
+            CategoryChannelEntity, ChannelEntity, GuildChannelEntity, MessageEntity,
+            MessageRepository,
+        },
+        gateway::PresenceEntity,
+        guild::{EmojiEntity, GuildEntity, GuildRepository, MemberEntity, RoleEntity},
+        voice::VoiceStateEntity,
+    },
+    repository::{GetEntityFuture, ListEntitiesFuture, Repository},
+    Backend,
+};
+use futures_util::future::{self, FutureExt};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use twilight_model::id::{ChannelId, GuildId, UserId};
+
+/// View of the cache scoped to a guild.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+pub struct GuildCacheView<'a, T: Backend> {
+    cache: &'a Cache<T>,
+    guild_id: GuildId,
+}
+
+impl<'a, T: Backend> GuildCacheView<'a, T> {
+    pub(crate) const fn new(cache: &'a Cache<T>, guild_id: GuildId) -> Self {
+        Self { cache, guild_id }
+    }
+
+    /// Return the ID of the guild.
+    pub const fn id(&self) -> GuildId {
+        self.guild_id
+    }
+
+    /// Return an immutable reference to the cache.
+    pub const fn cache(&self) -> &'a Cache<T> {
+        self.cache
+    }
+
+    /// Get the guild.
+    pub fn get(&self) -> GetEntityFuture<'a, GuildEntity, T::Error> {
+        self.cache.guilds.get(self.guild_id)
+    }
+
+    /// Retrieve a stream of the guild's channels.
+    pub fn channels(&self) -> ListEntitiesFuture<'a, GuildChannelEntity, T::Error> {
+        self.cache.guilds.channels(self.guild_id)
+    }
+
+    /// Retrieve a stream of the guild's emojis.
+    pub fn emojis(&self) -> ListEntitiesFuture<'a, EmojiEntity, T::Error> {
+        self.cache.guilds.emojis(self.guild_id)
+    }
+
+    /// Get a member of the guild by their user ID.
+    pub fn member(&self, user_id: UserId) -> GetEntityFuture<'a, MemberEntity, T::Error> {
+        self.cache.members.get((self.guild_id, user_id))
+    }
+
+    /// Retrieve a stream of the guild's members.
+    pub fn members(&self) -> ListEntitiesFuture<'a, MemberEntity, T::Error> {
+        self.cache.guilds.members(self.guild_id)
+    }
+
+    /// Get the presence of a user in the guild.
+    pub fn presence(&self, user_id: UserId) -> GetEntityFuture<'a, PresenceEntity, T::Error> {
+        self.cache.presences.get((self.guild_id, user_id))
+    }
+
+    /// Retrieve a stream of the guild's roles.
+    pub fn roles(&self) -> ListEntitiesFuture<'a, RoleEntity, T::Error> {
+        self.cache.guilds.roles(self.guild_id)
+    }
+
+    /// Get the voice state of a user in the guild.
+    pub fn voice_state(&self, user_id: UserId) -> GetEntityFuture<'a, VoiceStateEntity, T::Error> {
+        self.cache.voice_states.get((self.guild_id, user_id))
+    }
+
+    /// Retrieve a stream of the voice states of the guild.
+    pub fn voice_states(&self) -> ListEntitiesFuture<'a, VoiceStateEntity, T::Error> {
+        self.cache.guilds.voice_states(self.guild_id)
+    }
+}
+
+impl<T: Backend> Clone for GuildCacheView<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Backend> Copy for GuildCacheView<'_, T> {}
+
+impl<T: Backend> Debug for GuildCacheView<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("GuildCacheView")
+            .field("guild_id", &self.guild_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// View of the cache scoped to a channel of any kind.
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [module-level documentation]: index.html
+pub struct ChannelCacheView<'a, T: Backend> {
+    cache: &'a Cache<T>,
+    channel: ChannelEntity,
+}
+
+impl<'a, T: Backend> ChannelCacheView<'a, T> {
+    /// Resolve the kind of a channel, returning a view of it if it's cached.
+    pub(crate) fn resolve(
+        cache: &'a Cache<T>,
+        channel_id: ChannelId,
+    ) -> GetEntityFuture<'a, Self, T::Error> {
+        Box::pin(async move {
+            let channel = if let Some(channel) = cache.text_channels.get(channel_id).await? {
+                ChannelEntity::Guild(GuildChannelEntity::Text(channel))
+            } else if let Some(channel) = cache.voice_channels.get(channel_id).await? {
+                ChannelEntity::Guild(GuildChannelEntity::Voice(channel))
+            } else if let Some(channel) = cache.category_channels.get(channel_id).await? {
+                ChannelEntity::Guild(GuildChannelEntity::Category(channel))
+            } else if let Some(channel) = cache.private_channels.get(channel_id).await? {
+                ChannelEntity::Private(channel)
+            } else if let Some(channel) = cache.groups.get(channel_id).await? {
+                ChannelEntity::Group(channel)
+            } else {
+                return Ok(None);
+            };
+
+            Ok(Some(Self { cache, channel }))
+        })
+    }
+
+    /// Return the ID of the channel.
+    pub fn id(&self) -> ChannelId {
+        match &self.channel {
+            ChannelEntity::Group(group) => group.id,
+            ChannelEntity::Guild(GuildChannelEntity::Category(channel)) => channel.id,
+            ChannelEntity::Guild(GuildChannelEntity::Text(channel)) => channel.id,
+            ChannelEntity::Guild(GuildChannelEntity::Voice(channel)) => channel.id,
+            ChannelEntity::Private(channel) => channel.id,
+        }
+    }
+
+    /// Return an immutable reference to the cache.
+    pub const fn cache(&self) -> &'a Cache<T> {
+        self.cache
+    }
+
+    /// Return an immutable reference to the channel as it was when the view
+    /// was created.
+    pub const fn entity(&self) -> &ChannelEntity {
+        &self.channel
+    }
+
+    /// Consume the view, returning the channel as it was when the view was
+    /// created.
+    pub fn into_entity(self) -> ChannelEntity {
+        self.channel
+    }
+
+    /// Return the ID of the guild of the channel, if it's a guild channel.
+    pub const fn guild_id(&self) -> Option<GuildId> {
+        match &self.channel {
+            ChannelEntity::Guild(GuildChannelEntity::Category(channel)) => channel.guild_id,
+            ChannelEntity::Guild(GuildChannelEntity::Text(channel)) => channel.guild_id,
+            ChannelEntity::Guild(GuildChannelEntity::Voice(channel)) => channel.guild_id,
+            ChannelEntity::Group(_) | ChannelEntity::Private(_) => None,
+        }
+    }
+
+    /// Get the guild of the channel, if it's a guild channel.
+    pub fn guild(&self) -> GetEntityFuture<'a, GuildEntity, T::Error> {
+        match self.guild_id() {
+            Some(guild_id) => self.cache.guilds.get(guild_id),
+            None => future::ok(None).boxed(),
+        }
+    }
+
+    /// Get the last message sent in the channel, if the channel is a text
+    /// channel, private channel, or group.
+    pub fn last_message(&self) -> GetEntityFuture<'a, MessageEntity, T::Error> {
+        let last_message_id = match &self.channel {
+            ChannelEntity::Group(group) => group.last_message_id,
+            ChannelEntity::Guild(GuildChannelEntity::Text(channel)) => channel.last_message_id,
+            ChannelEntity::Private(channel) => channel.last_message_id,
+            ChannelEntity::Guild(_) => None,
+        };
+
+        match last_message_id {
+            Some(message_id) => self.cache.messages.get(message_id),
+            None => future::ok(None).boxed(),
+        }
+    }
+
+    /// Retrieve a stream of the messages in the channel.
+    pub fn messages(&self) -> ListEntitiesFuture<'a, MessageEntity, T::Error> {
+        self.cache.messages.list_by_channel(self.id())
+    }
+
+    /// Get the category that the channel is in, if it's a text or voice
+    /// channel with a parent.
+    pub fn parent(&self) -> GetEntityFuture<'a, CategoryChannelEntity, T::Error> {
+        let parent_id = match &self.channel {
+            ChannelEntity::Guild(GuildChannelEntity::Text(channel)) => channel.parent_id,
+            ChannelEntity::Guild(GuildChannelEntity::Voice(channel)) => channel.parent_id,
+            _ => None,
+        };
+
+        match parent_id {
+            Some(parent_id) => self.cache.category_channels.get(parent_id),
+            None => future::ok(None).boxed(),
+        }
+    }
+}
+
+impl<T: Backend> Clone for ChannelCacheView<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            cache: self.cache,
+            channel: self.channel.clone(),
+        }
+    }
+}
+
+impl<T: Backend> Debug for ChannelCacheView<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ChannelCacheView")
+            .field("channel", &self.channel)
+            .finish_non_exhaustive()
+    }
+}
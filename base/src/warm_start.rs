@@ -0,0 +1,180 @@
+//! Warm starts of a cache from dumps of guild payloads.
+//!
+//! Caches of bots in many guilds take a while to fill from a live gateway,
+//! which slows down testing against realistic data. Payloads of `GUILD_CREATE`
+//! events, such as the raw gateway payloads of `ShardPayload` events written to
+//! files, can instead be loaded with [`load_guild_dumps`], which processes
+//! them through the cache just like events received from the gateway.
+//!
+//! Each dump file contains one payload, either a whole gateway payload with
+//! the event's name and data or only the guild itself. Gateway payloads of
+//! other events are skipped.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::warm_start;
+//! use twilight_cache_inmemory::InMemoryCache;
+//!
+//! let cache = InMemoryCache::new();
+//! let loaded = warm_start::load_guild_dumps(&cache, "dumps").await?;
+//!
+//! println!("loaded {} guilds", loaded);
+//! # Ok(()) }
+//! ```
+//!
+//! [`load_guild_dumps`]: fn.load_guild_dumps.html
+
+use super::{
+    cache::{Cache, ProcessError},
+    Backend,
+};
+use serde_json::Value;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+use twilight_model::gateway::{event::Event, payload::GuildCreate};
+
+/// Error returned when loading guild dumps fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WarmStartError<E> {
+    /// Deserializing a dump file failed.
+    Deserialization {
+        /// Path to the dump file.
+        path: PathBuf,
+        /// Reason for the error.
+        source: serde_json::Error,
+    },
+    /// Reading the directory or a dump file failed.
+    Io {
+        /// Path to the directory or dump file.
+        path: PathBuf,
+        /// Reason for the error.
+        source: IoError,
+    },
+    /// Processing a guild failed.
+    Process {
+        /// Path to the dump file.
+        path: PathBuf,
+        /// Reason for the error.
+        source: ProcessError<E>,
+    },
+}
+
+impl<E> Display for WarmStartError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let (message, path) = match self {
+            Self::Deserialization { path, .. } => ("deserializing guild dump ", path),
+            Self::Io { path, .. } => ("reading guild dumps at ", path),
+            Self::Process { path, .. } => ("processing guild dump ", path),
+        };
+
+        f.write_str(message)?;
+        Display::fmt(&path.display(), f)?;
+
+        f.write_str(" failed")
+    }
+}
+
+impl<E: Error + 'static> Error for WarmStartError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Deserialization { source, .. } => Some(source),
+            Self::Io { source, .. } => Some(source),
+            Self::Process { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Parse the payload of a `GUILD_CREATE` event.
+///
+/// The payload may be a whole gateway payload, in which case `None` is
+/// returned if it's for another event, or only the guild.
+///
+/// # Errors
+///
+/// Returns a `serde_json` error if the payload isn't a valid guild or gateway
+/// payload.
+pub fn parse_guild_dump(payload: &[u8]) -> Result<Option<GuildCreate>, serde_json::Error> {
+    let mut value = serde_json::from_slice::<Value>(payload)?;
+
+    if let Some(name) = value.get("t") {
+        if name.as_str() != Some("GUILD_CREATE") {
+            return Ok(None);
+        }
+
+        value = value.get_mut("d").map(Value::take).unwrap_or_default();
+    }
+
+    serde_json::from_value(value).map(Some)
+}
+
+/// Process the `GUILD_CREATE` payloads of the JSON files in a directory,
+/// returning the number of guilds that were loaded.
+///
+/// Files are processed in the order of their names, and files without a
+/// `json` extension are ignored. Refer to [`parse_guild_dump`] for the
+/// accepted payloads.
+///
+/// # Errors
+///
+/// Returns [`WarmStartError::Io`] if reading the directory or a file fails.
+///
+/// Returns [`WarmStartError::Deserialization`] if a file isn't a valid
+/// payload.
+///
+/// Returns [`WarmStartError::Process`] if processing a guild fails.
+///
+/// [`parse_guild_dump`]: fn.parse_guild_dump.html
+/// [`WarmStartError::Deserialization`]: enum.WarmStartError.html#variant.Deserialization
+/// [`WarmStartError::Io`]: enum.WarmStartError.html#variant.Io
+/// [`WarmStartError::Process`]: enum.WarmStartError.html#variant.Process
+pub async fn load_guild_dumps<T: Backend>(
+    cache: &Cache<T>,
+    dir: impl AsRef<Path>,
+) -> Result<usize, WarmStartError<T::Error>> {
+    let dir = dir.as_ref();
+    let io_error = |path: &Path| {
+        let path = path.to_owned();
+
+        move |source| WarmStartError::<T::Error>::Io { path, source }
+    };
+
+    let mut paths = fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(io_error(dir))?;
+    paths.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+
+    let mut loaded = 0;
+
+    for path in paths {
+        let payload = fs::read(&path).map_err(io_error(&path))?;
+
+        let guild = match parse_guild_dump(&payload) {
+            Ok(Some(guild)) => guild,
+            Ok(None) => continue,
+            Err(source) => return Err(WarmStartError::Deserialization { path, source }),
+        };
+
+        cache
+            .process(&Event::GuildCreate(Box::new(guild)))
+            .await
+            .map_err(|source| WarmStartError::Process { path, source })?;
+
+        loaded += 1;
+    }
+
+    Ok(loaded)
+}
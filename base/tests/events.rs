@@ -0,0 +1,496 @@
+//! Property tests processing random sequences of guild events.
+//!
+//! Each sequence of events is also applied to a simple model of what should
+//! be cached. After every event, the cache's entities and its relation
+//! indexes are checked against the model so that entities orphaned by a
+//! deletion or indexes that drift from the entities are caught.
+
+use futures_util::stream::TryStreamExt;
+use proptest::{collection, prelude::*};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+};
+use tokio::runtime::Runtime;
+use twilight_cache::{
+    entity::guild::{GuildRepository, RoleRepository},
+    Repository,
+};
+use twilight_cache_inmemory::{InMemoryBackendError, InMemoryCache};
+use twilight_model::{
+    channel::{Channel, ChannelType, GuildChannel, TextChannel},
+    gateway::{
+        event::Event,
+        payload::{
+            ChannelCreate, ChannelDelete, ChannelUpdate, GuildCreate, GuildDelete, MemberAdd,
+            MemberRemove, MemberUpdate, RoleCreate, RoleDelete, RoleUpdate,
+        },
+    },
+    guild::{
+        member::Member, DefaultMessageNotificationLevel, ExplicitContentFilter, Guild, MfaLevel,
+        Permissions, PremiumTier, Role, SystemChannelFlags, VerificationLevel,
+    },
+    id::{ChannelId, GuildId, RoleId, UserId},
+    user::User,
+};
+
+const GUILDS: u64 = 3;
+
+/// Operation on a guild generated by the strategy.
+///
+/// Role and channel IDs are scoped to their guild, so a role or channel never
+/// moves between guilds.
+#[derive(Clone, Debug)]
+enum Op {
+    GuildCreate {
+        guild: u64,
+        channels: BTreeSet<u64>,
+        members: BTreeSet<u64>,
+        roles: BTreeSet<u64>,
+    },
+    GuildDelete {
+        guild: u64,
+        unavailable: bool,
+    },
+    ChannelUpsert {
+        guild: u64,
+        channel: u64,
+        create: bool,
+    },
+    ChannelDelete {
+        guild: u64,
+        channel: u64,
+    },
+    MemberUpsert {
+        guild: u64,
+        user: u64,
+        add: bool,
+    },
+    MemberRemove {
+        guild: u64,
+        user: u64,
+    },
+    RoleUpsert {
+        guild: u64,
+        role: u64,
+        create: bool,
+    },
+    RoleDelete {
+        guild: u64,
+        role: u64,
+    },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let guild = 1..=GUILDS;
+    let scoped = || 1..=4u64;
+    let ids = || collection::btree_set(scoped(), 0..=4);
+
+    prop_oneof![
+        (guild.clone(), ids(), ids(), ids()).prop_map(|(guild, channels, members, roles)| {
+            Op::GuildCreate {
+                guild,
+                channels,
+                members,
+                roles,
+            }
+        }),
+        (guild.clone(), any::<bool>())
+            .prop_map(|(guild, unavailable)| Op::GuildDelete { guild, unavailable }),
+        (guild.clone(), scoped(), any::<bool>()).prop_map(|(guild, channel, create)| {
+            Op::ChannelUpsert {
+                guild,
+                channel,
+                create,
+            }
+        }),
+        (guild.clone(), scoped()).prop_map(|(guild, channel)| Op::ChannelDelete { guild, channel }),
+        (guild.clone(), scoped(), any::<bool>()).prop_map(|(guild, user, add)| Op::MemberUpsert {
+            guild,
+            user,
+            add
+        }),
+        (guild.clone(), scoped()).prop_map(|(guild, user)| Op::MemberRemove { guild, user }),
+        (guild.clone(), scoped(), any::<bool>()).prop_map(|(guild, role, create)| Op::RoleUpsert {
+            guild,
+            role,
+            create
+        }),
+        (guild, scoped()).prop_map(|(guild, role)| Op::RoleDelete { guild, role }),
+    ]
+}
+
+fn channel_id(guild: u64, channel: u64) -> ChannelId {
+    ChannelId(guild * 100 + channel)
+}
+
+fn role_id(guild: u64, role: u64) -> RoleId {
+    RoleId(guild * 100 + role)
+}
+
+/// What the cache is expected to contain.
+#[derive(Debug, Default)]
+struct Model {
+    channels: BTreeMap<ChannelId, GuildId>,
+    guilds: BTreeSet<GuildId>,
+    members: BTreeSet<(GuildId, UserId)>,
+    roles: BTreeMap<RoleId, GuildId>,
+}
+
+impl Model {
+    fn apply(&mut self, op: &Op) {
+        match *op {
+            Op::GuildCreate {
+                guild,
+                ref channels,
+                ref members,
+                ref roles,
+            } => {
+                let guild_id = GuildId(guild);
+
+                self.guilds.insert(guild_id);
+                self.channels.extend(
+                    channels
+                        .iter()
+                        .map(|channel| (channel_id(guild, *channel), guild_id)),
+                );
+                self.members
+                    .extend(members.iter().map(|user| (guild_id, UserId(*user))));
+                self.roles
+                    .extend(roles.iter().map(|role| (role_id(guild, *role), guild_id)));
+            }
+            Op::GuildDelete {
+                unavailable: true, ..
+            } => {}
+            Op::GuildDelete { guild, .. } => {
+                let guild_id = GuildId(guild);
+
+                self.guilds.remove(&guild_id);
+                self.channels.retain(|_, id| *id != guild_id);
+                self.members.retain(|(id, _)| *id != guild_id);
+                self.roles.retain(|_, id| *id != guild_id);
+            }
+            Op::ChannelUpsert { guild, channel, .. } => {
+                self.channels
+                    .insert(channel_id(guild, channel), GuildId(guild));
+            }
+            Op::ChannelDelete { guild, channel } => {
+                self.channels.remove(&channel_id(guild, channel));
+            }
+            Op::MemberUpsert { guild, user, .. } => {
+                self.members.insert((GuildId(guild), UserId(user)));
+            }
+            Op::MemberRemove { guild, user } => {
+                self.members.remove(&(GuildId(guild), UserId(user)));
+            }
+            Op::RoleUpsert { guild, role, .. } => {
+                self.roles.insert(role_id(guild, role), GuildId(guild));
+            }
+            Op::RoleDelete { guild, role } => {
+                self.roles.remove(&role_id(guild, role));
+            }
+        }
+    }
+}
+
+fn user(id: u64) -> User {
+    User {
+        avatar: None,
+        bot: false,
+        discriminator: format!("{id:04}"),
+        email: None,
+        flags: None,
+        id: UserId(id),
+        locale: None,
+        mfa_enabled: None,
+        name: format!("user{id}"),
+        premium_type: None,
+        public_flags: None,
+        system: None,
+        verified: None,
+    }
+}
+
+fn member(guild: u64, id: u64) -> Member {
+    Member {
+        deaf: false,
+        guild_id: GuildId(guild),
+        hoisted_role: None,
+        joined_at: Some(String::from("2020-01-01T00:00:00.000000+00:00")),
+        mute: false,
+        nick: None,
+        pending: false,
+        premium_since: None,
+        roles: Vec::new(),
+        user: user(id),
+    }
+}
+
+fn role(guild: u64, id: u64) -> Role {
+    Role {
+        color: 0,
+        hoist: false,
+        id: role_id(guild, id),
+        managed: false,
+        mentionable: false,
+        name: format!("role{id}"),
+        permissions: Permissions::empty(),
+        position: i64::try_from(id).unwrap(),
+        tags: None,
+    }
+}
+
+fn text_channel(guild: u64, id: u64) -> TextChannel {
+    TextChannel {
+        guild_id: Some(GuildId(guild)),
+        id: channel_id(guild, id),
+        kind: ChannelType::GuildText,
+        last_message_id: None,
+        last_pin_timestamp: None,
+        name: format!("channel{id}"),
+        nsfw: false,
+        permission_overwrites: Vec::new(),
+        parent_id: None,
+        position: i64::try_from(id).unwrap(),
+        rate_limit_per_user: None,
+        topic: None,
+    }
+}
+
+fn guild(
+    id: u64,
+    channels: &BTreeSet<u64>,
+    members: &BTreeSet<u64>,
+    roles: &BTreeSet<u64>,
+) -> Guild {
+    Guild {
+        afk_channel_id: None,
+        afk_timeout: 0,
+        application_id: None,
+        approximate_member_count: None,
+        approximate_presence_count: None,
+        banner: None,
+        channels: channels
+            .iter()
+            .map(|channel| GuildChannel::Text(text_channel(id, *channel)))
+            .collect(),
+        default_message_notifications: DefaultMessageNotificationLevel::All,
+        description: None,
+        discovery_splash: None,
+        emojis: Vec::new(),
+        explicit_content_filter: ExplicitContentFilter::None,
+        features: Vec::new(),
+        icon: None,
+        id: GuildId(id),
+        joined_at: None,
+        large: false,
+        lazy: None,
+        max_members: None,
+        max_presences: None,
+        max_video_channel_users: None,
+        member_count: None,
+        members: members.iter().map(|user| member(id, *user)).collect(),
+        mfa_level: MfaLevel::None,
+        name: format!("guild{id}"),
+        nsfw: false,
+        owner_id: UserId(1),
+        owner: None,
+        permissions: None,
+        preferred_locale: String::from("en-US"),
+        premium_subscription_count: None,
+        premium_tier: PremiumTier::None,
+        presences: Vec::new(),
+        region: String::from("us-east"),
+        roles: roles.iter().map(|role| self::role(id, *role)).collect(),
+        rules_channel_id: None,
+        splash: None,
+        system_channel_flags: SystemChannelFlags::empty(),
+        system_channel_id: None,
+        unavailable: false,
+        vanity_url_code: None,
+        verification_level: VerificationLevel::None,
+        voice_states: Vec::new(),
+        widget_channel_id: None,
+        widget_enabled: None,
+    }
+}
+
+fn event(op: &Op) -> Event {
+    match *op {
+        Op::GuildCreate {
+            guild,
+            ref channels,
+            ref members,
+            ref roles,
+        } => Event::GuildCreate(Box::new(GuildCreate(self::guild(
+            guild, channels, members, roles,
+        )))),
+        Op::GuildDelete { guild, unavailable } => Event::GuildDelete(Box::new(GuildDelete {
+            id: GuildId(guild),
+            unavailable,
+        })),
+        Op::ChannelUpsert {
+            guild,
+            channel,
+            create,
+        } => {
+            let channel = Channel::Guild(GuildChannel::Text(text_channel(guild, channel)));
+
+            if create {
+                Event::ChannelCreate(ChannelCreate(channel))
+            } else {
+                Event::ChannelUpdate(ChannelUpdate(channel))
+            }
+        }
+        Op::ChannelDelete { guild, channel } => Event::ChannelDelete(ChannelDelete(
+            Channel::Guild(GuildChannel::Text(text_channel(guild, channel))),
+        )),
+        Op::MemberUpsert {
+            guild,
+            user,
+            add: true,
+        } => Event::MemberAdd(Box::new(MemberAdd(member(guild, user)))),
+        Op::MemberUpsert { guild, user, .. } => Event::MemberUpdate(Box::new(MemberUpdate {
+            guild_id: GuildId(guild),
+            deaf: None,
+            joined_at: String::from("2020-01-01T00:00:00.000000+00:00"),
+            mute: None,
+            nick: Some(format!("nick{user}")),
+            pending: false,
+            premium_since: None,
+            roles: Vec::new(),
+            user: self::user(user),
+        })),
+        Op::MemberRemove { guild, user } => Event::MemberRemove(MemberRemove {
+            guild_id: GuildId(guild),
+            user: self::user(user),
+        }),
+        Op::RoleUpsert {
+            guild,
+            role,
+            create,
+        } => {
+            let guild_id = GuildId(guild);
+            let role = self::role(guild, role);
+
+            if create {
+                Event::RoleCreate(RoleCreate { guild_id, role })
+            } else {
+                Event::RoleUpdate(RoleUpdate { guild_id, role })
+            }
+        }
+        Op::RoleDelete { guild, role } => Event::RoleDelete(RoleDelete {
+            guild_id: GuildId(guild),
+            role_id: role_id(guild, role),
+        }),
+    }
+}
+
+/// Check that the cache's entities and relation indexes match the model.
+async fn check(cache: &InMemoryCache, model: &Model) -> Result<(), InMemoryBackendError> {
+    let guilds = cache
+        .guilds
+        .list()
+        .await?
+        .map_ok(|guild| guild.id)
+        .try_collect::<BTreeSet<_>>()
+        .await?;
+    assert_eq!(model.guilds, guilds, "cached guilds");
+
+    let channels = cache
+        .text_channels
+        .list()
+        .await?
+        .map_ok(|channel| (channel.id, channel.guild_id.unwrap()))
+        .try_collect::<BTreeMap<_, _>>()
+        .await?;
+    assert_eq!(model.channels, channels, "cached channels");
+
+    let members = cache
+        .members
+        .list()
+        .await?
+        .map_ok(|member| (member.guild_id, member.user_id))
+        .try_collect::<BTreeSet<_>>()
+        .await?;
+    assert_eq!(model.members, members, "cached members");
+
+    let roles = cache
+        .roles
+        .list()
+        .await?
+        .map_ok(|role| (role.id, role.guild_id))
+        .try_collect::<BTreeMap<_, _>>()
+        .await?;
+    assert_eq!(model.roles, roles, "cached roles");
+
+    for guild_id in (1..=GUILDS).map(GuildId) {
+        let expected = model
+            .channels
+            .iter()
+            .filter(|(_, id)| **id == guild_id)
+            .map(|(channel_id, _)| *channel_id)
+            .collect::<BTreeSet<_>>();
+        let indexed = cache
+            .guilds
+            .channel_ids(guild_id)
+            .await?
+            .try_collect::<BTreeSet<_>>()
+            .await?;
+        assert_eq!(expected, indexed, "channel index of {guild_id}");
+
+        let expected = model
+            .members
+            .iter()
+            .filter(|(id, _)| *id == guild_id)
+            .map(|(_, user_id)| *user_id)
+            .collect::<BTreeSet<_>>();
+        let indexed = cache
+            .guilds
+            .member_ids(guild_id)
+            .await?
+            .try_collect::<BTreeSet<_>>()
+            .await?;
+        assert_eq!(expected, indexed, "member index of {guild_id}");
+
+        let expected = model
+            .roles
+            .iter()
+            .filter(|(_, id)| **id == guild_id)
+            .map(|(role_id, _)| *role_id)
+            .collect::<BTreeSet<_>>();
+        let indexed = cache
+            .guilds
+            .role_ids(guild_id)
+            .await?
+            .try_collect::<BTreeSet<_>>()
+            .await?;
+        assert_eq!(expected, indexed, "role index of {guild_id}");
+    }
+
+    for (role_id, guild_id) in &model.roles {
+        let guild = cache.roles.guild(*role_id).await?;
+        let expected = model.guilds.get(guild_id).copied();
+        assert_eq!(expected, guild.map(|guild| guild.id), "guild of {role_id}");
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn events_keep_cache_consistent(ops in collection::vec(op(), 1..40)) {
+        let mut runtime = Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let cache = InMemoryCache::new();
+            let mut model = Model::default();
+
+            for op in &ops {
+                cache.process(&event(op)).await.unwrap();
+                model.apply(op);
+                check(&cache, &model).await.unwrap();
+            }
+        });
+    }
+}
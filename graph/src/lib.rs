@@ -0,0 +1,1008 @@
+//! # twilight-cache-graph
+//!
+//! `twilight-cache-graph` is a backend for `twilight-cache` storing entities
+//! as the nodes of a graph, with the relations between them as edges.
+//!
+//! Edges aren't hard-coded per entity: when an entity is upserted, the fields
+//! described by its [schema] are read and an edge is added from the entity to
+//! every ID they refer to, such as from a member to its guild and user, or from
+//! a message to its author, channel, and mentioned users. Relations like the
+//! members of a guild or the guilds of a user are then answered by following
+//! edges backwards, and so are queries that the repositories don't offer, such
+//! as the [mutual guilds] of two users or the [authors mentioning] a user.
+//!
+//! The graph is held in the memory of the process.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{entity::guild::MemberEntity, Repository};
+//! use twilight_cache_graph::GraphCache;
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let cache = GraphCache::new();
+//!
+//! for guild_id in &[GuildId(1), GuildId(2), GuildId(3)] {
+//!     cache.members.upsert(MemberEntity::builder(*guild_id, UserId(4)).build()).await?;
+//! }
+//!
+//! cache.members.upsert(MemberEntity::builder(GuildId(2), UserId(5)).build()).await?;
+//!
+//! let mutual = cache.backend().mutual_guilds(UserId(4), UserId(5));
+//! assert_eq!(vec![GuildId(2)], mutual.into_iter().collect::<Vec<_>>());
+//! # Ok(()) }
+//! ```
+//!
+//! [authors mentioning]: struct.GraphBackend.html#method.mentioned_by
+//! [mutual guilds]: struct.GraphBackend.html#method.mutual_guilds
+//! [schema]: ../twilight_cache/schema/index.html
+
+#![deny(
+    clippy::all,
+    clippy::pedantic,
+    future_incompatible,
+    nonstandard_style,
+    rust_2018_idioms,
+    unused,
+    warnings
+)]
+#![allow(
+    clippy::doc_markdown,
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate
+)]
+
+use dashmap::DashMap;
+use futures_util::{
+    future::{self, FutureExt},
+    stream::{self, StreamExt},
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashSet},
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use twilight_cache::{
+    any::{AnyEntity, EntityKind, RawId},
+    entity::{
+        channel::{
+            AttachmentEntity, AttachmentRepository, CategoryChannelEntity,
+            CategoryChannelRepository, GroupEntity, GroupRepository, GuildChannelEntity,
+            MessageEntity, MessageRepository, PrivateChannelEntity, PrivateChannelRepository,
+            TextChannelEntity, TextChannelRepository, VoiceChannelEntity, VoiceChannelRepository,
+        },
+        gateway::{PresenceEntity, PresenceRepository},
+        guild::{
+            EmojiEntity, EmojiRepository, GuildEntity, GuildPreviewEntity, GuildPreviewRepository,
+            GuildRepository, MemberEntity, MemberRepository, RoleEntity, RoleRepository,
+        },
+        user::{CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository},
+        voice::{
+            VoiceRegionEntity, VoiceRegionId, VoiceRegionRepository, VoiceStateEntity,
+            VoiceStateRepository,
+        },
+        Entity,
+    },
+    repository::{
+        GetEntityFuture, ListEntitiesFuture, ListEntityIdsFuture, RemoveEntityFuture,
+        SingleEntityRepository, UpsertEntityFuture,
+    },
+    schema::{self, IdType},
+    Backend, Cache, Repository,
+};
+use twilight_model::id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+/// Alias over `twilight_cache::Cache` which uses the [`GraphBackend`].
+///
+/// [`GraphBackend`]: struct.GraphBackend.html
+pub type GraphCache = Cache<GraphBackend>;
+
+/// Error returned from backend operations.
+///
+/// This error type has no variants and will never occur. It currently only
+/// exists to satisfy the constraints of cache repositories.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct GraphBackendError;
+
+impl Display for GraphBackendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("this can't be constructed")
+    }
+}
+
+impl Error for GraphBackendError {}
+
+/// Node of the graph, identifying an entity by its kind and raw ID.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct NodeId {
+    /// Kind of the entity.
+    pub kind: EntityKind,
+    /// Raw ID of the entity.
+    pub id: RawId,
+}
+
+/// Edge from a node to an ID, named after the entity's field referring to
+/// the ID.
+type Edge = (&'static str, IdType, u64);
+
+/// ID type that can be turned into a raw ID.
+trait IntoRawId {
+    fn into_raw(self) -> RawId;
+}
+
+macro_rules! single_ids {
+    ($($id:ident),*) => {
+        $(
+            impl IntoRawId for $id {
+                fn into_raw(self) -> RawId {
+                    RawId::Single(self.0)
+                }
+            }
+        )*
+    };
+}
+
+single_ids!(
+    AttachmentId,
+    ChannelId,
+    EmojiId,
+    GuildId,
+    MessageId,
+    RoleId,
+    UserId,
+    VoiceRegionId
+);
+
+impl IntoRawId for (GuildId, UserId) {
+    fn into_raw(self) -> RawId {
+        RawId::Pair((self.0).0, (self.1).0)
+    }
+}
+
+/// Entity stored as a node of the graph backend.
+pub trait GraphEntity: Clone + Entity + Into<AnyEntity> + Serialize + 'static {
+    /// Kind of the entity.
+    const KIND: EntityKind;
+
+    /// Return the raw ID of the node storing an entity.
+    fn raw_id(id: Self::Id) -> RawId;
+
+    /// Return the entity stored in a node, if it's of this kind.
+    fn from_any(entity: &AnyEntity) -> Option<&Self>;
+}
+
+macro_rules! graph_entities {
+    ($($entity:ident => $variant:ident),* $(,)?) => {
+        $(
+            impl GraphEntity for $entity {
+                const KIND: EntityKind = EntityKind::$variant;
+
+                fn raw_id(id: Self::Id) -> RawId {
+                    id.into_raw()
+                }
+
+                fn from_any(entity: &AnyEntity) -> Option<&Self> {
+                    match entity {
+                        AnyEntity::$variant(entity) => Some(entity),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+graph_entities! {
+    AttachmentEntity => Attachment,
+    CategoryChannelEntity => CategoryChannel,
+    EmojiEntity => Emoji,
+    GroupEntity => Group,
+    GuildEntity => Guild,
+    GuildPreviewEntity => GuildPreview,
+    MemberEntity => Member,
+    MessageEntity => Message,
+    PresenceEntity => Presence,
+    PrivateChannelEntity => PrivateChannel,
+    RoleEntity => Role,
+    TextChannelEntity => TextChannel,
+    UserEntity => User,
+    VoiceChannelEntity => VoiceChannel,
+    VoiceRegionEntity => VoiceRegion,
+    VoiceStateEntity => VoiceState,
+}
+
+/// Return the edges from an entity to the IDs referred to by the fields
+/// described by its schema.
+fn edges<T: GraphEntity>(entity: &T) -> Vec<Edge> {
+    // serializing entities to values can't fail
+    let value = serde_json::to_value(entity).unwrap_or_default();
+    let mut edges = Vec::new();
+
+    for relation in schema::schema().entity(T::KIND).relations {
+        let ids: Vec<u64> = match value.get(relation.field) {
+            Some(Value::Array(ids)) => ids.iter().filter_map(raw_id).collect(),
+            Some(id) => raw_id(id).into_iter().collect(),
+            None => Vec::new(),
+        };
+
+        edges.extend(ids.into_iter().map(|id| (relation.field, relation.id, id)));
+    }
+
+    edges
+}
+
+/// Return the raw ID in a serialized ID, which may be a string.
+fn raw_id(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str()?.parse().ok())
+}
+
+/// Return a key ordering raw IDs.
+fn sort_key(id: RawId) -> (u64, u64) {
+    match id {
+        RawId::Single(id) => (id, 0),
+        RawId::Pair(first, second) => (first, second),
+    }
+}
+
+/// Create a future resolving to a stream over a list of items.
+fn stream<'a, T: Send + 'a>(items: Vec<T>) -> ListEntitiesFuture<'a, T, GraphBackendError> {
+    future::ok(stream::iter(items.into_iter().map(Ok)).boxed()).boxed()
+}
+
+#[derive(Debug, Default)]
+struct GraphBackendRef {
+    current_user: Mutex<Option<CurrentUserEntity>>,
+    edges: DashMap<NodeId, Vec<Edge>>,
+    nodes: DashMap<NodeId, AnyEntity>,
+    referrers: DashMap<(IdType, u64), HashSet<(NodeId, &'static str)>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GraphRepository<T>(GraphBackend, PhantomData<T>);
+
+impl<T> GraphRepository<T> {
+    fn new(backend: GraphBackend) -> Self {
+        Self(backend, PhantomData)
+    }
+}
+
+impl<T: GraphEntity> Repository<T, GraphBackend> for GraphRepository<T> {
+    fn backend(&self) -> &GraphBackend {
+        &self.0
+    }
+
+    fn get(&self, entity_id: T::Id) -> GetEntityFuture<'_, T, GraphBackendError> {
+        future::ok(self.0.node(T::raw_id(entity_id))).boxed()
+    }
+
+    fn list(&self) -> ListEntitiesFuture<'_, T, GraphBackendError> {
+        let entities = (self.0).0.nodes.iter().filter_map(|node| {
+            if node.key().kind == T::KIND {
+                T::from_any(node.value()).cloned()
+            } else {
+                None
+            }
+        });
+
+        stream(entities.collect())
+    }
+
+    fn remove(&self, entity_id: T::Id) -> RemoveEntityFuture<'_, GraphBackendError> {
+        self.0.remove(NodeId {
+            kind: T::KIND,
+            id: T::raw_id(entity_id),
+        });
+
+        future::ok(()).boxed()
+    }
+
+    fn upsert(&self, entity: T) -> UpsertEntityFuture<'_, GraphBackendError> {
+        self.0.insert(entity);
+
+        future::ok(()).boxed()
+    }
+}
+
+impl SingleEntityRepository<CurrentUserEntity, GraphBackend>
+    for GraphRepository<CurrentUserEntity>
+{
+    fn backend(&self) -> &GraphBackend {
+        &self.0
+    }
+
+    fn get(&self) -> GetEntityFuture<'_, CurrentUserEntity, GraphBackendError> {
+        future::ok(self.0.lock_current_user().clone()).boxed()
+    }
+
+    fn remove(&self) -> RemoveEntityFuture<'_, GraphBackendError> {
+        *self.0.lock_current_user() = None;
+
+        future::ok(()).boxed()
+    }
+
+    fn upsert(&self, entity: CurrentUserEntity) -> UpsertEntityFuture<'_, GraphBackendError> {
+        *self.0.lock_current_user() = Some(entity);
+
+        future::ok(()).boxed()
+    }
+}
+
+impl AttachmentRepository<GraphBackend> for GraphRepository<AttachmentEntity> {}
+
+impl CategoryChannelRepository<GraphBackend> for GraphRepository<CategoryChannelEntity> {}
+
+impl CurrentUserRepository<GraphBackend> for GraphRepository<CurrentUserEntity> {
+    fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, GraphBackendError> {
+        let user_id = self.0.lock_current_user().as_ref().map(|user| user.id);
+
+        stream(user_id.map_or_else(Vec::new, |id| self.0.user_guild_ids(id)))
+    }
+}
+
+impl EmojiRepository<GraphBackend> for GraphRepository<EmojiEntity> {}
+
+impl GroupRepository<GraphBackend> for GraphRepository<GroupEntity> {}
+
+impl GuildPreviewRepository<GraphBackend> for GraphRepository<GuildPreviewEntity> {}
+
+impl GuildRepository<GraphBackend> for GraphRepository<GuildEntity> {
+    fn channel_ids(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntityIdsFuture<'_, ChannelId, GraphBackendError> {
+        let mut channel_ids = Vec::new();
+
+        for kind in &[
+            EntityKind::CategoryChannel,
+            EntityKind::TextChannel,
+            EntityKind::VoiceChannel,
+        ] {
+            let ids = self.0.guild_referrers(guild_id, *kind);
+            channel_ids.extend(ids.into_iter().map(|(id, _)| ChannelId(id)));
+        }
+
+        channel_ids.sort_unstable();
+
+        stream(channel_ids)
+    }
+
+    fn channels(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, GuildChannelEntity, GraphBackendError> {
+        let categories = self.0.guild_entities(guild_id);
+        let texts = self.0.guild_entities(guild_id);
+        let voices = self.0.guild_entities(guild_id);
+
+        stream(
+            categories
+                .into_iter()
+                .map(GuildChannelEntity::Category)
+                .chain(texts.into_iter().map(GuildChannelEntity::Text))
+                .chain(voices.into_iter().map(GuildChannelEntity::Voice))
+                .collect(),
+        )
+    }
+
+    fn emoji_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, EmojiId, GraphBackendError> {
+        let ids = self.0.guild_referrers(guild_id, EntityKind::Emoji);
+
+        stream(ids.into_iter().map(|(id, _)| EmojiId(id)).collect())
+    }
+
+    fn emojis(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, EmojiEntity, GraphBackendError> {
+        stream(self.0.guild_entities(guild_id))
+    }
+
+    fn member_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, GraphBackendError> {
+        let ids = self.0.guild_referrers(guild_id, EntityKind::Member);
+
+        stream(ids.into_iter().map(|(_, id)| UserId(id)).collect())
+    }
+
+    fn members(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, MemberEntity, GraphBackendError> {
+        stream(self.0.guild_entities(guild_id))
+    }
+
+    fn presence_ids(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntityIdsFuture<'_, UserId, GraphBackendError> {
+        let ids = self.0.guild_referrers(guild_id, EntityKind::Presence);
+
+        stream(ids.into_iter().map(|(_, id)| UserId(id)).collect())
+    }
+
+    fn presences(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, PresenceEntity, GraphBackendError> {
+        stream(self.0.guild_entities(guild_id))
+    }
+
+    fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, GraphBackendError> {
+        let ids = self.0.guild_referrers(guild_id, EntityKind::Role);
+
+        stream(ids.into_iter().map(|(id, _)| RoleId(id)).collect())
+    }
+
+    fn roles(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, RoleEntity, GraphBackendError> {
+        stream(self.0.guild_entities(guild_id))
+    }
+
+    fn voice_state_ids(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntityIdsFuture<'_, UserId, GraphBackendError> {
+        let ids = self.0.guild_referrers(guild_id, EntityKind::VoiceState);
+
+        stream(ids.into_iter().map(|(_, id)| UserId(id)).collect())
+    }
+
+    fn voice_states(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceStateEntity, GraphBackendError> {
+        stream(self.0.guild_entities(guild_id))
+    }
+}
+
+impl MemberRepository<GraphBackend> for GraphRepository<MemberEntity> {}
+
+impl MessageRepository<GraphBackend> for GraphRepository<MessageEntity> {
+    fn attachments(
+        &self,
+        message_id: MessageId,
+    ) -> ListEntitiesFuture<'_, AttachmentEntity, GraphBackendError> {
+        stream(
+            self.0
+                .referring_entities(IdType::Message, message_id.0, "message_id"),
+        )
+    }
+
+    fn list_by_channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> ListEntitiesFuture<'_, MessageEntity, GraphBackendError> {
+        stream(
+            self.0
+                .referring_entities(IdType::Channel, channel_id.0, "channel_id"),
+        )
+    }
+}
+
+impl PresenceRepository<GraphBackend> for GraphRepository<PresenceEntity> {}
+
+impl PrivateChannelRepository<GraphBackend> for GraphRepository<PrivateChannelEntity> {}
+
+impl RoleRepository<GraphBackend> for GraphRepository<RoleEntity> {}
+
+impl TextChannelRepository<GraphBackend> for GraphRepository<TextChannelEntity> {}
+
+impl UserRepository<GraphBackend> for GraphRepository<UserEntity> {
+    fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, GraphBackendError> {
+        stream(self.0.user_guild_ids(user_id))
+    }
+}
+
+impl VoiceChannelRepository<GraphBackend> for GraphRepository<VoiceChannelEntity> {}
+
+impl VoiceRegionRepository<GraphBackend> for GraphRepository<VoiceRegionEntity> {}
+
+impl VoiceStateRepository<GraphBackend> for GraphRepository<VoiceStateEntity> {}
+
+/// `twilight-cache` backend storing entities as the nodes of a graph.
+///
+/// Refer to the [crate-level documentation] for more information.
+///
+/// [crate-level documentation]: index.html
+#[derive(Clone, Debug, Default)]
+pub struct GraphBackend(Arc<GraphBackendRef>);
+
+impl GraphBackend {
+    /// Create a new, empty graph backend.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the nodes with a field referring to an ID, along with the name
+    /// of the field.
+    ///
+    /// Channel IDs are shared by every kind of channel, so the nodes
+    /// referring to a channel ID may refer to a channel of any kind.
+    pub fn referrers(&self, id_type: IdType, id: u64) -> Vec<(NodeId, &'static str)> {
+        let mut referrers = self
+            .0
+            .referrers
+            .get(&(id_type, id))
+            .map(|referrers| referrers.iter().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+        referrers.sort_by_key(|(node, field)| (node.kind.name(), sort_key(node.id), *field));
+
+        referrers
+    }
+
+    /// Return the IDs of the guilds that two users are both members of.
+    pub fn mutual_guilds(&self, user_id: UserId, other_id: UserId) -> BTreeSet<GuildId> {
+        let guild_ids = self.user_guild_ids(user_id);
+        let other_ids = self.user_guild_ids(other_id);
+
+        guild_ids
+            .into_iter()
+            .filter(|guild_id| other_ids.contains(guild_id))
+            .collect()
+    }
+
+    /// Return the IDs of the cached messages mentioning a user.
+    pub fn mentions(&self, user_id: UserId) -> BTreeSet<MessageId> {
+        self.referring(IdType::User, user_id.0, EntityKind::Message, "mentions")
+            .into_iter()
+            .map(|id| MessageId(sort_key(id).0))
+            .collect()
+    }
+
+    /// Return the IDs of the authors of the cached messages mentioning a
+    /// user.
+    pub fn mentioned_by(&self, user_id: UserId) -> BTreeSet<UserId> {
+        self.mentions(user_id)
+            .into_iter()
+            .filter_map(|message_id| {
+                let node = NodeId {
+                    kind: EntityKind::Message,
+                    id: RawId::Single(message_id.0),
+                };
+                let edges = self.0.edges.get(&node)?;
+                let author_id = edges
+                    .iter()
+                    .find(|(field, ..)| *field == "author_id")
+                    .map(|(.., id)| UserId(*id));
+
+                author_id
+            })
+            .collect()
+    }
+
+    fn repo<T>(&self) -> GraphRepository<T> {
+        GraphRepository::new(self.clone())
+    }
+
+    fn lock_current_user(&self) -> MutexGuard<'_, Option<CurrentUserEntity>> {
+        self.0.current_user.lock().expect("current user poisoned")
+    }
+
+    fn node<T: GraphEntity>(&self, id: RawId) -> Option<T> {
+        let node = self.0.nodes.get(&NodeId { kind: T::KIND, id })?;
+
+        T::from_any(node.value()).cloned()
+    }
+
+    fn insert<T: GraphEntity>(&self, entity: T) {
+        let node = NodeId {
+            kind: T::KIND,
+            id: T::raw_id(entity.id()),
+        };
+        let edges = edges(&entity);
+
+        self.unlink(node);
+
+        for (field, id_type, id) in &edges {
+            self.0
+                .referrers
+                .entry((*id_type, *id))
+                .or_default()
+                .insert((node, *field));
+        }
+
+        self.0.edges.insert(node, edges);
+        self.0.nodes.insert(node, entity.into());
+    }
+
+    fn remove(&self, node: NodeId) {
+        self.unlink(node);
+        self.0.nodes.remove(&node);
+    }
+
+    /// Remove the edges from a node.
+    fn unlink(&self, node: NodeId) {
+        let Some((_, edges)) = self.0.edges.remove(&node) else {
+            return;
+        };
+
+        for (field, id_type, id) in edges {
+            let key = (id_type, id);
+
+            if let Some(mut referrers) = self.0.referrers.get_mut(&key) {
+                referrers.remove(&(node, field));
+            }
+
+            self.0
+                .referrers
+                .remove_if(&key, |_, referrers| referrers.is_empty());
+        }
+    }
+
+    /// Return the raw IDs of the nodes of a kind with a field referring to
+    /// an ID, in order.
+    fn referring(&self, id_type: IdType, id: u64, kind: EntityKind, field: &str) -> Vec<RawId> {
+        let mut ids = self
+            .0
+            .referrers
+            .get(&(id_type, id))
+            .map(|referrers| {
+                referrers
+                    .iter()
+                    .filter(|(node, node_field)| node.kind == kind && *node_field == field)
+                    .map(|(node, _)| node.id)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        ids.sort_unstable_by_key(|id| sort_key(*id));
+
+        ids
+    }
+
+    fn referring_entities<T: GraphEntity>(&self, id_type: IdType, id: u64, field: &str) -> Vec<T> {
+        self.referring(id_type, id, T::KIND, field)
+            .into_iter()
+            .filter_map(|id| self.node(id))
+            .collect()
+    }
+
+    /// Return the raw IDs, as pairs, of the entities of a kind in a guild.
+    fn guild_referrers(&self, guild_id: GuildId, kind: EntityKind) -> Vec<(u64, u64)> {
+        self.referring(IdType::Guild, guild_id.0, kind, "guild_id")
+            .into_iter()
+            .map(sort_key)
+            .collect()
+    }
+
+    fn guild_entities<T: GraphEntity>(&self, guild_id: GuildId) -> Vec<T> {
+        self.referring_entities(IdType::Guild, guild_id.0, "guild_id")
+    }
+
+    fn user_guild_ids(&self, user_id: UserId) -> Vec<GuildId> {
+        self.referring(IdType::User, user_id.0, EntityKind::Member, "user_id")
+            .into_iter()
+            .map(|id| GuildId(sort_key(id).0))
+            .collect()
+    }
+}
+
+impl Backend for GraphBackend {
+    type Error = GraphBackendError;
+    type AttachmentRepository = GraphRepository<AttachmentEntity>;
+    type CategoryChannelRepository = GraphRepository<CategoryChannelEntity>;
+    type CurrentUserRepository = GraphRepository<CurrentUserEntity>;
+    type EmojiRepository = GraphRepository<EmojiEntity>;
+    type GroupRepository = GraphRepository<GroupEntity>;
+    type GuildPreviewRepository = GraphRepository<GuildPreviewEntity>;
+    type GuildRepository = GraphRepository<GuildEntity>;
+    type MemberRepository = GraphRepository<MemberEntity>;
+    type MessageRepository = GraphRepository<MessageEntity>;
+    type PresenceRepository = GraphRepository<PresenceEntity>;
+    type PrivateChannelRepository = GraphRepository<PrivateChannelEntity>;
+    type RoleRepository = GraphRepository<RoleEntity>;
+    type TextChannelRepository = GraphRepository<TextChannelEntity>;
+    type UserRepository = GraphRepository<UserEntity>;
+    type VoiceChannelRepository = GraphRepository<VoiceChannelEntity>;
+    type VoiceRegionRepository = GraphRepository<VoiceRegionEntity>;
+    type VoiceStateRepository = GraphRepository<VoiceStateEntity>;
+
+    fn attachments(&self) -> Self::AttachmentRepository {
+        self.repo()
+    }
+
+    fn category_channels(&self) -> Self::CategoryChannelRepository {
+        self.repo()
+    }
+
+    fn current_user(&self) -> Self::CurrentUserRepository {
+        self.repo()
+    }
+
+    fn emojis(&self) -> Self::EmojiRepository {
+        self.repo()
+    }
+
+    fn groups(&self) -> Self::GroupRepository {
+        self.repo()
+    }
+
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        self.repo()
+    }
+
+    fn guilds(&self) -> Self::GuildRepository {
+        self.repo()
+    }
+
+    fn members(&self) -> Self::MemberRepository {
+        self.repo()
+    }
+
+    fn messages(&self) -> Self::MessageRepository {
+        self.repo()
+    }
+
+    fn presences(&self) -> Self::PresenceRepository {
+        self.repo()
+    }
+
+    fn private_channels(&self) -> Self::PrivateChannelRepository {
+        self.repo()
+    }
+
+    fn roles(&self) -> Self::RoleRepository {
+        self.repo()
+    }
+
+    fn text_channels(&self) -> Self::TextChannelRepository {
+        self.repo()
+    }
+
+    fn users(&self) -> Self::UserRepository {
+        self.repo()
+    }
+
+    fn voice_channels(&self) -> Self::VoiceChannelRepository {
+        self.repo()
+    }
+
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        self.repo()
+    }
+
+    fn voice_states(&self) -> Self::VoiceStateRepository {
+        self.repo()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GraphBackend, GraphCache, NodeId};
+    use futures_util::stream::TryStreamExt;
+    use std::error::Error;
+    use twilight_cache::{
+        any::{EntityKind, RawId},
+        entity::{
+            channel::{
+                AttachmentEntity, CategoryChannelEntity, GuildChannelEntity, MessageEntity,
+                MessageRepository, TextChannelEntity,
+            },
+            guild::{GuildEntity, GuildRepository, MemberEntity, RoleEntity},
+            user::{CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository},
+        },
+        repository::SingleEntityRepository,
+        schema::IdType,
+        Repository,
+    };
+    use twilight_model::id::{AttachmentId, ChannelId, GuildId, MessageId, RoleId, UserId};
+
+    #[tokio::test]
+    async fn test_repository() -> Result<(), Box<dyn Error>> {
+        let cache = GraphCache::new();
+        let users = (1..=3)
+            .map(|id| UserEntity::builder(UserId(id), "user", "0001").build())
+            .collect::<Vec<_>>();
+
+        cache.users.upsert_bulk(users.clone().into_iter()).await?;
+        assert_eq!(Some(users[1].clone()), cache.users.get(UserId(2)).await?);
+
+        let mut listed = cache.users.list().await?.try_collect::<Vec<_>>().await?;
+        listed.sort_by_key(|user| user.id);
+        assert_eq!(users, listed);
+
+        cache.users.remove(UserId(1)).await?;
+        assert_eq!(None, cache.users.get(UserId(1)).await?);
+        assert!(cache.guilds.get(GuildId(1)).await?.is_none());
+
+        let current_user = CurrentUserEntity::builder(UserId(2), "user", "0001").build();
+        cache.current_user.upsert(current_user.clone()).await?;
+        assert_eq!(Some(current_user), cache.current_user.get().await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relations() -> Result<(), Box<dyn Error>> {
+        let cache = GraphCache::new();
+
+        cache
+            .guilds
+            .upsert(GuildEntity::builder(GuildId(1), "guild", UserId(2)).build())
+            .await?;
+        cache
+            .current_user
+            .upsert(CurrentUserEntity::builder(UserId(2), "user", "0001").build())
+            .await?;
+
+        for guild_id in &[GuildId(1), GuildId(2)] {
+            cache
+                .members
+                .upsert(MemberEntity::builder(*guild_id, UserId(2)).build())
+                .await?;
+        }
+
+        cache
+            .members
+            .upsert(MemberEntity::builder(GuildId(1), UserId(3)).build())
+            .await?;
+        cache
+            .roles
+            .upsert(RoleEntity::builder(RoleId(4), GuildId(1), "role").build())
+            .await?;
+
+        let mut category = CategoryChannelEntity::builder(ChannelId(5), "category");
+        category.guild_id(GuildId(1));
+        cache.category_channels.upsert(category.build()).await?;
+        let mut text = TextChannelEntity::builder(ChannelId(6), "text");
+        text.guild_id(GuildId(1));
+        cache.text_channels.upsert(text.build()).await?;
+
+        cache
+            .messages
+            .upsert(MessageEntity::builder(MessageId(7), ChannelId(6), UserId(2)).build())
+            .await?;
+        let attachment = AttachmentEntity::builder(AttachmentId(8), MessageId(7)).build();
+        cache.attachments.upsert(attachment.clone()).await?;
+
+        assert_eq!(
+            vec![UserId(2), UserId(3)],
+            cache
+                .guilds
+                .member_ids(GuildId(1))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+        assert_eq!(
+            vec![GuildId(1), GuildId(2)],
+            cache
+                .users
+                .guild_ids(UserId(2))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+        assert_eq!(
+            2,
+            cache
+                .current_user
+                .guild_ids()
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+                .len()
+        );
+
+        let channels = cache
+            .guilds
+            .channels(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert!(matches!(channels[0], GuildChannelEntity::Category(_)));
+        assert!(matches!(channels[1], GuildChannelEntity::Text(_)));
+        assert_eq!(
+            vec![ChannelId(5), ChannelId(6)],
+            cache
+                .guilds
+                .channel_ids(GuildId(1))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+        assert_eq!(
+            vec![RoleId(4)],
+            cache
+                .guilds
+                .role_ids(GuildId(1))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+        assert_eq!(
+            vec![attachment],
+            cache
+                .messages
+                .attachments(MessageId(7))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+        assert_eq!(
+            vec![MessageId(7)],
+            cache
+                .messages
+                .list_by_channel(ChannelId(6))
+                .await?
+                .map_ok(|message| message.id)
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+
+        // removing a member removes its edges
+        cache.members.remove((GuildId(1), UserId(3))).await?;
+        assert_eq!(
+            vec![UserId(2)],
+            cache
+                .guilds
+                .member_ids(GuildId(1))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_graph_queries() -> Result<(), Box<dyn Error>> {
+        let backend = GraphBackend::new();
+        let cache = GraphCache::with_backend(backend.clone());
+
+        for (guild_id, user_id) in &[(1, 3), (2, 3), (2, 4), (5, 4)] {
+            cache
+                .members
+                .upsert(MemberEntity::builder(GuildId(*guild_id), UserId(*user_id)).build())
+                .await?;
+        }
+
+        assert_eq!(
+            vec![GuildId(2)],
+            backend
+                .mutual_guilds(UserId(3), UserId(4))
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        let mut message = MessageEntity::builder(MessageId(6), ChannelId(7), UserId(3));
+        message.mentions(vec![UserId(4)]);
+        cache.messages.upsert(message.build()).await?;
+
+        assert_eq!(
+            vec![MessageId(6)],
+            backend.mentions(UserId(4)).into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![UserId(3)],
+            backend
+                .mentioned_by(UserId(4))
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(
+                NodeId {
+                    kind: EntityKind::Message,
+                    id: RawId::Single(6),
+                },
+                "mentions"
+            )],
+            backend
+                .referrers(IdType::User, 4)
+                .into_iter()
+                .filter(|(node, _)| node.kind == EntityKind::Message)
+                .collect::<Vec<_>>()
+        );
+
+        // upserting a message again replaces its edges
+        cache
+            .messages
+            .upsert(MessageEntity::builder(MessageId(6), ChannelId(7), UserId(3)).build())
+            .await?;
+        assert!(backend.mentions(UserId(4)).is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! Interactive inspection of a cache snapshot.
+//!
+//! Loads a JSON [`Snapshot`] into an in-memory backend and answers queries
+//! read from standard input, one per line:
+//!
+//! - `kinds`: list the names of the kinds of entities;
+//! - `<kind> <id> [<id>] [show]`: show an entity, such as `message 456` or
+//!   `member 123 789`;
+//! - `guild <id> <relation> [count]`: list or count the IDs of a guild's
+//!   `channels`, `emojis`, `members`, `presences`, `roles`, or
+//!   `voice_states`;
+//! - `<channel kind> <id> messages [count]`: list or count the IDs of a
+//!   channel's cached messages;
+//! - `user <id> guilds [count]`: list or count the IDs of a user's guilds;
+//! - `quit`: exit.
+//!
+//! Requires the `cli` feature:
+//!
+//! ```sh
+//! cargo run -p twilight-cache-inmemory --features cli -- snapshot.json
+//! ```
+//!
+//! [`Snapshot`]: ../twilight_cache/snapshot/struct.Snapshot.html
+
+use std::{
+    collections::BTreeSet,
+    env,
+    error::Error,
+    fmt::Display,
+    fs,
+    io::{self, BufRead, Write},
+    process,
+};
+use twilight_cache::{
+    any::{self, EntityKind, RawId},
+    snapshot::Snapshot,
+};
+use twilight_cache_inmemory::InMemoryBackend;
+use twilight_model::id::{ChannelId, GuildId, UserId};
+
+#[tokio::main(basic_scheduler)]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: twilight-cache-cli <snapshot.json>");
+
+            process::exit(2);
+        }
+    };
+
+    let snapshot = serde_json::from_slice::<Snapshot>(&fs::read(&path)?)?;
+    let backend = InMemoryBackend::new();
+    snapshot.restore(&backend).await?;
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    loop {
+        write!(stdout, "> ")?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words.as_slice() {
+            [] => {}
+            ["quit"] | ["exit"] => break,
+            ["kinds"] => {
+                for kind in EntityKind::ALL {
+                    println!("{}", kind.name());
+                }
+            }
+            _ => {
+                if let Err(message) = query(&backend, &words).await {
+                    eprintln!("error: {}", message);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn query(backend: &InMemoryBackend, words: &[&str]) -> Result<(), String> {
+    let (name, rest) = words.split_first().ok_or("empty query")?;
+    let kind = EntityKind::from_name(name).ok_or_else(|| format!("unknown kind: {}", name))?;
+
+    let ids = rest
+        .iter()
+        .map_while(|word| word.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+    let rest = &rest[ids.len()..];
+
+    let raw_id = match (kind, ids.as_slice()) {
+        (EntityKind::CurrentUser, []) => RawId::Single(0),
+        (_, [id]) => RawId::Single(*id),
+        (_, [first, second]) => RawId::Pair(*first, *second),
+        _ => return Err(format!("expected one or two IDs for {}", kind.name())),
+    };
+
+    let count = match rest {
+        [] | ["show"] => {
+            return match any::get_any(backend, kind, raw_id).await {
+                Ok(Some(entity)) => {
+                    println!("{:#?}", entity);
+
+                    Ok(())
+                }
+                Ok(None) => Err(format!("{} not found", kind.name())),
+                Err(source) => Err(source.to_string()),
+            };
+        }
+        [_] => false,
+        [_, "count"] => true,
+        _ => return Err(format!("unknown query: {}", words.join(" "))),
+    };
+
+    let id = match raw_id {
+        RawId::Single(id) => id,
+        RawId::Pair(..) => return Err(format!("{} has no relations", kind.name())),
+    };
+
+    let relations = backend.relations();
+
+    match (kind, rest[0]) {
+        (EntityKind::Guild, "channels") => print(relations.guild_channels(GuildId(id)), count),
+        (EntityKind::Guild, "emojis") => print(relations.guild_emojis(GuildId(id)), count),
+        (EntityKind::Guild, "members") => print(relations.guild_members(GuildId(id)), count),
+        (EntityKind::Guild, "presences") => print(relations.guild_presences(GuildId(id)), count),
+        (EntityKind::Guild, "roles") => print(relations.guild_roles(GuildId(id)), count),
+        (EntityKind::Guild, "voice_states") => {
+            print(relations.guild_voice_states(GuildId(id)), count)
+        }
+        (EntityKind::Group | EntityKind::PrivateChannel | EntityKind::TextChannel, "messages") => {
+            print(relations.channel_messages(ChannelId(id)), count)
+        }
+        (EntityKind::User, "guilds") => print(relations.user_guilds(UserId(id)), count),
+        (_, relation) => return Err(format!("{} has no {}", kind.name(), relation)),
+    }
+
+    Ok(())
+}
+
+fn print<T: Display>(ids: BTreeSet<T>, count: bool) {
+    if count {
+        println!("{}", ids.len());
+    } else {
+        for id in ids {
+            println!("{}", id);
+        }
+    }
+}
@@ -1,4 +1,26 @@
 use bitflags::bitflags;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+use twilight_cache::{
+    entity::{
+        channel::{
+            AttachmentEntity, CategoryChannelEntity, GroupEntity, MessageEntity,
+            PrivateChannelEntity, TextChannelEntity, VoiceChannelEntity,
+        },
+        gateway::PresenceEntity,
+        guild::{EmojiEntity, GuildEntity, GuildPreviewEntity, MemberEntity, RoleEntity},
+        user::{CurrentUserEntity, UserEntity},
+        voice::{VoiceRegionEntity, VoiceStateEntity},
+    },
+    intents::EntityIntents,
+};
+use twilight_model::{
+    gateway::Intents,
+    id::{ChannelId, GuildId},
+};
 
 bitflags! {
     /// Flags to enable which entities to operate on.
@@ -22,6 +44,121 @@ bitflags! {
         const USER = 1 << 12;
         const USER_CURRENT = 1 << 13;
         const VOICE_STATE = 1 << 14;
+        const GUILD_PREVIEW = 1 << 15;
+        const VOICE_REGION = 1 << 16;
+    }
+}
+
+impl EntityType {
+    /// Return the entity types populated by the events received with the
+    /// given gateway intents.
+    ///
+    /// Refer to [`EntityIntents`] for more information.
+    ///
+    /// [`EntityIntents`]: ../../twilight_cache/intents/trait.EntityIntents.html
+    pub fn from_intents(intents: Intents) -> Self {
+        let mut entity_types = Self::empty();
+
+        entity_types.set(Self::ATTACHMENT, AttachmentEntity::is_populated_by(intents));
+        entity_types.set(
+            Self::CHANNEL_CATEGORY,
+            CategoryChannelEntity::is_populated_by(intents),
+        );
+        entity_types.set(Self::CHANNEL_GROUP, GroupEntity::is_populated_by(intents));
+        entity_types.set(
+            Self::CHANNEL_PRIVATE,
+            PrivateChannelEntity::is_populated_by(intents),
+        );
+        entity_types.set(
+            Self::CHANNEL_TEXT,
+            TextChannelEntity::is_populated_by(intents),
+        );
+        entity_types.set(
+            Self::CHANNEL_VOICE,
+            VoiceChannelEntity::is_populated_by(intents),
+        );
+        entity_types.set(Self::EMOJI, EmojiEntity::is_populated_by(intents));
+        entity_types.set(Self::GUILD, GuildEntity::is_populated_by(intents));
+        entity_types.set(
+            Self::GUILD_PREVIEW,
+            GuildPreviewEntity::is_populated_by(intents),
+        );
+        entity_types.set(Self::MEMBER, MemberEntity::is_populated_by(intents));
+        entity_types.set(Self::MESSAGE, MessageEntity::is_populated_by(intents));
+        entity_types.set(Self::PRESENCE, PresenceEntity::is_populated_by(intents));
+        entity_types.set(Self::ROLE, RoleEntity::is_populated_by(intents));
+        entity_types.set(Self::USER, UserEntity::is_populated_by(intents));
+        entity_types.set(
+            Self::USER_CURRENT,
+            CurrentUserEntity::is_populated_by(intents),
+        );
+        entity_types.set(
+            Self::VOICE_REGION,
+            VoiceRegionEntity::is_populated_by(intents),
+        );
+        entity_types.set(
+            Self::VOICE_STATE,
+            VoiceStateEntity::is_populated_by(intents),
+        );
+
+        entity_types
+    }
+}
+
+/// Policy deciding which message to evict when a channel's message cache is
+/// full.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum EvictionPolicy {
+    /// Evict the message with the lowest ID, keeping the newest messages.
+    #[default]
+    OldestId,
+    /// Evict the message with the highest ID, keeping the oldest messages.
+    NewestId,
+    /// Evict the message that was inserted into the cache the longest time
+    /// ago.
+    LeastRecentlyInserted,
+    /// Evict the message that was retrieved from or inserted into the cache
+    /// the longest time ago.
+    LeastRecentlyAccessed,
+}
+
+/// Policy deciding what happens when an entity is upserted into a guild that
+/// already has the maximum number of entities of its type cached.
+///
+/// Pinned guilds aren't capped, and the entities of pinned users are always
+/// cached and never evicted. Refer to [`InMemoryRepository::pin`].
+///
+/// [`InMemoryRepository::pin`]: ../repository/struct.InMemoryRepository.html#method.pin
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum GuildCapPolicy {
+    /// Don't cache the new entity, keeping the cached ones.
+    #[default]
+    Refuse,
+    /// Evict the entity of the guild that was inserted into the cache the
+    /// longest time ago to make room for the new entity.
+    EvictLeastRecentlyInserted,
+}
+
+/// Callback called with the type and estimated size of an entity that was
+/// rejected for being larger than the maximum entity size.
+#[derive(Clone)]
+pub struct OversizedEntityHook(Arc<dyn Fn(EntityType, usize) + Send + Sync>);
+
+impl OversizedEntityHook {
+    /// Create a hook from a callback.
+    pub fn new(hook: impl Fn(EntityType, usize) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    /// Call the hook.
+    pub fn call(&self, entity_type: EntityType, size: usize) {
+        (self.0)(entity_type, size);
+    }
+}
+
+impl Debug for OversizedEntityHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple("OversizedEntityHook").finish()
     }
 }
 
@@ -30,11 +167,113 @@ bitflags! {
 /// Refer to each setter method to know the default value.
 #[derive(Clone, Debug)]
 pub struct Config {
+    avatar_history_size: usize,
+    channel_message_cache_sizes: HashMap<ChannelId, usize>,
+    consistent_iteration: bool,
     entity_types: EntityType,
+    guild_allowlist: Option<HashSet<GuildId>>,
+    guild_cap_policy: GuildCapPolicy,
+    guild_denylist: HashSet<GuildId>,
+    index_member_names: bool,
+    index_presence_activities: bool,
+    max_cached_members_per_guild: Option<usize>,
+    max_cached_presences_per_guild: Option<usize>,
+    max_entity_size: Option<usize>,
+    max_total_messages: Option<usize>,
     message_cache_size: usize,
+    message_eviction_policy: EvictionPolicy,
+    name_history_size: usize,
+    oversized_entity_hook: Option<OversizedEntityHook>,
+    recent_joins_size: usize,
+    track_metadata: bool,
+    voice_history_size: usize,
 }
 
 impl Config {
+    /// Create a configuration with only the entity types populated by the
+    /// events received with the given gateway intents enabled.
+    ///
+    /// Refer to [`EntityType::from_intents`] for more information.
+    ///
+    /// [`EntityType::from_intents`]: struct.EntityType.html#method.from_intents
+    pub fn for_intents(intents: Intents) -> Self {
+        Self {
+            entity_types: EntityType::from_intents(intents),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the number of previous avatars recorded per user.
+    pub fn avatar_history_size(&self) -> usize {
+        self.avatar_history_size
+    }
+
+    /// Returns a mutable reference to the number of previous avatars recorded
+    /// per user.
+    ///
+    /// When non-zero, the previous avatar hash of a user is recorded when an
+    /// upsert changes it, keeping at most this many. They're returned by
+    /// [`UserRepository::avatar_history`] and dropped along with the user.
+    /// When zero, avatars aren't recorded.
+    ///
+    /// Defaults to 0.
+    ///
+    /// [`UserRepository::avatar_history`]: ../../twilight_cache/entity/user/trait.UserRepository.html#method.avatar_history
+    pub fn avatar_history_size_mut(&mut self) -> &mut usize {
+        &mut self.avatar_history_size
+    }
+
+    /// Returns whether the entities of a guild are cached, taking into account
+    /// the guild allowlist and denylist.
+    pub fn caches_guild(&self, guild_id: GuildId) -> bool {
+        !self.guild_denylist.contains(&guild_id)
+            && self
+                .guild_allowlist
+                .as_ref()
+                .is_none_or(|allowlist| allowlist.contains(&guild_id))
+    }
+
+    /// Returns an immutable reference to the message cache sizes of channels
+    /// that override the default message cache size.
+    pub fn channel_message_cache_sizes(&self) -> &HashMap<ChannelId, usize> {
+        &self.channel_message_cache_sizes
+    }
+
+    /// Returns a mutable reference to the message cache sizes of channels
+    /// that override the default message cache size.
+    ///
+    /// Defaults to no overrides.
+    pub fn channel_message_cache_sizes_mut(&mut self) -> &mut HashMap<ChannelId, usize> {
+        &mut self.channel_message_cache_sizes
+    }
+
+    /// Returns whether streams of entities are snapshots taken when they're
+    /// created.
+    pub fn consistent_iteration(&self) -> bool {
+        self.consistent_iteration
+    }
+
+    /// Returns a mutable reference to whether streams of entities are
+    /// snapshots taken when they're created.
+    ///
+    /// By default, streams returned by [`Repository::list`] and the relation
+    /// methods of repositories look up each entity as the stream is polled,
+    /// so entities updated while a stream is being consumed are yielded with
+    /// their new value and entities removed are skipped. When enabled, all of
+    /// the entities are cloned before the stream is returned, so that a long
+    /// iteration, such as over every member of a large guild, sees a stable
+    /// view unaffected by concurrently processed events, at the cost of
+    /// holding every entity of the stream in memory at once.
+    ///
+    /// In both modes, no locks are held while a stream is being consumed.
+    ///
+    /// Defaults to false.
+    ///
+    /// [`Repository::list`]: ../../twilight_cache/repository/trait.Repository.html#method.list
+    pub fn consistent_iteration_mut(&mut self) -> &mut bool {
+        &mut self.consistent_iteration
+    }
+
     /// Returns an immutable reference to the entity types enabled.
     pub fn entity_types(&self) -> EntityType {
         self.entity_types
@@ -51,6 +290,182 @@ impl Config {
         &mut self.entity_types
     }
 
+    /// Returns an immutable reference to the guilds whose entities are
+    /// cached, if only some are.
+    pub fn guild_allowlist(&self) -> Option<&HashSet<GuildId>> {
+        self.guild_allowlist.as_ref()
+    }
+
+    /// Returns a mutable reference to the guilds whose entities are cached,
+    /// if only some are.
+    ///
+    /// Events for other guilds are skipped entirely when they're processed,
+    /// which is useful for bots that only need to cache a handful of "home"
+    /// guilds. Guilds in the [denylist] aren't cached even if they're in the
+    /// allowlist.
+    ///
+    /// Defaults to caching every guild.
+    ///
+    /// [denylist]: #method.guild_denylist_mut
+    pub fn guild_allowlist_mut(&mut self) -> &mut Option<HashSet<GuildId>> {
+        &mut self.guild_allowlist
+    }
+
+    /// Returns the policy used when a guild has the maximum number of
+    /// entities of a type cached.
+    pub fn guild_cap_policy(&self) -> GuildCapPolicy {
+        self.guild_cap_policy
+    }
+
+    /// Returns a mutable reference to the policy used when a guild has the
+    /// maximum number of entities of a type cached.
+    ///
+    /// Applies to the [members] and [presences] caps.
+    ///
+    /// Defaults to [`GuildCapPolicy::Refuse`].
+    ///
+    /// [`GuildCapPolicy::Refuse`]: enum.GuildCapPolicy.html#variant.Refuse
+    /// [members]: #method.max_cached_members_per_guild_mut
+    /// [presences]: #method.max_cached_presences_per_guild_mut
+    pub fn guild_cap_policy_mut(&mut self) -> &mut GuildCapPolicy {
+        &mut self.guild_cap_policy
+    }
+
+    /// Returns an immutable reference to the guilds whose entities aren't
+    /// cached.
+    pub fn guild_denylist(&self) -> &HashSet<GuildId> {
+        &self.guild_denylist
+    }
+
+    /// Returns a mutable reference to the guilds whose entities aren't
+    /// cached.
+    ///
+    /// Events for these guilds are skipped entirely when they're processed.
+    ///
+    /// Defaults to no guilds.
+    pub fn guild_denylist_mut(&mut self) -> &mut HashSet<GuildId> {
+        &mut self.guild_denylist
+    }
+
+    /// Returns whether members are indexed by their names.
+    pub fn index_member_names(&self) -> bool {
+        self.index_member_names
+    }
+
+    /// Returns a mutable reference to whether members are indexed by their
+    /// names.
+    ///
+    /// The index keeps the lowercased nickname and username of each guild's
+    /// members in order, so [`GuildRepository::search_members`] only looks at
+    /// the members whose names start with the query instead of every member
+    /// in the guild. This costs memory for a copy of every name, which adds
+    /// up in large guilds.
+    ///
+    /// Defaults to false.
+    ///
+    /// [`GuildRepository::search_members`]: ../../twilight_cache/entity/guild/trait.GuildRepository.html#method.search_members
+    pub fn index_member_names_mut(&mut self) -> &mut bool {
+        &mut self.index_member_names
+    }
+
+    /// Returns whether presences are indexed by their activities.
+    pub fn index_presence_activities(&self) -> bool {
+        self.index_presence_activities
+    }
+
+    /// Returns a mutable reference to whether presences are indexed by their
+    /// activities.
+    ///
+    /// The index makes [`PresenceRepository::by_activity`] only look at the
+    /// presences with a matching activity instead of every presence in the
+    /// guild, at the cost of memory for the activity names.
+    ///
+    /// Defaults to false.
+    ///
+    /// [`PresenceRepository::by_activity`]: ../../twilight_cache/entity/gateway/trait.PresenceRepository.html#method.by_activity
+    pub fn index_presence_activities_mut(&mut self) -> &mut bool {
+        &mut self.index_presence_activities
+    }
+
+    /// Returns the maximum number of members cached per guild.
+    pub fn max_cached_members_per_guild(&self) -> Option<usize> {
+        self.max_cached_members_per_guild
+    }
+
+    /// Returns a mutable reference to the maximum number of members cached
+    /// per guild.
+    ///
+    /// Caps the memory that a single enormous guild can use in a bot that's
+    /// in many guilds. Members upserted into a guild that's at the cap are
+    /// handled according to the [guild cap policy]. Members that are already
+    /// cached are always updated. Members upserted concurrently may briefly
+    /// exceed the cap.
+    ///
+    /// Defaults to no limit.
+    ///
+    /// [guild cap policy]: #method.guild_cap_policy_mut
+    pub fn max_cached_members_per_guild_mut(&mut self) -> &mut Option<usize> {
+        &mut self.max_cached_members_per_guild
+    }
+
+    /// Returns the maximum number of presences cached per guild.
+    pub fn max_cached_presences_per_guild(&self) -> Option<usize> {
+        self.max_cached_presences_per_guild
+    }
+
+    /// Returns a mutable reference to the maximum number of presences cached
+    /// per guild.
+    ///
+    /// Works like the [members cap], for presences.
+    ///
+    /// Defaults to no limit.
+    ///
+    /// [members cap]: #method.max_cached_members_per_guild_mut
+    pub fn max_cached_presences_per_guild_mut(&mut self) -> &mut Option<usize> {
+        &mut self.max_cached_presences_per_guild
+    }
+
+    /// Returns the maximum estimated size of an entity in bytes.
+    pub fn max_entity_size(&self) -> Option<usize> {
+        self.max_entity_size
+    }
+
+    /// Returns a mutable reference to the maximum estimated size of an entity
+    /// in bytes.
+    ///
+    /// Upserted entities whose [estimated size] is larger than this are
+    /// rejected and the [oversized entity hook] is called. A previously cached
+    /// version of a rejected entity is kept as-is.
+    ///
+    /// Defaults to no limit.
+    ///
+    /// [estimated size]: ../../twilight_cache/size/trait.EstimateSize.html
+    /// [oversized entity hook]: #method.oversized_entity_hook_mut
+    pub fn max_entity_size_mut(&mut self) -> &mut Option<usize> {
+        &mut self.max_entity_size
+    }
+
+    /// Returns the maximum number of messages cached across all channels.
+    pub fn max_total_messages(&self) -> Option<usize> {
+        self.max_total_messages
+    }
+
+    /// Returns a mutable reference to the maximum number of messages cached
+    /// across all channels.
+    ///
+    /// When the cache is full, messages are evicted from a queue shared by
+    /// all channels in the order they were inserted, or in the order they
+    /// were last accessed if the eviction policy is
+    /// [`EvictionPolicy::LeastRecentlyAccessed`]. This applies in addition to
+    /// the per-channel message cache sizes.
+    ///
+    /// Defaults to no limit.
+    ///
+    /// [`EvictionPolicy::LeastRecentlyAccessed`]: enum.EvictionPolicy.html#variant.LeastRecentlyAccessed
+    pub fn max_total_messages_mut(&mut self) -> &mut Option<usize> {
+        &mut self.max_total_messages
+    }
+
     /// Returns an immutable reference to the message cache size.
     pub fn message_cache_size(&self) -> usize {
         self.message_cache_size
@@ -62,26 +477,199 @@ impl Config {
     pub fn message_cache_size_mut(&mut self) -> &mut usize {
         &mut self.message_cache_size
     }
+
+    /// Returns the message cache size of a channel, taking into account
+    /// per-channel overrides.
+    pub fn message_cache_size_for(&self, channel_id: ChannelId) -> usize {
+        self.channel_message_cache_sizes
+            .get(&channel_id)
+            .copied()
+            .unwrap_or(self.message_cache_size)
+    }
+
+    /// Returns the policy used to evict messages from full channels.
+    pub fn message_eviction_policy(&self) -> EvictionPolicy {
+        self.message_eviction_policy
+    }
+
+    /// Returns a mutable reference to the policy used to evict messages from
+    /// full channels.
+    ///
+    /// Defaults to [`EvictionPolicy::OldestId`].
+    ///
+    /// [`EvictionPolicy::OldestId`]: enum.EvictionPolicy.html#variant.OldestId
+    pub fn message_eviction_policy_mut(&mut self) -> &mut EvictionPolicy {
+        &mut self.message_eviction_policy
+    }
+
+    /// Returns the number of previous names recorded per member.
+    pub fn name_history_size(&self) -> usize {
+        self.name_history_size
+    }
+
+    /// Returns a mutable reference to the number of previous names recorded
+    /// per member.
+    ///
+    /// When non-zero, the previous nickname of a member is recorded when an
+    /// upsert changes it, as is the previous username of a user, keeping at
+    /// most this many of each. They're returned by
+    /// [`MemberRepository::nick_history`] and dropped along with the member
+    /// or user. When zero, names aren't recorded.
+    ///
+    /// Defaults to 0.
+    ///
+    /// [`MemberRepository::nick_history`]: ../../twilight_cache/entity/guild/member/trait.MemberRepository.html#method.nick_history
+    pub fn name_history_size_mut(&mut self) -> &mut usize {
+        &mut self.name_history_size
+    }
+
+    /// Returns an immutable reference to the hook called when an entity is
+    /// rejected for being too large.
+    pub fn oversized_entity_hook(&self) -> Option<&OversizedEntityHook> {
+        self.oversized_entity_hook.as_ref()
+    }
+
+    /// Returns a mutable reference to the hook called when an entity is
+    /// rejected for being larger than the [maximum entity size].
+    ///
+    /// Defaults to no hook.
+    ///
+    /// [maximum entity size]: #method.max_entity_size_mut
+    pub fn oversized_entity_hook_mut(&mut self) -> &mut Option<OversizedEntityHook> {
+        &mut self.oversized_entity_hook
+    }
+
+    /// Returns the number of recent joins indexed per guild.
+    pub fn recent_joins_size(&self) -> usize {
+        self.recent_joins_size
+    }
+
+    /// Returns a mutable reference to the number of recent joins indexed per
+    /// guild.
+    ///
+    /// When non-zero, the most recent joins of each guild are kept ordered by
+    /// join date as members are inserted, so that
+    /// [`GuildRepository::recent_joins`] doesn't sort every member of the
+    /// guild. At most this many joins are returned. When zero, joins are
+    /// sorted on each call instead.
+    ///
+    /// Defaults to 0.
+    ///
+    /// [`GuildRepository::recent_joins`]: ../../twilight_cache/entity/guild/trait.GuildRepository.html#method.recent_joins
+    pub fn recent_joins_size_mut(&mut self) -> &mut usize {
+        &mut self.recent_joins_size
+    }
+
+    /// Returns whether the times that entities were cached and last updated
+    /// at are tracked.
+    pub fn track_metadata(&self) -> bool {
+        self.track_metadata
+    }
+
+    /// Returns a mutable reference to whether the times that entities were
+    /// cached and last updated at are tracked.
+    ///
+    /// When enabled, [`Repository::get_with_meta`] returns the metadata of
+    /// entities, at the cost of memory for two timestamps per entity. The
+    /// current user's metadata isn't tracked.
+    ///
+    /// Defaults to false.
+    ///
+    /// [`Repository::get_with_meta`]: ../../twilight_cache/repository/trait.Repository.html#method.get_with_meta
+    pub fn track_metadata_mut(&mut self) -> &mut bool {
+        &mut self.track_metadata
+    }
+
+    /// Returns the number of voice state transitions recorded per user in a
+    /// guild.
+    pub fn voice_history_size(&self) -> usize {
+        self.voice_history_size
+    }
+
+    /// Returns a mutable reference to the number of voice state transitions
+    /// recorded per user in a guild.
+    ///
+    /// When non-zero, the transitions of a voice state's channel and
+    /// moderation flags are recorded when an upsert changes them, keeping at
+    /// most this many. They're returned by
+    /// [`VoiceStateRepository::transition_history`] and dropped along with
+    /// the voice state. When zero, transitions aren't recorded.
+    ///
+    /// Defaults to 0.
+    ///
+    /// [`VoiceStateRepository::transition_history`]: ../../twilight_cache/entity/voice/state/trait.VoiceStateRepository.html#method.transition_history
+    pub fn voice_history_size_mut(&mut self) -> &mut usize {
+        &mut self.voice_history_size
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            avatar_history_size: 0,
+            channel_message_cache_sizes: HashMap::new(),
+            consistent_iteration: false,
             entity_types: EntityType::all(),
+            guild_allowlist: None,
+            guild_cap_policy: GuildCapPolicy::Refuse,
+            guild_denylist: HashSet::new(),
+            index_member_names: false,
+            index_presence_activities: false,
+            max_cached_members_per_guild: None,
+            max_cached_presences_per_guild: None,
+            max_entity_size: None,
+            max_total_messages: None,
             message_cache_size: 100,
+            message_eviction_policy: EvictionPolicy::OldestId,
+            name_history_size: 0,
+            oversized_entity_hook: None,
+            recent_joins_size: 0,
+            track_metadata: false,
+            voice_history_size: 0,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Config, EntityType};
+    use super::{Config, EntityType, EvictionPolicy, GuildCapPolicy, OversizedEntityHook};
     use static_assertions::{assert_impl_all, assert_obj_safe};
-    use std::fmt::Debug;
+    use std::{
+        collections::{HashMap, HashSet},
+        fmt::Debug,
+        hash::Hash,
+    };
+    use twilight_model::{
+        gateway::Intents,
+        id::{ChannelId, GuildId},
+    };
 
     assert_impl_all!(Config: Clone, Debug, Send, Sync);
+    assert_impl_all!(OversizedEntityHook: Clone, Debug, Send, Sync);
     assert_impl_all!(EntityType: Clone, Copy, Debug, Eq, PartialEq, Send, Sync);
-    assert_obj_safe!(Config, EntityType);
+    assert_impl_all!(
+        EvictionPolicy: Clone,
+        Copy,
+        Debug,
+        Default,
+        Eq,
+        Hash,
+        PartialEq,
+        Send,
+        Sync
+    );
+    assert_impl_all!(
+        GuildCapPolicy: Clone,
+        Copy,
+        Debug,
+        Default,
+        Eq,
+        Hash,
+        PartialEq,
+        Send,
+        Sync
+    );
+    assert_obj_safe!(Config, EntityType, EvictionPolicy, GuildCapPolicy);
 
     #[test]
     fn test_event_type_const_values() {
@@ -100,21 +688,135 @@ mod tests {
         assert_eq!(1 << 12, EntityType::USER.bits());
         assert_eq!(1 << 13, EntityType::USER_CURRENT.bits());
         assert_eq!(1 << 14, EntityType::VOICE_STATE.bits());
+        assert_eq!(1 << 15, EntityType::GUILD_PREVIEW.bits());
+        assert_eq!(1 << 16, EntityType::VOICE_REGION.bits());
     }
 
     #[test]
     fn test_defaults() {
         let conf = Config {
+            avatar_history_size: 0,
+            channel_message_cache_sizes: HashMap::new(),
+            consistent_iteration: false,
             entity_types: EntityType::all(),
+            guild_allowlist: None,
+            guild_cap_policy: GuildCapPolicy::Refuse,
+            guild_denylist: HashSet::new(),
+            index_member_names: false,
+            index_presence_activities: false,
+            max_cached_members_per_guild: None,
+            max_cached_presences_per_guild: None,
+            max_entity_size: None,
+            max_total_messages: None,
             message_cache_size: 100,
+            message_eviction_policy: EvictionPolicy::OldestId,
+            name_history_size: 0,
+            oversized_entity_hook: None,
+            recent_joins_size: 0,
+            track_metadata: false,
+            voice_history_size: 0,
         };
         let default = Config::default();
+        assert_eq!(conf.avatar_history_size, default.avatar_history_size);
+        assert_eq!(
+            conf.channel_message_cache_sizes,
+            default.channel_message_cache_sizes
+        );
+        assert_eq!(conf.consistent_iteration, default.consistent_iteration);
         assert_eq!(conf.entity_types, default.entity_types);
+        assert_eq!(conf.guild_allowlist, default.guild_allowlist);
+        assert_eq!(conf.guild_cap_policy, default.guild_cap_policy);
+        assert_eq!(conf.guild_denylist, default.guild_denylist);
+        assert_eq!(conf.index_member_names, default.index_member_names);
+        assert_eq!(
+            conf.index_presence_activities,
+            default.index_presence_activities
+        );
+        assert_eq!(
+            conf.max_cached_members_per_guild,
+            default.max_cached_members_per_guild
+        );
+        assert_eq!(
+            conf.max_cached_presences_per_guild,
+            default.max_cached_presences_per_guild
+        );
+        assert_eq!(conf.max_entity_size, default.max_entity_size);
+        assert_eq!(conf.max_total_messages, default.max_total_messages);
         assert_eq!(conf.message_cache_size, default.message_cache_size);
+        assert_eq!(
+            conf.message_eviction_policy,
+            default.message_eviction_policy
+        );
+        assert_eq!(conf.name_history_size, default.name_history_size);
+        assert!(default.oversized_entity_hook.is_none());
+        assert_eq!(conf.recent_joins_size, default.recent_joins_size);
+        assert_eq!(conf.track_metadata, default.track_metadata);
+        assert_eq!(conf.voice_history_size, default.voice_history_size);
+    }
+
+    #[test]
+    fn test_message_cache_size_for() {
+        let mut conf = Config::default();
+        conf.channel_message_cache_sizes_mut()
+            .insert(ChannelId(1), 5);
+
+        assert_eq!(5, conf.message_cache_size_for(ChannelId(1)));
+        assert_eq!(100, conf.message_cache_size_for(ChannelId(2)));
+    }
+
+    #[test]
+    fn test_caches_guild() {
+        let mut conf = Config::default();
+        assert!(conf.caches_guild(GuildId(1)));
+
+        conf.guild_denylist_mut().insert(GuildId(1));
+        assert!(!conf.caches_guild(GuildId(1)));
+        assert!(conf.caches_guild(GuildId(2)));
+
+        *conf.guild_allowlist_mut() = Some(vec![GuildId(1), GuildId(3)].into_iter().collect());
+        assert!(!conf.caches_guild(GuildId(1)));
+        assert!(!conf.caches_guild(GuildId(2)));
+        assert!(conf.caches_guild(GuildId(3)));
+    }
+
+    #[test]
+    fn test_for_intents() {
+        let conf = Config::for_intents(Intents::GUILDS | Intents::GUILD_MESSAGES);
+        let entity_types = conf.entity_types();
+
+        assert!(entity_types.contains(EntityType::GUILD | EntityType::CHANNEL_TEXT));
+        assert!(entity_types.contains(EntityType::MESSAGE | EntityType::ATTACHMENT));
+        assert!(entity_types.contains(EntityType::USER | EntityType::USER_CURRENT));
+        assert!(!entity_types.intersects(EntityType::MEMBER | EntityType::PRESENCE));
+        assert!(!entity_types.intersects(EntityType::CHANNEL_PRIVATE | EntityType::VOICE_STATE));
+        assert_eq!(100, conf.message_cache_size());
+
+        assert_eq!(
+            EntityType::GUILD_PREVIEW | EntityType::USER_CURRENT | EntityType::VOICE_REGION,
+            EntityType::from_intents(Intents::empty())
+        );
     }
 
     #[test]
     fn test_config_fields() {
-        static_assertions::assert_fields!(Config: entity_types, message_cache_size);
+        static_assertions::assert_fields!(
+            Config: channel_message_cache_sizes,
+            consistent_iteration,
+            entity_types,
+            guild_allowlist,
+            guild_cap_policy,
+            guild_denylist,
+            index_member_names,
+            index_presence_activities,
+            max_cached_members_per_guild,
+            max_cached_presences_per_guild,
+            max_entity_size,
+            max_total_messages,
+            message_cache_size,
+            message_eviction_policy,
+            oversized_entity_hook,
+            track_metadata,
+            voice_history_size
+        );
     }
 }
@@ -71,28 +71,38 @@ pub extern crate twilight_cache as cache;
 
 pub mod config;
 pub mod prelude;
+#[cfg(feature = "unstable-relations")]
+pub mod relations;
 pub mod repository;
+#[cfg(feature = "wal")]
+pub mod wal;
 
 #[doc(no_inline)]
 pub use twilight_cache::Repository;
 
 use self::{
-    config::{Config, EntityType},
+    config::{Config, EntityType, EvictionPolicy, GuildCapPolicy, OversizedEntityHook},
     repository::{
-        InMemoryAttachmentRepository, InMemoryCategoryChannelRepository,
+        EntityExt, InMemoryAttachmentRepository, InMemoryCategoryChannelRepository,
         InMemoryCurrentUserRepository, InMemoryEmojiRepository, InMemoryGroupRepository,
-        InMemoryGuildRepository, InMemoryMemberRepository, InMemoryMessageRepository,
-        InMemoryPresenceRepository, InMemoryPrivateChannelRepository, InMemoryRepository,
-        InMemoryRoleRepository, InMemoryTextChannelRepository, InMemoryUserRepository,
-        InMemoryVoiceChannelRepository, InMemoryVoiceStateRepository,
+        InMemoryGuildPreviewRepository, InMemoryGuildRepository, InMemoryMemberRepository,
+        InMemoryMessageRepository, InMemoryPresenceRepository, InMemoryPrivateChannelRepository,
+        InMemoryRepository, InMemoryRoleRepository, InMemoryTextChannelRepository,
+        InMemoryUserRepository, InMemoryVoiceChannelRepository, InMemoryVoiceRegionRepository,
+        InMemoryVoiceStateRepository, MessageQueue, Metadata, Sequences, SingleEntityExt,
     },
 };
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
+use futures_util::future::{self, FutureExt};
+#[cfg(feature = "emoji-usage")]
+use std::collections::HashMap;
 use std::{
-    collections::{BTreeSet, HashSet},
+    collections::{BTreeSet, VecDeque},
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
+    hash::Hash,
     marker::PhantomData,
+    mem,
     sync::{Arc, Mutex},
 };
 use twilight_cache::{
@@ -101,14 +111,21 @@ use twilight_cache::{
             AttachmentEntity, CategoryChannelEntity, GroupEntity, MessageEntity,
             PrivateChannelEntity, TextChannelEntity, VoiceChannelEntity,
         },
-        gateway::PresenceEntity,
-        guild::{EmojiEntity, GuildEntity, MemberEntity, RoleEntity},
-        user::{CurrentUserEntity, UserEntity},
-        voice::VoiceStateEntity,
+        gateway::{ActivityQuery, PresenceEntity},
+        guild::{
+            EmojiEntity, GuildEntity, GuildPreviewEntity, MemberEntity, NameChange, RoleEntity,
+        },
+        user::{AvatarChange, CurrentUserEntity, UserEntity},
+        voice::{VoiceRegionEntity, VoiceRegionId, VoiceStateChange, VoiceStateEntity},
     },
+    integrity::{DanglingRelation, IntegrityReport, VerifyIntegrityFuture},
+    size::EstimateSize,
     Backend, Cache,
 };
-use twilight_model::id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+use twilight_model::{
+    gateway::Intents,
+    id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId},
+};
 
 /// Alias over `twilight_cache::Cache` which uses the [`InMemoryBackend`].
 ///
@@ -155,24 +172,44 @@ struct InMemoryBackendRef {
     channels_text: DashMap<ChannelId, TextChannelEntity>,
     channels_voice: DashMap<ChannelId, VoiceChannelEntity>,
     channel_messages: DashMap<ChannelId, BTreeSet<MessageId>>,
+    channel_message_order: DashMap<ChannelId, MessageQueue>,
     config: Config,
     emojis: DashMap<EmojiId, EmojiEntity>,
+    #[cfg(feature = "emoji-usage")]
+    emoji_usage: DashMap<GuildId, HashMap<EmojiId, u64>>,
     groups: DashMap<ChannelId, GroupEntity>,
     guilds: DashMap<GuildId, GuildEntity>,
-    guild_channels: DashMap<GuildId, HashSet<ChannelId>>,
-    guild_emojis: DashMap<GuildId, HashSet<EmojiId>>,
-    guild_members: DashMap<GuildId, HashSet<UserId>>,
-    guild_presences: DashMap<GuildId, HashSet<UserId>>,
-    guild_roles: DashMap<GuildId, HashSet<RoleId>>,
-    guild_voice_states: DashMap<GuildId, HashSet<UserId>>,
+    guild_previews: DashMap<GuildId, GuildPreviewEntity>,
+    guild_channels: DashMap<GuildId, BTreeSet<ChannelId>>,
+    guild_channel_order: DashMap<GuildId, BTreeSet<(i64, ChannelId)>>,
+    guild_emojis: DashMap<GuildId, BTreeSet<EmojiId>>,
+    guild_joins: DashMap<GuildId, BTreeSet<(String, UserId)>>,
+    guild_members: DashMap<GuildId, BTreeSet<UserId>>,
+    guild_member_order: DashMap<GuildId, VecDeque<UserId>>,
+    guild_presences: DashMap<GuildId, BTreeSet<UserId>>,
+    guild_presence_order: DashMap<GuildId, VecDeque<UserId>>,
+    guild_roles: DashMap<GuildId, BTreeSet<RoleId>>,
+    guild_voice_states: DashMap<GuildId, BTreeSet<UserId>>,
     members: DashMap<(GuildId, UserId), MemberEntity>,
+    member_names: DashMap<(GuildId, UserId), VecDeque<NameChange>>,
+    member_search: DashMap<GuildId, BTreeSet<(String, UserId)>>,
     messages: DashMap<MessageId, MessageEntity>,
+    message_queue: Mutex<MessageQueue>,
+    metadata: Metadata,
+    pinned_guilds: DashSet<GuildId>,
+    pinned_users: DashSet<UserId>,
     presences: DashMap<(GuildId, UserId), PresenceEntity>,
+    presence_activities: DashMap<(GuildId, ActivityQuery), BTreeSet<UserId>>,
     roles: DashMap<RoleId, RoleEntity>,
+    sequences: Sequences,
     users: DashMap<UserId, UserEntity>,
+    user_avatars: DashMap<UserId, VecDeque<AvatarChange>>,
+    user_names: DashMap<UserId, VecDeque<NameChange>>,
     user_current: Mutex<Option<CurrentUserEntity>>,
     user_guilds: DashMap<UserId, Vec<GuildId>>,
+    voice_regions: DashMap<VoiceRegionId, VoiceRegionEntity>,
     voice_states: DashMap<(GuildId, UserId), VoiceStateEntity>,
+    voice_transitions: DashMap<(GuildId, UserId), VecDeque<VoiceStateChange>>,
 }
 
 /// Builder to create a configured [`InMemoryBackend`].
@@ -186,6 +223,16 @@ impl InMemoryBackendBuilder {
         Self::default()
     }
 
+    /// Create a builder with only the entity types populated by the events
+    /// received with the given gateway intents enabled.
+    ///
+    /// Refer to [`Config::for_intents`] for more information.
+    ///
+    /// [`Config::for_intents`]: config/struct.Config.html#method.for_intents
+    pub fn for_intents(intents: Intents) -> Self {
+        Self(Config::for_intents(intents))
+    }
+
     pub fn build(self) -> InMemoryBackend {
         InMemoryBackend(Arc::new(InMemoryBackendRef {
             config: self.0,
@@ -204,6 +251,190 @@ impl InMemoryBackendBuilder {
 
         self
     }
+
+    /// Set the number of previous avatars recorded per user.
+    ///
+    /// Refer to [`Config::avatar_history_size_mut`] for more information.
+    ///
+    /// [`Config::avatar_history_size_mut`]: config/struct.Config.html#method.avatar_history_size_mut
+    pub fn avatar_history_size(&mut self, avatar_history_size: usize) -> &mut Self {
+        *self.0.avatar_history_size_mut() = avatar_history_size;
+
+        self
+    }
+
+    /// Override the message cache size of a specific channel.
+    pub fn channel_message_cache_size(
+        &mut self,
+        channel_id: ChannelId,
+        message_cache_size: usize,
+    ) -> &mut Self {
+        self.0
+            .channel_message_cache_sizes_mut()
+            .insert(channel_id, message_cache_size);
+
+        self
+    }
+
+    /// Set whether streams of entities are snapshots taken when they're
+    /// created.
+    ///
+    /// Refer to [`Config::consistent_iteration_mut`] for more information.
+    ///
+    /// [`Config::consistent_iteration_mut`]: config/struct.Config.html#method.consistent_iteration_mut
+    pub fn consistent_iteration(&mut self, consistent_iteration: bool) -> &mut Self {
+        *self.0.consistent_iteration_mut() = consistent_iteration;
+
+        self
+    }
+
+    /// Only cache the entities of the given guilds.
+    ///
+    /// Refer to [`Config::guild_allowlist_mut`] for more information.
+    ///
+    /// [`Config::guild_allowlist_mut`]: config/struct.Config.html#method.guild_allowlist_mut
+    pub fn guild_allowlist(&mut self, guild_ids: impl IntoIterator<Item = GuildId>) -> &mut Self {
+        *self.0.guild_allowlist_mut() = Some(guild_ids.into_iter().collect());
+
+        self
+    }
+
+    /// Set the policy used when a guild has the maximum number of entities of
+    /// a type cached.
+    ///
+    /// Refer to [`Config::guild_cap_policy_mut`] for more information.
+    ///
+    /// [`Config::guild_cap_policy_mut`]: config/struct.Config.html#method.guild_cap_policy_mut
+    pub fn guild_cap_policy(&mut self, guild_cap_policy: GuildCapPolicy) -> &mut Self {
+        *self.0.guild_cap_policy_mut() = guild_cap_policy;
+
+        self
+    }
+
+    /// Don't cache the entities of the given guilds.
+    ///
+    /// Refer to [`Config::guild_denylist_mut`] for more information.
+    ///
+    /// [`Config::guild_denylist_mut`]: config/struct.Config.html#method.guild_denylist_mut
+    pub fn guild_denylist(&mut self, guild_ids: impl IntoIterator<Item = GuildId>) -> &mut Self {
+        *self.0.guild_denylist_mut() = guild_ids.into_iter().collect();
+
+        self
+    }
+
+    pub fn index_member_names(&mut self, index_member_names: bool) -> &mut Self {
+        *self.0.index_member_names_mut() = index_member_names;
+
+        self
+    }
+
+    pub fn index_presence_activities(&mut self, index_presence_activities: bool) -> &mut Self {
+        *self.0.index_presence_activities_mut() = index_presence_activities;
+
+        self
+    }
+
+    /// Set the maximum number of members cached per guild.
+    ///
+    /// Refer to [`Config::max_cached_members_per_guild_mut`] for more
+    /// information.
+    ///
+    /// [`Config::max_cached_members_per_guild_mut`]: config/struct.Config.html#method.max_cached_members_per_guild_mut
+    pub fn max_cached_members_per_guild(&mut self, max_cached_members: usize) -> &mut Self {
+        *self.0.max_cached_members_per_guild_mut() = Some(max_cached_members);
+
+        self
+    }
+
+    /// Set the maximum number of presences cached per guild.
+    ///
+    /// Refer to [`Config::max_cached_presences_per_guild_mut`] for more
+    /// information.
+    ///
+    /// [`Config::max_cached_presences_per_guild_mut`]: config/struct.Config.html#method.max_cached_presences_per_guild_mut
+    pub fn max_cached_presences_per_guild(&mut self, max_cached_presences: usize) -> &mut Self {
+        *self.0.max_cached_presences_per_guild_mut() = Some(max_cached_presences);
+
+        self
+    }
+
+    pub fn max_entity_size(&mut self, max_entity_size: usize) -> &mut Self {
+        *self.0.max_entity_size_mut() = Some(max_entity_size);
+
+        self
+    }
+
+    pub fn max_total_messages(&mut self, max_total_messages: usize) -> &mut Self {
+        *self.0.max_total_messages_mut() = Some(max_total_messages);
+
+        self
+    }
+
+    pub fn message_eviction_policy(
+        &mut self,
+        message_eviction_policy: EvictionPolicy,
+    ) -> &mut Self {
+        *self.0.message_eviction_policy_mut() = message_eviction_policy;
+
+        self
+    }
+
+    /// Set the number of previous names recorded per member.
+    ///
+    /// Refer to [`Config::name_history_size_mut`] for more information.
+    ///
+    /// [`Config::name_history_size_mut`]: config/struct.Config.html#method.name_history_size_mut
+    pub fn name_history_size(&mut self, name_history_size: usize) -> &mut Self {
+        *self.0.name_history_size_mut() = name_history_size;
+
+        self
+    }
+
+    /// Set a callback to call with the type and estimated size of entities
+    /// rejected for being larger than the maximum entity size.
+    pub fn oversized_entity_hook(
+        &mut self,
+        hook: impl Fn(EntityType, usize) + Send + Sync + 'static,
+    ) -> &mut Self {
+        *self.0.oversized_entity_hook_mut() = Some(OversizedEntityHook::new(hook));
+
+        self
+    }
+
+    /// Set the number of recent joins indexed per guild.
+    ///
+    /// Refer to [`Config::recent_joins_size_mut`] for more information.
+    ///
+    /// [`Config::recent_joins_size_mut`]: config/struct.Config.html#method.recent_joins_size_mut
+    pub fn recent_joins_size(&mut self, recent_joins_size: usize) -> &mut Self {
+        *self.0.recent_joins_size_mut() = recent_joins_size;
+
+        self
+    }
+
+    /// Set whether the times that entities were cached and last updated at
+    /// are tracked.
+    ///
+    /// Refer to [`Config::track_metadata_mut`] for more information.
+    ///
+    /// [`Config::track_metadata_mut`]: config/struct.Config.html#method.track_metadata_mut
+    pub fn track_metadata(&mut self, track_metadata: bool) -> &mut Self {
+        *self.0.track_metadata_mut() = track_metadata;
+
+        self
+    }
+
+    /// Set the number of voice state transitions recorded per user in a
+    /// guild.
+    ///
+    /// Refer to [`Config::voice_history_size_mut`] for more information.
+    ///
+    /// [`Config::voice_history_size_mut`]: config/struct.Config.html#method.voice_history_size_mut
+    pub fn voice_history_size(&mut self, voice_history_size: usize) -> &mut Self {
+        *self.0.voice_history_size_mut() = voice_history_size;
+
+        self
+    }
 }
 
 /// Backend implementation to cache entities in the process's memory.
@@ -275,11 +506,257 @@ impl InMemoryBackend {
         self.0.config.clone()
     }
 
+    /// Return the relations indexed by the backend, such as the IDs of the
+    /// members of each guild.
+    ///
+    /// Refer to the [`relations`] module for more information.
+    ///
+    /// [`relations`]: relations/index.html
+    #[cfg(feature = "unstable-relations")]
+    pub fn relations(&self) -> relations::Relations<'_> {
+        relations::Relations(&self.0)
+    }
+
+    /// Return the approximate number of bytes used by the cached entities of
+    /// each type.
+    ///
+    /// Entities are measured with their [`EstimateSize`] implementations,
+    /// plus the size of their keys. Indexes, such as the IDs of the members
+    /// of each guild, aren't included. Estimating requires iterating over all
+    /// cached entities, so this shouldn't be called too often, such as only
+    /// when metrics are scraped.
+    ///
+    /// # Examples
+    ///
+    /// Report the memory used by each type of entity as a gauge:
+    ///
+    /// ```
+    /// use twilight_cache_inmemory::InMemoryBackend;
+    ///
+    /// let backend = InMemoryBackend::new();
+    ///
+    /// for (entity_type, bytes) in backend.memory_estimate().iter() {
+    ///     println!("cache_memory_bytes{{entity=\"{:?}\"}} {}", entity_type, bytes);
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current user's lock is poisoned.
+    ///
+    /// [`EstimateSize`]: ../twilight_cache/size/trait.EstimateSize.html
+    pub fn memory_estimate(&self) -> MemoryEstimate {
+        let current_user = CurrentUserEntity::lock(self)
+            .lock()
+            .expect("current user poisoned")
+            .as_ref()
+            .map_or(0, EstimateSize::estimated_size);
+
+        MemoryEstimate(vec![
+            (
+                EntityType::ATTACHMENT,
+                self.map_estimate::<AttachmentEntity>(),
+            ),
+            (
+                EntityType::CHANNEL_CATEGORY,
+                self.map_estimate::<CategoryChannelEntity>(),
+            ),
+            (
+                EntityType::CHANNEL_GROUP,
+                self.map_estimate::<GroupEntity>(),
+            ),
+            (
+                EntityType::CHANNEL_PRIVATE,
+                self.map_estimate::<PrivateChannelEntity>(),
+            ),
+            (
+                EntityType::CHANNEL_TEXT,
+                self.map_estimate::<TextChannelEntity>(),
+            ),
+            (
+                EntityType::CHANNEL_VOICE,
+                self.map_estimate::<VoiceChannelEntity>(),
+            ),
+            (EntityType::EMOJI, self.map_estimate::<EmojiEntity>()),
+            (EntityType::GUILD, self.map_estimate::<GuildEntity>()),
+            (
+                EntityType::GUILD_PREVIEW,
+                self.map_estimate::<GuildPreviewEntity>(),
+            ),
+            (EntityType::MEMBER, self.map_estimate::<MemberEntity>()),
+            (EntityType::MESSAGE, self.map_estimate::<MessageEntity>()),
+            (EntityType::PRESENCE, self.map_estimate::<PresenceEntity>()),
+            (EntityType::ROLE, self.map_estimate::<RoleEntity>()),
+            (EntityType::USER, self.map_estimate::<UserEntity>()),
+            (EntityType::USER_CURRENT, current_user),
+            (
+                EntityType::VOICE_REGION,
+                self.map_estimate::<VoiceRegionEntity>(),
+            ),
+            (
+                EntityType::VOICE_STATE,
+                self.map_estimate::<VoiceStateEntity>(),
+            ),
+        ])
+    }
+
+    /// Check the relation indexes for IDs of entities that aren't cached,
+    /// removing them if `repair` is set.
+    fn check_integrity(&self, repair: bool) -> IntegrityReport {
+        let backend = &self.0;
+        let mut dangling = Vec::new();
+
+        let channel_messages = dangling_relations(&backend.channel_messages, repair, |_, id| {
+            backend.messages.contains_key(&id)
+        });
+
+        for (channel_id, message_id) in channel_messages {
+            if repair {
+                if let Some(mut order) = backend.channel_message_order.get_mut(&channel_id) {
+                    order.remove(message_id);
+                }
+            }
+
+            dangling.push(DanglingRelation::ChannelMessage {
+                channel_id,
+                message_id,
+            });
+        }
+
+        let guild_channels = dangling_relations(&backend.guild_channels, repair, |_, id| {
+            backend.channels_text.contains_key(&id)
+                || backend.channels_voice.contains_key(&id)
+                || backend.channels_category.contains_key(&id)
+        });
+
+        for (guild_id, channel_id) in guild_channels {
+            if repair {
+                if let Some(mut order) = backend.guild_channel_order.get_mut(&guild_id) {
+                    order.retain(|(_, id)| *id != channel_id);
+                }
+            }
+
+            dangling.push(DanglingRelation::GuildChannel {
+                guild_id,
+                channel_id,
+            });
+        }
+
+        let guild_emojis = dangling_relations(&backend.guild_emojis, repair, |_, id| {
+            backend.emojis.contains_key(&id)
+        });
+        dangling.extend(
+            guild_emojis
+                .into_iter()
+                .map(|(guild_id, emoji_id)| DanglingRelation::GuildEmoji { guild_id, emoji_id }),
+        );
+
+        let guild_members = dangling_relations(&backend.guild_members, repair, |guild_id, id| {
+            backend.members.contains_key(&(guild_id, id))
+        });
+
+        for (guild_id, user_id) in guild_members {
+            if repair {
+                if let Some(mut names) = backend.member_search.get_mut(&guild_id) {
+                    names.retain(|(_, id)| *id != user_id);
+                }
+            }
+
+            dangling.push(DanglingRelation::GuildMember { guild_id, user_id });
+        }
+
+        let guild_presences =
+            dangling_relations(&backend.guild_presences, repair, |guild_id, id| {
+                backend.presences.contains_key(&(guild_id, id))
+            });
+        dangling.extend(
+            guild_presences
+                .into_iter()
+                .map(|(guild_id, user_id)| DanglingRelation::GuildPresence { guild_id, user_id }),
+        );
+
+        let guild_roles = dangling_relations(&backend.guild_roles, repair, |_, id| {
+            backend.roles.contains_key(&id)
+        });
+        dangling.extend(
+            guild_roles
+                .into_iter()
+                .map(|(guild_id, role_id)| DanglingRelation::GuildRole { guild_id, role_id }),
+        );
+
+        let guild_voice_states =
+            dangling_relations(&backend.guild_voice_states, repair, |guild_id, id| {
+                backend.voice_states.contains_key(&(guild_id, id))
+            });
+        dangling.extend(
+            guild_voice_states
+                .into_iter()
+                .map(|(guild_id, user_id)| DanglingRelation::GuildVoiceState { guild_id, user_id }),
+        );
+
+        for mut entry in backend.user_guilds.iter_mut() {
+            let user_id = *entry.key();
+
+            entry.value_mut().retain(|guild_id| {
+                if backend.guilds.contains_key(guild_id) {
+                    return true;
+                }
+
+                dangling.push(DanglingRelation::UserGuild {
+                    user_id,
+                    guild_id: *guild_id,
+                });
+
+                !repair
+            });
+        }
+
+        IntegrityReport::new(dangling, repair)
+    }
+
+    fn map_estimate<E: EntityExt>(&self) -> usize {
+        E::map(self)
+            .iter()
+            .map(|entry| mem::size_of::<E::Id>() + entry.value().estimated_size())
+            .sum()
+    }
+
     fn repo<T>(&self) -> InMemoryRepository<T> {
         InMemoryRepository(self.clone(), PhantomData)
     }
 }
 
+/// Approximate number of bytes used by the cached entities of each type.
+///
+/// Refer to [`InMemoryBackend::memory_estimate`] for more information.
+///
+/// [`InMemoryBackend::memory_estimate`]: struct.InMemoryBackend.html#method.memory_estimate
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemoryEstimate(Vec<(EntityType, usize)>);
+
+impl MemoryEstimate {
+    /// Return the approximate number of bytes used by entities of the given
+    /// types.
+    pub fn get(&self, entity_types: EntityType) -> usize {
+        self.0
+            .iter()
+            .filter(|(entity_type, _)| entity_types.contains(*entity_type))
+            .map(|(_, bytes)| bytes)
+            .sum()
+    }
+
+    /// Return an iterator over each entity type and the approximate number
+    /// of bytes used by its entities.
+    pub fn iter(&self) -> impl Iterator<Item = (EntityType, usize)> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Return the approximate number of bytes used by all entities.
+    pub fn total(&self) -> usize {
+        self.0.iter().map(|(_, bytes)| bytes).sum()
+    }
+}
+
 /// In memory implementation of a `twilight_cache` backend.
 ///
 /// **Note**: you should probably not be using the trait's methods directly, and
@@ -292,6 +769,7 @@ impl Backend for InMemoryBackend {
     type CurrentUserRepository = InMemoryCurrentUserRepository;
     type EmojiRepository = InMemoryEmojiRepository;
     type GroupRepository = InMemoryGroupRepository;
+    type GuildPreviewRepository = InMemoryGuildPreviewRepository;
     type GuildRepository = InMemoryGuildRepository;
     type MemberRepository = InMemoryMemberRepository;
     type MessageRepository = InMemoryMessageRepository;
@@ -301,6 +779,7 @@ impl Backend for InMemoryBackend {
     type TextChannelRepository = InMemoryTextChannelRepository;
     type UserRepository = InMemoryUserRepository;
     type VoiceChannelRepository = InMemoryVoiceChannelRepository;
+    type VoiceRegionRepository = InMemoryVoiceRegionRepository;
     type VoiceStateRepository = InMemoryVoiceStateRepository;
 
     /// A new instance of a repository for working with attachments.
@@ -328,6 +807,11 @@ impl Backend for InMemoryBackend {
         self.repo()
     }
 
+    /// A new instance of a repository for working with guild previews.
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        self.repo()
+    }
+
     /// A new instance of a repository for working with guilds.
     fn guilds(&self) -> Self::GuildRepository {
         self.repo()
@@ -373,61 +857,165 @@ impl Backend for InMemoryBackend {
         self.repo()
     }
 
+    /// A new instance of a repository for working with voice regions.
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        self.repo()
+    }
+
     /// A new instance of a repository for working with voice states.
     fn voice_states(&self) -> Self::VoiceStateRepository {
         self.repo()
     }
+
+    /// Whether the guild is allowed by the configured guild allowlist and
+    /// denylist.
+    fn caches_guild(&self, guild_id: GuildId) -> bool {
+        self.0.config.caches_guild(guild_id)
+    }
+
+    /// Whether the guild was pinned via its repository.
+    fn pins_guild(&self, guild_id: GuildId) -> bool {
+        self.0.pinned_guilds.contains(&guild_id)
+    }
+
+    /// Whether the user was pinned via its repository.
+    fn pins_user(&self, user_id: UserId) -> bool {
+        self.0.pinned_users.contains(&user_id)
+    }
+
+    /// Check the indexes of guilds' channels, emojis, members, presences,
+    /// roles, and voice states, the messages of channels, and the guilds of
+    /// users for IDs of entities that aren't cached.
+    fn verify_integrity(&self, repair: bool) -> VerifyIntegrityFuture<'_, Self::Error> {
+        future::ok(self.check_integrity(repair)).boxed()
+    }
+}
+
+/// Collect the relations of an index whose related entities aren't cached,
+/// removing them if `repair` is set.
+///
+/// The relations of each key stay locked while their entities are checked, so
+/// a relation added at the same time as its entity is never removed.
+fn dangling_relations<K: Copy + Eq + Hash, V: Copy + Ord>(
+    relations: &DashMap<K, BTreeSet<V>>,
+    repair: bool,
+    is_cached: impl Fn(K, V) -> bool,
+) -> Vec<(K, V)> {
+    let mut dangling = Vec::new();
+
+    for mut entry in relations.iter_mut() {
+        let key = *entry.key();
+
+        entry.value_mut().retain(|value| {
+            if is_cached(key, *value) {
+                return true;
+            }
+
+            dangling.push((key, *value));
+
+            !repair
+        });
+    }
+
+    dangling
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{prelude::*, InMemoryBackendBuilder};
-    use futures_util::stream::StreamExt;
+    use super::{
+        config::{EntityType, EvictionPolicy, GuildCapPolicy},
+        prelude::*,
+        repository::WouldBlock,
+        InMemoryBackendBuilder, MemoryEstimate,
+    };
+    use futures_util::{
+        future::{self, FutureExt, TryFutureExt},
+        stream::{self, StreamExt, TryStreamExt},
+    };
+    use rkyv::{with::DeserializeWith, Deserialize};
     use static_assertions::{assert_impl_all, assert_obj_safe};
-    use std::{error::Error, fmt::Debug};
+    use std::{
+        error::Error,
+        fmt::Debug,
+        fs,
+        io::{Result as IoResult, Write},
+        mem, process,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
     use twilight_cache::{
+        any::{AnyEntity, EntityKind},
+        archive,
+        cache::{CacheMode, ProcessErrorType},
+        cdn::ImageFormat,
+        compression::Compressor,
         entity::{
             channel::{
-                CategoryChannelEntity, GroupEntity, PrivateChannelEntity, TextChannelEntity,
+                AttachmentEntity, CategoryChannelEntity, ChannelEntity, GroupEntity,
+                GuildChannelEntity, MessageEntity, PrivateChannelEntity, TextChannelEntity,
                 VoiceChannelEntity,
             },
-            guild::{GuildEntity, MemberEntity},
-            user::{CurrentUserEntity, UserEntity},
+            gateway::PresenceEntity,
+            guild::{
+                EmojiEntity, GuildEntity, GuildPreviewEntity, MemberEntity, NameKind, RoleEntity,
+            },
+            user::{CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository},
+            voice::{VoiceRegionEntity, VoiceStateEntity, VoiceTransition},
         },
-        repository::SingleEntityRepository,
-        Backend,
+        export::{self, ExportType},
+        hook::CacheChange,
+        integrity::DanglingRelation,
+        intercept::EntityInterceptor,
+        partition::PartitionedBackend,
+        prune::Pruner,
+        recording::{self, Record, RecordedId, RecordingBackend},
+        repository::{
+            ConnectFuture, GetEntityFuture, HealthFuture, ListEntitiesFuture, ListEntityIdsFuture,
+            NoopRepository, RefreshingRepository, RemoveEntityFuture, SingleEntityRepository,
+            UpsertEntityFuture, UpsertOutcome,
+        },
+        size::EstimateSize,
+        snapshot::{self, Snapshot},
+        timestamp::ParsedTimestamp,
+        warm_start, Backend, Repository,
     };
     use twilight_model::{
         channel::{
-            message::{Message, MessageType},
+            message::{Message, MessageFlags, MessageReference, MessageType},
+            permission_overwrite::{PermissionOverwrite, PermissionOverwriteType},
             Attachment, CategoryChannel, Channel, ChannelType, Group, GuildChannel, PrivateChannel,
             TextChannel, VoiceChannel,
         },
         gateway::{
-            event::Event,
+            event::{Event, EventType},
             payload::{
                 ChannelCreate, ChannelDelete, ChannelPinsUpdate, ChannelUpdate, GuildCreate,
                 GuildDelete, GuildEmojisUpdate, GuildUpdate, MemberAdd, MemberChunk, MemberRemove,
                 MemberUpdate, MessageCreate, MessageDelete, MessageDeleteBulk, MessageUpdate,
-                PresenceUpdate, Ready, RoleCreate, RoleDelete, RoleUpdate, UserUpdate,
-                VoiceStateUpdate,
+                PresenceUpdate, Ready, RoleCreate, RoleDelete, RoleUpdate, UnavailableGuild,
+                UserUpdate, VoiceStateUpdate,
             },
-            presence::{ClientStatus, Presence, Status, UserOrId},
+            presence::{Activity, ActivityType, ClientStatus, Presence, Status, UserOrId},
         },
         guild::{
             member::Member, DefaultMessageNotificationLevel, Emoji, ExplicitContentFilter, Guild,
-            MfaLevel, PartialGuild, PartialMember, Permissions, PremiumTier, Role,
+            GuildStatus, MfaLevel, PartialGuild, PartialMember, Permissions, PremiumTier, Role,
             SystemChannelFlags, VerificationLevel,
         },
-        id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId},
-        user::{CurrentUser, User},
+        id::{ApplicationId, AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId},
+        user::{CurrentUser, PremiumType, User, UserFlags},
         voice::VoiceState,
     };
 
     assert_impl_all!(InMemoryBackendBuilder: Clone, Debug, Default, Send, Sync);
     assert_impl_all!(InMemoryBackendError: Clone, Debug, Error, Send, Sync);
+    assert_impl_all!(WouldBlock: Clone, Debug, Error, Send, Sync);
     assert_impl_all!(InMemoryBackend: Backend, Clone, Debug, Send, Sync);
     assert_impl_all!(InMemoryCache: Clone, Debug, Send, Sync);
+    assert_impl_all!(MemoryEstimate: Clone, Debug, Send, Sync);
     assert_obj_safe!(
         InMemoryBackendBuilder,
         InMemoryBackendError,
@@ -548,13 +1136,13 @@ mod tests {
                 user: User {
                     avatar: None,
                     bot: true,
-                    discriminator: format!("0{}", i),
+                    discriminator: format!("0{i}"),
                     email: None,
                     flags: None,
                     id: UserId(i),
                     locale: Some(String::from("en-US")),
                     mfa_enabled: None,
-                    name: format!("user{}", i),
+                    name: format!("user{i}"),
                     premium_type: None,
                     public_flags: None,
                     system: Some(false),
@@ -614,6 +1202,26 @@ mod tests {
         }
     }
 
+    fn activity(kind: ActivityType, name: &str) -> Activity {
+        Activity {
+            application_id: None,
+            assets: None,
+            created_at: None,
+            details: None,
+            emoji: None,
+            flags: None,
+            id: None,
+            instance: None,
+            kind,
+            name: name.to_owned(),
+            party: None,
+            secrets: None,
+            state: None,
+            timestamps: None,
+            url: None,
+        }
+    }
+
     fn voice_state() -> VoiceState {
         VoiceState {
             channel_id: Some(ChannelId(6)),
@@ -621,6 +1229,7 @@ mod tests {
             guild_id: Some(GuildId(1)),
             member: Some(member()),
             mute: false,
+            request_to_speak_timestamp: None,
             self_deaf: false,
             self_mute: false,
             self_stream: false,
@@ -633,7 +1242,7 @@ mod tests {
 
     fn role() -> Role {
         Role {
-            color: 0x000000,
+            color: 0x00_00_00,
             hoist: false,
             id: RoleId(12),
             managed: false,
@@ -697,7 +1306,9 @@ mod tests {
             permission_overwrites: Vec::new(),
             parent_id: Some(ChannelId(4)),
             position: 3,
+            rtc_region: None,
             user_limit: Some(3),
+            video_quality_mode: None,
         }
     }
 
@@ -713,7 +1324,8 @@ mod tests {
 
     fn attachment(id: u64) -> Attachment {
         Attachment {
-            filename: format!("filename{}.png", id),
+            content_type: Some(String::from("image/png")),
+            filename: format!("filename{id}.png"),
             height: Some(600),
             id: AttachmentId(id),
             proxy_url: String::from("proxy url"),
@@ -733,7 +1345,7 @@ mod tests {
                 attachments: vec![attachment(i + 100)],
                 author: user(),
                 channel_id: ChannelId(5),
-                content: format!("test {}", i),
+                content: format!("test {i}"),
                 edited_timestamp: None,
                 embeds: Vec::new(),
                 flags: None,
@@ -747,6 +1359,7 @@ mod tests {
                     nick: None,
                     premium_since: None,
                     roles: Vec::new(),
+                    user: None,
                 }),
                 mention_channels: Vec::new(),
                 mention_everyone: false,
@@ -767,11 +1380,9 @@ mod tests {
     }
 
     fn guild() -> Guild {
-        let mut members = Vec::new();
-        members.push(member());
+        let members = vec![member()];
 
-        let mut presences = Vec::new();
-        presences.push(Presence {
+        let presences = vec![Presence {
             activities: Vec::new(),
             client_status: ClientStatus {
                 desktop: None,
@@ -781,7 +1392,7 @@ mod tests {
             guild_id: GuildId(1),
             status: Status::Offline,
             user: UserOrId::UserId { id: UserId(2) },
-        });
+        }];
 
         Guild {
             afk_channel_id: None,
@@ -809,6 +1420,7 @@ mod tests {
             members,
             mfa_level: MfaLevel::None,
             name: String::from("guild"),
+            nsfw: false,
             owner_id: UserId(2),
             owner: Some(true),
             permissions: None,
@@ -850,6 +1462,7 @@ mod tests {
             member_count: Some(1),
             mfa_level: MfaLevel::None,
             name: String::from("new guild"),
+            nsfw: false,
             owner_id: UserId(2),
             owner: Some(true),
             permissions: None,
@@ -869,6 +1482,112 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_interaction_create() -> Result<(), Box<dyn Error>> {
+        use twilight_model::{
+            application::interaction::{
+                application_command::{
+                    ApplicationCommand, CommandData, CommandInteractionDataResolved,
+                    InteractionChannel, InteractionMember,
+                },
+                Interaction, InteractionType,
+            },
+            gateway::payload::InteractionCreate,
+            id::{ApplicationId, CommandId, InteractionId},
+        };
+
+        let cache = InMemoryCache::new();
+        cache
+            .process(&Event::ChannelCreate(ChannelCreate(Channel::Guild(
+                GuildChannel::Text(text()),
+            ))))
+            .await?;
+        cache
+            .members
+            .upsert(MemberEntity::from(Member {
+                deaf: true,
+                ..member2()
+            }))
+            .await?;
+
+        let command = ApplicationCommand {
+            application_id: ApplicationId(1),
+            channel_id: ChannelId(5),
+            data: CommandData {
+                id: CommandId(1),
+                name: String::from("command"),
+                options: Vec::new(),
+                resolved: Some(CommandInteractionDataResolved {
+                    channels: vec![InteractionChannel {
+                        id: ChannelId(5),
+                        kind: ChannelType::GuildText,
+                        name: String::from("renamed"),
+                        permissions: Permissions::empty(),
+                    }],
+                    members: vec![InteractionMember {
+                        hoisted_role: None,
+                        id: UserId(9),
+                        joined_at: None,
+                        nick: Some(String::from("nick")),
+                        premium_since: None,
+                        roles: vec![RoleId(12)],
+                    }],
+                    roles: vec![role()],
+                    users: vec![user2()],
+                }),
+            },
+            guild_id: Some(GuildId(1)),
+            id: InteractionId(1),
+            kind: InteractionType::ApplicationCommand,
+            member: Some(PartialMember {
+                deaf: false,
+                joined_at: Some(String::from("2012-11-21T10:00:00.40000+00:00")),
+                mute: false,
+                nick: Some(String::from("invoker")),
+                premium_since: None,
+                roles: Vec::new(),
+                user: Some(user()),
+            }),
+            token: String::from("token"),
+            user: None,
+        };
+        cache
+            .process(&Event::InteractionCreate(Box::new(InteractionCreate(
+                Interaction::ApplicationCommand(Box::new(command)),
+            ))))
+            .await?;
+
+        assert!(cache.users.get(UserId(2)).await?.is_some());
+        assert!(cache.users.get(UserId(9)).await?.is_some());
+        assert_eq!(
+            cache
+                .members
+                .get((GuildId(1), UserId(2)))
+                .await?
+                .unwrap()
+                .nick
+                .as_deref(),
+            Some("invoker")
+        );
+
+        let member = cache.members.get((GuildId(1), UserId(9))).await?.unwrap();
+        assert!(member.deaf);
+        assert_eq!(
+            member.joined_at.as_deref(),
+            Some("2012-11-21T11:00:00.40000+00:00")
+        );
+        assert_eq!(member.nick.as_deref(), Some("nick"));
+        assert_eq!(member.role_ids, [RoleId(12)]);
+
+        assert!(cache.roles.get(RoleId(12)).await?.is_some());
+        assert_eq!(
+            cache.text_channels.get(ChannelId(5)).await?.unwrap().name,
+            "renamed"
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_inmemory_cache() {
         let cache = InMemoryCache::new();
@@ -915,6 +1634,7 @@ mod tests {
                 icon: None,
                 id: GuildId(1),
                 joined_at: Some(String::from("2012-11-21T10:00:00.40000+00:00")),
+                parsed_joined_at: ParsedTimestamp::default(),
                 large: false,
                 lazy: None,
                 max_members: None,
@@ -929,6 +1649,7 @@ mod tests {
                 preferred_locale: String::from("en-US"),
                 premium_subscription_count: Some(0),
                 premium_tier: PremiumTier::None,
+                previous_owner_ids: Vec::new(),
                 region: String::from("us-east"),
                 rules_channel_id: None,
                 splash: None,
@@ -951,16 +1672,19 @@ mod tests {
                 guild_id: GuildId(1),
                 hoisted_role_id: None,
                 joined_at: Some(String::from("2012-11-21T10:00:00.40000+00:00")),
+                parsed_joined_at: ParsedTimestamp::default(),
                 mute: false,
                 nick: None,
                 pending: false,
                 premium_since: None,
+                parsed_premium_since: ParsedTimestamp::default(),
                 role_ids: Vec::new(),
                 user_id: UserId(2),
             }
         );
 
         assert!(member_ids.next().await.is_none());
+        drop(member_ids);
 
         assert_eq!(
             cache.guilds.owner(GuildId(1)).await.unwrap().unwrap(),
@@ -1002,6 +1726,7 @@ mod tests {
                 kind: ChannelType::Group,
                 last_message_id: None,
                 last_pin_timestamp: None,
+                parsed_last_pin_timestamp: ParsedTimestamp::default(),
                 name: Some(String::from("group")),
                 owner_id: UserId(2),
                 recipient_ids: vec![UserId(2), UserId(9)],
@@ -1046,6 +1771,7 @@ mod tests {
                 kind: ChannelType::GuildText,
                 last_message_id: None,
                 last_pin_timestamp: None,
+                parsed_last_pin_timestamp: ParsedTimestamp::default(),
                 name: String::from("text"),
                 nsfw: false,
                 permission_overwrites: Vec::new(),
@@ -1076,6 +1802,7 @@ mod tests {
                 permission_overwrites: Vec::new(),
                 parent_id: Some(ChannelId(4)),
                 position: 3,
+                rtc_region: None,
                 user_limit: Some(3),
             }
         );
@@ -1094,6 +1821,7 @@ mod tests {
                 id: ChannelId(7),
                 last_message_id: None,
                 last_pin_timestamp: None,
+                parsed_last_pin_timestamp: ParsedTimestamp::default(),
                 kind: ChannelType::Private,
                 recipient_id: Some(UserId(9)),
             }
@@ -1120,10 +1848,12 @@ mod tests {
                 guild_id: GuildId(1),
                 hoisted_role_id: None,
                 joined_at: Some(String::from("2012-11-21T11:00:00.40000+00:00")),
+                parsed_joined_at: ParsedTimestamp::default(),
                 mute: false,
                 nick: None,
                 pending: false,
                 premium_since: None,
+                parsed_premium_since: ParsedTimestamp::default(),
                 role_ids: Vec::new(),
                 user_id: UserId(9),
             }
@@ -1235,8 +1965,10 @@ mod tests {
 
         // member update
         let member3 = MemberUpdate {
+            deaf: None,
             guild_id: GuildId(1),
             joined_at: String::from("2012-11-21T11:00:00.40000+00:00"),
+            mute: None,
             nick: None,
             pending: false,
             premium_since: None,
@@ -1317,8 +2049,7 @@ mod tests {
         );
 
         // guild emojis update
-        let mut emojis = Vec::new();
-        emojis.push(emoji());
+        let emojis = vec![emoji()];
 
         let event = Event::GuildEmojisUpdate(GuildEmojisUpdate {
             emojis,
@@ -1517,4 +2248,3901 @@ mod tests {
 
         assert_eq!(cache.guilds.get(GuildId(1)).await.unwrap(), None);
     }
+
+    #[test]
+    fn test_archive_round_trip() -> Result<(), Box<dyn Error>> {
+        let mut user = UserEntity::from(user());
+        user.premium_type = Some(PremiumType::Nitro);
+        user.public_flags = Some(UserFlags::VERIFIED_BOT_DEVELOPER);
+
+        let bytes = rkyv::to_bytes::<_, 256>(&user)?;
+        let archived = rkyv::check_archived_root::<UserEntity>(&bytes).unwrap();
+        assert_eq!(2, archived.id);
+        assert_eq!("user", archived.name.as_str());
+        assert_eq!(
+            user.premium_type,
+            archive::from_json::<Option<PremiumType>>(&archived.premium_type)?
+        );
+
+        let deserialized: UserEntity = archived.deserialize(&mut archive::JsonDeserializer)?;
+        assert_eq!(user, deserialized);
+
+        // invalid json fields fail deserialization instead of panicking
+        let invalid = rkyv::to_bytes::<_, 256>(&b"{".to_vec())?;
+        let invalid = rkyv::check_archived_root::<Vec<u8>>(&invalid).unwrap();
+        assert!(
+            <archive::AsJson as DeserializeWith<_, Option<PremiumType>, _>>::deserialize_with(
+                invalid,
+                &mut archive::JsonDeserializer
+            )
+            .is_err()
+        );
+
+        // truncated bytes fail validation
+        assert!(rkyv::check_archived_root::<UserEntity>(&bytes[..bytes.len() / 2]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp_accessors() -> Result<(), Box<dyn Error>> {
+        let mut message = MessageEntity::builder(MessageId(1), ChannelId(2), UserId(3));
+        message.timestamp("2021-01-01T12:00:00.000000+00:00");
+        let mut message = message.build();
+
+        assert_eq!(
+            1_609_502_400,
+            message.timestamp_datetime()?.unix_timestamp()
+        );
+        assert_eq!(None, message.edited_timestamp_datetime()?);
+
+        // changes to a timestamp after it was parsed aren't hidden by the cache
+        message.edited_timestamp = Some(String::from("2021-01-01T13:00:00+00:00"));
+        assert_eq!(
+            Some(1_609_506_000),
+            message
+                .edited_timestamp_datetime()?
+                .map(|edited| edited.unix_timestamp())
+        );
+        message.timestamp = String::from("not a timestamp");
+        assert!(message.timestamp_datetime().is_err());
+
+        let mut member = MemberEntity::builder(GuildId(1), UserId(2));
+        member.joined_at("yesterday");
+        let member = member.build();
+        assert!(member.joined_at_datetime().is_err());
+        assert_eq!(None, member.premium_since_datetime()?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_member_update_uncached() {
+        let cache = InMemoryCache::new();
+
+        let event = Event::MemberUpdate(Box::new(MemberUpdate {
+            deaf: Some(true),
+            guild_id: GuildId(1),
+            joined_at: String::from("2012-11-21T11:00:00.40000+00:00"),
+            mute: None,
+            nick: Some(String::from("nick")),
+            pending: false,
+            premium_since: None,
+            roles: vec![RoleId(12)],
+            user: user2(),
+        }));
+        let _ = cache.process(&event).await;
+
+        let mut member = MemberEntity::builder(GuildId(1), UserId(9));
+        member
+            .deaf(true)
+            .joined_at("2012-11-21T11:00:00.40000+00:00")
+            .nick("nick")
+            .role_ids(vec![RoleId(12)]);
+
+        assert_eq!(
+            cache
+                .members
+                .get((GuildId(1), UserId(9)))
+                .await
+                .unwrap()
+                .unwrap(),
+            member.build()
+        );
+        assert_eq!(
+            cache.users.get(UserId(9)).await,
+            Ok(Some(UserEntity::from(user2())))
+        );
+    }
+
+    fn message_entity(id: u64, channel_id: u64) -> MessageEntity {
+        MessageEntity::builder(MessageId(id), ChannelId(channel_id), UserId(2)).build()
+    }
+
+    fn cached_message_ids(backend: &InMemoryBackend) -> Vec<MessageId> {
+        let mut ids = backend
+            .0
+            .messages
+            .iter()
+            .map(|r| *r.key())
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        ids
+    }
+
+    async fn evict_with(policy: EvictionPolicy) -> Vec<MessageId> {
+        let mut builder = InMemoryBackend::builder();
+        builder
+            .message_cache_size(2)
+            .message_eviction_policy(policy);
+        let backend = builder.build();
+        let messages = backend.messages();
+
+        messages.upsert(message_entity(2, 1)).await.unwrap();
+        messages.upsert(message_entity(3, 1)).await.unwrap();
+        messages.get(MessageId(2)).await.unwrap();
+        messages.upsert(message_entity(1, 1)).await.unwrap();
+
+        cached_message_ids(&backend)
+    }
+
+    #[tokio::test]
+    async fn test_message_eviction_policies() {
+        assert_eq!(
+            vec![MessageId(2), MessageId(3)],
+            evict_with(EvictionPolicy::OldestId).await
+        );
+        assert_eq!(
+            vec![MessageId(1), MessageId(2)],
+            evict_with(EvictionPolicy::NewestId).await
+        );
+        assert_eq!(
+            vec![MessageId(1), MessageId(3)],
+            evict_with(EvictionPolicy::LeastRecentlyInserted).await
+        );
+        assert_eq!(
+            vec![MessageId(1), MessageId(2)],
+            evict_with(EvictionPolicy::LeastRecentlyAccessed).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_eviction_removes_attachments() -> Result<(), Box<dyn Error>> {
+        let mut builder = InMemoryBackend::builder();
+        builder
+            .message_cache_size(2)
+            .channel_message_cache_size(ChannelId(1), 1);
+        let backend = builder.build();
+        let messages = backend.messages();
+        let attachments = backend.attachments();
+
+        for id in 1..=2 {
+            let mut message = MessageEntity::builder(MessageId(id), ChannelId(1), UserId(2));
+            message.attachments(vec![AttachmentId(id + 100)]);
+            messages.upsert(message.build()).await?;
+            attachments
+                .upsert(AttachmentEntity::builder(AttachmentId(id + 100), MessageId(id)).build())
+                .await?;
+        }
+
+        // evicted for the channel's overridden limit
+        assert!(messages.get(MessageId(1)).await?.is_none());
+        assert!(attachments.get(AttachmentId(101)).await?.is_none());
+        assert!(attachments.get(AttachmentId(102)).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_message_cache_size_override() {
+        let mut builder = InMemoryBackend::builder();
+        builder
+            .message_cache_size(2)
+            .channel_message_cache_size(ChannelId(1), 1);
+        let backend = builder.build();
+        let messages = backend.messages();
+
+        for id in 1..=3 {
+            messages.upsert(message_entity(id, 1)).await.unwrap();
+            messages.upsert(message_entity(id + 10, 2)).await.unwrap();
+        }
+
+        assert_eq!(
+            vec![MessageId(3), MessageId(12), MessageId(13)],
+            cached_message_ids(&backend)
+        );
+
+        messages.remove(MessageId(3)).await.unwrap();
+        messages.upsert(message_entity(4, 1)).await.unwrap();
+        assert_eq!(
+            vec![MessageId(4), MessageId(12), MessageId(13)],
+            cached_message_ids(&backend)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_total_messages_removes_attachments() -> Result<(), Box<dyn Error>> {
+        let mut builder = InMemoryBackend::builder();
+        builder.message_cache_size(1).max_total_messages(2);
+        let backend = builder.build();
+        let messages = backend.messages();
+        let attachments = backend.attachments();
+
+        for id in 1..=3 {
+            let mut message = MessageEntity::builder(MessageId(id), ChannelId(id), UserId(2));
+            message.attachments(vec![AttachmentId(id + 100)]);
+            messages.upsert(message.build()).await?;
+            attachments
+                .upsert(AttachmentEntity::builder(AttachmentId(id + 100), MessageId(id)).build())
+                .await?;
+        }
+
+        // evicted for the total number of messages
+        assert!(attachments.get(AttachmentId(101)).await?.is_none());
+
+        // evicted for the channel's limit
+        messages.upsert(message_entity(4, 2)).await?;
+        assert!(messages.get(MessageId(2)).await?.is_none());
+        assert!(attachments.get(AttachmentId(102)).await?.is_none());
+        assert!(attachments.get(AttachmentId(103)).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_total_messages() {
+        let mut builder = InMemoryBackend::builder();
+        builder.message_cache_size(2).max_total_messages(3);
+        let backend = builder.build();
+        let messages = backend.messages();
+
+        messages.upsert(message_entity(1, 1)).await.unwrap();
+        messages.upsert(message_entity(2, 2)).await.unwrap();
+        messages.upsert(message_entity(3, 3)).await.unwrap();
+        messages.upsert(message_entity(4, 3)).await.unwrap();
+        assert_eq!(
+            vec![MessageId(2), MessageId(3), MessageId(4)],
+            cached_message_ids(&backend)
+        );
+
+        // Evicting for the channel's own limit doesn't evict globally.
+        messages.upsert(message_entity(5, 3)).await.unwrap();
+        assert_eq!(
+            vec![MessageId(2), MessageId(4), MessageId(5)],
+            cached_message_ids(&backend)
+        );
+
+        messages.remove(MessageId(2)).await.unwrap();
+        messages.upsert(message_entity(6, 1)).await.unwrap();
+        assert_eq!(
+            vec![MessageId(4), MessageId(5), MessageId(6)],
+            cached_message_ids(&backend)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_guild_stats() {
+        let backend = InMemoryBackend::new();
+        let guilds = backend.guilds();
+
+        assert_eq!(None, guilds.stats(GuildId(1)).await.unwrap());
+
+        guilds
+            .upsert(GuildEntity::builder(GuildId(1), "guild", UserId(1)).build())
+            .await
+            .unwrap();
+
+        let mut text_channel = TextChannelEntity::builder(ChannelId(1), "general");
+        text_channel.guild_id(GuildId(1));
+        backend
+            .text_channels()
+            .upsert(text_channel.build())
+            .await
+            .unwrap();
+        let mut voice_channel = VoiceChannelEntity::builder(ChannelId(2), "voice");
+        voice_channel.guild_id(GuildId(1));
+        backend
+            .voice_channels()
+            .upsert(voice_channel.build())
+            .await
+            .unwrap();
+        backend
+            .roles()
+            .upsert(RoleEntity::builder(RoleId(1), GuildId(1), "@everyone").build())
+            .await
+            .unwrap();
+
+        for id in 1..=3 {
+            let mut presence = PresenceEntity::builder(GuildId(1), UserId(id));
+
+            if id == 3 {
+                presence.status(Status::Idle);
+            }
+
+            backend.presences().upsert(presence.build()).await.unwrap();
+            backend
+                .members()
+                .upsert(MemberEntity::builder(GuildId(1), UserId(id)).build())
+                .await
+                .unwrap();
+        }
+
+        backend
+            .voice_states()
+            .upsert(VoiceStateEntity::builder(GuildId(1), UserId(1), "session").build())
+            .await
+            .unwrap();
+        backend
+            .members()
+            .remove((GuildId(1), UserId(3)))
+            .await
+            .unwrap();
+
+        let stats = guilds.stats(GuildId(1)).await.unwrap().unwrap();
+        assert_eq!(2, stats.channels());
+        assert_eq!(1, stats.text_channels);
+        assert_eq!(1, stats.voice_channels);
+        assert_eq!(0, stats.emojis);
+        assert_eq!(2, stats.members);
+        assert_eq!(1, stats.roles);
+        assert_eq!(1, stats.voice_states);
+        assert_eq!(2, stats.presences.online);
+        assert_eq!(1, stats.presences.idle);
+        assert_eq!(3, stats.presences.total());
+    }
+
+    #[tokio::test]
+    async fn test_max_entity_size() {
+        let rejected = Arc::new(Mutex::new(Vec::new()));
+        let hook_rejected = Arc::clone(&rejected);
+
+        let mut builder = InMemoryBackend::builder();
+        builder
+            .max_entity_size(1024)
+            .oversized_entity_hook(move |entity_type, size| {
+                hook_rejected.lock().unwrap().push((entity_type, size));
+            });
+        let backend = builder.build();
+        let messages = backend.messages();
+
+        messages.upsert(message_entity(1, 1)).await.unwrap();
+
+        let mut message = MessageEntity::builder(MessageId(2), ChannelId(1), UserId(2));
+        message.content("a".repeat(2000));
+        let message = message.build();
+        let size = message.estimated_size();
+        messages.upsert(message).await.unwrap();
+
+        assert_eq!(vec![MessageId(1)], cached_message_ids(&backend));
+        assert_eq!(vec![(EntityType::MESSAGE, size)], *rejected.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_crossposts() -> Result<(), Box<dyn Error>> {
+        let backend = InMemoryBackend::new();
+        let messages = backend.messages();
+        let source = MessageReference {
+            channel_id: Some(ChannelId(1)),
+            guild_id: Some(GuildId(1)),
+            message_id: Some(MessageId(1)),
+        };
+
+        let mut announcement = MessageEntity::builder(MessageId(1), ChannelId(1), UserId(2));
+        announcement.flags(MessageFlags::CROSSPOSTED);
+        messages.upsert(announcement.build()).await?;
+
+        let mut crosspost = MessageEntity::builder(MessageId(2), ChannelId(2), UserId(2));
+        crosspost
+            .flags(MessageFlags::IS_CROSSPOST)
+            .reference(source.clone());
+        let crosspost = crosspost.build();
+        messages.upsert(crosspost.clone()).await?;
+
+        let mut reply = MessageEntity::builder(MessageId(3), ChannelId(1), UserId(3));
+        reply.kind(MessageType::Reply).reference(source);
+        messages.upsert(reply.build()).await?;
+
+        assert!(messages.get(MessageId(1)).await?.unwrap().is_crossposted());
+        assert!(crosspost.is_crosspost());
+
+        let crossposts = messages
+            .crossposts_of(MessageId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![crosspost], crossposts);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_by_guild() {
+        let backend = InMemoryBackend::new();
+
+        for (id, guild_id) in &[(1, 1), (2, 1), (3, 2)] {
+            backend
+                .members()
+                .upsert(MemberEntity::builder(GuildId(*guild_id), UserId(*id)).build())
+                .await
+                .unwrap();
+        }
+
+        let mut text_channel = TextChannelEntity::builder(ChannelId(1), "general");
+        text_channel.guild_id(GuildId(1));
+        backend
+            .text_channels()
+            .upsert(text_channel.build())
+            .await
+            .unwrap();
+        let mut voice_channel = VoiceChannelEntity::builder(ChannelId(2), "voice");
+        voice_channel.guild_id(GuildId(1));
+        backend
+            .voice_channels()
+            .upsert(voice_channel.build())
+            .await
+            .unwrap();
+
+        let mut user_ids = backend
+            .members()
+            .list_by_guild(GuildId(1))
+            .await
+            .unwrap()
+            .map(|member| member.unwrap().user_id)
+            .collect::<Vec<_>>()
+            .await;
+        user_ids.sort();
+        assert_eq!(vec![UserId(1), UserId(2)], user_ids);
+
+        let channel_ids = backend
+            .text_channels()
+            .list_by_guild(GuildId(1))
+            .await
+            .unwrap()
+            .map(|channel| channel.unwrap().id)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(vec![ChannelId(1)], channel_ids);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_guild() {
+        let cache = InMemoryCache::new();
+
+        let mut guild = guild();
+        guild.channels = vec![GuildChannel::Text(text()), GuildChannel::Voice(voice())];
+        guild.members.push(member2());
+        guild.roles = vec![role()];
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild.clone()))))
+            .await
+            .unwrap();
+        assert!(cache
+            .voice_channels
+            .get(ChannelId(6))
+            .await
+            .unwrap()
+            .is_some());
+
+        guild.channels = vec![GuildChannel::Text(text())];
+        guild.members = vec![member()];
+        guild.name = String::from("renamed");
+        guild.roles = Vec::new();
+        cache.reconcile_guild(GuildId(1), &guild).await.unwrap();
+
+        assert!(cache
+            .text_channels
+            .get(ChannelId(5))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(cache
+            .voice_channels
+            .get(ChannelId(6))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(cache.roles.get(RoleId(12)).await.unwrap().is_none());
+        assert!(cache
+            .members
+            .get((GuildId(1), UserId(2)))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(cache
+            .members
+            .get((GuildId(1), member2().user.id))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            "renamed",
+            cache.guilds.get(GuildId(1)).await.unwrap().unwrap().name
+        );
+
+        // Members aren't reconciled when the guild contains none.
+        guild.members = Vec::new();
+        cache.reconcile_guild(GuildId(1), &guild).await.unwrap();
+        assert!(cache
+            .members
+            .get((GuildId(1), UserId(2)))
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_prune() {
+        let cache = InMemoryCache::new();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            * 1000;
+        let recent_id = (now - 1_420_070_400_000) << 22;
+
+        cache.messages.upsert(message_entity(1, 1)).await.unwrap();
+        cache
+            .messages
+            .upsert(message_entity(recent_id, 1))
+            .await
+            .unwrap();
+        cache
+            .attachments
+            .upsert(AttachmentEntity::builder(AttachmentId(1), MessageId(1)).build())
+            .await
+            .unwrap();
+        cache
+            .attachments
+            .upsert(AttachmentEntity::builder(AttachmentId(2), MessageId(recent_id)).build())
+            .await
+            .unwrap();
+
+        let mut builder = Pruner::builder();
+        builder
+            .message_max_age(Duration::from_secs(90))
+            .orphaned_attachments(true);
+        let stats = cache.prune(&builder.build()).await.unwrap();
+
+        assert_eq!(1, stats.messages);
+        assert_eq!(1, stats.attachments);
+        assert_eq!(
+            vec![MessageId(recent_id)],
+            cached_message_ids(cache.backend())
+        );
+        assert!(cache
+            .attachments
+            .get(AttachmentId(2))
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pins() -> Result<(), Box<dyn Error>> {
+        let mut builder = InMemoryBackend::builder();
+        builder
+            .guild_cap_policy(GuildCapPolicy::EvictLeastRecentlyInserted)
+            .max_cached_members_per_guild(2);
+        let cache = InMemoryCache::with_backend(builder.build());
+
+        cache.guilds.pin(GuildId(2));
+        cache.users.pin(UserId(1));
+        assert!(cache.guilds.is_pinned(GuildId(2)));
+        assert!(cache.backend().pins_user(UserId(1)));
+        assert!(!cache.backend().pins_guild(GuildId(1)));
+
+        for guild_id in 1..=2 {
+            for user_id in 1..=4 {
+                cache
+                    .members
+                    .upsert(MemberEntity::builder(GuildId(guild_id), UserId(user_id)).build())
+                    .await?;
+            }
+        }
+
+        // the pinned user is never evicted, and the pinned guild isn't capped
+        let member_ids = |guild_id| {
+            cache
+                .guilds
+                .member_ids(guild_id)
+                .and_then(|ids| ids.try_collect::<Vec<_>>())
+        };
+        assert_eq!(vec![UserId(1), UserId(4)], member_ids(GuildId(1)).await?);
+        assert_eq!(4, member_ids(GuildId(2)).await?.len());
+
+        // with only pinned users left to evict, new members are refused
+        cache.users.pin(UserId(4));
+        cache
+            .members
+            .upsert(MemberEntity::builder(GuildId(1), UserId(5)).build())
+            .await?;
+        assert_eq!(vec![UserId(1), UserId(4)], member_ids(GuildId(1)).await?);
+
+        for (guild_id, user_id) in [(1, 1), (1, 3), (2, 3)] {
+            let mut presence = PresenceEntity::builder(GuildId(guild_id), UserId(user_id));
+            presence.status(Status::Offline);
+            cache.presences.upsert(presence.build()).await?;
+        }
+
+        let mut builder = Pruner::builder();
+        builder.offline_presences(true);
+        let stats = cache.prune(&builder.build()).await?;
+        assert_eq!(1, stats.presences);
+        assert!(cache
+            .presences
+            .get((GuildId(1), UserId(3)))
+            .await?
+            .is_none());
+
+        cache.guilds.unpin(GuildId(2));
+        assert!(!cache.guilds.is_pinned(GuildId(2)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_channel_tree() {
+        let cache = InMemoryCache::new();
+        assert_eq!(None, cache.guilds.channel_tree(GuildId(1)).await.unwrap());
+
+        let mut guild = guild();
+        let mut text2 = text();
+        text2.id = ChannelId(7);
+        text2.parent_id = None;
+        let mut text3 = text();
+        text3.id = ChannelId(8);
+        text3.position = 1;
+        guild.channels = vec![
+            GuildChannel::Voice(voice()),
+            GuildChannel::Text(text()),
+            GuildChannel::Text(text2),
+            GuildChannel::Text(text3),
+            GuildChannel::Category(category()),
+        ];
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild))))
+            .await
+            .unwrap();
+
+        let tree = cache
+            .guilds
+            .channel_tree(GuildId(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(1, tree.categories.len());
+        let category = &tree.categories[0];
+        assert_eq!(ChannelId(4), category.category.id);
+        assert_eq!(
+            vec![ChannelId(8), ChannelId(5)],
+            category
+                .text_channels
+                .iter()
+                .map(|c| c.id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(ChannelId(6), category.voice_channels[0].id);
+        assert_eq!(ChannelId(7), tree.text_channels[0].id);
+        assert!(tree.voice_channels.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_voice_state_session_duration() {
+        let cache = InMemoryCache::new();
+        assert_eq!(
+            None,
+            cache
+                .voice_states
+                .session_duration(GuildId(1), UserId(2))
+                .await
+                .unwrap()
+        );
+
+        let event = Event::VoiceStateUpdate(Box::new(VoiceStateUpdate(voice_state())));
+        cache.process(&event).await.unwrap();
+        let joined_at = cache
+            .voice_states
+            .get((GuildId(1), UserId(2)))
+            .await
+            .unwrap()
+            .unwrap()
+            .joined_channel_at;
+        assert!(joined_at.is_some());
+        assert!(cache
+            .voice_states
+            .session_duration(GuildId(1), UserId(2))
+            .await
+            .unwrap()
+            .is_some());
+
+        // muting doesn't restart the session
+        let mut muted = voice_state();
+        muted.self_mute = true;
+        let event = Event::VoiceStateUpdate(Box::new(VoiceStateUpdate(muted)));
+        cache.process(&event).await.unwrap();
+        let state = cache
+            .voice_states
+            .get((GuildId(1), UserId(2)))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(joined_at, state.joined_channel_at);
+
+        let mut left = voice_state();
+        left.channel_id = None;
+        let event = Event::VoiceStateUpdate(Box::new(VoiceStateUpdate(left)));
+        cache.process(&event).await.unwrap();
+        assert_eq!(
+            None,
+            cache
+                .voice_states
+                .session_duration(GuildId(1), UserId(2))
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_presences_by_activity() {
+        for index in [false, true] {
+            let mut builder = InMemoryBackend::builder();
+            builder.index_presence_activities(index);
+            let presences = builder.build().presences();
+
+            let mut game = activity(ActivityType::Playing, "game");
+            game.application_id = Some(ApplicationId(10));
+            let activities = vec![
+                vec![game.clone()],
+                vec![activity(ActivityType::Streaming, "game")],
+                vec![activity(ActivityType::Playing, "other")],
+            ];
+
+            for (id, activities) in (1..).zip(activities) {
+                let mut presence = PresenceEntity::builder(GuildId(1), UserId(id));
+                presence.activities(activities);
+                presences.upsert(presence.build()).await.unwrap();
+            }
+
+            let mut by_name = presences
+                .by_activity(GuildId(1), "game".into())
+                .await
+                .unwrap()
+                .map(|presence| presence.unwrap().user_id)
+                .collect::<Vec<_>>()
+                .await;
+            by_name.sort();
+            assert_eq!(vec![UserId(1), UserId(2)], by_name);
+
+            let by_application = presences
+                .by_activity(GuildId(1), ApplicationId(10).into())
+                .await
+                .unwrap()
+                .map(|presence| presence.unwrap().user_id)
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(vec![UserId(1)], by_application);
+
+            let streaming = presences
+                .streaming(GuildId(1))
+                .await
+                .unwrap()
+                .map(|presence| presence.unwrap().user_id)
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(vec![UserId(2)], streaming);
+
+            // the index follows activity changes
+            let mut presence = PresenceEntity::builder(GuildId(1), UserId(1));
+            presence.activities(vec![activity(ActivityType::Playing, "other")]);
+            presences.upsert(presence.build()).await.unwrap();
+            presences.remove((GuildId(1), UserId(2))).await.unwrap();
+
+            assert!(presences
+                .by_activity(GuildId(1), "game".into())
+                .await
+                .unwrap()
+                .next()
+                .await
+                .is_none());
+            assert_eq!(
+                2,
+                presences
+                    .by_activity(GuildId(1), "other".into())
+                    .await
+                    .unwrap()
+                    .count()
+                    .await
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_presence_changed_hook() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let hook_changes = Arc::clone(&changes);
+
+        let mut cache = InMemoryCache::new();
+        cache.add_hook(move |change| hook_changes.lock().unwrap().push(change.clone()));
+
+        let event = Event::PresenceUpdate(Box::new(presence_update()));
+        cache.process(&event).await.unwrap();
+        // an identical update doesn't change the presence
+        cache.process(&event).await.unwrap();
+
+        let mut update = presence_update();
+        update.activities = vec![activity(ActivityType::Streaming, "game")];
+        cache
+            .process(&Event::PresenceUpdate(Box::new(update.clone())))
+            .await
+            .unwrap();
+
+        let first = PresenceEntity::from(presence_update());
+        assert_eq!(
+            vec![
+                CacheChange::PresenceChanged {
+                    old: None,
+                    new: first.clone(),
+                },
+                CacheChange::PresenceChanged {
+                    old: Some(first),
+                    new: PresenceEntity::from(update),
+                },
+            ],
+            *changes.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_owner_change() {
+        struct DropGuilds;
+
+        impl EntityInterceptor for DropGuilds {
+            fn guild(&self, _: GuildEntity) -> Option<GuildEntity> {
+                None
+            }
+        }
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let hook_changes = Arc::clone(&changes);
+
+        let mut cache = InMemoryCache::new();
+        cache.add_hook(move |change| hook_changes.lock().unwrap().push(change.clone()));
+
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await
+            .unwrap();
+        assert_eq!(None, cache.guilds.previous_owner(GuildId(1)).await.unwrap());
+
+        let mut update = partial_guild();
+        update.owner_id = UserId(3);
+        cache
+            .process(&Event::GuildUpdate(Box::new(GuildUpdate(update))))
+            .await
+            .unwrap();
+
+        let mut user = UserEntity::from(user());
+        assert_eq!(
+            Some(user.clone()),
+            cache.guilds.previous_owner(GuildId(1)).await.unwrap()
+        );
+        user.id = UserId(3);
+        cache.users.upsert(user.clone()).await.unwrap();
+        assert_eq!(Some(user), cache.guilds.owner(GuildId(1)).await.unwrap());
+
+        // the history survives the guild being created again
+        let mut guild = guild();
+        guild.owner_id = UserId(4);
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild))))
+            .await
+            .unwrap();
+        let cached = cache.guilds.get(GuildId(1)).await.unwrap().unwrap();
+        assert_eq!(vec![UserId(2), UserId(3)], cached.previous_owner_ids);
+
+        // guilds dropped by the interceptor emit nothing
+        cache.set_entity_interceptor(DropGuilds);
+
+        let mut update = partial_guild();
+        update.owner_id = UserId(5);
+        cache
+            .process(&Event::GuildUpdate(Box::new(GuildUpdate(update))))
+            .await
+            .unwrap();
+
+        let mut guild = self::guild();
+        guild.owner_id = UserId(5);
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild))))
+            .await
+            .unwrap();
+
+        let cached = cache.guilds.get(GuildId(1)).await.unwrap().unwrap();
+        assert_eq!(UserId(4), cached.owner_id);
+
+        assert_eq!(
+            vec![
+                CacheChange::OwnerChanged {
+                    guild_id: GuildId(1),
+                    old: UserId(2),
+                    new: UserId(3),
+                },
+                CacheChange::OwnerChanged {
+                    guild_id: GuildId(1),
+                    old: UserId(3),
+                    new: UserId(4),
+                },
+            ],
+            *changes.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_text_channel_changes() {
+        struct Slowmode(Option<u64>);
+
+        impl EntityInterceptor for Slowmode {
+            fn text_channel(&self, channel: TextChannelEntity) -> Option<TextChannelEntity> {
+                self.0.map(|slowmode| TextChannelEntity {
+                    rate_limit_per_user: Some(slowmode),
+                    ..channel
+                })
+            }
+        }
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let hook_changes = Arc::clone(&changes);
+
+        let mut cache = InMemoryCache::new();
+        cache.add_hook(move |change| hook_changes.lock().unwrap().push(change.clone()));
+
+        let event = Event::ChannelCreate(ChannelCreate(Channel::Guild(GuildChannel::Text(text()))));
+        cache.process(&event).await.unwrap();
+        assert_eq!(
+            None,
+            cache.text_channels.slowmode(ChannelId(5)).await.unwrap()
+        );
+
+        let update = TextChannel {
+            rate_limit_per_user: Some(30),
+            topic: Some(String::from("rules")),
+            ..text()
+        };
+        let event = Event::ChannelUpdate(ChannelUpdate(Channel::Guild(GuildChannel::Text(update))));
+        cache.process(&event).await.unwrap();
+        assert_eq!(
+            Some(30),
+            cache.text_channels.slowmode(ChannelId(5)).await.unwrap()
+        );
+
+        // an update that doesn't change them emits nothing
+        cache.process(&event).await.unwrap();
+
+        // changes are emitted from what the interceptor lets through
+        let update = TextChannel {
+            rate_limit_per_user: Some(60),
+            topic: Some(String::from("rules")),
+            ..text()
+        };
+        let event = Event::ChannelUpdate(ChannelUpdate(Channel::Guild(GuildChannel::Text(update))));
+        cache.set_entity_interceptor(Slowmode(None));
+        cache.process(&event).await.unwrap();
+        cache.set_entity_interceptor(Slowmode(Some(30)));
+        cache.process(&event).await.unwrap();
+        assert_eq!(
+            Some(30),
+            cache.text_channels.slowmode(ChannelId(5)).await.unwrap()
+        );
+
+        assert_eq!(
+            vec![
+                CacheChange::SlowmodeChanged {
+                    guild_id: Some(GuildId(1)),
+                    channel_id: ChannelId(5),
+                    old: None,
+                    new: Some(30),
+                },
+                CacheChange::TopicChanged {
+                    guild_id: Some(GuildId(1)),
+                    channel_id: ChannelId(5),
+                    old: None,
+                    new: Some(String::from("rules")),
+                },
+            ],
+            *changes.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_entity_interceptor() {
+        struct Redact;
+
+        impl EntityInterceptor for Redact {
+            fn message(&self, mut message: MessageEntity) -> Option<MessageEntity> {
+                message.content.clear();
+
+                Some(message)
+            }
+
+            fn user(&self, user: UserEntity) -> Option<UserEntity> {
+                if user.bot {
+                    None
+                } else {
+                    Some(user)
+                }
+            }
+        }
+
+        let mut cache = InMemoryCache::new();
+        cache.set_entity_interceptor(Redact);
+
+        let message = messages().remove(0);
+        let event = Event::MessageCreate(Box::new(MessageCreate(message)));
+        cache.process(&event).await.unwrap();
+
+        let message = cache.messages.get(MessageId(100)).await.unwrap().unwrap();
+        assert!(message.content.is_empty());
+        assert!(cache
+            .attachments
+            .get(AttachmentId(200))
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(None, cache.users.get(UserId(2)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_event_stats() {
+        let slow = Arc::new(Mutex::new(Vec::new()));
+        let hook_slow = Arc::clone(&slow);
+
+        let mut cache = InMemoryCache::new();
+        cache.set_slow_event_hook(Duration::ZERO, move |event_type, _| {
+            hook_slow.lock().unwrap().push(event_type);
+        });
+
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await
+            .unwrap();
+
+        for message in messages() {
+            let event = Event::MessageCreate(Box::new(MessageCreate(message)));
+            cache.process(&event).await.unwrap();
+        }
+
+        let stats = cache.event_stats();
+        assert_eq!(2, stats.len());
+        let guild_create = stats[&EventType::GuildCreate];
+        assert_eq!(1, guild_create.processed);
+        assert_eq!(0, guild_create.failed);
+        assert_eq!(guild_create.max_duration, guild_create.p99_duration);
+        let message_create = stats[&EventType::MessageCreate];
+        assert_eq!(11, message_create.processed);
+        assert!(message_create.p99_duration <= message_create.max_duration);
+
+        assert_eq!(12, slow.lock().unwrap().len());
+        assert_eq!(EventType::GuildCreate, slow.lock().unwrap()[0]);
+
+        // clones share metrics
+        assert_eq!(stats, cache.clone().event_stats());
+
+        // the in-memory backend doesn't compress entities
+        assert!(cache.compression_stats().is_none());
+    }
+
+    #[test]
+    fn test_compression_round_trip() -> Result<(), Box<dyn Error>> {
+        let compressor = Compressor::new(64);
+
+        // entities below the threshold are stored as is
+        let small = "a".repeat(63);
+        let stored = compressor.compress(small.as_bytes());
+        assert_eq!(small.len() + 1, stored.len());
+        assert_eq!(small.as_bytes(), &*compressor.decompress(&stored)?);
+
+        for content in ["a".repeat(64), "a".repeat(1024)] {
+            let stored = compressor.compress(content.as_bytes());
+            assert!(stored.len() < content.len());
+            assert_eq!(content.as_bytes(), &*compressor.decompress(&stored)?);
+        }
+
+        // as are entities that compression doesn't make smaller
+        let incompressible = (0..=u8::MAX).collect::<Vec<_>>();
+        let stored = compressor.compress(&incompressible);
+        assert_eq!(incompressible.len() + 1, stored.len());
+        assert_eq!(incompressible, &*compressor.decompress(&stored)?);
+
+        let stats = compressor.stats();
+        assert_eq!(2, stats.compressed);
+        assert_eq!(2, stats.skipped);
+        assert_eq!(64 + 1024, stats.input_bytes);
+        assert!(stats.ratio().unwrap() < 1.0);
+        assert_eq!(stats.input_bytes - stats.output_bytes, stats.saved_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompress_corrupt() {
+        let compressor = Compressor::new(0);
+        let mut stored = compressor.compress("a".repeat(1024).as_bytes());
+        stored.truncate(stored.len() / 2);
+
+        assert!(compressor.decompress(&stored).is_err());
+        assert!(compressor.decompress(&[]).is_err());
+        assert!(compressor.decompress(&[1]).is_err());
+        assert!(compressor.decompress(&[2, 0]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_ndjson() -> Result<(), Box<dyn Error>> {
+        let backend = InMemoryBackend::new();
+        backend
+            .guilds()
+            .upsert(GuildEntity::builder(GuildId(1), "guild", UserId(2)).build())
+            .await?;
+        backend.users().upsert(UserEntity::from(user())).await?;
+        backend
+            .current_user()
+            .upsert(CurrentUserEntity::from(current_user()))
+            .await?;
+
+        let types = [
+            ExportType::CurrentUser,
+            ExportType::Role,
+            ExportType::User,
+            ExportType::Guild,
+        ];
+        let mut output = Vec::new();
+        assert_eq!(
+            3,
+            export::export_ndjson(&backend, &types, &mut output).await?
+        );
+
+        let lines = String::from_utf8(output)?
+            .lines()
+            .map(serde_json::from_str::<serde_json::Value>)
+            .collect::<Result<Vec<_>, _>>()?;
+        let kinds = lines
+            .iter()
+            .map(|line| line["type"].as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![Some("current_user"), Some("user"), Some("guild")],
+            kinds
+        );
+        assert_eq!(
+            serde_json::to_value(UserEntity::from(user()))?,
+            lines[1]["entity"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_repository() -> Result<(), Box<dyn Error>> {
+        /// Upsert users through the local traits, returning the users listed
+        /// afterwards.
+        async fn upsert_users<B: twilight_cache::local_repository::LocalBackend>(
+            backend: &B,
+        ) -> Result<Vec<UserEntity>, B::Error> {
+            use twilight_cache::local_repository::LocalRepository;
+
+            let users = backend.users();
+            let mut renamed = UserEntity::from(user());
+            renamed.name = String::from("renamed");
+
+            users.versioned_upsert(UserEntity::from(user()), 2).await?;
+            users.versioned_upsert(renamed, 1).await?;
+            users.upsert(UserEntity::from(user2())).await?;
+            users.remove(UserId(9)).await?;
+
+            users.list().await?.try_collect().await
+        }
+
+        let backend = InMemoryBackend::new();
+
+        // older versions are skipped by the wrapped repository rather than
+        // upserted by the default implementation
+        assert_eq!(
+            vec![UserEntity::from(user())],
+            upsert_users(&backend).await?
+        );
+        assert_eq!(
+            Some(UserEntity::from(user())),
+            backend.users().get(UserId(2)).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ordering() {
+        let mut cache = InMemoryCache::new();
+        cache.enable_ordering(4);
+
+        let add = Event::MemberAdd(Box::new(MemberAdd(member())));
+        let remove = Event::MemberRemove(MemberRemove {
+            guild_id: GuildId(1),
+            user: user(),
+        });
+
+        // a future dropped before completing doesn't hold up later events
+        drop(cache.process(&remove));
+
+        let add_fut = cache.process(&add);
+        let remove_fut = cache.process(&remove);
+        let (removed, added) = futures::join!(remove_fut, add_fut);
+        removed.unwrap();
+        added.unwrap();
+
+        assert_eq!(
+            None,
+            cache.members.get((GuildId(1), UserId(2))).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sequenced_upserts() {
+        let cache = InMemoryCache::new();
+
+        let update = |nick: &str| {
+            Event::MemberUpdate(Box::new(MemberUpdate {
+                deaf: None,
+                guild_id: GuildId(1),
+                joined_at: String::from("2012-11-21T11:00:00.40000+00:00"),
+                mute: None,
+                nick: Some(String::from(nick)),
+                pending: false,
+                premium_since: None,
+                roles: Vec::new(),
+                user: user2(),
+            }))
+        };
+        let nick = |cache: InMemoryCache| async move {
+            cache
+                .members
+                .get((GuildId(1), UserId(9)))
+                .await
+                .unwrap()
+                .unwrap()
+                .nick
+        };
+
+        cache.process_sequenced(&update("new"), 2).await.unwrap();
+        cache.process_sequenced(&update("old"), 1).await.unwrap();
+        assert_eq!(Some(String::from("new")), nick(cache.clone()).await);
+
+        cache.process_sequenced(&update("newer"), 2).await.unwrap();
+        assert_eq!(Some(String::from("newer")), nick(cache.clone()).await);
+
+        // unsequenced writes always apply
+        cache.process(&update("unsequenced")).await.unwrap();
+        assert_eq!(Some(String::from("unsequenced")), nick(cache.clone()).await);
+    }
+
+    #[tokio::test]
+    async fn test_partitioned_backend() -> Result<(), Box<dyn Error>> {
+        let backend = PartitionedBackend::new(vec![InMemoryBackend::new(), InMemoryBackend::new()]);
+        let cache = Cache::with_backend(backend.clone());
+
+        let mut other = guild();
+        other.id = GuildId(1 << 22);
+        other.members.clear();
+        other.presences.clear();
+
+        for guild in [guild(), other] {
+            cache
+                .process(&Event::GuildCreate(Box::new(GuildCreate(guild))))
+                .await?;
+        }
+
+        cache
+            .process(&Event::RoleCreate(RoleCreate {
+                guild_id: GuildId(1 << 22),
+                role: role(),
+            }))
+            .await?;
+
+        let [first, second] = [&backend.partitions()[0], &backend.partitions()[1]];
+        assert!(first.guilds().get(GuildId(1)).await?.is_some());
+        assert!(second.guilds().get(GuildId(1)).await?.is_none());
+        assert!(second.guilds().get(GuildId(1 << 22)).await?.is_some());
+        assert!(second.roles().get(RoleId(12)).await?.is_some());
+
+        assert_eq!(2, cache.guilds.list().await?.count().await);
+        assert_eq!(1, cache.guilds.members(GuildId(1)).await?.count().await);
+        assert_eq!(
+            Some(GuildId(1 << 22)),
+            cache.roles.guild(RoleId(12)).await?.map(|guild| guild.id)
+        );
+
+        cache.roles.remove(RoleId(12)).await?;
+        assert!(second.roles().get(RoleId(12)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_consistent_iteration() -> Result<(), Box<dyn Error>> {
+        let iterate = |consistent: bool| async move {
+            let mut builder = InMemoryBackend::builder();
+            builder.consistent_iteration(consistent);
+            let cache = Cache::with_backend(builder.build());
+
+            for user in [user(), user2()] {
+                cache.users.upsert(UserEntity::from(user)).await?;
+            }
+
+            let mut users = cache.users.list().await?;
+
+            // writes while a stream is alive must not block on shard locks
+            let mut renamed = UserEntity::from(user());
+            renamed.name = String::from("renamed");
+            cache.users.upsert(renamed).await?;
+            cache.users.remove(UserId(9)).await?;
+
+            let mut names = Vec::new();
+
+            while let Some(user) = users.next().await {
+                names.push(user?.name);
+            }
+
+            names.sort();
+
+            Ok::<_, Box<dyn Error>>(names)
+        };
+
+        assert_eq!(vec![String::from("renamed")], iterate(false).await?);
+        assert_eq!(
+            vec![String::from("user"), String::from("user2")],
+            iterate(true).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_buffered() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+
+        for id in 1..=5 {
+            cache
+                .users
+                .upsert(UserEntity::builder(UserId(id), "user", "0001").build())
+                .await?;
+        }
+
+        for chunk_size in [0, 2, 5, 10] {
+            let mut ids = cache
+                .users
+                .list_buffered(chunk_size)
+                .await?
+                .map_ok(|user| user.id.0)
+                .try_collect::<Vec<_>>()
+                .await?;
+            ids.sort_unstable();
+
+            assert_eq!(vec![1, 2, 3, 4, 5], ids);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_try_get() -> Result<(), Box<dyn Error>> {
+        let backend = InMemoryBackend::new();
+        let cache = Cache::with_backend(backend.clone());
+        cache.users.upsert(UserEntity::from(user())).await?;
+        cache
+            .current_user
+            .upsert(CurrentUserEntity::from(current_user()))
+            .await?;
+
+        assert_eq!(
+            Ok(Some(UserEntity::from(user()))),
+            cache.users.try_get(UserId(2))
+        );
+        assert_eq!(Ok(None), cache.users.try_get(UserId(9)));
+        assert!(cache.current_user.try_get()?.is_some());
+
+        {
+            let _user = backend.0.users.get_mut(&UserId(2));
+            assert_eq!(Err(WouldBlock), cache.users.try_get(UserId(2)));
+        }
+
+        {
+            let _current_user = backend.0.user_current.lock();
+            assert_eq!(Err(WouldBlock), cache.current_user.try_get());
+        }
+
+        assert!(cache.users.try_get(UserId(2))?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_buffering() -> Result<(), Box<dyn Error>> {
+        let mut cache = InMemoryCache::new();
+        cache.enable_unavailable_buffering(10);
+
+        let unavailable_ids = |cache: InMemoryCache| async move {
+            cache
+                .guilds
+                .unavailable_ids()
+                .await?
+                .try_collect::<Vec<_>>()
+                .await
+        };
+
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        assert!(unavailable_ids(cache.clone()).await?.is_empty());
+
+        cache
+            .process(&Event::UnavailableGuild(UnavailableGuild {
+                id: GuildId(1),
+            }))
+            .await?;
+        assert_eq!(vec![GuildId(1)], unavailable_ids(cache.clone()).await?);
+
+        // member events are held back while the guild is unavailable
+        cache
+            .process(&Event::MemberAdd(Box::new(MemberAdd(member2()))))
+            .await?;
+        assert!(cache.members.get((GuildId(1), UserId(9))).await?.is_none());
+
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        assert!(unavailable_ids(cache.clone()).await?.is_empty());
+        assert!(cache.members.get((GuildId(1), UserId(9))).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ready_guild_skeletons() -> Result<(), Box<dyn Error>> {
+        let mut cache = InMemoryCache::new();
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let hook_changes = Arc::clone(&changes);
+        cache.add_hook(move |change| hook_changes.lock().unwrap().push(change.clone()));
+
+        let mut event = ready();
+        event.guilds = vec![GuildStatus::Offline(
+            twilight_model::guild::UnavailableGuild {
+                id: GuildId(1),
+                unavailable: true,
+            },
+        )];
+        cache.process(&Event::Ready(Box::new(event))).await?;
+
+        let skeleton = cache.guilds.get(GuildId(1)).await?.unwrap();
+        assert!(skeleton.is_skeleton());
+        assert!(skeleton.unavailable);
+        assert_eq!(1, cache.guilds.list().await?.count().await);
+        assert!(cache.current_user.get().await?.is_some());
+
+        let session = cache.session(0).unwrap();
+        assert_eq!("session", session.session_id);
+        assert_eq!(Some([0, 1]), session.shard);
+        assert!(cache.session(1).is_none());
+
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+
+        let guild = cache.guilds.get(GuildId(1)).await?.unwrap();
+        assert!(!guild.is_skeleton());
+        assert!(!guild.unavailable);
+        assert!(guild.previous_owner_ids.is_empty());
+        assert!(changes.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recording_backend() -> Result<(), Box<dyn Error>> {
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> IoResult<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let backend = RecordingBackend::to_writer(InMemoryBackend::new(), buffer.clone());
+        let cache = Cache::with_backend(backend.clone());
+
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        cache
+            .process(&Event::RoleCreate(RoleCreate {
+                guild_id: GuildId(1),
+                role: role(),
+            }))
+            .await?;
+        cache.roles.remove(RoleId(12)).await?;
+
+        let log = buffer.0.lock().unwrap().clone();
+        let records = recording::read_records(log.as_slice()).collect::<Result<Vec<_>, _>>()?;
+        assert!(records.contains(&Record::Remove {
+            id: RecordedId::Role(RoleId(12)),
+        }));
+
+        let fresh = InMemoryBackend::new();
+        recording::replay(&fresh, records).await?;
+
+        let inner = backend.inner();
+        assert_eq!(
+            inner.guilds().get(GuildId(1)).await?,
+            fresh.guilds().get(GuildId(1)).await?
+        );
+        assert_eq!(
+            inner.members().get((GuildId(1), UserId(2))).await?,
+            fresh.members().get((GuildId(1), UserId(2))).await?
+        );
+        assert!(fresh.roles().get(RoleId(12)).await?.is_none());
+
+        Ok(())
+    }
+
+    /// Backend whose users fail to be written while it's down, logging
+    /// the users written to it.
+    #[derive(Clone, Debug, Default)]
+    struct FlakyBackend {
+        down: Arc<AtomicBool>,
+        written: Arc<Mutex<Vec<UserEntity>>>,
+    }
+
+    impl FlakyBackend {
+        fn check(&self) -> Result<(), InMemoryBackendError> {
+            if self.down.load(Ordering::SeqCst) {
+                Err(InMemoryBackendError)
+            } else {
+                Ok(())
+            }
+        }
+
+        fn names(&self) -> Vec<String> {
+            let written = self.written.lock().unwrap();
+
+            written.iter().map(|user| user.name.clone()).collect()
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct FlakyUsers(FlakyBackend);
+
+    #[derive(Clone, Debug)]
+    struct FlakyCurrentUser(FlakyBackend);
+
+    impl Backend for FlakyBackend {
+        type Error = InMemoryBackendError;
+        type AttachmentRepository = NoopRepository<Self>;
+        type CategoryChannelRepository = NoopRepository<Self>;
+        type CurrentUserRepository = FlakyCurrentUser;
+        type EmojiRepository = NoopRepository<Self>;
+        type GroupRepository = NoopRepository<Self>;
+        type GuildPreviewRepository = NoopRepository<Self>;
+        type GuildRepository = NoopRepository<Self>;
+        type MemberRepository = NoopRepository<Self>;
+        type MessageRepository = NoopRepository<Self>;
+        type PresenceRepository = NoopRepository<Self>;
+        type PrivateChannelRepository = NoopRepository<Self>;
+        type RoleRepository = NoopRepository<Self>;
+        type TextChannelRepository = NoopRepository<Self>;
+        type UserRepository = FlakyUsers;
+        type VoiceChannelRepository = NoopRepository<Self>;
+        type VoiceRegionRepository = NoopRepository<Self>;
+        type VoiceStateRepository = NoopRepository<Self>;
+
+        fn attachments(&self) -> Self::AttachmentRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn category_channels(&self) -> Self::CategoryChannelRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn current_user(&self) -> Self::CurrentUserRepository {
+            FlakyCurrentUser(self.clone())
+        }
+
+        fn emojis(&self) -> Self::EmojiRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn groups(&self) -> Self::GroupRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn guild_previews(&self) -> Self::GuildPreviewRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn guilds(&self) -> Self::GuildRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn members(&self) -> Self::MemberRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn messages(&self) -> Self::MessageRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn presences(&self) -> Self::PresenceRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn private_channels(&self) -> Self::PrivateChannelRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn roles(&self) -> Self::RoleRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn text_channels(&self) -> Self::TextChannelRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn users(&self) -> Self::UserRepository {
+            FlakyUsers(self.clone())
+        }
+
+        fn voice_channels(&self) -> Self::VoiceChannelRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn voice_regions(&self) -> Self::VoiceRegionRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn voice_states(&self) -> Self::VoiceStateRepository {
+            NoopRepository::new(self.clone())
+        }
+
+        fn reconnect(&self) -> ConnectFuture<'_, Self::Error> {
+            future::ready(self.check()).boxed()
+        }
+
+        fn health(&self) -> HealthFuture<'_, Self::Error> {
+            future::ready(self.check()).boxed()
+        }
+    }
+
+    impl Repository<UserEntity, FlakyBackend> for FlakyUsers {
+        fn backend(&self) -> &FlakyBackend {
+            &self.0
+        }
+
+        fn get(&self, user_id: UserId) -> GetEntityFuture<'_, UserEntity, InMemoryBackendError> {
+            let written = self.0.written.lock().unwrap();
+            let user = written
+                .iter()
+                .rev()
+                .find(|user| user.id == user_id)
+                .cloned();
+
+            future::ready(self.0.check().map(|()| user)).boxed()
+        }
+
+        fn list(&self) -> ListEntitiesFuture<'_, UserEntity, InMemoryBackendError> {
+            future::ok(stream::empty().boxed()).boxed()
+        }
+
+        fn remove(&self, _: UserId) -> RemoveEntityFuture<'_, InMemoryBackendError> {
+            future::ready(self.0.check()).boxed()
+        }
+
+        fn upsert(&self, user: UserEntity) -> UpsertEntityFuture<'_, InMemoryBackendError> {
+            let result = self.0.check();
+
+            if result.is_ok() {
+                self.0.written.lock().unwrap().push(user);
+            }
+
+            future::ready(result).boxed()
+        }
+    }
+
+    impl UserRepository<FlakyBackend> for FlakyUsers {
+        fn guild_ids(&self, _: UserId) -> ListEntityIdsFuture<'_, GuildId, InMemoryBackendError> {
+            future::ok(stream::empty().boxed()).boxed()
+        }
+    }
+
+    impl SingleEntityRepository<CurrentUserEntity, FlakyBackend> for FlakyCurrentUser {
+        fn backend(&self) -> &FlakyBackend {
+            &self.0
+        }
+
+        fn get(&self) -> GetEntityFuture<'_, CurrentUserEntity, InMemoryBackendError> {
+            future::ok(None).boxed()
+        }
+
+        fn remove(&self) -> RemoveEntityFuture<'_, InMemoryBackendError> {
+            future::ok(()).boxed()
+        }
+
+        fn upsert(&self, _: CurrentUserEntity) -> UpsertEntityFuture<'_, InMemoryBackendError> {
+            future::ok(()).boxed()
+        }
+    }
+
+    impl CurrentUserRepository<FlakyBackend> for FlakyCurrentUser {
+        fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, InMemoryBackendError> {
+            future::ok(stream::empty().boxed()).boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batching_backend() -> Result<(), Box<dyn Error>> {
+        use twilight_cache::batch::BatchingBackend;
+
+        let user = |id, name| UserEntity::builder(UserId(id), name, "0001").build();
+
+        // writes are flushed once the batch is full, and writes replacing a
+        // queued write don't count towards it
+        let backend = BatchingBackend::new(InMemoryBackend::new(), 2);
+        let users = backend.users();
+
+        users.upsert(user(2, "first")).await?;
+        users.upsert(user(2, "second")).await?;
+        assert_eq!(1, backend.queued());
+        assert_eq!(Some(user(2, "second")), users.get(UserId(2)).await?);
+        assert!(backend.inner().users().get(UserId(2)).await?.is_none());
+
+        users.upsert(user(3, "third")).await?;
+        assert_eq!(0, backend.queued());
+        assert_eq!(
+            Some(user(2, "second")),
+            backend.inner().users().get(UserId(2)).await?
+        );
+        assert!(backend.inner().users().get(UserId(3)).await?.is_some());
+
+        // older versioned upserts don't replace the queued one
+        let backend = BatchingBackend::new(InMemoryBackend::new(), 10);
+        let users = backend.users();
+
+        users.versioned_upsert(user(2, "newer"), 5).await?;
+        users.versioned_upsert(user(2, "older"), 3).await?;
+        assert_eq!(1, backend.queued());
+        assert_eq!(Some(user(2, "newer")), users.get(UserId(2)).await?);
+
+        assert_eq!(1, backend.flush_queued().await?);
+        assert_eq!(
+            Some(user(2, "newer")),
+            backend.inner().users().get(UserId(2)).await?
+        );
+
+        // removals are queued too
+        users.remove(UserId(2)).await?;
+        assert_eq!(1, backend.queued());
+        assert!(users.get(UserId(2)).await?.is_none());
+        assert!(backend.inner().users().get(UserId(2)).await?.is_some());
+
+        backend.flush_queued().await?;
+        assert!(backend.inner().users().get(UserId(2)).await?.is_none());
+
+        // taking and upserting with an outcome read the queued write and
+        // queue their own instead of being forwarded
+        assert!(users
+            .upsert_with_outcome(user(2, "created"))
+            .await?
+            .is_created());
+        assert!(users
+            .upsert_with_outcome(user(2, "updated"))
+            .await?
+            .is_updated());
+        assert!(backend.inner().users().get(UserId(2)).await?.is_none());
+        assert_eq!(Some(user(2, "updated")), users.take(UserId(2)).await?);
+        assert_eq!(1, backend.queued());
+        assert!(users.get(UserId(2)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batching_backend_replaced_during_flush() -> Result<(), Box<dyn Error>> {
+        use twilight_cache::batch::BatchingBackend;
+
+        type Batching = BatchingBackend<RecordingBackend<InMemoryBackend>>;
+
+        let user = |name| UserEntity::builder(UserId(2), name, "0001").build();
+
+        // the first write flushed to the wrapped backend queues a newer
+        // write of the same user while the flush is in progress
+        let handle = Arc::new(Mutex::new(None::<Batching>));
+        let pending = Arc::clone(&handle);
+        let inner = RecordingBackend::new(InMemoryBackend::new(), move |_| {
+            if let Some(backend) = pending.lock().unwrap().take() {
+                drop(backend.users().upsert(user("second")));
+            }
+        });
+        let backend = BatchingBackend::new(inner, 10);
+        *handle.lock().unwrap() = Some(backend.clone());
+
+        backend.users().upsert(user("first")).await?;
+        assert_eq!(0, backend.flush_queued().await?);
+
+        // the newer write stays queued
+        assert_eq!(1, backend.queued());
+        assert_eq!(Some(user("second")), backend.users().get(UserId(2)).await?);
+        assert_eq!(
+            Some(user("first")),
+            backend.inner().users().get(UserId(2)).await?
+        );
+
+        assert_eq!(1, backend.flush_queued().await?);
+        assert_eq!(0, backend.queued());
+        assert_eq!(
+            Some(user("second")),
+            backend.inner().users().get(UserId(2)).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batching_backend_failed_flush() -> Result<(), Box<dyn Error>> {
+        use twilight_cache::batch::BatchingBackend;
+
+        let flaky = FlakyBackend::default();
+        let backend = BatchingBackend::new(flaky.clone(), 10);
+        let user = UserEntity::builder(UserId(2), "user", "0001").build();
+        backend.users().upsert(user.clone()).await?;
+
+        // writes that fail to be flushed stay queued
+        flaky.down.store(true, Ordering::SeqCst);
+        assert!(backend.flush_queued().await.is_err());
+        assert!(backend.flush().await.is_err());
+        assert_eq!(1, backend.queued());
+        assert_eq!(Some(user.clone()), backend.users().get(UserId(2)).await?);
+        assert!(flaky.names().is_empty());
+
+        flaky.down.store(false, Ordering::SeqCst);
+        assert_eq!(1, backend.flush_queued().await?);
+        assert_eq!(0, backend.queued());
+        assert_eq!(["user"], flaky.names().as_slice());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batching_backend_shutdown() -> Result<(), Box<dyn Error>> {
+        use twilight_cache::batch::BatchingBackend;
+
+        let backend = BatchingBackend::new(InMemoryBackend::new(), 1000);
+        let cache = Cache::with_backend(backend.clone());
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        assert!(backend.queued() > 0);
+        assert!(backend.inner().guilds().get(GuildId(1)).await?.is_none());
+
+        cache.shutdown().await?;
+        assert_eq!(0, backend.queued());
+        assert!(backend.inner().guilds().get(GuildId(1)).await?.is_some());
+        assert!(backend
+            .inner()
+            .members()
+            .get((GuildId(1), UserId(2)))
+            .await?
+            .is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_estimate() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        let estimate = cache.backend().memory_estimate();
+        assert_eq!(0, estimate.total());
+
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+
+        let estimate = cache.backend().memory_estimate();
+        let guild = cache.guilds.get(GuildId(1)).await?.unwrap();
+        assert_eq!(
+            mem::size_of::<GuildId>() + guild.estimated_size(),
+            estimate.get(EntityType::GUILD)
+        );
+        assert!(estimate.get(EntityType::MEMBER) > 0);
+        assert_eq!(0, estimate.get(EntityType::MESSAGE));
+        assert_eq!(
+            estimate.get(EntityType::GUILD | EntityType::MEMBER),
+            estimate.get(EntityType::GUILD) + estimate.get(EntityType::MEMBER)
+        );
+        assert_eq!(
+            estimate.total(),
+            estimate.iter().map(|(_, bytes)| bytes).sum::<usize>()
+        );
+        assert_eq!(estimate.total(), estimate.get(EntityType::all()));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "unstable-relations")]
+    #[tokio::test]
+    async fn test_relations() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        cache
+            .process(&Event::RoleCreate(RoleCreate {
+                guild_id: GuildId(1),
+                role: role(),
+            }))
+            .await?;
+
+        let relations = cache.backend().relations();
+        assert_eq!(
+            vec![UserId(2)],
+            relations
+                .guild_members(GuildId(1))
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![RoleId(12)],
+            relations
+                .guild_roles(GuildId(1))
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![GuildId(1)],
+            relations
+                .user_guilds(UserId(2))
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert!(relations.guild_members(GuildId(5)).is_empty());
+        assert!(relations.channel_messages(ChannelId(1)).is_empty());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "emoji-usage")]
+    #[tokio::test]
+    async fn test_emoji_usage() -> Result<(), Box<dyn Error>> {
+        use twilight_cache::entity::guild::EmojiRepository as _;
+        use twilight_model::{
+            channel::{Reaction, ReactionType},
+            gateway::payload::ReactionAdd,
+        };
+
+        let cache = InMemoryCache::new();
+
+        let mut message = messages().remove(0);
+        message.content = String::from("<:wave:10> <:wave:10> <a:party:11> <:nope:>");
+        cache
+            .process(&Event::MessageCreate(Box::new(MessageCreate(message))))
+            .await?;
+
+        let reaction = |guild_id, emoji| Reaction {
+            channel_id: ChannelId(1),
+            emoji,
+            guild_id,
+            member: None,
+            message_id: MessageId(100),
+            user_id: UserId(2),
+        };
+
+        for (guild_id, emoji) in [
+            (
+                Some(GuildId(1)),
+                ReactionType::Custom {
+                    animated: false,
+                    id: EmojiId(11),
+                    name: None,
+                },
+            ),
+            (
+                None,
+                ReactionType::Custom {
+                    animated: false,
+                    id: EmojiId(11),
+                    name: None,
+                },
+            ),
+            (
+                Some(GuildId(1)),
+                ReactionType::Unicode {
+                    name: String::from("👍"),
+                },
+            ),
+        ] {
+            let event = Event::ReactionAdd(Box::new(ReactionAdd(reaction(guild_id, emoji))));
+            cache.process(&event).await?;
+        }
+
+        let mut usage = cache
+            .emojis
+            .usage(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        usage.sort();
+
+        assert_eq!(vec![(EmojiId(10), 2), (EmojiId(11), 2)], usage);
+        assert!(cache
+            .emojis
+            .usage(GuildId(2))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_projected() -> Result<(), Box<dyn Error>> {
+        use twilight_cache::projection::GuildName;
+
+        let cache = InMemoryCache::new();
+        let guild = guild();
+        let name = guild.name.clone();
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild))))
+            .await?;
+
+        assert_eq!(
+            Some(GuildName(name)),
+            cache.guilds.get_projected::<GuildName>(GuildId(1)).await?
+        );
+        assert_eq!(
+            None,
+            cache.guilds.get_projected::<GuildName>(GuildId(2)).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_backend_ops() -> Result<(), Box<dyn Error>> {
+        let mut cache = InMemoryCache::new();
+        cache.set_max_concurrent_backend_ops(3);
+
+        let events = [
+            Event::GuildCreate(Box::new(GuildCreate(guild()))),
+            Event::MemberChunk(member_chunk()),
+            Event::MessageCreate(Box::new(MessageCreate(messages().remove(0)))),
+        ];
+        futures::future::try_join_all(events.iter().map(|event| cache.process(event))).await?;
+        cache
+            .process_sequenced(&Event::MemberChunk(member_chunk()), 1)
+            .await?;
+
+        for i in 400..=410 {
+            assert!(cache.members.get((GuildId(1), UserId(i))).await?.is_some());
+            assert!(cache
+                .presences
+                .get((GuildId(1), UserId(i)))
+                .await?
+                .is_some());
+        }
+
+        assert!(cache.guilds.get(GuildId(1)).await?.is_some());
+        assert!(cache.messages.get(MessageId(100)).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_guild_view() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        cache.process(&Event::MemberChunk(member_chunk())).await?;
+
+        let view = cache.guild(GuildId(1));
+        assert_eq!(GuildId(1), view.id());
+        assert_eq!(cache.guilds.get(GuildId(1)).await?, view.get().await?);
+        assert_eq!(
+            cache.members.get((GuildId(1), UserId(400))).await?,
+            view.member(UserId(400)).await?
+        );
+        assert_eq!(
+            cache.guilds.member_ids(GuildId(1)).await?.count().await,
+            view.members().await?.count().await
+        );
+        assert_eq!(
+            cache.guilds.role_ids(GuildId(1)).await?.count().await,
+            view.roles().await?.count().await
+        );
+        assert_eq!(
+            cache.guilds.channel_ids(GuildId(1)).await?.count().await,
+            view.channels().await?.count().await
+        );
+        assert!(cache.guild(GuildId(2)).member(UserId(400)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_channel_view() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+
+        let channels = [
+            GuildChannel::Category(category()),
+            GuildChannel::Text(text()),
+        ];
+
+        for channel in channels {
+            let event = Event::ChannelCreate(ChannelCreate(Channel::Guild(channel)));
+            cache.process(&event).await?;
+        }
+
+        for message in messages() {
+            cache
+                .process(&Event::MessageCreate(Box::new(MessageCreate(message))))
+                .await?;
+        }
+
+        let text_channel = cache.text_channels.get(ChannelId(5)).await?.unwrap();
+        let view = cache.channel(text_channel.id).await?.unwrap();
+        assert_eq!(text_channel.id, view.id());
+        assert_eq!(text_channel.guild_id, view.guild_id());
+        assert_eq!(cache.guilds.get(GuildId(1)).await?, view.guild().await?);
+        assert_eq!(
+            cache.text_channels.last_message(text_channel.id).await?,
+            view.last_message().await?
+        );
+        assert_eq!(
+            cache.text_channels.parent(text_channel.id).await?,
+            view.parent().await?
+        );
+        assert!(view.parent().await?.is_some());
+
+        let messages = view.messages().await?.try_collect::<Vec<_>>().await?;
+        assert!(!messages.is_empty());
+        assert!(messages
+            .iter()
+            .all(|message| message.channel_id == text_channel.id));
+        assert!(messages.windows(2).all(|pair| pair[0].id < pair[1].id));
+
+        assert!(cache.channel(ChannelId(u64::MAX)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recent_joins() -> Result<(), Box<dyn Error>> {
+        for size in [0, 3] {
+            let mut builder = InMemoryBackend::builder();
+            builder.recent_joins_size(size);
+            let cache = Cache::with_backend(builder.build());
+
+            for i in 1..=5 {
+                let mut member = member();
+                member.joined_at = Some(format!("2021-01-0{i}T00:00:00+00:00"));
+                member.user.id = UserId(i);
+                cache
+                    .process(&Event::MemberAdd(Box::new(MemberAdd(member))))
+                    .await?;
+            }
+
+            let mut user = user();
+            user.id = UserId(5);
+            cache
+                .process(&Event::MemberRemove(MemberRemove {
+                    guild_id: GuildId(1),
+                    user,
+                }))
+                .await?;
+
+            let joins = cache
+                .guilds
+                .recent_joins(GuildId(1), 2)
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?;
+            assert_eq!(
+                vec![
+                    (UserId(4), String::from("2021-01-04T00:00:00+00:00")),
+                    (UserId(3), String::from("2021-01-03T00:00:00+00:00")),
+                ],
+                joins
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_heuristics() -> Result<(), Box<dyn Error>> {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        use twilight_cache::{
+            heuristics::{AccountAges, GuildHeuristics},
+            snowflake::DISCORD_EPOCH,
+        };
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() * 1000;
+        let new_account = UserId((now - DISCORD_EPOCH) << 22);
+
+        let cache = InMemoryCache::new();
+        let joins = [
+            (UserId(1), "2000-01-01T00:00:00+00:00"),
+            (UserId(2), "2100-01-01T00:00:00+00:00"),
+            (new_account, "2100-01-02T00:00:00+00:00"),
+        ];
+
+        for (user_id, joined_at) in &joins {
+            let mut member = member();
+            member.joined_at = Some(String::from(*joined_at));
+            member.user.id = *user_id;
+            cache
+                .process(&Event::MemberAdd(Box::new(MemberAdd(member))))
+                .await?;
+        }
+
+        assert_eq!(
+            2,
+            cache
+                .guilds
+                .join_rate(GuildId(1), Duration::from_secs(30))
+                .await?
+        );
+        assert_eq!(
+            AccountAges {
+                under_day: 1,
+                older: 2,
+                ..AccountAges::default()
+            },
+            cache.guilds.account_ages(GuildId(1), 10).await?
+        );
+        assert_eq!(0, cache.guilds.account_ages(GuildId(2), 10).await?.total());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_with_meta() -> Result<(), Box<dyn Error>> {
+        let mut builder = InMemoryBackend::builder();
+        builder.track_metadata(true);
+        let cache = Cache::with_backend(builder.build());
+
+        let before = SystemTime::now();
+        cache.users.upsert(UserEntity::from(user())).await?;
+        let (_, inserted) = cache.users.get_with_meta(UserId(2)).await?.unwrap();
+        let inserted = inserted.unwrap();
+        assert!(inserted.cached_at >= before);
+        assert_eq!(inserted.cached_at, inserted.updated_at);
+
+        let mut renamed = UserEntity::from(user());
+        renamed.name = String::from("renamed");
+        cache.users.upsert(renamed).await?;
+        let (user, updated) = cache.users.get_with_meta(UserId(2)).await?.unwrap();
+        let updated = updated.unwrap();
+        assert_eq!("renamed", user.name);
+        assert_eq!(inserted.cached_at, updated.cached_at);
+        assert!(updated.updated_at >= inserted.updated_at);
+
+        cache.users.remove(UserId(2)).await?;
+        assert!(cache.users.get_with_meta(UserId(2)).await?.is_none());
+
+        // metadata isn't tracked by default
+        let cache = InMemoryCache::new();
+        cache.users.upsert(UserEntity::from(user())).await?;
+        assert_eq!(
+            Some((UserEntity::from(user()), None)),
+            cache.users.get_with_meta(UserId(2)).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refreshing_repository() -> Result<(), Box<dyn Error>> {
+        let backend = InMemoryBackend::new();
+        let refreshed = Arc::new(Mutex::new(Vec::new()));
+        let users = {
+            let refreshed = Arc::clone(&refreshed);
+
+            let staleness = Duration::from_secs(60 * 60);
+
+            RefreshingRepository::<UserEntity, _>::new(backend.users(), staleness, move |id| {
+                refreshed.lock().unwrap().push(id);
+            })
+        };
+
+        // entities of unknown age are stale, and are only refreshed once
+        backend.users().upsert(UserEntity::from(user())).await?;
+        assert!(users.get(UserId(2)).await?.is_some());
+        assert!(users.get(UserId(2)).await?.is_some());
+        assert_eq!([UserId(2)], refreshed.lock().unwrap().as_slice());
+
+        // removing the entity forgets its age
+        users.remove(UserId(2)).await?;
+        assert!(users.cached_at(UserId(2)).is_none());
+        backend.users().upsert(UserEntity::from(user())).await?;
+        users.get(UserId(2)).await?;
+        assert_eq!([UserId(2); 2], refreshed.lock().unwrap().as_slice());
+
+        // upserting through the layer resets the age
+        refreshed.lock().unwrap().clear();
+        users.remove(UserId(2)).await?;
+        users.upsert(UserEntity::from(user())).await?;
+        assert!(users.cached_at(UserId(2)).is_some());
+        users.get(UserId(2)).await?;
+        assert!(refreshed.lock().unwrap().is_empty());
+
+        // without a staleness, every read is stale
+        let users = {
+            let refreshed = Arc::clone(&refreshed);
+
+            RefreshingRepository::<UserEntity, _>::new(backend.users(), Duration::ZERO, move |id| {
+                refreshed.lock().unwrap().push(id);
+            })
+        };
+        users.upsert(UserEntity::from(user())).await?;
+        users.get(UserId(2)).await?;
+        assert_eq!([UserId(2)], refreshed.lock().unwrap().as_slice());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refreshing_repository_meta() -> Result<(), Box<dyn Error>> {
+        let mut builder = InMemoryBackend::builder();
+        builder.track_metadata(true);
+        let backend = builder.build();
+        let refreshed = Arc::new(Mutex::new(Vec::new()));
+        let users = {
+            let refreshed = Arc::clone(&refreshed);
+
+            let staleness = Duration::from_secs(60 * 60);
+
+            RefreshingRepository::<UserEntity, _>::new(backend.users(), staleness, move |id| {
+                refreshed.lock().unwrap().push(id);
+            })
+        };
+
+        // entities upserted elsewhere are aged by their metadata
+        backend.users().upsert(UserEntity::from(user())).await?;
+        assert!(users.get(UserId(2)).await?.is_some());
+        assert!(refreshed.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_guild_previews() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        let mut builder = GuildPreviewEntity::builder(GuildId(1), "guild");
+        builder
+            .approximate_member_count(120)
+            .emoji_ids(vec![EmojiId(200), EmojiId(201)]);
+        let preview = builder.build();
+        cache.guild_previews.upsert(preview.clone()).await?;
+        cache
+            .emojis
+            .upsert(EmojiEntity::from((GuildId(1), emoji())))
+            .await?;
+
+        assert_eq!(Some(preview), cache.guild_previews.get(GuildId(1)).await?);
+        assert!(cache.guilds.get(GuildId(1)).await?.is_none());
+        assert!(cache.guild_previews.guild(GuildId(1)).await?.is_none());
+
+        let emojis = cache
+            .guild_previews
+            .emojis(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![EmojiEntity::from((GuildId(1), emoji()))], emojis);
+
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        assert_eq!(
+            cache.guilds.get(GuildId(1)).await?,
+            cache.guild_previews.guild(GuildId(1)).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_voice_regions() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        let mut builder = VoiceRegionEntity::builder("us-east", "US East");
+        builder.optimal(true);
+        let us_east = builder.build();
+        let europe = VoiceRegionEntity::builder("europe", "Europe").build();
+        cache.voice_regions.upsert(us_east.clone()).await?;
+        cache.voice_regions.upsert(europe.clone()).await?;
+
+        assert_eq!(
+            Some(europe.clone()),
+            cache.voice_regions.by_name("europe").await?
+        );
+        assert!(cache.voice_regions.by_name("mars").await?.is_none());
+        assert_eq!(Some(us_east), cache.voice_regions.optimal().await?);
+
+        let mut channel = voice();
+        channel.rtc_region = Some(String::from("europe"));
+        cache
+            .process(&Event::ChannelCreate(ChannelCreate(Channel::Guild(
+                GuildChannel::Voice(channel),
+            ))))
+            .await?;
+        assert_eq!(
+            Some(europe),
+            cache.voice_channels.rtc_region(ChannelId(6)).await?
+        );
+
+        cache
+            .process(&Event::ChannelUpdate(ChannelUpdate(Channel::Guild(
+                GuildChannel::Voice(voice()),
+            ))))
+            .await?;
+        assert!(cache
+            .voice_channels
+            .rtc_region(ChannelId(6))
+            .await?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reorder_roles() -> Result<(), Box<dyn Error>> {
+        async fn sorted(cache: &InMemoryCache) -> Result<Vec<(RoleId, i64)>, InMemoryBackendError> {
+            cache
+                .guilds
+                .sorted_roles(GuildId(1))
+                .await?
+                .map_ok(|role| (role.id, role.position))
+                .try_collect()
+                .await
+        }
+
+        let cache = InMemoryCache::new();
+
+        for (id, name) in &[(10, "everyone"), (11, "mod"), (12, "admin")] {
+            let mut builder = RoleEntity::builder(RoleId(*id), GuildId(1), *name);
+            builder.position(1);
+            cache.roles.upsert(builder.build()).await?;
+        }
+
+        assert_eq!(
+            vec![(RoleId(10), 1), (RoleId(11), 1), (RoleId(12), 1)],
+            sorted(&cache).await?
+        );
+
+        cache
+            .guilds
+            .reorder_roles(
+                GuildId(1),
+                vec![RoleId(10), RoleId(12), RoleId(99), RoleId(11)],
+            )
+            .await?;
+        assert_eq!(
+            vec![(RoleId(10), 0), (RoleId(12), 1), (RoleId(11), 3)],
+            sorted(&cache).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ordered_channel_ids() -> Result<(), Box<dyn Error>> {
+        async fn ordered(cache: &InMemoryCache) -> Result<Vec<ChannelId>, InMemoryBackendError> {
+            cache
+                .guilds
+                .ordered_channel_ids(GuildId(1))
+                .await?
+                .try_collect()
+                .await
+        }
+
+        let cache = InMemoryCache::new();
+
+        for (id, position) in &[(1, 0), (2, 1)] {
+            let mut builder = TextChannelEntity::builder(ChannelId(*id), "text");
+            builder.guild_id(GuildId(1)).position(*position);
+            cache.text_channels.upsert(builder.build()).await?;
+        }
+
+        let mut category = CategoryChannelEntity::builder(ChannelId(3), "category");
+        category.guild_id(GuildId(1)).position(1);
+        cache.category_channels.upsert(category.build()).await?;
+
+        assert_eq!(
+            vec![ChannelId(1), ChannelId(2), ChannelId(3)],
+            ordered(&cache).await?
+        );
+
+        let mut moved = TextChannelEntity::builder(ChannelId(1), "text");
+        moved.guild_id(GuildId(1)).position(2);
+        cache.text_channels.upsert(moved.build()).await?;
+        assert_eq!(
+            vec![ChannelId(2), ChannelId(3), ChannelId(1)],
+            ordered(&cache).await?
+        );
+
+        cache.text_channels.remove(ChannelId(2)).await?;
+        assert_eq!(vec![ChannelId(3), ChannelId(1)], ordered(&cache).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nick_history() -> Result<(), Box<dyn Error>> {
+        let mut builder = InMemoryBackend::builder();
+        builder.name_history_size(2);
+        let cache = Cache::with_backend(builder.build());
+        cache
+            .process(&Event::MemberAdd(Box::new(MemberAdd(member()))))
+            .await?;
+
+        for (nick, name) in &[
+            ("a", "user"),
+            ("b", "user"),
+            ("c", "user"),
+            ("c", "renamed"),
+        ] {
+            cache
+                .process(&Event::MemberUpdate(Box::new(MemberUpdate {
+                    deaf: None,
+                    guild_id: GuildId(1),
+                    joined_at: String::from("2012-11-21T10:00:00.40000+00:00"),
+                    mute: None,
+                    nick: Some(String::from(*nick)),
+                    pending: false,
+                    premium_since: None,
+                    roles: Vec::new(),
+                    user: User {
+                        name: String::from(*name),
+                        ..user()
+                    },
+                })))
+                .await?;
+        }
+
+        let history = cache
+            .members
+            .nick_history(GuildId(1), UserId(2))
+            .await?
+            .map_ok(|change| (change.kind, change.old))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(
+            vec![
+                (NameKind::Nickname, Some(String::from("a"))),
+                (NameKind::Nickname, Some(String::from("b"))),
+                (NameKind::Username, Some(String::from("user"))),
+            ],
+            history
+        );
+
+        cache
+            .process(&Event::MemberRemove(MemberRemove {
+                guild_id: GuildId(1),
+                user: user(),
+            }))
+            .await?;
+        let history = cache
+            .members
+            .nick_history(GuildId(1), UserId(2))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(1, history.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_avatar_history() -> Result<(), Box<dyn Error>> {
+        let mut builder = InMemoryBackend::builder();
+        builder.avatar_history_size(2);
+        let cache = Cache::with_backend(builder.build());
+
+        for avatar in &[Some("a_animated"), Some("static"), Some("static"), None] {
+            let mut user = UserEntity::builder(UserId(2), "user", "0001");
+
+            if let Some(avatar) = avatar {
+                user.avatar(*avatar);
+            }
+
+            cache.users.upsert(user.build()).await?;
+        }
+
+        let history = cache
+            .users
+            .avatar_history(UserId(2))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        let old = history
+            .iter()
+            .map(|change| change.old.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![Some("a_animated"), Some("static")], old);
+
+        let user = cache.users.get(UserId(2)).await?.expect("user is cached");
+        assert_eq!(
+            "https://cdn.discordapp.com/embed/avatars/1.png",
+            user.avatar_url(128, ImageFormat::Gif)
+        );
+        assert_eq!(
+            "https://cdn.discordapp.com/avatars/2/a_animated.gif?size=64",
+            history[0].avatar_url(&user, 64, ImageFormat::Gif)
+        );
+        assert_eq!(
+            "https://cdn.discordapp.com/avatars/2/static.png?size=64",
+            history[1].avatar_url(&user, 64, ImageFormat::Gif)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_members_with_users() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        cache
+            .process(&Event::MemberAdd(Box::new(MemberAdd(member()))))
+            .await?;
+        cache
+            .members
+            .upsert(MemberEntity::builder(GuildId(1), UserId(9)).build())
+            .await?;
+
+        let mut members = cache
+            .guilds
+            .members_with_users(GuildId(1))
+            .await?
+            .map_ok(|(member, user)| (member.user_id, user))
+            .try_collect::<Vec<_>>()
+            .await?;
+        members.sort_by_key(|(user_id, _)| *user_id);
+        assert_eq!(
+            vec![
+                (UserId(2), Some(UserEntity::from(user()))),
+                (UserId(9), None),
+            ],
+            members
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_message_context() -> Result<(), Box<dyn Error>> {
+        let backend = InMemoryBackend::new();
+        let guild = GuildEntity::builder(GuildId(1), "guild", UserId(1)).build();
+        backend.guilds().upsert(guild.clone()).await?;
+        let mut text_channel = TextChannelEntity::builder(ChannelId(1), "general");
+        text_channel.guild_id(GuildId(1));
+        let text_channel = text_channel.build();
+        backend.text_channels().upsert(text_channel.clone()).await?;
+        let member = MemberEntity::builder(GuildId(1), UserId(2)).build();
+        backend.members().upsert(member.clone()).await?;
+        let user = UserEntity::from(user());
+        backend.users().upsert(user.clone()).await?;
+
+        let mut message = MessageEntity::builder(MessageId(1), ChannelId(1), UserId(2));
+        message.guild_id(GuildId(1));
+        let message = message.build();
+        backend.messages().upsert(message.clone()).await?;
+        backend.messages().upsert(message_entity(2, 3)).await?;
+
+        let context = backend.messages().context(MessageId(1)).await?.unwrap();
+        assert_eq!(Some(user), context.author);
+        assert_eq!(
+            Some(ChannelEntity::Guild(GuildChannelEntity::Text(text_channel))),
+            context.channel
+        );
+        assert_eq!(Some(guild), context.guild);
+        assert_eq!(Some(member), context.member);
+        assert_eq!(message, context.message);
+
+        let context = backend.messages().context(MessageId(2)).await?.unwrap();
+        assert!(context.channel.is_none());
+        assert!(context.guild.is_none());
+        assert!(context.member.is_none());
+        assert!(backend.messages().context(MessageId(3)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_suppression() -> Result<(), Box<dyn Error>> {
+        let upserts = Arc::new(Mutex::new(0));
+        let recorded = Arc::clone(&upserts);
+        let backend = RecordingBackend::new(InMemoryBackend::new(), move |record| {
+            if let Record::Upsert { .. } = record {
+                *recorded.lock().unwrap() += 1;
+            }
+        });
+        let mut cache = Cache::with_backend(backend);
+        cache.enable_duplicate_suppression();
+
+        let event = Event::MemberAdd(Box::new(MemberAdd(member())));
+        cache.process(&event).await?;
+        let written = *upserts.lock().unwrap();
+        assert!(written > 0);
+
+        cache.process(&event).await?;
+        assert_eq!(written, *upserts.lock().unwrap());
+
+        let mut member = member();
+        member.nick = Some(String::from("nick"));
+        cache
+            .process(&Event::MemberAdd(Box::new(MemberAdd(member))))
+            .await?;
+        assert_eq!(written + 1, *upserts.lock().unwrap());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_panic_isolation() {
+        struct Panic;
+
+        impl EntityInterceptor for Panic {
+            fn role(&self, _: RoleEntity) -> Option<RoleEntity> {
+                panic!("role rejected");
+            }
+        }
+
+        let mut cache = InMemoryCache::new();
+        cache.set_entity_interceptor(Panic);
+        cache.enable_panic_isolation();
+
+        let event = Event::RoleCreate(RoleCreate {
+            guild_id: GuildId(1),
+            role: role(),
+        });
+        let error = cache.process(&event).await.unwrap_err();
+        assert_eq!(Some(EventType::RoleCreate), error.event_type());
+        assert!(matches!(
+            error.kind(),
+            ProcessErrorType::Panicked {
+                message: Some(message),
+            } if message == "role rejected"
+        ));
+
+        cache
+            .process(&Event::MemberAdd(Box::new(MemberAdd(member()))))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_relation_ids_ordered() -> Result<(), Box<dyn Error>> {
+        let backend = InMemoryBackend::new();
+
+        for id in [5, 2, 9, 2, 7] {
+            backend
+                .members()
+                .upsert(MemberEntity::builder(GuildId(1), UserId(id)).build())
+                .await?;
+            backend
+                .roles()
+                .upsert(RoleEntity::builder(RoleId(id), GuildId(1), "role").build())
+                .await?;
+        }
+
+        backend.members().remove((GuildId(1), UserId(7))).await?;
+
+        let member_ids = backend
+            .guilds()
+            .member_ids(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![UserId(2), UserId(5), UserId(9)], member_ids);
+
+        let role_ids = backend
+            .guilds()
+            .role_ids(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![RoleId(2), RoleId(5), RoleId(7), RoleId(9)], role_ids);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_members() -> Result<(), Box<dyn Error>> {
+        for index in [false, true] {
+            let mut builder = InMemoryBackend::builder();
+            builder.index_member_names(index);
+            let backend = builder.build();
+            let guilds = backend.guilds();
+
+            let search = |query: &'static str, limit| {
+                guilds
+                    .search_members(GuildId(1), query, limit)
+                    .and_then(|members| {
+                        members
+                            .map_ok(|member| member.user_id)
+                            .try_collect::<Vec<_>>()
+                    })
+            };
+
+            for (id, name, nick) in [
+                (2, "alice", None),
+                (3, "Albert", Some("Zed")),
+                (4, "bob", Some("Alfie")),
+            ] {
+                backend
+                    .users()
+                    .upsert(UserEntity::builder(UserId(id), name, "0001").build())
+                    .await?;
+
+                let mut member = MemberEntity::builder(GuildId(1), UserId(id));
+
+                if let Some(nick) = nick {
+                    member.nick(nick);
+                }
+
+                backend.members().upsert(member.build()).await?;
+            }
+
+            assert_eq!(
+                vec![UserId(3), UserId(4), UserId(2)],
+                search("AL", 10).await?
+            );
+            assert_eq!(vec![UserId(3), UserId(4)], search("al", 2).await?);
+            assert_eq!(vec![UserId(3)], search("z", 10).await?);
+
+            backend
+                .users()
+                .upsert(UserEntity::builder(UserId(2), "carol", "0001").build())
+                .await?;
+            backend
+                .members()
+                .upsert(MemberEntity::builder(GuildId(1), UserId(4)).build())
+                .await?;
+            backend.members().remove((GuildId(1), UserId(3))).await?;
+
+            assert!(search("al", 10).await?.is_empty());
+            assert_eq!(vec![UserId(4)], search("b", 10).await?);
+            assert_eq!(vec![UserId(2)], search("car", 10).await?);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_channel_overwrites() -> Result<(), Box<dyn Error>> {
+        let backend = InMemoryBackend::new();
+        let overwrite = |kind, allow| PermissionOverwrite {
+            allow,
+            deny: Permissions::empty(),
+            kind,
+        };
+        let member_overwrite = overwrite(
+            PermissionOverwriteType::Member(UserId(2)),
+            Permissions::ATTACH_FILES,
+        );
+        let role_overwrite = overwrite(
+            PermissionOverwriteType::Role(RoleId(3)),
+            Permissions::EMBED_LINKS,
+        );
+        let everyone_overwrite = overwrite(
+            PermissionOverwriteType::Role(RoleId(1)),
+            Permissions::SEND_MESSAGES,
+        );
+        let other_overwrite = overwrite(
+            PermissionOverwriteType::Role(RoleId(4)),
+            Permissions::MANAGE_MESSAGES,
+        );
+
+        let mut channel = TextChannelEntity::builder(ChannelId(5), "general");
+        channel.guild_id(GuildId(1)).permission_overwrites(vec![
+            member_overwrite.clone(),
+            role_overwrite.clone(),
+            everyone_overwrite.clone(),
+            other_overwrite.clone(),
+        ]);
+        backend.text_channels().upsert(channel.build()).await?;
+
+        let mut member = MemberEntity::builder(GuildId(1), UserId(2));
+        member.role_ids(vec![RoleId(3)]);
+        backend.members().upsert(member.build()).await?;
+
+        let channels = backend.text_channels();
+        assert_eq!(
+            Some(other_overwrite),
+            channels
+                .overwrite_for(ChannelId(5), PermissionOverwriteType::Role(RoleId(4)))
+                .await?
+        );
+        assert!(channels
+            .overwrite_for(ChannelId(5), PermissionOverwriteType::Member(UserId(6)))
+            .await?
+            .is_none());
+        assert_eq!(
+            Some(vec![everyone_overwrite, role_overwrite, member_overwrite]),
+            channels
+                .effective_overwrites(ChannelId(5), UserId(2))
+                .await?
+        );
+        assert!(channels
+            .effective_overwrites(ChannelId(5), UserId(6))
+            .await?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_channel_synced_with_parent() -> Result<(), Box<dyn Error>> {
+        let backend = InMemoryBackend::new();
+        let overwrites = vec![
+            PermissionOverwrite {
+                allow: Permissions::SEND_MESSAGES,
+                deny: Permissions::empty(),
+                kind: PermissionOverwriteType::Role(RoleId(1)),
+            },
+            PermissionOverwrite {
+                allow: Permissions::empty(),
+                deny: Permissions::ATTACH_FILES,
+                kind: PermissionOverwriteType::Member(UserId(2)),
+            },
+        ];
+
+        let mut category = CategoryChannelEntity::builder(ChannelId(3), "category");
+        category
+            .guild_id(GuildId(1))
+            .permission_overwrites(overwrites.clone());
+        backend.category_channels().upsert(category.build()).await?;
+
+        let mut channel = TextChannelEntity::builder(ChannelId(4), "general");
+        channel
+            .guild_id(GuildId(1))
+            .parent_id(ChannelId(3))
+            .permission_overwrites(overwrites.iter().rev().cloned().collect());
+        backend
+            .text_channels()
+            .upsert(channel.clone().build())
+            .await?;
+
+        let channels = backend.text_channels();
+        assert_eq!(
+            Some(true),
+            channels.is_synced_with_parent(ChannelId(4)).await?
+        );
+
+        channel.permission_overwrites(overwrites[..1].to_vec());
+        backend.text_channels().upsert(channel.build()).await?;
+        assert_eq!(
+            Some(false),
+            channels.is_synced_with_parent(ChannelId(4)).await?
+        );
+
+        let mut orphan = TextChannelEntity::builder(ChannelId(5), "orphan");
+        orphan.guild_id(GuildId(1));
+        backend.text_channels().upsert(orphan.build()).await?;
+        assert_eq!(None, channels.is_synced_with_parent(ChannelId(5)).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_voice_transitions() -> Result<(), Box<dyn Error>> {
+        let mut builder = InMemoryBackend::builder();
+        builder.voice_history_size(2);
+        let backend = builder.build();
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let hook_changes = Arc::clone(&changes);
+
+        let mut cache = Cache::with_backend(backend.clone());
+        cache.add_hook(move |change| hook_changes.lock().unwrap().push(change.clone()));
+
+        let mut state = voice_state();
+        cache
+            .process(&Event::VoiceStateUpdate(Box::new(VoiceStateUpdate(
+                state.clone(),
+            ))))
+            .await?;
+        state.mute = true;
+        state.self_deaf = true;
+        cache
+            .process(&Event::VoiceStateUpdate(Box::new(VoiceStateUpdate(
+                state.clone(),
+            ))))
+            .await?;
+        // deafening themselves isn't a moderation transition
+        state.self_deaf = false;
+        cache
+            .process(&Event::VoiceStateUpdate(Box::new(VoiceStateUpdate(
+                state.clone(),
+            ))))
+            .await?;
+        state.channel_id = Some(ChannelId(7));
+        state.mute = false;
+        cache
+            .process(&Event::VoiceStateUpdate(Box::new(VoiceStateUpdate(state))))
+            .await?;
+
+        let changed = |transitions| CacheChange::VoiceStateChanged {
+            guild_id: GuildId(1),
+            user_id: UserId(2),
+            transitions,
+        };
+        assert_eq!(
+            vec![
+                changed(vec![VoiceTransition::Channel {
+                    old: None,
+                    new: Some(ChannelId(6)),
+                }]),
+                changed(vec![VoiceTransition::Mute {
+                    old: false,
+                    new: true,
+                }]),
+                changed(vec![
+                    VoiceTransition::Channel {
+                        old: Some(ChannelId(6)),
+                        new: Some(ChannelId(7)),
+                    },
+                    VoiceTransition::Mute {
+                        old: true,
+                        new: false,
+                    },
+                ]),
+            ],
+            *changes.lock().unwrap()
+        );
+
+        let history = backend
+            .voice_states()
+            .transition_history(GuildId(1), UserId(2))
+            .await?
+            .map_ok(|change| change.transition)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(
+            vec![
+                VoiceTransition::Channel {
+                    old: Some(ChannelId(6)),
+                    new: Some(ChannelId(7)),
+                },
+                VoiceTransition::Mute {
+                    old: true,
+                    new: false,
+                },
+            ],
+            history
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_filtering() -> Result<(), Box<dyn Error>> {
+        let mut cache = InMemoryCache::new();
+        cache.enable_ephemeral_filtering();
+
+        let mut messages = messages();
+        messages[0].flags = Some(MessageFlags::EPHEMERAL);
+
+        for message in messages.drain(..2) {
+            let event = Event::MessageCreate(Box::new(MessageCreate(message)));
+            cache.process(&event).await?;
+        }
+
+        assert!(cache.messages.get(MessageId(100)).await?.is_none());
+        assert!(cache.messages.get(MessageId(101)).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_non_system_messages() -> Result<(), Box<dyn Error>> {
+        let backend = InMemoryBackend::new();
+        let messages = backend.messages();
+
+        for (id, kind) in [
+            (1, MessageType::Regular),
+            (2, MessageType::GuildMemberJoin),
+            (3, MessageType::Reply),
+            (4, MessageType::ChannelMessagePinned),
+        ] {
+            let mut message = MessageEntity::builder(MessageId(id), ChannelId(5), UserId(6));
+            message.kind(kind);
+            messages.upsert(message.build()).await?;
+        }
+
+        let ids = messages
+            .non_system(ChannelId(5))
+            .await?
+            .map_ok(|message| message.id)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![MessageId(1), MessageId(3)], ids);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_integrity() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        assert!(cache.verify_integrity(false).await?.is_consistent());
+
+        let backend = cache.backend();
+        backend.0.members.remove(&(GuildId(1), UserId(2)));
+        backend
+            .0
+            .channel_messages
+            .entry(ChannelId(3))
+            .or_default()
+            .insert(MessageId(4));
+        backend
+            .0
+            .user_guilds
+            .insert(UserId(2), vec![GuildId(1), GuildId(5)]);
+
+        let expected = [
+            DanglingRelation::ChannelMessage {
+                channel_id: ChannelId(3),
+                message_id: MessageId(4),
+            },
+            DanglingRelation::GuildMember {
+                guild_id: GuildId(1),
+                user_id: UserId(2),
+            },
+            DanglingRelation::UserGuild {
+                user_id: UserId(2),
+                guild_id: GuildId(5),
+            },
+        ];
+
+        let report = cache.verify_integrity(false).await?;
+        assert_eq!(&expected, report.dangling());
+        assert!(!report.is_repaired());
+
+        let report = cache.verify_integrity(true).await?;
+        assert_eq!(&expected, report.dangling());
+        assert!(report.is_repaired());
+
+        assert!(cache.verify_integrity(false).await?.is_consistent());
+        assert_eq!(0, cache.guilds.member_ids(GuildId(1)).await?.count().await);
+        assert_eq!(
+            vec![GuildId(1)],
+            *backend.0.user_guilds.get(&UserId(2)).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_guild_caps() -> Result<(), Box<dyn Error>> {
+        for policy in [
+            GuildCapPolicy::Refuse,
+            GuildCapPolicy::EvictLeastRecentlyInserted,
+        ] {
+            let mut builder = InMemoryBackend::builder();
+            builder
+                .guild_cap_policy(policy)
+                .max_cached_members_per_guild(2)
+                .max_cached_presences_per_guild(0);
+            let backend = builder.build();
+            let members = backend.members();
+
+            for id in 1..=3 {
+                members
+                    .upsert(MemberEntity::builder(GuildId(1), UserId(id)).build())
+                    .await?;
+            }
+
+            members
+                .upsert(MemberEntity::builder(GuildId(2), UserId(1)).build())
+                .await?;
+
+            let mut member = MemberEntity::builder(GuildId(1), UserId(2));
+            member.nick("nick");
+            members.upsert(member.build()).await?;
+
+            let member_ids = backend
+                .guilds()
+                .member_ids(GuildId(1))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?;
+
+            let expected = match policy {
+                GuildCapPolicy::Refuse => vec![UserId(1), UserId(2)],
+                GuildCapPolicy::EvictLeastRecentlyInserted => vec![UserId(2), UserId(3)],
+            };
+            assert_eq!(expected, member_ids);
+            assert_eq!(
+                expected.contains(&UserId(1)),
+                members.get((GuildId(1), UserId(1))).await?.is_some()
+            );
+            assert_eq!(
+                Some("nick"),
+                members
+                    .get((GuildId(1), UserId(2)))
+                    .await?
+                    .unwrap()
+                    .nick
+                    .as_deref()
+            );
+            assert!(members.get((GuildId(2), UserId(1))).await?.is_some());
+
+            backend
+                .presences()
+                .upsert(PresenceEntity::builder(GuildId(1), UserId(1)).build())
+                .await?;
+            assert!(backend
+                .presences()
+                .get((GuildId(1), UserId(1)))
+                .await?
+                .is_none());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_guild_allowlist_denylist() -> Result<(), Box<dyn Error>> {
+        for allowlist in [true, false] {
+            let mut builder = InMemoryBackend::builder();
+
+            if allowlist {
+                builder.guild_allowlist(vec![GuildId(2)]);
+            } else {
+                builder.guild_denylist(vec![GuildId(1)]);
+            }
+
+            let cache = Cache::with_backend(builder.build());
+            cache
+                .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+                .await?;
+            cache
+                .process(&Event::RoleCreate(RoleCreate {
+                    guild_id: GuildId(1),
+                    role: role(),
+                }))
+                .await?;
+
+            assert!(cache.guilds.get(GuildId(1)).await?.is_none());
+            assert!(cache.members.get((GuildId(1), UserId(2))).await?.is_none());
+            assert!(cache.roles.get(RoleId(12)).await?.is_none());
+
+            let mut event = ready();
+            event.guilds = vec![
+                GuildStatus::Offline(twilight_model::guild::UnavailableGuild {
+                    id: GuildId(1),
+                    unavailable: true,
+                }),
+                GuildStatus::Offline(twilight_model::guild::UnavailableGuild {
+                    id: GuildId(2),
+                    unavailable: true,
+                }),
+            ];
+            cache.process(&Event::Ready(Box::new(event))).await?;
+
+            assert!(cache.guilds.get(GuildId(1)).await?.is_none());
+            assert!(cache.guilds.get(GuildId(2)).await?.is_some());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache_mode() -> Result<(), Box<dyn Error>> {
+        for mode in [CacheMode::DirectMessagesOnly, CacheMode::GuildsOnly] {
+            let mut cache = InMemoryCache::new();
+            cache.set_mode(mode);
+
+            let mut message = messages().remove(0);
+            message.channel_id = ChannelId(7);
+            message.guild_id = None;
+            message.member = None;
+
+            let events = [
+                Event::GuildCreate(Box::new(GuildCreate(guild()))),
+                Event::ChannelCreate(ChannelCreate(Channel::Private(private()))),
+                Event::ChannelCreate(ChannelCreate(Channel::Group(group()))),
+                Event::MessageCreate(Box::new(MessageCreate(message))),
+            ];
+
+            for event in &events {
+                cache.process(event).await?;
+            }
+
+            let guilds = mode == CacheMode::GuildsOnly;
+            assert_eq!(guilds, cache.guilds.get(GuildId(1)).await?.is_some());
+            assert_eq!(
+                guilds,
+                cache.members.get((GuildId(1), UserId(2))).await?.is_some()
+            );
+            assert_eq!(
+                !guilds,
+                cache.private_channels.get(ChannelId(7)).await?.is_some()
+            );
+            assert_eq!(!guilds, cache.groups.get(ChannelId(3)).await?.is_some());
+            assert_eq!(!guilds, cache.messages.get(MessageId(100)).await?.is_some());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache_mode_sequenced() -> Result<(), Box<dyn Error>> {
+        let mut cache = InMemoryCache::new();
+        cache.set_mode(CacheMode::DirectMessagesOnly);
+
+        let mut event = ready();
+        event.guilds = vec![
+            GuildStatus::Online(guild()),
+            GuildStatus::Offline(twilight_model::guild::UnavailableGuild {
+                id: GuildId(2),
+                unavailable: true,
+            }),
+        ];
+        cache
+            .process_sequenced(&Event::Ready(Box::new(event)), 1)
+            .await?;
+
+        assert!(cache.current_user.get().await?.is_some());
+        assert!(cache.guilds.get(GuildId(1)).await?.is_none());
+        assert!(cache.guilds.get(GuildId(2)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_partial() -> Result<(), Box<dyn Error>> {
+        use twilight_model::channel::message::Mention;
+
+        let cache = InMemoryCache::new();
+
+        let mut user = UserEntity::from(user());
+        user.email = Some(String::from("user@example.com"));
+        user.public_flags = Some(UserFlags::HOUSE_BRAVERY);
+        cache.users.upsert(user).await?;
+
+        let mut member = MemberEntity::builder(GuildId(1), UserId(2));
+        member.hoisted_role_id(RoleId(3)).nick("old");
+        cache.members.upsert(member.build()).await?;
+
+        let mut message = messages().remove(0);
+        message.author.locale = None;
+        message.author.name = String::from("renamed");
+        message.member.as_mut().unwrap().nick = Some(String::from("new"));
+        message.mentions = vec![Mention {
+            avatar: None,
+            bot: false,
+            discriminator: String::from("0003"),
+            id: UserId(3),
+            member: message.member.clone(),
+            name: String::from("mentioned"),
+            public_flags: UserFlags::empty(),
+        }];
+        cache
+            .process(&Event::MessageCreate(Box::new(MessageCreate(message))))
+            .await?;
+
+        // fields missing from the author keep their known values
+        let user = cache.users.get(UserId(2)).await?.unwrap();
+        assert_eq!("renamed", user.name);
+        assert_eq!(Some("user@example.com"), user.email.as_deref());
+        assert_eq!(Some("en-US"), user.locale.as_deref());
+        assert!(user.has_badge(UserFlags::HOUSE_BRAVERY));
+
+        let member = cache.members.get((GuildId(1), UserId(2))).await?.unwrap();
+        assert_eq!(Some("new"), member.nick.as_deref());
+        assert_eq!(Some(RoleId(3)), member.hoisted_role_id);
+        assert!(member.joined_at.is_some());
+
+        // mentioned users and their members are hydrated
+        assert_eq!("mentioned", cache.users.get(UserId(3)).await?.unwrap().name);
+        assert!(cache.members.get((GuildId(1), UserId(3))).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_user_badges() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+
+        let mut developer = UserEntity::builder(UserId(1), "developer", "0001");
+        developer.public_flags(UserFlags::VERIFIED_BOT_DEVELOPER | UserFlags::HOUSE_BRAVERY);
+        cache.users.upsert(developer.build()).await?;
+        cache.users.upsert(UserEntity::from(user())).await?;
+
+        let user_ids = cache
+            .users
+            .with_public_flag(UserFlags::VERIFIED_BOT_DEVELOPER)
+            .await?
+            .map_ok(|user| user.id)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![UserId(1)], user_ids);
+
+        let mut message = messages().remove(0);
+        message.author.public_flags = Some(UserFlags::VERIFIED_BOT_DEVELOPER);
+        cache
+            .process(&Event::MessageCreate(Box::new(MessageCreate(message))))
+            .await?;
+
+        let user = cache.users.get(UserId(2)).await?.unwrap();
+        assert!(user.has_badge(UserFlags::VERIFIED_BOT_DEVELOPER));
+        assert!(!user.has_badge(UserFlags::HOUSE_BRAVERY));
+
+        let mut user_ids = cache
+            .users
+            .with_public_flag(UserFlags::VERIFIED_BOT_DEVELOPER)
+            .await?
+            .map_ok(|user| user.id)
+            .try_collect::<Vec<_>>()
+            .await?;
+        user_ids.sort();
+        assert_eq!(vec![UserId(1), UserId(2)], user_ids);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_warm_start() -> Result<(), Box<dyn Error>> {
+        let dir = std::env::temp_dir().join(format!("twilight-cache-dumps-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let payload = serde_json::json!({
+            "op": 0,
+            "s": 1,
+            "t": "GUILD_CREATE",
+            "d": guild(),
+        });
+        fs::write(dir.join("1.json"), serde_json::to_vec(&payload)?)?;
+        fs::write(dir.join("2.json"), r#"{"op":11,"t":null,"d":null}"#)?;
+        let other = Guild {
+            id: GuildId(5),
+            members: Vec::new(),
+            presences: Vec::new(),
+            ..guild()
+        };
+        fs::write(dir.join("3.json"), serde_json::to_vec(&other)?)?;
+        fs::write(dir.join("notes.txt"), "not a payload")?;
+
+        let cache = InMemoryCache::new();
+        let loaded = warm_start::load_guild_dumps(&cache, &dir).await;
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(2, loaded?);
+        assert!(cache.guilds.get(GuildId(1)).await?.is_some());
+        assert!(cache.members.get((GuildId(1), UserId(2))).await?.is_some());
+        assert!(cache.guilds.get(GuildId(5)).await?.is_some());
+
+        assert!(warm_start::parse_guild_dump(b"{}").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_any() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        cache.process(&Event::Ready(Box::new(ready()))).await?;
+
+        let member = cache.get_any(EntityKind::Member, (1, 2)).await?.unwrap();
+        assert_eq!(EntityKind::Member, member.kind());
+        assert_eq!(
+            AnyEntity::Member(cache.members.get((GuildId(1), UserId(2))).await?.unwrap()),
+            member
+        );
+        assert!(cache.get_any(EntityKind::Member, 1).await?.is_none());
+        assert!(cache.get_any(EntityKind::Guild, (1, 2)).await?.is_none());
+        assert!(cache.get_any(EntityKind::Role, 1).await?.is_none());
+        assert_eq!(
+            Some(EntityKind::User),
+            cache
+                .get_any(EntityKind::User, 2)
+                .await?
+                .map(|user| user.kind())
+        );
+        assert_eq!(
+            Some(EntityKind::CurrentUser),
+            cache
+                .get_any(EntityKind::CurrentUser, 0)
+                .await?
+                .map(|user| user.kind())
+        );
+
+        for kind in EntityKind::ALL {
+            assert_eq!(Some(*kind), EntityKind::from_name(kind.name()));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore() -> Result<(), Box<dyn Error>> {
+        let cache = InMemoryCache::new();
+        cache
+            .process(&Event::GuildCreate(Box::new(GuildCreate(guild()))))
+            .await?;
+        cache.process(&Event::Ready(Box::new(ready()))).await?;
+        let snapshot = Snapshot::capture(cache.backend().as_ref()).await?;
+
+        let backend = InMemoryBackend::new();
+        snapshot.restore(&backend).await?;
+
+        assert!(snapshot::diff(&snapshot, &Snapshot::capture(&backend).await?).is_empty());
+        assert!(backend
+            .guilds()
+            .members(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?
+            .iter()
+            .any(|member| member.user_id == UserId(2)));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "wal")]
+    #[tokio::test]
+    async fn test_write_ahead_log() -> Result<(), Box<dyn Error>> {
+        use super::wal::WriteAheadLog;
+
+        let path = std::env::temp_dir().join(format!("twilight-cache-{}.wal", process::id()));
+        let _ = fs::remove_file(&path);
+        let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+
+        {
+            let wal = WriteAheadLog::open(InMemoryBackend::new(), &path).await?;
+            let backend = wal.backend();
+            backend.guilds().upsert(guild.clone()).await?;
+            backend
+                .users()
+                .upsert(UserEntity::builder(UserId(2), "user", "0001").build())
+                .await?;
+            backend.users().remove(UserId(2)).await?;
+            wal.sync()?;
+        }
+
+        // a write cut off by a crash is ignored
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)?
+            .write_all(br#"{"op":"upsert","entity":{"gu"#)?;
+
+        let wal = WriteAheadLog::open(InMemoryBackend::new(), &path).await?;
+        let backend = wal.backend();
+        assert_eq!(Some(guild.clone()), backend.guilds().get(GuildId(1)).await?);
+        assert!(backend.users().get(UserId(2)).await?.is_none());
+
+        // compacting leaves only upserts of the cached entities
+        wal.compact()?;
+        let compacted = fs::read_to_string(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(1, compacted?.lines().count());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "wal")]
+    #[tokio::test]
+    async fn test_write_ahead_log_concurrent_writes() -> Result<(), Box<dyn Error>> {
+        use super::wal::WriteAheadLog;
+        use std::thread;
+
+        let path =
+            std::env::temp_dir().join(format!("twilight-cache-{}-concurrent.wal", process::id()));
+        let _ = fs::remove_file(&path);
+        let wal = WriteAheadLog::open(InMemoryBackend::new(), &path).await?;
+
+        // writes to the same users race each other and compaction
+        let writers = (0..4)
+            .map(|writer| {
+                let users = wal.backend().users();
+
+                thread::spawn(move || {
+                    futures::executor::block_on(async {
+                        for idx in 0..100 {
+                            let user_id = UserId(idx % 5 + 1);
+                            let name = format!("{}-{}", writer, idx);
+                            users
+                                .upsert(UserEntity::builder(user_id, name, "0001").build())
+                                .await?;
+
+                            if idx % 7 == 0 {
+                                users.remove(user_id).await?;
+                            }
+                        }
+
+                        Ok::<_, InMemoryBackendError>(())
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..20 {
+            wal.compact()?;
+        }
+
+        for writer in writers {
+            writer.join().unwrap()?;
+        }
+
+        wal.sync()?;
+
+        // the log replays to the state the writes were applied in
+        let mut expected = wal
+            .backend()
+            .users()
+            .list()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        let replayed = WriteAheadLog::open(InMemoryBackend::new(), &path).await;
+        let _ = fs::remove_file(&path);
+        let mut replayed = replayed?
+            .backend()
+            .users()
+            .list()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        expected.sort_by_key(|user| user.id);
+        replayed.sort_by_key(|user| user.id);
+        assert_eq!(expected, replayed);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalidator() -> Result<(), Box<dyn Error>> {
+        use twilight_cache::invalidation::{
+            Invalidation, InvalidationBus, Invalidator, SubscribeFuture,
+        };
+
+        /// Bus delivering every write published so far to each subscriber.
+        ///
+        /// Publishing fails while the bus is closed, and subscribers receive
+        /// an error in place of the write.
+        #[derive(Clone, Debug, Default)]
+        struct HistoryBus {
+            closed: Arc<AtomicBool>,
+            history: Arc<Mutex<Vec<Result<Invalidation, InMemoryBackendError>>>>,
+        }
+
+        impl InvalidationBus for HistoryBus {
+            type Error = InMemoryBackendError;
+
+            fn publish(&self, invalidation: &Invalidation) -> Result<(), Self::Error> {
+                let mut history = self.history.lock().unwrap();
+
+                if self.closed.load(Ordering::Relaxed) {
+                    history.push(Err(InMemoryBackendError));
+
+                    return Err(InMemoryBackendError);
+                }
+
+                history.push(Ok(invalidation.clone()));
+
+                Ok(())
+            }
+
+            fn subscribe(&self) -> SubscribeFuture<'_, Self::Error> {
+                let history = self.history.lock().unwrap().clone();
+
+                future::ok(stream::iter(history).boxed()).boxed()
+            }
+        }
+
+        let bus = HistoryBus::default();
+        let first = Invalidator::new(InMemoryBackend::new(), bus.clone());
+        let second = Invalidator::new(InMemoryBackend::new(), bus.clone());
+        assert_ne!(first.origin(), second.origin());
+
+        let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+        let user = UserEntity::builder(UserId(2), "user", "0001").build();
+        first.backend().guilds().upsert(guild.clone()).await?;
+        first.backend().users().upsert(user.clone()).await?;
+        second.backend().users().upsert(user.clone()).await?;
+        first.backend().users().remove(UserId(2)).await?;
+        assert_eq!(4, bus.history.lock().unwrap().len());
+
+        second.listen(|_| false).await?;
+        let backend = second.backend();
+        assert_eq!(Some(guild), backend.guilds().get(GuildId(1)).await?);
+        assert!(backend.users().get(UserId(2)).await?.is_none());
+
+        // an instance doesn't apply its own writes again, so its removal of
+        // the user isn't applied after the other instance's upsert
+        first.listen(|_| false).await?;
+        assert_eq!(Some(user), first.backend().users().get(UserId(2)).await?);
+        assert_eq!(4, bus.history.lock().unwrap().len());
+
+        // writes that fail to be published are still applied, and the error
+        // is kept until it's taken
+        assert!(first.take_publish_error().is_none());
+        bus.closed.store(true, Ordering::Relaxed);
+        let channel = TextChannelEntity::builder(ChannelId(3), "channel").build();
+        first
+            .backend()
+            .text_channels()
+            .upsert(channel.clone())
+            .await?;
+        assert!(first.take_publish_error().is_some());
+        assert!(first.take_publish_error().is_none());
+        assert_eq!(
+            Some(channel),
+            first.backend().text_channels().get(ChannelId(3)).await?
+        );
+
+        // the callback decides whether to keep listening after an error
+        bus.closed.store(false, Ordering::Relaxed);
+        second.backend().users().remove(UserId(4)).await?;
+        assert!(first.listen(|_| false).await.is_err());
+
+        let mut errors = 0;
+        first
+            .listen(|_| {
+                errors += 1;
+
+                true
+            })
+            .await?;
+        assert_eq!(1, errors);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upsert_with_outcome() -> Result<(), Box<dyn Error>> {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&records);
+        let backend = RecordingBackend::new(InMemoryBackend::new(), move |record| {
+            recorded.lock().unwrap().push(record.clone());
+        });
+        let users = backend.users();
+
+        let user = UserEntity::builder(UserId(2), "user", "0001").build();
+        assert_eq!(
+            UpsertOutcome::Created,
+            users.upsert_with_outcome(user.clone()).await?
+        );
+        assert_eq!(
+            UpsertOutcome::Updated,
+            users.upsert_with_outcome(user).await?
+        );
+        assert_eq!(2, records.lock().unwrap().len());
+
+        let mut builder = InMemoryBackend::builder();
+        builder.entity_types(EntityType::MESSAGE);
+        let backend = builder.build();
+
+        let user = UserEntity::builder(UserId(2), "user", "0001").build();
+        assert_eq!(
+            UpsertOutcome::Skipped,
+            backend.users().upsert_with_outcome(user).await?
+        );
+        assert!(backend.users().get(UserId(2)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_take() -> Result<(), Box<dyn Error>> {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&records);
+        let backend = RecordingBackend::new(InMemoryBackend::new(), move |record| {
+            recorded.lock().unwrap().push(record.clone());
+        });
+        let users = backend.users();
+
+        let user = UserEntity::builder(UserId(2), "user", "0001").build();
+        users.upsert(user.clone()).await?;
+
+        assert_eq!(Some(user), users.take(UserId(2)).await?);
+        assert!(users.get(UserId(2)).await?.is_none());
+        assert!(users.take(UserId(2)).await?.is_none());
+        assert_eq!(
+            Some(&Record::Remove {
+                id: RecordedId::User(UserId(2)),
+            }),
+            records.lock().unwrap().get(1)
+        );
+
+        let repo = backend.inner().current_user();
+        repo.upsert(CurrentUserEntity::from(current_user())).await?;
+
+        assert_eq!(
+            Some(CurrentUserEntity::from(current_user())),
+            repo.take().await?
+        );
+        assert!(repo.get().await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconnecting_backend() -> Result<(), Box<dyn Error>> {
+        use twilight_cache::reconnect::ReconnectingBackend;
+
+        let user = |name| UserEntity::builder(UserId(2), name, "0001").build();
+
+        // the retry interval never elapses, so only flushing reconnects
+        let flaky = FlakyBackend::default();
+        let backend =
+            ReconnectingBackend::with_retry_interval(flaky.clone(), 2, Duration::from_secs(3600));
+        let users = backend.users();
+
+        users.upsert(user("first")).await?;
+        assert_eq!(["first"], flaky.names().as_slice());
+
+        // writes failing along with the health check are buffered
+        flaky.down.store(true, Ordering::SeqCst);
+        users.upsert(user("second")).await?;
+        users.upsert(user("third")).await?;
+        assert!(!backend.is_connected());
+        assert_eq!(2, backend.buffered());
+        assert!(users.get(UserId(2)).await.is_err());
+
+        // flushing fails while the backend is down, keeping the buffer
+        assert!(backend.flush().await.is_err());
+        assert_eq!(2, backend.buffered());
+
+        // flushing replays the buffered writes in order before the retry
+        // interval elapses
+        flaky.down.store(false, Ordering::SeqCst);
+        backend.flush().await?;
+        assert!(backend.is_connected());
+        assert_eq!(0, backend.buffered());
+        assert_eq!(["first", "second", "third"], flaky.names().as_slice());
+        assert_eq!(Some(user("third")), users.get(UserId(2)).await?);
+        assert!(!backend.take_overflow());
+
+        // writes made while the buffer is full aren't passed through ahead of
+        // the buffered writes, even if the backend is back up
+        flaky.down.store(true, Ordering::SeqCst);
+        users.upsert(user("fourth")).await?;
+        flaky.down.store(false, Ordering::SeqCst);
+        users.upsert(user("fifth")).await?;
+        users.upsert(user("sixth")).await?;
+        assert_eq!(1, backend.buffered());
+        assert_eq!(3, flaky.names().len());
+        assert!(backend.take_overflow());
+        assert!(!backend.take_overflow());
+
+        backend.flush().await?;
+        assert_eq!(
+            ["first", "second", "third", "sixth"],
+            flaky.names().as_slice()
+        );
+        assert_eq!(Some(user("sixth")), users.get(UserId(2)).await?);
+
+        Ok(())
+    }
 }
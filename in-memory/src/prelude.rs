@@ -14,11 +14,11 @@ pub use twilight_cache::{
         },
         gateway::presence::PresenceRepository as _,
         guild::{
-            emoji::EmojiRepository as _, member::MemberRepository as _, role::RoleRepository as _,
-            GuildRepository as _,
+            emoji::EmojiRepository as _, member::MemberRepository as _,
+            preview::GuildPreviewRepository as _, role::RoleRepository as _, GuildRepository as _,
         },
         user::UserRepository as _,
-        voice::VoiceStateRepository as _,
+        voice::{VoiceRegionRepository as _, VoiceStateRepository as _},
     },
     Backend as _, Cache, Repository as _,
 };
@@ -0,0 +1,98 @@
+//! Snapshots of the relations indexed by the backend.
+//!
+//! The backend indexes relations between entities, such as the IDs of the
+//! members of each guild, separately from the entities themselves. The
+//! repositories only expose the relations that every backend supports, so
+//! [`Relations`] exposes the backend's indexes directly for building custom
+//! queries on top of them.
+//!
+//! The indexes are an implementation detail of the backend and may change
+//! between minor versions, so this module is exempt from semver guarantees
+//! and requires the `unstable-relations` feature.
+//!
+//! # Examples
+//!
+//! Count the members of a guild without retrieving them:
+//!
+//! ```
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_model::id::GuildId;
+//!
+//! let backend = InMemoryBackend::new();
+//! let members = backend.relations().guild_members(GuildId(1));
+//!
+//! assert!(members.is_empty());
+//! ```
+//!
+//! [`Relations`]: struct.Relations.html
+
+use super::InMemoryBackendRef;
+use dashmap::DashMap;
+use std::{collections::BTreeSet, hash::Hash};
+use twilight_model::id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+/// Relations indexed by an [`InMemoryBackend`].
+///
+/// Each method returns a snapshot of a relation taken when it's called, in
+/// order of ID. Relations of entities that aren't cached are empty.
+///
+/// [`InMemoryBackend`]: ../struct.InMemoryBackend.html
+#[derive(Clone, Copy, Debug)]
+pub struct Relations<'a>(pub(crate) &'a InMemoryBackendRef);
+
+impl Relations<'_> {
+    /// Return the IDs of the cached messages of a channel.
+    pub fn channel_messages(&self, channel_id: ChannelId) -> BTreeSet<MessageId> {
+        snapshot(&self.0.channel_messages, &channel_id)
+    }
+
+    /// Return the IDs of the channels of a guild.
+    pub fn guild_channels(&self, guild_id: GuildId) -> BTreeSet<ChannelId> {
+        snapshot(&self.0.guild_channels, &guild_id)
+    }
+
+    /// Return the IDs of the emojis of a guild.
+    pub fn guild_emojis(&self, guild_id: GuildId) -> BTreeSet<EmojiId> {
+        snapshot(&self.0.guild_emojis, &guild_id)
+    }
+
+    /// Return the IDs of the users that are members of a guild.
+    pub fn guild_members(&self, guild_id: GuildId) -> BTreeSet<UserId> {
+        snapshot(&self.0.guild_members, &guild_id)
+    }
+
+    /// Return the IDs of the users with a presence in a guild.
+    pub fn guild_presences(&self, guild_id: GuildId) -> BTreeSet<UserId> {
+        snapshot(&self.0.guild_presences, &guild_id)
+    }
+
+    /// Return the IDs of the roles of a guild.
+    pub fn guild_roles(&self, guild_id: GuildId) -> BTreeSet<RoleId> {
+        snapshot(&self.0.guild_roles, &guild_id)
+    }
+
+    /// Return the IDs of the users with a voice state in a guild.
+    pub fn guild_voice_states(&self, guild_id: GuildId) -> BTreeSet<UserId> {
+        snapshot(&self.0.guild_voice_states, &guild_id)
+    }
+
+    /// Return the IDs of the guilds that a user is a member of.
+    pub fn user_guilds(&self, user_id: UserId) -> BTreeSet<GuildId> {
+        self.0
+            .user_guilds
+            .get(&user_id)
+            .map(|guild_ids| guild_ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Clone the set of a relation's key, if any.
+fn snapshot<K: Eq + Hash, V: Clone + Ord>(
+    relations: &DashMap<K, BTreeSet<V>>,
+    key: &K,
+) -> BTreeSet<V> {
+    relations
+        .get(key)
+        .map(|values| values.clone())
+        .unwrap_or_default()
+}
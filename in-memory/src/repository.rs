@@ -1,10 +1,24 @@
-use crate::{config::EntityType, InMemoryBackend, InMemoryBackendError};
+use crate::{
+    config::{EntityType, EvictionPolicy, GuildCapPolicy},
+    InMemoryBackend, InMemoryBackendError, InMemoryBackendRef,
+};
 use dashmap::DashMap;
 use futures_util::{
     future::{self, FutureExt},
-    stream::{self, StreamExt},
+    stream::{self, BoxStream, StreamExt},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    hash::Hash,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Mutex, TryLockError},
+    task::{Context, Poll},
+    time::SystemTime,
 };
-use std::{marker::PhantomData, sync::Mutex};
 use twilight_cache::{
     entity::{
         channel::{
@@ -17,24 +31,30 @@ use twilight_cache::{
             voice_channel::{VoiceChannelEntity, VoiceChannelRepository},
             ChannelEntity, GuildChannelEntity,
         },
-        gateway::presence::{PresenceEntity, PresenceRepository},
+        gateway::presence::{ActivityQuery, PresenceEntity, PresenceRepository},
         guild::{
             emoji::{EmojiEntity, EmojiRepository},
-            member::{MemberEntity, MemberRepository},
+            member::{MemberEntity, MemberRepository, NameChange, NameKind},
+            preview::{GuildPreviewEntity, GuildPreviewRepository},
             role::{RoleEntity, RoleRepository},
-            GuildEntity, GuildRepository,
+            GuildEntity, GuildRepository, GuildStats,
         },
         user::{
             current_user::{CurrentUserEntity, CurrentUserRepository},
-            UserEntity, UserRepository,
+            AvatarChange, UserEntity, UserRepository,
+        },
+        voice::{
+            VoiceRegionEntity, VoiceRegionId, VoiceRegionRepository, VoiceStateChange,
+            VoiceStateEntity, VoiceStateRepository, VoiceTransition,
         },
-        voice::{VoiceStateEntity, VoiceStateRepository},
         Entity,
     },
+    meta::CacheMeta,
     repository::{
         GetEntityFuture, ListEntitiesFuture, ListEntityIdsFuture, RemoveEntityFuture, Repository,
-        SingleEntityRepository, UpsertEntityFuture,
+        SingleEntityRepository, UpsertEntityFuture, UpsertOutcome, UpsertOutcomeFuture,
     },
+    size::EstimateSize,
 };
 use twilight_model::id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
 
@@ -43,6 +63,7 @@ pub type InMemoryCategoryChannelRepository = InMemoryRepository<CategoryChannelE
 pub type InMemoryCurrentUserRepository = InMemoryRepository<CurrentUserEntity>;
 pub type InMemoryEmojiRepository = InMemoryRepository<EmojiEntity>;
 pub type InMemoryGroupRepository = InMemoryRepository<GroupEntity>;
+pub type InMemoryGuildPreviewRepository = InMemoryRepository<GuildPreviewEntity>;
 pub type InMemoryGuildRepository = InMemoryRepository<GuildEntity>;
 pub type InMemoryMemberRepository = InMemoryRepository<MemberEntity>;
 pub type InMemoryMessageRepository = InMemoryRepository<MessageEntity>;
@@ -52,14 +73,141 @@ pub type InMemoryRoleRepository = InMemoryRepository<RoleEntity>;
 pub type InMemoryTextChannelRepository = InMemoryRepository<TextChannelEntity>;
 pub type InMemoryUserRepository = InMemoryRepository<UserEntity>;
 pub type InMemoryVoiceChannelRepository = InMemoryRepository<VoiceChannelEntity>;
+pub type InMemoryVoiceRegionRepository = InMemoryRepository<VoiceRegionEntity>;
 pub type InMemoryVoiceStateRepository = InMemoryRepository<VoiceStateEntity>;
 
-pub trait EntityExt: Clone + Entity {
+/// Times that entities were cached and last updated at, by type.
+#[derive(Debug, Default)]
+pub(crate) struct Metadata {
+    attachments: DashMap<AttachmentId, CacheMeta>,
+    channels_category: DashMap<ChannelId, CacheMeta>,
+    channels_private: DashMap<ChannelId, CacheMeta>,
+    channels_text: DashMap<ChannelId, CacheMeta>,
+    channels_voice: DashMap<ChannelId, CacheMeta>,
+    emojis: DashMap<EmojiId, CacheMeta>,
+    groups: DashMap<ChannelId, CacheMeta>,
+    guild_previews: DashMap<GuildId, CacheMeta>,
+    guilds: DashMap<GuildId, CacheMeta>,
+    members: DashMap<(GuildId, UserId), CacheMeta>,
+    messages: DashMap<MessageId, CacheMeta>,
+    presences: DashMap<(GuildId, UserId), CacheMeta>,
+    roles: DashMap<RoleId, CacheMeta>,
+    users: DashMap<UserId, CacheMeta>,
+    voice_regions: DashMap<VoiceRegionId, CacheMeta>,
+    voice_states: DashMap<(GuildId, UserId), CacheMeta>,
+}
+
+/// Sequences that entities were last upserted with, by type.
+#[derive(Debug, Default)]
+pub(crate) struct Sequences {
+    attachments: DashMap<AttachmentId, u64>,
+    channels_category: DashMap<ChannelId, u64>,
+    channels_private: DashMap<ChannelId, u64>,
+    channels_text: DashMap<ChannelId, u64>,
+    channels_voice: DashMap<ChannelId, u64>,
+    emojis: DashMap<EmojiId, u64>,
+    groups: DashMap<ChannelId, u64>,
+    guild_previews: DashMap<GuildId, u64>,
+    guilds: DashMap<GuildId, u64>,
+    members: DashMap<(GuildId, UserId), u64>,
+    messages: DashMap<MessageId, u64>,
+    presences: DashMap<(GuildId, UserId), u64>,
+    roles: DashMap<RoleId, u64>,
+    users: DashMap<UserId, u64>,
+    voice_regions: DashMap<VoiceRegionId, u64>,
+    voice_states: DashMap<(GuildId, UserId), u64>,
+}
+
+/// Queue of messages in the order they're evicted in, used for each channel
+/// and across all channels when the total number of messages is capped.
+///
+/// Messages are indexed by their position in the queue so that they can be
+/// removed or moved to the back without scanning it.
+#[derive(Debug, Default)]
+pub(crate) struct MessageQueue {
+    next: u64,
+    order: BTreeMap<u64, MessageId>,
+    positions: HashMap<MessageId, u64>,
+}
+
+impl MessageQueue {
+    /// Add a message to the back of the queue, moving it if it's already
+    /// queued.
+    fn push_back(&mut self, message_id: MessageId) {
+        self.remove(message_id);
+
+        self.order.insert(self.next, message_id);
+        self.positions.insert(message_id, self.next);
+        self.next += 1;
+    }
+
+    /// Return the message at the front of the queue.
+    fn front(&self) -> Option<MessageId> {
+        self.order.values().next().copied()
+    }
+
+    /// Remove the message at the front of the queue.
+    fn pop_front(&mut self) -> Option<MessageId> {
+        let (_, message_id) = self.order.pop_first()?;
+        self.positions.remove(&message_id);
+
+        Some(message_id)
+    }
+
+    /// Remove a message from the queue, returning whether it was queued.
+    pub(crate) fn remove(&mut self, message_id: MessageId) -> bool {
+        match self.positions.remove(&message_id) {
+            Some(position) => {
+                self.order.remove(&position);
+
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub trait EntityExt: Clone + Entity + EstimateSize {
     const TYPE: EntityType;
 
     fn map(backend: &InMemoryBackend) -> &DashMap<Self::Id, Self>
     where
         Self: Sized;
+
+    /// Sequences that entities of the type were last upserted with.
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<Self::Id, u64>
+    where
+        Self: Sized;
+
+    /// Times that entities of the type were cached and last updated at.
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<Self::Id, CacheMeta>
+    where
+        Self: Sized;
+
+    /// Update the backend's indexes after an entity was retrieved.
+    fn accessed(_: &InMemoryBackend, _: &Self) {}
+
+    /// Return whether an entity that isn't cached yet can be inserted,
+    /// evicting other entities to make room for it if needed.
+    fn admit(_: &InMemoryBackend, _: &Self) -> bool {
+        true
+    }
+
+    /// Update the backend's indexes after an entity was inserted, and whether
+    /// it's new to the cache.
+    fn inserted(_: &InMemoryBackend, _: Self::Id, _: bool) {}
+
+    /// Update the backend's indexes when an entity was inserted in place of
+    /// an older version of it, given the older version.
+    ///
+    /// This is called before [`inserted`], with the new version already in
+    /// the map.
+    ///
+    /// [`inserted`]: #method.inserted
+    fn replaced(_: &InMemoryBackend, _: &Self) {}
+
+    /// Update the backend's indexes after an entity was removed.
+    fn removed(_: &InMemoryBackend, _: &Self) {}
 }
 
 impl EntityExt for AttachmentEntity {
@@ -68,6 +216,14 @@ impl EntityExt for AttachmentEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<AttachmentId, AttachmentEntity> {
         &backend.0.attachments
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<AttachmentId, u64> {
+        &backend.0.sequences.attachments
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<AttachmentId, CacheMeta> {
+        &backend.0.metadata.attachments
+    }
 }
 
 impl EntityExt for CategoryChannelEntity {
@@ -76,6 +232,43 @@ impl EntityExt for CategoryChannelEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<ChannelId, CategoryChannelEntity> {
         &backend.0.channels_category
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<ChannelId, u64> {
+        &backend.0.sequences.channels_category
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<ChannelId, CacheMeta> {
+        &backend.0.metadata.channels_category
+    }
+
+    fn inserted(backend: &InMemoryBackend, channel_id: ChannelId, new: bool) {
+        let channel = backend
+            .0
+            .channels_category
+            .get(&channel_id)
+            .and_then(|c| c.guild_id.map(|guild_id| (guild_id, c.position)));
+
+        if let Some((guild_id, position)) = channel {
+            if new {
+                relate(&backend.0.guild_channels, guild_id, channel_id);
+            }
+
+            order_channel(backend, guild_id, position, channel_id);
+        }
+    }
+
+    fn replaced(backend: &InMemoryBackend, channel: &Self) {
+        if let Some(guild_id) = channel.guild_id {
+            unorder_channel(backend, &guild_id, channel.position, channel.id);
+        }
+    }
+
+    fn removed(backend: &InMemoryBackend, channel: &Self) {
+        if let Some(guild_id) = channel.guild_id {
+            unrelate(&backend.0.guild_channels, &guild_id, &channel.id);
+            unorder_channel(backend, &guild_id, channel.position, channel.id);
+        }
+    }
 }
 
 impl EntityExt for EmojiEntity {
@@ -84,6 +277,28 @@ impl EntityExt for EmojiEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<EmojiId, EmojiEntity> {
         &backend.0.emojis
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<EmojiId, u64> {
+        &backend.0.sequences.emojis
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<EmojiId, CacheMeta> {
+        &backend.0.metadata.emojis
+    }
+
+    fn inserted(backend: &InMemoryBackend, emoji_id: EmojiId, new: bool) {
+        if new {
+            let guild_id = backend.0.emojis.get(&emoji_id).map(|emoji| emoji.guild_id);
+
+            if let Some(guild_id) = guild_id {
+                relate(&backend.0.guild_emojis, guild_id, emoji_id);
+            }
+        }
+    }
+
+    fn removed(backend: &InMemoryBackend, emoji: &Self) {
+        unrelate(&backend.0.guild_emojis, &emoji.guild_id, &emoji.id);
+    }
 }
 
 impl EntityExt for GroupEntity {
@@ -92,6 +307,14 @@ impl EntityExt for GroupEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<ChannelId, GroupEntity> {
         &backend.0.groups
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<ChannelId, u64> {
+        &backend.0.sequences.groups
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<ChannelId, CacheMeta> {
+        &backend.0.metadata.groups
+    }
 }
 
 impl EntityExt for GuildEntity {
@@ -100,6 +323,30 @@ impl EntityExt for GuildEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<GuildId, GuildEntity> {
         &backend.0.guilds
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<GuildId, u64> {
+        &backend.0.sequences.guilds
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<GuildId, CacheMeta> {
+        &backend.0.metadata.guilds
+    }
+}
+
+impl EntityExt for GuildPreviewEntity {
+    const TYPE: EntityType = EntityType::GUILD_PREVIEW;
+
+    fn map(backend: &InMemoryBackend) -> &DashMap<GuildId, GuildPreviewEntity> {
+        &backend.0.guild_previews
+    }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<GuildId, u64> {
+        &backend.0.sequences.guild_previews
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<GuildId, CacheMeta> {
+        &backend.0.metadata.guild_previews
+    }
 }
 
 impl EntityExt for MemberEntity {
@@ -108,6 +355,225 @@ impl EntityExt for MemberEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<Self::Id, Self> {
         &backend.0.members
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<Self::Id, u64> {
+        &backend.0.sequences.members
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<Self::Id, CacheMeta> {
+        &backend.0.metadata.members
+    }
+
+    fn admit(backend: &InMemoryBackend, member: &Self) -> bool {
+        admit_to_guild::<Self>(
+            backend,
+            &backend.0.guild_members,
+            &backend.0.guild_member_order,
+            backend.0.config.max_cached_members_per_guild(),
+            (member.guild_id, member.user_id),
+        )
+    }
+
+    fn inserted(backend: &InMemoryBackend, (guild_id, user_id): Self::Id, new: bool) {
+        index_member_names(backend, guild_id, user_id);
+
+        if !new {
+            return;
+        }
+
+        relate(&backend.0.guild_members, guild_id, user_id);
+
+        if evicts_from_guild(backend, backend.0.config.max_cached_members_per_guild()) {
+            backend
+                .0
+                .guild_member_order
+                .entry(guild_id)
+                .or_default()
+                .push_back(user_id);
+        }
+
+        let size = backend.0.config.recent_joins_size();
+
+        if size == 0 {
+            return;
+        }
+
+        let joined_at = backend
+            .0
+            .members
+            .get(&(guild_id, user_id))
+            .and_then(|member| member.joined_at.clone());
+
+        if let Some(joined_at) = joined_at {
+            let mut joins = backend.0.guild_joins.entry(guild_id).or_default();
+            joins.insert((joined_at, user_id));
+
+            while joins.len() > size {
+                joins.pop_first();
+            }
+        }
+    }
+
+    fn replaced(backend: &InMemoryBackend, member: &Self) {
+        if let Some(nick) = &member.nick {
+            unindex_member_name(backend, member.guild_id, member.user_id, nick);
+        }
+
+        let size = backend.0.config.name_history_size();
+
+        if size == 0 {
+            return;
+        }
+
+        let key = (member.guild_id, member.user_id);
+        let renamed = backend
+            .0
+            .members
+            .get(&key)
+            .is_some_and(|new| new.nick != member.nick);
+
+        if renamed {
+            let change = NameChange {
+                changed_at: SystemTime::now(),
+                kind: NameKind::Nickname,
+                old: member.nick.clone(),
+            };
+
+            record_change(&backend.0.member_names, key, change, size);
+        }
+    }
+
+    fn removed(backend: &InMemoryBackend, member: &Self) {
+        unrelate(&backend.0.guild_members, &member.guild_id, &member.user_id);
+
+        if evicts_from_guild(backend, backend.0.config.max_cached_members_per_guild()) {
+            if let Some(mut order) = backend.0.guild_member_order.get_mut(&member.guild_id) {
+                remove_from(&mut order, member.user_id);
+            }
+        }
+
+        if backend.0.config.index_member_names() {
+            let username = backend
+                .0
+                .users
+                .get(&member.user_id)
+                .map(|user| user.name.clone());
+
+            if let Some(mut names) = backend.0.member_search.get_mut(&member.guild_id) {
+                for name in member.nick.iter().chain(&username) {
+                    names.remove(&(name.to_lowercase(), member.user_id));
+                }
+            }
+        }
+
+        backend
+            .0
+            .member_names
+            .remove(&(member.guild_id, member.user_id));
+
+        if let Some(joined_at) = member.joined_at.clone() {
+            if let Some(mut joins) = backend.0.guild_joins.get_mut(&member.guild_id) {
+                joins.remove(&(joined_at, member.user_id));
+            }
+        }
+    }
+}
+
+/// Add a member to the index of members by name under their nickname and the
+/// username of their user, if it's cached.
+fn index_member_names(backend: &InMemoryBackend, guild_id: GuildId, user_id: UserId) {
+    if !backend.0.config.index_member_names() {
+        return;
+    }
+
+    let nick = backend
+        .0
+        .members
+        .get(&(guild_id, user_id))
+        .and_then(|member| member.nick.clone());
+    let username = backend.0.users.get(&user_id).map(|user| user.name.clone());
+
+    let mut names = backend.0.member_search.entry(guild_id).or_default();
+
+    for name in nick.iter().chain(&username) {
+        names.insert((name.to_lowercase(), user_id));
+    }
+}
+
+/// Remove one of a member's previous names from the index of members by name,
+/// unless the member still has the name.
+fn unindex_member_name(backend: &InMemoryBackend, guild_id: GuildId, user_id: UserId, name: &str) {
+    if !backend.0.config.index_member_names() {
+        return;
+    }
+
+    let nick = backend
+        .0
+        .members
+        .get(&(guild_id, user_id))
+        .and_then(|member| member.nick.clone());
+    let username = backend.0.users.get(&user_id).map(|user| user.name.clone());
+    let name = name.to_lowercase();
+
+    if nick
+        .iter()
+        .chain(&username)
+        .any(|current| current.to_lowercase() == name)
+    {
+        return;
+    }
+
+    if let Some(mut names) = backend.0.member_search.get_mut(&guild_id) {
+        names.remove(&(name, user_id));
+    }
+}
+
+/// Move a renamed user's members in the index of members by name from their
+/// previous username to their current one.
+fn reindex_username(backend: &InMemoryBackend, user: &UserEntity) {
+    if !backend.0.config.index_member_names() {
+        return;
+    }
+
+    let renamed = backend
+        .0
+        .users
+        .get(&user.id)
+        .is_some_and(|new| new.name != user.name);
+
+    if !renamed {
+        return;
+    }
+
+    let key = (user.name.to_lowercase(), user.id);
+    let guild_ids = backend
+        .0
+        .member_search
+        .iter()
+        .filter(|names| names.contains(&key))
+        .map(|names| *names.key())
+        .collect::<Vec<_>>();
+
+    for guild_id in guild_ids {
+        unindex_member_name(backend, guild_id, user.id, &user.name);
+        index_member_names(backend, guild_id, user.id);
+    }
+}
+
+/// Record a change to a member or user, dropping the oldest changes over the
+/// limit.
+fn record_change<K: Eq + Hash, T>(
+    changes: &DashMap<K, VecDeque<T>>,
+    key: K,
+    change: T,
+    size: usize,
+) {
+    let mut history = changes.entry(key).or_default();
+    history.push_back(change);
+
+    while history.len() > size {
+        history.pop_front();
+    }
 }
 
 impl EntityExt for MessageEntity {
@@ -116,6 +582,344 @@ impl EntityExt for MessageEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<MessageId, MessageEntity> {
         &backend.0.messages
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<MessageId, u64> {
+        &backend.0.sequences.messages
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<MessageId, CacheMeta> {
+        &backend.0.metadata.messages
+    }
+
+    fn accessed(backend: &InMemoryBackend, message: &Self) {
+        touch_message(backend, message.channel_id, message.id);
+    }
+
+    fn inserted(backend: &InMemoryBackend, message_id: MessageId, new: bool) {
+        let config = &backend.0.config;
+
+        let Some(channel_id) = backend
+            .0
+            .messages
+            .get(&message_id)
+            .map(|message| message.channel_id)
+        else {
+            return;
+        };
+
+        if !new {
+            touch_message(backend, channel_id, message_id);
+
+            return;
+        }
+
+        let mut evicted = Vec::new();
+
+        {
+            let mut ids = backend.0.channel_messages.entry(channel_id).or_default();
+            let mut order = backend
+                .0
+                .channel_message_order
+                .entry(channel_id)
+                .or_default();
+
+            ids.insert(message_id);
+            order.push_back(message_id);
+
+            let max = config.message_cache_size_for(channel_id);
+
+            while ids.len() > max {
+                let id = match config.message_eviction_policy() {
+                    EvictionPolicy::OldestId => ids.iter().next().copied(),
+                    EvictionPolicy::NewestId => ids.iter().next_back().copied(),
+                    EvictionPolicy::LeastRecentlyInserted
+                    | EvictionPolicy::LeastRecentlyAccessed => order.front(),
+                };
+
+                let Some(id) = id else {
+                    break;
+                };
+
+                ids.remove(&id);
+                order.remove(id);
+                evicted.push(id);
+            }
+        }
+
+        if let Some(max_total) = config.max_total_messages() {
+            let mut queue = backend.0.message_queue.lock().expect("queue poisoned");
+
+            for id in &evicted {
+                queue.remove(*id);
+            }
+
+            if !evicted.contains(&message_id) {
+                queue.push_back(message_id);
+            }
+
+            // Messages evicted for their channel's limit are still cached
+            // until they're removed below.
+            let excess = backend
+                .0
+                .messages
+                .len()
+                .saturating_sub(evicted.len())
+                .saturating_sub(max_total);
+
+            evicted.extend((0..excess).map_while(|_| queue.pop_front()));
+        }
+
+        // Channel indexes and the shared queue must not be locked while
+        // removing evicted messages, as removing them updates both.
+        for id in evicted {
+            remove_entity::<Self>(backend, id);
+        }
+    }
+
+    fn removed(backend: &InMemoryBackend, message: &Self) {
+        remove_from_channel(backend, message);
+
+        if backend.0.config.max_total_messages().is_some() {
+            backend
+                .0
+                .message_queue
+                .lock()
+                .expect("queue poisoned")
+                .remove(message.id);
+        }
+
+        for attachment_id in &message.attachments {
+            remove_entity::<AttachmentEntity>(backend, *attachment_id);
+        }
+    }
+}
+
+/// Move a message to the back of the eviction queues if messages are evicted
+/// by when they were last accessed.
+fn touch_message(backend: &InMemoryBackend, channel_id: ChannelId, message_id: MessageId) {
+    let config = &backend.0.config;
+
+    if config.message_eviction_policy() != EvictionPolicy::LeastRecentlyAccessed {
+        return;
+    }
+
+    if let Some(mut order) = backend.0.channel_message_order.get_mut(&channel_id) {
+        if order.remove(message_id) {
+            order.push_back(message_id);
+        }
+    }
+
+    if config.max_total_messages().is_some() {
+        let mut queue = backend.0.message_queue.lock().expect("queue poisoned");
+
+        if queue.remove(message_id) {
+            queue.push_back(message_id);
+        }
+    }
+}
+
+/// Remove a message from the indexes of its channel.
+fn remove_from_channel(backend: &InMemoryBackend, message: &MessageEntity) {
+    if let Some(mut ids) = backend.0.channel_messages.get_mut(&message.channel_id) {
+        ids.remove(&message.id);
+    }
+
+    if let Some(mut order) = backend.0.channel_message_order.get_mut(&message.channel_id) {
+        order.remove(message.id);
+    }
+}
+
+/// Remove an ID from an eviction queue, returning whether it was in the
+/// queue.
+fn remove_from<T: PartialEq>(queue: &mut VecDeque<T>, id: T) -> bool {
+    match queue.iter().position(|queued| *queued == id) {
+        Some(idx) => {
+            queue.remove(idx);
+
+            true
+        }
+        None => false,
+    }
+}
+
+/// Add an entity to the set of entities related to another.
+fn relate<K: Eq + Hash, V: Ord>(relations: &DashMap<K, BTreeSet<V>>, key: K, value: V) {
+    relations.entry(key).or_default().insert(value);
+}
+
+/// Remove an entity from the set of entities related to another.
+fn unrelate<K: Eq + Hash, V: Ord>(relations: &DashMap<K, BTreeSet<V>>, key: &K, value: &V) {
+    if let Some(mut set) = relations.get_mut(key) {
+        set.remove(value);
+    }
+}
+
+/// Remove an entity along with its sequence and metadata, updating the
+/// backend's indexes and returning the removed entity.
+fn remove_entity<E: EntityExt>(backend: &InMemoryBackend, entity_id: E::Id) -> Option<E> {
+    let entity = E::map(backend).remove(&entity_id).map(|(_, entity)| entity);
+
+    if let Some(entity) = entity.as_ref() {
+        E::removed(backend, entity);
+    }
+
+    E::sequences(backend).remove(&entity_id);
+    E::metadata(backend).remove(&entity_id);
+
+    entity
+}
+
+/// Upsert an entity, updating the backend's indexes and returning whether it
+/// was created, updated, or skipped.
+fn upsert_entity<E: EntityExt>(backend: &InMemoryBackend, entity: E) -> UpsertOutcome {
+    let config = &backend.0.config;
+
+    if !config.entity_types().contains(E::TYPE) {
+        return UpsertOutcome::Skipped;
+    }
+
+    if let Some(max) = config.max_entity_size() {
+        let size = entity.estimated_size();
+
+        if size > max {
+            if let Some(hook) = config.oversized_entity_hook() {
+                hook.call(E::TYPE, size);
+            }
+
+            return UpsertOutcome::Skipped;
+        }
+    }
+
+    let id = entity.id();
+
+    if !E::map(backend).contains_key(&id) && !E::admit(backend, &entity) {
+        return UpsertOutcome::Skipped;
+    }
+
+    let old = E::map(backend).insert(id, entity);
+
+    if let Some(old) = old.as_ref() {
+        E::replaced(backend, old);
+    }
+
+    if config.track_metadata() {
+        let now = SystemTime::now();
+
+        E::metadata(backend)
+            .entry(id)
+            .and_modify(|meta| *meta = meta.updated(now))
+            .or_insert_with(|| CacheMeta::new(now));
+    }
+
+    E::inserted(backend, id, old.is_none());
+
+    if old.is_some() {
+        UpsertOutcome::Updated
+    } else {
+        UpsertOutcome::Created
+    }
+}
+
+/// Return whether entities of a type are evicted from guilds at their cap,
+/// in which case the order that they were inserted in is tracked.
+fn evicts_from_guild(backend: &InMemoryBackend, max: Option<usize>) -> bool {
+    max.is_some()
+        && backend.0.config.guild_cap_policy() == GuildCapPolicy::EvictLeastRecentlyInserted
+}
+
+/// Return whether a new entity can be cached in a guild with a cap on the
+/// number of entities of its type, evicting the guild's least recently
+/// inserted entities to make room for it if the policy allows it.
+///
+/// Pinned guilds aren't capped, and the entities of pinned users are always
+/// cached and never evicted.
+fn admit_to_guild<E: EntityExt<Id = (GuildId, UserId)>>(
+    backend: &InMemoryBackend,
+    cached: &DashMap<GuildId, BTreeSet<UserId>>,
+    order: &DashMap<GuildId, VecDeque<UserId>>,
+    max: Option<usize>,
+    (guild_id, user_id): (GuildId, UserId),
+) -> bool {
+    let Some(max) = max else {
+        return true;
+    };
+
+    if backend.0.pinned_guilds.contains(&guild_id) || backend.0.pinned_users.contains(&user_id) {
+        return true;
+    }
+
+    let len = cached.get(&guild_id).map_or(0, |user_ids| user_ids.len());
+
+    if len < max {
+        return true;
+    }
+
+    if max == 0 || !evicts_from_guild(backend, Some(max)) {
+        return false;
+    }
+
+    // The queue must not be locked while evicting, as removing an entity
+    // removes it from the queue.
+    let evicted = order.get_mut(&guild_id).map_or_else(
+        || Some(Vec::new()),
+        |mut order| {
+            let count = (len - max + 1).min(order.len());
+            let evicted = order
+                .iter()
+                .copied()
+                .filter(|user_id| !backend.0.pinned_users.contains(user_id))
+                .take(count)
+                .collect::<Vec<_>>();
+
+            // Entities of pinned users may leave too few entities to evict.
+            if evicted.len() < count {
+                return None;
+            }
+
+            order.retain(|user_id| !evicted.contains(user_id));
+
+            Some(evicted)
+        },
+    );
+
+    let Some(evicted) = evicted else {
+        return false;
+    };
+
+    for user_id in evicted {
+        remove_entity::<E>(backend, (guild_id, user_id));
+    }
+
+    true
+}
+
+/// Add a guild channel to the index of the guild's channels ordered by
+/// position.
+fn order_channel(
+    backend: &InMemoryBackend,
+    guild_id: GuildId,
+    position: i64,
+    channel_id: ChannelId,
+) {
+    backend
+        .0
+        .guild_channel_order
+        .entry(guild_id)
+        .or_default()
+        .insert((position, channel_id));
+}
+
+/// Remove a guild channel from the index of the guild's channels ordered by
+/// position.
+fn unorder_channel(
+    backend: &InMemoryBackend,
+    guild_id: &GuildId,
+    position: i64,
+    channel_id: ChannelId,
+) {
+    if let Some(mut order) = backend.0.guild_channel_order.get_mut(guild_id) {
+        order.remove(&(position, channel_id));
+    }
 }
 
 impl EntityExt for PresenceEntity {
@@ -124,6 +928,94 @@ impl EntityExt for PresenceEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<(GuildId, UserId), PresenceEntity> {
         &backend.0.presences
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<(GuildId, UserId), u64> {
+        &backend.0.sequences.presences
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<(GuildId, UserId), CacheMeta> {
+        &backend.0.metadata.presences
+    }
+
+    fn admit(backend: &InMemoryBackend, presence: &Self) -> bool {
+        admit_to_guild::<Self>(
+            backend,
+            &backend.0.guild_presences,
+            &backend.0.guild_presence_order,
+            backend.0.config.max_cached_presences_per_guild(),
+            (presence.guild_id, presence.user_id),
+        )
+    }
+
+    fn inserted(backend: &InMemoryBackend, (guild_id, user_id): Self::Id, new: bool) {
+        if new {
+            relate(&backend.0.guild_presences, guild_id, user_id);
+
+            if evicts_from_guild(backend, backend.0.config.max_cached_presences_per_guild()) {
+                backend
+                    .0
+                    .guild_presence_order
+                    .entry(guild_id)
+                    .or_default()
+                    .push_back(user_id);
+            }
+        }
+
+        if backend.0.config.index_presence_activities() {
+            let queries = backend
+                .0
+                .presences
+                .get(&(guild_id, user_id))
+                .map(|presence| {
+                    presence
+                        .activities
+                        .iter()
+                        .flat_map(ActivityQuery::keys)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+
+            for query in queries {
+                relate(&backend.0.presence_activities, (guild_id, query), user_id);
+            }
+        }
+    }
+
+    fn replaced(backend: &InMemoryBackend, presence: &Self) {
+        unindex_activities(backend, presence);
+    }
+
+    fn removed(backend: &InMemoryBackend, presence: &Self) {
+        unrelate(
+            &backend.0.guild_presences,
+            &presence.guild_id,
+            &presence.user_id,
+        );
+
+        if evicts_from_guild(backend, backend.0.config.max_cached_presences_per_guild()) {
+            if let Some(mut order) = backend.0.guild_presence_order.get_mut(&presence.guild_id) {
+                remove_from(&mut order, presence.user_id);
+            }
+        }
+
+        unindex_activities(backend, presence);
+    }
+}
+
+/// Remove a presence from the index of presences by activity.
+fn unindex_activities(backend: &InMemoryBackend, presence: &PresenceEntity) {
+    if !backend.0.config.index_presence_activities() {
+        return;
+    }
+
+    for query in presence.activities.iter().flat_map(ActivityQuery::keys) {
+        let key = (presence.guild_id, query);
+        unrelate(&backend.0.presence_activities, &key, &presence.user_id);
+        backend
+            .0
+            .presence_activities
+            .remove_if(&key, |_, user_ids| user_ids.is_empty());
+    }
 }
 
 impl EntityExt for PrivateChannelEntity {
@@ -132,6 +1024,14 @@ impl EntityExt for PrivateChannelEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<ChannelId, PrivateChannelEntity> {
         &backend.0.channels_private
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<ChannelId, u64> {
+        &backend.0.sequences.channels_private
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<ChannelId, CacheMeta> {
+        &backend.0.metadata.channels_private
+    }
 }
 
 impl EntityExt for RoleEntity {
@@ -140,6 +1040,28 @@ impl EntityExt for RoleEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<RoleId, RoleEntity> {
         &backend.0.roles
     }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<RoleId, u64> {
+        &backend.0.sequences.roles
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<RoleId, CacheMeta> {
+        &backend.0.metadata.roles
+    }
+
+    fn inserted(backend: &InMemoryBackend, role_id: RoleId, new: bool) {
+        if new {
+            let guild_id = backend.0.roles.get(&role_id).map(|role| role.guild_id);
+
+            if let Some(guild_id) = guild_id {
+                relate(&backend.0.guild_roles, guild_id, role_id);
+            }
+        }
+    }
+
+    fn removed(backend: &InMemoryBackend, role: &Self) {
+        unrelate(&backend.0.guild_roles, &role.guild_id, &role.id);
+    }
 }
 
 impl EntityExt for TextChannelEntity {
@@ -148,29 +1070,241 @@ impl EntityExt for TextChannelEntity {
     fn map(backend: &InMemoryBackend) -> &DashMap<ChannelId, TextChannelEntity> {
         &backend.0.channels_text
     }
-}
 
-impl EntityExt for UserEntity {
-    const TYPE: EntityType = EntityType::USER;
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<ChannelId, u64> {
+        &backend.0.sequences.channels_text
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<ChannelId, CacheMeta> {
+        &backend.0.metadata.channels_text
+    }
+
+    fn inserted(backend: &InMemoryBackend, channel_id: ChannelId, new: bool) {
+        let channel = backend
+            .0
+            .channels_text
+            .get(&channel_id)
+            .and_then(|c| c.guild_id.map(|guild_id| (guild_id, c.position)));
+
+        if let Some((guild_id, position)) = channel {
+            if new {
+                relate(&backend.0.guild_channels, guild_id, channel_id);
+            }
+
+            order_channel(backend, guild_id, position, channel_id);
+        }
+    }
+
+    fn replaced(backend: &InMemoryBackend, channel: &Self) {
+        if let Some(guild_id) = channel.guild_id {
+            unorder_channel(backend, &guild_id, channel.position, channel.id);
+        }
+    }
+
+    fn removed(backend: &InMemoryBackend, channel: &Self) {
+        if let Some(guild_id) = channel.guild_id {
+            unrelate(&backend.0.guild_channels, &guild_id, &channel.id);
+            unorder_channel(backend, &guild_id, channel.position, channel.id);
+        }
+    }
+}
+
+impl EntityExt for UserEntity {
+    const TYPE: EntityType = EntityType::USER;
+
+    fn map(backend: &InMemoryBackend) -> &DashMap<UserId, UserEntity> {
+        &backend.0.users
+    }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<UserId, u64> {
+        &backend.0.sequences.users
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<UserId, CacheMeta> {
+        &backend.0.metadata.users
+    }
+
+    fn replaced(backend: &InMemoryBackend, user: &Self) {
+        reindex_username(backend, user);
+
+        let config = &backend.0.config;
+        let (avatar_size, name_size) = (config.avatar_history_size(), config.name_history_size());
+
+        if avatar_size == 0 && name_size == 0 {
+            return;
+        }
+
+        let Some((avatar_changed, renamed)) = backend
+            .0
+            .users
+            .get(&user.id)
+            .map(|new| (new.avatar != user.avatar, new.name != user.name))
+        else {
+            return;
+        };
+
+        let now = SystemTime::now();
+
+        if avatar_changed && avatar_size > 0 {
+            let change = AvatarChange {
+                changed_at: now,
+                old: user.avatar.clone(),
+            };
+
+            record_change(&backend.0.user_avatars, user.id, change, avatar_size);
+        }
+
+        if renamed && name_size > 0 {
+            let change = NameChange {
+                changed_at: now,
+                kind: NameKind::Username,
+                old: Some(user.name.clone()),
+            };
+
+            record_change(&backend.0.user_names, user.id, change, name_size);
+        }
+    }
+
+    fn removed(backend: &InMemoryBackend, user: &Self) {
+        backend.0.user_avatars.remove(&user.id);
+        backend.0.user_names.remove(&user.id);
+    }
+}
+
+impl EntityExt for VoiceChannelEntity {
+    const TYPE: EntityType = EntityType::CHANNEL_VOICE;
+
+    fn map(backend: &InMemoryBackend) -> &DashMap<ChannelId, VoiceChannelEntity> {
+        &backend.0.channels_voice
+    }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<ChannelId, u64> {
+        &backend.0.sequences.channels_voice
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<ChannelId, CacheMeta> {
+        &backend.0.metadata.channels_voice
+    }
+
+    fn inserted(backend: &InMemoryBackend, channel_id: ChannelId, new: bool) {
+        let channel = backend
+            .0
+            .channels_voice
+            .get(&channel_id)
+            .and_then(|c| c.guild_id.map(|guild_id| (guild_id, c.position)));
+
+        if let Some((guild_id, position)) = channel {
+            if new {
+                relate(&backend.0.guild_channels, guild_id, channel_id);
+            }
+
+            order_channel(backend, guild_id, position, channel_id);
+        }
+    }
+
+    fn replaced(backend: &InMemoryBackend, channel: &Self) {
+        if let Some(guild_id) = channel.guild_id {
+            unorder_channel(backend, &guild_id, channel.position, channel.id);
+        }
+    }
+
+    fn removed(backend: &InMemoryBackend, channel: &Self) {
+        if let Some(guild_id) = channel.guild_id {
+            unrelate(&backend.0.guild_channels, &guild_id, &channel.id);
+            unorder_channel(backend, &guild_id, channel.position, channel.id);
+        }
+    }
+}
+
+impl EntityExt for VoiceRegionEntity {
+    const TYPE: EntityType = EntityType::VOICE_REGION;
+
+    fn map(backend: &InMemoryBackend) -> &DashMap<VoiceRegionId, VoiceRegionEntity> {
+        &backend.0.voice_regions
+    }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<VoiceRegionId, u64> {
+        &backend.0.sequences.voice_regions
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<VoiceRegionId, CacheMeta> {
+        &backend.0.metadata.voice_regions
+    }
+}
+
+impl EntityExt for VoiceStateEntity {
+    const TYPE: EntityType = EntityType::VOICE_STATE;
+
+    fn map(backend: &InMemoryBackend) -> &DashMap<(GuildId, UserId), VoiceStateEntity> {
+        &backend.0.voice_states
+    }
+
+    fn sequences(backend: &InMemoryBackend) -> &DashMap<(GuildId, UserId), u64> {
+        &backend.0.sequences.voice_states
+    }
+
+    fn metadata(backend: &InMemoryBackend) -> &DashMap<(GuildId, UserId), CacheMeta> {
+        &backend.0.metadata.voice_states
+    }
+
+    fn inserted(backend: &InMemoryBackend, (guild_id, user_id): Self::Id, new: bool) {
+        if !new {
+            return;
+        }
 
-    fn map(backend: &InMemoryBackend) -> &DashMap<UserId, UserEntity> {
-        &backend.0.users
+        relate(&backend.0.guild_voice_states, guild_id, user_id);
+        record_transitions(backend, (guild_id, user_id), None);
     }
-}
 
-impl EntityExt for VoiceChannelEntity {
-    const TYPE: EntityType = EntityType::CHANNEL_VOICE;
+    fn replaced(backend: &InMemoryBackend, voice_state: &Self) {
+        record_transitions(
+            backend,
+            (voice_state.guild_id, voice_state.user_id),
+            Some(voice_state),
+        );
+    }
 
-    fn map(backend: &InMemoryBackend) -> &DashMap<ChannelId, VoiceChannelEntity> {
-        &backend.0.channels_voice
+    fn removed(backend: &InMemoryBackend, voice_state: &Self) {
+        unrelate(
+            &backend.0.guild_voice_states,
+            &voice_state.guild_id,
+            &voice_state.user_id,
+        );
+        backend
+            .0
+            .voice_transitions
+            .remove(&(voice_state.guild_id, voice_state.user_id));
     }
 }
 
-impl EntityExt for VoiceStateEntity {
-    const TYPE: EntityType = EntityType::VOICE_STATE;
+/// Record the transitions from a previous version of a voice state to the
+/// cached one.
+fn record_transitions(
+    backend: &InMemoryBackend,
+    key: (GuildId, UserId),
+    old: Option<&VoiceStateEntity>,
+) {
+    let size = backend.0.config.voice_history_size();
+
+    if size == 0 {
+        return;
+    }
+
+    let transitions = backend
+        .0
+        .voice_states
+        .get(&key)
+        .map(|new| VoiceTransition::between(old, &new))
+        .unwrap_or_default();
+    let now = SystemTime::now();
+
+    for transition in transitions {
+        let change = VoiceStateChange {
+            changed_at: now,
+            transition,
+        };
 
-    fn map(backend: &InMemoryBackend) -> &DashMap<(GuildId, UserId), VoiceStateEntity> {
-        &backend.0.voice_states
+        record_change(&backend.0.voice_transitions, key, change, size);
     }
 }
 
@@ -193,51 +1327,295 @@ impl SingleEntityExt for CurrentUserEntity {
     }
 }
 
+/// Stream the entities yielded by an iterator looking them up.
+///
+/// When consistent iteration is enabled the entities are looked up before the
+/// stream is returned, so that it isn't affected by later writes.
+fn entity_stream<'a, T: Send + 'a>(
+    backend: &InMemoryBackend,
+    iter: impl Iterator<Item = Result<T, InMemoryBackendError>> + Send + 'a,
+) -> BoxStream<'a, Result<T, InMemoryBackendError>> {
+    if backend.0.config.consistent_iteration() {
+        stream::iter(iter.collect::<Vec<_>>()).boxed()
+    } else {
+        stream::iter(iter).boxed()
+    }
+}
+
+/// Future that returns `Pending` once, waking its task right away so that the
+/// executor can run other tasks before it completes.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+
+        self.0 = true;
+        cx.waker().wake_by_ref();
+
+        Poll::Pending
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct InMemoryRepository<T>(pub(crate) InMemoryBackend, pub(crate) PhantomData<T>);
 
+/// Error returned when an entity can't be retrieved without blocking.
+///
+/// This occurs when another operation on the backend, such as an event being
+/// processed, is holding the lock that the entity is stored behind.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct WouldBlock;
+
+impl Display for WouldBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("retrieving the entity would block")
+    }
+}
+
+impl Error for WouldBlock {}
+
+impl<E: EntityExt> InMemoryRepository<E> {
+    /// Get an entity by its ID without blocking.
+    ///
+    /// Unlike [`Repository::get`], this returns [`WouldBlock`] instead of
+    /// waiting when the shard of the map that the entity is stored in is
+    /// locked. Use this from code that may run while the same thread is
+    /// updating the cache, such as an [`OversizedEntityHook`], where waiting
+    /// for the lock would deadlock.
+    ///
+    /// Retrieving an entity this way doesn't count as an access for the
+    /// message eviction policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WouldBlock`] if the entity's shard is locked for writing.
+    ///
+    /// [`OversizedEntityHook`]: ../config/struct.OversizedEntityHook.html
+    /// [`Repository::get`]: ../../twilight_cache/repository/trait.Repository.html#method.get
+    /// [`WouldBlock`]: struct.WouldBlock.html
+    pub fn try_get(&self, entity_id: E::Id) -> Result<Option<E>, WouldBlock> {
+        let map = E::map(&self.0);
+        let shard = map.shards()[map.determine_map(&entity_id)]
+            .try_read()
+            .ok_or(WouldBlock)?;
+
+        Ok(shard.get(&entity_id).map(|value| value.get().clone()))
+    }
+
+    /// List the entities in chunks, yielding to the executor between chunks.
+    ///
+    /// [`Repository::list`] streams every entity without yielding, so
+    /// iterating over millions of entities, such as users, can starve the
+    /// other tasks on the executor. Entities are instead looked up
+    /// `chunk_size` at a time, and the stream returns `Pending` once before
+    /// each chunk after the first. A `chunk_size` of 0 is treated as 1.
+    ///
+    /// When consistent iteration is enabled this is the same as
+    /// [`Repository::list`], since every entity is looked up before the
+    /// stream is returned.
+    ///
+    /// [`Repository::list`]: ../../twilight_cache/repository/trait.Repository.html#method.list
+    pub fn list_buffered(
+        &self,
+        chunk_size: usize,
+    ) -> ListEntitiesFuture<'_, E, InMemoryBackendError> {
+        if (self.0).0.config.consistent_iteration() {
+            return self.list();
+        }
+
+        // Collect the keys up front like `list` does, so that no shard locks
+        // are held between chunks.
+        let keys = E::map(&self.0).iter().map(|r| *r.key()).collect::<Vec<_>>();
+        let chunks = keys
+            .chunks(chunk_size.max(1))
+            .map(<[E::Id]>::to_vec)
+            .collect::<Vec<_>>();
+        let backend = &self.0;
+
+        let stream = stream::iter(chunks.into_iter().enumerate())
+            .then(move |(idx, chunk)| async move {
+                if idx > 0 {
+                    YieldNow(false).await;
+                }
+
+                let entities = chunk
+                    .into_iter()
+                    .filter_map(|key| E::map(backend).get(&key).map(|r| Ok(r.value().clone())))
+                    .collect::<Vec<_>>();
+
+                stream::iter(entities)
+            })
+            .flatten()
+            .boxed();
+
+        future::ok(stream).boxed()
+    }
+}
+
+impl InMemoryRepository<CurrentUserEntity> {
+    /// Get the current user without blocking.
+    ///
+    /// Returns [`WouldBlock`] instead of waiting when the current user is
+    /// being updated.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WouldBlock`] if the current user is locked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current user's lock is poisoned.
+    ///
+    /// [`WouldBlock`]: struct.WouldBlock.html
+    pub fn try_get(&self) -> Result<Option<CurrentUserEntity>, WouldBlock> {
+        match CurrentUserEntity::lock(&self.0).try_lock() {
+            Ok(user) => Ok(user.clone()),
+            Err(TryLockError::WouldBlock) => Err(WouldBlock),
+            Err(TryLockError::Poisoned(_)) => panic!("current user poisoned"),
+        }
+    }
+}
+
+impl InMemoryRepository<GuildEntity> {
+    /// Pin a guild, such as the bot's home guild.
+    ///
+    /// Pinned guilds aren't subject to the caps on the number of members and
+    /// presences cached per guild, and their messages and presences are kept
+    /// by a [`Pruner`]. The guild doesn't need to be cached yet.
+    ///
+    /// [`Pruner`]: ../../twilight_cache/prune/struct.Pruner.html
+    pub fn pin(&self, guild_id: GuildId) {
+        (self.0).0.pinned_guilds.insert(guild_id);
+    }
+
+    /// Unpin a guild, subjecting it to the caps and retention rules again.
+    pub fn unpin(&self, guild_id: GuildId) {
+        (self.0).0.pinned_guilds.remove(&guild_id);
+    }
+
+    /// Return whether a guild is pinned.
+    pub fn is_pinned(&self, guild_id: GuildId) -> bool {
+        (self.0).0.pinned_guilds.contains(&guild_id)
+    }
+}
+
+impl InMemoryRepository<UserEntity> {
+    /// Pin a user, such as a user that configures the bot.
+    ///
+    /// The members and presences of pinned users are never evicted to make
+    /// room for others in guilds at their caps, and their messages and
+    /// presences are kept by a [`Pruner`]. The user doesn't need to be cached
+    /// yet.
+    ///
+    /// [`Pruner`]: ../../twilight_cache/prune/struct.Pruner.html
+    pub fn pin(&self, user_id: UserId) {
+        (self.0).0.pinned_users.insert(user_id);
+    }
+
+    /// Unpin a user, allowing the user's entities to be evicted and pruned
+    /// again.
+    pub fn unpin(&self, user_id: UserId) {
+        (self.0).0.pinned_users.remove(&user_id);
+    }
+
+    /// Return whether a user is pinned.
+    pub fn is_pinned(&self, user_id: UserId) -> bool {
+        (self.0).0.pinned_users.contains(&user_id)
+    }
+}
+
 impl<E: EntityExt> Repository<E, InMemoryBackend> for InMemoryRepository<E> {
-    fn backend(&self) -> InMemoryBackend {
-        self.0.clone()
+    fn backend(&self) -> &InMemoryBackend {
+        &self.0
     }
 
     fn get(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, InMemoryBackendError> {
-        future::ok(E::map(&self.0).get(&entity_id).map(|r| r.value().clone())).boxed()
+        let entity = E::map(&self.0).get(&entity_id).map(|r| r.value().clone());
+
+        if let Some(entity) = entity.as_ref() {
+            E::accessed(&self.0, entity);
+        }
+
+        future::ok(entity).boxed()
+    }
+
+    fn get_with_meta(
+        &self,
+        entity_id: E::Id,
+    ) -> GetEntityFuture<'_, (E, Option<CacheMeta>), InMemoryBackendError> {
+        let entity = E::map(&self.0).get(&entity_id).map(|r| r.value().clone());
+
+        let Some(entity) = entity else {
+            return future::ok(None).boxed();
+        };
+
+        E::accessed(&self.0, &entity);
+        let meta = E::metadata(&self.0).get(&entity_id).map(|r| *r.value());
+
+        future::ok(Some((entity, meta))).boxed()
     }
 
     fn list(&self) -> ListEntitiesFuture<'_, E, InMemoryBackendError> {
-        let values = E::map(&self.0)
-            .into_iter()
-            .map(|r| *r.key())
-            .filter_map(move |key| E::map(&self.0).get(&key).map(|r| r.value().clone()));
+        // Collect the keys up front so that no shard locks are held while the
+        // stream is consumed.
+        let keys = E::map(&self.0).iter().map(|r| *r.key()).collect::<Vec<_>>();
 
-        let stream = stream::iter(values).map(Ok).boxed();
+        let iter = keys
+            .into_iter()
+            .filter_map(move |key| E::map(&self.0).get(&key).map(|r| Ok(r.value().clone())));
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
 
     fn remove(&self, entity_id: E::Id) -> RemoveEntityFuture<'_, InMemoryBackendError> {
-        E::map(&self.0).remove(&entity_id);
+        remove_entity::<E>(&self.0, entity_id);
 
         future::ok(()).boxed()
     }
 
-    fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, InMemoryBackendError> {
-        if !self.0.config().entity_types().contains(E::TYPE) {
+    fn take(&self, entity_id: E::Id) -> GetEntityFuture<'_, E, InMemoryBackendError> {
+        future::ok(remove_entity::<E>(&self.0, entity_id)).boxed()
+    }
+
+    fn versioned_upsert(
+        &self,
+        entity: E,
+        sequence: u64,
+    ) -> UpsertEntityFuture<'_, InMemoryBackendError> {
+        let mut latest = E::sequences(&self.0).entry(entity.id()).or_insert(sequence);
+
+        if *latest > sequence {
             return future::ok(()).boxed();
         }
 
-        E::map(&self.0).insert(entity.id(), entity);
+        *latest = sequence;
+
+        self.upsert(entity)
+    }
+
+    fn upsert(&self, entity: E) -> UpsertEntityFuture<'_, InMemoryBackendError> {
+        upsert_entity(&self.0, entity);
 
         future::ok(()).boxed()
     }
+
+    fn upsert_with_outcome(&self, entity: E) -> UpsertOutcomeFuture<'_, InMemoryBackendError> {
+        future::ok(upsert_entity(&self.0, entity)).boxed()
+    }
 }
 
 impl SingleEntityRepository<CurrentUserEntity, InMemoryBackend>
     for InMemoryRepository<CurrentUserEntity>
 {
-    fn backend(&self) -> InMemoryBackend {
-        self.0.clone()
+    fn backend(&self) -> &InMemoryBackend {
+        &self.0
     }
 
     fn get(&self) -> GetEntityFuture<'_, CurrentUserEntity, InMemoryBackendError> {
@@ -259,6 +1637,16 @@ impl SingleEntityRepository<CurrentUserEntity, InMemoryBackend>
         future::ok(()).boxed()
     }
 
+    fn take(&self) -> GetEntityFuture<'_, CurrentUserEntity, InMemoryBackendError> {
+        future::ok(
+            CurrentUserEntity::lock(&self.0)
+                .lock()
+                .expect("current user poisoned")
+                .take(),
+        )
+        .boxed()
+    }
+
     fn upsert(&self, entity: CurrentUserEntity) -> UpsertEntityFuture<'_, InMemoryBackendError> {
         if !self
             .0
@@ -319,9 +1707,7 @@ impl CurrentUserRepository<InMemoryBackend> for InMemoryRepository<CurrentUserEn
         let current_user_fut = self.get();
 
         Box::pin(async move {
-            let user = if let Some(user) = current_user_fut.await? {
-                user
-            } else {
+            let Some(user) = current_user_fut.await? else {
                 return Ok(stream::empty().boxed());
             };
 
@@ -336,9 +1722,7 @@ impl CurrentUserRepository<InMemoryBackend> for InMemoryRepository<CurrentUserEn
 
     fn guilds(&self) -> ListEntitiesFuture<'_, GuildEntity, InMemoryBackendError> {
         Box::pin(async move {
-            let user = if let Some(user) = self.get().await? {
-                user
-            } else {
+            let Some(user) = self.get().await? else {
                 return Ok(stream::empty().boxed());
             };
 
@@ -350,7 +1734,7 @@ impl CurrentUserRepository<InMemoryBackend> for InMemoryRepository<CurrentUserEn
             let iter = guild_ids
                 .into_iter()
                 .filter_map(move |id| (self.0).0.guilds.get(&id).map(|r| Ok(r.value().clone())));
-            let stream = stream::iter(iter).boxed();
+            let stream = entity_stream(&self.0, iter);
 
             Ok(stream)
         })
@@ -380,7 +1764,7 @@ impl EmojiRepository<InMemoryBackend> for InMemoryRepository<EmojiEntity> {
         let iter = role_ids
             .into_iter()
             .filter_map(move |id| (self.0).0.roles.get(&id).map(|r| Ok(r.value().clone())));
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -397,6 +1781,40 @@ impl EmojiRepository<InMemoryBackend> for InMemoryRepository<EmojiEntity> {
 
         future::ok(user).boxed()
     }
+
+    #[cfg(feature = "emoji-usage")]
+    fn record_usage(
+        &self,
+        guild_id: GuildId,
+        emoji_id: EmojiId,
+        count: u64,
+    ) -> UpsertEntityFuture<'_, InMemoryBackendError> {
+        *(self.0)
+            .0
+            .emoji_usage
+            .entry(guild_id)
+            .or_default()
+            .entry(emoji_id)
+            .or_insert(0) += count;
+
+        future::ok(()).boxed()
+    }
+
+    #[cfg(feature = "emoji-usage")]
+    fn usage(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, (EmojiId, u64), InMemoryBackendError> {
+        let usage = (self.0)
+            .0
+            .emoji_usage
+            .get(&guild_id)
+            .map_or_else(Vec::new, |usage| {
+                usage.iter().map(|(id, count)| Ok((*id, *count))).collect()
+            });
+
+        future::ok(stream::iter(usage).boxed()).boxed()
+    }
 }
 
 impl GroupRepository<InMemoryBackend> for InMemoryRepository<GroupEntity> {
@@ -441,10 +1859,40 @@ impl GroupRepository<InMemoryBackend> for InMemoryRepository<GroupEntity> {
         let iter = recipient_ids
             .into_iter()
             .filter_map(move |id| (self.0).0.users.get(&id).map(|r| Ok(r.value().clone())));
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
+
+        future::ok(stream).boxed()
+    }
+}
+
+impl GuildPreviewRepository<InMemoryBackend> for InMemoryRepository<GuildPreviewEntity> {
+    fn emojis(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, EmojiEntity, InMemoryBackendError> {
+        let emoji_ids = match (self.0).0.guild_previews.get(&guild_id) {
+            Some(preview) => preview.emoji_ids.clone(),
+            None => return future::ok(stream::empty().boxed()).boxed(),
+        };
+
+        let iter = emoji_ids
+            .into_iter()
+            .filter_map(move |id| (self.0).0.emojis.get(&id).map(|r| Ok(r.value().clone())));
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
+
+    fn guild(&self, guild_id: GuildId) -> GetEntityFuture<'_, GuildEntity, InMemoryBackendError> {
+        let guild = (self.0)
+            .0
+            .guild_previews
+            .get(&guild_id)
+            .and_then(|preview| (self.0).0.guilds.get(&preview.id))
+            .map(|r| r.value().clone());
+
+        future::ok(guild).boxed()
+    }
 }
 
 impl GuildRepository<InMemoryBackend> for InMemoryRepository<GuildEntity> {
@@ -500,7 +1948,7 @@ impl GuildRepository<InMemoryBackend> for InMemoryRepository<GuildEntity> {
 
             None
         });
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -529,7 +1977,7 @@ impl GuildRepository<InMemoryBackend> for InMemoryRepository<GuildEntity> {
         let iter = emoji_ids
             .into_iter()
             .filter_map(move |id| (self.0).0.emojis.get(&id).map(|r| Ok(r.value().clone())));
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -562,7 +2010,39 @@ impl GuildRepository<InMemoryBackend> for InMemoryRepository<GuildEntity> {
                 .get(&(guild_id, id))
                 .map(|r| Ok(r.value().clone()))
         });
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
+
+        future::ok(stream).boxed()
+    }
+
+    fn members_with_users(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, (MemberEntity, Option<UserEntity>), InMemoryBackendError> {
+        let user_ids = match (self.0).0.guild_members.get(&guild_id) {
+            Some(guild_members) => guild_members.clone(),
+            None => return future::ok(stream::empty().boxed()).boxed(),
+        };
+
+        let iter = user_ids.into_iter().filter_map(move |id| {
+            let member = (self.0).0.members.get(&(guild_id, id))?.value().clone();
+            let user = (self.0).0.users.get(&id).map(|r| r.value().clone());
+
+            Some(Ok((member, user)))
+        });
+        let stream = entity_stream(&self.0, iter);
+
+        future::ok(stream).boxed()
+    }
+
+    fn ordered_channel_ids(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntityIdsFuture<'_, ChannelId, InMemoryBackendError> {
+        let stream = (self.0).0.guild_channel_order.get(&guild_id).map_or_else(
+            || stream::empty().boxed(),
+            |order| stream::iter(order.iter().map(|(_, id)| Ok(*id)).collect::<Vec<_>>()).boxed(),
+        );
 
         future::ok(stream).boxed()
     }
@@ -608,11 +2088,53 @@ impl GuildRepository<InMemoryBackend> for InMemoryRepository<GuildEntity> {
                 .get(&(guild_id, id))
                 .map(|r| Ok(r.value().clone()))
         });
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
 
+    fn recent_joins(
+        &self,
+        guild_id: GuildId,
+        limit: usize,
+    ) -> ListEntitiesFuture<'_, (UserId, String), InMemoryBackendError> {
+        let backend = &(self.0).0;
+
+        let joins = if backend.config.recent_joins_size() > 0 {
+            backend
+                .guild_joins
+                .get(&guild_id)
+                .map_or_else(Vec::new, |joins| {
+                    joins.iter().rev().take(limit).cloned().collect()
+                })
+        } else {
+            let mut joins =
+                backend
+                    .guild_members
+                    .get(&guild_id)
+                    .map_or_else(Vec::new, |user_ids| {
+                        user_ids
+                            .iter()
+                            .filter_map(|user_id| backend.members.get(&(guild_id, *user_id)))
+                            .filter_map(|member| {
+                                member.joined_at.clone().map(|at| (at, member.user_id))
+                            })
+                            .collect::<Vec<_>>()
+                    });
+
+            joins.sort_unstable_by(|a, b| b.cmp(a));
+            joins.truncate(limit);
+
+            joins
+        };
+
+        let iter = joins
+            .into_iter()
+            .map(|(joined_at, user_id)| Ok((user_id, joined_at)));
+
+        future::ok(stream::iter(iter).boxed()).boxed()
+    }
+
     fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, InMemoryBackendError> {
         let stream = (self.0).0.guild_roles.get(&guild_id).map_or_else(
             || stream::empty().boxed(),
@@ -631,7 +2153,7 @@ impl GuildRepository<InMemoryBackend> for InMemoryRepository<GuildEntity> {
         let iter = role_ids
             .into_iter()
             .filter_map(move |id| (self.0).0.roles.get(&id).map(|r| Ok(r.value().clone())));
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -652,6 +2174,119 @@ impl GuildRepository<InMemoryBackend> for InMemoryRepository<GuildEntity> {
         future::ok(guild).boxed()
     }
 
+    fn search_members(
+        &self,
+        guild_id: GuildId,
+        query: &str,
+        limit: usize,
+    ) -> ListEntitiesFuture<'_, MemberEntity, InMemoryBackendError> {
+        let backend = &(self.0).0;
+        let query = query.to_lowercase();
+
+        let members = if backend.config.index_member_names() {
+            backend
+                .member_search
+                .get(&guild_id)
+                .map_or_else(Vec::new, |names| {
+                    let mut seen = BTreeSet::new();
+
+                    names
+                        .range((query.clone(), UserId(0))..)
+                        .take_while(|(name, _)| name.starts_with(&query))
+                        .filter(|(_, user_id)| seen.insert(*user_id))
+                        .filter_map(|(_, user_id)| backend.members.get(&(guild_id, *user_id)))
+                        .take(limit)
+                        .map(|member| member.value().clone())
+                        .collect()
+                })
+        } else {
+            let mut matches =
+                backend
+                    .guild_members
+                    .get(&guild_id)
+                    .map_or_else(Vec::new, |user_ids| {
+                        user_ids
+                            .iter()
+                            .filter_map(|user_id| {
+                                let member = backend.members.get(&(guild_id, *user_id))?;
+                                let username = backend
+                                    .users
+                                    .get(user_id)
+                                    .map(|user| user.name.to_lowercase());
+                                let name = member
+                                    .nick
+                                    .as_ref()
+                                    .map(|nick| nick.to_lowercase())
+                                    .into_iter()
+                                    .chain(username)
+                                    .filter(|name| name.starts_with(&query))
+                                    .min()?;
+
+                                Some((name, member.value().clone()))
+                            })
+                            .collect::<Vec<_>>()
+                    });
+
+            matches.sort_unstable_by(|(a, a_member), (b, b_member)| {
+                a.cmp(b).then(a_member.user_id.cmp(&b_member.user_id))
+            });
+            matches.truncate(limit);
+
+            matches.into_iter().map(|(_, member)| member).collect()
+        };
+
+        future::ok(stream::iter(members.into_iter().map(Ok)).boxed()).boxed()
+    }
+
+    fn stats(&self, guild_id: GuildId) -> GetEntityFuture<'_, GuildStats, InMemoryBackendError> {
+        let backend = &(self.0).0;
+
+        if !backend.guilds.contains_key(&guild_id) {
+            return future::ok(None).boxed();
+        }
+
+        let mut stats = GuildStats::default();
+
+        if let Some(channel_ids) = backend.guild_channels.get(&guild_id) {
+            for id in channel_ids.iter() {
+                if backend.channels_text.contains_key(id) {
+                    stats.text_channels += 1;
+                } else if backend.channels_voice.contains_key(id) {
+                    stats.voice_channels += 1;
+                } else if backend.channels_category.contains_key(id) {
+                    stats.category_channels += 1;
+                }
+            }
+        }
+
+        if let Some(user_ids) = backend.guild_presences.get(&guild_id) {
+            for id in user_ids.iter() {
+                if let Some(presence) = backend.presences.get(&(guild_id, *id)) {
+                    stats.presences.add(presence.status);
+                }
+            }
+        }
+
+        stats.emojis = backend
+            .guild_emojis
+            .get(&guild_id)
+            .map_or(0, |set| set.len());
+        stats.members = backend
+            .guild_members
+            .get(&guild_id)
+            .map_or(0, |set| set.len());
+        stats.roles = backend
+            .guild_roles
+            .get(&guild_id)
+            .map_or(0, |set| set.len());
+        stats.voice_states = backend
+            .guild_voice_states
+            .get(&guild_id)
+            .map_or(0, |set| set.len());
+
+        future::ok(Some(stats)).boxed()
+    }
+
     fn system_channel(
         &self,
         guild_id: GuildId,
@@ -696,7 +2331,7 @@ impl GuildRepository<InMemoryBackend> for InMemoryRepository<GuildEntity> {
                 .get(&(guild_id, id))
                 .map(|r| Ok(r.value().clone()))
         });
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -750,6 +2385,27 @@ impl MemberRepository<InMemoryBackend> for InMemoryRepository<MemberEntity> {
         future::ok(role).boxed()
     }
 
+    fn nick_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, NameChange, InMemoryBackendError> {
+        let backend = &(self.0).0;
+        let mut history = backend
+            .member_names
+            .get(&(guild_id, user_id))
+            .map(|names| names.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        if let Some(names) = backend.user_names.get(&user_id) {
+            history.extend(names.iter().cloned());
+        }
+
+        history.sort_by_key(|change| change.changed_at);
+
+        future::ok(stream::iter(history.into_iter().map(Ok)).boxed()).boxed()
+    }
+
     fn roles(
         &self,
         guild_id: GuildId,
@@ -763,7 +2419,7 @@ impl MemberRepository<InMemoryBackend> for InMemoryRepository<MemberEntity> {
         let iter = role_ids
             .into_iter()
             .filter_map(move |id| (self.0).0.roles.get(&id).map(|r| Ok(r.value().clone())));
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -786,7 +2442,7 @@ impl MessageRepository<InMemoryBackend> for InMemoryRepository<MessageEntity> {
                 .get(&id)
                 .map(|r| Ok(r.value().clone()))
         });
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -869,7 +2525,7 @@ impl MessageRepository<InMemoryBackend> for InMemoryRepository<MessageEntity> {
                 .get(&id)
                 .map(|r| Ok(r.value().clone()))
         });
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -886,7 +2542,7 @@ impl MessageRepository<InMemoryBackend> for InMemoryRepository<MessageEntity> {
         let iter = role_ids
             .into_iter()
             .filter_map(move |id| (self.0).0.roles.get(&id).map(|r| Ok(r.value().clone())));
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -903,13 +2559,84 @@ impl MessageRepository<InMemoryBackend> for InMemoryRepository<MessageEntity> {
         let iter = user_ids
             .into_iter()
             .filter_map(move |id| (self.0).0.users.get(&id).map(|r| Ok(r.value().clone())));
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
+
+        future::ok(stream).boxed()
+    }
+
+    fn list_by_channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> ListEntitiesFuture<'_, MessageEntity, InMemoryBackendError> {
+        let message_ids = match (self.0).0.channel_messages.get(&channel_id) {
+            Some(message_ids) => message_ids.clone(),
+            None => return future::ok(stream::empty().boxed()).boxed(),
+        };
+
+        let iter = message_ids
+            .into_iter()
+            .filter_map(move |id| (self.0).0.messages.get(&id).map(|r| Ok(r.value().clone())));
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
 }
 
-impl PresenceRepository<InMemoryBackend> for InMemoryRepository<PresenceEntity> {}
+impl PresenceRepository<InMemoryBackend> for InMemoryRepository<PresenceEntity> {
+    fn by_activity(
+        &self,
+        guild_id: GuildId,
+        query: ActivityQuery,
+    ) -> ListEntitiesFuture<'_, PresenceEntity, InMemoryBackendError> {
+        let backend = &(self.0).0;
+
+        if !backend.config.index_presence_activities() {
+            return guild_presences(backend, guild_id, |presence| presence.has_activity(&query));
+        }
+
+        let user_ids = match backend.presence_activities.get(&(guild_id, query)) {
+            Some(user_ids) => user_ids.clone(),
+            None => return future::ok(stream::empty().boxed()).boxed(),
+        };
+
+        let presences = user_ids
+            .into_iter()
+            .filter_map(|id| backend.presences.get(&(guild_id, id)))
+            .map(|r| Ok(r.value().clone()))
+            .collect::<Vec<_>>();
+
+        future::ok(stream::iter(presences).boxed()).boxed()
+    }
+
+    fn streaming(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, PresenceEntity, InMemoryBackendError> {
+        guild_presences(&(self.0).0, guild_id, PresenceEntity::is_streaming)
+    }
+}
+
+/// Stream the presences in a guild matching a predicate.
+fn guild_presences<'a>(
+    backend: &InMemoryBackendRef,
+    guild_id: GuildId,
+    mut predicate: impl FnMut(&PresenceEntity) -> bool,
+) -> ListEntitiesFuture<'a, PresenceEntity, InMemoryBackendError> {
+    let presences = backend
+        .guild_presences
+        .get(&guild_id)
+        .map(|user_ids| {
+            user_ids
+                .iter()
+                .filter_map(|id| backend.presences.get(&(guild_id, *id)))
+                .filter(|r| predicate(r.value()))
+                .map(|r| Ok(r.value().clone()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    future::ok(stream::iter(presences).boxed()).boxed()
+}
 
 impl PrivateChannelRepository<InMemoryBackend> for InMemoryRepository<PrivateChannelEntity> {
     fn last_message(
@@ -1011,6 +2738,20 @@ impl TextChannelRepository<InMemoryBackend> for InMemoryRepository<TextChannelEn
 }
 
 impl UserRepository<InMemoryBackend> for InMemoryRepository<UserEntity> {
+    fn avatar_history(
+        &self,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, AvatarChange, InMemoryBackendError> {
+        let history = (self.0)
+            .0
+            .user_avatars
+            .get(&user_id)
+            .map(|changes| changes.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        future::ok(stream::iter(history.into_iter().map(Ok)).boxed()).boxed()
+    }
+
     fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, InMemoryBackendError> {
         let stream = (self.0).0.user_guilds.get(&user_id).map_or_else(
             || stream::empty().boxed(),
@@ -1029,7 +2770,7 @@ impl UserRepository<InMemoryBackend> for InMemoryRepository<UserEntity> {
         let iter = guild_ids
             .into_iter()
             .filter_map(move |id| (self.0).0.guilds.get(&id).map(|r| Ok(r.value().clone())));
-        let stream = stream::iter(iter).boxed();
+        let stream = entity_stream(&self.0, iter);
 
         future::ok(stream).boxed()
     }
@@ -1067,6 +2808,35 @@ impl VoiceChannelRepository<InMemoryBackend> for InMemoryRepository<VoiceChannel
 
         future::ok(parent).boxed()
     }
+
+    fn rtc_region(
+        &self,
+        channel_id: ChannelId,
+    ) -> GetEntityFuture<'_, VoiceRegionEntity, InMemoryBackendError> {
+        let region = self
+            .0
+             .0
+            .channels_voice
+            .get(&channel_id)
+            .and_then(|channel| channel.rtc_region.as_deref().map(VoiceRegionId::new))
+            .and_then(|id| (self.0).0.voice_regions.get(&id))
+            .map(|r| r.value().clone());
+
+        future::ok(region).boxed()
+    }
+}
+
+impl VoiceRegionRepository<InMemoryBackend> for InMemoryRepository<VoiceRegionEntity> {
+    fn optimal(&self) -> GetEntityFuture<'_, VoiceRegionEntity, InMemoryBackendError> {
+        let region = (self.0)
+            .0
+            .voice_regions
+            .iter()
+            .find(|region| region.optimal)
+            .map(|r| r.value().clone());
+
+        future::ok(region).boxed()
+    }
 }
 
 impl VoiceStateRepository<InMemoryBackend> for InMemoryRepository<VoiceStateEntity> {
@@ -1086,4 +2856,19 @@ impl VoiceStateRepository<InMemoryBackend> for InMemoryRepository<VoiceStateEnti
 
         future::ok(channel).boxed()
     }
+
+    fn transition_history(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> ListEntitiesFuture<'_, VoiceStateChange, InMemoryBackendError> {
+        let history = (self.0)
+            .0
+            .voice_transitions
+            .get(&(guild_id, user_id))
+            .map(|changes| changes.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        future::ok(stream::iter(history.into_iter().map(Ok)).boxed()).boxed()
+    }
 }
@@ -0,0 +1,328 @@
+//! Write-ahead log making the contents of an in-memory backend survive
+//! restarts and crashes.
+//!
+//! A [`WriteAheadLog`] appends every upsert and removal made through its
+//! [backend] to a file as a line of JSON, in the format of a
+//! [`RecordingBackend`]. Writes are appended before they're applied to the
+//! backend, while the log is locked, so concurrent writes are appended in the
+//! order they're applied. When the log is opened again, such as after a
+//! crash, the writes in it are replayed into a fresh backend before the
+//! backend is used. A write cut off by a crash is ignored along with anything
+//! after it.
+//!
+//! Logs grow with every write, so they're [compacted] when opened and can be
+//! compacted periodically: the log is rewritten to only contain upserts of the
+//! entities currently in the backend.
+//!
+//! Appends aren't synced to disk on every write. Call [`sync`] to make sure
+//! the writes so far have been persisted.
+//!
+//! Requires the `wal` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::{sync::Arc, time::Duration};
+//! use twilight_cache::Cache;
+//! use twilight_cache_inmemory::{wal::WriteAheadLog, InMemoryBackend};
+//!
+//! let wal = Arc::new(WriteAheadLog::open(InMemoryBackend::new(), "cache.wal").await?);
+//! WriteAheadLog::spawn_compaction(&wal, Duration::from_secs(600));
+//!
+//! let cache = Cache::with_backend(wal.backend());
+//! # Ok(()) }
+//! ```
+//!
+//! [backend]: struct.WriteAheadLog.html#method.backend
+//! [compacted]: struct.WriteAheadLog.html#method.compact
+//! [`RecordingBackend`]: ../../twilight_cache/recording/struct.RecordingBackend.html
+//! [`sync`]: struct.WriteAheadLog.html#method.sync
+//! [`WriteAheadLog`]: struct.WriteAheadLog.html
+
+use super::{InMemoryBackend, InMemoryBackendError, InMemoryBackendRef};
+use dashmap::DashMap;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::{self, File},
+    hash::Hash,
+    io::{BufReader, BufWriter, Error as IoError, ErrorKind, Result as IoResult, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use twilight_cache::recording::{
+    self, Record, Recordable, RecordedEntity, Recorder, RecordingBackend,
+};
+
+/// Error returned when opening, compacting, or syncing a log fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WalError {
+    /// Reading, writing, or syncing the log failed.
+    Io {
+        /// Path to the log or the file it's being compacted into.
+        path: PathBuf,
+        /// Reason for the error.
+        source: IoError,
+    },
+    /// Replaying the log into the backend failed.
+    Replay {
+        /// Reason for the error.
+        source: InMemoryBackendError,
+    },
+}
+
+impl Display for WalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Io { path, .. } => {
+                f.write_str("accessing write-ahead log ")?;
+                Display::fmt(&path.display(), f)?;
+
+                f.write_str(" failed")
+            }
+            Self::Replay { .. } => f.write_str("replaying write-ahead log failed"),
+        }
+    }
+}
+
+impl Error for WalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Replay { source } => Some(source),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Log {
+    /// First error appending a write since the log was last synced.
+    error: Option<IoError>,
+    file: File,
+}
+
+/// Write-ahead log of the writes made to an [`InMemoryBackend`].
+///
+/// Refer to the [module-level documentation] for more information.
+///
+/// [`InMemoryBackend`]: ../struct.InMemoryBackend.html
+/// [module-level documentation]: index.html
+#[derive(Debug)]
+pub struct WriteAheadLog {
+    backend: RecordingBackend<InMemoryBackend>,
+    log: Arc<Mutex<Log>>,
+    path: PathBuf,
+}
+
+impl WriteAheadLog {
+    /// Open the log at a path, replaying the writes in it into a backend and
+    /// compacting it.
+    ///
+    /// The log is created if it doesn't exist. The backend should be empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WalError::Io`] if reading or compacting the log fails.
+    ///
+    /// Returns [`WalError::Replay`] if replaying the writes fails.
+    ///
+    /// [`WalError::Io`]: enum.WalError.html#variant.Io
+    /// [`WalError::Replay`]: enum.WalError.html#variant.Replay
+    pub async fn open(backend: InMemoryBackend, path: impl AsRef<Path>) -> Result<Self, WalError> {
+        let path = path.as_ref().to_owned();
+
+        match File::open(&path) {
+            Ok(file) => {
+                let records = recording::read_records(BufReader::new(file)).map_while(Result::ok);
+
+                recording::replay(&backend, records)
+                    .await
+                    .map_err(|source| WalError::Replay { source })?;
+            }
+            Err(source) if source.kind() == ErrorKind::NotFound => {}
+            Err(source) => return Err(WalError::Io { path, source }),
+        }
+
+        let file = rewrite(&path, &backend.0)?;
+        let log = Arc::new(Mutex::new(Log { error: None, file }));
+        let appended = Arc::clone(&log);
+
+        let recorder = Recorder::write_ahead(move |record, write| {
+            let mut log = appended.lock().unwrap_or_else(PoisonError::into_inner);
+
+            if let Err(source) = append(&mut log.file, record) {
+                log.error.get_or_insert(source);
+            }
+
+            // The write is applied while the log is locked, so that writes
+            // are appended in the order they're applied and the log can't be
+            // compacted between appending and applying a write.
+            write();
+        });
+        let backend = RecordingBackend::with_recorder(backend, recorder);
+
+        Ok(Self { backend, log, path })
+    }
+
+    /// Return the backend whose writes are appended to the log.
+    ///
+    /// Writes made to the wrapped backend directly aren't logged.
+    pub fn backend(&self) -> RecordingBackend<InMemoryBackend> {
+        self.backend.clone()
+    }
+
+    /// Rewrite the log to only contain upserts of the entities currently in
+    /// the backend.
+    ///
+    /// The compacted log is written to a separate file which then replaces
+    /// the log, so the log is intact if compacting it fails. Writes are
+    /// blocked until compaction completes, so every write is either in the
+    /// compacted log or appended to it afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WalError::Io`] if writing the compacted log fails.
+    ///
+    /// [`WalError::Io`]: enum.WalError.html#variant.Io
+    pub fn compact(&self) -> Result<(), WalError> {
+        let mut log = self.lock();
+        log.file = rewrite(&self.path, &(self.backend.inner().0))?;
+
+        Ok(())
+    }
+
+    /// Sync the writes appended to the log so far to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WalError::Io`] if appending a write since the last sync
+    /// failed, or if syncing fails.
+    ///
+    /// [`WalError::Io`]: enum.WalError.html#variant.Io
+    pub fn sync(&self) -> Result<(), WalError> {
+        let mut log = self.lock();
+
+        let result = match log.error.take() {
+            Some(source) => Err(source),
+            None => log.file.sync_data(),
+        };
+
+        result.map_err(|source| WalError::Io {
+            path: self.path.clone(),
+            source,
+        })
+    }
+
+    /// Spawn a thread compacting a log every period.
+    ///
+    /// The thread stops once every other reference to the log is dropped.
+    /// It also stops when compacting fails, resolving the handle to the error.
+    pub fn spawn_compaction(wal: &Arc<Self>, period: Duration) -> JoinHandle<Result<(), WalError>> {
+        let wal = Arc::downgrade(wal);
+
+        thread::spawn(move || loop {
+            thread::sleep(period);
+
+            let Some(wal) = wal.upgrade() else {
+                return Ok(());
+            };
+
+            wal.compact()?;
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Log> {
+        self.log.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Append a record to a log as a line of JSON.
+fn append(file: &mut impl Write, record: &Record) -> IoResult<()> {
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+
+    file.write_all(&line)
+}
+
+/// Write upserts of every entity in a backend to a new log replacing the log
+/// at a path, returning the new log.
+fn rewrite(path: &Path, backend: &InMemoryBackendRef) -> Result<File, WalError> {
+    let mut compacted = path.as_os_str().to_owned();
+    compacted.push(".compact");
+    let compacted = PathBuf::from(compacted);
+
+    let file = write_entities(&compacted, backend).map_err(|source| WalError::Io {
+        path: compacted.clone(),
+        source,
+    })?;
+
+    fs::rename(&compacted, path).map_err(|source| WalError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    Ok(file)
+}
+
+fn write_entities(path: &Path, backend: &InMemoryBackendRef) -> IoResult<File> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    let current_user = backend
+        .user_current
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+
+    if let Some(user) = current_user {
+        let record = Record::Upsert {
+            entity: Box::new(RecordedEntity::CurrentUser(user)),
+            sequence: None,
+        };
+
+        append(&mut writer, &record)?;
+    }
+
+    // entities are written before the entities belonging to them
+    write_map(&mut writer, &backend.guilds)?;
+    write_map(&mut writer, &backend.guild_previews)?;
+    write_map(&mut writer, &backend.users)?;
+    write_map(&mut writer, &backend.voice_regions)?;
+    write_map(&mut writer, &backend.channels_category)?;
+    write_map(&mut writer, &backend.groups)?;
+    write_map(&mut writer, &backend.channels_private)?;
+    write_map(&mut writer, &backend.channels_text)?;
+    write_map(&mut writer, &backend.channels_voice)?;
+    write_map(&mut writer, &backend.emojis)?;
+    write_map(&mut writer, &backend.members)?;
+    write_map(&mut writer, &backend.presences)?;
+    write_map(&mut writer, &backend.roles)?;
+    write_map(&mut writer, &backend.voice_states)?;
+    write_map(&mut writer, &backend.messages)?;
+    write_map(&mut writer, &backend.attachments)?;
+
+    let file = writer.into_inner().map_err(|error| error.into_error())?;
+    file.sync_all()?;
+
+    Ok(file)
+}
+
+fn write_map<K: Eq + Hash, E: Recordable>(
+    writer: &mut impl Write,
+    entities: &DashMap<K, E>,
+) -> IoResult<()> {
+    for entity in entities.iter() {
+        let record = Record::Upsert {
+            entity: Box::new(entity.value().clone().into_recorded()),
+            sequence: None,
+        };
+
+        append(writer, &record)?;
+    }
+
+    Ok(())
+}
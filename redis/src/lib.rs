@@ -1,3 +1,215 @@
+//! # twilight-cache-redis
+//!
+//! `twilight-cache-redis` provides [Redis] integrations for `twilight-cache`.
+//!
+//! A Redis backend is planned. For now, the crate provides a
+//! [`RedisInvalidationBus`], which broadcasts the writes made to one cache
+//! instance to other instances over Redis' pub/sub so that horizontally scaled
+//! bots don't serve stale entities. Refer to `twilight-cache`'s
+//! [`invalidation`] module for more information.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{invalidation::Invalidator, Cache};
+//! use twilight_cache_inmemory::InMemoryBackend;
+//! use twilight_cache_redis::RedisInvalidationBus;
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/")?;
+//! let (bus, _publisher) = RedisInvalidationBus::connect(client, "cache").await?;
+//!
+//! let invalidator = Invalidator::new(InMemoryBackend::new(), bus);
+//! let listener = invalidator.clone();
+//! tokio::spawn(async move { listener.listen(|_| true).await });
+//!
+//! let cache = Cache::with_backend(invalidator.backend());
+//! # Ok(()) }
+//! ```
+//!
+//! [`invalidation`]: ../twilight_cache/invalidation/index.html
+//! [Redis]: https://redis.io
+//! [`RedisInvalidationBus`]: struct.RedisInvalidationBus.html
+
+#![deny(
+    clippy::all,
+    clippy::pedantic,
+    future_incompatible,
+    nonstandard_style,
+    rust_2018_idioms,
+    unused,
+    warnings
+)]
+#![allow(
+    clippy::doc_markdown,
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate
+)]
+
+use futures_util::{
+    future::{self, FutureExt},
+    stream::StreamExt,
+};
+use redis::{aio::Connection, AsyncCommands, Client, RedisError, RedisResult};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    task::JoinHandle,
+};
+use twilight_cache::invalidation::{Invalidation, InvalidationBus, SubscribeFuture};
+
+/// Error returned when publishing or receiving writes fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RedisBusError {
+    /// Serializing a write failed.
+    Json {
+        /// Reason for the error.
+        source: serde_json::Error,
+    },
+    /// The task publishing writes has stopped, so writes can't be queued.
+    PublisherStopped,
+    /// Communicating with Redis failed.
+    Redis {
+        /// Reason for the error.
+        source: RedisError,
+    },
+}
+
+impl Display for RedisBusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Json { .. } => f.write_str("serializing a write failed"),
+            Self::PublisherStopped => f.write_str("the task publishing writes has stopped"),
+            Self::Redis { .. } => f.write_str("communicating with redis failed"),
+        }
+    }
+}
+
+impl Error for RedisBusError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Json { source } => Some(source),
+            Self::PublisherStopped => None,
+            Self::Redis { source } => Some(source),
+        }
+    }
+}
+
+impl From<RedisError> for RedisBusError {
+    fn from(source: RedisError) -> Self {
+        Self::Redis { source }
+    }
+}
+
+/// [`InvalidationBus`] publishing writes as JSON messages on a Redis pub/sub
+/// channel.
+///
+/// Every instance of a cache should connect to the same channel. Messages on
+/// the channel that aren't writes are ignored.
+///
+/// [`InvalidationBus`]: ../twilight_cache/invalidation/trait.InvalidationBus.html
+#[derive(Clone, Debug)]
+pub struct RedisInvalidationBus {
+    channel: String,
+    client: Client,
+    sender: UnboundedSender<Vec<u8>>,
+}
+
+impl RedisInvalidationBus {
+    /// Connect to Redis, spawning a task that publishes queued writes to a
+    /// channel.
+    ///
+    /// Writes are queued until the task publishes them, so that making a
+    /// write doesn't wait on Redis. When publishing a write fails, the task
+    /// reconnects to Redis and publishes it again. If that fails too, the task
+    /// stops, resolving the returned handle to the error, and publishing
+    /// further writes returns [`RedisBusError::PublisherStopped`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`RedisError`] if connecting to Redis fails.
+    ///
+    /// [`RedisBusError::PublisherStopped`]: enum.RedisBusError.html#variant.PublisherStopped
+    /// [`RedisError`]: https://docs.rs/redis/*/redis/struct.RedisError.html
+    pub async fn connect(
+        client: Client,
+        channel: impl Into<String>,
+    ) -> RedisResult<(Self, JoinHandle<RedisResult<()>>)> {
+        let channel = channel.into();
+        let mut connection = client.get_async_connection().await?;
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<u8>>();
+        let publishing = channel.clone();
+        let reconnecting = client.clone();
+
+        let publisher = tokio::spawn(async move {
+            while let Some(payload) = receiver.recv().await {
+                if publish(&mut connection, &publishing, &payload)
+                    .await
+                    .is_err()
+                {
+                    connection = reconnecting.get_async_connection().await?;
+                    publish(&mut connection, &publishing, &payload).await?;
+                }
+            }
+
+            Ok(())
+        });
+
+        let bus = Self {
+            channel,
+            client,
+            sender,
+        };
+
+        Ok((bus, publisher))
+    }
+
+    /// Return the name of the channel that writes are published on.
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+}
+
+/// Publish a payload on a channel.
+async fn publish(connection: &mut Connection, channel: &str, payload: &[u8]) -> RedisResult<()> {
+    connection.publish(channel, payload).await
+}
+
+impl InvalidationBus for RedisInvalidationBus {
+    type Error = RedisBusError;
+
+    fn publish(&self, invalidation: &Invalidation) -> Result<(), RedisBusError> {
+        let payload =
+            serde_json::to_vec(invalidation).map_err(|source| RedisBusError::Json { source })?;
+
+        self.sender
+            .send(payload)
+            .map_err(|_| RedisBusError::PublisherStopped)
+    }
+
+    fn subscribe(&self) -> SubscribeFuture<'_, RedisBusError> {
+        async move {
+            let mut pubsub = self.client.get_async_connection().await?.into_pubsub();
+            pubsub.subscribe(&self.channel).await?;
+
+            let invalidations = pubsub.into_on_message().filter_map(|message| {
+                let invalidation =
+                    serde_json::from_slice::<Invalidation>(message.get_payload_bytes());
+
+                future::ready(invalidation.ok().map(Ok))
+            });
+
+            Ok(invalidations.boxed())
+        }
+        .boxed()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
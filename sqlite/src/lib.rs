@@ -0,0 +1,1435 @@
+//! # twilight-cache-sqlite
+//!
+//! `twilight-cache-sqlite` is a backend for `twilight-cache` storing entities
+//! in a [SQLite] database, for bots wanting a persistent cache without running
+//! a database server.
+//!
+//! Each type of entity is stored in its own table, with indexed columns for
+//! the guilds and parents that entities relate to, so that listing entities
+//! and retrieving relations such as a guild's members are answered by a single
+//! query. Databases are opened in WAL mode, and queries are prepared once per
+//! connection and reused.
+//!
+//! Entities are serialized as CBOR within a [`VersionedEntity`]. Entities
+//! stored with an older schema version are passed to the [`Migrator`] of
+//! their type configured with [`SqliteBackend::with_migrations`], and are
+//! otherwise treated as not being in the cache, like entities stored with a
+//! newer schema version.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twilight_cache::{entity::guild::GuildEntity, Repository};
+//! use twilight_cache_sqlite::{SqliteBackend, SqliteCache};
+//! use twilight_model::id::{GuildId, UserId};
+//!
+//! let cache = SqliteCache::with_backend(SqliteBackend::open_in_memory()?);
+//!
+//! let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+//! cache.guilds.upsert(guild.clone()).await?;
+//!
+//! assert_eq!(Some(guild), cache.guilds.get(GuildId(1)).await?);
+//! # Ok(()) }
+//! ```
+//!
+//! [SQLite]: https://sqlite.org
+//! [`Migrator`]: ../twilight_cache/migration/trait.Migrator.html
+//! [`SqliteBackend::with_migrations`]: struct.SqliteBackend.html#method.with_migrations
+//! [`VersionedEntity`]: ../twilight_cache/migration/struct.VersionedEntity.html
+
+#![deny(
+    clippy::all,
+    clippy::pedantic,
+    future_incompatible,
+    nonstandard_style,
+    rust_2018_idioms,
+    unused,
+    warnings
+)]
+#![allow(
+    clippy::doc_markdown,
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate
+)]
+
+use futures_util::{
+    future::{self, FutureExt},
+    stream::{self, StreamExt},
+};
+use rusqlite::{params, Connection, Params};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    marker::PhantomData,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use twilight_cache::{
+    entity::{
+        channel::{
+            AttachmentEntity, AttachmentRepository, CategoryChannelEntity,
+            CategoryChannelRepository, GroupEntity, GroupRepository, GuildChannelEntity,
+            MessageEntity, MessageRepository, PrivateChannelEntity, PrivateChannelRepository,
+            TextChannelEntity, TextChannelRepository, VoiceChannelEntity, VoiceChannelRepository,
+        },
+        gateway::{PresenceEntity, PresenceRepository},
+        guild::{
+            EmojiEntity, EmojiRepository, GuildEntity, GuildPreviewEntity, GuildPreviewRepository,
+            GuildRepository, MemberEntity, MemberRepository, RoleEntity, RoleRepository,
+        },
+        user::{CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository},
+        voice::{VoiceRegionEntity, VoiceRegionRepository, VoiceStateEntity, VoiceStateRepository},
+        Entity,
+    },
+    migration::{Migrations, VersionHeader, VersionedEntity, CACHE_SCHEMA_VERSION},
+    repository::{
+        GetEntityFuture, HealthFuture, ListEntitiesFuture, ListEntityIdsFuture,
+        RemoveEntitiesFuture, RemoveEntityFuture, SingleEntityRepository, UpsertEntitiesFuture,
+        UpsertEntityFuture,
+    },
+    Backend, Cache, Repository,
+};
+use twilight_model::id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
+
+/// Alias over `twilight_cache::Cache` which uses the [`SqliteBackend`].
+///
+/// [`SqliteBackend`]: struct.SqliteBackend.html
+pub type SqliteCache = Cache<SqliteBackend>;
+
+/// Tables of the database, one per type of entity.
+///
+/// Every table has the same columns:
+///
+/// - `scope`: guild ID of entities identified by a guild and user ID, such as
+///   members, otherwise 0
+/// - `id`: ID of the entity, or the user ID of entities identified by a guild
+///   and user ID
+/// - `guild_id`: ID of the guild that the entity is in, if any
+/// - `parent_id`: ID of the message of attachments and the channel of messages
+///   and voice states
+/// - `data`: the serialized entity
+const TABLES: [&str; 17] = [
+    "attachments",
+    "channels_category",
+    "channels_private",
+    "channels_text",
+    "channels_voice",
+    "current_user",
+    "emojis",
+    "groups",
+    "guild_previews",
+    "guilds",
+    "members",
+    "messages",
+    "presences",
+    "roles",
+    "users",
+    "voice_regions",
+    "voice_states",
+];
+
+/// Queries operating on the table of a type of entity.
+#[derive(Clone, Copy, Debug)]
+pub struct Queries {
+    get: &'static str,
+    list: &'static str,
+    list_by_guild: &'static str,
+    list_by_parent: &'static str,
+    list_ids_by_guild: &'static str,
+    remove: &'static str,
+    upsert: &'static str,
+}
+
+macro_rules! queries {
+    ($table:literal) => {
+        Queries {
+            get: concat!("SELECT data FROM ", $table, " WHERE scope = ?1 AND id = ?2"),
+            list: concat!("SELECT data FROM ", $table),
+            list_by_guild: concat!("SELECT data FROM ", $table, " WHERE guild_id = ?1"),
+            list_by_parent: concat!("SELECT data FROM ", $table, " WHERE parent_id = ?1"),
+            list_ids_by_guild: concat!(
+                "SELECT id FROM ",
+                $table,
+                " WHERE guild_id = ?1 ORDER BY id"
+            ),
+            remove: concat!("DELETE FROM ", $table, " WHERE scope = ?1 AND id = ?2"),
+            upsert: concat!(
+                "INSERT OR REPLACE INTO ",
+                $table,
+                " (scope, id, guild_id, parent_id, data) VALUES (?1, ?2, ?3, ?4, ?5)"
+            ),
+        }
+    };
+}
+
+const CURRENT_USER: Queries = queries!("current_user");
+
+const CHANNEL_IDS: &str = "SELECT id FROM channels_category WHERE guild_id = ?1 \
+    UNION ALL SELECT id FROM channels_text WHERE guild_id = ?1 \
+    UNION ALL SELECT id FROM channels_voice WHERE guild_id = ?1 \
+    ORDER BY id";
+
+const USER_GUILD_IDS: &str = "SELECT guild_id FROM members WHERE id = ?1";
+
+const MEMBERS_WITH_USERS: &str = "SELECT members.data, users.data FROM members \
+    LEFT JOIN users ON users.scope = 0 AND users.id = members.id \
+    WHERE members.guild_id = ?1";
+
+/// Error returned from backend operations.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SqliteBackendError {
+    /// Migrating an entity stored with an older schema version failed.
+    Migration {
+        /// Reason for the error.
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// Serializing or deserializing an entity failed.
+    Serialization {
+        /// Reason for the error.
+        source: serde_cbor::Error,
+    },
+    /// Executing a query failed.
+    Sqlite {
+        /// Reason for the error.
+        source: rusqlite::Error,
+    },
+}
+
+impl Display for SqliteBackendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Migration { .. } => f.write_str("migrating an entity failed"),
+            Self::Serialization { .. } => f.write_str("serializing an entity failed"),
+            Self::Sqlite { .. } => f.write_str("executing a query failed"),
+        }
+    }
+}
+
+impl Error for SqliteBackendError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Migration { source } => Some(&**source),
+            Self::Serialization { source } => Some(source),
+            Self::Sqlite { source } => Some(source),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for SqliteBackendError {
+    fn from(source: rusqlite::Error) -> Self {
+        Self::Sqlite { source }
+    }
+}
+
+impl From<serde_cbor::Error> for SqliteBackendError {
+    fn from(source: serde_cbor::Error) -> Self {
+        Self::Serialization { source }
+    }
+}
+
+/// Entity stored in a table of the SQLite backend.
+pub trait SqliteEntity: Entity + DeserializeOwned + Serialize + 'static {
+    /// Queries operating on the entity's table.
+    const QUERIES: Queries;
+
+    /// Return the scope and ID of the row storing an entity.
+    fn key(id: Self::Id) -> (u64, u64);
+
+    /// Return the ID of the guild that the entity is in, if any.
+    fn guild_id(&self) -> Option<GuildId> {
+        None
+    }
+
+    /// Return the ID of the entity's parent, if any.
+    fn parent_id(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl SqliteEntity for AttachmentEntity {
+    const QUERIES: Queries = queries!("attachments");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+
+    fn parent_id(&self) -> Option<u64> {
+        Some(self.message_id.0)
+    }
+}
+
+impl SqliteEntity for CategoryChannelEntity {
+    const QUERIES: Queries = queries!("channels_category");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+}
+
+impl SqliteEntity for EmojiEntity {
+    const QUERIES: Queries = queries!("emojis");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        Some(self.guild_id)
+    }
+}
+
+impl SqliteEntity for GroupEntity {
+    const QUERIES: Queries = queries!("groups");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+}
+
+impl SqliteEntity for GuildEntity {
+    const QUERIES: Queries = queries!("guilds");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+}
+
+impl SqliteEntity for GuildPreviewEntity {
+    const QUERIES: Queries = queries!("guild_previews");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+}
+
+impl SqliteEntity for MemberEntity {
+    const QUERIES: Queries = queries!("members");
+
+    fn key((guild_id, user_id): Self::Id) -> (u64, u64) {
+        (guild_id.0, user_id.0)
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        Some(self.guild_id)
+    }
+}
+
+impl SqliteEntity for MessageEntity {
+    const QUERIES: Queries = queries!("messages");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+
+    fn parent_id(&self) -> Option<u64> {
+        Some(self.channel_id.0)
+    }
+}
+
+impl SqliteEntity for PresenceEntity {
+    const QUERIES: Queries = queries!("presences");
+
+    fn key((guild_id, user_id): Self::Id) -> (u64, u64) {
+        (guild_id.0, user_id.0)
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        Some(self.guild_id)
+    }
+}
+
+impl SqliteEntity for PrivateChannelEntity {
+    const QUERIES: Queries = queries!("channels_private");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+}
+
+impl SqliteEntity for RoleEntity {
+    const QUERIES: Queries = queries!("roles");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        Some(self.guild_id)
+    }
+}
+
+impl SqliteEntity for TextChannelEntity {
+    const QUERIES: Queries = queries!("channels_text");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+}
+
+impl SqliteEntity for UserEntity {
+    const QUERIES: Queries = queries!("users");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+}
+
+impl SqliteEntity for VoiceChannelEntity {
+    const QUERIES: Queries = queries!("channels_voice");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+}
+
+impl SqliteEntity for VoiceRegionEntity {
+    const QUERIES: Queries = queries!("voice_regions");
+
+    fn key(id: Self::Id) -> (u64, u64) {
+        (0, id.0)
+    }
+}
+
+impl SqliteEntity for VoiceStateEntity {
+    const QUERIES: Queries = queries!("voice_states");
+
+    fn key((guild_id, user_id): Self::Id) -> (u64, u64) {
+        (guild_id.0, user_id.0)
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        Some(self.guild_id)
+    }
+
+    fn parent_id(&self) -> Option<u64> {
+        self.channel_id.map(|id| id.0)
+    }
+}
+
+/// Decode a stored entity, migrating entities stored with an older schema
+/// version and treating entities stored with a newer schema version as not
+/// being in the cache.
+fn decode<T: DeserializeOwned + 'static>(
+    migrations: &Migrations,
+    bytes: &[u8],
+) -> Result<Option<T>, SqliteBackendError> {
+    let header = serde_cbor::from_slice::<VersionHeader>(bytes)?;
+
+    match header.version.cmp(&CACHE_SCHEMA_VERSION) {
+        Ordering::Less => migrations
+            .migrate(header.version, bytes)
+            .map_err(|source| SqliteBackendError::Migration { source }),
+        Ordering::Equal => {
+            let versioned = serde_cbor::from_slice::<VersionedEntity<T>>(bytes)?;
+
+            Ok(Some(versioned.entity))
+        }
+        Ordering::Greater => Ok(None),
+    }
+}
+
+/// Insert or replace the row of an entity.
+fn store<T: Serialize>(
+    connection: &Connection,
+    queries: &Queries,
+    (scope, id): (u64, u64),
+    guild_id: Option<GuildId>,
+    parent_id: Option<u64>,
+    entity: &T,
+) -> Result<(), SqliteBackendError> {
+    let data = serde_cbor::to_vec(&VersionedEntity::new(entity))?;
+
+    connection.prepare_cached(queries.upsert)?.execute(params![
+        scope,
+        id,
+        guild_id.map(|id| id.0),
+        parent_id,
+        data
+    ])?;
+
+    Ok(())
+}
+
+fn store_entity<T: SqliteEntity>(
+    connection: &Connection,
+    entity: &T,
+) -> Result<(), SqliteBackendError> {
+    store(
+        connection,
+        &T::QUERIES,
+        T::key(entity.id()),
+        entity.guild_id(),
+        entity.parent_id(),
+        entity,
+    )
+}
+
+/// Create a future resolving to a stream over a list of results.
+fn stream<'a, T: Send + 'a>(
+    result: Result<Vec<T>, SqliteBackendError>,
+) -> ListEntitiesFuture<'a, T, SqliteBackendError> {
+    future::ready(result.map(|items| stream::iter(items.into_iter().map(Ok)).boxed())).boxed()
+}
+
+pub struct SqliteRepository<T>(SqliteBackend, PhantomData<T>);
+
+impl<T> SqliteRepository<T> {
+    fn new(backend: SqliteBackend) -> Self {
+        Self(backend, PhantomData)
+    }
+}
+
+impl<T: SqliteEntity> SqliteRepository<T> {
+    fn by_guild(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, T, SqliteBackendError> {
+        stream(self.0.fetch(T::QUERIES.list_by_guild, params![guild_id.0]))
+    }
+}
+
+impl<T: SqliteEntity> Repository<T, SqliteBackend> for SqliteRepository<T> {
+    fn backend(&self) -> &SqliteBackend {
+        &self.0
+    }
+
+    fn get(&self, entity_id: T::Id) -> GetEntityFuture<'_, T, SqliteBackendError> {
+        let (scope, id) = T::key(entity_id);
+
+        future::ready(
+            self.0
+                .fetch(T::QUERIES.get, params![scope, id])
+                .map(|mut entities| entities.pop()),
+        )
+        .boxed()
+    }
+
+    fn list(&self) -> ListEntitiesFuture<'_, T, SqliteBackendError> {
+        stream(self.0.fetch(T::QUERIES.list, []))
+    }
+
+    fn remove(&self, entity_id: T::Id) -> RemoveEntityFuture<'_, SqliteBackendError> {
+        let (scope, id) = T::key(entity_id);
+
+        future::ready(self.0.execute(T::QUERIES.remove, params![scope, id])).boxed()
+    }
+
+    /// Remove the entities within a single transaction.
+    fn remove_bulk<I: Iterator<Item = T::Id>>(
+        &self,
+        entity_ids: I,
+    ) -> RemoveEntitiesFuture<'_, SqliteBackendError> {
+        future::ready(self.0.transaction(|connection| {
+            let mut statement = connection.prepare_cached(T::QUERIES.remove)?;
+
+            for (scope, id) in entity_ids.map(T::key) {
+                statement.execute(params![scope, id])?;
+            }
+
+            Ok(())
+        }))
+        .boxed()
+    }
+
+    fn upsert(&self, entity: T) -> UpsertEntityFuture<'_, SqliteBackendError> {
+        future::ready(store_entity(&self.0.connection(), &entity)).boxed()
+    }
+
+    /// Upsert the entities within a single transaction.
+    fn upsert_bulk<I: Iterator<Item = T> + Send>(
+        &self,
+        entities: I,
+    ) -> UpsertEntitiesFuture<'_, SqliteBackendError> {
+        future::ready(self.0.transaction(|connection| {
+            for entity in entities {
+                store_entity(connection, &entity)?;
+            }
+
+            Ok(())
+        }))
+        .boxed()
+    }
+}
+
+impl SingleEntityRepository<CurrentUserEntity, SqliteBackend>
+    for SqliteRepository<CurrentUserEntity>
+{
+    fn backend(&self) -> &SqliteBackend {
+        &self.0
+    }
+
+    fn get(&self) -> GetEntityFuture<'_, CurrentUserEntity, SqliteBackendError> {
+        future::ready(self.0.current_user()).boxed()
+    }
+
+    fn remove(&self) -> RemoveEntityFuture<'_, SqliteBackendError> {
+        future::ready(self.0.execute(CURRENT_USER.remove, params![0, 0])).boxed()
+    }
+
+    fn upsert(&self, entity: CurrentUserEntity) -> UpsertEntityFuture<'_, SqliteBackendError> {
+        future::ready(store(
+            &self.0.connection(),
+            &CURRENT_USER,
+            (0, 0),
+            None,
+            None,
+            &entity,
+        ))
+        .boxed()
+    }
+}
+
+impl AttachmentRepository<SqliteBackend> for SqliteRepository<AttachmentEntity> {}
+
+impl CategoryChannelRepository<SqliteBackend> for SqliteRepository<CategoryChannelEntity> {
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, CategoryChannelEntity, SqliteBackendError> {
+        self.by_guild(guild_id)
+    }
+}
+
+impl CurrentUserRepository<SqliteBackend> for SqliteRepository<CurrentUserEntity> {
+    fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, SqliteBackendError> {
+        let guild_ids = self.0.current_user().and_then(|user| match user {
+            Some(user) => self.0.guild_ids(user.id),
+            None => Ok(Vec::new()),
+        });
+
+        stream(guild_ids)
+    }
+}
+
+impl EmojiRepository<SqliteBackend> for SqliteRepository<EmojiEntity> {
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, EmojiEntity, SqliteBackendError> {
+        self.by_guild(guild_id)
+    }
+}
+
+impl GroupRepository<SqliteBackend> for SqliteRepository<GroupEntity> {}
+
+impl GuildPreviewRepository<SqliteBackend> for SqliteRepository<GuildPreviewEntity> {}
+
+impl GuildRepository<SqliteBackend> for SqliteRepository<GuildEntity> {
+    fn channel_ids(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntityIdsFuture<'_, ChannelId, SqliteBackendError> {
+        stream(self.0.fetch_ids(CHANNEL_IDS, guild_id, ChannelId))
+    }
+
+    fn channels(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, GuildChannelEntity, SqliteBackendError> {
+        let channels = (|| {
+            let categories = self.0.fetch(
+                CategoryChannelEntity::QUERIES.list_by_guild,
+                params![guild_id.0],
+            )?;
+            let texts = self.0.fetch(
+                TextChannelEntity::QUERIES.list_by_guild,
+                params![guild_id.0],
+            )?;
+            let voices = self.0.fetch(
+                VoiceChannelEntity::QUERIES.list_by_guild,
+                params![guild_id.0],
+            )?;
+
+            Ok(categories
+                .into_iter()
+                .map(GuildChannelEntity::Category)
+                .chain(texts.into_iter().map(GuildChannelEntity::Text))
+                .chain(voices.into_iter().map(GuildChannelEntity::Voice))
+                .collect())
+        })();
+
+        stream(channels)
+    }
+
+    fn emoji_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, EmojiId, SqliteBackendError> {
+        stream(
+            self.0
+                .fetch_ids(EmojiEntity::QUERIES.list_ids_by_guild, guild_id, EmojiId),
+        )
+    }
+
+    fn emojis(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, EmojiEntity, SqliteBackendError> {
+        stream(
+            self.0
+                .fetch(EmojiEntity::QUERIES.list_by_guild, params![guild_id.0]),
+        )
+    }
+
+    fn member_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, SqliteBackendError> {
+        stream(
+            self.0
+                .fetch_ids(MemberEntity::QUERIES.list_ids_by_guild, guild_id, UserId),
+        )
+    }
+
+    fn members(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, MemberEntity, SqliteBackendError> {
+        stream(
+            self.0
+                .fetch(MemberEntity::QUERIES.list_by_guild, params![guild_id.0]),
+        )
+    }
+
+    fn members_with_users(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, (MemberEntity, Option<UserEntity>), SqliteBackendError> {
+        stream(self.0.members_with_users(guild_id))
+    }
+
+    fn presence_ids(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntityIdsFuture<'_, UserId, SqliteBackendError> {
+        stream(
+            self.0
+                .fetch_ids(PresenceEntity::QUERIES.list_ids_by_guild, guild_id, UserId),
+        )
+    }
+
+    fn presences(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, PresenceEntity, SqliteBackendError> {
+        stream(
+            self.0
+                .fetch(PresenceEntity::QUERIES.list_by_guild, params![guild_id.0]),
+        )
+    }
+
+    fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, SqliteBackendError> {
+        stream(
+            self.0
+                .fetch_ids(RoleEntity::QUERIES.list_ids_by_guild, guild_id, RoleId),
+        )
+    }
+
+    fn roles(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, RoleEntity, SqliteBackendError> {
+        stream(
+            self.0
+                .fetch(RoleEntity::QUERIES.list_by_guild, params![guild_id.0]),
+        )
+    }
+
+    fn voice_state_ids(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntityIdsFuture<'_, UserId, SqliteBackendError> {
+        stream(self.0.fetch_ids(
+            VoiceStateEntity::QUERIES.list_ids_by_guild,
+            guild_id,
+            UserId,
+        ))
+    }
+
+    fn voice_states(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceStateEntity, SqliteBackendError> {
+        stream(
+            self.0
+                .fetch(VoiceStateEntity::QUERIES.list_by_guild, params![guild_id.0]),
+        )
+    }
+}
+
+impl MemberRepository<SqliteBackend> for SqliteRepository<MemberEntity> {
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, MemberEntity, SqliteBackendError> {
+        self.by_guild(guild_id)
+    }
+}
+
+impl MessageRepository<SqliteBackend> for SqliteRepository<MessageEntity> {
+    fn attachments(
+        &self,
+        message_id: MessageId,
+    ) -> ListEntitiesFuture<'_, AttachmentEntity, SqliteBackendError> {
+        stream(self.0.fetch(
+            AttachmentEntity::QUERIES.list_by_parent,
+            params![message_id.0],
+        ))
+    }
+
+    fn list_by_channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> ListEntitiesFuture<'_, MessageEntity, SqliteBackendError> {
+        let messages = self
+            .0
+            .fetch(MessageEntity::QUERIES.list_by_parent, params![channel_id.0])
+            .map(|mut messages: Vec<MessageEntity>| {
+                messages.sort_unstable_by_key(|message| message.id);
+
+                messages
+            });
+
+        stream(messages)
+    }
+}
+
+impl PresenceRepository<SqliteBackend> for SqliteRepository<PresenceEntity> {}
+
+impl PrivateChannelRepository<SqliteBackend> for SqliteRepository<PrivateChannelEntity> {}
+
+impl RoleRepository<SqliteBackend> for SqliteRepository<RoleEntity> {
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, RoleEntity, SqliteBackendError> {
+        self.by_guild(guild_id)
+    }
+}
+
+impl TextChannelRepository<SqliteBackend> for SqliteRepository<TextChannelEntity> {
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, TextChannelEntity, SqliteBackendError> {
+        self.by_guild(guild_id)
+    }
+}
+
+impl UserRepository<SqliteBackend> for SqliteRepository<UserEntity> {
+    fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, SqliteBackendError> {
+        stream(self.0.guild_ids(user_id))
+    }
+}
+
+impl VoiceChannelRepository<SqliteBackend> for SqliteRepository<VoiceChannelEntity> {
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceChannelEntity, SqliteBackendError> {
+        self.by_guild(guild_id)
+    }
+}
+
+impl VoiceRegionRepository<SqliteBackend> for SqliteRepository<VoiceRegionEntity> {}
+
+impl VoiceStateRepository<SqliteBackend> for SqliteRepository<VoiceStateEntity> {
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceStateEntity, SqliteBackendError> {
+        self.by_guild(guild_id)
+    }
+}
+
+/// `twilight-cache` backend for a [SQLite] database.
+///
+/// Queries are executed synchronously on a single connection shared by
+/// clones of the backend.
+///
+/// [SQLite]: https://sqlite.org
+#[derive(Clone, Debug)]
+pub struct SqliteBackend(Arc<Mutex<Connection>>, Arc<Migrations>);
+
+impl SqliteBackend {
+    /// Create a new `twilight-cache` SQLite backend with a provided connection.
+    ///
+    /// The connection is switched to WAL mode and the tables of the cache are
+    /// created if they don't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SqliteBackendError::Sqlite`] if configuring the database or
+    /// creating the tables fails.
+    ///
+    /// [`SqliteBackendError::Sqlite`]: enum.SqliteBackendError.html#variant.Sqlite
+    pub fn new(connection: Connection) -> Result<Self, SqliteBackendError> {
+        connection.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        connection.execute_batch("PRAGMA synchronous = NORMAL")?;
+
+        for table in &TABLES {
+            connection.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {table} (
+                    scope INTEGER NOT NULL,
+                    id INTEGER NOT NULL,
+                    guild_id INTEGER,
+                    parent_id INTEGER,
+                    data BLOB NOT NULL,
+                    PRIMARY KEY (scope, id)
+                );
+                CREATE INDEX IF NOT EXISTS {table}_guild_id ON {table} (guild_id);
+                CREATE INDEX IF NOT EXISTS {table}_parent_id ON {table} (parent_id);",
+            ))?;
+        }
+
+        // user IDs are in the `id` column of members
+        connection.execute_batch("CREATE INDEX IF NOT EXISTS members_id ON members (id)")?;
+
+        Ok(Self(
+            Arc::new(Mutex::new(connection)),
+            Arc::new(Migrations::new()),
+        ))
+    }
+
+    /// Migrate entities stored with an older schema version with the
+    /// configured migrators.
+    ///
+    /// Entities of types without a migrator are treated as not being in the
+    /// cache.
+    pub fn with_migrations(mut self, migrations: Migrations) -> Self {
+        self.1 = Arc::new(migrations);
+
+        self
+    }
+
+    /// Shortcut for `Connection::open` and [`new`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SqliteBackendError::Sqlite`] if opening the database fails.
+    ///
+    /// [`new`]: #method.new
+    /// [`SqliteBackendError::Sqlite`]: enum.SqliteBackendError.html#variant.Sqlite
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SqliteBackendError> {
+        Self::new(Connection::open(path)?)
+    }
+
+    /// Shortcut for `Connection::open_in_memory` and [`new`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SqliteBackendError::Sqlite`] if opening the database fails.
+    ///
+    /// [`new`]: #method.new
+    /// [`SqliteBackendError::Sqlite`]: enum.SqliteBackendError.html#variant.Sqlite
+    pub fn open_in_memory() -> Result<Self, SqliteBackendError> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn repo<T>(&self) -> SqliteRepository<T> {
+        SqliteRepository::new(self.clone())
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.0.lock().expect("connection poisoned")
+    }
+
+    fn current_user(&self) -> Result<Option<CurrentUserEntity>, SqliteBackendError> {
+        self.fetch(CURRENT_USER.get, params![0, 0])
+            .map(|mut users| users.pop())
+    }
+
+    fn execute(&self, sql: &str, params: impl Params) -> Result<(), SqliteBackendError> {
+        self.connection().prepare_cached(sql)?.execute(params)?;
+
+        Ok(())
+    }
+
+    /// Query the serialized entities in the first column of the rows.
+    fn fetch<T: DeserializeOwned + 'static>(
+        &self,
+        sql: &str,
+        params: impl Params,
+    ) -> Result<Vec<T>, SqliteBackendError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(sql)?;
+        let rows = statement.query_map(params, |row| row.get::<_, Vec<u8>>(0))?;
+        let mut entities = Vec::new();
+
+        for bytes in rows {
+            if let Some(entity) = decode(&self.1, &bytes?)? {
+                entities.push(entity);
+            }
+        }
+
+        Ok(entities)
+    }
+
+    /// Query the IDs in the first column of the rows within a guild.
+    fn fetch_ids<T>(
+        &self,
+        sql: &str,
+        guild_id: GuildId,
+        f: fn(u64) -> T,
+    ) -> Result<Vec<T>, SqliteBackendError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(sql)?;
+        let ids = statement
+            .query_map(params![guild_id.0], |row| row.get::<_, u64>(0).map(f))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(ids)
+    }
+
+    fn guild_ids(&self, user_id: UserId) -> Result<Vec<GuildId>, SqliteBackendError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(USER_GUILD_IDS)?;
+        let ids = statement
+            .query_map(params![user_id.0], |row| row.get::<_, u64>(0).map(GuildId))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(ids)
+    }
+
+    /// Query the members of a guild joined with their users.
+    fn members_with_users(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<Vec<(MemberEntity, Option<UserEntity>)>, SqliteBackendError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(MEMBERS_WITH_USERS)?;
+        let rows = statement.query_map(params![guild_id.0], |row| {
+            Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Option<Vec<u8>>>(1)?))
+        })?;
+        let mut pairs = Vec::new();
+
+        for row in rows {
+            let (member, user) = row?;
+
+            if let Some(member) = decode(&self.1, &member)? {
+                let user = user
+                    .as_deref()
+                    .map(|user| decode(&self.1, user))
+                    .transpose()?
+                    .flatten();
+                pairs.push((member, user));
+            }
+        }
+
+        Ok(pairs)
+    }
+
+    /// Run a function within a transaction, committing it if the function
+    /// succeeds.
+    fn transaction(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<(), SqliteBackendError>,
+    ) -> Result<(), SqliteBackendError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        f(&transaction)?;
+        transaction.commit()?;
+
+        Ok(())
+    }
+}
+
+impl Backend for SqliteBackend {
+    type Error = SqliteBackendError;
+    type AttachmentRepository = SqliteRepository<AttachmentEntity>;
+    type CategoryChannelRepository = SqliteRepository<CategoryChannelEntity>;
+    type CurrentUserRepository = SqliteRepository<CurrentUserEntity>;
+    type EmojiRepository = SqliteRepository<EmojiEntity>;
+    type GroupRepository = SqliteRepository<GroupEntity>;
+    type GuildPreviewRepository = SqliteRepository<GuildPreviewEntity>;
+    type GuildRepository = SqliteRepository<GuildEntity>;
+    type MemberRepository = SqliteRepository<MemberEntity>;
+    type MessageRepository = SqliteRepository<MessageEntity>;
+    type PresenceRepository = SqliteRepository<PresenceEntity>;
+    type PrivateChannelRepository = SqliteRepository<PrivateChannelEntity>;
+    type RoleRepository = SqliteRepository<RoleEntity>;
+    type TextChannelRepository = SqliteRepository<TextChannelEntity>;
+    type UserRepository = SqliteRepository<UserEntity>;
+    type VoiceChannelRepository = SqliteRepository<VoiceChannelEntity>;
+    type VoiceRegionRepository = SqliteRepository<VoiceRegionEntity>;
+    type VoiceStateRepository = SqliteRepository<VoiceStateEntity>;
+
+    fn attachments(&self) -> Self::AttachmentRepository {
+        self.repo()
+    }
+
+    fn category_channels(&self) -> Self::CategoryChannelRepository {
+        self.repo()
+    }
+
+    fn current_user(&self) -> Self::CurrentUserRepository {
+        self.repo()
+    }
+
+    fn emojis(&self) -> Self::EmojiRepository {
+        self.repo()
+    }
+
+    fn groups(&self) -> Self::GroupRepository {
+        self.repo()
+    }
+
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        self.repo()
+    }
+
+    fn guilds(&self) -> Self::GuildRepository {
+        self.repo()
+    }
+
+    fn members(&self) -> Self::MemberRepository {
+        self.repo()
+    }
+
+    fn messages(&self) -> Self::MessageRepository {
+        self.repo()
+    }
+
+    fn presences(&self) -> Self::PresenceRepository {
+        self.repo()
+    }
+
+    fn private_channels(&self) -> Self::PrivateChannelRepository {
+        self.repo()
+    }
+
+    fn roles(&self) -> Self::RoleRepository {
+        self.repo()
+    }
+
+    fn text_channels(&self) -> Self::TextChannelRepository {
+        self.repo()
+    }
+
+    fn users(&self) -> Self::UserRepository {
+        self.repo()
+    }
+
+    fn voice_channels(&self) -> Self::VoiceChannelRepository {
+        self.repo()
+    }
+
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        self.repo()
+    }
+
+    fn voice_states(&self) -> Self::VoiceStateRepository {
+        self.repo()
+    }
+
+    fn health(&self) -> HealthFuture<'_, SqliteBackendError> {
+        future::ready(
+            self.connection()
+                .query_row("SELECT 1", [], |_| Ok(()))
+                .map_err(SqliteBackendError::from),
+        )
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SqliteBackend, SqliteCache, SqliteEntity};
+    use futures_util::stream::TryStreamExt;
+    use rusqlite::params;
+    use std::{error::Error, process};
+    use twilight_cache::{
+        entity::{
+            channel::{
+                AttachmentEntity, CategoryChannelEntity, GuildChannelEntity, MessageEntity,
+                MessageRepository, TextChannelEntity,
+            },
+            guild::{GuildEntity, GuildRepository, MemberEntity, MemberRepository, RoleEntity},
+            user::{CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository},
+        },
+        migration::{Migrations, Migrator, VersionedEntity, CACHE_SCHEMA_VERSION},
+        repository::SingleEntityRepository,
+        Backend, Repository,
+    };
+    use twilight_model::id::{AttachmentId, ChannelId, GuildId, MessageId, RoleId, UserId};
+
+    fn cache() -> SqliteCache {
+        SqliteCache::with_backend(SqliteBackend::open_in_memory().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_repository() -> Result<(), Box<dyn Error>> {
+        let cache = cache();
+        let users = (1..=3)
+            .map(|id| UserEntity::builder(UserId(id), "user", "0001").build())
+            .collect::<Vec<_>>();
+
+        cache.users.upsert_bulk(users.clone().into_iter()).await?;
+        assert_eq!(Some(users[1].clone()), cache.users.get(UserId(2)).await?);
+
+        let mut listed = cache.users.list().await?.try_collect::<Vec<_>>().await?;
+        listed.sort_by_key(|user| user.id);
+        assert_eq!(users, listed);
+
+        let mut renamed = users[0].clone();
+        renamed.name = String::from("renamed");
+        cache.users.upsert(renamed.clone()).await?;
+        assert_eq!(Some(renamed), cache.users.get(UserId(1)).await?);
+
+        cache.users.remove(UserId(1)).await?;
+        cache
+            .users
+            .remove_bulk(vec![UserId(2), UserId(4)].into_iter())
+            .await?;
+        assert_eq!(None, cache.users.get(UserId(1)).await?);
+        assert_eq!(
+            1,
+            cache
+                .users
+                .list()
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+                .len()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_health() -> Result<(), Box<dyn Error>> {
+        cache().health().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relations() -> Result<(), Box<dyn Error>> {
+        let cache = cache();
+
+        cache
+            .guilds
+            .upsert(GuildEntity::builder(GuildId(1), "guild", UserId(2)).build())
+            .await?;
+        cache
+            .current_user
+            .upsert(CurrentUserEntity::builder(UserId(2), "user", "0001").build())
+            .await?;
+
+        for guild_id in &[GuildId(1), GuildId(2)] {
+            cache
+                .members
+                .upsert(MemberEntity::builder(*guild_id, UserId(2)).build())
+                .await?;
+        }
+
+        cache
+            .members
+            .upsert(MemberEntity::builder(GuildId(1), UserId(3)).build())
+            .await?;
+        cache
+            .users
+            .upsert(UserEntity::builder(UserId(2), "user", "0001").build())
+            .await?;
+        cache
+            .roles
+            .upsert(RoleEntity::builder(RoleId(4), GuildId(1), "role").build())
+            .await?;
+
+        let mut category = CategoryChannelEntity::builder(ChannelId(5), "category");
+        category.guild_id(GuildId(1));
+        cache.category_channels.upsert(category.build()).await?;
+        let mut text = TextChannelEntity::builder(ChannelId(6), "text");
+        text.guild_id(GuildId(1));
+        cache.text_channels.upsert(text.build()).await?;
+
+        cache
+            .messages
+            .upsert(MessageEntity::builder(MessageId(7), ChannelId(6), UserId(2)).build())
+            .await?;
+        let attachment = AttachmentEntity::builder(AttachmentId(8), MessageId(7)).build();
+        cache.attachments.upsert(attachment.clone()).await?;
+
+        let mut member_ids = cache
+            .guilds
+            .member_ids(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        member_ids.sort();
+        assert_eq!(vec![UserId(2), UserId(3)], member_ids);
+        assert_eq!(
+            2,
+            cache
+                .members
+                .list_by_guild(GuildId(1))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+                .len()
+        );
+
+        let mut members = cache
+            .guilds
+            .members_with_users(GuildId(1))
+            .await?
+            .map_ok(|(member, user)| (member.user_id, user.map(|user| user.id)))
+            .try_collect::<Vec<_>>()
+            .await?;
+        members.sort();
+        assert_eq!(
+            vec![(UserId(2), Some(UserId(2))), (UserId(3), None)],
+            members
+        );
+
+        let mut guild_ids = cache
+            .users
+            .guild_ids(UserId(2))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        guild_ids.sort();
+        assert_eq!(vec![GuildId(1), GuildId(2)], guild_ids);
+        assert_eq!(
+            guild_ids.len(),
+            cache
+                .current_user
+                .guild_ids()
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+                .len()
+        );
+
+        let channels = cache
+            .guilds
+            .channels(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert!(matches!(channels[0], GuildChannelEntity::Category(_)));
+        assert!(matches!(channels[1], GuildChannelEntity::Text(_)));
+        assert_eq!(
+            vec![RoleId(4)],
+            cache
+                .guilds
+                .role_ids(GuildId(1))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+        assert_eq!(
+            vec![attachment],
+            cache
+                .messages
+                .attachments(MessageId(7))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+        assert_eq!(
+            vec![MessageId(7)],
+            cache
+                .messages
+                .list_by_channel(ChannelId(6))
+                .await?
+                .map_ok(|message| message.id)
+                .try_collect::<Vec<_>>()
+                .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migration() -> Result<(), Box<dyn Error>> {
+        /// Migrate users stored with version 0, which only stored the ID, name,
+        /// and discriminator of users.
+        struct FromVersion0;
+
+        impl Migrator<UserEntity> for FromVersion0 {
+            type Error = serde_cbor::Error;
+
+            fn migrate(
+                &self,
+                version: u32,
+                bytes: &[u8],
+            ) -> Result<Option<UserEntity>, Self::Error> {
+                if version != 0 {
+                    return Ok(None);
+                }
+
+                let stored =
+                    serde_cbor::from_slice::<VersionedEntity<(u64, String, String)>>(bytes)?;
+                let (id, name, discriminator) = stored.entity;
+
+                Ok(Some(
+                    UserEntity::builder(UserId(id), name, discriminator).build(),
+                ))
+            }
+        }
+
+        let backend = SqliteBackend::open_in_memory()?;
+        let old = VersionedEntity {
+            version: 0,
+            entity: (2_u64, "user", "0001"),
+        };
+        backend.execute(
+            UserEntity::QUERIES.upsert,
+            params![0, 2, None::<u64>, None::<u64>, serde_cbor::to_vec(&old)?],
+        )?;
+
+        // without a migrator outdated entities are treated as not being cached
+        assert!(backend.users().get(UserId(2)).await?.is_none());
+
+        let mut migrations = Migrations::new();
+        migrations.add::<UserEntity, _>(FromVersion0);
+        let backend = backend.with_migrations(migrations);
+        assert_eq!(
+            Some(UserEntity::builder(UserId(2), "user", "0001").build()),
+            backend.users().get(UserId(2)).await?
+        );
+
+        // entities stored by a newer version of the cache can't be migrated
+        let newer = VersionedEntity {
+            version: CACHE_SCHEMA_VERSION + 1,
+            entity: UserEntity::builder(UserId(3), "user", "0001").build(),
+        };
+        backend.execute(
+            UserEntity::QUERIES.upsert,
+            params![0, 3, None::<u64>, None::<u64>, serde_cbor::to_vec(&newer)?],
+        )?;
+        assert!(backend.users().get(UserId(3)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_persistence() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("twilight-cache-{}.sqlite", process::id()));
+        let guild = GuildEntity::builder(GuildId(1), "guild", UserId(2)).build();
+
+        {
+            let cache = SqliteCache::with_backend(SqliteBackend::open(&path)?);
+            cache.guilds.upsert(guild.clone()).await?;
+        }
+
+        let cache = SqliteCache::with_backend(SqliteBackend::open(&path)?);
+        let stored = cache.guilds.get(GuildId(1)).await;
+        drop(cache);
+
+        for suffix in &["", "-shm", "-wal"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+
+        assert_eq!(Some(guild), stored?);
+
+        Ok(())
+    }
+}
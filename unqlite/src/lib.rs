@@ -1,4 +1,13 @@
-use futures_util::future::{self, FutureExt};
+use futures_util::{
+    future::{self, FutureExt},
+    stream::{self, StreamExt, TryStreamExt},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{cmp::Ordering, sync::Arc};
+#[cfg(feature = "compression")]
+use twilight_cache::compression::{CompressionStats, Compressor};
+#[cfg(feature = "encryption")]
+use twilight_cache::encryption::EntityCipher;
 use twilight_cache::{
     entity::{
         channel::{
@@ -9,11 +18,13 @@ use twilight_cache::{
             private_channel::{PrivateChannelEntity, PrivateChannelRepository},
             text_channel::{TextChannelEntity, TextChannelRepository},
             voice_channel::{VoiceChannelEntity, VoiceChannelRepository},
+            GuildChannelEntity,
         },
         gateway::presence::{PresenceEntity, PresenceRepository},
         guild::{
             emoji::{EmojiEntity, EmojiRepository},
             member::{MemberEntity, MemberRepository},
+            preview::{GuildPreviewEntity, GuildPreviewRepository},
             role::{RoleEntity, RoleRepository},
             GuildEntity, GuildRepository,
         },
@@ -21,279 +32,212 @@ use twilight_cache::{
             current_user::{CurrentUserEntity, CurrentUserRepository},
             UserEntity, UserRepository,
         },
-        voice::{VoiceStateEntity, VoiceStateRepository},
-        Entity,
+        voice::{
+            region::{VoiceRegionEntity, VoiceRegionRepository},
+            VoiceStateEntity, VoiceStateRepository,
+        },
     },
+    kv::{KvRepository, KvStore},
+    migration::{Migrations, VersionHeader, VersionedEntity, CACHE_SCHEMA_VERSION},
     repository::{
-        GetEntityFuture, ListEntitiesFuture, RemoveEntityFuture, SingleEntityRepository,
-        UpsertEntityFuture,
+        GetEntityFuture, ListEntitiesFuture, ListEntityIdsFuture, RemoveEntityFuture,
+        SingleEntityRepository, UpsertEntityFuture,
     },
-    Backend, Cache, Repository,
+    Backend, Cache,
 };
-use serde::{de::DeserializeOwned, Serialize};
-use std::{marker::PhantomData, sync::Arc};
-use twilight_model::id::{AttachmentId, ChannelId, EmojiId, GuildId, MessageId, RoleId, UserId};
-use unqlite::{Error, UnQLite, KV};
+use twilight_model::id::{ChannelId, EmojiId, GuildId, RoleId, UserId};
+use unqlite::{Cursor, Error, UnQLite, KV};
 
 pub type UnqliteCache = Cache<UnqliteBackend>;
 
-pub trait UnqliteEntity: Entity {
-    fn key(id: Self::Id) -> Vec<u8>;
-}
-
-pub trait UnqliteSingleEntity: Entity {
-    fn key() -> &'static [u8];
-}
+/// Decode a stored entity, migrating entities stored with an older schema
+/// version and treating entities that can't be decoded or migrated as not
+/// being in the cache.
+fn decode<T: DeserializeOwned + 'static>(migrations: &Migrations, bytes: &[u8]) -> Option<T> {
+    let header = serde_cbor::from_slice::<VersionHeader>(bytes).ok()?;
 
-impl UnqliteEntity for AttachmentEntity {
-    fn key(id: AttachmentId) -> Vec<u8> {
-        format!("at:{}", id).into_bytes()
+    match header.version.cmp(&CACHE_SCHEMA_VERSION) {
+        Ordering::Less => migrations.migrate(header.version, bytes).ok().flatten(),
+        Ordering::Equal => serde_cbor::from_slice::<VersionedEntity<T>>(bytes)
+            .ok()
+            .map(|versioned| versioned.entity),
+        Ordering::Greater => None,
     }
 }
 
-impl UnqliteEntity for CategoryChannelEntity {
-    fn key(id: ChannelId) -> Vec<u8> {
-        format!("cc:{}", id).into_bytes()
-    }
-}
-
-impl UnqliteSingleEntity for CurrentUserEntity {
-    fn key() -> &'static [u8] {
-        &[b'u', b'c']
-    }
-}
-
-impl UnqliteEntity for EmojiEntity {
-    fn key(id: EmojiId) -> Vec<u8> {
-        format!("em:{}", id).into_bytes()
-    }
-}
+impl AttachmentRepository<UnqliteBackend> for KvRepository<AttachmentEntity, UnqliteBackend> {}
 
-impl UnqliteEntity for GroupEntity {
-    fn key(id: ChannelId) -> Vec<u8> {
-        format!("gr:{}", id).into_bytes()
+impl CategoryChannelRepository<UnqliteBackend>
+    for KvRepository<CategoryChannelEntity, UnqliteBackend>
+{
+    fn list_by_guild(
+        &self,
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, CategoryChannelEntity, Error> {
+        self.list_where(move |channel| channel.guild_id == Some(guild_id))
     }
 }
 
-impl UnqliteEntity for GuildEntity {
-    fn key(id: GuildId) -> Vec<u8> {
-        format!("g:{}", id).into_bytes()
+impl CurrentUserRepository<UnqliteBackend> for KvRepository<CurrentUserEntity, UnqliteBackend> {
+    fn guild_ids(&self) -> ListEntityIdsFuture<'_, GuildId, Error> {
+        Box::pin(async move {
+            match SingleEntityRepository::get(self).await? {
+                Some(user) => self.store().members().guild_ids_by_user(user.id).await,
+                None => Ok(stream::empty().boxed()),
+            }
+        })
     }
 }
 
-impl UnqliteEntity for MemberEntity {
-    fn key((guild_id, user_id): (GuildId, UserId)) -> Vec<u8> {
-        format!("m:{}:{}", guild_id, user_id).into_bytes()
+impl EmojiRepository<UnqliteBackend> for KvRepository<EmojiEntity, UnqliteBackend> {
+    fn list_by_guild(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, EmojiEntity, Error> {
+        self.list_where(move |emoji| emoji.guild_id == guild_id)
     }
 }
 
-impl UnqliteEntity for MessageEntity {
-    fn key(id: MessageId) -> Vec<u8> {
-        format!("ms:{}", id).into_bytes()
-    }
-}
+impl GroupRepository<UnqliteBackend> for KvRepository<GroupEntity, UnqliteBackend> {}
 
-impl UnqliteEntity for PresenceEntity {
-    fn key((guild_id, user_id): (GuildId, UserId)) -> Vec<u8> {
-        format!("pr:{}:{}", guild_id, user_id).into_bytes()
-    }
-}
+impl GuildPreviewRepository<UnqliteBackend> for KvRepository<GuildPreviewEntity, UnqliteBackend> {}
 
-impl UnqliteEntity for PrivateChannelEntity {
-    fn key(id: ChannelId) -> Vec<u8> {
-        format!("cp:{}", id).into_bytes()
-    }
-}
+impl GuildRepository<UnqliteBackend> for KvRepository<GuildEntity, UnqliteBackend> {
+    fn channel_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, ChannelId, Error> {
+        let channels = self.channels(guild_id);
 
-impl UnqliteEntity for RoleEntity {
-    fn key(id: RoleId) -> Vec<u8> {
-        format!("r:{}", id).into_bytes()
-    }
-}
+        Box::pin(async move {
+            let mut ids = channels
+                .await?
+                .map_ok(|channel| channel.id())
+                .try_collect::<Vec<_>>()
+                .await?;
+            ids.sort_unstable();
 
-impl UnqliteEntity for TextChannelEntity {
-    fn key(id: ChannelId) -> Vec<u8> {
-        format!("ct:{}", id).into_bytes()
+            Ok(stream::iter(ids.into_iter().map(Ok)).boxed())
+        })
     }
-}
 
-impl UnqliteEntity for UserEntity {
-    fn key(id: UserId) -> Vec<u8> {
-        format!("u:{}", id).into_bytes()
-    }
-}
+    fn channels(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, GuildChannelEntity, Error> {
+        let in_guild = move |channel_guild_id: Option<GuildId>| channel_guild_id == Some(guild_id);
+        let categories = self
+            .store()
+            .category_channels()
+            .list_where(move |channel| in_guild(channel.guild_id));
+        let text_channels = self
+            .store()
+            .text_channels()
+            .list_where(move |channel| in_guild(channel.guild_id));
+        let voice_channels = self
+            .store()
+            .voice_channels()
+            .list_where(move |channel| in_guild(channel.guild_id));
 
-impl UnqliteEntity for VoiceChannelEntity {
-    fn key(id: ChannelId) -> Vec<u8> {
-        format!("cv:{}", id).into_bytes()
-    }
-}
+        Box::pin(async move {
+            let categories = categories.await?.map_ok(GuildChannelEntity::Category);
+            let text_channels = text_channels.await?.map_ok(GuildChannelEntity::Text);
+            let voice_channels = voice_channels.await?.map_ok(GuildChannelEntity::Voice);
 
-impl UnqliteEntity for VoiceStateEntity {
-    fn key((guild_id, user_id): (GuildId, UserId)) -> Vec<u8> {
-        format!("v:{}:{}", guild_id, user_id).into_bytes()
+            Ok(categories
+                .chain(text_channels)
+                .chain(voice_channels)
+                .boxed())
+        })
     }
-}
 
-pub struct UnqliteRepository<T>(UnqliteBackend, PhantomData<T>);
-
-impl<T> UnqliteRepository<T> {
-    fn new(backend: UnqliteBackend) -> Self {
-        Self(backend, PhantomData)
+    fn emoji_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, EmojiId, Error> {
+        self.store()
+            .emojis()
+            .ids_where(move |emoji| (emoji.guild_id == guild_id).then_some(emoji.id))
     }
-}
 
-impl<T: DeserializeOwned + Serialize + UnqliteEntity> Repository<T, UnqliteBackend>
-    for UnqliteRepository<T>
-{
-    fn backend(&self) -> UnqliteBackend {
-        self.0.clone()
+    fn emojis(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, EmojiEntity, Error> {
+        self.store()
+            .emojis()
+            .list_where(move |emoji| emoji.guild_id == guild_id)
     }
 
-    fn get(&self, entity_id: T::Id) -> GetEntityFuture<'_, T, Error> {
-        let bytes: Vec<u8> = (self.0).0.kv_fetch(T::key(entity_id)).unwrap();
-
-        future::ok(Some(serde_cbor::from_slice::<T>(&bytes).unwrap())).boxed()
+    fn member_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, Error> {
+        self.store().members().user_ids_by_guild(guild_id)
     }
 
-    fn list(&self) -> ListEntitiesFuture<'_, T, Error> {
-        unimplemented!("not implemented by this backend");
+    fn members(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, MemberEntity, Error> {
+        self.store().members().list_by_guild_key(guild_id)
     }
 
-    fn remove(&self, entity_id: T::Id) -> RemoveEntityFuture<'_, Error> {
-        future::ready((self.0).0.kv_delete(T::key(entity_id))).boxed()
+    fn presence_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, Error> {
+        self.store().presences().user_ids_by_guild(guild_id)
     }
 
-    fn upsert(&self, entity: T) -> UpsertEntityFuture<'_, Error> {
-        let bytes = serde_cbor::to_vec(&entity).unwrap();
-
-        future::ready((self.0).0.kv_store(T::key(entity.id()), bytes)).boxed()
+    fn presences(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, PresenceEntity, Error> {
+        self.store().presences().list_by_guild_key(guild_id)
     }
-}
 
-impl<T: DeserializeOwned + Serialize + UnqliteSingleEntity>
-    SingleEntityRepository<T, UnqliteBackend> for UnqliteRepository<T>
-{
-    fn backend(&self) -> UnqliteBackend {
-        self.0.clone()
+    fn role_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, RoleId, Error> {
+        self.store()
+            .roles()
+            .ids_where(move |role| (role.guild_id == guild_id).then_some(role.id))
     }
 
-    fn get(&self) -> GetEntityFuture<'_, T, Error> {
-        let bytes: Vec<u8> = (self.0).0.kv_fetch(T::key()).unwrap();
-
-        future::ok(Some(serde_cbor::from_slice::<T>(&bytes).unwrap())).boxed()
+    fn roles(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, RoleEntity, Error> {
+        self.store()
+            .roles()
+            .list_where(move |role| role.guild_id == guild_id)
     }
 
-    fn remove(&self) -> RemoveEntityFuture<'_, Error> {
-        future::ready((self.0).0.kv_delete(T::key())).boxed()
+    fn voice_state_ids(&self, guild_id: GuildId) -> ListEntityIdsFuture<'_, UserId, Error> {
+        self.store().voice_states().user_ids_by_guild(guild_id)
     }
 
-    fn upsert(&self, entity: T) -> UpsertEntityFuture<'_, Error> {
-        let bytes = serde_cbor::to_vec(&entity).unwrap();
-
-        future::ready((self.0).0.kv_store(T::key(), bytes)).boxed()
+    fn voice_states(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, VoiceStateEntity, Error> {
+        self.store().voice_states().list_by_guild_key(guild_id)
     }
 }
 
-impl AttachmentRepository<UnqliteBackend> for UnqliteRepository<AttachmentEntity> {}
-
-impl CategoryChannelRepository<UnqliteBackend> for UnqliteRepository<CategoryChannelEntity> {}
-
-impl CurrentUserRepository<UnqliteBackend> for UnqliteRepository<CurrentUserEntity> {
-    fn guild_ids(&self) -> twilight_cache::repository::ListEntityIdsFuture<'_, GuildId, Error> {
-        unimplemented!("not implemented by this backend");
+impl MemberRepository<UnqliteBackend> for KvRepository<MemberEntity, UnqliteBackend> {
+    fn list_by_guild(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, MemberEntity, Error> {
+        self.list_by_guild_key(guild_id)
     }
 }
 
-impl EmojiRepository<UnqliteBackend> for UnqliteRepository<EmojiEntity> {}
+impl MessageRepository<UnqliteBackend> for KvRepository<MessageEntity, UnqliteBackend> {}
 
-impl GroupRepository<UnqliteBackend> for UnqliteRepository<GroupEntity> {}
+impl PresenceRepository<UnqliteBackend> for KvRepository<PresenceEntity, UnqliteBackend> {}
 
-impl GuildRepository<UnqliteBackend> for UnqliteRepository<GuildEntity> {
-    fn channel_ids(
-        &self,
-        _: GuildId,
-    ) -> twilight_cache::repository::ListEntityIdsFuture<'_, ChannelId, Error> {
-        unimplemented!("not implemented by this backend");
-    }
-
-    fn channels(
-        &self,
-        _: GuildId,
-    ) -> ListEntitiesFuture<'_, twilight_cache::entity::channel::GuildChannelEntity, Error> {
-        unimplemented!("not implemented by this backend");
-    }
-
-    fn emoji_ids(
-        &self,
-        _: GuildId,
-    ) -> twilight_cache::repository::ListEntityIdsFuture<'_, EmojiId, Error> {
-        unimplemented!("not implemented by this backend");
-    }
-
-    fn member_ids(
-        &self,
-        _: GuildId,
-    ) -> twilight_cache::repository::ListEntityIdsFuture<'_, UserId, Error> {
-        unimplemented!("not implemented by this backend");
-    }
-
-    fn members(&self, _: GuildId) -> ListEntitiesFuture<'_, MemberEntity, Error> {
-        unimplemented!("not implemented by this backend");
-    }
+impl PrivateChannelRepository<UnqliteBackend>
+    for KvRepository<PrivateChannelEntity, UnqliteBackend>
+{
+}
 
-    fn presence_ids(
-        &self,
-        _: GuildId,
-    ) -> twilight_cache::repository::ListEntityIdsFuture<'_, UserId, Error> {
-        unimplemented!("not implemented by this backend");
+impl RoleRepository<UnqliteBackend> for KvRepository<RoleEntity, UnqliteBackend> {
+    fn list_by_guild(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, RoleEntity, Error> {
+        self.list_where(move |role| role.guild_id == guild_id)
     }
+}
 
-    fn presences(&self, _: GuildId) -> ListEntitiesFuture<'_, PresenceEntity, Error> {
-        unimplemented!("not implemented by this backend");
+impl TextChannelRepository<UnqliteBackend> for KvRepository<TextChannelEntity, UnqliteBackend> {
+    fn list_by_guild(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, TextChannelEntity, Error> {
+        self.list_where(move |channel| channel.guild_id == Some(guild_id))
     }
+}
 
-    fn role_ids(
+impl VoiceChannelRepository<UnqliteBackend> for KvRepository<VoiceChannelEntity, UnqliteBackend> {
+    fn list_by_guild(
         &self,
-        _: GuildId,
-    ) -> twilight_cache::repository::ListEntityIdsFuture<'_, RoleId, Error> {
-        unimplemented!("not implemented by this backend");
+        guild_id: GuildId,
+    ) -> ListEntitiesFuture<'_, VoiceChannelEntity, Error> {
+        self.list_where(move |channel| channel.guild_id == Some(guild_id))
     }
+}
 
-    fn voice_state_ids(
-        &self,
-        _: GuildId,
-    ) -> twilight_cache::repository::ListEntityIdsFuture<'_, UserId, Error> {
-        unimplemented!("not implemented by this backend");
-    }
+impl VoiceRegionRepository<UnqliteBackend> for KvRepository<VoiceRegionEntity, UnqliteBackend> {}
 
-    fn voice_states(&self, _: GuildId) -> ListEntitiesFuture<'_, VoiceStateEntity, Error> {
-        unimplemented!("not implemented by this backend");
+impl VoiceStateRepository<UnqliteBackend> for KvRepository<VoiceStateEntity, UnqliteBackend> {
+    fn list_by_guild(&self, guild_id: GuildId) -> ListEntitiesFuture<'_, VoiceStateEntity, Error> {
+        self.list_by_guild_key(guild_id)
     }
 }
 
-impl MemberRepository<UnqliteBackend> for UnqliteRepository<MemberEntity> {}
-
-impl MessageRepository<UnqliteBackend> for UnqliteRepository<MessageEntity> {}
-
-impl PresenceRepository<UnqliteBackend> for UnqliteRepository<PresenceEntity> {}
-
-impl PrivateChannelRepository<UnqliteBackend> for UnqliteRepository<PrivateChannelEntity> {}
-
-impl RoleRepository<UnqliteBackend> for UnqliteRepository<RoleEntity> {}
-
-impl TextChannelRepository<UnqliteBackend> for UnqliteRepository<TextChannelEntity> {}
-
-impl VoiceChannelRepository<UnqliteBackend> for UnqliteRepository<VoiceChannelEntity> {}
-
-impl VoiceStateRepository<UnqliteBackend> for UnqliteRepository<VoiceStateEntity> {}
-
-impl UserRepository<UnqliteBackend> for UnqliteRepository<UserEntity> {
-    fn guild_ids(
-        &self,
-        _: UserId,
-    ) -> twilight_cache::repository::ListEntityIdsFuture<'_, GuildId, Error> {
-        unimplemented!("not implemented by this backend")
+impl UserRepository<UnqliteBackend> for KvRepository<UserEntity, UnqliteBackend> {
+    fn guild_ids(&self, user_id: UserId) -> ListEntityIdsFuture<'_, GuildId, Error> {
+        self.store().members().guild_ids_by_user(user_id)
     }
 }
 
@@ -301,12 +245,63 @@ impl UserRepository<UnqliteBackend> for UnqliteRepository<UserEntity> {
 ///
 /// [UnQLite]: https://docs.rs/unqlite
 #[derive(Clone)]
-pub struct UnqliteBackend(Arc<UnQLite>);
+pub struct UnqliteBackend {
+    #[cfg(feature = "encryption")]
+    cipher: Option<Arc<EntityCipher>>,
+    #[cfg(feature = "compression")]
+    compressor: Option<Compressor>,
+    db: Arc<UnQLite>,
+    migrations: Arc<Migrations>,
+}
 
 impl UnqliteBackend {
     /// Create a new `twilight-cache` UnQLite backend with a provided instance.
     pub fn new(unqlite: UnQLite) -> Self {
-        Self(Arc::new(unqlite))
+        Self {
+            #[cfg(feature = "encryption")]
+            cipher: None,
+            #[cfg(feature = "compression")]
+            compressor: None,
+            db: Arc::new(unqlite),
+            migrations: Arc::new(Migrations::new()),
+        }
+    }
+
+    /// Create a new `twilight-cache` UnQLite backend with a provided instance,
+    /// encrypting entities with a cipher before storing them.
+    ///
+    /// Entities that can't be decrypted, such as ones stored before
+    /// encryption was enabled or with a different key, are treated as not
+    /// being in the cache.
+    #[cfg(feature = "encryption")]
+    pub fn new_encrypted(unqlite: UnQLite, cipher: EntityCipher) -> Self {
+        Self {
+            cipher: Some(Arc::new(cipher)),
+            ..Self::new(unqlite)
+        }
+    }
+
+    /// Compress entities whose serialized size is above the compressor's
+    /// threshold before storing them.
+    ///
+    /// Entities stored before compression was enabled are treated as not
+    /// being in the cache.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, compressor: Compressor) -> Self {
+        self.compressor = Some(compressor);
+
+        self
+    }
+
+    /// Migrate entities stored with an older schema version with the
+    /// configured migrators.
+    ///
+    /// Entities of types without a migrator are treated as not being in the
+    /// cache.
+    pub fn with_migrations(mut self, migrations: Migrations) -> Self {
+        self.migrations = Arc::new(migrations);
+
+        self
     }
 
     /// Shortcut for `UnQLite::create` and [`new`].
@@ -344,28 +339,30 @@ impl UnqliteBackend {
         UnQLite::open_readonly(filename)
     }
 
-    fn repo<T>(&self) -> UnqliteRepository<T> {
-        UnqliteRepository::new(self.clone())
+    fn repo<T>(&self) -> KvRepository<T, Self> {
+        KvRepository::new(self.clone())
     }
 }
 
 impl Backend for UnqliteBackend {
     type Error = Error;
-    type AttachmentRepository = UnqliteRepository<AttachmentEntity>;
-    type CategoryChannelRepository = UnqliteRepository<CategoryChannelEntity>;
-    type CurrentUserRepository = UnqliteRepository<CurrentUserEntity>;
-    type EmojiRepository = UnqliteRepository<EmojiEntity>;
-    type GroupRepository = UnqliteRepository<GroupEntity>;
-    type GuildRepository = UnqliteRepository<GuildEntity>;
-    type MemberRepository = UnqliteRepository<MemberEntity>;
-    type MessageRepository = UnqliteRepository<MessageEntity>;
-    type PresenceRepository = UnqliteRepository<PresenceEntity>;
-    type PrivateChannelRepository = UnqliteRepository<PrivateChannelEntity>;
-    type RoleRepository = UnqliteRepository<RoleEntity>;
-    type TextChannelRepository = UnqliteRepository<TextChannelEntity>;
-    type UserRepository = UnqliteRepository<UserEntity>;
-    type VoiceChannelRepository = UnqliteRepository<VoiceChannelEntity>;
-    type VoiceStateRepository = UnqliteRepository<VoiceStateEntity>;
+    type AttachmentRepository = KvRepository<AttachmentEntity, UnqliteBackend>;
+    type CategoryChannelRepository = KvRepository<CategoryChannelEntity, UnqliteBackend>;
+    type CurrentUserRepository = KvRepository<CurrentUserEntity, UnqliteBackend>;
+    type EmojiRepository = KvRepository<EmojiEntity, UnqliteBackend>;
+    type GroupRepository = KvRepository<GroupEntity, UnqliteBackend>;
+    type GuildPreviewRepository = KvRepository<GuildPreviewEntity, UnqliteBackend>;
+    type GuildRepository = KvRepository<GuildEntity, UnqliteBackend>;
+    type MemberRepository = KvRepository<MemberEntity, UnqliteBackend>;
+    type MessageRepository = KvRepository<MessageEntity, UnqliteBackend>;
+    type PresenceRepository = KvRepository<PresenceEntity, UnqliteBackend>;
+    type PrivateChannelRepository = KvRepository<PrivateChannelEntity, UnqliteBackend>;
+    type RoleRepository = KvRepository<RoleEntity, UnqliteBackend>;
+    type TextChannelRepository = KvRepository<TextChannelEntity, UnqliteBackend>;
+    type UserRepository = KvRepository<UserEntity, UnqliteBackend>;
+    type VoiceChannelRepository = KvRepository<VoiceChannelEntity, UnqliteBackend>;
+    type VoiceRegionRepository = KvRepository<VoiceRegionEntity, UnqliteBackend>;
+    type VoiceStateRepository = KvRepository<VoiceStateEntity, UnqliteBackend>;
 
     fn attachments(&self) -> Self::AttachmentRepository {
         self.repo()
@@ -387,6 +384,10 @@ impl Backend for UnqliteBackend {
         self.repo()
     }
 
+    fn guild_previews(&self) -> Self::GuildPreviewRepository {
+        self.repo()
+    }
+
     fn guilds(&self) -> Self::GuildRepository {
         self.repo()
     }
@@ -423,7 +424,339 @@ impl Backend for UnqliteBackend {
         self.repo()
     }
 
+    fn voice_regions(&self) -> Self::VoiceRegionRepository {
+        self.repo()
+    }
+
     fn voice_states(&self) -> Self::VoiceStateRepository {
         self.repo()
     }
+
+    #[cfg(feature = "compression")]
+    fn compression_stats(&self) -> Option<CompressionStats> {
+        self.compressor.as_ref().map(Compressor::stats)
+    }
+}
+
+impl KvStore for UnqliteBackend {
+    fn get(&self, key: Vec<u8>) -> GetEntityFuture<'_, Vec<u8>, Error> {
+        if !self.db.kv_contains(&key) {
+            return future::ok(None).boxed();
+        }
+
+        future::ready(self.db.kv_fetch(key).map(Some)).boxed()
+    }
+
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> UpsertEntityFuture<'_, Error> {
+        future::ready(self.db.kv_store(key, value)).boxed()
+    }
+
+    fn delete(&self, key: Vec<u8>) -> RemoveEntityFuture<'_, Error> {
+        future::ready(self.db.kv_delete(key)).boxed()
+    }
+
+    fn scan_prefix(&self, prefix: Vec<u8>) -> ListEntitiesFuture<'_, (Vec<u8>, Vec<u8>), Error> {
+        let mut entries = Vec::new();
+        let mut entry = self.db.first();
+
+        while let Some(current) = entry {
+            let (key, value) = current.key_value();
+
+            if key.starts_with(&prefix) {
+                entries.push(Ok((key, value)));
+            }
+
+            entry = current.next();
+        }
+
+        future::ok(stream::iter(entries).boxed()).boxed()
+    }
+
+    /// Serialize an entity to store under a key, compressing and encrypting it
+    /// if configured.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    fn encode<T: Serialize>(&self, key: &[u8], entity: &T) -> Vec<u8> {
+        let bytes = serde_cbor::to_vec(&VersionedEntity::new(entity)).unwrap();
+
+        #[cfg(feature = "compression")]
+        let bytes = match &self.compressor {
+            Some(compressor) => compressor.compress(&bytes),
+            None => bytes,
+        };
+
+        #[cfg(feature = "encryption")]
+        let bytes = match &self.cipher {
+            Some(cipher) => cipher.encrypt(key, &bytes),
+            None => bytes,
+        };
+
+        bytes
+    }
+
+    /// Decode an entity stored under a key, decrypting and decompressing it if
+    /// configured.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    fn decode<T: DeserializeOwned + 'static>(&self, key: &[u8], bytes: &[u8]) -> Option<T> {
+        #[cfg(feature = "encryption")]
+        let decrypted;
+        #[cfg(feature = "encryption")]
+        let bytes: &[u8] = match &self.cipher {
+            Some(cipher) => {
+                decrypted = cipher.decrypt(key, bytes).ok()?;
+
+                &decrypted
+            }
+            None => bytes,
+        };
+
+        #[cfg(feature = "compression")]
+        if let Some(compressor) = &self.compressor {
+            return decode(&self.migrations, &compressor.decompress(bytes).ok()?);
+        }
+
+        decode(&self.migrations, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnqliteBackend;
+    use futures_util::stream::TryStreamExt;
+    use std::error::Error;
+    use twilight_cache::{
+        entity::{
+            guild::{GuildEntity, GuildRepository, MemberEntity, MemberRepository, RoleEntity},
+            user::{CurrentUserEntity, CurrentUserRepository, UserEntity, UserRepository},
+        },
+        kv::{KvEntity, KvStore},
+        migration::{Migrations, Migrator, VersionedEntity, CACHE_SCHEMA_VERSION},
+        repository::SingleEntityRepository,
+        Backend, Repository,
+    };
+    use twilight_model::id::{GuildId, RoleId, UserId};
+
+    fn user() -> UserEntity {
+        UserEntity::builder(UserId(2), "test", "0001").build()
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() -> Result<(), Box<dyn Error>> {
+        let backend = UnqliteBackend::new(UnqliteBackend::create_in_memory());
+        backend.users().upsert(user()).await?;
+
+        assert_eq!(Some(user()), backend.users().get(UserId(2)).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_kv_repository() -> Result<(), Box<dyn Error>> {
+        let backend = UnqliteBackend::new(UnqliteBackend::create_in_memory());
+        let users = backend.users();
+        let mut other = user();
+        other.id = UserId(3);
+        users.upsert(user()).await?;
+        users.upsert(other.clone()).await?;
+
+        // entities of other types with the same ID aren't listed
+        backend
+            .guilds()
+            .upsert(GuildEntity::builder(GuildId(2), "guild", UserId(2)).build())
+            .await?;
+
+        let mut listed = users.list().await?.try_collect::<Vec<_>>().await?;
+        listed.sort_by_key(|user| user.id);
+        assert_eq!(vec![user(), other], listed);
+
+        users.remove(UserId(2)).await?;
+        assert!(users.get(UserId(2)).await?.is_none());
+
+        // entities stored by a newer version of the cache are treated as not
+        // being cached
+        let newer = VersionedEntity {
+            version: CACHE_SCHEMA_VERSION + 1,
+            entity: user(),
+        };
+        backend
+            .put(UserEntity::key(UserId(2)), serde_cbor::to_vec(&newer)?)
+            .await?;
+        assert!(users.get(UserId(2)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relations() -> Result<(), Box<dyn Error>> {
+        let backend = UnqliteBackend::new(UnqliteBackend::create_in_memory());
+        let members = backend.members();
+        members
+            .upsert(MemberEntity::builder(GuildId(1), UserId(3)).build())
+            .await?;
+        members
+            .upsert(MemberEntity::builder(GuildId(1), UserId(2)).build())
+            .await?;
+        // the ID of guild 10 starts with the ID of guild 1
+        members
+            .upsert(MemberEntity::builder(GuildId(10), UserId(2)).build())
+            .await?;
+        backend
+            .roles()
+            .upsert(RoleEntity::builder(RoleId(4), GuildId(1), "role").build())
+            .await?;
+        backend
+            .roles()
+            .upsert(RoleEntity::builder(RoleId(5), GuildId(10), "role").build())
+            .await?;
+
+        let guilds = backend.guilds();
+        let member_ids = guilds
+            .member_ids(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![UserId(2), UserId(3)], member_ids);
+
+        let listed = members
+            .list_by_guild(GuildId(10))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(
+            vec![MemberEntity::builder(GuildId(10), UserId(2)).build()],
+            listed
+        );
+
+        let role_ids = guilds
+            .role_ids(GuildId(1))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![RoleId(4)], role_ids);
+
+        let guild_ids = backend
+            .users()
+            .guild_ids(UserId(2))
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![GuildId(1), GuildId(10)], guild_ids);
+
+        // the current user's guilds are the guilds it's a member of
+        let current_user = backend.current_user();
+        assert!(current_user
+            .guild_ids()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?
+            .is_empty());
+        current_user
+            .upsert(CurrentUserEntity::builder(UserId(3), "test", "0001").build())
+            .await?;
+        let guild_ids = current_user
+            .guild_ids()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec![GuildId(1)], guild_ids);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migration() -> Result<(), Box<dyn Error>> {
+        /// Migrate users stored with version 0, which only stored the ID, name,
+        /// and discriminator of users.
+        struct FromVersion0;
+
+        impl Migrator<UserEntity> for FromVersion0 {
+            type Error = serde_cbor::Error;
+
+            fn migrate(
+                &self,
+                version: u32,
+                bytes: &[u8],
+            ) -> Result<Option<UserEntity>, Self::Error> {
+                if version != 0 {
+                    return Ok(None);
+                }
+
+                let stored =
+                    serde_cbor::from_slice::<VersionedEntity<(u64, String, String)>>(bytes)?;
+                let (id, name, discriminator) = stored.entity;
+
+                Ok(Some(
+                    UserEntity::builder(UserId(id), name, discriminator).build(),
+                ))
+            }
+        }
+
+        let backend = UnqliteBackend::new(UnqliteBackend::create_in_memory());
+        let old = VersionedEntity {
+            version: 0,
+            entity: (2_u64, "test", "0001"),
+        };
+        backend
+            .put(UserEntity::key(UserId(2)), serde_cbor::to_vec(&old)?)
+            .await?;
+
+        // without a migrator outdated entities are treated as not being cached
+        assert!(backend.users().get(UserId(2)).await?.is_none());
+
+        let mut migrations = Migrations::new();
+        migrations.add::<UserEntity, _>(FromVersion0);
+        let backend = backend.with_migrations(migrations);
+        assert_eq!(Some(user()), backend.users().get(UserId(2)).await?);
+
+        // entities that fail to deserialize are treated as not being cached
+        backend
+            .put(
+                UserEntity::key(UserId(3)),
+                serde_cbor::to_vec(&VersionedEntity::new("user"))?,
+            )
+            .await?;
+        assert!(backend.users().get(UserId(3)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_encryption_round_trip() -> Result<(), Box<dyn Error>> {
+        use std::sync::Arc;
+        use twilight_cache::encryption::EntityCipher;
+
+        let key = EntityCipher::generate_key();
+        let backend = UnqliteBackend::new_encrypted(
+            UnqliteBackend::create_in_memory(),
+            EntityCipher::new(&key),
+        );
+        backend.users().upsert(user()).await?;
+
+        assert_eq!(Some(user()), backend.users().get(UserId(2)).await?);
+
+        // entities encrypted with another key are treated as not cached
+        let wrong_key = UnqliteBackend {
+            cipher: Some(Arc::new(EntityCipher::new(&EntityCipher::generate_key()))),
+            ..backend
+        };
+        assert!(wrong_key.users().get(UserId(2)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_compression_round_trip() -> Result<(), Box<dyn Error>> {
+        use twilight_cache::compression::Compressor;
+
+        let backend = UnqliteBackend::new(UnqliteBackend::create_in_memory())
+            .with_compression(Compressor::new(0));
+        backend.users().upsert(user()).await?;
+
+        assert_eq!(Some(user()), backend.users().get(UserId(2)).await?);
+        let stats = backend.compression_stats().unwrap();
+        assert_eq!(1, stats.compressed + stats.skipped);
+
+        Ok(())
+    }
 }